@@ -0,0 +1,336 @@
+//! FFI command-line client.
+//!
+//! Talks to the running service over its named pipe, for scripts and
+//! terminal users:
+//! ```cmd
+//! ffi-cli search report ext:pdf
+//! ffi-cli search --json "report ext:pdf"
+//! ffi-cli search --here TODO.md
+//! ffi-cli export --output pdfs.csv ext:pdf
+//! ffi-cli du D:\Projects
+//! ffi-cli du --by ext C:\Users size:>1mb
+//! ffi-cli status
+//! ffi-cli rescan D:
+//! ffi-cli rescan D:\Photos
+//! ffi-cli log-level debug
+//! ```
+//!
+//! `search` prints one full path per line, or with `--json` the results
+//! (path, size, modified time, ...) as a JSON array. `--here` limits the
+//! search to the working copy (Git, Mercurial, Subversion or Jujutsu)
+//! containing the current directory and skips VCS metadata and anything its
+//! `.gitignore`, `.ignore` or `.git/info/exclude` files exclude. `--limit <n>`
+//! sets the maximum number of results (default 100). Entries hidden by the
+//! `[search]` config options are left out unless the query has an `attrib:`
+//! filter.
+//!
+//! `export` writes every match (not just the first `--limit`) as CSV, JSON
+//! Lines or TSV, to `--output <file>` or stdout. `--format csv|jsonl|tsv`
+//! picks the format; otherwise it follows the output file's extension,
+//! defaulting to CSV.
+//!
+//! `du` shows where the space under a folder goes: the total size and file
+//! count of each folder directly below it, or with `--by ext` of each
+//! extension, largest first (`--limit`, default 20). Hidden files and the
+//! Recycle Bin are counted. A leading path (`D:\Projects`, or `D:` for a
+//! whole drive) is the folder; without one, the top-level folders of every
+//! volume are shown. Further terms filter the files counted.
+//!
+//! `status` prints each volume's state and scan statistics (`--json` for
+//! the raw status), and `rescan` asks the service to rescan an NTFS volume
+//! in the background, or, given a folder, to walk just that folder again.
+//! `log-level` changes the service's log filter (a level or directives like
+//! `info,ffi::indexer=debug`) until it restarts.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ffi::db::{ExportFormat, UsageGroup};
+use ffi::ipc::{FileResult, IpcClient, SearchRequest};
+use ffi::search::{ProjectScope, Ranking};
+use ffi::ui::results::format_size;
+use ffi::FFIError;
+
+/// Results printed when `--limit` is not given.
+const DEFAULT_LIMIT: usize = 100;
+
+/// Groups `du` prints when `--limit` is not given.
+const DEFAULT_USAGE_LIMIT: usize = 20;
+
+/// Most nested `.gitignore` files fetched for `--here`.
+const MAX_NESTED_IGNORE_FILES: usize = 1_000;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Parse the command line and run the command.
+fn run(args: &[String]) -> ffi::Result<()> {
+    let usage = || {
+        FFIError::Config(format!(
+            "Usage: {0} search [--here] [--json] [--limit <n>] <query>\n       \
+             {0} export [--format csv|jsonl|tsv] [--output <file>] <query>\n       \
+             {0} du [--by folder|ext] [--limit <n>] [--json] [<folder>] [<query>]\n       \
+             {0} status [--json]\n       {0} rescan <drive|folder>\n       {0} log-level <level>",
+            args.first().map(String::as_str).unwrap_or("ffi-cli")
+        ))
+    };
+
+    let command = args.get(1).map(String::as_str);
+    let mut rest = args.get(2..).unwrap_or_default().iter();
+
+    let mut here = false;
+    let mut json = false;
+    let mut limit: Option<usize> = None;
+    let mut group_by = UsageGroup::default();
+    let mut format: Option<ExportFormat> = None;
+    let mut output: Option<PathBuf> = None;
+    let mut terms: Vec<&str> = Vec::new();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--here" if command == Some("search") => here = true,
+            "--json" => json = true,
+            "--limit" if matches!(command, Some("search" | "du")) => {
+                limit = Some(
+                    rest.next()
+                        .and_then(|n| n.parse().ok())
+                        .filter(|n| *n > 0)
+                        .ok_or_else(usage)?,
+                );
+            }
+            "--by" if command == Some("du") => {
+                group_by = rest.next().and_then(|g| UsageGroup::from_name(g)).ok_or_else(usage)?;
+            }
+            "--format" if command == Some("export") => {
+                format = Some(rest.next().and_then(|f| ExportFormat::from_name(f)).ok_or_else(usage)?);
+            }
+            "--output" if command == Some("export") => {
+                output = Some(rest.next().map(PathBuf::from).ok_or_else(usage)?);
+            }
+            term => terms.push(term),
+        }
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| FFIError::Ipc(format!("Failed to start async runtime: {}", e)))?;
+    let client = IpcClient::new();
+
+    match (command, terms.as_slice()) {
+        (Some("search"), terms) if !terms.is_empty() => {
+            let scope = if here {
+                let cwd = std::env::current_dir()?;
+                let scope = ProjectScope::detect(&cwd).ok_or_else(|| {
+                    FFIError::Search(format!(
+                        "{} is not inside a Git, Mercurial, Subversion or Jujutsu working copy",
+                        cwd.display()
+                    ))
+                })?;
+                Some(scope)
+            } else {
+                None
+            };
+
+            let limit = limit.unwrap_or(DEFAULT_LIMIT);
+            let results = runtime.block_on(search(&client, &terms.join(" "), scope, limit))?;
+            if json {
+                println!("{}", to_json(&results)?);
+            } else {
+                for result in results {
+                    println!("{}", result.path);
+                }
+            }
+            Ok(())
+        }
+        (Some("export"), terms) if !terms.is_empty() && !json => {
+            let format = format
+                .or_else(|| output.as_deref().and_then(ExportFormat::from_path))
+                .unwrap_or_default();
+            let query = terms.join(" ");
+            match output {
+                Some(path) => {
+                    let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+                    let rows = runtime.block_on(client.export(&query, format, &mut file))?;
+                    file.flush()?;
+                    eprintln!("Exported {} matches to {}", rows, path.display());
+                }
+                None => {
+                    let mut stdout = std::io::stdout().lock();
+                    runtime.block_on(client.export(&query, format, &mut stdout))?;
+                }
+            }
+            Ok(())
+        }
+        (Some("du"), terms) => {
+            let query = usage_query(terms);
+            let limit = limit.unwrap_or(DEFAULT_USAGE_LIMIT);
+            let usage = runtime.block_on(client.aggregate(&query, group_by, limit))?;
+            if json {
+                println!("{}", to_json(&usage)?);
+                return Ok(());
+            }
+
+            for row in usage {
+                let key = match group_by {
+                    UsageGroup::Extension if row.key.is_empty() => "(no extension)",
+                    _ => row.key.as_str(),
+                };
+                println!("{:>10}  {:>9} files  {}", format_size(row.bytes), row.files, key);
+            }
+            Ok(())
+        }
+        (Some("status"), []) => {
+            let status = runtime.block_on(client.get_status())?;
+            if json {
+                println!("{}", to_json(&status)?);
+                return Ok(());
+            }
+
+            println!("Indexing: {}", if status.indexing_paused { "paused" } else { "running" });
+            for volume in status.volumes {
+                let scanned = volume
+                    .last_scan_time
+                    .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "never".to_string());
+                println!(
+                    "{}  {:<6} {:<12} {} files, {} folders, last scan {}",
+                    volume.drive_letter,
+                    volume.fs_type,
+                    volume.state_label(),
+                    volume.file_count,
+                    volume.dir_count,
+                    scanned
+                );
+            }
+            if let Some(maintenance) = status.maintenance {
+                let ran = chrono::DateTime::from_timestamp(maintenance.ran_at, 0)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                let integrity = if maintenance.is_healthy() {
+                    "ok".to_string()
+                } else {
+                    format!("{} problems", maintenance.integrity_errors.len())
+                };
+                println!(
+                    "Maintenance: {}, integrity {}, {} pages freed{}",
+                    ran,
+                    integrity,
+                    maintenance.freed_pages,
+                    if maintenance.rebuilt { ", index rebuilt" } else { "" }
+                );
+            }
+            if let Some(pruning) = status.pruning {
+                println!(
+                    "Pruned to size limit: {} -> {} bytes, {} offline volumes, {} excluded entries, {} old files",
+                    pruning.size_before,
+                    pruning.size_after,
+                    pruning.offline_volumes.len(),
+                    pruning.excluded_entries,
+                    pruning.stale_entries
+                );
+            }
+            Ok(())
+        }
+        (Some("rescan"), [target]) if !json => {
+            let drive_letter = target.trim_end_matches(['\\', '/']);
+            let response = if drive_letter.len() <= 2 {
+                runtime.block_on(client.trigger_rescan(drive_letter))?
+            } else {
+                runtime.block_on(client.rescan_path(target))?
+            };
+            if !response.success {
+                return Err(FFIError::Ipc(response.message));
+            }
+            println!("{}", response.message);
+            Ok(())
+        }
+        (Some("log-level"), [level]) if !json => {
+            let response = runtime.block_on(client.set_log_level(level))?;
+            if !response.success {
+                return Err(FFIError::Ipc(response.message));
+            }
+            println!("{}", response.message);
+            Ok(())
+        }
+        _ => Err(usage()),
+    }
+}
+
+/// Search through the service, returning up to `limit` results.
+///
+/// With a scope, results its ignore files exclude are dropped.
+async fn search(
+    client: &IpcClient,
+    query: &str,
+    mut scope: Option<ProjectScope>,
+    limit: usize,
+) -> ffi::Result<Vec<FileResult>> {
+    let filter = scope.as_ref().map(ProjectScope::filter_term);
+    let scoped = |query: &str| match &filter {
+        Some(filter) => format!("{} {}", filter, query),
+        None => query.to_string(),
+    };
+    let request = |query: String, limit: usize| SearchRequest {
+        query,
+        limit,
+        offset: 0,
+        count_queries: Vec::new(),
+        sort: Vec::new(),
+        show_all_links: false,
+        ranking: Ranking::default(),
+        stream: false,
+    };
+
+    if scope.is_some() {
+        let found = client
+            .send_search(&request(scoped(".gitignore type:file"), MAX_NESTED_IGNORE_FILES))
+            .await?;
+        if let Some(scope) = scope.as_mut() {
+            for file in found.results.iter().filter(|f| f.name.eq_ignore_ascii_case(".gitignore")) {
+                scope.add_nested_ignore_file(Path::new(&file.path));
+            }
+        }
+    }
+
+    // Ignored entries are dropped after the query, so fetch more until
+    // enough are left or the index has no more matches
+    let mut fetch = limit;
+    loop {
+        let response = client.send_search(&request(scoped(query), fetch)).await?;
+        let exhausted = response.total_count <= fetch;
+
+        let mut results: Vec<FileResult> = response
+            .results
+            .into_iter()
+            .filter(|r| !scope.as_ref().is_some_and(|s| s.is_ignored(Path::new(&r.path), r.is_dir)))
+            .collect();
+        if results.len() >= limit || exhausted {
+            results.truncate(limit);
+            return Ok(results);
+        }
+        fetch = fetch.saturating_mul(4);
+    }
+}
+
+/// Build the query of `du`: a leading path becomes its `path:` scope, the
+/// other terms are kept as typed.
+fn usage_query(terms: &[&str]) -> String {
+    match terms.split_first() {
+        Some((first, rest)) if first.get(1..2) == Some(":") && first.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+            let scope = format!("path:\"{}\"", first.trim_end_matches(['\\', '/']));
+            std::iter::once(scope.as_str()).chain(rest.iter().copied()).collect::<Vec<_>>().join(" ")
+        }
+        _ => terms.join(" "),
+    }
+}
+
+/// Format a value as pretty-printed JSON.
+fn to_json(value: &impl serde::Serialize) -> ffi::Result<String> {
+    serde_json::to_string_pretty(value).map_err(|e| FFIError::Ipc(format!("Failed to format JSON: {}", e)))
+}
@@ -1,10 +1,12 @@
 //! FFI Search UI - Global hotkey search popup.
 //!
 //! This binary provides the user-facing search interface:
-//! - Global hotkey (Ctrl+Space) to show/hide the popup
+//! - Global hotkey (`[ui] hotkey`, Ctrl+Space by default) to show/hide the
+//!   popup, re-registered when the config file changes
 //! - Search-as-you-type with results from FFI service
 //! - Keyboard navigation (Up/Down/Enter/Esc)
 //! - File actions (open, reveal, copy path)
+//! - Tray icon showing the service status, with quick actions
 
 use std::sync::mpsc;
 use std::sync::Arc;
@@ -14,9 +16,9 @@ use eframe::egui;
 use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use ffi::ui::SearchApp;
-#[cfg(windows)]
-use ffi::ui::HotkeyManager;
+use ffi::service::config::Config;
+use ffi::ui::hotkey::HotkeyCombo;
+use ffi::ui::{HotkeyManager, SearchApp};
 
 /// Main entry point for the FFI search UI.
 fn main() -> eframe::Result<()> {
@@ -36,33 +38,35 @@ fn main() -> eframe::Result<()> {
     // Visibility state shared with hotkey manager
     let visible = Arc::new(AtomicBool::new(true));
 
-    // Setup global hotkey (Ctrl+Space)
-    #[cfg(windows)]
-    {
-        let tx = hotkey_tx;
-        match HotkeyManager::new(move || {
-            info!("Hotkey triggered");
-            let _ = tx.send(());
-        }) {
-            Ok(mut hkm) => {
-                if let Err(e) = hkm.start() {
-                    warn!("Failed to start hotkey listener: {}. Use window focus instead.", e);
-                }
-                // Keep the hotkey manager alive by leaking it
-                // It needs to stay alive for the hotkey to work
-                std::mem::forget(hkm);
-            }
-            Err(e) => {
-                warn!("Failed to register hotkey: {}. Use window focus instead.", e);
+    // Setup the configured global hotkey
+    let combo = match Config::load().map(|config| config.ui.hotkey) {
+        Ok(hotkey) => hotkey.parse::<HotkeyCombo>().unwrap_or_else(|e| {
+            warn!("{}. Using {} instead.", e, HotkeyCombo::default());
+            HotkeyCombo::default()
+        }),
+        Err(e) => {
+            warn!("Failed to load config, using default hotkey: {}", e);
+            HotkeyCombo::default()
+        }
+    };
+    let tx = hotkey_tx;
+    // The manager is kept by the app, as the hotkey is unregistered when
+    // it is dropped
+    let hotkey = match HotkeyManager::new(&combo, move || {
+        info!("Hotkey triggered");
+        let _ = tx.send(());
+    }) {
+        Ok(mut hkm) => {
+            if let Err(e) = hkm.start() {
+                warn!("Failed to start hotkey listener: {}. Use window focus instead.", e);
             }
+            Some(hkm)
         }
-    }
-
-    #[cfg(not(windows))]
-    {
-        let _ = hotkey_tx; // Suppress unused warning on non-Windows
-        warn!("Global hotkey not supported on this platform");
-    }
+        Err(e) => {
+            warn!("Failed to register hotkey: {}. Use window focus instead.", e);
+            None
+        }
+    };
 
     // Create tokio runtime for async IPC
     let runtime = tokio::runtime::Builder::new_multi_thread()
@@ -93,6 +97,7 @@ fn main() -> eframe::Result<()> {
                 cc,
                 runtime.handle().clone(),
                 hotkey_rx,
+                hotkey,
                 visible,
             )))
         }),
@@ -5,26 +5,65 @@
 //!
 //! # Usage
 //!
-//! The service is registered and controlled via `sc.exe`:
+//! The service registers and controls itself from an elevated prompt:
 //! ```cmd
-//! sc create FFIService binPath= "C:\path\to\ffi-service.exe"
-//! sc start FFIService
-//! sc stop FFIService
-//! sc delete FFIService
+//! ffi-service install
+//! ffi-service start
+//! ffi-service stop
+//! ffi-service uninstall
 //! ```
 //!
-//! Logs are written to `C:\ProgramData\FFI\logs\ffi-service.log`.
+//! `install` registers the executable it is run from as a delayed
+//! automatic service with restart-on-failure recovery actions.
+//!
+//! `ffi-service --console [--read-only]` runs the same startup path as the
+//! service in the foreground, logging to the console (filtered by
+//! `RUST_LOG`, default the `[logging]` level) until Ctrl+C, for debugging
+//! indexing without registering a service.
+//!
+//! Logs are written to daily files `C:\ProgramData\FFI\logs\ffi-service.log.<date>`,
+//! as text or JSON lines, and deleted after `[logging] retention_days`.
+//! `ffi-cli log-level <level>` changes the level of the running service.
+//!
+//! Volume snapshots for cataloging offline disks can be exported and
+//! imported from the command line:
+//! ```cmd
+//! ffi-service export-snapshot E: E-archive.ffisnap
+//! ffi-service import-snapshot E-archive.ffisnap ["Archive 2019"]
+//! ```
+//!
+//! `ffi-service status` prints indexed volumes, the directories skipped
+//! after repeated access-denied errors, and when offline volumes are due
+//! for cleanup. Offline volumes can be kept or purged right away:
+//! ```cmd
+//! ffi-service keep-volume E: [off]
+//! ffi-service purge-volume E:
+//! ```
+//!
+//! `ffi-service selftest [dir]` checks the running service end to end: it
+//! creates, renames and deletes a file in `dir` (default: `selftest` in the
+//! data directory, which must be on an indexed NTFS volume) and reports
+//! whether searches picked up each change.
 
-#[cfg(windows)]
 use std::ffi::OsString;
 
 #[cfg(windows)]
 use windows_service::{define_windows_service, service_dispatcher};
 
-use ffi::service::{run_service, ServiceConfig};
+use std::path::Path;
 
-#[cfg(windows)]
-use ffi::service::SERVICE_NAME;
+use ffi::db::{
+    configure_database, export_volume_snapshot, get_all_volumes, get_offline_volumes, get_skipped_paths,
+    get_volume_state, get_volume_stats, import_volume_snapshot, open_database,
+};
+use ffi::ipc::commands::execute_command;
+use ffi::ipc::Command;
+use ffi::service::config::Config;
+use ffi::service::{
+    init_console_logging, init_file_logging, install_service, run_console, run_selftest, run_service, start_service,
+    stop_service, uninstall_service, StageOutcome, SERVICE_NAME,
+};
+use tracing_appender::non_blocking::WorkerGuard;
 
 #[cfg(windows)]
 define_windows_service!(ffi_service_main, service_main);
@@ -32,8 +71,9 @@ define_windows_service!(ffi_service_main, service_main);
 /// Service entry point called by Windows SCM.
 #[cfg(windows)]
 fn service_main(arguments: Vec<OsString>) {
-    // Initialize logging before service starts
-    init_logging();
+    // Initialize logging before service starts; the guard writes out the
+    // last lines when the service returns
+    let _log_guard = init_logging();
 
     // Run the service
     if let Err(e) = run_service(arguments) {
@@ -42,29 +82,28 @@ fn service_main(arguments: Vec<OsString>) {
 }
 
 /// Initialize tracing with file appender for service logging.
-fn init_logging() {
-    let config = ServiceConfig::load();
-    let log_dir = config.data_dir.join("logs");
+///
+/// Returns the guard flushing buffered log lines, or `None` if logging fell
+/// back to stderr.
+fn init_logging() -> Option<WorkerGuard> {
+    let config = Config::load().unwrap_or_default();
+    let log_dir = config.data_dir().join("logs");
 
     // Create log directory if it doesn't exist
-    if let Err(e) = std::fs::create_dir_all(&log_dir) {
-        eprintln!("Failed to create log directory {:?}: {}", log_dir, e);
-        // Fall back to stderr logging
-        tracing_subscriber::fmt()
-            .with_env_filter("info")
-            .init();
-        return;
-    }
-
-    // Set up daily rotating file appender
-    let file_appender = tracing_appender::rolling::daily(&log_dir, "ffi-service.log");
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-
-    tracing_subscriber::fmt()
-        .with_writer(non_blocking)
-        .with_env_filter("info")
-        .with_ansi(false) // No ANSI colors in log files
-        .init();
+    let guard = std::fs::create_dir_all(&log_dir)
+        .map_err(ffi::FFIError::from)
+        .and_then(|()| init_file_logging(&log_dir, &config.logging));
+    let guard = match guard {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Failed to set up logging in {:?}: {}", log_dir, e);
+            // Fall back to stderr logging
+            tracing_subscriber::fmt()
+                .with_env_filter("info")
+                .init();
+            return None;
+        }
+    };
 
     tracing::info!("Logging initialized to {:?}", log_dir);
 
@@ -73,10 +112,171 @@ fn init_logging() {
         "FastFileIndex Service v{} starting",
         env!("CARGO_PKG_VERSION")
     );
+    Some(guard)
+}
+
+/// Run in the foreground, logging to the console, if `--console` was given.
+///
+/// Returns `None` otherwise.
+fn run_console_mode(args: &[String]) -> Option<ffi::Result<()>> {
+    if !args.iter().skip(1).any(|arg| arg == "--console") {
+        return None;
+    }
+
+    if let Err(e) = init_console_logging(&Config::load().unwrap_or_default().logging) {
+        return Some(Err(e));
+    }
+    tracing::info!("FastFileIndex Service v{} starting in the console", env!("CARGO_PKG_VERSION"));
+
+    Some(run_console(args.iter().skip(1).map(OsString::from).collect()))
+}
+
+/// Run a snapshot command if one was given on the command line.
+///
+/// Returns `None` when no command was given (normal service start).
+fn run_command(args: &[String]) -> Option<ffi::Result<()>> {
+    let command = args.get(1)?;
+    let config = Config::load().unwrap_or_default();
+    let db_path = config.data_dir().join("index.db");
+    if let Err(e) = configure_database(config.database.clone()) {
+        eprintln!("Invalid [database] settings, using defaults: {}", e);
+    }
+
+    let result = match (command.as_str(), &args[2..]) {
+        ("export-snapshot", [drive, dest]) => open_database(&db_path).and_then(|db| {
+            let info = export_volume_snapshot(db.conn(), drive, Path::new(dest))?;
+            println!("Exported {} files from {} to {}", info.file_count, info.drive_letter, dest);
+            Ok(())
+        }),
+        ("import-snapshot", [src, rest @ ..]) if rest.len() <= 1 => {
+            open_database(&db_path).and_then(|mut db| {
+                let name = rest.first().map(String::as_str);
+                let info = import_volume_snapshot(db.conn_mut(), Path::new(src), name)?;
+                println!(
+                    "Imported {} files from {} as {}",
+                    info.file_count,
+                    src,
+                    name.map(str::to_string).unwrap_or_else(|| info.default_import_name())
+                );
+                Ok(())
+            })
+        }
+        ("install", []) => install_service().map(|()| {
+            println!("Installed {}; start it with `{} start`", SERVICE_NAME, args[0]);
+        }),
+        ("uninstall", []) => uninstall_service().map(|()| println!("Uninstalled {}", SERVICE_NAME)),
+        ("start", []) => start_service().map(|()| println!("{} is running", SERVICE_NAME)),
+        ("stop", []) => stop_service().map(|()| println!("{} stopped", SERVICE_NAME)),
+        ("status", []) => open_database(&db_path).and_then(|db| print_status(db.conn(), &config)),
+        ("keep-volume", [drive, rest @ ..]) if rest.is_empty() || rest == ["off"] => {
+            run_volume_command(&db_path, Command::KeepVolume {
+                drive_letter: drive.clone(),
+                keep: rest.is_empty(),
+            })
+        }
+        ("purge-volume", [drive]) => run_volume_command(&db_path, Command::PurgeVolume {
+            drive_letter: drive.clone(),
+        }),
+        ("selftest", rest) if rest.len() <= 1 => {
+            let results = run_selftest(&config, rest.first().map(Path::new));
+            for result in &results {
+                println!("{}  {:<9}  {}", result.outcome.label(), result.stage, result.detail);
+            }
+            match results.iter().find(|r| r.outcome == StageOutcome::Fail) {
+                Some(failed) => Err(ffi::FFIError::Service(format!("Self-test failed at the {} stage", failed.stage))),
+                None => Ok(()),
+            }
+        }
+        _ => Err(ffi::FFIError::Config(format!(
+            "Usage: {0} install | {0} uninstall | {0} start | {0} stop | {0} status | {0} export-snapshot <drive> <file> | {0} import-snapshot <file> [name] \
+             | {0} keep-volume <drive> [off] | {0} purge-volume <drive> | {0} selftest [dir]",
+            args[0]
+        ))),
+    };
+    Some(result)
+}
+
+/// Run a volume retention command against the database.
+fn run_volume_command(db_path: &Path, command: Command) -> ffi::Result<()> {
+    let mut db = open_database(db_path)?;
+    let response = execute_command(db.conn_mut(), &command);
+    if response.success {
+        println!("{}", response.message);
+        Ok(())
+    } else {
+        Err(ffi::FFIError::Config(response.message))
+    }
+}
+
+/// Print volumes with their state, stored scan statistics, skipped
+/// directories, and pending offline cleanup.
+fn print_status(conn: &rusqlite::Connection, config: &Config) -> ffi::Result<()> {
+    let skipped = get_skipped_paths(conn, None)?;
+    let offline = get_offline_volumes(conn, config.offline_retention())?;
+
+    for volume in get_all_volumes(conn)? {
+        let state = get_volume_state(conn, volume.id)?;
+        let stats = get_volume_stats(conn, volume.id)?;
+        println!(
+            "{} ({}, {}{}): {} files, {} directories, {} bytes",
+            volume.drive_letter,
+            volume.fs_type,
+            state.to_db_str(),
+            state.progress().map(|percent| format!(" {}%", percent)).unwrap_or_default(),
+            stats.file_count,
+            stats.dir_count,
+            stats.total_bytes
+        );
+        if let Some(scanned) = stats.last_scan_time {
+            println!(
+                "  last scan {} ({:.1}s)",
+                format_timestamp(scanned),
+                stats.last_scan_duration_ms as f64 / 1000.0
+            );
+        }
+
+        if let Some(offline) = offline.iter().find(|o| o.volume.id == volume.id) {
+            match offline.purge_after {
+                Some(purge_after) => println!(
+                    "  offline since {}, index deleted after {} (keep-volume to retain)",
+                    format_timestamp(offline.offline_since),
+                    format_timestamp(purge_after)
+                ),
+                None => println!(
+                    "  offline since {}, kept forever",
+                    format_timestamp(offline.offline_since)
+                ),
+            }
+        }
+
+        for path in skipped.iter().filter(|s| s.volume_id == volume.id) {
+            println!(
+                "  access denied on {} consecutive scans: {}",
+                path.failures, path.path
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Format a Unix timestamp as a local date and time.
+fn format_timestamp(timestamp: i64) -> String {
+    use chrono::TimeZone;
+
+    chrono::Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
 }
 
 #[cfg(windows)]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(result) = run_console_mode(&args).or_else(|| run_command(&args)) {
+        return result.map_err(Into::into);
+    }
+
     // Start the service dispatcher
     // This blocks until the service is stopped
     service_dispatcher::start(SERVICE_NAME, ffi_service_main)?;
@@ -86,7 +286,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// Non-Windows entry point for development/testing.
 #[cfg(not(windows))]
 fn main() {
-    init_logging();
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(result) = run_console_mode(&args).or_else(|| run_command(&args)) {
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let _log_guard = init_logging();
     tracing::warn!("FFI Service requires Windows to run as a service");
     tracing::info!("On non-Windows platforms, this binary can only be used for testing");
 
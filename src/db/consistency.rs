@@ -0,0 +1,238 @@
+//! Consistency of the indexed file tree.
+//!
+//! Missed or reordered USN records and interrupted batches can leave
+//! entries that no path leads to: files whose parent directory was deleted
+//! or never indexed, children of an entry that is not a directory, and
+//! entries caught in a parent cycle whose `full_path` stays NULL. This
+//! module finds them and deletes subtrees found gone from disk; the
+//! background pass in `indexer::consistency` decides what to do with each.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use super::facets::FacetDeltas;
+use crate::{FFIError, Result};
+
+/// What is wrong with an entry's place in the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TreeIssueKind {
+    /// The parent is not indexed: deleted, or never seen
+    MissingParent,
+    /// The parent is indexed as a file
+    ParentNotDirectory,
+    /// The parent is a directory, but no path could be built (a parent
+    /// cycle or a chain deeper than paths are built for)
+    Unreachable,
+}
+
+/// An entry whose place in the tree is broken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeIssue {
+    /// File reference of the entry
+    pub file_ref: i64,
+    /// Parent reference it has in the index
+    pub parent_ref: i64,
+    /// Indexed name
+    pub name: String,
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+    /// What is wrong
+    pub kind: TreeIssueKind,
+}
+
+/// Find entries of a volume whose place in the tree is broken.
+///
+/// Only primary names are checked; hard links and streams follow their
+/// file. Walks of FAT volumes and shares don't index the root, so their
+/// top-level entries (parent 0) are not orphans.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume to check
+/// * `limit` - Most issues to return
+///
+/// # Returns
+/// Issues ordered by file reference.
+pub fn find_tree_issues(conn: &Connection, volume_id: i64, limit: usize) -> Result<Vec<TreeIssue>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT f.file_ref, f.parent_ref, f.name, f.is_dir,
+                    CASE WHEN p.id IS NULL THEN 0 WHEN p.is_dir = 0 THEN 1 ELSE 2 END
+             FROM files f
+             LEFT JOIN files p ON p.volume_id = f.volume_id AND p.file_ref = f.parent_ref
+                 AND p.link = 0 AND p.stream = ''
+             WHERE f.volume_id = ?1 AND f.link = 0 AND f.stream = '' AND f.file_ref IS NOT NULL
+               AND f.parent_ref IS NOT NULL AND f.parent_ref <> f.file_ref AND f.parent_ref <> 0
+               AND (p.id IS NULL OR p.is_dir = 0 OR f.full_path IS NULL)
+             ORDER BY f.file_ref
+             LIMIT ?2",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare tree check: {}", e)))?;
+
+    let rows = stmt
+        .query_map(params![volume_id, limit as i64], |row| {
+            let kind = match row.get::<_, i64>(4)? {
+                0 => TreeIssueKind::MissingParent,
+                1 => TreeIssueKind::ParentNotDirectory,
+                _ => TreeIssueKind::Unreachable,
+            };
+            Ok(TreeIssue {
+                file_ref: row.get(0)?,
+                parent_ref: row.get(1)?,
+                name: row.get(2)?,
+                is_dir: row.get(3)?,
+                kind,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to check tree: {}", e)))?;
+
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read tree issue: {}", e)))
+}
+
+/// Delete entries and everything indexed below them.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume the entries belong to
+/// * `file_refs` - Roots of the subtrees to delete
+///
+/// # Returns
+/// The number of rows deleted, hard links and streams included.
+pub fn delete_subtrees(conn: &mut Connection, volume_id: i64, file_refs: &[i64]) -> Result<usize> {
+    if file_refs.is_empty() {
+        return Ok(0);
+    }
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+    tx.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS subtree_roots (file_ref INTEGER PRIMARY KEY);
+         DELETE FROM temp.subtree_roots;",
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to prepare subtree delete: {}", e)))?;
+    {
+        let mut stmt = tx
+            .prepare("INSERT OR IGNORE INTO temp.subtree_roots (file_ref) VALUES (?1)")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare subtree delete: {}", e)))?;
+        for file_ref in file_refs {
+            stmt.execute(params![file_ref])
+                .map_err(|e| FFIError::Database(format!("Failed to seed subtree delete: {}", e)))?;
+        }
+    }
+
+    let mut facets = FacetDeltas::new();
+    let mut deleted = 0;
+    {
+        // UNION rather than UNION ALL stops at parent cycles
+        let mut stmt = tx
+            .prepare(
+                "WITH RECURSIVE subtree(file_ref) AS (
+                     SELECT file_ref FROM temp.subtree_roots
+                     UNION
+                     SELECT c.file_ref FROM subtree
+                     JOIN files c ON c.volume_id = ?1 AND c.parent_ref = subtree.file_ref
+                         AND c.file_ref <> c.parent_ref AND c.link = 0 AND c.stream = ''
+                 )
+                 DELETE FROM files WHERE volume_id = ?1 AND file_ref IN (SELECT file_ref FROM subtree)
+                 RETURNING name, size, is_dir, link, stream",
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to prepare subtree delete: {}", e)))?;
+        let rows = stmt
+            .query_map(params![volume_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, bool>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })
+            .map_err(|e| FFIError::Database(format!("Failed to delete subtrees: {}", e)))?;
+        for row in rows {
+            let (name, size, is_dir, link, stream) =
+                row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+            // Facets count each file once, by its primary name
+            if link == 0 && stream.is_empty() {
+                facets.remove(&name, size, is_dir);
+            }
+            deleted += 1;
+        }
+    }
+    if !facets.is_empty() {
+        facets.apply(&tx, volume_id)?;
+    }
+
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit subtree delete: {}", e)))?;
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, insert_volume, schema, FileEntry};
+
+    fn setup() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        batch_insert_files(
+            &mut conn,
+            &[
+                FileEntry::test(1, 5, 5, ".", true),
+                FileEntry::test(1, 100, 5, "Users", true),
+                FileEntry::test(1, 101, 100, "notes.txt", false),
+                // Parent 200 was deleted
+                FileEntry::test(1, 201, 200, "orphan", true),
+                FileEntry::test(1, 202, 201, "below.txt", false),
+                // Parent is a file
+                FileEntry::test(1, 301, 101, "child.txt", false),
+                // A parent cycle
+                FileEntry::test(1, 401, 402, "a", true),
+                FileEntry::test(1, 402, 401, "b", true),
+            ],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_find_tree_issues() {
+        let conn = setup();
+        let issues = find_tree_issues(&conn, 1, 100).unwrap();
+        let found: Vec<(i64, TreeIssueKind)> = issues.iter().map(|i| (i.file_ref, i.kind)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (201, TreeIssueKind::MissingParent),
+                (301, TreeIssueKind::ParentNotDirectory),
+                (401, TreeIssueKind::Unreachable),
+                (402, TreeIssueKind::Unreachable),
+            ]
+        );
+        assert_eq!(issues[0].parent_ref, 200);
+        assert_eq!(issues[0].name, "orphan");
+        assert!(issues[0].is_dir);
+
+        assert_eq!(find_tree_issues(&conn, 1, 1).unwrap().len(), 1);
+        assert!(find_tree_issues(&conn, 2, 100).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_subtrees() {
+        let mut conn = setup();
+        assert_eq!(delete_subtrees(&mut conn, 1, &[201, 401]).unwrap(), 4);
+
+        let remaining: Vec<i64> = conn
+            .prepare("SELECT file_ref FROM files ORDER BY file_ref")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(remaining, vec![5, 100, 101, 301]);
+        assert_eq!(delete_subtrees(&mut conn, 1, &[]).unwrap(), 0);
+    }
+}
@@ -0,0 +1,245 @@
+//! Indexed text of small files.
+//!
+//! With `[content]` indexing enabled, the background pass in
+//! `indexer::content` reads files with allowed extensions up to a size cap
+//! and stores their text in the `file_contents` FTS5 table, keyed by
+//! `files.id`, for `content:` filters. This module finds the files whose
+//! text is missing or stale and writes, prunes and clears the stored text.
+
+use rusqlite::{params, params_from_iter, Connection};
+
+use crate::{FFIError, Result};
+
+/// A file whose text should be read, because it was never read or changed
+/// since.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentCandidate {
+    /// Row ID in `files`
+    pub id: i64,
+    /// Full path, with the volume name
+    pub path: String,
+    /// Size in bytes
+    pub size: i64,
+    /// Last modified time as Unix timestamp
+    pub modified: Option<i64>,
+}
+
+/// Text read from a file, with the version of the file it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileContent {
+    /// Row ID in `files`
+    pub id: i64,
+    /// Size in bytes when read
+    pub size: i64,
+    /// Last modified time when read
+    pub modified: Option<i64>,
+    /// The text; empty for a file that could not be read as text
+    pub text: String,
+}
+
+/// Find files of online volumes whose text is missing or stale.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `extensions` - Extensions to read, lowercase and without the dot
+/// * `max_size` - Largest file read, in bytes
+/// * `limit` - Most files to return
+///
+/// # Returns
+/// Candidates in row order.
+pub fn content_candidates(
+    conn: &Connection,
+    extensions: &[String],
+    max_size: i64,
+    limit: usize,
+) -> Result<Vec<ContentCandidate>> {
+    if extensions.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders = vec!["?"; extensions.len()].join(", ");
+    let sql = format!(
+        "SELECT f.id, v.drive_letter || '\\' || f.full_path, f.size, f.modified
+         FROM files f
+         JOIN volumes v ON v.id = f.volume_id AND v.state = 'online'
+         LEFT JOIN file_contents c ON c.rowid = f.id
+         WHERE f.ext IN ({}) AND f.is_dir = 0 AND f.link = 0 AND f.stream = ''
+           AND f.size <= ? AND f.full_path IS NOT NULL
+           AND (c.rowid IS NULL OR c.size IS NOT f.size OR c.modified IS NOT f.modified)
+         ORDER BY f.id
+         LIMIT ?",
+        placeholders
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| FFIError::Database(format!("Failed to prepare content query: {}", e)))?;
+
+    let mut values: Vec<rusqlite::types::Value> = extensions
+        .iter()
+        .map(|ext| rusqlite::types::Value::Text(ext.to_ascii_lowercase()))
+        .collect();
+    values.push(rusqlite::types::Value::Integer(max_size));
+    values.push(rusqlite::types::Value::Integer(limit as i64));
+
+    let rows = stmt
+        .query_map(params_from_iter(values), |row| {
+            Ok(ContentCandidate {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                size: row.get(2)?,
+                modified: row.get(3)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query content candidates: {}", e)))?;
+
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read content candidate: {}", e)))
+}
+
+/// Store the text of files, replacing what was stored for them.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `contents` - Text read from each file
+pub fn store_contents(conn: &mut Connection, contents: &[FileContent]) -> Result<()> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+    {
+        let mut delete = tx
+            .prepare_cached("DELETE FROM file_contents WHERE rowid = ?1")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare content delete: {}", e)))?;
+        let mut insert = tx
+            .prepare_cached("INSERT INTO file_contents (rowid, content, modified, size) VALUES (?1, ?2, ?3, ?4)")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare content insert: {}", e)))?;
+        for content in contents {
+            delete
+                .execute(params![content.id])
+                .map_err(|e| FFIError::Database(format!("Failed to replace file contents: {}", e)))?;
+            insert
+                .execute(params![content.id, content.text, content.modified, content.size])
+                .map_err(|e| FFIError::Database(format!("Failed to store file contents: {}", e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit file contents: {}", e)))
+}
+
+/// Delete the text of files no longer read: other extensions, or grown
+/// past the size cap.
+///
+/// # Returns
+/// The number of files whose text was deleted.
+pub fn prune_contents(conn: &Connection, extensions: &[String], max_size: i64) -> Result<usize> {
+    let placeholders = vec!["?"; extensions.len()].join(", ");
+    let sql = format!(
+        "DELETE FROM file_contents WHERE rowid NOT IN (
+             SELECT id FROM files WHERE ext IN ({}) AND size <= ?
+         )",
+        placeholders
+    );
+
+    let mut values: Vec<rusqlite::types::Value> = extensions
+        .iter()
+        .map(|ext| rusqlite::types::Value::Text(ext.to_ascii_lowercase()))
+        .collect();
+    values.push(rusqlite::types::Value::Integer(max_size));
+
+    conn.execute(&sql, params_from_iter(values))
+        .map_err(|e| FFIError::Database(format!("Failed to prune file contents: {}", e)))
+}
+
+/// Delete all stored text, once content indexing is turned off.
+///
+/// # Returns
+/// The number of files whose text was deleted.
+pub fn clear_contents(conn: &Connection) -> Result<usize> {
+    let stored: i64 = conn
+        .query_row("SELECT COUNT(*) FROM file_contents", [], |row| row.get(0))
+        .map_err(|e| FFIError::Database(format!("Failed to count file contents: {}", e)))?;
+    if stored == 0 {
+        return Ok(0);
+    }
+
+    conn.execute_batch("DELETE FROM file_contents")
+        .map_err(|e| FFIError::Database(format!("Failed to clear file contents: {}", e)))?;
+    Ok(stored as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, insert_volume, schema, update_volume_state, FileEntry};
+    use crate::VolumeState;
+
+    fn stored(conn: &Connection, query: &str) -> Vec<i64> {
+        conn.prepare("SELECT rowid FROM file_contents WHERE file_contents MATCH ?1 ORDER BY rowid")
+            .unwrap()
+            .query_map([query], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_contents() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let c = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let e = insert_volume(&conn, "E:", "5678", "FAT32").unwrap();
+        update_volume_state(&conn, e, VolumeState::Offline { since: 1_700_000_000 }).unwrap();
+        let file = |volume_id, file_ref, name, size| FileEntry {
+            size,
+            modified: Some(1_700_000_000),
+            ..FileEntry::test(volume_id, file_ref, 5, name, false)
+        };
+        batch_insert_files(
+            &mut conn,
+            &[
+                FileEntry::test(c, 5, 5, ".", true),
+                file(c, 10, "main.rs", 100),
+                file(c, 11, "notes.TXT", 200),
+                file(c, 12, "photo.jpg", 100),
+                file(c, 13, "huge.log", 10_000_000),
+                file(e, 20, "readme.txt", 100),
+            ],
+        )
+        .unwrap();
+
+        let extensions = vec!["rs".to_string(), "txt".to_string(), "log".to_string()];
+        let candidates = content_candidates(&conn, &extensions, 1024 * 1024, 100).unwrap();
+        let paths: Vec<&str> = candidates.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec![r"C:\main.rs", r"C:\notes.TXT"]);
+
+        let contents: Vec<FileContent> = candidates
+            .iter()
+            .map(|c| FileContent {
+                id: c.id,
+                size: c.size,
+                modified: c.modified,
+                text: format!("// TODO refactor {}", c.size),
+            })
+            .collect();
+        store_contents(&mut conn, &contents).unwrap();
+        assert!(content_candidates(&conn, &extensions, 1024 * 1024, 100).unwrap().is_empty());
+        assert_eq!(stored(&conn, "\"TODO refactor\" 200"), vec![candidates[1].id]);
+
+        // A changed file is read again; replacing keeps one row per file
+        conn.execute("UPDATE files SET modified = modified + 1 WHERE name = 'main.rs'", [])
+            .unwrap();
+        let stale = content_candidates(&conn, &extensions, 1024 * 1024, 100).unwrap();
+        assert_eq!(stale.len(), 1);
+        store_contents(&mut conn, &[FileContent { text: "fn main".to_string(), ..contents[0].clone() }]).unwrap();
+        assert_eq!(stored(&conn, "refactor"), vec![candidates[1].id]);
+
+        // Deleted files take their text along; unlisted extensions are pruned
+        conn.execute("DELETE FROM files WHERE name = 'main.rs'", []).unwrap();
+        assert!(stored(&conn, "main").is_empty());
+        assert_eq!(prune_contents(&conn, &["rs".to_string()], 1024 * 1024).unwrap(), 1);
+        assert!(stored(&conn, "refactor").is_empty());
+
+        store_contents(&mut conn, &contents[1..]).unwrap();
+        assert_eq!(clear_contents(&conn).unwrap(), 1);
+        assert_eq!(clear_contents(&conn).unwrap(), 0);
+    }
+}
@@ -0,0 +1,862 @@
+//! Exclusion suggestions from index analysis.
+//!
+//! After the initial scan the service looks for trees that are large but
+//! rarely searched for (browser caches, `node_modules`, telemetry folders)
+//! and for directories whose children change constantly according to the
+//! USN journal. Suggestions are stored so the settings UI can list them and
+//! adopt one into `[exclude] paths` with a click.
+//!
+//! Entries indexed before a pattern was added to `[exclude]` or a volume's
+//! `[volumes.<name>.exclude]`, or before its `max_depth` or `max_files` was
+//! lowered, are purged by a maintenance pass, so the index converges on
+//! what the scanners and the USN monitor would store today.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection};
+
+use crate::service::config::{Config, ExcludeConfig};
+use crate::service::ExcludeMatcher;
+use crate::{FFIError, Result};
+
+use super::facets::FacetDeltas;
+use super::ops::{get_all_volumes, reconstruct_path_checked, set_volume_truncated};
+
+/// Directory names that usually hold low-value, high-churn content.
+pub const LOW_VALUE_DIR_NAMES: &[&str] = &[
+    "node_modules",
+    "__pycache__",
+    ".cache",
+    "Cache",
+    "Cache2",
+    "Code Cache",
+    "GPUCache",
+    "ShaderCache",
+    "INetCache",
+    "Temp",
+    "CrashDumps",
+    "Crashpad",
+    "Telemetry",
+    "DiagTrack",
+];
+
+/// Minimum files under a known low-value directory before it is suggested.
+pub const MIN_SUGGESTED_FILES: i64 = 1_000;
+
+/// USN changes per day in a directory's children that count as high churn.
+pub const HIGH_CHURN_PER_DAY: f64 = 500.0;
+
+/// A directory suggested for exclusion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExclusionSuggestion {
+    /// Volume the directory belongs to
+    pub volume_id: i64,
+    /// Full path of the directory (e.g., `C:\Users\me\node_modules`)
+    pub path: String,
+    /// Why the directory was suggested
+    pub reason: String,
+    /// Number of indexed entries below the directory
+    pub file_count: i64,
+    /// Average USN changes per day to its direct children
+    pub changes_per_day: f64,
+}
+
+/// Count USN changes per directory, for churn-based suggestions.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume the changes were read from
+/// * `counts` - Number of changes keyed by parent directory reference
+pub fn record_dir_churn(conn: &Connection, volume_id: i64, counts: &HashMap<i64, i64>) -> Result<()> {
+    let mut stmt = conn
+        .prepare_cached(
+            "INSERT INTO dir_churn (volume_id, dir_ref, changes, since)
+             VALUES (?1, ?2, ?3, strftime('%s', 'now'))
+             ON CONFLICT(volume_id, dir_ref) DO UPDATE SET changes = changes + excluded.changes",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare churn update: {}", e)))?;
+
+    for (dir_ref, changes) in counts {
+        stmt.execute(params![volume_id, dir_ref, changes])
+            .map_err(|e| FFIError::Database(format!("Failed to record directory churn: {}", e)))?;
+    }
+
+    Ok(())
+}
+
+/// Analyze the index for directories worth excluding.
+///
+/// Finds known low-value directories with at least [`MIN_SUGGESTED_FILES`]
+/// entries and directories changing at least [`HIGH_CHURN_PER_DAY`] times a
+/// day. Paths already excluded and directories nested in another suggestion
+/// are left out.
+///
+/// # Returns
+/// Suggestions ordered by file count, largest first.
+pub fn analyze_exclusions(conn: &Connection, exclude: &ExcludeConfig) -> Result<Vec<ExclusionSuggestion>> {
+    let now = chrono::Utc::now().timestamp();
+    let exclude = exclude.matcher();
+    let mut candidates: HashMap<(i64, i64), (Option<String>, f64)> = HashMap::new();
+
+    // Known low-value directory names
+    let mut stmt = conn
+        .prepare(
+            "SELECT volume_id, file_ref FROM files
+             WHERE name = ?1 COLLATE NOCASE AND is_dir = 1 AND file_ref IS NOT NULL
+               AND volume_id IN (SELECT id FROM volumes WHERE state NOT IN ('shadow', 'imported'))",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare directory query: {}", e)))?;
+    for name in LOW_VALUE_DIR_NAMES {
+        let rows = stmt
+            .query_map(params![name], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| FFIError::Database(format!("Failed to query directories: {}", e)))?;
+        for row in rows {
+            let key = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+            candidates.insert(key, (Some(format!("{} folder", name)), 0.0));
+        }
+    }
+
+    // High-churn directories seen by the USN monitor
+    let mut stmt = conn
+        .prepare(
+            "SELECT volume_id, dir_ref, CAST(changes AS REAL) / MAX(?1 - since, 86400) * 86400
+             FROM dir_churn",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare churn query: {}", e)))?;
+    let rows = stmt
+        .query_map(params![now], |row| {
+            Ok(((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?), row.get::<_, f64>(2)?))
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query churn: {}", e)))?;
+    for row in rows {
+        let (key, rate) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+        if rate >= HIGH_CHURN_PER_DAY {
+            candidates.entry(key).or_insert((None, 0.0)).1 = rate;
+        }
+    }
+
+    let letters: HashMap<i64, String> = get_all_volumes(conn)?
+        .into_iter()
+        .map(|v| (v.id, v.drive_letter))
+        .collect();
+
+    let mut suggestions = Vec::new();
+    for ((volume_id, dir_ref), (name_reason, changes_per_day)) in candidates {
+        let file_count = subtree_size(conn, volume_id, dir_ref)?;
+        if name_reason.is_some() && changes_per_day == 0.0 && file_count < MIN_SUGGESTED_FILES {
+            continue;
+        }
+
+        let reconstructed = reconstruct_path_checked(conn, volume_id, dir_ref)?;
+        let relative = reconstructed.path;
+        if reconstructed.truncated || relative.as_os_str().is_empty() {
+            continue; // Never suggest a volume root or a partial path
+        }
+        let path = match letters.get(&volume_id) {
+            Some(letter) => format!("{}\\{}", letter, relative.display()),
+            None => relative.display().to_string(),
+        };
+        if exclude.should_exclude_path(&path) {
+            continue;
+        }
+
+        let reason = match (name_reason, changes_per_day > 0.0) {
+            (Some(name), true) => format!("{}, {:.0} changes/day", name, changes_per_day),
+            (Some(name), false) => name,
+            (None, _) => format!("{:.0} changes/day", changes_per_day),
+        };
+        suggestions.push(ExclusionSuggestion {
+            volume_id,
+            path,
+            reason,
+            file_count,
+            changes_per_day,
+        });
+    }
+
+    // Drop suggestions inside another suggestion (e.g. nested node_modules)
+    suggestions.sort_by_key(|s| s.path.to_lowercase());
+    let mut outermost: Vec<ExclusionSuggestion> = Vec::new();
+    for suggestion in suggestions {
+        let lower = suggestion.path.to_lowercase();
+        let nested = outermost.iter().any(|parent| {
+            let parent = parent.path.to_lowercase();
+            lower.starts_with(&parent) && lower[parent.len()..].starts_with(['\\', '/'])
+        });
+        if !nested {
+            outermost.push(suggestion);
+        }
+    }
+
+    outermost.sort_by(|a, b| b.file_count.cmp(&a.file_count).then_with(|| a.path.cmp(&b.path)));
+    Ok(outermost)
+}
+
+/// Replace the stored suggestions with a fresh analysis.
+pub fn save_exclusion_suggestions(conn: &mut Connection, suggestions: &[ExclusionSuggestion]) -> Result<()> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+    tx.execute("DELETE FROM exclusion_suggestions", [])
+        .map_err(|e| FFIError::Database(format!("Failed to clear exclusion suggestions: {}", e)))?;
+
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT OR REPLACE INTO exclusion_suggestions
+                 (path, volume_id, reason, file_count, changes_per_day)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to prepare suggestion insert: {}", e)))?;
+        for s in suggestions {
+            stmt.execute(params![s.path, s.volume_id, s.reason, s.file_count, s.changes_per_day])
+                .map_err(|e| FFIError::Database(format!("Failed to store exclusion suggestion: {}", e)))?;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit exclusion suggestions: {}", e)))
+}
+
+/// Get the stored suggestions, largest first.
+pub fn get_exclusion_suggestions(conn: &Connection) -> Result<Vec<ExclusionSuggestion>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT volume_id, path, reason, file_count, changes_per_day
+             FROM exclusion_suggestions
+             ORDER BY file_count DESC, path",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare suggestions query: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(ExclusionSuggestion {
+                volume_id: row.get(0)?,
+                path: row.get(1)?,
+                reason: row.get(2)?,
+                file_count: row.get(3)?,
+                changes_per_day: row.get(4)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query suggestions: {}", e)))?;
+
+    let mut suggestions = Vec::new();
+    for row in rows {
+        suggestions.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
+    }
+
+    Ok(suggestions)
+}
+
+/// Delete the entries of a volume under excluded path prefixes.
+///
+/// Prefixes are matched against `full_path`, so a whole excluded tree is
+/// removed with one indexed range delete per prefix. Prefixes for other
+/// drives are ignored. Directory names and glob patterns are left to
+/// [`purge_excluded_patterns`]. Removed entries are subtracted from
+/// `facets`; the caller applies them.
+///
+/// # Returns
+/// The number of entries deleted.
+pub fn purge_excluded_paths(
+    conn: &Connection,
+    volume_id: i64,
+    exclude: &ExcludeMatcher,
+    facets: &mut FacetDeltas,
+) -> Result<usize> {
+    if exclude.paths().is_empty() {
+        return Ok(0);
+    }
+
+    let drive_letter = volume_name(conn, volume_id)?;
+
+    let mut stmt = conn
+        .prepare_cached(
+            "DELETE FROM files
+             WHERE volume_id = ?1 AND full_path >= ?2 COLLATE NOCASE AND full_path < ?3 COLLATE NOCASE
+             RETURNING name, size, is_dir",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare excluded path delete: {}", e)))?;
+
+    let mut deleted = 0;
+    for pattern in exclude.paths() {
+        // "C:\Windows\Temp" -> "Windows\Temp" for volume "C:"
+        let pattern = pattern.replace('/', "\\");
+        let Some(relative) = pattern
+            .get(..drive_letter.len())
+            .filter(|drive| drive.eq_ignore_ascii_case(&drive_letter))
+            .and_then(|_| pattern.get(drive_letter.len()..))
+            .filter(|rest| rest.is_empty() || rest.starts_with('\\'))
+        else {
+            continue;
+        };
+        let relative = relative.trim_start_matches('\\');
+
+        // Every path with the prefix sorts between it and prefix + U+10FFFF
+        let upper = format!("{}\u{10FFFF}", relative);
+        let rows = stmt
+            .query_map(params![volume_id, relative, upper], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?))
+            })
+            .map_err(|e| FFIError::Database(format!("Failed to delete excluded paths: {}", e)))?;
+        for row in rows {
+            let (name, size, is_dir) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+            facets.remove(&name, size, is_dir);
+            deleted += 1;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Delete the files of a volume with excluded extensions.
+///
+/// Scans every file name of the volume, so it runs as a maintenance pass
+/// rather than per change. Removed entries are subtracted from `facets`.
+///
+/// # Returns
+/// The number of entries deleted.
+pub fn purge_excluded_extensions(
+    conn: &Connection,
+    volume_id: i64,
+    exclude: &ExcludeMatcher,
+    facets: &mut FacetDeltas,
+) -> Result<usize> {
+    let mut stmt = conn
+        .prepare_cached(
+            "DELETE FROM files WHERE volume_id = ?1 AND is_dir = 0 AND name LIKE ?2 ESCAPE '\\'
+             RETURNING name, size, is_dir",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare excluded extension delete: {}", e)))?;
+
+    let mut deleted = 0;
+    for ext in exclude.extensions() {
+        let escaped = ext.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let rows = stmt
+            .query_map(params![volume_id, format!("%.{}", escaped)], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?))
+            })
+            .map_err(|e| FFIError::Database(format!("Failed to delete excluded extensions: {}", e)))?;
+        for row in rows {
+            let (name, size, is_dir) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+            facets.remove(&name, size, is_dir);
+            deleted += 1;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Delete the entries of a volume in excluded directories or matching
+/// excluded glob patterns, with everything below them. Entries past the
+/// depth limit go too, though [`purge_too_deep`] removes them faster.
+///
+/// Checks the path of every entry of the volume, skipping the trees
+/// already deleted, so it runs after full scans and as a maintenance pass.
+/// Removed entries are subtracted from `facets`; the caller applies them.
+///
+/// # Returns
+/// The number of entries deleted.
+pub fn purge_excluded_patterns(
+    conn: &Connection,
+    volume_id: i64,
+    exclude: &ExcludeMatcher,
+    facets: &mut FacetDeltas,
+) -> Result<usize> {
+    if !exclude.has_patterns() {
+        return Ok(0);
+    }
+    let volume = volume_name(conn, volume_id)?;
+    purge_matching(conn, volume_id, &volume, None, exclude, facets)
+}
+
+/// Delete changed entries of a volume that are in excluded directories,
+/// match excluded glob patterns or are past the depth limit, checking the
+/// trees below them as well.
+///
+/// Used after a batch of journal changes, once the paths of the changed
+/// entries are known. Removed entries are subtracted from `facets`.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume of the entries
+/// * `file_refs` - Entries created, renamed or moved
+/// * `exclude` - Exclude settings of the volume
+/// * `facets` - Cached facet count changes
+///
+/// # Returns
+/// The number of entries deleted.
+pub fn purge_excluded_entries(
+    conn: &Connection,
+    volume_id: i64,
+    file_refs: &[i64],
+    exclude: &ExcludeMatcher,
+    facets: &mut FacetDeltas,
+) -> Result<usize> {
+    if (!exclude.has_patterns() && exclude.max_depth().is_none()) || file_refs.is_empty() {
+        return Ok(0);
+    }
+    let volume = volume_name(conn, volume_id)?;
+
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT full_path FROM files
+             WHERE volume_id = ?1 AND file_ref = ?2 AND stream = '' AND full_path IS NOT NULL",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare changed path query: {}", e)))?;
+    let mut scopes = Vec::new();
+    for file_ref in file_refs {
+        let rows = stmt
+            .query_map(params![volume_id, file_ref], |row| row.get::<_, String>(0))
+            .map_err(|e| FFIError::Database(format!("Failed to query changed paths: {}", e)))?;
+        for row in rows {
+            scopes.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
+        }
+    }
+    scopes.sort();
+    scopes.dedup();
+
+    let mut deleted = 0;
+    for scope in &scopes {
+        deleted += purge_matching(conn, volume_id, &volume, Some(scope), exclude, facets)?;
+    }
+    Ok(deleted)
+}
+
+/// Delete the entries matching directory names or patterns, or past the
+/// depth limit, optionally only an entry and the tree below it.
+///
+/// # Arguments
+/// * `volume` - Name of the volume, the start of each entry's full path
+/// * `scope` - Path from the volume root of the entry to check, or None
+///   for the whole volume
+fn purge_matching(
+    conn: &Connection,
+    volume_id: i64,
+    volume: &str,
+    scope: Option<&str>,
+    exclude: &ExcludeMatcher,
+    facets: &mut FacetDeltas,
+) -> Result<usize> {
+    let volume = volume.trim_end_matches('\\');
+
+    // Matches are collected first, in path order so a tree that is deleted
+    // whole is not checked entry by entry
+    let mut matched: Vec<(String, bool)> = Vec::new();
+    {
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT full_path, is_dir FROM files
+                 WHERE volume_id = ?1 AND stream = '' AND full_path IS NOT NULL AND full_path != ''
+                   AND (?2 IS NULL OR full_path = ?2 COLLATE NOCASE
+                        OR (full_path >= ?3 COLLATE NOCASE AND full_path < ?4 COLLATE NOCASE))
+                 ORDER BY full_path COLLATE NOCASE",
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to prepare excluded pattern query: {}", e)))?;
+        let (lower, upper) = match scope {
+            Some(scope) => (Some(format!("{}\\", scope)), Some(format!("{}\\\u{10FFFF}", scope))),
+            None => (None, None),
+        };
+        let rows = stmt
+            .query_map(params![volume_id, scope, lower, upper], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+            })
+            .map_err(|e| FFIError::Database(format!("Failed to query paths: {}", e)))?;
+
+        let mut excluded_dir: Option<String> = None;
+        for row in rows {
+            let (path, is_dir) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+            let lower = path.to_lowercase();
+            if excluded_dir.as_ref().is_some_and(|dir| lower.starts_with(dir.as_str())) {
+                continue;
+            }
+            let too_deep = exclude.is_too_deep(path.split('\\').count());
+            if too_deep || exclude.should_exclude(&format!("{}\\{}", volume, path), is_dir) {
+                if is_dir {
+                    excluded_dir = Some(format!("{}\\", lower));
+                }
+                matched.push((path, is_dir));
+            }
+        }
+    }
+
+    // An entry goes with the tree below a directory, or the streams of a file
+    let mut stmt = conn
+        .prepare_cached(
+            "DELETE FROM files
+             WHERE volume_id = ?1 AND (full_path = ?2 COLLATE NOCASE
+                   OR (full_path >= ?3 COLLATE NOCASE AND full_path < ?4 COLLATE NOCASE))
+             RETURNING name, size, is_dir",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare excluded pattern delete: {}", e)))?;
+    let mut deleted = 0;
+    for (path, is_dir) in matched {
+        let separator = if is_dir { '\\' } else { ':' };
+        let rows = stmt
+            .query_map(
+                params![volume_id, path, format!("{}{}", path, separator), format!("{}{}\u{10FFFF}", path, separator)],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?)),
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to delete excluded entries: {}", e)))?;
+        for row in rows {
+            let (name, size, is_dir) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+            facets.remove(&name, size, is_dir);
+            deleted += 1;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Delete the entries of a volume more than `max_depth` levels below its
+/// root, with their streams.
+///
+/// A faster pass than [`purge_excluded_patterns`] for the depth limit
+/// alone, counting the separators in each `full_path`. Removed entries
+/// are subtracted from `facets`; the caller applies them.
+///
+/// # Returns
+/// The number of entries deleted.
+pub fn purge_too_deep(conn: &Connection, volume_id: i64, max_depth: usize, facets: &mut FacetDeltas) -> Result<usize> {
+    let mut stmt = conn
+        .prepare_cached(
+            "DELETE FROM files
+             WHERE volume_id = ?1 AND length(full_path) - length(replace(full_path, '\\', '')) >= ?2
+             RETURNING name, size, is_dir",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare deep entry delete: {}", e)))?;
+    let rows = stmt
+        .query_map(params![volume_id, max_depth as i64], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?))
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to delete deep entries: {}", e)))?;
+
+    let mut deleted = 0;
+    for row in rows {
+        let (name, size, is_dir) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+        facets.remove(&name, size, is_dir);
+        deleted += 1;
+    }
+    Ok(deleted)
+}
+
+/// Delete the entries of a volume beyond the first `max_files`.
+///
+/// Entries are kept level by level from the root, in path order within a
+/// level, so every kept entry still has its parent folder indexed. Entries
+/// without a path go first. Removed entries are subtracted from `facets`.
+///
+/// # Returns
+/// The number of entries deleted.
+pub fn purge_over_file_limit(
+    conn: &Connection,
+    volume_id: i64,
+    max_files: u64,
+    facets: &mut FacetDeltas,
+) -> Result<usize> {
+    let mut stmt = conn
+        .prepare_cached(
+            "DELETE FROM files WHERE id IN (
+                 SELECT id FROM files WHERE volume_id = ?1
+                 ORDER BY full_path IS NULL, length(full_path) - length(replace(full_path, '\\', '')),
+                          full_path COLLATE NOCASE
+                 LIMIT -1 OFFSET ?2
+             )
+             RETURNING name, size, is_dir",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare entry limit delete: {}", e)))?;
+    let rows = stmt
+        .query_map(params![volume_id, max_files.min(i64::MAX as u64) as i64], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?))
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to delete entries over the limit: {}", e)))?;
+
+    let mut deleted = 0;
+    for row in rows {
+        let (name, size, is_dir) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+        facets.remove(&name, size, is_dir);
+        deleted += 1;
+    }
+    Ok(deleted)
+}
+
+/// Purge already-indexed entries that match the exclude settings.
+///
+/// Maintenance pass over every volume, for settings added after the
+/// entries were indexed, with each volume's own excludes and limits on top
+/// of the global excludes. Each volume is purged in its own transaction
+/// and its cached facet counts are updated. Volumes without limits lose
+/// any truncation recorded under earlier ones.
+///
+/// # Returns
+/// The number of entries deleted.
+pub fn purge_excluded(conn: &mut Connection, config: &Config) -> Result<usize> {
+    let mut total = 0;
+    for volume in get_all_volumes(conn)? {
+        let exclude = config.exclude_matcher(volume.drive_letter.as_str());
+        if exclude.max_depth().is_none() && exclude.max_files().is_none() {
+            set_volume_truncated(conn, volume.id, None)?;
+        }
+        if exclude.is_empty() {
+            continue;
+        }
+        let tx = conn
+            .transaction()
+            .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+        // The entry limit counts what the excludes leave
+        let mut facets = FacetDeltas::new();
+        let too_deep = match exclude.max_depth() {
+            Some(max_depth) => purge_too_deep(&tx, volume.id, max_depth, &mut facets)?,
+            None => 0,
+        };
+        let excluded = purge_excluded_paths(&tx, volume.id, &exclude, &mut facets)?
+            + purge_excluded_extensions(&tx, volume.id, &exclude, &mut facets)?
+            + purge_excluded_patterns(&tx, volume.id, &exclude, &mut facets)?;
+        let too_many = match exclude.max_files() {
+            Some(max_files) => purge_over_file_limit(&tx, volume.id, max_files, &mut facets)?,
+            None => 0,
+        };
+        if let Some(truncated) = exclude.limit_reason(too_deep > 0, too_many > 0) {
+            set_volume_truncated(&tx, volume.id, Some(&truncated))?;
+        }
+        let deleted = too_deep + excluded + too_many;
+        if !facets.is_empty() {
+            facets.apply(&tx, volume.id)?;
+        }
+
+        tx.commit()
+            .map_err(|e| FFIError::Database(format!("Failed to commit excluded entry purge: {}", e)))?;
+
+        if deleted > 0 {
+            tracing::info!("Purged {} excluded entries from {}", deleted, volume.drive_letter);
+        }
+        total += deleted;
+    }
+
+    Ok(total)
+}
+
+/// Name a volume is indexed under (`C:`, `C:\Mount\Data`, `\\NAS\Share`).
+fn volume_name(conn: &Connection, volume_id: i64) -> Result<String> {
+    conn.query_row("SELECT drive_letter FROM volumes WHERE id = ?1", params![volume_id], |row| row.get(0))
+        .map_err(|e| FFIError::Database(format!("Failed to get volume: {}", e)))
+}
+
+/// Number of entries below a directory.
+fn subtree_size(conn: &Connection, volume_id: i64, dir_ref: i64) -> Result<i64> {
+    conn.query_row(
+        "WITH RECURSIVE subtree(ref) AS (
+             SELECT ?2
+             UNION
+             SELECT f.file_ref FROM files f JOIN subtree s ON f.parent_ref = s.ref
+             WHERE f.volume_id = ?1 AND f.file_ref IS NOT NULL
+         )
+         SELECT COUNT(*) - 1 FROM subtree",
+        params![volume_id, dir_ref],
+        |row| row.get(0),
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to count directory entries: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, insert_volume, schema, FileEntry};
+
+    fn setup_test_db() -> (Connection, i64) {
+        let mut conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let volume_id = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+
+        // Users (1) > project (2) > node_modules (3) > pkg (4) > node_modules (5)
+        let dir = |file_ref, parent_ref, name: &str| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            is_dir: true,
+            ..Default::default()
+        };
+        let mut entries = vec![
+            dir(1, 0, "Users"),
+            dir(2, 1, "project"),
+            dir(3, 2, "node_modules"),
+            dir(4, 3, "pkg"),
+            dir(5, 4, "node_modules"),
+        ];
+        for i in 0..MIN_SUGGESTED_FILES {
+            entries.push(FileEntry {
+                is_dir: false,
+                ..dir(100 + i, 5, &format!("{}.js", i))
+            });
+        }
+        batch_insert_files(&mut conn, &entries).unwrap();
+
+        (conn, volume_id)
+    }
+
+    #[test]
+    fn test_suggests_outermost_low_value_dir() {
+        let (conn, volume_id) = setup_test_db();
+
+        let suggestions = analyze_exclusions(&conn, &ExcludeConfig::default()).unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].volume_id, volume_id);
+        assert!(suggestions[0].path.starts_with("C:\\Users"));
+        assert!(suggestions[0].path.ends_with("node_modules"));
+        assert_eq!(suggestions[0].file_count, MIN_SUGGESTED_FILES + 2);
+        assert_eq!(suggestions[0].reason, "node_modules folder");
+    }
+
+    #[test]
+    fn test_skips_excluded_and_small_dirs() {
+        let (conn, _) = setup_test_db();
+
+        let exclude = ExcludeConfig {
+            paths: vec![r"C:\Users".to_string()],
+            ..Default::default()
+        };
+        assert!(analyze_exclusions(&conn, &exclude).unwrap().is_empty());
+
+        conn.execute("DELETE FROM files WHERE file_ref >= 200", []).unwrap();
+        assert!(analyze_exclusions(&conn, &ExcludeConfig::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_suggests_high_churn_dir() {
+        let (conn, volume_id) = setup_test_db();
+        conn.execute("DELETE FROM files WHERE file_ref >= 100", []).unwrap();
+
+        let mut counts = HashMap::new();
+        counts.insert(2, HIGH_CHURN_PER_DAY as i64);
+        record_dir_churn(&conn, volume_id, &counts).unwrap();
+
+        let suggestions = analyze_exclusions(&conn, &ExcludeConfig::default()).unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert!(suggestions[0].path.ends_with("project"));
+        assert_eq!(suggestions[0].reason, "500 changes/day");
+    }
+
+    #[test]
+    fn test_save_and_get_suggestions() {
+        let (mut conn, _) = setup_test_db();
+
+        let suggestions = analyze_exclusions(&conn, &ExcludeConfig::default()).unwrap();
+        save_exclusion_suggestions(&mut conn, &suggestions).unwrap();
+        assert_eq!(get_exclusion_suggestions(&conn).unwrap(), suggestions);
+
+        save_exclusion_suggestions(&mut conn, &[]).unwrap();
+        assert!(get_exclusion_suggestions(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purge_excluded() {
+        let (mut conn, volume_id) = setup_test_db();
+        batch_insert_files(
+            &mut conn,
+            &[FileEntry {
+                volume_id,
+                file_ref: Some(50),
+                parent_ref: Some(2),
+                name: "notes.TMP".to_string(),
+                size: 10,
+                ..Default::default()
+            }],
+        )
+        .unwrap();
+        crate::db::rebuild_facet_counts(&mut conn, volume_id).unwrap();
+
+        let config = Config {
+            exclude: ExcludeConfig {
+                paths: vec![r"c:/users/project/node_modules".to_string(), r"D:\Users".to_string()],
+                extensions: vec!["tmp".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(purge_excluded(&mut conn, &config).unwrap(), MIN_SUGGESTED_FILES as usize + 4);
+
+        let remaining: Vec<String> = conn
+            .prepare("SELECT full_path FROM files ORDER BY full_path")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(remaining, vec!["Users".to_string(), r"Users\project".to_string()]);
+
+        // Facet counts follow the purge
+        let dirs = crate::db::get_facet_counts(&conn, crate::db::Facet::Type, Some(volume_id)).unwrap();
+        assert_eq!(dirs.iter().find(|c| c.value == "folder").map(|c| c.count), Some(2));
+    }
+
+    #[test]
+    fn test_purge_excluded_patterns() {
+        let (conn, volume_id) = setup_test_db();
+        let count = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0)).unwrap()
+        };
+        let total = count(&conn);
+
+        // Nothing matches: every path is checked, nothing deleted
+        let exclude = ExcludeConfig {
+            directories: vec!["vendor".to_string()],
+            ..Default::default()
+        }
+        .matcher();
+        assert_eq!(purge_excluded_patterns(&conn, volume_id, &exclude, &mut FacetDeltas::new()).unwrap(), 0);
+
+        // Only the entry changed and the tree below it are checked
+        let exclude = ExcludeConfig {
+            patterns: vec!["**/pkg/**".to_string()],
+            ..Default::default()
+        }
+        .matcher();
+        let purged = purge_excluded_entries(&conn, volume_id, &[2], &exclude, &mut FacetDeltas::new()).unwrap();
+        assert_eq!(purged, MIN_SUGGESTED_FILES as usize + 2);
+        assert_eq!(count(&conn), total - purged as i64);
+
+        // The outer node_modules goes whole, with the tree below it
+        let exclude = ExcludeConfig {
+            directories: vec!["Node_Modules".to_string()],
+            ..Default::default()
+        }
+        .matcher();
+        let mut facets = FacetDeltas::new();
+        assert_eq!(purge_excluded_patterns(&conn, volume_id, &exclude, &mut facets).unwrap(), 1);
+        assert!(!facets.is_empty());
+        let remaining: Vec<String> = conn
+            .prepare("SELECT full_path FROM files ORDER BY full_path")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(remaining, vec!["Users".to_string(), r"Users\project".to_string()]);
+    }
+
+    #[test]
+    fn test_purge_over_limits() {
+        let (conn, volume_id) = setup_test_db();
+        let mut facets = FacetDeltas::new();
+
+        // Users\project\node_modules\pkg is at depth 4
+        let purged = purge_too_deep(&conn, volume_id, 3, &mut facets).unwrap();
+        assert_eq!(purged, MIN_SUGGESTED_FILES as usize + 2);
+        assert!(!facets.is_empty());
+
+        // The deepest entries go first
+        assert_eq!(purge_over_file_limit(&conn, volume_id, 2, &mut facets).unwrap(), 1);
+        assert_eq!(purge_over_file_limit(&conn, volume_id, 2, &mut facets).unwrap(), 0);
+        let remaining: Vec<String> = conn
+            .prepare("SELECT full_path FROM files ORDER BY full_path")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(remaining, vec!["Users".to_string(), r"Users\project".to_string()]);
+    }
+}
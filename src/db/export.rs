@@ -0,0 +1,286 @@
+//! Exporting every match of a query as CSV, JSON Lines or TSV.
+//!
+//! [`Exporter`] writes the matches a page at a time, so the IPC server can
+//! stream a large export without holding the database lock throughout;
+//! [`export_matches`] writes them all in one go. Pages are read by offset,
+//! so an export running while the index changes may skip or repeat entries.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{get_all_volumes, Database, FileEntry, Store};
+use crate::search::ParsedQuery;
+use crate::Result;
+
+/// Matches read per page.
+pub const EXPORT_PAGE_SIZE: usize = 1_000;
+
+/// Columns written for each match, in order.
+const COLUMNS: [&str; 6] = ["path", "name", "size", "modified", "created", "is_dir"];
+
+/// Output format of an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row (RFC 4180 quoting)
+    #[default]
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+impl ExportFormat {
+    /// Formats offered in the UI, in display order.
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Csv, ExportFormat::JsonLines, ExportFormat::Tsv];
+
+    /// Human-readable label for the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::JsonLines => "JSON Lines",
+            ExportFormat::Tsv => "TSV",
+        }
+    }
+
+    /// Usual file extension, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Tsv => "tsv",
+        }
+    }
+
+    /// Format named on a command line ("csv", "jsonl", "tsv").
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "jsonl" | "json_lines" | "ndjson" => Some(ExportFormat::JsonLines),
+            "tsv" => Some(ExportFormat::Tsv),
+            _ => None,
+        }
+    }
+
+    /// Format matching a file's extension, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension().and_then(|ext| Self::from_name(&ext.to_string_lossy()))
+    }
+}
+
+/// One exported match, as written to JSON Lines.
+#[derive(Serialize)]
+struct ExportRow<'a> {
+    path: &'a str,
+    name: &'a str,
+    size: i64,
+    modified: Option<String>,
+    created: Option<String>,
+    is_dir: bool,
+}
+
+/// Writes a query's matches page by page.
+pub struct Exporter {
+    format: ExportFormat,
+    query: ParsedQuery,
+    drive_letters: HashMap<i64, String>,
+    offset: usize,
+    rows: usize,
+}
+
+impl Exporter {
+    /// Prepare an export of a query's matches.
+    ///
+    /// # Arguments
+    /// * `db` - Index to export from; its hidden attributes apply
+    /// * `query` - Query whose matches are exported, in its sort order
+    /// * `format` - Output format
+    pub fn new(db: &Database, query: ParsedQuery, format: ExportFormat) -> Result<Self> {
+        let drive_letters = get_all_volumes(db.conn())?
+            .into_iter()
+            .map(|v| (v.id, v.drive_letter))
+            .collect();
+        Ok(Self {
+            format,
+            query,
+            drive_letters,
+            offset: 0,
+            rows: 0,
+        })
+    }
+
+    /// Header line (CSV and TSV), written before the first page.
+    pub fn header(&self) -> Option<String> {
+        match self.format {
+            ExportFormat::Csv => Some(format!("{}\r\n", COLUMNS.join(","))),
+            ExportFormat::Tsv => Some(format!("{}\n", COLUMNS.join("\t"))),
+            ExportFormat::JsonLines => None,
+        }
+    }
+
+    /// Write the next page of matches.
+    ///
+    /// # Returns
+    /// false once every match has been written.
+    pub fn write_page(&mut self, db: &Database, out: &mut impl Write) -> Result<bool> {
+        let entries = db.search(&self.query, EXPORT_PAGE_SIZE, self.offset)?;
+        self.offset += entries.len();
+        for entry in &entries {
+            self.write_row(db, entry, out)?;
+        }
+        self.rows += entries.len();
+        Ok(entries.len() == EXPORT_PAGE_SIZE)
+    }
+
+    /// Number of matches written so far.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Write one match in the export's format.
+    fn write_row(&self, db: &Database, entry: &FileEntry, out: &mut impl Write) -> Result<()> {
+        let path = match self.drive_letters.get(&entry.volume_id) {
+            Some(letter) => match db.entry_path(entry)? {
+                Some(relative) => format!("{}\\{}", letter, relative),
+                None => entry.name.clone(),
+            },
+            None => entry.name.clone(),
+        };
+        let row = ExportRow {
+            path: &path,
+            name: &entry.name,
+            size: entry.size,
+            modified: entry.modified.and_then(timestamp),
+            created: entry.created.and_then(timestamp),
+            is_dir: entry.is_dir,
+        };
+
+        match self.format {
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut *out, &row).map_err(std::io::Error::from)?;
+                out.write_all(b"\n")?;
+            }
+            ExportFormat::Csv => {
+                let fields = fields(&row).map(|field| csv_field(&field));
+                out.write_all(fields.join(",").as_bytes())?;
+                out.write_all(b"\r\n")?;
+            }
+            ExportFormat::Tsv => {
+                let fields = fields(&row).map(|field| field.replace(['\t', '\r', '\n'], " "));
+                out.write_all(fields.join("\t").as_bytes())?;
+                out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Export every match of a query.
+///
+/// # Arguments
+/// * `db` - Index to export from; its hidden attributes apply
+/// * `query` - Query whose matches are exported, in its sort order
+/// * `format` - Output format
+/// * `out` - Destination, e.g. a file or stdout
+///
+/// # Returns
+/// Number of matches written.
+pub fn export_matches(db: &Database, query: &ParsedQuery, format: ExportFormat, out: &mut impl Write) -> Result<usize> {
+    let mut exporter = Exporter::new(db, query.clone(), format)?;
+    if let Some(header) = exporter.header() {
+        out.write_all(header.as_bytes())?;
+    }
+    while exporter.write_page(db, out)? {}
+    out.flush()?;
+    Ok(exporter.rows())
+}
+
+/// RFC 3339 UTC time of a Unix timestamp.
+fn timestamp(secs: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(secs, 0).map(|t| t.to_rfc3339())
+}
+
+/// A row's values as text, in [`COLUMNS`] order.
+fn fields(row: &ExportRow) -> [String; 6] {
+    [
+        row.path.to_string(),
+        row.name.to_string(),
+        row.size.to_string(),
+        row.modified.clone().unwrap_or_default(),
+        row.created.clone().unwrap_or_default(),
+        row.is_dir.to_string(),
+    ]
+}
+
+/// A CSV field, quoted if it holds a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_volume, open_database};
+    use crate::search::parse_query;
+
+    #[test]
+    fn test_export_matches() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_export");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let entry = |file_ref: i64, parent_ref: i64, name: &str, is_dir: bool| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            size: 42,
+            modified: Some(1_700_000_000),
+            is_dir,
+            ..Default::default()
+        };
+        let mut files = vec![entry(5, 5, ".", true), entry(10, 5, "Docs", true), entry(11, 10, "a, \"b\".txt", false)];
+        files.extend((0..EXPORT_PAGE_SIZE as i64 + 5).map(|i| entry(100 + i, 10, &format!("log{}.txt", i), false)));
+        db.insert_batch(&files).unwrap();
+
+        let export = |query: &str, format: ExportFormat| {
+            let mut out = Vec::new();
+            let rows = export_matches(&db, &parse_query(query).unwrap(), format, &mut out).unwrap();
+            (rows, String::from_utf8(out).unwrap())
+        };
+
+        let (rows, csv) = export("a,", ExportFormat::Csv);
+        assert_eq!(rows, 1);
+        assert_eq!(
+            csv,
+            "path,name,size,modified,created,is_dir\r\n\
+             \"C:\\Docs\\a, \"\"b\"\".txt\",\"a, \"\"b\"\".txt\",42,2023-11-14T22:13:20+00:00,,false\r\n"
+        );
+
+        let (_, tsv) = export("a,", ExportFormat::Tsv);
+        assert_eq!(tsv.lines().nth(1).unwrap(), "C:\\Docs\\a, \"b\".txt\ta, \"b\".txt\t42\t2023-11-14T22:13:20+00:00\t\tfalse");
+
+        // Every page is written, not just the first
+        let (rows, jsonl) = export("log", ExportFormat::JsonLines);
+        assert_eq!(rows, EXPORT_PAGE_SIZE + 5);
+        assert_eq!(jsonl.lines().count(), rows);
+        let first: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert!(first["path"].as_str().unwrap().starts_with("C:\\Docs\\log"));
+        assert!(first["created"].is_null());
+
+        assert_eq!(ExportFormat::from_path(Path::new("out.JSONL")), Some(ExportFormat::JsonLines));
+        assert_eq!(ExportFormat::from_name("xml"), None);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,376 @@
+//! Cached counts for common filter values.
+//!
+//! Entries are counted per volume by extension and by type (file or folder)
+//! in the `facet_counts` table. Counts are rebuilt after each full scan and
+//! adjusted from USN batches in between, so filter chips and statistics can
+//! read them without aggregating over the whole files table. Bulk inserts
+//! mark a volume's counts stale until the next rebuild, and counts are only
+//! served while every volume's are current.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection};
+
+use super::ops::file_extension;
+use crate::search::{FileType, Filter, ParsedQuery};
+use crate::{FFIError, Result};
+
+/// A dimension entries are counted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Facet {
+    /// Lowercase extension after the last dot (e.g. `pdf`)
+    Extension,
+    /// `file` or `folder`
+    Type,
+}
+
+impl Facet {
+    /// Name stored in the `facet` column.
+    pub fn to_db_str(self) -> &'static str {
+        match self {
+            Facet::Extension => "ext",
+            Facet::Type => "type",
+        }
+    }
+}
+
+/// Cached count for one facet value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FacetCount {
+    /// Facet value (e.g. `pdf`, `folder`)
+    pub value: String,
+    /// Entries with this value
+    pub count: i64,
+    /// Sum of file sizes with this value
+    pub bytes: i64,
+}
+
+/// Pending count changes for one volume.
+#[derive(Debug, Default)]
+pub struct FacetDeltas {
+    deltas: HashMap<(Facet, String), (i64, i64)>,
+}
+
+impl FacetDeltas {
+    /// Create an empty set of changes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count an entry that was added to the index.
+    pub fn add(&mut self, name: &str, size: i64, is_dir: bool) {
+        self.record(name, size, is_dir, 1);
+    }
+
+    /// Uncount an entry that was removed from the index.
+    pub fn remove(&mut self, name: &str, size: i64, is_dir: bool) {
+        self.record(name, size, is_dir, -1);
+    }
+
+    fn record(&mut self, name: &str, size: i64, is_dir: bool, sign: i64) {
+        let bytes = if is_dir { 0 } else { size * sign };
+        let kind = if is_dir { "folder" } else { "file" };
+
+        let mut bump = |facet: Facet, value: String| {
+            let entry = self.deltas.entry((facet, value)).or_insert((0, 0));
+            entry.0 += sign;
+            entry.1 += bytes;
+        };
+        bump(Facet::Type, kind.to_string());
+        if let Some(ext) = file_extension(name) {
+            bump(Facet::Extension, ext);
+        }
+    }
+
+    /// Whether there is nothing to apply.
+    pub fn is_empty(&self) -> bool {
+        self.deltas.values().all(|&(count, bytes)| count == 0 && bytes == 0)
+    }
+
+    /// Apply the changes to a volume's cached counts.
+    ///
+    /// The volume's stored file, folder and byte totals are adjusted too, so
+    /// they stay current between full scans.
+    pub fn apply(&self, conn: &Connection, volume_id: i64) -> Result<()> {
+        let mut upsert = conn
+            .prepare_cached(
+                "INSERT INTO facet_counts (volume_id, facet, value, count, bytes)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (volume_id, facet, value)
+                 DO UPDATE SET count = count + excluded.count, bytes = bytes + excluded.bytes",
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to prepare facet update: {}", e)))?;
+
+        let (mut files, mut dirs, mut bytes) = (0, 0, 0);
+        for ((facet, value), &(count, size)) in &self.deltas {
+            if count == 0 && size == 0 {
+                continue;
+            }
+            upsert
+                .execute(params![volume_id, facet.to_db_str(), value, count, size])
+                .map_err(|e| FFIError::Database(format!("Failed to update facet counts: {}", e)))?;
+
+            match (facet, value.as_str()) {
+                (Facet::Type, "file") => {
+                    files += count;
+                    bytes += size;
+                }
+                (Facet::Type, _) => dirs += count,
+                _ => {}
+            }
+        }
+
+        conn.execute(
+            "DELETE FROM facet_counts WHERE volume_id = ?1 AND count <= 0",
+            params![volume_id],
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prune facet counts: {}", e)))?;
+
+        conn.execute(
+            "UPDATE volumes SET file_count = MAX(file_count + ?1, 0), dir_count = MAX(dir_count + ?2, 0),
+                 total_bytes = MAX(total_bytes + ?3, 0)
+             WHERE id = ?4",
+            params![files, dirs, bytes, volume_id],
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to update volume stats: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Recount all facets of a volume from its files.
+///
+/// Run after a full scan; one pass over the volume's rows replaces whatever
+/// the incremental updates accumulated.
+pub fn rebuild_facet_counts(conn: &mut Connection, volume_id: i64) -> Result<()> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+    let mut deltas = FacetDeltas::new();
+    {
+        let mut stmt = tx
+            .prepare("SELECT name, size, is_dir FROM files WHERE volume_id = ?1")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare facet scan: {}", e)))?;
+        let mut rows = stmt
+            .query(params![volume_id])
+            .map_err(|e| FFIError::Database(format!("Failed to scan files for facets: {}", e)))?;
+
+        while let Some(row) = rows
+            .next()
+            .map_err(|e| FFIError::Database(format!("Failed to read file row: {}", e)))?
+        {
+            let name: String = row.get(0).map_err(|e| FFIError::Database(e.to_string()))?;
+            let size: i64 = row.get(1).map_err(|e| FFIError::Database(e.to_string()))?;
+            let is_dir: bool = row.get(2).map_err(|e| FFIError::Database(e.to_string()))?;
+            deltas.add(&name, size, is_dir);
+        }
+    }
+
+    tx.execute("DELETE FROM facet_counts WHERE volume_id = ?1", params![volume_id])
+        .map_err(|e| FFIError::Database(format!("Failed to clear facet counts: {}", e)))?;
+
+    {
+        let mut insert = tx
+            .prepare("INSERT INTO facet_counts (volume_id, facet, value, count, bytes) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare facet insert: {}", e)))?;
+        for ((facet, value), (count, bytes)) in &deltas.deltas {
+            insert
+                .execute(params![volume_id, facet.to_db_str(), value, count, bytes])
+                .map_err(|e| FFIError::Database(format!("Failed to store facet counts: {}", e)))?;
+        }
+    }
+
+    tx.execute("UPDATE volumes SET facets_valid = 1 WHERE id = ?1", params![volume_id])
+        .map_err(|e| FFIError::Database(format!("Failed to mark facet counts current: {}", e)))?;
+
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit facet counts: {}", e)))?;
+
+    Ok(())
+}
+
+/// Get cached counts for a facet, largest first.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `facet` - Facet to list
+/// * `volume_id` - Restrict to one volume, or sum over all volumes
+pub fn get_facet_counts(conn: &Connection, facet: Facet, volume_id: Option<i64>) -> Result<Vec<FacetCount>> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT value, SUM(count), SUM(bytes) FROM facet_counts
+             WHERE facet = ?1 AND (?2 IS NULL OR volume_id = ?2)
+             GROUP BY value ORDER BY SUM(count) DESC, value",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare facet query: {}", e)))?;
+
+    let counts = stmt
+        .query_map(params![facet.to_db_str(), volume_id], |row| {
+            Ok(FacetCount {
+                value: row.get(0)?,
+                count: row.get(1)?,
+                bytes: row.get(2)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query facet counts: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read facet count: {}", e)))?;
+
+    Ok(counts)
+}
+
+/// Answer a count from the cache when the query is a single facet filter.
+///
+/// # Returns
+/// `None` if the query needs the files table (name pattern, other filters
+/// or conditions, or an extension `LIKE` would not match exactly) or some
+/// volume's counts are stale.
+pub fn cached_query_count(conn: &Connection, parsed: &ParsedQuery) -> Result<Option<usize>> {
+    if parsed.pattern.is_some() || parsed.filters.len() != 1 || !parsed.conditions.is_empty() {
+        return Ok(None);
+    }
+
+    let (facet, value) = match &parsed.filters[0] {
+        Filter::Extension(ext)
+            if !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            (Facet::Extension, ext.to_ascii_lowercase())
+        }
+        Filter::Type(FileType::File) => (Facet::Type, "file".to_string()),
+        Filter::Type(FileType::Folder) => (Facet::Type, "folder".to_string()),
+        _ => return Ok(None),
+    };
+
+    let stale: bool = conn
+        .query_row("SELECT EXISTS (SELECT 1 FROM volumes WHERE facets_valid = 0)", [], |row| row.get(0))
+        .map_err(|e| FFIError::Database(format!("Failed to check facet counts: {}", e)))?;
+    if stale {
+        return Ok(None);
+    }
+
+    let count: i64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(count), 0) FROM facet_counts WHERE facet = ?1 AND value = ?2",
+            params![facet.to_db_str(), value],
+            |row| row.get(0),
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to read cached count: {}", e)))?;
+
+    Ok(Some(count.max(0) as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        batch_insert_files, count_query_matches, get_volume_stats, insert_volume, schema,
+        update_volume_stats, FileEntry,
+    };
+    use crate::search::parse_query;
+
+    fn setup_test_db() -> (Connection, i64) {
+        let mut conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let volume_id = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+
+        let entry = |file_ref: i64, name: &str, size: i64, is_dir: bool| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(5),
+            name: name.to_string(),
+            size,
+            is_dir,
+            ..Default::default()
+        };
+        let files = vec![
+            entry(10, "Docs", 0, true),
+            entry(11, "report.PDF", 100, false),
+            entry(12, "notes.pdf", 50, false),
+            entry(13, "photo.jpg", 300, false),
+            entry(14, "Makefile", 10, false),
+        ];
+        batch_insert_files(&mut conn, &files).unwrap();
+        update_volume_stats(&conn, volume_id, 0).unwrap();
+        rebuild_facet_counts(&mut conn, volume_id).unwrap();
+        (conn, volume_id)
+    }
+
+    #[test]
+    fn test_rebuild_facet_counts() {
+        let (conn, volume_id) = setup_test_db();
+
+        let exts = get_facet_counts(&conn, Facet::Extension, Some(volume_id)).unwrap();
+        assert_eq!(
+            exts,
+            vec![
+                FacetCount { value: "pdf".to_string(), count: 2, bytes: 150 },
+                FacetCount { value: "jpg".to_string(), count: 1, bytes: 300 },
+            ]
+        );
+
+        let types = get_facet_counts(&conn, Facet::Type, None).unwrap();
+        assert_eq!(types[0], FacetCount { value: "file".to_string(), count: 4, bytes: 460 });
+        assert_eq!(types[1], FacetCount { value: "folder".to_string(), count: 1, bytes: 0 });
+    }
+
+    #[test]
+    fn test_apply_deltas() {
+        let (conn, volume_id) = setup_test_db();
+
+        let mut deltas = FacetDeltas::new();
+        deltas.remove("photo.jpg", 300, false);
+        deltas.add("photo.png", 300, false);
+        deltas.add("scan.pdf", 0, false);
+        deltas.apply(&conn, volume_id).unwrap();
+
+        let exts = get_facet_counts(&conn, Facet::Extension, None).unwrap();
+        let values: Vec<(&str, i64)> = exts.iter().map(|f| (f.value.as_str(), f.count)).collect();
+        assert_eq!(values, vec![("pdf", 3), ("png", 1)]);
+
+        // Volume totals follow the type deltas
+        let stats = get_volume_stats(&conn, volume_id).unwrap();
+        assert_eq!((stats.file_count, stats.dir_count, stats.total_bytes), (5, 1, 460));
+    }
+
+    #[test]
+    fn test_cached_query_count_matches_sql() {
+        let (conn, _) = setup_test_db();
+
+        for query in ["ext:pdf", "type:folder", "type:file", "ext:jpg", "ext:zip"] {
+            let parsed = parse_query(query).unwrap();
+            assert_eq!(
+                cached_query_count(&conn, &parsed).unwrap(),
+                Some(count_query_matches(&conn, &parsed).unwrap()),
+                "{}",
+                query
+            );
+        }
+
+        // Anything else goes to the files table
+        for query in ["report ext:pdf", "ext:pdf type:file", "size:>10"] {
+            assert_eq!(cached_query_count(&conn, &parse_query(query).unwrap()).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_bulk_insert_invalidates_cache() {
+        let (mut conn, volume_id) = setup_test_db();
+        let parsed = parse_query("ext:pdf").unwrap();
+        assert_eq!(cached_query_count(&conn, &parsed).unwrap(), Some(2));
+
+        let more = vec![FileEntry {
+            volume_id,
+            file_ref: Some(20),
+            parent_ref: Some(5),
+            name: "new.pdf".to_string(),
+            size: 1,
+            ..Default::default()
+        }];
+        batch_insert_files(&mut conn, &more).unwrap();
+        assert_eq!(cached_query_count(&conn, &parsed).unwrap(), None);
+
+        rebuild_facet_counts(&mut conn, volume_id).unwrap();
+        assert_eq!(cached_query_count(&conn, &parsed).unwrap(), Some(3));
+    }
+}
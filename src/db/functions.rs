@@ -0,0 +1,73 @@
+//! SQL functions registered on every connection.
+//!
+//! SQLite parses `X REGEXP Y` but leaves the `regexp` function undefined;
+//! this module provides it with the `regex` crate for `regex:` filters.
+
+use std::sync::Arc;
+
+use regex::Regex;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+use crate::{FFIError, Result};
+
+/// Error type accepted by rusqlite's function auxiliary data.
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Register the crate's SQL functions on a connection.
+///
+/// `regexp(pattern, text)` backs the `REGEXP` operator. The compiled
+/// pattern is cached by SQLite for the statement, so each row only runs
+/// the match.
+pub fn register_functions(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let regex: Arc<Regex> =
+                ctx.get_or_create_aux(0, |pattern| -> std::result::Result<_, BoxError> {
+                    Ok(Regex::new(pattern.as_str()?)?)
+                })?;
+            let text = ctx
+                .get_raw(1)
+                .as_str()
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+            Ok(regex.is_match(text))
+        },
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to register regexp function: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, count_query_matches, insert_volume, schema, FileEntry};
+    use crate::search::parse_query;
+
+    #[test]
+    fn test_regex_filter_query() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        register_functions(&conn).unwrap();
+
+        let volume_id = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let files: Vec<FileEntry> = ["IMG_2041.jpg", "img_0007.JPG", "IMG_12.jpg", "notes_2041.jpg"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| FileEntry {
+                volume_id,
+                file_ref: Some(i as i64 + 1),
+                parent_ref: Some(0),
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        let count = |query: &str| count_query_matches(&conn, &parse_query(query).unwrap()).unwrap();
+        assert_eq!(count(r"regex:^IMG_\d{4}\.jpg$"), 2);
+        assert_eq!(count(r"regex:\d{4}"), 3);
+        assert_eq!(count(r"notes regex:\d{4}"), 1);
+    }
+}
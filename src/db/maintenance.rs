@@ -0,0 +1,295 @@
+//! Periodic database maintenance: planner statistics, returning free pages
+//! to the file system and integrity checks.
+//!
+//! Run by the job pool as `JobKind::Maintenance` every
+//! `database.maintenance_interval_hours`. The outcome of the last run is
+//! stored in the `maintenance` table so the IPC server, which has its own
+//! connection, can report it with the service status.
+//!
+//! The index can always be rebuilt from the volumes, so a corrupt `files`
+//! table is dropped and recreated rather than repaired; the caller then
+//! queues a full index.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use super::schema;
+use crate::{FFIError, Result};
+
+/// `PRAGMA auto_vacuum` value of incremental mode.
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+/// Most problems `PRAGMA integrity_check` reports before stopping.
+const MAX_INTEGRITY_ERRORS: i64 = 100;
+
+/// Outcome of a maintenance run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    /// Unix timestamp the run started
+    pub ran_at: i64,
+    /// How long the run took, in milliseconds
+    pub duration_ms: i64,
+    /// Free pages returned to the file system
+    pub freed_pages: i64,
+    /// Problems found by the integrity check; empty if it passed
+    pub integrity_errors: Vec<String>,
+    /// Whether the index was rebuilt because the database was corrupt
+    pub rebuilt: bool,
+}
+
+impl MaintenanceReport {
+    /// Whether the database passed the integrity check (after any rebuild).
+    pub fn is_healthy(&self) -> bool {
+        self.integrity_errors.is_empty()
+    }
+}
+
+/// Run maintenance on the database and record the outcome.
+///
+/// In order: `PRAGMA optimize`, an incremental vacuum (switching the
+/// database to incremental auto-vacuum with a one-time `VACUUM` if needed),
+/// `ANALYZE` and `PRAGMA integrity_check` plus the name index's own check.
+/// If the check fails, indexes are rebuilt with `REINDEX`; if it still
+/// fails, the index tables are dropped and recreated empty.
+///
+/// # Arguments
+/// * `conn` - Writable database connection
+/// * `now` - Current Unix timestamp
+///
+/// # Returns
+/// The report also stored for [`get_last_maintenance`]. If `rebuilt` is
+/// set, every volume must be indexed again.
+pub fn run_maintenance(conn: &Connection, now: i64) -> Result<MaintenanceReport> {
+    let started = Instant::now();
+
+    conn.execute_batch("PRAGMA optimize")
+        .map_err(|e| FFIError::Database(format!("Failed to optimize database: {}", e)))?;
+
+    let freed_pages = incremental_vacuum(conn)?;
+
+    conn.execute_batch("ANALYZE")
+        .map_err(|e| FFIError::Database(format!("Failed to analyze database: {}", e)))?;
+
+    let mut integrity_errors = integrity_check(conn)?;
+    let mut rebuilt = false;
+    if !integrity_errors.is_empty() {
+        tracing::error!(
+            "Database integrity check failed ({} problems), rebuilding indexes: {}",
+            integrity_errors.len(),
+            integrity_errors[0]
+        );
+        conn.execute_batch("REINDEX; INSERT INTO files_fts (files_fts) VALUES ('rebuild');")
+            .map_err(|e| FFIError::Database(format!("Failed to rebuild indexes: {}", e)))?;
+        integrity_errors = integrity_check(conn)?;
+    }
+    if !integrity_errors.is_empty() {
+        tracing::error!("Database still corrupt after REINDEX, rebuilding the index");
+        rebuild_index(conn)?;
+        rebuilt = true;
+        integrity_errors = integrity_check(conn)?;
+    }
+
+    let report = MaintenanceReport {
+        ran_at: now,
+        duration_ms: started.elapsed().as_millis() as i64,
+        freed_pages,
+        integrity_errors,
+        rebuilt,
+    };
+    save_report(conn, &report)?;
+    Ok(report)
+}
+
+/// Outcome of the last maintenance run, if any.
+pub fn get_last_maintenance(conn: &Connection) -> Result<Option<MaintenanceReport>> {
+    conn.query_row(
+        "SELECT ran_at, duration_ms, freed_pages, integrity_errors, rebuilt FROM maintenance WHERE id = 1",
+        [],
+        |row| {
+            let errors: String = row.get(3)?;
+            Ok(MaintenanceReport {
+                ran_at: row.get(0)?,
+                duration_ms: row.get(1)?,
+                freed_pages: row.get(2)?,
+                integrity_errors: errors.lines().map(str::to_string).collect(),
+                rebuilt: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| FFIError::Database(format!("Failed to read maintenance report: {}", e)))
+}
+
+fn save_report(conn: &Connection, report: &MaintenanceReport) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO maintenance (id, ran_at, duration_ms, freed_pages, integrity_errors, rebuilt)
+         VALUES (1, ?1, ?2, ?3, ?4, ?5)",
+        params![
+            report.ran_at,
+            report.duration_ms,
+            report.freed_pages,
+            report.integrity_errors.join("\n"),
+            report.rebuilt
+        ],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to save maintenance report: {}", e)))?;
+    Ok(())
+}
+
+fn pragma_i64(conn: &Connection, name: &str) -> Result<i64> {
+    conn.pragma_query_value(None, name, |row| row.get(0))
+        .map_err(|e| FFIError::Database(format!("Failed to read {}: {}", name, e)))
+}
+
+/// Return free pages to the file system.
+///
+/// Databases created before maintenance existed don't track free pages
+/// for incremental vacuum; they are converted once with a full `VACUUM`.
+///
+/// # Returns
+/// Number of pages freed.
+fn incremental_vacuum(conn: &Connection) -> Result<i64> {
+    let before = pragma_i64(conn, "page_count")?;
+
+    if pragma_i64(conn, "auto_vacuum")? != AUTO_VACUUM_INCREMENTAL {
+        tracing::info!("Switching database to incremental auto-vacuum");
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")
+            .map_err(|e| FFIError::Database(format!("Failed to vacuum database: {}", e)))?;
+    } else {
+        conn.execute_batch("PRAGMA incremental_vacuum")
+            .map_err(|e| FFIError::Database(format!("Failed to vacuum database: {}", e)))?;
+    }
+
+    Ok((before - pragma_i64(conn, "page_count")?).max(0))
+}
+
+/// Copy committed transactions from the WAL into the database file, so
+/// they survive a power loss while the machine sleeps.
+///
+/// # Arguments
+/// * `conn` - Writable database connection
+pub fn checkpoint_wal(conn: &Connection) -> Result<()> {
+    conn.query_row("PRAGMA wal_checkpoint(PASSIVE)", [], |_| Ok(()))
+        .map_err(|e| FFIError::Database(format!("Failed to checkpoint WAL: {}", e)))
+}
+
+/// Problems found by SQLite's integrity check and the name index's own
+/// check; empty if the database is sound.
+fn integrity_check(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA integrity_check({})", MAX_INTEGRITY_ERRORS))
+        .map_err(|e| FFIError::Database(format!("Failed to check integrity: {}", e)))?;
+    let mut errors = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| FFIError::Database(format!("Failed to check integrity: {}", e)))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| FFIError::Database(format!("Failed to check integrity: {}", e)))?;
+    errors.retain(|message| message != "ok");
+
+    // The name index stores no names, so only FTS5 can tell whether it
+    // still matches `files` (rank 1 compares it with the content table)
+    if let Err(e) = conn.execute_batch("INSERT INTO files_fts (files_fts, rank) VALUES ('integrity-check', 1)") {
+        errors.push(format!("files_fts: {}", e));
+    }
+    Ok(errors)
+}
+
+/// Drop the file index and everything derived from it, indexed file
+/// contents and recycled items included, then recreate the tables empty. Volumes, open history, saved searches and kept volumes
+/// are preserved; volume counters are reset until the next full scan.
+fn rebuild_index(conn: &Connection) -> Result<()> {
+    // Recreated from its own definition, since migrations won't run again
+    let files_sql: String = conn
+        .query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'files'", [], |row| {
+            row.get(0)
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to read files table definition: {}", e)))?;
+
+    conn.execute_batch(
+        "DROP TABLE IF EXISTS files_fts;
+         DROP TABLE IF EXISTS files;
+         DELETE FROM file_contents;
+         DELETE FROM recycled_items;
+         DELETE FROM facet_counts;
+         DELETE FROM dir_churn;
+         DELETE FROM skipped_paths;
+         DELETE FROM exclusion_suggestions;
+         UPDATE volumes SET file_count = 0, dir_count = 0, total_bytes = 0, facets_valid = 0,
+             last_usn = NULL, usn_journal_id = NULL;",
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to drop corrupt index: {}", e)))?;
+    conn.execute_batch(&files_sql)
+        .map_err(|e| FFIError::Database(format!("Failed to recreate files table: {}", e)))?;
+
+    // Rewrites every page, dropping damage outside the index tables where possible
+    if let Err(e) = conn.execute_batch("VACUUM") {
+        tracing::warn!("VACUUM after dropping the index failed: {}", e);
+    }
+
+    schema::init(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, insert_volume, open_database, FileEntry};
+    use std::fs;
+
+    #[test]
+    fn test_run_maintenance() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_maintenance");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+
+        let volume_id = insert_volume(db.conn(), "C:", "1234-ABCD", "NTFS").unwrap();
+        let entries: Vec<_> = (100..2100)
+            .map(|i| FileEntry::test(volume_id, i, 5, &format!("file{:04}.txt", i), false))
+            .collect();
+        batch_insert_files(db.conn_mut(), &entries).unwrap();
+
+        assert_eq!(get_last_maintenance(db.conn()).unwrap(), None);
+        let report = run_maintenance(db.conn(), 1_700_000_000).unwrap();
+        assert!(report.is_healthy(), "{:?}", report.integrity_errors);
+        assert!(!report.rebuilt);
+        assert_eq!(pragma_i64(db.conn(), "auto_vacuum").unwrap(), AUTO_VACUUM_INCREMENTAL);
+
+        // Pages freed by deletes are returned on the next run
+        db.conn().execute("DELETE FROM files", []).unwrap();
+        let report = run_maintenance(db.conn(), 1_700_086_400).unwrap();
+        assert!(report.freed_pages > 0);
+        assert_eq!(get_last_maintenance(db.conn()).unwrap(), Some(report));
+
+        drop(db);
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_rebuild_index() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_maintenance_rebuild");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+
+        let volume_id = insert_volume(db.conn(), "C:", "1234-ABCD", "NTFS").unwrap();
+        batch_insert_files(db.conn_mut(), &[FileEntry::test(volume_id, 100, 5, "report.pdf", false)]).unwrap();
+
+        // A name index out of step with `files` fails the check
+        db.conn()
+            .execute_batch("INSERT INTO files_fts (rowid, name) VALUES (999, 'ghost.txt')")
+            .unwrap();
+        assert!(!integrity_check(db.conn()).unwrap().is_empty());
+
+        rebuild_index(db.conn()).unwrap();
+        assert!(integrity_check(db.conn()).unwrap().is_empty());
+        let (files, volumes): (i64, i64) = db
+            .conn()
+            .query_row("SELECT (SELECT COUNT(*) FROM files), (SELECT COUNT(*) FROM volumes)", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((files, volumes), (0, 1));
+
+        drop(db);
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
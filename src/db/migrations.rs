@@ -0,0 +1,667 @@
+//! Versioned schema migrations.
+//!
+//! The schema version is kept in `PRAGMA user_version`. Every database,
+//! new or existing, is brought to [`SCHEMA_VERSION`] by applying the steps
+//! in [`MIGRATIONS`] above its version in order, each in its own
+//! transaction together with the version bump, so an interrupted upgrade
+//! resumes at the failed step on the next open.
+//!
+//! Databases created before versioning report version 0 like new ones.
+//! Every step therefore checks before it changes anything (tables are
+//! created if missing, columns added if missing), so it is a no-op on a
+//! database that already has its change.
+//!
+//! To change the schema, append a step and never edit a released one: a
+//! step runs against the schema as its version left it, so it must not
+//! call code written for a later schema. Indexes, the name index and its
+//! triggers are derived from the tables and recreated by
+//! [`super::schema::init`] whenever they are missing, so steps only manage
+//! tables and columns.
+
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+
+use super::ops::MAX_PATH_DEPTH;
+use crate::{FFIError, Result};
+
+/// Schema version written by this build.
+pub const SCHEMA_VERSION: u32 = 16;
+
+/// One schema change.
+struct Migration {
+    /// Version the database has after this step
+    version: u32,
+    /// What the step changes, for the log
+    description: &'static str,
+    apply: fn(&Connection) -> Result<()>,
+}
+
+/// Every schema change, oldest first; `version` counts up from 1.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "volumes and files tables",
+        apply: create_base_tables,
+    },
+    Migration {
+        version: 2,
+        description: "skipped paths, directory churn, exclusion suggestions and kept volumes",
+        apply: create_volume_housekeeping_tables,
+    },
+    Migration {
+        version: 3,
+        description: "volume counters and facet counts",
+        apply: add_volume_counters,
+    },
+    Migration {
+        version: 4,
+        description: "open history, full paths and extensions",
+        apply: add_full_paths,
+    },
+    Migration {
+        version: 5,
+        description: "creation times and attributes",
+        apply: add_created_and_attributes,
+    },
+    Migration {
+        version: 6,
+        description: "saved searches and open events",
+        apply: create_saved_searches,
+    },
+    Migration {
+        version: 7,
+        description: "hard links and alternate data streams",
+        apply: rebuild_files_key,
+    },
+    Migration {
+        version: 8,
+        description: "maintenance results",
+        apply: create_maintenance_table,
+    },
+    Migration {
+        version: 9,
+        description: "pruning results",
+        apply: create_pruning_table,
+    },
+    Migration {
+        version: 10,
+        description: "scan checkpoints",
+        apply: add_scan_checkpoints,
+    },
+    Migration {
+        version: 11,
+        description: "volumes identified by serial",
+        apply: rekey_volumes_by_serial,
+    },
+    Migration {
+        version: 12,
+        description: "scan progress counts",
+        apply: add_scan_progress_counts,
+    },
+    Migration {
+        version: 13,
+        description: "file contents index",
+        apply: create_content_index,
+    },
+    Migration {
+        version: 14,
+        description: "file tags",
+        apply: create_tags,
+    },
+    Migration {
+        version: 15,
+        description: "recycled items",
+        apply: create_recycled_items,
+    },
+    Migration {
+        version: 16,
+        description: "volume truncation",
+        apply: add_volume_truncation,
+    },
+];
+
+/// Read the schema version of a database.
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| FFIError::Database(format!("Failed to read schema version: {}", e)))
+}
+
+/// Bring the database to [`SCHEMA_VERSION`].
+///
+/// Safe to call from several connections at once: each step re-reads the
+/// version inside its write transaction, so only one connection applies it.
+///
+/// # Errors
+/// Returns an error if a step fails (its changes are rolled back) or the
+/// database was written by a newer build with a schema this one doesn't know.
+pub fn migrate(conn: &Connection) -> Result<()> {
+    migrate_to(conn, SCHEMA_VERSION)
+}
+
+/// Apply the steps up to and including `target`.
+fn migrate_to(conn: &Connection, target: u32) -> Result<()> {
+    let version = schema_version(conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(FFIError::Database(format!(
+            "Database schema version {} is newer than this build supports ({})",
+            version, SCHEMA_VERSION
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > version && m.version <= target) {
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+            .map_err(|e| FFIError::Database(format!("Failed to begin migration: {}", e)))?;
+        // Another connection may have applied it while this one waited
+        if schema_version(&tx)? >= migration.version {
+            continue;
+        }
+
+        if version > 0 {
+            tracing::info!("Migrating database to schema version {}: {}", migration.version, migration.description);
+        }
+        (migration.apply)(&tx)?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| FFIError::Database(format!("Failed to set schema version: {}", e)))?;
+        tx.commit().map_err(|e| {
+            FFIError::Database(format!("Failed to commit schema version {}: {}", migration.version, e))
+        })?;
+    }
+    Ok(())
+}
+
+fn execute(conn: &Connection, sql: &str, what: &str) -> Result<()> {
+    conn.execute_batch(sql)
+        .map_err(|e| FFIError::Database(format!("Failed to create {}: {}", what, e)))
+}
+
+/// Add a column to an existing table unless it is already there.
+///
+/// Returns true if the column was added.
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<bool> {
+    let exists: bool = conn
+        .query_row(
+            &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1", table),
+            [column],
+            |row| row.get(0),
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to inspect {} table: {}", table, e)))?;
+
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .map_err(|e| FFIError::Database(format!("Failed to add {}.{}: {}", table, column, e)))?;
+    }
+    Ok(!exists)
+}
+
+/// Version 1: the volumes and files tables of the first release.
+fn create_base_tables(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS volumes (
+            id INTEGER PRIMARY KEY,
+            drive_letter TEXT NOT NULL UNIQUE,
+            volume_serial TEXT NOT NULL,
+            fs_type TEXT NOT NULL,
+            last_usn INTEGER,
+            usn_journal_id INTEGER,
+            last_scan_time INTEGER,
+            state TEXT NOT NULL DEFAULT 'online',
+            offline_since INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS files (
+            id INTEGER PRIMARY KEY,
+            volume_id INTEGER NOT NULL REFERENCES volumes(id),
+            file_ref INTEGER,
+            parent_ref INTEGER,
+            name TEXT NOT NULL,
+            size INTEGER NOT NULL DEFAULT 0,
+            modified INTEGER,
+            is_dir INTEGER NOT NULL DEFAULT 0,
+            UNIQUE(volume_id, file_ref)
+        );",
+        "volumes and files tables",
+    )
+}
+
+/// Version 2: tables kept per volume besides its files.
+fn create_volume_housekeeping_tables(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS skipped_paths (
+            volume_id INTEGER NOT NULL REFERENCES volumes(id),
+            path TEXT NOT NULL,
+            error TEXT,
+            failures INTEGER NOT NULL DEFAULT 1,
+            first_failed INTEGER NOT NULL,
+            last_failed INTEGER NOT NULL,
+            PRIMARY KEY (volume_id, path)
+        );
+
+        CREATE TABLE IF NOT EXISTS dir_churn (
+            volume_id INTEGER NOT NULL REFERENCES volumes(id),
+            dir_ref INTEGER NOT NULL,
+            changes INTEGER NOT NULL DEFAULT 0,
+            since INTEGER NOT NULL,
+            PRIMARY KEY (volume_id, dir_ref)
+        );
+
+        CREATE TABLE IF NOT EXISTS exclusion_suggestions (
+            path TEXT PRIMARY KEY,
+            volume_id INTEGER NOT NULL REFERENCES volumes(id),
+            reason TEXT NOT NULL,
+            file_count INTEGER NOT NULL,
+            changes_per_day REAL NOT NULL DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS kept_volumes (
+            volume_id INTEGER PRIMARY KEY REFERENCES volumes(id)
+        );",
+        "volume housekeeping tables",
+    )
+}
+
+/// Version 3: counters stored by full scans and the facet count cache.
+fn add_volume_counters(conn: &Connection) -> Result<()> {
+    for column in ["file_count", "dir_count", "total_bytes", "last_scan_duration_ms", "facets_valid"] {
+        add_column_if_missing(conn, "volumes", column, "INTEGER NOT NULL DEFAULT 0")?;
+    }
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS facet_counts (
+            volume_id INTEGER NOT NULL REFERENCES volumes(id),
+            facet TEXT NOT NULL,
+            value TEXT NOT NULL,
+            count INTEGER NOT NULL DEFAULT 0,
+            bytes INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (volume_id, facet, value)
+        );",
+        "facet_counts table",
+    )
+}
+
+/// Version 4: open history, and the `full_path` and `ext` columns filled
+/// for existing rows.
+fn add_full_paths(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS open_history (
+            path TEXT PRIMARY KEY,
+            open_count INTEGER NOT NULL DEFAULT 1,
+            last_opened INTEGER NOT NULL
+        );",
+        "open_history table",
+    )?;
+
+    if add_column_if_missing(conn, "files", "full_path", "TEXT")? {
+        // Roots and rows whose parent isn't indexed start a path; paths
+        // below a parent cycle stay NULL
+        conn.execute(
+            r"WITH RECURSIVE tree(id, volume_id, file_ref, path, depth) AS (
+                  SELECT f.id, f.volume_id, f.file_ref,
+                         CASE WHEN f.name IN ('', '.') THEN '' ELSE f.name END, 1
+                  FROM files f
+                  WHERE f.parent_ref IS NULL
+                     OR f.parent_ref = f.file_ref
+                     OR NOT EXISTS (SELECT 1 FROM files p WHERE p.volume_id = f.volume_id AND p.file_ref = f.parent_ref)
+                  UNION ALL
+                  SELECT c.id, c.volume_id, c.file_ref,
+                         CASE WHEN tree.path = '' THEN c.name ELSE tree.path || '\' || c.name END,
+                         tree.depth + 1
+                  FROM tree
+                  JOIN files c ON c.volume_id = tree.volume_id AND c.parent_ref = tree.file_ref
+                      AND c.file_ref <> c.parent_ref
+                  WHERE tree.depth < ?1
+              )
+              UPDATE files SET full_path = tree.path FROM tree WHERE files.id = tree.id",
+            [MAX_PATH_DEPTH as i64],
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to fill full paths: {}", e)))?;
+    }
+
+    if add_column_if_missing(conn, "files", "ext", "TEXT")? {
+        // Same as `file_extension`: the prefix up to the last dot is the
+        // name with its trailing non-dot characters trimmed
+        conn.execute_batch(
+            "UPDATE files SET ext = lower(nullif(replace(name, rtrim(name, replace(name, '.', '')), ''), ''))
+             WHERE instr(name, '.') > 0",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to fill extensions: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Version 5: creation times and attributes, filled in by the next scan.
+fn add_created_and_attributes(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "files", "created", "INTEGER")?;
+    add_column_if_missing(conn, "files", "attributes", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+/// Version 6: saved searches and the open times behind frecency ranking.
+fn create_saved_searches(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS saved_searches (
+            name TEXT PRIMARY KEY COLLATE NOCASE,
+            query TEXT NOT NULL,
+            created INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS open_events (
+            path TEXT NOT NULL,
+            opened_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_open_events_path ON open_events(path, opened_at);",
+        "saved_searches and open_events tables",
+    )
+}
+
+/// Version 7: rebuild `files` with the `(volume_id, file_ref, link, stream)`
+/// key, so every hard link and stream of a file can have a row.
+///
+/// SQLite can't change a table's unique key in place. Row ids are kept, so
+/// the name index (whose rowids are file ids) stays valid; the indexes and
+/// triggers dropped with the old table are recreated by `schema::init`.
+/// Columns the old table lacks take their defaults. Databases from before
+/// versioning may already have the `link` column, with a key without
+/// `stream`; they are rebuilt too.
+fn rebuild_files_key(conn: &Connection) -> Result<()> {
+    let columns: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info('files')")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .map_err(|e| FFIError::Database(format!("Failed to inspect files table: {}", e)))?;
+    if columns.iter().any(|column| column == "stream") {
+        return Ok(());
+    }
+
+    tracing::info!("Rebuilding files table for hard link and stream support");
+    let columns = columns.join(", ");
+    conn.execute_batch(&format!(
+        "CREATE TABLE files_rebuild (
+            id INTEGER PRIMARY KEY,
+            volume_id INTEGER NOT NULL REFERENCES volumes(id),
+            file_ref INTEGER,
+            parent_ref INTEGER,
+            name TEXT NOT NULL,
+            size INTEGER NOT NULL DEFAULT 0,
+            modified INTEGER,
+            created INTEGER,
+            is_dir INTEGER NOT NULL DEFAULT 0,
+            attributes INTEGER NOT NULL DEFAULT 0,
+            full_path TEXT,
+            ext TEXT,
+            link INTEGER NOT NULL DEFAULT 0,
+            link_target TEXT,
+            stream TEXT NOT NULL DEFAULT '',
+            UNIQUE(volume_id, file_ref, link, stream)
+         );
+         INSERT INTO files_rebuild ({columns}) SELECT {columns} FROM files;
+         DROP TABLE files;
+         ALTER TABLE files_rebuild RENAME TO files;"
+    ))
+    .map_err(|e| FFIError::Database(format!("Failed to rebuild files table: {}", e)))
+}
+
+/// Version 8: outcome of the last maintenance run.
+fn create_maintenance_table(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS maintenance (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            ran_at INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            freed_pages INTEGER NOT NULL DEFAULT 0,
+            integrity_errors TEXT NOT NULL DEFAULT '',
+            rebuilt INTEGER NOT NULL DEFAULT 0
+        );",
+        "maintenance table",
+    )
+}
+
+/// Version 9: what the last pruning to the size budget deleted.
+fn create_pruning_table(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS pruning (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            ran_at INTEGER NOT NULL,
+            size_before INTEGER NOT NULL,
+            size_after INTEGER NOT NULL,
+            offline_volumes TEXT NOT NULL DEFAULT '',
+            excluded_entries INTEGER NOT NULL DEFAULT 0,
+            stale_entries INTEGER NOT NULL DEFAULT 0,
+            modified_cutoff INTEGER
+        );",
+        "pruning table",
+    )
+}
+
+/// Version 10: where an interrupted full scan of a volume stopped, and
+/// how far it got.
+fn add_scan_checkpoints(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "volumes", "scan_next_record", "INTEGER")?;
+    add_column_if_missing(conn, "volumes", "scan_last_path", "TEXT")?;
+    add_column_if_missing(conn, "volumes", "scan_percent", "INTEGER")?;
+    Ok(())
+}
+
+/// Version 11: volumes keyed on their serial rather than their drive letter.
+///
+/// A disk that comes back under another letter is the same volume, so the
+/// letter is no longer unique. The table is rebuilt with the same ids,
+/// which the other tables reference; their foreign keys are checked once
+/// the rows are back. Where several volumes share a serial, only the
+/// newest keeps it.
+fn rekey_volumes_by_serial(conn: &Connection) -> Result<()> {
+    let columns: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info('volumes')")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+        .map_err(|e| FFIError::Database(format!("Failed to inspect volumes table: {}", e)))?;
+
+    let columns = columns.join(", ");
+    conn.execute_batch(&format!(
+        "PRAGMA defer_foreign_keys = ON;
+         CREATE TEMP TABLE volumes_copy AS SELECT * FROM volumes;
+         DROP TABLE volumes;
+
+         CREATE TABLE volumes (
+            id INTEGER PRIMARY KEY,
+            drive_letter TEXT NOT NULL,
+            volume_serial TEXT NOT NULL,
+            fs_type TEXT NOT NULL,
+            last_usn INTEGER,
+            usn_journal_id INTEGER,
+            last_scan_time INTEGER,
+            state TEXT NOT NULL DEFAULT 'online',
+            offline_since INTEGER,
+            file_count INTEGER NOT NULL DEFAULT 0,
+            dir_count INTEGER NOT NULL DEFAULT 0,
+            total_bytes INTEGER NOT NULL DEFAULT 0,
+            last_scan_duration_ms INTEGER NOT NULL DEFAULT 0,
+            facets_valid INTEGER NOT NULL DEFAULT 0,
+            scan_next_record INTEGER,
+            scan_last_path TEXT,
+            scan_percent INTEGER
+         );
+         INSERT INTO volumes ({columns}) SELECT {columns} FROM temp.volumes_copy;
+         DROP TABLE temp.volumes_copy;
+
+         UPDATE volumes SET volume_serial = ''
+         WHERE volume_serial != '' AND state != 'imported'
+           AND id NOT IN (
+               SELECT MAX(id) FROM volumes
+               WHERE volume_serial != '' AND state != 'imported'
+               GROUP BY volume_serial
+           );
+         CREATE UNIQUE INDEX IF NOT EXISTS idx_volumes_serial ON volumes(volume_serial)
+             WHERE volume_serial != '' AND state != 'imported';
+         CREATE INDEX IF NOT EXISTS idx_volumes_drive_letter ON volumes(drive_letter);"
+    ))
+    .map_err(|e| FFIError::Database(format!("Failed to rebuild volumes table: {}", e)))
+}
+
+/// Version 12: entries a full scan processed and expected, for progress
+/// reporting.
+fn add_scan_progress_counts(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "volumes", "scan_processed", "INTEGER")?;
+    add_column_if_missing(conn, "volumes", "scan_total", "INTEGER")?;
+    Ok(())
+}
+
+/// Version 13: text of small files for `content:` searches, filled only
+/// when content indexing is enabled.
+fn create_content_index(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE VIRTUAL TABLE IF NOT EXISTS file_contents USING fts5(
+            content, modified UNINDEXED, size UNINDEXED
+        );",
+        "file contents index",
+    )
+}
+
+/// Version 14: tags users attach to files, keyed by volume serial and file
+/// reference so they survive drive letter changes and rescans.
+fn create_tags(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS tags (
+            volume_serial TEXT NOT NULL,
+            file_ref INTEGER NOT NULL,
+            tag TEXT NOT NULL COLLATE NOCASE,
+            created INTEGER NOT NULL,
+            PRIMARY KEY (volume_serial, file_ref, tag)
+        );
+        CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag);",
+        "tags table",
+    )
+}
+
+/// Version 15: original paths of the items in the Recycle Bin, read from
+/// their `$I` files.
+fn create_recycled_items(conn: &Connection) -> Result<()> {
+    execute(
+        conn,
+        "CREATE TABLE IF NOT EXISTS recycled_items (
+            volume_id INTEGER NOT NULL,
+            item_ref INTEGER NOT NULL,
+            info_ref INTEGER NOT NULL,
+            original_path TEXT NOT NULL,
+            deleted_at INTEGER,
+            PRIMARY KEY (volume_id, item_ref)
+        );",
+        "recycled items table",
+    )
+}
+
+/// Version 16: which limit, if any, kept part of a volume out of the index
+/// on its last full scan.
+fn add_volume_truncation(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "volumes", "truncated", "TEXT")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    /// Every table's columns, to compare schemas reached by different paths.
+    fn table_columns(conn: &Connection) -> Vec<(String, Vec<String>)> {
+        let tables: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        tables
+            .into_iter()
+            .map(|table| {
+                let columns = conn
+                    .prepare(&format!("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info('{}')", table))
+                    .unwrap()
+                    .query_map([], |row| {
+                        Ok(format!(
+                            "{} {} {} {:?} {}",
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, i64>(2)?,
+                            row.get::<_, Option<String>>(3)?,
+                            row.get::<_, i64>(4)?
+                        ))
+                    })
+                    .unwrap()
+                    .map(|r| r.unwrap())
+                    .collect();
+                (table, columns)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_new_database_is_current() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert_eq!(versions, (1..=SCHEMA_VERSION).collect::<Vec<_>>());
+
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+        schema::init(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+
+        // Nothing left to apply on the next open
+        schema::init(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_upgrade_from_each_version() {
+        let current = Connection::open_in_memory().unwrap();
+        schema::init(&current).unwrap();
+        let expected = table_columns(&current);
+
+        for version in 1..SCHEMA_VERSION {
+            let conn = Connection::open_in_memory().unwrap();
+            migrate_to(&conn, version).unwrap();
+            assert_eq!(schema_version(&conn).unwrap(), version);
+            conn.execute_batch(
+                "INSERT INTO volumes (id, drive_letter, volume_serial, fs_type) VALUES (1, 'C:', '', 'NTFS');
+                 INSERT INTO files (volume_id, file_ref, parent_ref, name, is_dir) VALUES
+                     (1, 5, 5, '.', 1),
+                     (1, 100, 5, 'Users', 1),
+                     (1, 200, 100, 'notes.txt', 0);",
+            )
+            .unwrap();
+
+            schema::init(&conn).unwrap();
+            assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION, "from version {}", version);
+            assert_eq!(table_columns(&conn), expected, "from version {}", version);
+
+            // Rows survive and are found through the name index
+            let (path, ext): (Option<String>, Option<String>) = conn
+                .query_row(
+                    "SELECT full_path, ext FROM files
+                     WHERE id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH 'notes')",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap();
+            // Filled in by the step adding the columns, otherwise by the indexer
+            if version < 4 {
+                assert_eq!((path.as_deref(), ext.as_deref()), (Some(r"Users\notes.txt"), Some("txt")));
+            } else {
+                assert_eq!((path, ext), (None, None));
+            }
+        }
+    }
+
+    #[test]
+    fn test_newer_schema_refused() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+
+        let err = schema::init(&conn).unwrap_err();
+        assert!(err.to_string().contains("newer"), "{}", err);
+    }
+}
@@ -3,22 +3,92 @@
 //! This module provides database connection management with optimized PRAGMAs
 //! for high-performance file indexing operations.
 
-mod schema;
+pub(crate) mod schema;
+mod consistency;
+mod content;
+mod exclusions;
+mod export;
+mod facets;
+mod functions;
+mod maintenance;
+mod migrations;
 mod ops;
+mod pool;
+mod pruning;
+mod recycle_bin;
+mod snapshot;
+mod store;
+mod tags;
+mod usage;
+mod writer;
 
+pub use consistency::{delete_subtrees, find_tree_issues, TreeIssue, TreeIssueKind};
+pub use content::{clear_contents, content_candidates, prune_contents, store_contents, ContentCandidate, FileContent};
+pub use exclusions::{
+    analyze_exclusions, get_exclusion_suggestions, purge_excluded, purge_excluded_entries, purge_excluded_extensions,
+    purge_excluded_paths, purge_excluded_patterns, purge_over_file_limit, purge_too_deep, record_dir_churn,
+    save_exclusion_suggestions, ExclusionSuggestion, HIGH_CHURN_PER_DAY, LOW_VALUE_DIR_NAMES, MIN_SUGGESTED_FILES,
+};
+pub use export::{export_matches, ExportFormat, Exporter, EXPORT_PAGE_SIZE};
+pub use facets::{
+    cached_query_count, get_facet_counts, rebuild_facet_counts, Facet, FacetCount, FacetDeltas,
+};
+pub use functions::register_functions;
+pub use maintenance::{checkpoint_wal, get_last_maintenance, run_maintenance, MaintenanceReport};
 pub use ops::*;
+pub use pool::{DatabasePool, PooledReader};
+pub use pruning::{get_last_pruning, index_size, prune_to_budget, PruneReport, PRUNE_TARGET};
+pub use recycle_bin::{
+    prune_recycled_items, recycled_original_path, store_recycled_items, unread_recycle_info, RecycleInfoFile,
+    RecycledItem,
+};
+pub use snapshot::{
+    export_volume_snapshot, import_volume_snapshot, read_snapshot_info, SnapshotInfo,
+    SNAPSHOT_FORMAT_VERSION,
+};
+pub use store::Store;
+pub use tags::{add_tags, get_tags, remove_tags};
+pub use usage::{disk_usage, UsageGroup, UsageRow};
+pub use writer::{apply_write, current_writer, start_db_writer, DbWriter, WriteOp, WriterThread};
 
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
 use std::path::Path;
+use std::sync::RwLock;
 
+use crate::search::FileAttribute;
+use crate::service::config::DatabaseConfig;
 use crate::{FFIError, Result};
 
+/// Tuning applied to connections opened from now on; defaults until set.
+static TUNING: RwLock<Option<DatabaseConfig>> = RwLock::new(None);
+
 /// Database wrapper providing connection management.
 pub struct Database {
     conn: Connection,
+    /// Attributes whose entries [`Store`] searches leave out
+    excluded_attributes: Vec<FileAttribute>,
 }
 
 impl Database {
+    fn new(conn: Connection) -> Self {
+        Self {
+            conn,
+            excluded_attributes: Vec::new(),
+        }
+    }
+
+    /// Leave entries with any of these attributes out of [`Store`]
+    /// searches (the `[search]` hide options) unless the query has an
+    /// `attrib:` filter; empty shows everything.
+    pub fn set_excluded_attributes(&mut self, attributes: &[FileAttribute]) {
+        self.excluded_attributes = attributes.to_vec();
+    }
+
+    /// Attributes left out of searches.
+    pub fn excluded_attributes(&self) -> &[FileAttribute] {
+        &self.excluded_attributes
+    }
+
     /// Get a reference to the underlying connection.
     pub fn conn(&self) -> &Connection {
         &self.conn
@@ -30,14 +100,58 @@ impl Database {
     }
 }
 
+/// Set the SQLite tuning used by every connection opened afterwards.
+///
+/// Called once at startup with the `[database]` config section. Invalid
+/// settings are rejected and the previous tuning is kept.
+///
+/// # Arguments
+/// * `tuning` - Memory, busy timeout and synchronous settings
+pub fn configure_database(tuning: DatabaseConfig) -> Result<()> {
+    tuning.validate()?;
+    *TUNING.write().unwrap_or_else(|e| e.into_inner()) = Some(tuning);
+    Ok(())
+}
+
+/// The configured tuning, or the defaults if none was set.
+fn current_tuning() -> DatabaseConfig {
+    TUNING
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Apply memory and busy timeout PRAGMAs, plus the synchronous level for
+/// writable connections.
+fn apply_tuning(conn: &Connection, tuning: &DatabaseConfig, writable: bool) -> Result<()> {
+    if writable {
+        conn.pragma_update(None, "synchronous", tuning.synchronous.as_pragma())
+            .map_err(|e| FFIError::Database(format!("Failed to set synchronous: {}", e)))?;
+    }
+
+    conn.pragma_update(None, "mmap_size", (tuning.mmap_size_mb * 1024 * 1024) as i64)
+        .map_err(|e| FFIError::Database(format!("Failed to set mmap_size: {}", e)))?;
+
+    // Negative value = KB
+    conn.pragma_update(None, "cache_size", -((tuning.cache_size_mb * 1024) as i64))
+        .map_err(|e| FFIError::Database(format!("Failed to set cache_size: {}", e)))?;
+
+    conn.pragma_update(None, "busy_timeout", tuning.busy_timeout_ms as i64)
+        .map_err(|e| FFIError::Database(format!("Failed to set busy_timeout: {}", e)))?;
+
+    Ok(())
+}
+
 /// Open a database connection with WAL mode and optimized PRAGMAs.
 ///
 /// This function:
 /// 1. Creates parent directory if it doesn't exist
 /// 2. Opens connection with rusqlite
 /// 3. Configures WAL mode for crash safety
-/// 4. Sets performance-optimized PRAGMAs
+/// 4. Sets the PRAGMAs configured with [`configure_database`]
 /// 5. Initializes schema (creates tables if needed)
+/// 6. Registers the SQL functions used by searches
 ///
 /// # Arguments
 /// * `path` - Path to the SQLite database file
@@ -59,30 +173,59 @@ pub fn open_database(path: &Path) -> Result<Database> {
     conn.pragma_update(None, "journal_mode", "WAL")
         .map_err(|e| FFIError::Database(format!("Failed to set journal_mode: {}", e)))?;
 
-    // NORMAL synchronous is safe in WAL mode, faster than FULL
-    conn.pragma_update(None, "synchronous", "NORMAL")
-        .map_err(|e| FFIError::Database(format!("Failed to set synchronous: {}", e)))?;
-
     // Store temp tables in memory
     conn.pragma_update(None, "temp_store", "MEMORY")
         .map_err(|e| FFIError::Database(format!("Failed to set temp_store: {}", e)))?;
 
-    // Enable memory-mapped I/O (256MB)
-    conn.pragma_update(None, "mmap_size", 268435456i64)
-        .map_err(|e| FFIError::Database(format!("Failed to set mmap_size: {}", e)))?;
-
-    // 64MB page cache (negative value = KB)
-    conn.pragma_update(None, "cache_size", -64000i32)
-        .map_err(|e| FFIError::Database(format!("Failed to set cache_size: {}", e)))?;
-
-    // 5 second busy timeout for concurrent access
-    conn.pragma_update(None, "busy_timeout", 5000i32)
-        .map_err(|e| FFIError::Database(format!("Failed to set busy_timeout: {}", e)))?;
+    // Synchronous level, mmap and page cache sizes, busy timeout
+    apply_tuning(&conn, &current_tuning(), true)?;
 
     // Initialize schema (creates tables if needed)
     schema::init(&conn)?;
 
-    Ok(Database { conn })
+    register_functions(&conn)?;
+
+    Ok(Database::new(conn))
+}
+
+/// Open an existing database read-only, for replica mode.
+///
+/// Unlike [`open_database`], this never creates the file, changes the
+/// journal mode, or runs schema initialization. `query_only` is enabled as
+/// a second guard so any accidental write fails.
+///
+/// # Arguments
+/// * `path` - Path to an existing SQLite database file
+///
+/// # Returns
+/// * `Result<Database>` - Wrapped read-only database connection
+pub fn open_database_read_only(path: &Path) -> Result<Database> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| FFIError::Database(format!("Failed to open database read-only: {}", e)))?;
+
+    conn.pragma_update(None, "query_only", true)
+        .map_err(|e| FFIError::Database(format!("Failed to set query_only: {}", e)))?;
+
+    conn.pragma_update(None, "temp_store", "MEMORY")
+        .map_err(|e| FFIError::Database(format!("Failed to set temp_store: {}", e)))?;
+
+    apply_tuning(&conn, &current_tuning(), false)?;
+
+    // Fail early on files that are not an FFI index
+    let tables: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('volumes', 'files')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to read schema: {}", e)))?;
+    if tables != 2 {
+        return Err(FFIError::Database(format!("{:?} is not an FFI index database", path)));
+    }
+
+    register_functions(&conn)?;
+
+    Ok(Database::new(conn))
 }
 
 #[cfg(test)]
@@ -129,4 +272,70 @@ mod tests {
         // Cleanup
         let _ = fs::remove_dir_all(&temp_dir);
     }
+
+    #[test]
+    fn test_apply_tuning() {
+        use crate::service::config::SynchronousLevel;
+
+        let temp_dir = std::env::temp_dir().join("ffi_test_tuning");
+        let db_path = temp_dir.join("test.db");
+
+        // Ensure clean state
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        let db = open_database(&db_path).unwrap();
+        let pragma = |name: &str| -> i64 {
+            db.conn().pragma_query_value(None, name, |row| row.get(0)).unwrap()
+        };
+        assert_eq!(pragma("synchronous"), 1); // NORMAL
+        assert_eq!(pragma("busy_timeout"), 5000);
+
+        let tuning = DatabaseConfig {
+            mmap_size_mb: 0,
+            cache_size_mb: 8,
+            busy_timeout_ms: 250,
+            synchronous: SynchronousLevel::Full,
+            ..Default::default()
+        };
+        apply_tuning(db.conn(), &tuning, true).unwrap();
+        assert_eq!(pragma("synchronous"), 2); // FULL
+        assert_eq!(pragma("mmap_size"), 0);
+        assert_eq!(pragma("cache_size"), -8192);
+        assert_eq!(pragma("busy_timeout"), 250);
+
+        // Invalid settings are rejected
+        let invalid = DatabaseConfig { cache_size_mb: 0, ..Default::default() };
+        assert!(configure_database(invalid).is_err());
+
+        // Cleanup
+        drop(db);
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_open_database_read_only() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_read_only");
+        let db_path = temp_dir.join("test.db");
+
+        // Ensure clean state
+        let _ = fs::remove_dir_all(&temp_dir);
+
+        // Missing file is an error rather than being created
+        assert!(open_database_read_only(&db_path).is_err());
+
+        drop(open_database(&db_path).unwrap());
+        let db = open_database_read_only(&db_path).unwrap();
+
+        let count: i64 = db
+            .conn()
+            .query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+
+        // Writes are rejected
+        assert!(ops::insert_volume(db.conn(), "C:", "1234-ABCD", "NTFS").is_err());
+
+        // Cleanup
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
 }
@@ -3,9 +3,17 @@
 //! This module provides all database operations for the FFI index,
 //! including volume management, file operations, and path reconstruction.
 
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
+use super::facets::cached_query_count;
+use crate::search::query::{fts_name_query, NAME_INDEX_SQL};
+use crate::search::{
+    build_count_query, build_relevance_query, build_sql_query_page, order_by_clause, ParsedQuery,
+    SortSpec, SqlParam,
+};
 use crate::{FFIError, Result, VolumeState};
 
 /// Batch size for bulk inserts - 100,000 records per transaction.
@@ -25,8 +33,12 @@ pub struct VolumeInfo {
     pub fs_type: String,
 }
 
+/// `FILE_ATTRIBUTE_REPARSE_POINT`: set on symlinks, junctions and other
+/// reparse points.
+pub const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
 /// A file entry for insertion into the database.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct FileEntry {
     /// Volume this file belongs to
     pub volume_id: i64,
@@ -40,8 +52,168 @@ pub struct FileEntry {
     pub size: i64,
     /// Last modified time as Unix timestamp
     pub modified: Option<i64>,
+    /// Creation time as Unix timestamp
+    pub created: Option<i64>,
     /// Whether this is a directory
     pub is_dir: bool,
+    /// Windows `FILE_ATTRIBUTE_*` flags (hidden, system, ...), 0 where unknown
+    pub attributes: u32,
+    /// Which of the file's hard links this name is: 0 for its primary name,
+    /// 1.. for further names sharing the `file_ref`
+    pub link: u32,
+    /// Target of a symbolic link or junction, if known
+    pub link_target: Option<String>,
+    /// Name of the NTFS alternate data stream this row stands for (the
+    /// row's name is then `file:stream`), or None for the file itself
+    pub stream: Option<String>,
+}
+
+#[cfg(test)]
+impl FileEntry {
+    /// Entry named `name` in `parent_ref`, with everything else defaulted.
+    pub(crate) fn test(volume_id: i64, file_ref: i64, parent_ref: i64, name: &str, is_dir: bool) -> Self {
+        Self {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            is_dir,
+            ..Default::default()
+        }
+    }
+}
+
+/// Extension stored for a name: the text after the last dot, lowercased.
+///
+/// Names without a dot, or ending in one, have no extension. Stored in the
+/// indexed `ext` column so `ext:` filters are an exact match.
+pub fn file_extension(name: &str) -> Option<String> {
+    let (_, ext) = name.rsplit_once('.')?;
+    (!ext.is_empty()).then(|| ext.to_ascii_lowercase())
+}
+
+/// A directory that repeatedly failed with access-denied during scans.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedPath {
+    /// Volume the directory belongs to
+    pub volume_id: i64,
+    /// Full path of the directory
+    pub path: String,
+    /// Last error message
+    pub error: Option<String>,
+    /// Consecutive scans that failed on this path
+    pub failures: i64,
+    /// Unix timestamp of the first failure
+    pub first_failed: i64,
+    /// Unix timestamp of the latest failure
+    pub last_failed: i64,
+}
+
+/// A file opened from the search UI, used for frecency ranking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenRecord {
+    /// Lowercase full path
+    pub path: String,
+    /// Times the file was opened
+    pub open_count: i64,
+    /// Unix timestamp of the latest open
+    pub last_opened: i64,
+}
+
+/// A named query pinned in the search UI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    /// Name shown on the chip (e.g., "Big installers")
+    pub name: String,
+    /// Search query, as typed (e.g., `ext:msi;exe size:>100mb`)
+    pub query: String,
+    /// Unix timestamp of the first save
+    pub created: i64,
+}
+
+/// Number of opened files remembered for frecency ranking.
+pub const MAX_OPEN_HISTORY: usize = 5000;
+
+/// Latest opens of each file kept as `open_events`, to weigh its opens by age.
+pub const MAX_OPEN_SAMPLES: usize = 10;
+
+/// An offline volume waiting for retention cleanup.
+#[derive(Debug, Clone)]
+pub struct OfflineVolume {
+    /// The volume
+    pub volume: VolumeInfo,
+    /// Unix timestamp when the volume went offline
+    pub offline_since: i64,
+    /// Whether the user chose to keep the index forever
+    pub keep_forever: bool,
+    /// Unix timestamp after which cleanup deletes the index (None if kept)
+    pub purge_after: Option<i64>,
+}
+
+/// How long offline volumes are kept before cleanup deletes their index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Retention in days for volumes without an override
+    pub default_days: u32,
+    /// Retention in days per volume name, uppercased (e.g., "E:")
+    pub per_volume: HashMap<String, u32>,
+}
+
+impl RetentionPolicy {
+    /// Retention in days for a volume.
+    pub fn days(&self, drive_letter: &str) -> u32 {
+        self.per_volume
+            .get(&drive_letter.to_uppercase())
+            .copied()
+            .unwrap_or(self.default_days)
+    }
+}
+
+impl From<u32> for RetentionPolicy {
+    fn from(default_days: u32) -> Self {
+        Self {
+            default_days,
+            per_volume: HashMap::new(),
+        }
+    }
+}
+
+/// Per-volume counters stored with the volume at the end of each full scan.
+///
+/// Read back on status requests so nothing has to count millions of rows
+/// after a restart.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeStats {
+    /// Files indexed on the volume
+    pub file_count: i64,
+    /// Directories indexed on the volume
+    pub dir_count: i64,
+    /// Sum of file sizes in bytes
+    pub total_bytes: i64,
+    /// Duration of the last full scan in milliseconds
+    pub last_scan_duration_ms: i64,
+    /// Unix timestamp of the last scan (None if never scanned)
+    pub last_scan_time: Option<i64>,
+    /// Limits that left entries out on the last full scan (e.g.
+    /// "max_files = 1000"), None if everything was indexed
+    pub truncated: Option<String>,
+}
+
+/// How far a full scan of a volume got, saved as it goes so a scan cut
+/// short by a stop or crash resumes there instead of starting over.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanCheckpoint {
+    /// MFT scans: every record below this one is indexed
+    pub next_record: Option<u64>,
+    /// Directory walks: every entry up to this path (relative to the root,
+    /// in walk order) is indexed
+    pub last_path: Option<String>,
+    /// Share of the volume indexed
+    pub percent: u8,
+    /// Entries the scan processed, out of `total`
+    pub processed: u64,
+    /// Entries the scan expected (0 if unknown)
+    pub total: u64,
 }
 
 // Volume operations will be implemented in Task 3
@@ -50,45 +222,91 @@ pub struct FileEntry {
 
 /// Insert or update a volume, returning its ID.
 ///
-/// If a volume with the same drive letter exists, it will be updated.
-/// Otherwise, a new volume record will be created.
+/// Volumes are identified by serial; the drive letter is an attribute. A
+/// known serial updates its volume in place, moving it to `drive_letter`
+/// if the disk came back under another letter. An empty serial (scanners
+/// only know the name) updates the volume currently at `drive_letter` (see
+/// [`get_volume`]), and so does a serial seen for the first time when that
+/// volume was recorded without one. Updated volumes keep their ID, and so
+/// their files. Otherwise, a new volume record will be created.
 pub fn insert_volume(
     conn: &Connection,
     drive_letter: &str,
     serial: &str,
     fs_type: &str,
 ) -> Result<i64> {
-    // Use INSERT OR REPLACE to handle both insert and update
-    conn.execute(
-        "INSERT OR REPLACE INTO volumes (drive_letter, volume_serial, fs_type, last_scan_time)
-         VALUES (?1, ?2, ?3, strftime('%s', 'now'))",
-        params![drive_letter, serial, fs_type],
-    )
-    .map_err(|e| FFIError::Database(format!("Failed to insert volume: {}", e)))?;
-
-    // Get the ID of the inserted/updated row
-    let id = conn.last_insert_rowid();
-
-    // If last_insert_rowid returns 0, the row already existed and was replaced
-    // In that case, query for the actual ID
-    if id == 0 {
-        let id: i64 = conn
-            .query_row(
-                "SELECT id FROM volumes WHERE drive_letter = ?1",
-                params![drive_letter],
-                |row| row.get(0),
-            )
-            .map_err(|e| FFIError::Database(format!("Failed to get volume ID: {}", e)))?;
-        Ok(id)
+    let known = if serial.is_empty() {
+        None
     } else {
-        Ok(id)
+        get_volume_by_serial(conn, serial)?
+    };
+    let existing = match known {
+        Some(volume) => Some(volume),
+        None => get_volume(conn, drive_letter)?
+            .filter(|volume| serial.is_empty() || volume.volume_serial.is_empty()),
+    };
+
+    // Replacing the row would delete it, orphaning the volume's files
+    match existing {
+        Some(volume) => {
+            conn.execute(
+                "UPDATE volumes SET
+                     drive_letter = ?1,
+                     volume_serial = CASE WHEN ?2 = '' THEN volume_serial ELSE ?2 END,
+                     fs_type = ?3,
+                     last_scan_time = strftime('%s', 'now')
+                 WHERE id = ?4",
+                params![drive_letter, serial, fs_type, volume.id],
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to update volume: {}", e)))?;
+            Ok(volume.id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO volumes (drive_letter, volume_serial, fs_type, last_scan_time)
+                 VALUES (?1, ?2, ?3, strftime('%s', 'now'))",
+                params![drive_letter, serial, fs_type],
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to insert volume: {}", e)))?;
+            Ok(conn.last_insert_rowid())
+        }
     }
 }
 
+/// Record a volume found mounted at `drive_letter`, returning its ID.
+///
+/// The volume is found by serial as in [`insert_volume`], so a disk that
+/// comes back under another letter keeps its index and moves to the new
+/// letter. An offline volume comes back online; any other volume still
+/// recorded at the letter is no longer there and goes offline.
+pub fn mount_volume(conn: &Connection, drive_letter: &str, serial: &str, fs_type: &str) -> Result<i64> {
+    let volume_id = insert_volume(conn, drive_letter, serial, fs_type)?;
+
+    conn.execute(
+        "UPDATE volumes SET state = 'online', offline_since = NULL WHERE id = ?1 AND state = 'offline'",
+        params![volume_id],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to bring volume online: {}", e)))?;
+    conn.execute(
+        "UPDATE volumes SET state = 'offline', offline_since = strftime('%s', 'now')
+         WHERE drive_letter = ?1 AND id != ?2 AND state IN ('online', 'indexing', 'rescanning')",
+        params![drive_letter, volume_id],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to mark replaced volume offline: {}", e)))?;
+
+    Ok(volume_id)
+}
+
 /// Get volume information by drive letter.
+///
+/// Several volumes can be recorded at one letter when disks were swapped;
+/// this returns the one there now: a volume that is not offline, else the
+/// most recently scanned.
 pub fn get_volume(conn: &Connection, drive_letter: &str) -> Result<Option<VolumeInfo>> {
     let result = conn.query_row(
-        "SELECT id, drive_letter, volume_serial, fs_type FROM volumes WHERE drive_letter = ?1",
+        "SELECT id, drive_letter, volume_serial, fs_type FROM volumes WHERE drive_letter = ?1
+         ORDER BY state = 'offline', last_scan_time DESC, id DESC
+         LIMIT 1",
         params![drive_letter],
         |row| {
             Ok(VolumeInfo {
@@ -156,9 +374,11 @@ pub fn update_volume_state(conn: &Connection, volume_id: i64, state: VolumeState
     let state_str = state.to_db_str();
     let offline_since = state.offline_since();
 
+    // Progress is kept with the scan checkpoint when leaving Indexing
     conn.execute(
-        "UPDATE volumes SET state = ?1, offline_since = ?2 WHERE id = ?3",
-        params![state_str, offline_since, volume_id],
+        "UPDATE volumes SET state = ?1, offline_since = ?2, scan_percent = COALESCE(?3, scan_percent)
+         WHERE id = ?4",
+        params![state_str, offline_since, state.progress(), volume_id],
     )
     .map_err(|e| FFIError::Database(format!("Failed to update volume state: {}", e)))?;
 
@@ -175,17 +395,20 @@ pub fn update_volume_state(conn: &Connection, volume_id: i64, state: VolumeState
 /// The volume state, or error if volume not found.
 pub fn get_volume_state(conn: &Connection, volume_id: i64) -> Result<VolumeState> {
     let result = conn.query_row(
-        "SELECT state, offline_since FROM volumes WHERE id = ?1",
+        "SELECT state, offline_since, scan_percent FROM volumes WHERE id = ?1",
         params![volume_id],
         |row| {
             let state_str: String = row.get(0)?;
             let offline_since: Option<i64> = row.get(1)?;
-            Ok((state_str, offline_since))
+            let scan_percent: Option<i64> = row.get(2)?;
+            Ok((state_str, offline_since, scan_percent))
         },
     );
 
     match result {
-        Ok((state_str, offline_since)) => Ok(VolumeState::from_db(&state_str, offline_since)),
+        Ok((state_str, offline_since, scan_percent)) => {
+            Ok(VolumeState::from_db(&state_str, offline_since, scan_percent))
+        }
         Err(rusqlite::Error::QueryReturnedNoRows) => {
             Err(FFIError::Database("Volume not found".to_string()))
         }
@@ -205,7 +428,8 @@ pub fn get_volume_state(conn: &Connection, volume_id: i64) -> Result<VolumeState
 /// Volume info if found, None otherwise.
 pub fn get_volume_by_serial(conn: &Connection, serial: &str) -> Result<Option<VolumeInfo>> {
     let result = conn.query_row(
-        "SELECT id, drive_letter, volume_serial, fs_type FROM volumes WHERE volume_serial = ?1",
+        "SELECT id, drive_letter, volume_serial, fs_type FROM volumes
+         WHERE volume_serial = ?1 AND state != 'imported'",
         params![serial],
         |row| {
             Ok(VolumeInfo {
@@ -227,43 +451,155 @@ pub fn get_volume_by_serial(conn: &Connection, serial: &str) -> Result<Option<Vo
 /// Clean up old offline volumes and their file data.
 ///
 /// Deletes all files and volumes where the volume has been offline
-/// longer than the retention period.
+/// longer than its retention period.
 ///
 /// # Arguments
 /// * `conn` - Database connection
-/// * `retention_days` - Number of days to retain offline volume data
+/// * `retention` - Days to retain offline volume data, per volume
 ///
 /// # Returns
 /// The number of file entries deleted.
-pub fn cleanup_old_offline_volumes(conn: &Connection, retention_days: u32) -> Result<usize> {
-    // Calculate cutoff timestamp (now - retention_days in seconds)
+pub fn cleanup_old_offline_volumes(conn: &Connection, retention: impl Into<RetentionPolicy>) -> Result<usize> {
+    let retention = retention.into();
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
-    let cutoff = now - (retention_days as i64 * 86400);
 
-    // Delete files first (foreign key constraint)
-    let deleted = conn
-        .execute(
-            "DELETE FROM files WHERE volume_id IN (
-                SELECT id FROM volumes WHERE state = 'offline' AND offline_since < ?1
-            )",
-            params![cutoff],
+    // Volumes kept forever by the user are never cleaned up
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, drive_letter, offline_since FROM volumes
+             WHERE state = 'offline' AND offline_since IS NOT NULL
+               AND id NOT IN (SELECT volume_id FROM kept_volumes)",
         )
-        .map_err(|e| FFIError::Database(format!("Failed to delete offline volume files: {}", e)))?;
+        .map_err(|e| FFIError::Database(format!("Failed to prepare offline volumes query: {}", e)))?;
+    let offline: Vec<(i64, String, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| FFIError::Database(format!("Failed to query offline volumes: {}", e)))?;
+
+    let mut deleted = 0;
+    for (volume_id, drive_letter, offline_since) in offline {
+        // Cutoff timestamp: now - retention days in seconds
+        let cutoff = now - (retention.days(&drive_letter) as i64 * 86400);
+        if offline_since >= cutoff {
+            continue;
+        }
 
-    // Then delete the volumes
-    conn.execute(
-        "DELETE FROM volumes WHERE state = 'offline' AND offline_since < ?1",
-        params![cutoff],
-    )
-    .map_err(|e| FFIError::Database(format!("Failed to delete offline volumes: {}", e)))?;
+        // Delete files first (foreign key constraint)
+        deleted += conn
+            .execute("DELETE FROM files WHERE volume_id = ?1", params![volume_id])
+            .map_err(|e| FFIError::Database(format!("Failed to delete offline volume files: {}", e)))?;
+
+        for table in ["skipped_paths", "dir_churn", "exclusion_suggestions", "facet_counts", "recycled_items"] {
+            conn.execute(&format!("DELETE FROM {} WHERE volume_id = ?1", table), params![volume_id])
+                .map_err(|e| FFIError::Database(format!("Failed to delete offline volume {}: {}", table, e)))?;
+        }
+
+        // Then delete the volume
+        conn.execute("DELETE FROM volumes WHERE id = ?1", params![volume_id])
+            .map_err(|e| FFIError::Database(format!("Failed to delete offline volumes: {}", e)))?;
+    }
 
     if deleted > 0 {
-        tracing::info!("Cleaned up {} files from old offline volumes (cutoff: {} days)", deleted, retention_days);
+        tracing::info!(
+            "Cleaned up {} files from old offline volumes (default retention: {} days)",
+            deleted,
+            retention.default_days
+        );
+    }
+
+    Ok(deleted)
+}
+
+/// Get offline volumes with their pending cleanup time.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `retention` - Days offline volume data is retained, per volume
+pub fn get_offline_volumes(conn: &Connection, retention: impl Into<RetentionPolicy>) -> Result<Vec<OfflineVolume>> {
+    let retention = retention.into();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, drive_letter, volume_serial, fs_type, COALESCE(offline_since, 0),
+                    id IN (SELECT volume_id FROM kept_volumes)
+             FROM volumes WHERE state = 'offline' ORDER BY drive_letter",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare offline volumes query: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            let drive_letter: String = row.get(1)?;
+            let offline_since: i64 = row.get(4)?;
+            let keep_forever: bool = row.get(5)?;
+            let retention_days = retention.days(&drive_letter);
+            Ok(OfflineVolume {
+                volume: VolumeInfo {
+                    id: row.get(0)?,
+                    drive_letter,
+                    volume_serial: row.get(2)?,
+                    fs_type: row.get(3)?,
+                },
+                offline_since,
+                keep_forever,
+                purge_after: (!keep_forever).then_some(offline_since + retention_days as i64 * 86400),
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query offline volumes: {}", e)))?;
+
+    let mut volumes = Vec::new();
+    for row in rows {
+        volumes.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
+    }
+
+    Ok(volumes)
+}
+
+/// Keep a volume's index forever when offline, or return it to normal retention.
+pub fn set_volume_kept(conn: &Connection, volume_id: i64, keep: bool) -> Result<()> {
+    let sql = if keep {
+        "INSERT OR IGNORE INTO kept_volumes (volume_id) VALUES (?1)"
+    } else {
+        "DELETE FROM kept_volumes WHERE volume_id = ?1"
+    };
+    conn.execute(sql, params![volume_id])
+        .map_err(|e| FFIError::Database(format!("Failed to update volume retention: {}", e)))?;
+
+    Ok(())
+}
+
+/// Delete a volume with its files and per-volume bookkeeping.
+///
+/// # Returns
+/// The number of file entries deleted.
+pub fn delete_volume(conn: &mut Connection, volume_id: i64) -> Result<usize> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+    let deleted = tx
+        .execute("DELETE FROM files WHERE volume_id = ?1", params![volume_id])
+        .map_err(|e| FFIError::Database(format!("Failed to delete volume files: {}", e)))?;
+
+    for table in [
+        "skipped_paths",
+        "dir_churn",
+        "exclusion_suggestions",
+        "facet_counts",
+        "kept_volumes",
+        "recycled_items",
+    ] {
+        tx.execute(&format!("DELETE FROM {} WHERE volume_id = ?1", table), params![volume_id])
+            .map_err(|e| FFIError::Database(format!("Failed to delete volume {}: {}", table, e)))?;
     }
 
+    tx.execute("DELETE FROM volumes WHERE id = ?1", params![volume_id])
+        .map_err(|e| FFIError::Database(format!("Failed to delete volume: {}", e)))?;
+
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit volume removal: {}", e)))?;
+
     Ok(deleted)
 }
 
@@ -330,8 +666,11 @@ pub fn get_volume_serial(_drive_letter: char) -> Option<u32> {
 /// Uses BATCH_SIZE (100,000) records per transaction as recommended
 /// by SQLite benchmarks. Uses prepared cached statements for efficiency.
 ///
+/// A file whose `(volume_id, file_ref)` already exists is updated in place,
+/// keeping its row ID, so rescans preserve file identity.
+///
 /// # Returns
-/// The number of files successfully inserted.
+/// The number of files successfully inserted or updated.
 pub fn batch_insert_files(conn: &mut Connection, files: &[FileEntry]) -> Result<usize> {
     let mut total_inserted = 0;
 
@@ -340,29 +679,7 @@ pub fn batch_insert_files(conn: &mut Connection, files: &[FileEntry]) -> Result<
             .transaction()
             .map_err(|e| FFIError::Database(format!("Failed to start transaction: {}", e)))?;
 
-        {
-            let mut stmt = tx
-                .prepare_cached(
-                    "INSERT INTO files (volume_id, file_ref, parent_ref, name, size, modified, is_dir)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                )
-                .map_err(|e| FFIError::Database(format!("Failed to prepare statement: {}", e)))?;
-
-            for file in chunk {
-                stmt.execute(params![
-                    file.volume_id,
-                    file.file_ref,
-                    file.parent_ref,
-                    file.name,
-                    file.size,
-                    file.modified,
-                    file.is_dir as i32,
-                ])
-                .map_err(|e| FFIError::Database(format!("Failed to insert file: {}", e)))?;
-
-                total_inserted += 1;
-            }
-        }
+        total_inserted += upsert_files(&tx, chunk)?;
 
         tx.commit()
             .map_err(|e| FFIError::Database(format!("Failed to commit transaction: {}", e)))?;
@@ -371,136 +688,1171 @@ pub fn batch_insert_files(conn: &mut Connection, files: &[FileEntry]) -> Result<
     Ok(total_inserted)
 }
 
-/// Delete all files for a volume.
+/// Insert or update files by `(volume_id, file_ref)` and refresh their paths.
 ///
-/// # Returns
-/// The number of files deleted.
-pub fn delete_volume_files(conn: &Connection, volume_id: i64) -> Result<usize> {
-    let deleted = conn
-        .execute("DELETE FROM files WHERE volume_id = ?1", params![volume_id])
-        .map_err(|e| FFIError::Database(format!("Failed to delete files: {}", e)))?;
+/// Marks the cached facet counts of the touched volumes stale until the
+/// caller rebuilds them.
+fn upsert_files(conn: &Connection, files: &[FileEntry]) -> Result<usize> {
+    let mut stmt = conn
+        .prepare_cached(
+            "INSERT INTO files
+                 (volume_id, file_ref, parent_ref, name, size, modified, created, is_dir, ext, attributes, link,
+                  link_target, stream)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+             ON CONFLICT(volume_id, file_ref, link, stream) DO UPDATE SET
+                 parent_ref = excluded.parent_ref,
+                 name = excluded.name,
+                 size = excluded.size,
+                 modified = excluded.modified,
+                 created = excluded.created,
+                 is_dir = excluded.is_dir,
+                 ext = excluded.ext,
+                 attributes = excluded.attributes,
+                 link_target = excluded.link_target",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare statement: {}", e)))?;
+
+    for file in files {
+        stmt.execute(params![
+            file.volume_id,
+            file.file_ref,
+            file.parent_ref,
+            file.name,
+            file.size,
+            file.modified,
+            file.created,
+            file.is_dir as i32,
+            file_extension(&file.name),
+            file.attributes,
+            file.link,
+            file.link_target,
+            file.stream.as_deref().unwrap_or(""),
+        ])
+        .map_err(|e| FFIError::Database(format!("Failed to insert file: {}", e)))?;
+    }
 
-    Ok(deleted)
+    // Cached facet counts are stale until the scan rebuilds them
+    let volume_ids: HashSet<i64> = files.iter().map(|f| f.volume_id).collect();
+    for volume_id in volume_ids {
+        invalidate_facet_counts(conn, volume_id)?;
+
+        let file_refs: Vec<i64> = files
+            .iter()
+            .filter(|f| f.volume_id == volume_id)
+            .filter_map(|f| f.file_ref)
+            .collect();
+        refresh_full_paths(conn, volume_id, &file_refs)?;
+    }
+
+    Ok(files.len())
 }
 
-/// Search files by name (case-insensitive LIKE search).
+/// Mark a volume's cached facet counts stale.
+fn invalidate_facet_counts(conn: &Connection, volume_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE volumes SET facets_valid = 0 WHERE id = ?1 AND facets_valid = 1",
+        params![volume_id],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to invalidate facet counts: {}", e)))?;
+    Ok(())
+}
+
+/// The fields of an indexed file a reconciliation compares with the disk.
+#[derive(Debug, Clone)]
+pub struct FileSignature {
+    /// Parent file reference
+    pub parent_ref: Option<i64>,
+    /// Filename only (not full path)
+    pub name: String,
+    /// File size in bytes
+    pub size: i64,
+    /// Last modified time as Unix timestamp
+    pub modified: Option<i64>,
+    /// Whether this is a directory
+    pub is_dir: bool,
+    /// Windows `FILE_ATTRIBUTE_*` flags
+    pub attributes: u32,
+    /// Where a symlink or junction points
+    pub link_target: Option<String>,
+    /// Stored path relative to the volume root, if resolved
+    pub full_path: Option<String>,
+}
+
+impl FileSignature {
+    /// Whether a freshly scanned entry matches the indexed row.
+    pub fn matches(&self, entry: &FileEntry) -> bool {
+        self.parent_ref == entry.parent_ref
+            && self.name == entry.name
+            && self.size == entry.size
+            && self.modified == entry.modified
+            && self.is_dir == entry.is_dir
+            && self.attributes == entry.attributes
+            && self.link_target == entry.link_target
+    }
+}
+
+/// Load the signatures of a volume's files, keyed by file reference.
+///
+/// Used by reconciliation to diff a directory walk against the index.
+pub fn get_file_signatures(conn: &Connection, volume_id: i64) -> Result<HashMap<i64, FileSignature>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT file_ref, parent_ref, name, size, modified, is_dir, attributes, link_target, full_path
+             FROM files WHERE volume_id = ?1 AND file_ref IS NOT NULL AND link = 0 AND stream = ''",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare statement: {}", e)))?;
+
+    let rows = stmt
+        .query_map(params![volume_id], signature_row)
+        .map_err(|e| FFIError::Database(format!("Failed to load file signatures: {}", e)))?;
+
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read file signature: {}", e)))
+}
+
+/// Load the signatures of a directory and everything indexed below it,
+/// keyed by file reference.
+///
+/// Used to diff a walk of part of a volume against the index. The subtree
+/// follows parent references, so entries with a stale `full_path` are
+/// included and entries elsewhere with a matching path are not.
 ///
 /// # Arguments
 /// * `conn` - Database connection
-/// * `query` - Search query (will be wrapped in %...%)
-/// * `limit` - Maximum number of results to return
-pub fn search_files(conn: &Connection, query: &str, limit: usize) -> Result<Vec<FileEntry>> {
-    let pattern = format!("%{}%", query);
-
+/// * `volume_id` - Volume the directory belongs to
+/// * `dir_ref` - Reference of the directory (0 for the root of a walked volume)
+pub fn get_subtree_signatures(conn: &Connection, volume_id: i64, dir_ref: i64) -> Result<HashMap<i64, FileSignature>> {
     let mut stmt = conn
-        .prepare_cached(
-            "SELECT volume_id, file_ref, parent_ref, name, size, modified, is_dir
-             FROM files
-             WHERE name LIKE ?1
-             LIMIT ?2",
+        .prepare(
+            "WITH RECURSIVE subtree(file_ref) AS (
+                 SELECT ?2
+                 UNION
+                 SELECT c.file_ref FROM subtree
+                 JOIN files c ON c.volume_id = ?1 AND c.parent_ref = subtree.file_ref
+                     AND c.file_ref <> c.parent_ref AND c.link = 0 AND c.stream = ''
+             )
+             SELECT f.file_ref, f.parent_ref, f.name, f.size, f.modified, f.is_dir, f.attributes, f.link_target,
+                    f.full_path
+             FROM subtree JOIN files f ON f.volume_id = ?1 AND f.file_ref = subtree.file_ref
+             WHERE f.link = 0 AND f.stream = ''",
         )
-        .map_err(|e| FFIError::Database(format!("Failed to prepare search: {}", e)))?;
+        .map_err(|e| FFIError::Database(format!("Failed to prepare statement: {}", e)))?;
 
     let rows = stmt
-        .query_map(params![pattern, limit as i64], |row| {
-            Ok(FileEntry {
-                volume_id: row.get(0)?,
-                file_ref: row.get(1)?,
-                parent_ref: row.get(2)?,
-                name: row.get(3)?,
-                size: row.get(4)?,
-                modified: row.get(5)?,
-                is_dir: row.get::<_, i32>(6)? != 0,
-            })
-        })
-        .map_err(|e| FFIError::Database(format!("Failed to execute search: {}", e)))?;
+        .query_map(params![volume_id, dir_ref], signature_row)
+        .map_err(|e| FFIError::Database(format!("Failed to load file signatures: {}", e)))?;
 
-    let mut results = Vec::new();
-    for row in rows {
-        results.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
-    }
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read file signature: {}", e)))
+}
 
-    Ok(results)
+/// Read a file reference and signature selected in the column order of
+/// [`get_file_signatures`].
+fn signature_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<(i64, FileSignature)> {
+    Ok((
+        row.get(0)?,
+        FileSignature {
+            parent_ref: row.get(1)?,
+            name: row.get(2)?,
+            size: row.get(3)?,
+            modified: row.get(4)?,
+            is_dir: row.get(5)?,
+            attributes: row.get(6)?,
+            link_target: row.get(7)?,
+            full_path: row.get(8)?,
+        },
+    ))
 }
 
-/// Get the total count of files in the database.
+/// Apply a reconciliation diff to a volume in one transaction.
 ///
 /// # Arguments
 /// * `conn` - Database connection
-/// * `volume_id` - Optional volume ID to filter by
-pub fn get_file_count(conn: &Connection, volume_id: Option<i64>) -> Result<i64> {
-    let count = if let Some(vid) = volume_id {
-        conn.query_row(
-            "SELECT COUNT(*) FROM files WHERE volume_id = ?1",
-            params![vid],
-            |row| row.get(0),
+/// * `volume_id` - Volume the files belong to
+/// * `upserts` - New and changed files, written like [`batch_insert_files`]
+/// * `deletes` - References of files no longer on the volume
+///
+/// # Returns
+/// The number of rows written and deleted.
+pub fn apply_file_diff(
+    conn: &mut Connection,
+    volume_id: i64,
+    upserts: &[FileEntry],
+    deletes: &[i64],
+) -> Result<usize> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to start transaction: {}", e)))?;
+
+    let mut applied = upsert_files(&tx, upserts)?;
+    if !deletes.is_empty() {
+        let mut stmt = tx
+            .prepare_cached("DELETE FROM files WHERE volume_id = ?1 AND file_ref = ?2")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare statement: {}", e)))?;
+        for file_ref in deletes {
+            applied += stmt
+                .execute(params![volume_id, file_ref])
+                .map_err(|e| FFIError::Database(format!("Failed to delete file: {}", e)))?;
+        }
+        drop(stmt);
+        invalidate_facet_counts(&tx, volume_id)?;
+    }
+
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit transaction: {}", e)))?;
+    Ok(applied)
+}
+
+/// Links (reparse points) on a volume whose target is not recorded.
+///
+/// NTFS scans only see the reparse flag, so the service resolves targets
+/// through the filesystem afterwards.
+///
+/// # Returns
+/// The file reference and stored path of each such link with a known path.
+pub fn get_unresolved_links(conn: &Connection, volume_id: i64) -> Result<Vec<(i64, String)>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT file_ref, full_path FROM files
+             WHERE volume_id = ?1 AND link = 0 AND stream = '' AND (attributes & ?2) != 0
+               AND link_target IS NULL AND file_ref IS NOT NULL AND full_path IS NOT NULL",
         )
-    } else {
-        conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))
-    };
+        .map_err(|e| FFIError::Database(format!("Failed to prepare statement: {}", e)))?;
 
-    count.map_err(|e| FFIError::Database(format!("Failed to count files: {}", e)))
+    let rows = stmt
+        .query_map(params![volume_id, FILE_ATTRIBUTE_REPARSE_POINT], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| FFIError::Database(format!("Failed to load links: {}", e)))?;
+
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read link: {}", e)))
 }
 
-/// Reconstruct the full path for a file by walking the parent_ref chain.
+/// Record the targets of links in one transaction.
 ///
 /// # Arguments
 /// * `conn` - Database connection
-/// * `volume_id` - Volume the file belongs to
-/// * `file_ref` - File reference to reconstruct path for
+/// * `volume_id` - Volume the links belong to
+/// * `targets` - File reference and target of each link
 ///
 /// # Returns
-/// The reconstructed path starting from root
-pub fn reconstruct_path(conn: &Connection, volume_id: i64, file_ref: i64) -> Result<PathBuf> {
-    let mut components: Vec<String> = Vec::new();
-    let mut current_ref = Some(file_ref);
-
-    // Walk up the parent chain
-    while let Some(ref_num) = current_ref {
-        let result = conn.query_row(
-            "SELECT name, parent_ref FROM files WHERE volume_id = ?1 AND file_ref = ?2",
-            params![volume_id, ref_num],
-            |row| {
-                let name: String = row.get(0)?;
-                let parent: Option<i64> = row.get(1)?;
-                Ok((name, parent))
-            },
-        );
-
-        match result {
-            Ok((name, parent)) => {
-                // Don't add root node name (usually empty or ".")
-                if !name.is_empty() && name != "." {
-                    components.push(name);
-                }
-                current_ref = parent;
-            }
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                // Reached root or broken chain
-                break;
-            }
-            Err(e) => {
-                return Err(FFIError::Database(format!("Failed to reconstruct path: {}", e)));
-            }
+/// The number of rows updated.
+pub fn set_link_targets(conn: &mut Connection, volume_id: i64, targets: &[(i64, String)]) -> Result<usize> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to start transaction: {}", e)))?;
+
+    let mut updated = 0;
+    {
+        let mut stmt = tx
+            .prepare_cached("UPDATE files SET link_target = ?1 WHERE volume_id = ?2 AND file_ref = ?3 AND link = 0 AND stream = ''")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare statement: {}", e)))?;
+        for (file_ref, target) in targets {
+            updated += stmt
+                .execute(params![target, volume_id, file_ref])
+                .map_err(|e| FFIError::Database(format!("Failed to record link target: {}", e)))?;
         }
     }
 
-    // Reverse to get path from root to file
-    components.reverse();
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit transaction: {}", e)))?;
+    Ok(updated)
+}
 
-    // Build path
-    let mut path = PathBuf::new();
-    for component in components {
-        path.push(component);
-    }
+/// Delete all files for a volume.
+///
+/// # Returns
+/// The number of files deleted.
+pub fn delete_volume_files(conn: &Connection, volume_id: i64) -> Result<usize> {
+    let deleted = conn
+        .execute("DELETE FROM files WHERE volume_id = ?1", params![volume_id])
+        .map_err(|e| FFIError::Database(format!("Failed to delete files: {}", e)))?;
 
-    Ok(path)
+    Ok(deleted)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::db::schema;
+/// Start recording which entries a full scan sees.
+///
+/// Rescans update existing rows in place; entries the scan never reports
+/// are removed afterwards by [`finish_scan_tracking`]. Seen references,
+/// link numbers and stream names are kept per volume in a temp table on
+/// this connection, so a hard link or stream removed since the last scan
+/// goes too, and scans of different volumes can share the connection.
+pub fn begin_scan_tracking(conn: &Connection, volume_id: i64) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS scan_seen (
+             volume_id INTEGER, file_ref INTEGER, link INTEGER, stream TEXT,
+             PRIMARY KEY (volume_id, file_ref, link, stream)
+         );",
+    )
+    .and_then(|_| conn.execute("DELETE FROM temp.scan_seen WHERE volume_id = ?1", params![volume_id]))
+    .map_err(|e| FFIError::Database(format!("Failed to prepare scan tracking: {}", e)))?;
+    Ok(())
+}
+
+/// Record entries written by a scan started with [`begin_scan_tracking`].
+pub fn mark_scanned(conn: &mut Connection, files: &[FileEntry]) -> Result<()> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to start transaction: {}", e)))?;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT OR IGNORE INTO temp.scan_seen (volume_id, file_ref, link, stream) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to prepare statement: {}", e)))?;
+        for file in files.iter().filter(|f| f.file_ref.is_some()) {
+            stmt.execute(params![file.volume_id, file.file_ref, file.link, file.stream.as_deref().unwrap_or("")])
+                .map_err(|e| FFIError::Database(format!("Failed to record scanned file: {}", e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit transaction: {}", e)))
+}
+
+/// Stop tracking a scan, deleting the volume's entries it did not see.
+///
+/// Entries are only deleted when the scan ran to completion; an interrupted
+/// scan leaves the volume as it was, apart from the rows it updated.
+///
+/// # Returns
+/// The number of files deleted.
+pub fn finish_scan_tracking(conn: &Connection, volume_id: i64, complete: bool) -> Result<usize> {
+    let deleted = if complete {
+        conn.execute(
+            "DELETE FROM files
+             WHERE volume_id = ?1 AND file_ref IS NOT NULL
+               AND NOT EXISTS (
+                   SELECT 1 FROM temp.scan_seen s
+                   WHERE s.volume_id = files.volume_id AND s.file_ref = files.file_ref
+                     AND s.link = files.link AND s.stream = files.stream
+               )",
+            params![volume_id],
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to delete unscanned files: {}", e)))?
+    } else {
+        0
+    };
+
+    conn.execute("DELETE FROM temp.scan_seen WHERE volume_id = ?1", params![volume_id])
+        .map_err(|e| FFIError::Database(format!("Failed to clear scan tracking: {}", e)))?;
+    Ok(deleted)
+}
+
+/// Get where an interrupted full scan of a volume stopped.
+///
+/// # Returns
+/// The saved checkpoint, or the default (start from the beginning) if
+/// the last scan completed or none ran.
+pub fn get_scan_checkpoint(conn: &Connection, volume_id: i64) -> Result<ScanCheckpoint> {
+    let result = conn.query_row(
+        "SELECT scan_next_record, scan_last_path, scan_percent, scan_processed, scan_total
+         FROM volumes WHERE id = ?1",
+        params![volume_id],
+        |row| {
+            Ok(ScanCheckpoint {
+                next_record: row.get::<_, Option<i64>>(0)?.map(|record| record.max(0) as u64),
+                last_path: row.get(1)?,
+                percent: row.get::<_, Option<i64>>(2)?.unwrap_or(0).clamp(0, 100) as u8,
+                processed: row.get::<_, Option<i64>>(3)?.unwrap_or(0).max(0) as u64,
+                total: row.get::<_, Option<i64>>(4)?.unwrap_or(0).max(0) as u64,
+            })
+        },
+    );
+
+    match result {
+        Ok(checkpoint) => Ok(checkpoint),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(ScanCheckpoint::default()),
+        Err(e) => Err(FFIError::Database(format!("Failed to get scan checkpoint: {}", e))),
+    }
+}
+
+/// Save how far a full scan of a volume got.
+///
+/// The checkpoint must only cover entries already written, so a scan
+/// resumed from it misses nothing.
+pub fn save_scan_checkpoint(conn: &Connection, volume_id: i64, checkpoint: &ScanCheckpoint) -> Result<()> {
+    conn.execute(
+        "UPDATE volumes SET scan_next_record = ?1, scan_last_path = ?2, scan_percent = ?3,
+             scan_processed = ?4, scan_total = ?5
+         WHERE id = ?6",
+        params![
+            checkpoint.next_record.map(|record| record as i64),
+            checkpoint.last_path,
+            checkpoint.percent,
+            checkpoint.processed as i64,
+            checkpoint.total as i64,
+            volume_id
+        ],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to save scan checkpoint: {}", e)))?;
+    Ok(())
+}
+
+/// Forget a volume's scan checkpoint once the scan has completed.
+pub fn clear_scan_checkpoint(conn: &Connection, volume_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE volumes SET scan_next_record = NULL, scan_last_path = NULL, scan_percent = NULL,
+             scan_processed = NULL, scan_total = NULL
+         WHERE id = ?1",
+        params![volume_id],
+    )
+        .map_err(|e| FFIError::Database(format!("Failed to clear scan checkpoint: {}", e)))?;
+    Ok(())
+}
+
+/// Record an access-denied failure for a directory.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume the directory belongs to
+/// * `path` - Full path of the directory
+/// * `error` - Error message to store
+///
+/// # Returns
+/// The number of consecutive failures recorded for the path.
+pub fn record_path_failure(conn: &Connection, volume_id: i64, path: &str, error: &str) -> Result<i64> {
+    conn.query_row(
+        "INSERT INTO skipped_paths (volume_id, path, error, failures, first_failed, last_failed)
+         VALUES (?1, ?2, ?3, 1, strftime('%s', 'now'), strftime('%s', 'now'))
+         ON CONFLICT(volume_id, path) DO UPDATE SET
+             error = excluded.error,
+             failures = failures + 1,
+             last_failed = excluded.last_failed
+         RETURNING failures",
+        params![volume_id, path, error],
+        |row| row.get(0),
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to record path failure: {}", e)))
+}
+
+/// Forget a directory's failures once it can be read again.
+pub fn clear_path_failure(conn: &Connection, volume_id: i64, path: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM skipped_paths WHERE volume_id = ?1 AND path = ?2",
+        params![volume_id, path],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to clear path failure: {}", e)))?;
+
+    Ok(())
+}
+
+/// Get recorded access-denied directories, for one volume or all volumes.
+pub fn get_skipped_paths(conn: &Connection, volume_id: Option<i64>) -> Result<Vec<SkippedPath>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT volume_id, path, error, failures, first_failed, last_failed
+             FROM skipped_paths
+             WHERE ?1 IS NULL OR volume_id = ?1
+             ORDER BY volume_id, path",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare skipped paths query: {}", e)))?;
+
+    let rows = stmt
+        .query_map(params![volume_id], |row| {
+            Ok(SkippedPath {
+                volume_id: row.get(0)?,
+                path: row.get(1)?,
+                error: row.get(2)?,
+                failures: row.get(3)?,
+                first_failed: row.get(4)?,
+                last_failed: row.get(5)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query skipped paths: {}", e)))?;
+
+    let mut paths = Vec::new();
+    for row in rows {
+        paths.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
+    }
+
+    Ok(paths)
+}
+
+/// Record that a file was opened from the search UI.
+///
+/// Only the [`MAX_OPEN_HISTORY`] most recently opened files are kept, each
+/// with the times of its [`MAX_OPEN_SAMPLES`] latest opens.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `path` - Full path of the opened file
+/// * `now` - Unix timestamp of the open
+pub fn record_open(conn: &Connection, path: &str, now: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO open_history (path, open_count, last_opened) VALUES (?1, 1, ?2)
+         ON CONFLICT(path) DO UPDATE SET
+             open_count = open_count + 1,
+             last_opened = excluded.last_opened",
+        params![path.to_lowercase(), now],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to record open: {}", e)))?;
+    conn.execute(
+        "INSERT INTO open_events (path, opened_at) VALUES (?1, ?2)",
+        params![path.to_lowercase(), now],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to record open event: {}", e)))?;
+
+    conn.execute(
+        "DELETE FROM open_history WHERE path NOT IN
+             (SELECT path FROM open_history ORDER BY last_opened DESC LIMIT ?1)",
+        params![MAX_OPEN_HISTORY as i64],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to prune open history: {}", e)))?;
+    conn.execute(
+        "DELETE FROM open_events WHERE path NOT IN (SELECT path FROM open_history)
+            OR (path = ?1 AND rowid NOT IN
+                (SELECT rowid FROM open_events WHERE path = ?1 ORDER BY opened_at DESC LIMIT ?2))",
+        params![path.to_lowercase(), MAX_OPEN_SAMPLES as i64],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to prune open events: {}", e)))?;
+
+    Ok(())
+}
+
+/// Get the recorded open history, most recent first.
+pub fn get_open_history(conn: &Connection) -> Result<Vec<OpenRecord>> {
+    let mut stmt = conn
+        .prepare_cached("SELECT path, open_count, last_opened FROM open_history ORDER BY last_opened DESC")
+        .map_err(|e| FFIError::Database(format!("Failed to prepare open history query: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(OpenRecord {
+                path: row.get(0)?,
+                open_count: row.get(1)?,
+                last_opened: row.get(2)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query open history: {}", e)))?;
+
+    let mut records = Vec::new();
+    for row in rows {
+        records.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
+    }
+
+    Ok(records)
+}
+
+/// Get the times of each file's latest opens, newest first, keyed by
+/// lowercase path.
+///
+/// Files opened before open times were recorded have none; their
+/// [`OpenRecord::last_opened`] stands in.
+pub fn get_open_samples(conn: &Connection) -> Result<HashMap<String, Vec<i64>>> {
+    let mut stmt = conn
+        .prepare_cached("SELECT path, opened_at FROM open_events ORDER BY path, opened_at DESC")
+        .map_err(|e| FFIError::Database(format!("Failed to prepare open events query: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| FFIError::Database(format!("Failed to query open events: {}", e)))?;
+
+    let mut samples: HashMap<String, Vec<i64>> = HashMap::new();
+    for row in rows {
+        let (path, opened_at) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+        samples.entry(path).or_default().push(opened_at);
+    }
+
+    Ok(samples)
+}
+
+/// Save a named query, replacing the query of a search with the same name.
+///
+/// Names are compared case-insensitively; a replaced search keeps its
+/// place among the pinned chips.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `name` - Name of the search
+/// * `query` - Search query, as typed
+/// * `now` - Unix timestamp of the save
+pub fn save_search(conn: &Connection, name: &str, query: &str, now: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO saved_searches (name, query, created) VALUES (?1, ?2, ?3)
+         ON CONFLICT(name) DO UPDATE SET name = excluded.name, query = excluded.query",
+        params![name, query, now],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to save search: {}", e)))?;
+
+    Ok(())
+}
+
+/// Get the saved searches, oldest first.
+pub fn get_saved_searches(conn: &Connection) -> Result<Vec<SavedSearch>> {
+    let mut stmt = conn
+        .prepare_cached("SELECT name, query, created FROM saved_searches ORDER BY created, name")
+        .map_err(|e| FFIError::Database(format!("Failed to prepare saved search query: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(SavedSearch {
+                name: row.get(0)?,
+                query: row.get(1)?,
+                created: row.get(2)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query saved searches: {}", e)))?;
+
+    let mut searches = Vec::new();
+    for row in rows {
+        searches.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
+    }
+
+    Ok(searches)
+}
+
+/// Delete a saved search by name (case-insensitive).
+///
+/// # Returns
+/// Whether a search with that name existed.
+pub fn delete_saved_search(conn: &Connection, name: &str) -> Result<bool> {
+    let deleted = conn
+        .execute("DELETE FROM saved_searches WHERE name = ?1", params![name])
+        .map_err(|e| FFIError::Database(format!("Failed to delete saved search: {}", e)))?;
+
+    Ok(deleted > 0)
+}
+
+/// Get information for all volumes, ordered by drive letter.
+pub fn get_all_volumes(conn: &Connection) -> Result<Vec<VolumeInfo>> {
+    let mut stmt = conn
+        .prepare("SELECT id, drive_letter, volume_serial, fs_type FROM volumes ORDER BY drive_letter")
+        .map_err(|e| FFIError::Database(format!("Failed to prepare volumes query: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(VolumeInfo {
+                id: row.get(0)?,
+                drive_letter: row.get(1)?,
+                volume_serial: row.get(2)?,
+                fs_type: row.get(3)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query volumes: {}", e)))?;
+
+    let mut volumes = Vec::new();
+    for row in rows {
+        volumes.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
+    }
+
+    Ok(volumes)
+}
+
+/// Search files by name (case-insensitive LIKE search).
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `query` - Search query (will be wrapped in %...%)
+/// * `limit` - Maximum number of results to return
+pub fn search_files(conn: &Connection, query: &str, limit: usize) -> Result<Vec<FileEntry>> {
+    search_files_sorted(conn, query, limit, 0, &[])
+}
+
+/// Search files by name with an explicit sort order.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `query` - Search query (will be wrapped in %...%)
+/// * `limit` - Maximum number of results to return
+/// * `offset` - Number of matches to skip, for later pages
+/// * `sort` - Sort keys, primary first (empty = name order)
+pub fn search_files_sorted(
+    conn: &Connection,
+    query: &str,
+    limit: usize,
+    offset: usize,
+    sort: &[SortSpec],
+) -> Result<Vec<FileEntry>> {
+    let pattern = format!("%{}%", query);
+
+    // Candidates come from the trigram name index when the query has a
+    // long enough literal run; LIKE still decides the match
+    let fts_query = fts_name_query(query, &['%', '_']);
+    let sql = format!(
+        "SELECT volume_id, file_ref, parent_ref, name, size, modified, is_dir, created, attributes,
+                link, link_target, NULLIF(stream, '')
+         FROM files
+         WHERE name LIKE ?{}
+         ORDER BY {}
+         LIMIT ? OFFSET ?",
+        if fts_query.is_some() { format!(" AND {}", NAME_INDEX_SQL) } else { String::new() },
+        order_by_clause(sort)
+    );
+
+    let mut stmt = conn
+        .prepare_cached(&sql)
+        .map_err(|e| FFIError::Database(format!("Failed to prepare search: {}", e)))?;
+
+    let mut search_params: Vec<&dyn rusqlite::ToSql> = vec![&pattern];
+    if let Some(fts_query) = &fts_query {
+        search_params.push(fts_query);
+    }
+    let (limit, offset) = (limit as i64, offset as i64);
+    search_params.push(&limit);
+    search_params.push(&offset);
+
+    let rows = stmt
+        .query_map(search_params.as_slice(), |row| {
+            Ok(FileEntry {
+                volume_id: row.get(0)?,
+                file_ref: row.get(1)?,
+                parent_ref: row.get(2)?,
+                name: row.get(3)?,
+                size: row.get(4)?,
+                modified: row.get(5)?,
+                created: row.get(7)?,
+                is_dir: row.get::<_, i32>(6)? != 0,
+                attributes: row.get(8)?,
+                link: row.get(9)?,
+                link_target: row.get(10)?,
+                stream: row.get(11)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to execute search: {}", e)))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?);
+    }
+
+    Ok(results)
+}
+
+/// Get the total count of files in the database.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Optional volume ID to filter by
+pub fn get_file_count(conn: &Connection, volume_id: Option<i64>) -> Result<i64> {
+    let count = if let Some(vid) = volume_id {
+        conn.query_row(
+            "SELECT COUNT(*) FROM files WHERE volume_id = ?1",
+            params![vid],
+            |row| row.get(0),
+        )
+    } else {
+        conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))
+    };
+
+    count.map_err(|e| FFIError::Database(format!("Failed to count files: {}", e)))
+}
+
+/// Recount a volume's files, directories and bytes and store them with the
+/// duration of the scan that produced them.
+///
+/// Called once after a full scan, so the aggregate runs per scan rather
+/// than per status request.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume database ID
+/// * `scan_duration_ms` - How long the full scan took
+pub fn update_volume_stats(conn: &Connection, volume_id: i64, scan_duration_ms: i64) -> Result<VolumeStats> {
+    conn.execute(
+        "UPDATE volumes SET
+             file_count = (SELECT COUNT(*) FROM files WHERE volume_id = ?1 AND is_dir = 0),
+             dir_count = (SELECT COUNT(*) FROM files WHERE volume_id = ?1 AND is_dir = 1),
+             total_bytes = (SELECT COALESCE(SUM(size), 0) FROM files WHERE volume_id = ?1 AND is_dir = 0),
+             last_scan_duration_ms = ?2
+         WHERE id = ?1",
+        params![volume_id, scan_duration_ms],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to update volume stats: {}", e)))?;
+
+    get_volume_stats(conn, volume_id)
+}
+
+/// Record which limits left entries of a volume out of the index.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - The volume
+/// * `truncated` - The limits that applied (e.g. "max_depth = 3"), or None
+///   if everything was indexed
+pub fn set_volume_truncated(conn: &Connection, volume_id: i64, truncated: Option<&str>) -> Result<()> {
+    conn.execute("UPDATE volumes SET truncated = ?2 WHERE id = ?1", params![volume_id, truncated])
+        .map_err(|e| FFIError::Database(format!("Failed to record volume truncation: {}", e)))?;
+    Ok(())
+}
+
+/// Get the counters stored for a volume by its last full scan.
+///
+/// # Returns
+/// The stored stats (all zero if the volume was never fully scanned), or
+/// error if the volume is not found.
+pub fn get_volume_stats(conn: &Connection, volume_id: i64) -> Result<VolumeStats> {
+    conn.query_row(
+        "SELECT file_count, dir_count, total_bytes, last_scan_duration_ms, last_scan_time, truncated
+         FROM volumes WHERE id = ?1",
+        params![volume_id],
+        |row| {
+            Ok(VolumeStats {
+                file_count: row.get(0)?,
+                dir_count: row.get(1)?,
+                total_bytes: row.get(2)?,
+                last_scan_duration_ms: row.get(3)?,
+                last_scan_time: row.get(4)?,
+                truncated: row.get(5)?,
+            })
+        },
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => FFIError::Database("Volume not found".to_string()),
+        e => FFIError::Database(format!("Failed to get volume stats: {}", e)),
+    })
+}
+
+/// Fetch the files matching a parsed search query, filters included.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `parsed` - Parsed query (pattern, filters, conditions and sort)
+/// * `limit` - Maximum number of results to return
+pub fn query_files(conn: &Connection, parsed: &ParsedQuery, limit: usize) -> Result<Vec<FileEntry>> {
+    query_files_page(conn, parsed, limit, 0)
+}
+
+/// Fetch one page of the files matching a parsed search query.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `parsed` - Parsed query (pattern, filters, conditions and sort)
+/// * `limit` - Maximum number of results to return
+/// * `offset` - Number of matches to skip, for later pages
+pub fn query_files_page(
+    conn: &Connection,
+    parsed: &ParsedQuery,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<FileEntry>> {
+    let (sql, params) = build_sql_query_page(parsed, limit as i64, offset as i64);
+    query_file_rows(conn, &sql, &params)
+}
+
+/// Fetch one page of the files matching a parsed search query, most
+/// relevant names first.
+///
+/// Exact names come first, then names starting with the pattern, then
+/// other matches; shorter names first within each group. This picks the
+/// page a [`RelevanceRanker`](crate::search::RelevanceRanker) then reorders.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `parsed` - Parsed query (pattern, filters and conditions; the sort is ignored)
+/// * `limit` - Maximum number of results to return
+/// * `offset` - Number of matches to skip, for later pages
+pub fn query_files_by_relevance(
+    conn: &Connection,
+    parsed: &ParsedQuery,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<FileEntry>> {
+    let (sql, params) = build_relevance_query(parsed, limit as i64, offset as i64);
+    query_file_rows(conn, &sql, &params)
+}
+
+/// Run a SELECT built by the search query builder and read its rows.
+fn query_file_rows(conn: &Connection, sql: &str, params: &[SqlParam]) -> Result<Vec<FileEntry>> {
+    let mut stmt = conn
+        .prepare_cached(sql)
+        .map_err(|e| FFIError::Database(format!("Failed to prepare search: {}", e)))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(FileEntry {
+                volume_id: row.get(1)?,
+                file_ref: row.get(2)?,
+                parent_ref: row.get(3)?,
+                name: row.get(4)?,
+                size: row.get(5)?,
+                modified: row.get(6)?,
+                created: row.get(8)?,
+                is_dir: row.get::<_, i32>(7)? != 0,
+                attributes: row.get(9)?,
+                link: row.get(10)?,
+                link_target: row.get(11)?,
+                stream: row.get(12)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to execute search: {}", e)))?;
+
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))
+}
+
+/// Count the files matching a parsed search query.
+pub fn count_query_matches(conn: &Connection, parsed: &ParsedQuery) -> Result<usize> {
+    let (sql, params) = build_count_query(parsed);
+
+    let mut stmt = conn
+        .prepare_cached(&sql)
+        .map_err(|e| FFIError::Database(format!("Failed to prepare count: {}", e)))?;
+
+    let count: i64 = stmt
+        .query_row(rusqlite::params_from_iter(params.iter()), |row| row.get(0))
+        .map_err(|e| FFIError::Database(format!("Failed to execute count: {}", e)))?;
+
+    Ok(count as usize)
+}
+
+/// Count matches for several parsed queries in one call.
+///
+/// Returns one count per query, in the same order. Used to compute
+/// result count badges for filter suggestions. Single facet filters are
+/// answered from the cached facet counts.
+pub fn count_query_matches_batch(conn: &Connection, queries: &[ParsedQuery]) -> Result<Vec<usize>> {
+    queries
+        .iter()
+        .map(|parsed| match cached_query_count(conn, parsed)? {
+            Some(count) => Ok(count),
+            None => count_query_matches(conn, parsed),
+        })
+        .collect()
+}
+
+/// Maximum parent links followed when reconstructing a path.
+///
+/// Far deeper than any real directory tree; only corrupt data gets here.
+pub const MAX_PATH_DEPTH: usize = 1024;
+
+/// A path rebuilt from the parent_ref chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconstructedPath {
+    /// Path from the volume root (or from where the walk stopped)
+    pub path: PathBuf,
+    /// Whether the walk stopped at a parent cycle or [`MAX_PATH_DEPTH`],
+    /// so leading components are missing
+    pub truncated: bool,
+}
+
+/// Reconstruct the full path for a file by walking the parent_ref chain.
+///
+/// A truncated path (see [`reconstruct_path_checked`]) is returned as-is.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume the file belongs to
+/// * `file_ref` - File reference to reconstruct path for
+///
+/// # Returns
+/// The reconstructed path starting from root
+pub fn reconstruct_path(conn: &Connection, volume_id: i64, file_ref: i64) -> Result<PathBuf> {
+    reconstruct_path_checked(conn, volume_id, file_ref).map(|p| p.path)
+}
+
+/// Reconstruct a path, stopping at parent cycles and excessive depth.
+///
+/// Broken parent chains on corrupt data can loop or run thousands of
+/// levels deep. The walk stops at the first repeated reference or after
+/// [`MAX_PATH_DEPTH`] links and flags the partial path as truncated.
+/// A record that is its own parent (the NTFS root) ends the walk normally.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume the file belongs to
+/// * `file_ref` - File reference to reconstruct path for
+pub fn reconstruct_path_checked(
+    conn: &Connection,
+    volume_id: i64,
+    file_ref: i64,
+) -> Result<ReconstructedPath> {
+    let mut stmt = conn
+        .prepare_cached("SELECT name, parent_ref FROM files WHERE volume_id = ?1 AND file_ref = ?2 AND link = 0 AND stream = ''")
+        .map_err(|e| FFIError::Database(format!("Failed to reconstruct path: {}", e)))?;
+
+    let mut components: Vec<String> = Vec::new();
+    let mut visited: HashSet<i64> = HashSet::new();
+    let mut current_ref = Some(file_ref);
+    let mut truncated = false;
+
+    // Walk up the parent chain
+    while let Some(ref_num) = current_ref {
+        if !visited.insert(ref_num) || visited.len() > MAX_PATH_DEPTH {
+            truncated = true;
+            break;
+        }
+
+        let result = stmt.query_row(params![volume_id, ref_num], |row| {
+            let name: String = row.get(0)?;
+            let parent: Option<i64> = row.get(1)?;
+            Ok((name, parent))
+        });
+
+        match result {
+            Ok((name, parent)) => {
+                // Don't add root node name (usually empty or ".")
+                if !name.is_empty() && name != "." {
+                    components.push(name);
+                }
+                // The root directory is its own parent
+                current_ref = parent.filter(|&p| p != ref_num);
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                // Reached root or broken chain
+                break;
+            }
+            Err(e) => {
+                return Err(FFIError::Database(format!("Failed to reconstruct path: {}", e)));
+            }
+        }
+    }
+
+    if truncated {
+        tracing::warn!(
+            "Path reconstruction for file_ref {} on volume {} stopped after {} levels",
+            file_ref,
+            volume_id,
+            components.len()
+        );
+    }
+
+    // Reverse to get path from root to file
+    components.reverse();
+
+    // Build path
+    let mut path = PathBuf::new();
+    for component in components {
+        path.push(component);
+    }
+
+    Ok(ReconstructedPath { path, truncated })
+}
+
+/// Get the stored `full_path` of a file's primary name.
+///
+/// # Returns
+/// The path from the volume root, or None if the file is unknown or its
+/// path could not be resolved (use [`reconstruct_path_checked`] then).
+pub fn get_full_path(conn: &Connection, volume_id: i64, file_ref: i64) -> Result<Option<String>> {
+    conn.prepare_cached("SELECT full_path FROM files WHERE volume_id = ?1 AND file_ref = ?2 AND link = 0 AND stream = ''")
+        .and_then(|mut stmt| {
+            stmt.query_row(params![volume_id, file_ref], |row| row.get(0))
+                .optional()
+        })
+        .map(Option::flatten)
+        .map_err(|e| FFIError::Database(format!("Failed to read full path: {}", e)))
+}
+
+/// Find the file at a path of a volume.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume the path is on
+/// * `path` - Path from the volume root (case-insensitive)
+///
+/// # Returns
+/// The file reference of the file or directory, or None if nothing is
+/// indexed at the path.
+pub fn get_file_ref_by_path(conn: &Connection, volume_id: i64, path: &str) -> Result<Option<i64>> {
+    conn.prepare_cached(
+        "SELECT file_ref FROM files
+         WHERE volume_id = ?1 AND full_path = ?2 COLLATE NOCASE AND stream = '' AND file_ref IS NOT NULL
+         LIMIT 1",
+    )
+    .and_then(|mut stmt| stmt.query_row(params![volume_id, path], |row| row.get(0)).optional())
+    .map_err(|e| FFIError::Database(format!("Failed to find file by path: {}", e)))
+}
+
+/// Recompute `full_path` for files and everything below them.
+///
+/// Called after files are inserted, moved or renamed, so renaming a
+/// directory updates the paths of all its descendants. A file whose parent
+/// is not indexed yet gets a path starting at its own name; the parent's
+/// insert fixes it up later.
+///
+/// # Arguments
+/// * `conn` - Database connection (usually inside a transaction)
+/// * `volume_id` - Volume the files belong to
+/// * `file_refs` - Files whose name or parent changed
+///
+/// # Returns
+/// The number of rows whose path was recomputed.
+pub fn refresh_full_paths(conn: &Connection, volume_id: i64, file_refs: &[i64]) -> Result<usize> {
+    if file_refs.is_empty() {
+        return Ok(0);
+    }
+    prepare_path_seeds(conn)?;
+
+    let mut stmt = conn
+        .prepare_cached("INSERT OR IGNORE INTO temp.path_seeds (file_ref) VALUES (?1)")
+        .map_err(|e| FFIError::Database(format!("Failed to prepare path seeds: {}", e)))?;
+    for file_ref in file_refs {
+        stmt.execute(params![file_ref])
+            .map_err(|e| FFIError::Database(format!("Failed to seed path update: {}", e)))?;
+    }
+
+    update_full_paths_from_seeds(conn, volume_id)
+}
+
+/// Recompute `full_path` for every file on a volume.
+///
+/// Used after bulk loads that bypass [`batch_insert_files`] and when the
+/// column is added to an existing database.
+pub fn rebuild_full_paths(conn: &Connection, volume_id: i64) -> Result<usize> {
+    prepare_path_seeds(conn)?;
+
+    conn.execute(
+        "INSERT OR IGNORE INTO temp.path_seeds (file_ref)
+         SELECT file_ref FROM files WHERE volume_id = ?1 AND file_ref IS NOT NULL",
+        params![volume_id],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to seed path rebuild: {}", e)))?;
+
+    update_full_paths_from_seeds(conn, volume_id)
+}
+
+/// Create (or empty) the temp table of files whose paths need recomputing.
+fn prepare_path_seeds(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS path_seeds (file_ref INTEGER PRIMARY KEY);
+         DELETE FROM temp.path_seeds;",
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to prepare path seeds: {}", e)))
+}
+
+/// Recompute paths from the topmost seeded files down through their descendants.
+///
+/// Seeds whose parent is also seeded are reached by the recursion instead,
+/// so each path is built from an up-to-date parent. Paths below a parent
+/// with a NULL path stay NULL, and the walk stops at [`MAX_PATH_DEPTH`].
+/// Only a directory's own row is a parent, not its stream rows.
+fn update_full_paths_from_seeds(conn: &Connection, volume_id: i64) -> Result<usize> {
+    conn.execute(
+        r"WITH RECURSIVE tree(id, file_ref, stream, path, depth) AS (
+              SELECT f.id, f.file_ref, f.stream,
+                     CASE
+                         WHEN p.id IS NULL THEN CASE WHEN f.name IN ('', '.') THEN '' ELSE f.name END
+                         WHEN p.full_path = '' THEN f.name
+                         ELSE p.full_path || '\' || f.name
+                     END,
+                     1
+              FROM temp.path_seeds s
+              JOIN files f ON f.volume_id = ?1 AND f.file_ref = s.file_ref
+              LEFT JOIN files p ON p.volume_id = ?1 AND p.file_ref = f.parent_ref AND p.file_ref <> f.file_ref
+                  AND p.link = 0 AND p.stream = ''
+              WHERE f.parent_ref IS NULL
+                 OR f.parent_ref = f.file_ref
+                 OR f.parent_ref NOT IN (SELECT file_ref FROM temp.path_seeds)
+              UNION ALL
+              SELECT c.id, c.file_ref, c.stream,
+                     CASE WHEN tree.path = '' THEN c.name ELSE tree.path || '\' || c.name END,
+                     tree.depth + 1
+              FROM tree
+              JOIN files c ON c.volume_id = ?1 AND c.parent_ref = tree.file_ref AND c.file_ref <> c.parent_ref
+              WHERE tree.depth < ?2 AND tree.stream = ''
+          )
+          UPDATE files SET full_path = t.path
+          FROM (SELECT id, path, MIN(depth) FROM tree GROUP BY id) AS t
+          WHERE files.id = t.id",
+        params![volume_id, MAX_PATH_DEPTH as i64],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to update full paths: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
 
     fn setup_test_db() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
@@ -510,9 +1862,57 @@ mod tests {
 
     #[test]
     fn test_insert_volume() {
-        let conn = setup_test_db();
+        let mut conn = setup_test_db();
         let id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
         assert!(id > 0);
+
+        // Rescans update the volume in place, keeping its files and serial
+        batch_insert_files(&mut conn, &[FileEntry {
+            volume_id: id,
+            file_ref: Some(100),
+            parent_ref: Some(5),
+            name: "a.txt".to_string(),
+            size: 1,
+            ..Default::default()
+        }])
+        .unwrap();
+        assert_eq!(insert_volume(&conn, "C:", "", "NTFS").unwrap(), id);
+        assert_eq!(get_volume(&conn, "C:").unwrap().unwrap().volume_serial, "1234-ABCD");
+        assert_eq!(get_file_count(&conn, Some(id)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_volume_identity_by_serial() {
+        let mut conn = setup_test_db();
+        let usb = mount_volume(&conn, "D:", "AAAA0001", "FAT").unwrap();
+        batch_insert_files(&mut conn, &[FileEntry {
+            volume_id: usb,
+            file_ref: Some(100),
+            parent_ref: Some(0),
+            name: "photo.jpg".to_string(),
+            size: 1,
+            ..Default::default()
+        }])
+        .unwrap();
+
+        // Back under another letter: same volume, moved
+        assert_eq!(mount_volume(&conn, "E:", "AAAA0001", "FAT").unwrap(), usb);
+        assert_eq!(get_volume(&conn, "E:").unwrap().unwrap().id, usb);
+        assert!(get_volume(&conn, "D:").unwrap().is_none());
+
+        // Another disk takes the letter; the first keeps its index offline
+        let other = mount_volume(&conn, "E:", "BBBB0002", "FAT").unwrap();
+        assert_ne!(other, usb);
+        assert!(matches!(get_volume_state(&conn, usb).unwrap(), VolumeState::Offline { .. }));
+        assert_eq!(get_volume(&conn, "E:").unwrap().unwrap().id, other);
+
+        // Scanners, which only know the letter, write to the disk there now
+        assert_eq!(insert_volume(&conn, "E:", "", "FAT").unwrap(), other);
+
+        // The first disk returns elsewhere with its files
+        assert_eq!(mount_volume(&conn, "F:", "AAAA0001", "FAT").unwrap(), usb);
+        assert_eq!(get_volume_state(&conn, usb).unwrap(), VolumeState::Online);
+        assert_eq!(get_file_count(&conn, Some(usb)).unwrap(), 1);
     }
 
     #[test]
@@ -548,7 +1948,7 @@ mod tests {
                 name: format!("file_{}.txt", i),
                 size: 1024,
                 modified: Some(1700000000),
-                is_dir: false,
+                ..Default::default()
             })
             .collect();
 
@@ -560,6 +1960,147 @@ mod tests {
         assert_eq!(count, 1000);
     }
 
+    #[test]
+    fn test_batch_insert_files_rescan_keeps_identity() {
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "D:", "1234-ABCD", "FAT").unwrap();
+
+        let mut file = FileEntry {
+            volume_id,
+            file_ref: Some(42),
+            parent_ref: Some(0),
+            name: "notes.txt".to_string(),
+            size: 10,
+            modified: Some(1700000000),
+            ..Default::default()
+        };
+        batch_insert_files(&mut conn, std::slice::from_ref(&file)).unwrap();
+        let id_before: i64 = conn
+            .query_row("SELECT id FROM files WHERE file_ref = 42", [], |row| row.get(0))
+            .unwrap();
+
+        // Rescan with the same ref updates the row in place
+        file.size = 20;
+        batch_insert_files(&mut conn, std::slice::from_ref(&file)).unwrap();
+        let (id_after, size): (i64, i64) = conn
+            .query_row("SELECT id, size FROM files WHERE file_ref = 42", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+
+        assert_eq!(id_after, id_before);
+        assert_eq!(size, 20);
+        assert_eq!(get_file_count(&conn, Some(volume_id)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_scan_tracking_removes_unseen_files() {
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+        let other_id = insert_volume(&conn, "D:", "5678-ABCD", "NTFS").unwrap();
+        let file = |volume_id, file_ref| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(5),
+            name: format!("file_{}.txt", file_ref),
+            size: 1,
+            ..Default::default()
+        };
+        let existing: Vec<FileEntry> = (100..110).map(|i| file(volume_id, i)).collect();
+        batch_insert_files(&mut conn, &existing).unwrap();
+        batch_insert_files(&mut conn, &[file(other_id, 100)]).unwrap();
+
+        // An interrupted rescan deletes nothing
+        begin_scan_tracking(&conn, volume_id).unwrap();
+        mark_scanned(&mut conn, &existing[..3]).unwrap();
+        assert_eq!(finish_scan_tracking(&conn, volume_id, false).unwrap(), 0);
+        assert_eq!(get_file_count(&conn, Some(volume_id)).unwrap(), 10);
+
+        // A complete rescan removes what it did not see, on its volume only,
+        // also while another volume's scan is tracked on the connection
+        begin_scan_tracking(&conn, volume_id).unwrap();
+        begin_scan_tracking(&conn, other_id).unwrap();
+        mark_scanned(&mut conn, &[file(other_id, 100)]).unwrap();
+        let rescanned = vec![file(volume_id, 100), file(volume_id, 105), file(volume_id, 200)];
+        batch_insert_files(&mut conn, &rescanned).unwrap();
+        mark_scanned(&mut conn, &rescanned).unwrap();
+        assert_eq!(finish_scan_tracking(&conn, volume_id, true).unwrap(), 8);
+        assert_eq!(get_file_count(&conn, Some(volume_id)).unwrap(), 3);
+        assert_eq!(finish_scan_tracking(&conn, other_id, true).unwrap(), 0);
+        assert_eq!(get_file_count(&conn, Some(other_id)).unwrap(), 1);
+
+        // A hard link or stream removed since the last scan goes, the file stays
+        let link = FileEntry { link: 1, name: "link.txt".to_string(), ..file(volume_id, 100) };
+        let stream = FileEntry {
+            name: "file_100.txt:Zone.Identifier".to_string(),
+            stream: Some("Zone.Identifier".to_string()),
+            ..file(volume_id, 100)
+        };
+        batch_insert_files(&mut conn, &[link, stream]).unwrap();
+        assert_eq!(get_file_count(&conn, Some(volume_id)).unwrap(), 5);
+        begin_scan_tracking(&conn, volume_id).unwrap();
+        mark_scanned(&mut conn, &rescanned).unwrap();
+        assert_eq!(finish_scan_tracking(&conn, volume_id, true).unwrap(), 2);
+        assert_eq!(get_file_count(&conn, Some(volume_id)).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_scan_checkpoint() {
+        let conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+        assert_eq!(get_scan_checkpoint(&conn, volume_id).unwrap(), ScanCheckpoint::default());
+
+        update_volume_state(&conn, volume_id, VolumeState::Indexing { percent: 0 }).unwrap();
+        let checkpoint = ScanCheckpoint {
+            next_record: Some(65536),
+            last_path: None,
+            percent: 40,
+            processed: 65536,
+            total: 163840,
+        };
+        save_scan_checkpoint(&conn, volume_id, &checkpoint).unwrap();
+        assert_eq!(get_scan_checkpoint(&conn, volume_id).unwrap(), checkpoint);
+        assert_eq!(get_volume_state(&conn, volume_id).unwrap(), VolumeState::Indexing { percent: 40 });
+
+        // A stop in the middle keeps the checkpoint for the next start
+        update_volume_state(&conn, volume_id, VolumeState::Offline { since: 1700000000 }).unwrap();
+        assert_eq!(get_scan_checkpoint(&conn, volume_id).unwrap(), checkpoint);
+
+        clear_scan_checkpoint(&conn, volume_id).unwrap();
+        assert_eq!(get_scan_checkpoint(&conn, volume_id).unwrap(), ScanCheckpoint::default());
+    }
+
+    #[test]
+    fn test_link_targets() {
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+        let entry = |file_ref, name: &str, attributes| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(5),
+            name: name.to_string(),
+            attributes,
+            ..Default::default()
+        };
+        batch_insert_files(&mut conn, &[
+            entry(5, ".", 0),
+            entry(100, "notes.txt", 0x20),
+            entry(101, "Projects", FILE_ATTRIBUTE_REPARSE_POINT),
+        ])
+        .unwrap();
+
+        let links = get_unresolved_links(&conn, volume_id).unwrap();
+        assert_eq!(links, vec![(101, "Projects".to_string())]);
+
+        let targets = vec![(101, r"D:\Projects".to_string())];
+        assert_eq!(set_link_targets(&mut conn, volume_id, &targets).unwrap(), 1);
+        assert!(get_unresolved_links(&conn, volume_id).unwrap().is_empty());
+        let target: Option<String> = conn
+            .query_row("SELECT link_target FROM files WHERE file_ref = 101", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(target.as_deref(), Some(r"D:\Projects"));
+    }
+
     #[test]
     fn test_search_files() {
         let mut conn = setup_test_db();
@@ -573,7 +2114,7 @@ mod tests {
                 name: "document.txt".to_string(),
                 size: 1024,
                 modified: Some(1700000000),
-                is_dir: false,
+                ..Default::default()
             },
             FileEntry {
                 volume_id,
@@ -582,7 +2123,7 @@ mod tests {
                 name: "Document.pdf".to_string(),
                 size: 2048,
                 modified: Some(1700000000),
-                is_dir: false,
+                ..Default::default()
             },
             FileEntry {
                 volume_id,
@@ -591,7 +2132,7 @@ mod tests {
                 name: "image.png".to_string(),
                 size: 4096,
                 modified: Some(1700000000),
-                is_dir: false,
+                ..Default::default()
             },
         ];
 
@@ -602,6 +2143,309 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    #[test]
+    fn test_name_index_follows_changes() {
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+        let entry = |file_ref: i64, name: &str| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(0),
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let names = |conn: &Connection, query: &str| -> Vec<String> {
+            search_files(conn, query, 100).unwrap().into_iter().map(|f| f.name).collect()
+        };
+
+        batch_insert_files(&mut conn, &[entry(1, "quarterly.xlsx"), entry(2, "notes.txt")]).unwrap();
+        assert_eq!(names(&conn, "QUARTER"), vec!["quarterly.xlsx"]);
+
+        // Rescan with a renamed file
+        batch_insert_files(&mut conn, &[entry(1, "annual.xlsx")]).unwrap();
+        assert!(names(&conn, "quarter").is_empty());
+        assert_eq!(names(&conn, "annual"), vec!["annual.xlsx"]);
+
+        // Replaced and deleted rows leave the index
+        conn.execute(
+            "INSERT OR REPLACE INTO files (volume_id, file_ref, name) VALUES (?1, 2, 'draft.txt')",
+            params![volume_id],
+        )
+        .unwrap();
+        assert!(names(&conn, "notes").is_empty());
+        delete_volume_files(&conn, volume_id).unwrap();
+        assert!(names(&conn, "draft").is_empty());
+
+        let indexed: i64 = conn
+            .query_row("SELECT COUNT(*) FROM files_fts WHERE files_fts MATCH 'txt'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(indexed, 0);
+    }
+
+    #[test]
+    fn test_skipped_paths() {
+        let conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "D:", "1234-ABCD", "FAT").unwrap();
+        let path = r"D:\System Volume Information";
+
+        assert_eq!(record_path_failure(&conn, volume_id, path, "Access is denied").unwrap(), 1);
+        assert_eq!(record_path_failure(&conn, volume_id, path, "Access is denied").unwrap(), 2);
+
+        let skipped = get_skipped_paths(&conn, Some(volume_id)).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, path);
+        assert_eq!(skipped[0].failures, 2);
+        assert_eq!(skipped[0].error.as_deref(), Some("Access is denied"));
+
+        clear_path_failure(&conn, volume_id, path).unwrap();
+        assert!(get_skipped_paths(&conn, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_files_sorted() {
+        use crate::search::SortField;
+
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+
+        let files: Vec<FileEntry> = [("b.txt", 10), ("a.pdf", 30), ("c.txt", 20)]
+            .iter()
+            .enumerate()
+            .map(|(i, (name, size))| FileEntry {
+                volume_id,
+                file_ref: Some(i as i64),
+                parent_ref: Some(0),
+                name: name.to_string(),
+                size: *size,
+                modified: Some(1700000000),
+                ..Default::default()
+            })
+            .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        let names = |sort: &[SortSpec]| -> Vec<String> {
+            search_files_sorted(&conn, "", 100, 0, sort)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.name)
+                .collect()
+        };
+
+        assert_eq!(names(&[]), vec!["a.pdf", "b.txt", "c.txt"]);
+        assert_eq!(names(&[SortSpec::desc(SortField::Size)]), vec!["a.pdf", "c.txt", "b.txt"]);
+        assert_eq!(
+            names(&[SortSpec::desc(SortField::Extension), SortSpec::desc(SortField::Size)]),
+            vec!["c.txt", "b.txt", "a.pdf"]
+        );
+
+        // Later pages continue where the first stopped
+        let page = |offset| -> Vec<String> {
+            search_files_sorted(&conn, ".", 2, offset, &[])
+                .unwrap()
+                .into_iter()
+                .map(|f| f.name)
+                .collect()
+        };
+        assert_eq!(page(0), vec!["a.pdf", "b.txt"]);
+        assert_eq!(page(2), vec!["c.txt"]);
+    }
+
+    #[test]
+    fn test_query_files_by_relevance() {
+        use crate::search::parse_query;
+
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+
+        let files: Vec<FileEntry> = ["my report.txt", "Report", "report-final.pdf", "report.pdf", "notes.txt"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| FileEntry {
+                volume_id,
+                file_ref: Some(i as i64),
+                parent_ref: Some(0),
+                name: name.to_string(),
+                modified: Some(1700000000),
+                ..Default::default()
+            })
+            .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        let names = |query: &str, limit, offset| -> Vec<String> {
+            query_files_by_relevance(&conn, &parse_query(query).unwrap(), limit, offset)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.name)
+                .collect()
+        };
+        assert_eq!(
+            names("report", 100, 0),
+            vec!["Report", "report.pdf", "report-final.pdf", "my report.txt"]
+        );
+        assert_eq!(names("report ext:pdf", 100, 0), vec!["report.pdf", "report-final.pdf"]);
+
+        // The limit keeps the best matches, and later pages continue from there
+        assert_eq!(names("report", 1, 0), vec!["Report"]);
+        assert_eq!(names("report", 2, 2), vec!["report-final.pdf", "my report.txt"]);
+        assert_eq!(count_query_matches(&conn, &parse_query("report").unwrap()).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_count_query_matches_batch() {
+        use crate::search::parse_query;
+
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+
+        let files: Vec<FileEntry> = ["report.pdf", "report.docx", "notes.pdf"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| FileEntry {
+                volume_id,
+                file_ref: Some(i as i64),
+                parent_ref: Some(0),
+                name: name.to_string(),
+                size: 1024,
+                modified: Some(1700000000),
+                ..Default::default()
+            })
+            .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        let queries = vec![
+            parse_query("ext:pdf").unwrap(),
+            parse_query("report ext:pdf").unwrap(),
+            parse_query("ext:zip").unwrap(),
+        ];
+        let counts = count_query_matches_batch(&conn, &queries).unwrap();
+        assert_eq!(counts, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_cleanup_spares_kept_volumes() {
+        let conn = setup_test_db();
+        let old = VolumeState::Offline { since: 1_000 };
+        let kept = insert_volume(&conn, "E:", "1111", "FAT32").unwrap();
+        let expired = insert_volume(&conn, "F:", "2222", "FAT32").unwrap();
+        update_volume_state(&conn, kept, old).unwrap();
+        update_volume_state(&conn, expired, old).unwrap();
+        set_volume_kept(&conn, kept, true).unwrap();
+
+        let offline = get_offline_volumes(&conn, 7).unwrap();
+        assert_eq!(offline.len(), 2);
+        assert_eq!(offline[1].purge_after, Some(1_000 + 7 * 86400));
+
+        cleanup_old_offline_volumes(&conn, 7).unwrap();
+        assert!(get_volume(&conn, "E:").unwrap().is_some());
+        assert!(get_volume(&conn, "F:").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cleanup_uses_per_volume_retention() {
+        let conn = setup_test_db();
+        let now = chrono::Utc::now().timestamp();
+        let ten_days_ago = VolumeState::Offline { since: now - 10 * 86400 };
+        let archive = insert_volume(&conn, "E:", "1111", "NTFS").unwrap();
+        let data = insert_volume(&conn, "F:", "2222", "NTFS").unwrap();
+        update_volume_state(&conn, archive, ten_days_ago).unwrap();
+        update_volume_state(&conn, data, ten_days_ago).unwrap();
+
+        let mut retention = RetentionPolicy::from(7);
+        retention.per_volume.insert("E:".to_string(), 90);
+
+        let offline = get_offline_volumes(&conn, retention.clone()).unwrap();
+        assert_eq!(offline[0].purge_after, Some(now + 80 * 86400));
+
+        cleanup_old_offline_volumes(&conn, retention).unwrap();
+        assert!(get_volume(&conn, "E:").unwrap().is_some());
+        assert!(get_volume(&conn, "F:").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_volume_stats() {
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+        assert_eq!(get_volume_stats(&conn, volume_id).unwrap().file_count, 0);
+
+        let files: Vec<FileEntry> = (0..10)
+            .map(|i| FileEntry {
+                volume_id,
+                file_ref: Some(i),
+                parent_ref: Some(0),
+                name: format!("entry_{}", i),
+                size: if i < 3 { 0 } else { 100 },
+                modified: Some(1700000000),
+                is_dir: i < 3,
+                ..Default::default()
+            })
+            .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        let stats = update_volume_stats(&conn, volume_id, 1500).unwrap();
+        assert_eq!(stats.file_count, 7);
+        assert_eq!(stats.dir_count, 3);
+        assert_eq!(stats.total_bytes, 700);
+        assert_eq!(stats.last_scan_duration_ms, 1500);
+        assert!(stats.last_scan_time.is_some());
+
+        // Stored, not recounted
+        delete_volume_files(&conn, volume_id).unwrap();
+        assert_eq!(get_volume_stats(&conn, volume_id).unwrap(), stats);
+        assert!(get_volume_stats(&conn, 999).is_err());
+    }
+
+    #[test]
+    fn test_open_history() {
+        let conn = setup_test_db();
+        record_open(&conn, r"C:\Docs\Report.pdf", 100).unwrap();
+        record_open(&conn, r"C:\Docs\notes.txt", 150).unwrap();
+        record_open(&conn, r"c:\docs\report.PDF", 200).unwrap();
+
+        let history = get_open_history(&conn).unwrap();
+        assert_eq!(
+            history[0],
+            OpenRecord {
+                path: r"c:\docs\report.pdf".to_string(),
+                open_count: 2,
+                last_opened: 200,
+            }
+        );
+        assert_eq!(history.len(), 2);
+
+        for now in 300..300 + MAX_OPEN_SAMPLES as i64 {
+            record_open(&conn, r"C:\Docs\notes.txt", now).unwrap();
+        }
+        let samples = get_open_samples(&conn).unwrap();
+        assert_eq!(samples[r"c:\docs\report.pdf"], vec![200, 100]);
+        let notes = &samples[r"c:\docs\notes.txt"];
+        assert_eq!(notes.len(), MAX_OPEN_SAMPLES);
+        assert_eq!(notes[0], 300 + MAX_OPEN_SAMPLES as i64 - 1);
+    }
+
+    #[test]
+    fn test_saved_searches() {
+        let conn = setup_test_db();
+        save_search(&conn, "Big installers", "ext:msi;exe size:>100mb", 100).unwrap();
+        save_search(&conn, "Notes", "ext:md", 150).unwrap();
+        // Same name, new query: keeps its place
+        save_search(&conn, "big Installers", "ext:msi size:>1gb", 200).unwrap();
+
+        let saved = get_saved_searches(&conn).unwrap();
+        assert_eq!(
+            saved[0],
+            SavedSearch {
+                name: "big Installers".to_string(),
+                query: "ext:msi size:>1gb".to_string(),
+                created: 100,
+            }
+        );
+        assert_eq!(saved.len(), 2);
+
+        assert!(delete_saved_search(&conn, "NOTES").unwrap());
+        assert!(!delete_saved_search(&conn, "Notes").unwrap());
+        assert_eq!(get_saved_searches(&conn).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_delete_volume_files() {
         let mut conn = setup_test_db();
@@ -615,7 +2459,7 @@ mod tests {
                 name: format!("file_{}.txt", i),
                 size: 1024,
                 modified: Some(1700000000),
-                is_dir: false,
+                ..Default::default()
             })
             .collect();
 
@@ -642,6 +2486,11 @@ mod tests {
                 name: "".to_string(),
                 size: 0,
                 modified: None,
+                created: None,
+                attributes: 0,
+                link: 0,
+                link_target: None,
+                stream: None,
                 is_dir: true,
             },
             FileEntry {
@@ -649,27 +2498,24 @@ mod tests {
                 file_ref: Some(100),
                 parent_ref: Some(5),
                 name: "Users".to_string(),
-                size: 0,
-                modified: None,
                 is_dir: true,
+                ..Default::default()
             },
             FileEntry {
                 volume_id,
                 file_ref: Some(200),
                 parent_ref: Some(100),
                 name: "John".to_string(),
-                size: 0,
-                modified: None,
                 is_dir: true,
+                ..Default::default()
             },
             FileEntry {
                 volume_id,
                 file_ref: Some(300),
                 parent_ref: Some(200),
                 name: "Documents".to_string(),
-                size: 0,
-                modified: None,
                 is_dir: true,
+                ..Default::default()
             },
             FileEntry {
                 volume_id,
@@ -678,7 +2524,7 @@ mod tests {
                 name: "file.txt".to_string(),
                 size: 1024,
                 modified: Some(1700000000),
-                is_dir: false,
+                ..Default::default()
             },
         ];
 
@@ -687,4 +2533,99 @@ mod tests {
         let path = reconstruct_path(&conn, volume_id, 400).unwrap();
         assert_eq!(path, PathBuf::from("Users/John/Documents/file.txt"));
     }
+
+    #[test]
+    fn test_full_path_maintenance() {
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+        let entry = |file_ref: i64, parent_ref: i64, name: &str| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            is_dir: true,
+            ..Default::default()
+        };
+
+        // Children indexed before their parents are fixed up when the parents arrive
+        batch_insert_files(&mut conn, &[entry(300, 200, "report.pdf")]).unwrap();
+        assert_eq!(get_full_path(&conn, volume_id, 300).unwrap().as_deref(), Some("report.pdf"));
+        batch_insert_files(&mut conn, &[entry(200, 100, "Docs"), entry(100, 5, "Users"), entry(5, 5, ".")])
+            .unwrap();
+        assert_eq!(
+            get_full_path(&conn, volume_id, 300).unwrap().as_deref(),
+            Some(r"Users\Docs\report.pdf")
+        );
+        assert_eq!(get_full_path(&conn, volume_id, 5).unwrap().as_deref(), Some(""));
+
+        // A rescan that renames a directory cascades to its descendants
+        batch_insert_files(&mut conn, &[entry(100, 5, "People")]).unwrap();
+        assert_eq!(
+            get_full_path(&conn, volume_id, 300).unwrap().as_deref(),
+            Some(r"People\Docs\report.pdf")
+        );
+
+        let parsed = crate::search::parse_query(r"path:C:\people").unwrap();
+        assert_eq!(count_query_matches(&conn, &parsed).unwrap(), 2);
+        assert_eq!(get_full_path(&conn, volume_id, 999).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reconstruct_path_stops_at_cycles() {
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+
+        let entry = |file_ref, parent_ref, name: &str| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            is_dir: true,
+            ..Default::default()
+        };
+        batch_insert_files(
+            &mut conn,
+            &[
+                // NTFS root is its own parent
+                entry(5, 5, "."),
+                entry(100, 5, "Users"),
+                // Corrupt loop: 200 -> 300 -> 200
+                entry(200, 300, "a"),
+                entry(300, 200, "b"),
+                entry(400, 200, "file.txt"),
+            ],
+        )
+        .unwrap();
+
+        let root = reconstruct_path_checked(&conn, volume_id, 100).unwrap();
+        assert_eq!(root.path, PathBuf::from("Users"));
+        assert!(!root.truncated);
+
+        let looped = reconstruct_path_checked(&conn, volume_id, 400).unwrap();
+        assert_eq!(looped.path, PathBuf::from("b/a/file.txt"));
+        assert!(looped.truncated);
+    }
+
+    #[test]
+    fn test_reconstruct_path_depth_cap() {
+        let mut conn = setup_test_db();
+        let volume_id = insert_volume(&conn, "C:", "1234-ABCD", "NTFS").unwrap();
+
+        let depth = MAX_PATH_DEPTH as i64 + 10;
+        let files: Vec<FileEntry> = (1..=depth)
+            .map(|i| FileEntry {
+                volume_id,
+                file_ref: Some(i),
+                parent_ref: Some(i - 1),
+                name: "d".to_string(),
+                is_dir: true,
+                ..Default::default()
+            })
+            .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        let deep = reconstruct_path_checked(&conn, volume_id, depth).unwrap();
+        assert!(deep.truncated);
+        assert_eq!(deep.path.components().count(), MAX_PATH_DEPTH);
+    }
 }
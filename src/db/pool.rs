@@ -0,0 +1,188 @@
+//! Connection pool for the IPC server: several readers and one writer.
+//!
+//! In WAL mode readers never wait for a writer, but a single shared
+//! connection behind a mutex made every search queue behind whatever held
+//! it. [`DatabasePool`] hands each search its own read-only connection, so
+//! searches run side by side and alongside the job workers' batch inserts,
+//! and keeps one writable connection for the commands that change the
+//! database (recording opens, saved searches, keeping volumes).
+
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError, RwLock};
+
+use super::{open_database, open_database_read_only, Database};
+use crate::search::FileAttribute;
+use crate::{FFIError, Result};
+
+/// Read-only connections for searches and one writable connection.
+pub struct DatabasePool {
+    /// Idle readers
+    readers: Mutex<Vec<Database>>,
+    /// Signalled when a reader is returned
+    returned: Condvar,
+    writer: Mutex<Database>,
+    /// Applied to each reader as it is checked out
+    excluded_attributes: RwLock<Vec<FileAttribute>>,
+}
+
+impl DatabasePool {
+    /// Open the pool's connections.
+    ///
+    /// The writer is opened first, so the schema is created or migrated
+    /// before the readers check for it.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the SQLite database file
+    /// * `read_only` - Open the "writer" read-only too (replica mode)
+    /// * `readers` - Number of read-only connections, at least 1
+    pub fn open(path: &Path, read_only: bool, readers: usize) -> Result<Self> {
+        let writer = if read_only {
+            open_database_read_only(path)?
+        } else {
+            open_database(path)?
+        };
+        let readers = (0..readers.max(1))
+            .map(|_| open_database_read_only(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            readers: Mutex::new(readers),
+            returned: Condvar::new(),
+            writer: Mutex::new(writer),
+            excluded_attributes: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Take a read-only connection, waiting for one to be returned if all
+    /// are in use. It goes back to the pool when the guard is dropped.
+    pub fn reader(&self) -> Result<PooledReader<'_>> {
+        let mut idle = self
+            .readers
+            .lock()
+            .map_err(|e| FFIError::Database(format!("Failed to acquire reader pool: {}", e)))?;
+        let mut db = loop {
+            match idle.pop() {
+                Some(db) => break db,
+                None => {
+                    idle = self
+                        .returned
+                        .wait(idle)
+                        .map_err(|e| FFIError::Database(format!("Failed to wait for a reader: {}", e)))?;
+                }
+            }
+        };
+        drop(idle);
+
+        db.set_excluded_attributes(&self.excluded_attributes.read().unwrap_or_else(PoisonError::into_inner));
+        Ok(PooledReader { pool: self, db: Some(db) })
+    }
+
+    /// Take the writable connection, waiting while another task uses it.
+    pub fn writer(&self) -> Result<MutexGuard<'_, Database>> {
+        self.writer
+            .lock()
+            .map_err(|e| FFIError::Database(format!("Failed to acquire database writer: {}", e)))
+    }
+
+    /// Leave entries with these attributes out of searches on every
+    /// connection; see [`Database::set_excluded_attributes`].
+    pub fn set_excluded_attributes(&self, attributes: &[FileAttribute]) {
+        *self.excluded_attributes.write().unwrap_or_else(PoisonError::into_inner) = attributes.to_vec();
+        self.writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .set_excluded_attributes(attributes);
+    }
+}
+
+/// A reader checked out of a [`DatabasePool`].
+pub struct PooledReader<'a> {
+    pool: &'a DatabasePool,
+    db: Option<Database>,
+}
+
+impl Deref for PooledReader<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.db.as_ref().expect("reader is held until dropped")
+    }
+}
+
+impl DerefMut for PooledReader<'_> {
+    fn deref_mut(&mut self) -> &mut Database {
+        self.db.as_mut().expect("reader is held until dropped")
+    }
+}
+
+impl Drop for PooledReader<'_> {
+    fn drop(&mut self) {
+        if let Some(db) = self.db.take() {
+            self.pool.readers.lock().unwrap_or_else(PoisonError::into_inner).push(db);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_volume, Store};
+    use crate::search::parse_query;
+    use std::fs;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_readers_do_not_wait_for_writer() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_pool");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let pool = DatabasePool::open(&temp_dir.join("test.db"), false, 2).unwrap();
+        insert_volume(pool.writer().unwrap().conn(), "C:", "1234", "NTFS").unwrap();
+
+        // A write transaction in progress doesn't hold up searches, which
+        // see the last committed state
+        let writer = pool.writer().unwrap();
+        writer
+            .conn()
+            .execute_batch("BEGIN; INSERT INTO files (volume_id, file_ref, parent_ref, name) VALUES (1, 100, 5, 'a.txt');")
+            .unwrap();
+        let query = parse_query("a.txt").unwrap();
+        assert!(pool.reader().unwrap().search(&query, 10, 0).unwrap().is_empty());
+        writer.conn().execute_batch("COMMIT").unwrap();
+        drop(writer);
+        assert_eq!(pool.reader().unwrap().search(&query, 10, 0).unwrap().len(), 1);
+
+        // Readers can't write
+        let reader = pool.reader().unwrap();
+        assert!(insert_volume(reader.conn(), "D:", "5678", "NTFS").is_err());
+
+        drop(reader);
+        drop(pool);
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_reader_checkout_waits_for_return() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_pool_wait");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let pool = Arc::new(DatabasePool::open(&temp_dir.join("test.db"), false, 1).unwrap());
+        pool.set_excluded_attributes(&[FileAttribute::Hidden]);
+
+        let reader = pool.reader().unwrap();
+        assert_eq!(reader.excluded_attributes(), &[FileAttribute::Hidden]);
+        let waiting = {
+            let pool = Arc::clone(&pool);
+            std::thread::spawn(move || pool.reader().map(|reader| reader.excluded_attributes().to_vec()))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiting.is_finished());
+
+        drop(reader);
+        assert_eq!(waiting.join().unwrap().unwrap(), vec![FileAttribute::Hidden]);
+
+        drop(pool);
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,331 @@
+//! Keeping the index within its size budget.
+//!
+//! When `database.max_size_mb` is set and the index outgrows it, the
+//! lowest-value data is deleted first until the index is back under
+//! [`PRUNE_TARGET`] of the budget: the indexes of offline volumes, oldest
+//! first (volumes the user chose to keep are spared), then entries that
+//! match the exclude settings, then the least recently modified files.
+//!
+//! Run by the job pool as `JobKind::Prune`. The outcome of the last
+//! pruning is stored in the `pruning` table for the service status.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::exclusions::purge_excluded;
+use super::facets::FacetDeltas;
+use super::ops::{delete_volume, get_all_volumes, get_offline_volumes};
+use crate::service::config::Config;
+use crate::{FFIError, Result};
+
+/// Share of the budget pruning brings the index down to, so the next few
+/// changes don't exceed it again straight away.
+pub const PRUNE_TARGET: f64 = 0.9;
+
+/// What a pruning deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PruneReport {
+    /// Unix timestamp of the pruning
+    pub ran_at: i64,
+    /// Index size before pruning, in bytes
+    pub size_before: i64,
+    /// Index size after pruning, in bytes
+    pub size_after: i64,
+    /// Offline volumes whose index was deleted
+    pub offline_volumes: Vec<String>,
+    /// Entries deleted because they match the exclude settings
+    pub excluded_entries: i64,
+    /// Files deleted because they were the least recently modified
+    pub stale_entries: i64,
+    /// Files modified at or before this Unix timestamp were deleted
+    /// (None if no files were)
+    pub modified_cutoff: Option<i64>,
+}
+
+/// Bytes used by the database's pages, not counting free pages.
+pub fn index_size(conn: &Connection) -> Result<i64> {
+    conn.query_row(
+        "SELECT (page_count - freelist_count) * page_size
+         FROM pragma_page_count, pragma_freelist_count, pragma_page_size",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to read database size: {}", e)))
+}
+
+/// Prune the index if it is larger than `max_bytes`.
+///
+/// # Arguments
+/// * `conn` - Writable database connection
+/// * `max_bytes` - Size budget of the index
+/// * `config` - Configuration whose excluded entries are pruned
+/// * `now` - Current Unix timestamp
+///
+/// # Returns
+/// What was pruned, also stored for [`get_last_pruning`], or None if the
+/// index was within its budget.
+pub fn prune_to_budget(
+    conn: &mut Connection,
+    max_bytes: u64,
+    config: &Config,
+    now: i64,
+) -> Result<Option<PruneReport>> {
+    let size_before = index_size(conn)?;
+    if size_before <= max_bytes as i64 {
+        return Ok(None);
+    }
+
+    let target = (max_bytes as f64 * PRUNE_TARGET) as i64;
+    let mut report = PruneReport {
+        ran_at: now,
+        size_before,
+        ..Default::default()
+    };
+    tracing::warn!("Index is {} bytes, over its budget of {} bytes; pruning", size_before, max_bytes);
+
+    // Oldest offline volumes first
+    let mut offline: Vec<_> = get_offline_volumes(conn, 0u32)?
+        .into_iter()
+        .filter(|offline| !offline.keep_forever)
+        .collect();
+    offline.sort_by_key(|offline| offline.offline_since);
+    for offline in offline {
+        if compacted_size(conn)? <= target {
+            break;
+        }
+        let deleted = delete_volume(conn, offline.volume.id)?;
+        tracing::info!("Pruned index of offline volume {} ({} entries)", offline.volume.drive_letter, deleted);
+        report.offline_volumes.push(offline.volume.drive_letter);
+    }
+
+    if compacted_size(conn)? > target {
+        report.excluded_entries = purge_excluded(conn, config)? as i64;
+    }
+
+    let size = compacted_size(conn)?;
+    if size > target {
+        let (deleted, cutoff) = prune_stale_files(conn, size, target)?;
+        report.stale_entries = deleted;
+        report.modified_cutoff = cutoff;
+    }
+
+    report.size_after = compacted_size(conn)?;
+    save_report(conn, &report)?;
+
+    // Return the freed pages if the database tracks them (see maintenance)
+    conn.execute_batch("PRAGMA incremental_vacuum")
+        .map_err(|e| FFIError::Database(format!("Failed to vacuum database: {}", e)))?;
+    Ok(Some(report))
+}
+
+/// Outcome of the last pruning, if the index was ever pruned.
+pub fn get_last_pruning(conn: &Connection) -> Result<Option<PruneReport>> {
+    conn.query_row(
+        "SELECT ran_at, size_before, size_after, offline_volumes, excluded_entries, stale_entries, modified_cutoff
+         FROM pruning WHERE id = 1",
+        [],
+        |row| {
+            let volumes: String = row.get(3)?;
+            Ok(PruneReport {
+                ran_at: row.get(0)?,
+                size_before: row.get(1)?,
+                size_after: row.get(2)?,
+                offline_volumes: volumes.lines().map(str::to_string).collect(),
+                excluded_entries: row.get(4)?,
+                stale_entries: row.get(5)?,
+                modified_cutoff: row.get(6)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| FFIError::Database(format!("Failed to read pruning report: {}", e)))
+}
+
+fn save_report(conn: &Connection, report: &PruneReport) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO pruning
+             (id, ran_at, size_before, size_after, offline_volumes, excluded_entries, stale_entries, modified_cutoff)
+         VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            report.ran_at,
+            report.size_before,
+            report.size_after,
+            report.offline_volumes.join("\n"),
+            report.excluded_entries,
+            report.stale_entries,
+            report.modified_cutoff
+        ],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to save pruning report: {}", e)))?;
+    Ok(())
+}
+
+/// Index size once the name index has merged away the deletions.
+///
+/// Deleting a row only adds a tombstone to the FTS5 index; until its
+/// segments are merged, the name index grows rather than shrinks.
+fn compacted_size(conn: &Connection) -> Result<i64> {
+    conn.execute_batch("INSERT INTO files_fts (files_fts) VALUES ('optimize')")
+        .map_err(|e| FFIError::Database(format!("Failed to optimize name index: {}", e)))?;
+    index_size(conn)
+}
+
+/// Delete the least recently modified files, sized so the index shrinks
+/// from `size` to about `target` bytes. Directories are kept, so the paths
+/// of the remaining files stay complete.
+///
+/// # Returns
+/// The number of entries deleted and the modification time cutoff.
+fn prune_stale_files(conn: &mut Connection, size: i64, target: i64) -> Result<(i64, Option<i64>)> {
+    let files: i64 = conn
+        .query_row("SELECT COUNT(*) FROM files WHERE is_dir = 0", [], |row| row.get(0))
+        .map_err(|e| FFIError::Database(format!("Failed to count files: {}", e)))?;
+    // Every table and index takes a page even when empty; rows take about
+    // the same space each in the rest, so prune the same share of them
+    let fixed: i64 = conn
+        .query_row(
+            "SELECT (COUNT(*) + 1) * (SELECT page_size FROM pragma_page_size) FROM sqlite_master WHERE rootpage > 0",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to count tables: {}", e)))?;
+    let excess = ((files as f64) * (size - target) as f64 / (size - fixed).max(1) as f64).ceil() as i64;
+    if excess <= 0 {
+        return Ok((0, None));
+    }
+
+    // Unknown modification times count as oldest
+    let cutoff: Option<i64> = conn
+        .query_row(
+            "SELECT COALESCE(modified, 0) FROM files WHERE is_dir = 0
+             ORDER BY COALESCE(modified, 0) LIMIT 1 OFFSET ?1",
+            params![excess.min(files) - 1],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| FFIError::Database(format!("Failed to find pruning cutoff: {}", e)))?;
+    let Some(cutoff) = cutoff else {
+        return Ok((0, None));
+    };
+
+    let mut deleted = 0;
+    for volume in get_all_volumes(conn)? {
+        let tx = conn
+            .transaction()
+            .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+        let mut facets = FacetDeltas::new();
+        {
+            let mut stmt = tx
+                .prepare(
+                    "DELETE FROM files WHERE volume_id = ?1 AND is_dir = 0 AND COALESCE(modified, 0) <= ?2
+                     RETURNING name, size, is_dir",
+                )
+                .map_err(|e| FFIError::Database(format!("Failed to prepare stale file delete: {}", e)))?;
+            let rows = stmt
+                .query_map(params![volume.id, cutoff], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?))
+                })
+                .map_err(|e| FFIError::Database(format!("Failed to delete stale files: {}", e)))?;
+            for row in rows {
+                let (name, size, is_dir) = row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+                facets.remove(&name, size, is_dir);
+                deleted += 1;
+            }
+        }
+        if !facets.is_empty() {
+            facets.apply(&tx, volume.id)?;
+        }
+
+        tx.commit()
+            .map_err(|e| FFIError::Database(format!("Failed to commit stale file pruning: {}", e)))?;
+    }
+
+    tracing::info!("Pruned {} files modified at or before {}", deleted, cutoff);
+    Ok((deleted, Some(cutoff)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::config::ExcludeConfig;
+    use crate::db::{batch_insert_files, insert_volume, open_database, set_volume_kept, update_volume_state, FileEntry};
+    use crate::VolumeState;
+    use std::fs;
+
+    fn files(volume_id: i64, count: i64, ext: &str) -> Vec<FileEntry> {
+        (0..count)
+            .map(|i| FileEntry {
+                volume_id,
+                file_ref: Some(100 + i),
+                parent_ref: Some(5),
+                name: format!("document number {:05}.{}", i, ext),
+                size: 1000,
+                modified: Some(1_600_000_000 + i),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_prune_to_budget() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_pruning");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+        let conn = db.conn_mut();
+
+        let c = insert_volume(conn, "C:", "1111", "NTFS").unwrap();
+        let d = insert_volume(conn, "D:", "2222", "NTFS").unwrap();
+        let e = insert_volume(conn, "E:", "3333", "NTFS").unwrap();
+        let f = insert_volume(conn, "F:", "4444", "NTFS").unwrap();
+        update_volume_state(conn, d, VolumeState::Offline { since: 1_700_000_000 }).unwrap();
+        update_volume_state(conn, e, VolumeState::Offline { since: 1_690_000_000 }).unwrap();
+        update_volume_state(conn, f, VolumeState::Offline { since: 1_680_000_000 }).unwrap();
+        set_volume_kept(conn, f, true).unwrap();
+        for (volume, ext) in [(c, "txt"), (d, "txt"), (e, "txt"), (f, "txt"), (c, "tmp")] {
+            batch_insert_files(conn, &files(volume, 2000, ext)).unwrap();
+        }
+        let config = Config {
+            exclude: ExcludeConfig {
+                extensions: vec!["tmp".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Within budget
+        let size = compacted_size(conn).unwrap();
+        assert_eq!(prune_to_budget(conn, size as u64, &config, 1_700_100_000).unwrap(), None);
+        assert_eq!(get_last_pruning(conn).unwrap(), None);
+
+        // Just over: the offline volume that went offline first goes; the
+        // kept one is spared
+        let report = prune_to_budget(conn, (size as f64 * 0.95) as u64, &config, 1_700_100_000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.offline_volumes, vec!["E:"]);
+        assert_eq!((report.excluded_entries, report.stale_entries), (0, 0));
+        assert!(report.size_after < report.size_before);
+        assert_eq!(get_last_pruning(conn).unwrap(), Some(report));
+
+        // Far over: the remaining offline volume, excluded entries, then the
+        // oldest files
+        let report = prune_to_budget(conn, (size as f64 * 0.25) as u64, &config, 1_700_200_000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.offline_volumes, vec!["D:"]);
+        assert_eq!(report.excluded_entries, 2000);
+        assert!(report.stale_entries > 0);
+        let cutoff = report.modified_cutoff.unwrap();
+        assert_eq!(count(conn, &format!("SELECT COUNT(*) FROM files WHERE modified <= {}", cutoff)), 0);
+        assert_eq!(count(conn, "SELECT COUNT(*) FROM volumes"), 2);
+        assert!(report.size_after <= (size as f64 * 0.25) as i64, "{:?}", report);
+
+        drop(db);
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,226 @@
+//! Items in the Recycle Bin and where they were deleted from.
+//!
+//! Windows moves a deleted file or folder to `$Recycle.Bin\<SID>\$R<id>`
+//! on its volume (keeping the extension) and writes its original path and
+//! deletion time to a `$I<id>` file beside it. The background pass in
+//! `indexer::recycle_bin` reads the `$I` files; this module finds those not
+//! read yet and stores what they hold in `recycled_items`, keyed by the
+//! `$R` entry, so `in:recyclebin` results can show where they came from.
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::search::RECYCLE_BIN;
+use crate::{FFIError, Result};
+
+/// A `$I` file of an online volume that was not read yet, with the item it
+/// describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecycleInfoFile {
+    /// Volume of both entries
+    pub volume_id: i64,
+    /// File reference of the `$I` file
+    pub info_ref: i64,
+    /// File reference of the `$R` entry holding the deleted item
+    pub item_ref: i64,
+    /// Full path of the `$I` file, with the volume name
+    pub path: String,
+}
+
+/// What a `$I` file says about a deleted item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecycledItem {
+    /// Volume of the item
+    pub volume_id: i64,
+    /// File reference of the `$R` entry
+    pub item_ref: i64,
+    /// File reference of the `$I` file it was read from
+    pub info_ref: i64,
+    /// Full path the item was deleted from; empty if the `$I` file could
+    /// not be read
+    pub original_path: String,
+    /// Deletion time as Unix timestamp
+    pub deleted_at: Option<i64>,
+}
+
+/// Find `$I` files of online volumes that were not read yet, or changed
+/// since, and whose `$R` entry is indexed.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `limit` - Most files to return
+pub fn unread_recycle_info(conn: &Connection, limit: usize) -> Result<Vec<RecycleInfoFile>> {
+    // `$I` files sit directly in a per-user folder of the Recycle Bin
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT i.volume_id, i.file_ref, r.file_ref, v.drive_letter || '\\' || i.full_path
+             FROM files i
+             JOIN volumes v ON v.id = i.volume_id AND v.state = 'online'
+             JOIN files r ON r.volume_id = i.volume_id AND r.parent_ref = i.parent_ref
+                 AND r.name = '$R' || substr(i.name, 3) COLLATE NOCASE AND r.link = 0 AND r.stream = ''
+             LEFT JOIN recycled_items ri ON ri.volume_id = i.volume_id AND ri.item_ref = r.file_ref
+             WHERE i.full_path >= ?1 COLLATE NOCASE AND i.full_path < ?2 COLLATE NOCASE
+               AND i.full_path NOT LIKE ?3 AND i.name LIKE '$I%'
+               AND i.is_dir = 0 AND i.link = 0 AND i.stream = ''
+               AND ri.info_ref IS NOT i.file_ref
+             ORDER BY i.volume_id, i.file_ref
+             LIMIT ?4",
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to prepare recycle bin query: {}", e)))?;
+
+    let rows = stmt
+        .query_map(
+            params![
+                format!("{}\\", RECYCLE_BIN),
+                format!("{}]", RECYCLE_BIN),
+                format!("{}\\%\\%\\%", RECYCLE_BIN),
+                limit as i64
+            ],
+            |row| {
+                Ok(RecycleInfoFile {
+                    volume_id: row.get(0)?,
+                    info_ref: row.get(1)?,
+                    item_ref: row.get(2)?,
+                    path: row.get(3)?,
+                })
+            },
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to query recycle bin: {}", e)))?;
+
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read recycle bin entry: {}", e)))
+}
+
+/// Store what was read about deleted items, replacing what was stored for
+/// them.
+pub fn store_recycled_items(conn: &mut Connection, items: &[RecycledItem]) -> Result<()> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT OR REPLACE INTO recycled_items (volume_id, item_ref, info_ref, original_path, deleted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to prepare recycled item insert: {}", e)))?;
+        for item in items {
+            stmt.execute(params![item.volume_id, item.item_ref, item.info_ref, item.original_path, item.deleted_at])
+                .map_err(|e| FFIError::Database(format!("Failed to store recycled item: {}", e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit recycled items: {}", e)))
+}
+
+/// Delete what is stored about items no longer in the Recycle Bin: emptied,
+/// restored (renamed back) or rescanned away.
+///
+/// # Returns
+/// The number of items forgotten.
+pub fn prune_recycled_items(conn: &Connection) -> Result<usize> {
+    conn.execute(
+        "DELETE FROM recycled_items WHERE NOT EXISTS (
+             SELECT 1 FROM files f
+             WHERE f.volume_id = recycled_items.volume_id AND f.file_ref = recycled_items.item_ref
+               AND f.name LIKE '$R%' AND f.link = 0 AND f.stream = ''
+         )",
+        [],
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to prune recycled items: {}", e)))
+}
+
+/// Where an entry in the Recycle Bin was deleted from.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_id` - Volume of the entry
+/// * `path` - Path of the entry below the volume root: a `$R` item, or an
+///   entry below a deleted folder
+///
+/// # Returns
+/// The original full path, or None if the entry is not in the Recycle Bin
+/// or its `$I` file was not read.
+pub fn recycled_original_path(conn: &Connection, volume_id: i64, path: &str) -> Result<Option<String>> {
+    let mut parts = path.splitn(4, '\\');
+    let (Some(bin), Some(user), Some(item)) = (parts.next(), parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let is_item = item.get(..2).is_some_and(|prefix| prefix.eq_ignore_ascii_case("$R"));
+    if !bin.eq_ignore_ascii_case(RECYCLE_BIN) || !is_item {
+        return Ok(None);
+    }
+
+    let original: Option<String> = conn
+        .query_row(
+            "SELECT r.original_path FROM recycled_items r
+             JOIN files f ON f.volume_id = r.volume_id AND f.file_ref = r.item_ref
+             WHERE r.volume_id = ?1 AND f.full_path = ?2 COLLATE NOCASE AND f.link = 0 AND f.stream = ''
+               AND r.original_path <> ''",
+            params![volume_id, format!("{}\\{}\\{}", bin, user, item)],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| FFIError::Database(format!("Failed to query recycled item: {}", e)))?;
+
+    Ok(original.map(|original| match parts.next() {
+        Some(rest) => format!("{}\\{}", original, rest),
+        None => original,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, insert_volume, schema, FileEntry};
+
+    #[test]
+    fn test_recycled_items() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let c = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        batch_insert_files(
+            &mut conn,
+            &[
+                FileEntry::test(c, 5, 5, ".", true),
+                FileEntry::test(c, 10, 5, "$Recycle.Bin", true),
+                FileEntry::test(c, 11, 10, "S-1-5-21-1000", true),
+                FileEntry::test(c, 20, 11, "$IAB12CD.txt", false),
+                FileEntry::test(c, 21, 11, "$RAB12CD.txt", false),
+                FileEntry::test(c, 30, 11, "$IXY34ZW", false),
+                FileEntry::test(c, 31, 11, "$RXY34ZW", true),
+                FileEntry::test(c, 32, 31, "plan.docx", false),
+                // Its item was emptied from the Recycle Bin already
+                FileEntry::test(c, 40, 11, "$IGONE00", false),
+                FileEntry::test(c, 50, 5, "$Iconic.txt", false),
+            ],
+        )
+        .unwrap();
+
+        let unread = unread_recycle_info(&conn, 100).unwrap();
+        let paths: Vec<&str> = unread.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec![r"C:\$Recycle.Bin\S-1-5-21-1000\$IAB12CD.txt", r"C:\$Recycle.Bin\S-1-5-21-1000\$IXY34ZW"]);
+        assert_eq!((unread[1].info_ref, unread[1].item_ref), (30, 31));
+
+        let item = |info: &RecycleInfoFile, original: &str| RecycledItem {
+            volume_id: info.volume_id,
+            item_ref: info.item_ref,
+            info_ref: info.info_ref,
+            original_path: original.to_string(),
+            deleted_at: Some(1_700_000_000),
+        };
+        store_recycled_items(&mut conn, &[item(&unread[0], r"C:\Users\me\notes.txt"), item(&unread[1], r"D:\Work")])
+            .unwrap();
+        assert!(unread_recycle_info(&conn, 100).unwrap().is_empty());
+
+        let original = |path: &str| recycled_original_path(&conn, c, path).unwrap();
+        assert_eq!(original(r"$Recycle.Bin\S-1-5-21-1000\$RAB12CD.txt").as_deref(), Some(r"C:\Users\me\notes.txt"));
+        assert_eq!(original(r"$RECYCLE.BIN\S-1-5-21-1000\$RXY34ZW\plan.docx").as_deref(), Some(r"D:\Work\plan.docx"));
+        assert_eq!(original(r"$Recycle.Bin\S-1-5-21-1000\$IAB12CD.txt"), None);
+        assert_eq!(original(r"Users\me\notes.txt"), None);
+
+        // Restored items are forgotten
+        conn.execute("UPDATE files SET name = 'notes.txt' WHERE file_ref = 21", []).unwrap();
+        assert_eq!(prune_recycled_items(&conn).unwrap(), 1);
+        assert_eq!(original(r"$Recycle.Bin\S-1-5-21-1000\$RXY34ZW").as_deref(), Some(r"D:\Work"));
+    }
+}
@@ -1,29 +1,46 @@
-//! Database schema module - table definitions and migrations.
+//! Database schema module - table documentation and index setup.
 //!
-//! This module contains the SQL schema for the FFI database, including
-//! the volumes and files tables with appropriate indexes.
+//! This module documents the SQL schema for the FFI database and creates
+//! the indexes over it. The tables themselves are created and upgraded by
+//! the versioned steps in [`super::migrations`].
 
 use rusqlite::Connection;
 use crate::{FFIError, Result};
 
+use super::migrations;
+
 /// Initialize the database schema.
 ///
-/// Creates the volumes and files tables with appropriate indexes if they
-/// don't already exist. This is called on every database open to ensure
-/// the schema is up to date.
+/// Brings the tables to the current schema version by applying the pending
+/// steps in [`super::migrations`], then creates any missing indexes and
+/// the name index. This is called on every database open.
 ///
 /// # Schema
 ///
 /// ## volumes table
 /// - `id`: Primary key
-/// - `drive_letter`: Drive letter (e.g., "C:", "D:")
-/// - `volume_serial`: Volume serial number for identity
+/// - `drive_letter`: Volume name: its drive letter (e.g., "C:", "D:"), or the folder mount
+///   point or `\\?\Volume{GUID}` path of a volume mounted without one; updated when a
+///   disk comes back under another letter, so not unique (swapped-out disks keep theirs)
+/// - `volume_serial`: Volume serial number for identity, unique among non-imported
+///   volumes that have one (`idx_volumes_serial`)
 /// - `fs_type`: Filesystem type ("NTFS", "FAT32", "exFAT")
 /// - `last_usn`: Last processed USN (NTFS only)
 /// - `usn_journal_id`: USN Journal ID (NTFS only)
 /// - `last_scan_time`: Unix timestamp of last scan
 /// - `state`: Volume state ("online", "offline", "indexing", "rescanning", "disabled")
 /// - `offline_since`: Unix timestamp when volume went offline (nullable)
+/// - `file_count` / `dir_count`: Entries indexed on the volume
+/// - `total_bytes`: Sum of file sizes on the volume
+/// - `last_scan_duration_ms`: Duration of the last full scan
+/// - `facets_valid`: Whether `facet_counts` matches the volume's files
+/// - `scan_next_record`: MFT record an interrupted full scan resumes from (nullable)
+/// - `scan_last_path`: Last entry, in walk order, an interrupted directory walk
+///   indexed (nullable)
+/// - `scan_percent`: Progress of the full scan in progress or interrupted (nullable)
+/// - `scan_processed` / `scan_total`: Entries that scan processed and expected (nullable)
+/// - `truncated`: Limits that left entries out on the last full scan, e.g.
+///   "max_files = 1000" (nullable)
 ///
 /// ## files table
 /// - `id`: Primary key
@@ -33,51 +50,195 @@ use crate::{FFIError, Result};
 /// - `name`: Filename only (not full path)
 /// - `size`: File size in bytes
 /// - `modified`: Last modified time (Unix timestamp)
+/// - `created`: Creation time (Unix timestamp), NULL where unknown
 /// - `is_dir`: Whether this is a directory
+/// - `attributes`: Windows `FILE_ATTRIBUTE_*` flags (hidden, system, ...), 0 where unknown;
+///   `FILE_ATTRIBUTE_REPARSE_POINT` marks symbolic links and junctions
+/// - `full_path`: Path from the volume root (e.g. `Users\Docs\a.txt`), kept
+///   current on insert and rename; NULL where the parent chain is broken
+/// - `ext`: Lowercase extension after the last dot, NULL if none
+/// - `link`: Which hard link of the file this row names: 0 for the primary
+///   name, 1.. for further names, which share the `file_ref`
+/// - `link_target`: Target of a symbolic link or junction, NULL if not one or unknown
+/// - `stream`: Name of the NTFS alternate data stream a row stands for, empty
+///   for the file itself; stream rows share the file's `file_ref` and are
+///   named `file:stream`
+///
+/// ## files_fts table
+/// FTS5 index over `files.name` with the trigram tokenizer, so substring
+/// name searches use an index instead of scanning every row. It stores no
+/// copy of the names (external content) and is kept in sync by triggers
+/// on `files`.
+///
+/// ## file_contents table
+/// FTS5 index over the text of small files, for `content:` filters; empty
+/// unless `[content]` indexing is enabled. The rowid is the file's
+/// `files.id`, and a trigger deletes the text with the file.
+/// - `content`: The file's text
+/// - `modified` / `size`: The file's modified time and size when read, to
+///   tell when it must be read again
+///
+/// ## skipped_paths table
+/// - `volume_id`: Foreign key to volumes
+/// - `path`: Full path of a directory that returned access-denied
+/// - `error`: Last error message
+/// - `failures`: Consecutive scans that failed on this path
+/// - `first_failed` / `last_failed`: Unix timestamps of the first and latest failure
+///
+/// ## dir_churn table
+/// - `volume_id`: Foreign key to volumes
+/// - `dir_ref`: Directory whose children changed (USN parent reference)
+/// - `changes`: USN changes counted since `since`
+/// - `since`: Unix timestamp of the first counted change
+///
+/// ## exclusion_suggestions table
+/// - `path`: Full path of a directory suggested for exclusion
+/// - `volume_id`: Foreign key to volumes
+/// - `reason`: Why it was suggested
+/// - `file_count`: Entries below the directory
+/// - `changes_per_day`: Average USN changes per day to its children
+///
+/// ## facet_counts table
+/// - `volume_id`: Foreign key to volumes
+/// - `facet`: What is counted ("ext" or "type")
+/// - `value`: Facet value (e.g. "pdf", "folder")
+/// - `count`: Entries with this value
+/// - `bytes`: Sum of file sizes with this value
+///
+/// ## open_history table
+/// - `path`: Lowercase full path of a file opened from the search UI
+/// - `open_count`: Times it was opened
+/// - `last_opened`: Unix timestamp of the latest open
+///
+/// ## open_events table
+/// - `path`: Lowercase full path, as in `open_history`
+/// - `opened_at`: Unix timestamp of one of its latest opens
+///
+/// ## kept_volumes table
+/// - `volume_id`: Volume the user chose to keep forever while offline
+///
+/// ## saved_searches table
+/// - `name`: Name shown on the search UI's chip (unique, case-insensitive)
+/// - `query`: Search query, as typed
+/// - `created`: Unix timestamp of the first save; chips are pinned in this order
+///
+/// ## tags table
+/// - `volume_serial`: Serial number of the tagged file's volume, so tags follow
+///   a disk to another drive letter
+/// - `file_ref`: File reference of the tagged file, kept across rescans
+/// - `tag`: Free-form tag (case-insensitive)
+/// - `created`: Unix timestamp the tag was attached
+///
+/// ## recycled_items table
+/// - `volume_id`: Volume whose Recycle Bin holds the item
+/// - `item_ref`: File reference of the item's `$R` entry
+/// - `info_ref`: File reference of the `$I` file describing it
+/// - `original_path`: Full path the item was deleted from; empty if the
+///   `$I` file could not be read
+/// - `deleted_at`: Unix timestamp of the deletion
+///
+/// ## maintenance table
+/// Single row (`id` = 1) with the outcome of the last maintenance run:
+/// - `ran_at`: Unix timestamp the run started
+/// - `duration_ms`: How long it took
+/// - `freed_pages`: Pages returned to the file system by vacuuming
+/// - `integrity_errors`: Problems found by the integrity check, one per line; empty if none
+/// - `rebuilt`: Whether the index was dropped and rebuilt because of corruption
+///
+/// ## pruning table
+/// Single row (`id` = 1) with what the last pruning to the size budget deleted:
+/// - `ran_at`: Unix timestamp of the pruning
+/// - `size_before` / `size_after`: Index size in bytes
+/// - `offline_volumes`: Names of the offline volumes whose index was deleted, one per line
+/// - `excluded_entries`: Entries deleted because they match the exclude settings
+/// - `stale_entries`: Least recently modified files deleted
+/// - `modified_cutoff`: Files modified at or before this Unix timestamp were deleted; NULL if none
 ///
 /// ## Indexes
 /// - `idx_files_name`: Fast case-insensitive filename search
 /// - `idx_files_parent`: Path reconstruction (parent lookups)
 /// - `idx_files_volume`: Volume-based operations
+/// - `idx_files_path`: `path:` scope filters (case-insensitive prefix ranges)
+/// - `idx_files_ext`: `ext:` filters (exact match)
+/// - `idx_files_modified`: Recently modified files, newest first
 pub fn init(conn: &Connection) -> Result<()> {
+    migrations::migrate(conn)?;
+
+    // Derived from the tables, so created here rather than by a migration
+    // (and again after a migration or maintenance rebuilt `files`)
     conn.execute_batch(
-        r#"
-        CREATE TABLE IF NOT EXISTS volumes (
-            id INTEGER PRIMARY KEY,
-            drive_letter TEXT NOT NULL UNIQUE,
-            volume_serial TEXT NOT NULL,
-            fs_type TEXT NOT NULL,
-            last_usn INTEGER,
-            usn_journal_id INTEGER,
-            last_scan_time INTEGER,
-            state TEXT NOT NULL DEFAULT 'online',
-            offline_since INTEGER
-        );
+        "-- Index for fast filename search (case-insensitive)
+         CREATE INDEX IF NOT EXISTS idx_files_name ON files(name COLLATE NOCASE);
+
+         -- Index for path reconstruction (parent lookups)
+         CREATE INDEX IF NOT EXISTS idx_files_parent ON files(volume_id, parent_ref);
+
+         -- Index for volume-based operations
+         CREATE INDEX IF NOT EXISTS idx_files_volume ON files(volume_id);
+
+         CREATE INDEX IF NOT EXISTS idx_files_path ON files(volume_id, full_path COLLATE NOCASE);
+         CREATE INDEX IF NOT EXISTS idx_files_ext ON files(ext);
+         CREATE INDEX IF NOT EXISTS idx_files_modified ON files(modified);",
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to create file indexes: {}", e)))?;
+
+    init_name_index(conn)?;
+
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS file_contents_delete AFTER DELETE ON files BEGIN
+             DELETE FROM file_contents WHERE rowid = old.id;
+         END;",
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to create file contents trigger: {}", e)))?;
+
+    Ok(())
+}
+
+/// Create the trigram name index and the triggers maintaining it.
+///
+/// An index created on an existing database is filled from `files`.
+fn init_name_index(conn: &Connection) -> Result<()> {
+    // INSERT OR REPLACE only fires delete triggers for the replaced row
+    // with recursive triggers on; without them its name stays indexed
+    conn.pragma_update(None, "recursive_triggers", true)
+        .map_err(|e| FFIError::Database(format!("Failed to enable recursive triggers: {}", e)))?;
+
+    let exists: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'files_fts'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to inspect schema: {}", e)))?;
 
-        CREATE TABLE IF NOT EXISTS files (
-            id INTEGER PRIMARY KEY,
-            volume_id INTEGER NOT NULL REFERENCES volumes(id),
-            file_ref INTEGER,
-            parent_ref INTEGER,
-            name TEXT NOT NULL,
-            size INTEGER NOT NULL DEFAULT 0,
-            modified INTEGER,
-            is_dir INTEGER NOT NULL DEFAULT 0,
-            UNIQUE(volume_id, file_ref)
+    conn.execute_batch(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS files_fts USING fts5(
+            name, content = 'files', content_rowid = 'id', tokenize = 'trigram'
         );
 
-        -- Index for fast filename search (case-insensitive)
-        CREATE INDEX IF NOT EXISTS idx_files_name ON files(name COLLATE NOCASE);
+        CREATE TRIGGER IF NOT EXISTS files_fts_insert AFTER INSERT ON files BEGIN
+            INSERT INTO files_fts (rowid, name) VALUES (new.id, new.name);
+        END;
 
-        -- Index for path reconstruction (parent lookups)
-        CREATE INDEX IF NOT EXISTS idx_files_parent ON files(volume_id, parent_ref);
+        CREATE TRIGGER IF NOT EXISTS files_fts_delete AFTER DELETE ON files BEGIN
+            INSERT INTO files_fts (files_fts, rowid, name) VALUES ('delete', old.id, old.name);
+        END;
 
-        -- Index for volume-based operations
-        CREATE INDEX IF NOT EXISTS idx_files_volume ON files(volume_id);
+        -- Rescans rewrite every name; only actual renames touch the index
+        CREATE TRIGGER IF NOT EXISTS files_fts_update AFTER UPDATE OF name ON files
+        WHEN old.name IS NOT new.name BEGIN
+            INSERT INTO files_fts (files_fts, rowid, name) VALUES ('delete', old.id, old.name);
+            INSERT INTO files_fts (rowid, name) VALUES (new.id, new.name);
+        END;
         "#,
     )
-    .map_err(|e| FFIError::Database(format!("Failed to initialize schema: {}", e)))?;
+    .map_err(|e| FFIError::Database(format!("Failed to create name index: {}", e)))?;
 
+    if !exists {
+        conn.execute_batch("INSERT INTO files_fts (files_fts) VALUES ('rebuild')")
+            .map_err(|e| FFIError::Database(format!("Failed to build name index: {}", e)))?;
+    }
     Ok(())
 }
 
@@ -139,4 +300,109 @@ mod tests {
         init(&conn).unwrap();
         init(&conn).unwrap();
     }
+
+    #[test]
+    fn test_schema_init_migrates_volume_stats() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE volumes (
+                id INTEGER PRIMARY KEY,
+                drive_letter TEXT NOT NULL UNIQUE,
+                volume_serial TEXT NOT NULL,
+                fs_type TEXT NOT NULL,
+                last_usn INTEGER,
+                usn_journal_id INTEGER,
+                last_scan_time INTEGER,
+                state TEXT NOT NULL DEFAULT 'online',
+                offline_since INTEGER
+            );
+            INSERT INTO volumes (drive_letter, volume_serial, fs_type) VALUES ('C:', '', 'NTFS');",
+        )
+        .unwrap();
+
+        init(&conn).unwrap();
+
+        let file_count: i64 = conn
+            .query_row("SELECT file_count FROM volumes WHERE drive_letter = 'C:'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(file_count, 0);
+    }
+
+    #[test]
+    fn test_schema_init_migrates_full_path() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE volumes (
+                id INTEGER PRIMARY KEY,
+                drive_letter TEXT NOT NULL UNIQUE,
+                volume_serial TEXT NOT NULL,
+                fs_type TEXT NOT NULL,
+                last_usn INTEGER,
+                usn_journal_id INTEGER,
+                last_scan_time INTEGER,
+                state TEXT NOT NULL DEFAULT 'online',
+                offline_since INTEGER
+            );
+            CREATE TABLE files (
+                id INTEGER PRIMARY KEY,
+                volume_id INTEGER NOT NULL REFERENCES volumes(id),
+                file_ref INTEGER,
+                parent_ref INTEGER,
+                name TEXT NOT NULL,
+                size INTEGER NOT NULL DEFAULT 0,
+                modified INTEGER,
+                is_dir INTEGER NOT NULL DEFAULT 0,
+                UNIQUE(volume_id, file_ref)
+            );
+            INSERT INTO volumes (id, drive_letter, volume_serial, fs_type) VALUES (1, 'C:', '', 'NTFS');
+            INSERT INTO files (volume_id, file_ref, parent_ref, name, is_dir) VALUES
+                (1, 5, 5, '.', 1),
+                (1, 100, 5, 'Users', 1),
+                (1, 200, 100, 'notes.txt', 0),
+                (1, 250, 100, 'Report.PDF', 0),
+                (1, 300, 100, 'archive.tar.gz', 0),
+                (1, 400, 100, 'trailing.', 0);",
+        )
+        .unwrap();
+
+        init(&conn).unwrap();
+
+        let path: String = conn
+            .query_row("SELECT full_path FROM files WHERE file_ref = 200", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(path, r"Users\notes.txt");
+
+        // Extensions are filled in for existing rows, as on insert
+        let mut stmt = conn.prepare("SELECT name, ext FROM files ORDER BY file_ref").unwrap();
+        let rows: Vec<(String, Option<String>)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        let exts: Vec<Option<&str>> = rows.iter().map(|(_, ext)| ext.as_deref()).collect();
+        assert_eq!(exts, vec![None, None, Some("txt"), Some("pdf"), Some("gz"), None]);
+        for (name, ext) in &rows {
+            assert_eq!(&crate::db::file_extension(name), ext);
+        }
+
+        // Rebuilt for hard links and streams: a second name or a stream of a
+        // file gets its own row, and the name index still finds the old rows
+        conn.execute_batch(
+            "INSERT INTO files (volume_id, file_ref, parent_ref, name, link) VALUES (1, 200, 5, 'notes-link.txt', 1);
+             INSERT INTO files (volume_id, file_ref, parent_ref, name, stream)
+                 VALUES (1, 200, 100, 'notes.txt:Zone.Identifier', 'Zone.Identifier');",
+        )
+        .unwrap();
+        let names: Vec<String> = conn
+            .prepare(
+                "SELECT name FROM files WHERE id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH 'notes')
+                 ORDER BY link, stream",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(names, vec!["notes.txt", "notes.txt:Zone.Identifier", "notes-link.txt"]);
+    }
 }
@@ -0,0 +1,416 @@
+//! Portable volume snapshots for cataloging offline disks.
+//!
+//! A snapshot is a standalone SQLite file holding one volume's `files` rows
+//! (without database IDs) plus a small `snapshot_meta` key/value table.
+//! Another FFI instance can import it as an [`VolumeState::Imported`] volume,
+//! which stays searchable and is never rescanned or cleaned up.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+use super::facets::rebuild_facet_counts;
+use super::ops::{file_extension, rebuild_full_paths, update_volume_stats};
+use crate::{FFIError, Result, VolumeState};
+
+/// Snapshot file format version, bumped on incompatible layout changes.
+pub const SNAPSHOT_FORMAT_VERSION: i64 = 1;
+
+/// Metadata describing a volume snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    /// Snapshot file format version
+    pub format_version: i64,
+    /// Drive letter the volume was indexed under (e.g., "E:")
+    pub drive_letter: String,
+    /// Volume serial number
+    pub volume_serial: String,
+    /// Filesystem type ("NTFS", "FAT32", "exFAT")
+    pub fs_type: String,
+    /// Unix timestamp when the snapshot was exported
+    pub exported_at: i64,
+    /// Number of file entries in the snapshot
+    pub file_count: i64,
+}
+
+impl SnapshotInfo {
+    /// Default name for the imported volume, e.g. `E: [1234-ABCD]`.
+    ///
+    /// Distinct from live drive letters so an import never collides with
+    /// a mounted volume.
+    pub fn default_import_name(&self) -> String {
+        format!("{} [{}]", self.drive_letter, self.volume_serial)
+    }
+}
+
+/// Export a volume to a portable snapshot file.
+///
+/// Overwrites `dest` if it already exists.
+///
+/// # Arguments
+/// * `conn` - Source database connection
+/// * `drive_letter` - Volume to export (e.g., "E:")
+/// * `dest` - Path of the snapshot file to create
+///
+/// # Returns
+/// Metadata written to the snapshot.
+pub fn export_volume_snapshot(conn: &Connection, drive_letter: &str, dest: &Path) -> Result<SnapshotInfo> {
+    let volume = super::get_volume(conn, drive_letter)?
+        .ok_or_else(|| FFIError::Database(format!("Volume {} not found", drive_letter)))?;
+
+    if dest.exists() {
+        std::fs::remove_file(dest)?;
+    }
+
+    let mut snap = Connection::open(dest)
+        .map_err(|e| FFIError::Database(format!("Failed to create snapshot: {}", e)))?;
+    snap.execute_batch(
+        r#"
+        CREATE TABLE snapshot_meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE files (
+            file_ref INTEGER,
+            parent_ref INTEGER,
+            name TEXT NOT NULL,
+            size INTEGER NOT NULL DEFAULT 0,
+            modified INTEGER,
+            is_dir INTEGER NOT NULL DEFAULT 0
+        );
+        "#,
+    )
+    .map_err(|e| FFIError::Database(format!("Failed to create snapshot schema: {}", e)))?;
+
+    let tx = snap
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+    let mut file_count = 0i64;
+    {
+        let mut select = conn
+            .prepare("SELECT file_ref, parent_ref, name, size, modified, is_dir FROM files WHERE volume_id = ?1 AND link = 0 AND stream = ''")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare export: {}", e)))?;
+        let mut insert = tx
+            .prepare("INSERT INTO files (file_ref, parent_ref, name, size, modified, is_dir) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare snapshot insert: {}", e)))?;
+
+        let rows = select
+            .query_map(params![volume.id], |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                    row.get::<_, i32>(5)?,
+                ))
+            })
+            .map_err(|e| FFIError::Database(format!("Failed to read volume files: {}", e)))?;
+
+        for row in rows {
+            let (file_ref, parent_ref, name, size, modified, is_dir) =
+                row.map_err(|e| FFIError::Database(format!("Failed to read row: {}", e)))?;
+            insert
+                .execute(params![file_ref, parent_ref, name, size, modified, is_dir])
+                .map_err(|e| FFIError::Database(format!("Failed to write snapshot row: {}", e)))?;
+            file_count += 1;
+        }
+    }
+
+    let info = SnapshotInfo {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        drive_letter: volume.drive_letter,
+        volume_serial: volume.volume_serial,
+        fs_type: volume.fs_type,
+        exported_at: chrono::Utc::now().timestamp(),
+        file_count,
+    };
+
+    for (key, value) in [
+        ("format_version", info.format_version.to_string()),
+        ("drive_letter", info.drive_letter.clone()),
+        ("volume_serial", info.volume_serial.clone()),
+        ("fs_type", info.fs_type.clone()),
+        ("exported_at", info.exported_at.to_string()),
+        ("file_count", info.file_count.to_string()),
+    ] {
+        tx.execute(
+            "INSERT INTO snapshot_meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to write snapshot metadata: {}", e)))?;
+    }
+
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit snapshot: {}", e)))?;
+
+    tracing::info!(
+        "Exported {} files from {} to snapshot {:?}",
+        info.file_count,
+        info.drive_letter,
+        dest
+    );
+    Ok(info)
+}
+
+/// Read snapshot metadata without importing it.
+///
+/// # Errors
+/// Returns error if the file is not a snapshot or uses a newer format version.
+pub fn read_snapshot_info(path: &Path) -> Result<SnapshotInfo> {
+    let snap = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| FFIError::Database(format!("Failed to open snapshot: {}", e)))?;
+    read_meta(&snap)
+}
+
+/// Import a snapshot as an offline, searchable volume.
+///
+/// The volume is stored under `name` (defaults to
+/// [`SnapshotInfo::default_import_name`]). Re-importing under the same name
+/// replaces the previous import; a live volume with that name is never touched.
+///
+/// # Arguments
+/// * `conn` - Destination database connection
+/// * `src` - Snapshot file to import
+/// * `name` - Optional volume name to import as
+///
+/// # Returns
+/// Metadata of the imported snapshot.
+pub fn import_volume_snapshot(conn: &mut Connection, src: &Path, name: Option<&str>) -> Result<SnapshotInfo> {
+    let snap = Connection::open_with_flags(src, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| FFIError::Database(format!("Failed to open snapshot: {}", e)))?;
+    let info = read_meta(&snap)?;
+    let name = name.map(str::to_string).unwrap_or_else(|| info.default_import_name());
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+
+    let existing: Option<(i64, String)> = tx
+        .query_row(
+            "SELECT id, state FROM volumes WHERE drive_letter = ?1 ORDER BY state = 'imported' DESC LIMIT 1",
+            params![name],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| FFIError::Database(format!("Failed to look up volume: {}", e)))?;
+
+    let volume_id = match existing {
+        Some((id, state)) if state == VolumeState::Imported.to_db_str() => {
+            tx.execute("DELETE FROM files WHERE volume_id = ?1", params![id])
+                .map_err(|e| FFIError::Database(format!("Failed to clear previous import: {}", e)))?;
+            tx.execute(
+                "UPDATE volumes SET volume_serial = ?1, fs_type = ?2, last_scan_time = ?3 WHERE id = ?4",
+                params![info.volume_serial, info.fs_type, info.exported_at, id],
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to update volume: {}", e)))?;
+            id
+        }
+        Some(_) => {
+            return Err(FFIError::Database(format!(
+                "Volume {} already exists and is not an imported snapshot",
+                name
+            )));
+        }
+        None => {
+            tx.execute(
+                "INSERT INTO volumes (drive_letter, volume_serial, fs_type, last_scan_time, state)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    name,
+                    info.volume_serial,
+                    info.fs_type,
+                    info.exported_at,
+                    VolumeState::Imported.to_db_str()
+                ],
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to insert volume: {}", e)))?;
+            tx.last_insert_rowid()
+        }
+    };
+
+    {
+        let mut select = snap
+            .prepare("SELECT file_ref, parent_ref, name, size, modified, is_dir FROM files")
+            .map_err(|e| FFIError::Database(format!("Failed to read snapshot files: {}", e)))?;
+        let mut insert = tx
+            .prepare_cached(
+                "INSERT OR REPLACE INTO files (volume_id, file_ref, parent_ref, name, size, modified, is_dir, ext)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )
+            .map_err(|e| FFIError::Database(format!("Failed to prepare insert: {}", e)))?;
+
+        let rows = select
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                    row.get::<_, i32>(5)?,
+                ))
+            })
+            .map_err(|e| FFIError::Database(format!("Failed to read snapshot files: {}", e)))?;
+
+        for row in rows {
+            let (file_ref, parent_ref, file_name, size, modified, is_dir) =
+                row.map_err(|e| FFIError::Database(format!("Failed to read snapshot row: {}", e)))?;
+            let ext = file_extension(&file_name);
+            insert
+                .execute(params![volume_id, file_ref, parent_ref, file_name, size, modified, is_dir, ext])
+                .map_err(|e| FFIError::Database(format!("Failed to import file: {}", e)))?;
+        }
+    }
+    rebuild_full_paths(&tx, volume_id)?;
+
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit import: {}", e)))?;
+    update_volume_stats(conn, volume_id, 0)?;
+    rebuild_facet_counts(conn, volume_id)?;
+
+    tracing::info!("Imported {} files from snapshot {:?} as {}", info.file_count, src, name);
+    Ok(info)
+}
+
+/// Read and validate the `snapshot_meta` table.
+fn read_meta(snap: &Connection) -> Result<SnapshotInfo> {
+    let get = |key: &str| -> Result<String> {
+        snap.query_row(
+            "SELECT value FROM snapshot_meta WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .map_err(|e| FFIError::Database(format!("Invalid snapshot (missing {}): {}", key, e)))
+    };
+    let get_i64 = |key: &str| -> Result<i64> {
+        get(key)?
+            .parse()
+            .map_err(|e| FFIError::Database(format!("Invalid snapshot {}: {}", key, e)))
+    };
+
+    let format_version = get_i64("format_version")?;
+    if format_version > SNAPSHOT_FORMAT_VERSION {
+        return Err(FFIError::Database(format!(
+            "Snapshot format version {} is newer than supported version {}",
+            format_version, SNAPSHOT_FORMAT_VERSION
+        )));
+    }
+
+    Ok(SnapshotInfo {
+        format_version,
+        drive_letter: get("drive_letter")?,
+        volume_serial: get("volume_serial")?,
+        fs_type: get("fs_type")?,
+        exported_at: get_i64("exported_at")?,
+        file_count: get_i64("file_count")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, get_volume, get_volume_state, insert_volume, reconstruct_path, schema, FileEntry};
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        conn
+    }
+
+    fn populate(conn: &mut Connection) {
+        let volume_id = insert_volume(conn, "E:", "1234-ABCD", "exFAT").unwrap();
+        let files = vec![
+            FileEntry {
+                volume_id,
+                file_ref: Some(5),
+                name: "".to_string(),
+                is_dir: true,
+                ..Default::default()
+            },
+            FileEntry {
+                volume_id,
+                file_ref: Some(100),
+                parent_ref: Some(5),
+                name: "Photos".to_string(),
+                modified: Some(1700000000),
+                is_dir: true,
+                ..Default::default()
+            },
+            FileEntry {
+                volume_id,
+                file_ref: Some(200),
+                parent_ref: Some(100),
+                name: "beach.jpg".to_string(),
+                size: 2048,
+                modified: Some(1700000100),
+                ..Default::default()
+            },
+        ];
+        batch_insert_files(conn, &files).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let dir = std::env::temp_dir().join("ffi_test_snapshot_roundtrip");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.ffisnap");
+
+        let mut source = setup_test_db();
+        populate(&mut source);
+        let exported = export_volume_snapshot(&source, "E:", &path).unwrap();
+        assert_eq!(exported.file_count, 3);
+        assert_eq!(read_snapshot_info(&path).unwrap(), exported);
+
+        let mut target = setup_test_db();
+        let imported = import_volume_snapshot(&mut target, &path, None).unwrap();
+        assert_eq!(imported.volume_serial, "1234-ABCD");
+
+        let volume = get_volume(&target, "E: [1234-ABCD]").unwrap().unwrap();
+        assert_eq!(get_volume_state(&target, volume.id).unwrap(), VolumeState::Imported);
+        assert_eq!(
+            reconstruct_path(&target, volume.id, 200).unwrap(),
+            std::path::PathBuf::from("Photos").join("beach.jpg")
+        );
+        assert_eq!(
+            crate::db::get_full_path(&target, volume.id, 200).unwrap().as_deref(),
+            Some(r"Photos\beach.jpg")
+        );
+
+        // Re-importing replaces the previous import rather than duplicating it
+        import_volume_snapshot(&mut target, &path, None).unwrap();
+        assert_eq!(crate::db::get_file_count(&target, Some(volume.id)).unwrap(), 3);
+        let stats = crate::db::get_volume_stats(&target, volume.id).unwrap();
+        assert_eq!(stats.file_count + stats.dir_count, 3);
+        assert_eq!(stats.last_scan_time, Some(exported.exported_at));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_import_does_not_overwrite_live_volume() {
+        let dir = std::env::temp_dir().join("ffi_test_snapshot_live");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.ffisnap");
+
+        let mut source = setup_test_db();
+        populate(&mut source);
+        export_volume_snapshot(&source, "E:", &path).unwrap();
+
+        let mut target = setup_test_db();
+        insert_volume(&target, "E:", "9999-0000", "NTFS").unwrap();
+        assert!(import_volume_snapshot(&mut target, &path, Some("E:")).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_export_unknown_volume() {
+        let conn = setup_test_db();
+        let path = std::env::temp_dir().join("ffi_test_snapshot_missing.ffisnap");
+        assert!(export_volume_snapshot(&conn, "Z:", &path).is_err());
+    }
+}
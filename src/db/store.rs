@@ -0,0 +1,348 @@
+//! Storage backend abstraction.
+//!
+//! [`Store`] covers the operations on the index hot paths: scan writers
+//! insert batches, USN monitors apply changes, and the IPC server searches
+//! and resolves paths. [`Database`] (SQLite) is the default implementation;
+//! other backends (in-memory, LMDB, sharded) implement the same trait so
+//! they can be tried and benchmarked behind those callers.
+
+use crate::indexer::{apply_changes_batch, UsnChange};
+use crate::search::ParsedQuery;
+use crate::service::ExcludeMatcher;
+use crate::Result;
+
+use super::ops::{
+    batch_insert_files, count_query_matches, get_full_path, query_files_by_relevance,
+    query_files_page, reconstruct_path_checked, FileEntry,
+};
+use super::Database;
+
+/// Persistence operations used by the indexer and IPC server.
+pub trait Store {
+    /// Insert or update a batch of scanned entries.
+    ///
+    /// # Returns
+    /// Number of entries written.
+    fn insert_batch(&mut self, files: &[FileEntry]) -> Result<usize>;
+
+    /// Apply deduplicated USN changes to a volume's entries.
+    ///
+    /// # Arguments
+    /// * `volume_id` - Volume the changes belong to
+    /// * `changes` - Changes, at most one per file reference
+    /// * `exclude` - Excludes; entries that become excluded are removed
+    ///
+    /// # Returns
+    /// Number of changes applied.
+    fn apply_changes(
+        &mut self,
+        volume_id: i64,
+        changes: &[UsnChange],
+        exclude: &ExcludeMatcher,
+    ) -> Result<usize>;
+
+    /// Search for entries matching a parsed query, in its sort order.
+    ///
+    /// Entries with attributes the user chose to hide (see
+    /// [`Database::set_excluded_attributes`]) are left out.
+    ///
+    /// # Arguments
+    /// * `query` - Parsed query (pattern, filters, conditions and sort)
+    /// * `limit` - Maximum number of results
+    /// * `offset` - Number of matches to skip, for later pages
+    fn search(&self, query: &ParsedQuery, limit: usize, offset: usize) -> Result<Vec<FileEntry>>;
+
+    /// Search like [`Store::search`], but exact name matches first, then
+    /// prefix matches, then other matches; the query's sort is ignored.
+    ///
+    /// Backends without relevance ordering return [`Store::search`]'s order.
+    fn search_by_relevance(&self, query: &ParsedQuery, limit: usize, offset: usize) -> Result<Vec<FileEntry>> {
+        self.search(query, limit, offset)
+    }
+
+    /// Number of entries [`Store::search`] matches in total, across all pages.
+    fn count(&self, query: &ParsedQuery) -> Result<usize>;
+
+    /// Path of an entry relative to its volume root.
+    ///
+    /// Paths cut short by a broken parent chain start with `...\`.
+    fn reconstruct_path(&self, volume_id: i64, file_ref: i64) -> Result<String>;
+
+    /// Path of a search result relative to its volume root.
+    ///
+    /// Further hard links and alternate data streams of a file share its
+    /// reference, so their paths are built from the parent directory and the
+    /// row's own name (`report.docx:Zone.Identifier` for a stream).
+    ///
+    /// # Returns
+    /// The path, or `None` for entries without a file reference.
+    fn entry_path(&self, entry: &FileEntry) -> Result<Option<String>> {
+        let Some(file_ref) = entry.file_ref else {
+            return Ok(None);
+        };
+        match entry.parent_ref {
+            Some(parent_ref) if entry.link > 0 || entry.stream.is_some() => {
+                let parent = self.reconstruct_path(entry.volume_id, parent_ref)?;
+                Ok(Some(if parent.is_empty() {
+                    entry.name.clone()
+                } else {
+                    format!("{}\\{}", parent, entry.name)
+                }))
+            }
+            _ => self.reconstruct_path(entry.volume_id, file_ref).map(Some),
+        }
+    }
+}
+
+impl Store for Database {
+    fn insert_batch(&mut self, files: &[FileEntry]) -> Result<usize> {
+        batch_insert_files(self.conn_mut(), files)
+    }
+
+    fn apply_changes(
+        &mut self,
+        volume_id: i64,
+        changes: &[UsnChange],
+        exclude: &ExcludeMatcher,
+    ) -> Result<usize> {
+        apply_changes_batch(self, volume_id, changes, exclude)
+    }
+
+    fn search(&self, query: &ParsedQuery, limit: usize, offset: usize) -> Result<Vec<FileEntry>> {
+        query_files_page(self.conn(), &self.visible(query), limit, offset)
+    }
+
+    fn search_by_relevance(&self, query: &ParsedQuery, limit: usize, offset: usize) -> Result<Vec<FileEntry>> {
+        query_files_by_relevance(self.conn(), &self.visible(query), limit, offset)
+    }
+
+    fn count(&self, query: &ParsedQuery) -> Result<usize> {
+        count_query_matches(self.conn(), &self.visible(query))
+    }
+
+    fn reconstruct_path(&self, volume_id: i64, file_ref: i64) -> Result<String> {
+        // Stored path, walking the parents only where it could not be resolved
+        if let Some(path) = get_full_path(self.conn(), volume_id, file_ref)? {
+            return Ok(path);
+        }
+
+        let reconstructed = reconstruct_path_checked(self.conn(), volume_id, file_ref)?;
+        Ok(if reconstructed.truncated {
+            format!("...\\{}", reconstructed.path.display())
+        } else {
+            reconstructed.path.display().to_string()
+        })
+    }
+}
+
+impl Database {
+    /// The query with the excluded attributes and the Recycle Bin hidden.
+    fn visible(&self, query: &ParsedQuery) -> ParsedQuery {
+        let mut query = query.clone();
+        query.exclude_attributes(self.excluded_attributes());
+        query.exclude_recycle_bin();
+        query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_volume, open_database};
+    use crate::indexer::ChangeType;
+    use crate::search::{parse_query, FileAttribute};
+
+    /// Index, change and search through the trait only.
+    fn exercise(store: &mut impl Store, volume_id: i64) {
+        let entry = |file_ref: i64, parent_ref: i64, name: &str, is_dir: bool| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            is_dir,
+            ..Default::default()
+        };
+        let inserted = store
+            .insert_batch(&[entry(5, 5, ".", true), entry(10, 5, "Docs", true), entry(11, 10, "draft.txt", false)])
+            .unwrap();
+        assert_eq!(inserted, 3);
+
+        let rename = UsnChange {
+            file_ref: 11,
+            parent_ref: 10,
+            name: "report.txt".to_string(),
+            change_type: ChangeType::Rename,
+            is_dir: false,
+            old_parent_ref: None,
+            old_name: None,
+        };
+        assert_eq!(store.apply_changes(volume_id, &[rename], &ExcludeMatcher::default()).unwrap(), 1);
+
+        let query = |text: &str| parse_query(text).unwrap();
+        let found = store.search(&query("report"), 10, 0).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(store.search(&query("draft"), 10, 0).unwrap().is_empty());
+        assert_eq!(store.count(&query("report")).unwrap(), 1);
+        assert!(store.search(&query("report"), 10, 1).unwrap().is_empty());
+        assert_eq!(store.count(&query("ext:txt")).unwrap(), 1);
+        assert_eq!(store.reconstruct_path(volume_id, 11).unwrap(), r"Docs\report.txt");
+
+        // A second hard link of the file, in another directory
+        let mut link = entry(11, 5, "report-link.txt", false);
+        link.link = 1;
+        store.insert_batch(&[link.clone()]).unwrap();
+        assert_eq!(store.entry_path(&link).unwrap().as_deref(), Some("report-link.txt"));
+        assert_eq!(store.reconstruct_path(volume_id, 11).unwrap(), r"Docs\report.txt");
+        assert_eq!(store.search(&query("report"), 10, 0).unwrap().len(), 2);
+
+        // An alternate data stream sits beside its file
+        let mut stream = entry(11, 10, "report.txt:Zone.Identifier", false);
+        stream.stream = Some("Zone.Identifier".to_string());
+        store.insert_batch(&[stream]).unwrap();
+        let found = store.search(&query("stream:*"), 10, 0).unwrap();
+        assert_eq!(found[0].stream.as_deref(), Some("Zone.Identifier"));
+        assert_eq!(store.entry_path(&found[0]).unwrap().as_deref(), Some(r"Docs\report.txt:Zone.Identifier"));
+        assert_eq!(store.reconstruct_path(volume_id, 11).unwrap(), r"Docs\report.txt");
+    }
+
+    #[test]
+    fn test_database_excluded_attributes() {
+        use crate::search::FileAttribute;
+
+        let temp_dir = std::env::temp_dir().join("ffi_test_store_attributes");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let entry = |file_ref: i64, name: &str, attributes: u32| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(5),
+            name: name.to_string(),
+            attributes,
+            ..Default::default()
+        };
+        db.insert_batch(&[entry(1, "notes.txt", 0x20), entry(2, "desktop.ini", 0x2 | 0x4), entry(3, "notes.bak", 0x2)])
+            .unwrap();
+
+        let all = ParsedQuery::default();
+        assert_eq!(db.search(&all, 10, 0).unwrap().len(), 3);
+        db.set_excluded_attributes(&[FileAttribute::System]);
+        assert_eq!(db.search(&all, 10, 0).unwrap().len(), 2);
+        db.set_excluded_attributes(&[FileAttribute::Hidden, FileAttribute::System]);
+        let names: Vec<String> = db.search_by_relevance(&all, 10, 0).unwrap().into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["notes.txt"]);
+        assert_eq!(db.count(&all).unwrap(), 1);
+
+        // Asking for an attribute shows those entries again
+        let hidden = parse_query("attrib:hidden").unwrap();
+        assert_eq!(db.count(&hidden).unwrap(), 2);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_database_recycle_bin() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_store_recycle_bin");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let entry = |file_ref: i64, parent_ref: i64, name: &str, is_dir: bool| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            is_dir,
+            ..Default::default()
+        };
+        db.insert_batch(&[
+            entry(5, 5, ".", true),
+            entry(10, 5, "$Recycle.Bin", true),
+            entry(11, 10, "S-1-5-21-1000", true),
+            entry(20, 11, "$IAB12CD.txt", false),
+            entry(21, 11, "$RAB12CD.txt", false),
+            entry(30, 11, "$RXY34ZW", true),
+            entry(31, 30, "notes.txt", false),
+            entry(40, 5, "notes.txt", false),
+        ])
+        .unwrap();
+
+        let count = |text: &str| db.count(&parse_query(text).unwrap()).unwrap();
+        assert_eq!(count("notes"), 1);
+        assert_eq!(count("*.txt"), 1);
+        // The deleted items and what is below them, not the `$I` files
+        assert_eq!(count("in:recyclebin"), 3);
+        assert_eq!(count("notes in:recyclebin"), 1);
+        assert_eq!(count("*.txt -in:recyclebin"), 1);
+        assert_eq!(count(r"path:C:\$Recycle.Bin\S-1-5-21-1000"), 4);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_database_recent_files() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_store_recent");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+        db.set_excluded_attributes(&[FileAttribute::Hidden]);
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let entry = |file_ref: i64, name: &str, modified: i64| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(5),
+            name: name.to_string(),
+            modified: Some(modified),
+            is_dir: name == "Reports",
+            attributes: if name == "desktop.ini" { FileAttribute::Hidden.flag() } else { 0 },
+            ..Default::default()
+        };
+        db.insert_batch(&[
+            entry(10, "old.txt", 1_600_000_000),
+            entry(11, "report.docx", 1_700_000_300),
+            entry(12, "notes.txt", 1_700_000_100),
+            entry(13, "desktop.ini", 1_700_000_400),
+            entry(14, "Reports", 1_700_000_500),
+            FileEntry {
+                stream: Some("Zone.Identifier".to_string()),
+                ..entry(11, "report.docx:Zone.Identifier", 1_700_000_300)
+            },
+        ])
+        .unwrap();
+
+        let recent = ParsedQuery::recent_files(1_700_000_000);
+        let names: Vec<String> = db.search(&recent, 10, 0).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["report.docx", "notes.txt"]);
+
+        // Served newest first from the index on `modified`
+        let (sql, params) = crate::search::build_sql_query_page(&db.visible(&recent), 10, 0);
+        let plan: Vec<String> = db
+            .conn()
+            .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+            .unwrap()
+            .query_map(rusqlite::params_from_iter(params.iter()), |row| row.get(3))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert!(plan.iter().any(|step| step.contains("idx_files_modified")), "{:?}", plan);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_database_store() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_store");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        exercise(&mut db, volume_id);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,95 @@
+//! Tags users attach to files.
+//!
+//! A tag belongs to a file, not to a row: it is stored by the serial number
+//! of the file's volume and the file's reference, so it survives the volume
+//! coming back under another drive letter and the rows being rewritten by
+//! rescans. `tag:` filters match every row of a tagged file.
+
+use rusqlite::{params, Connection};
+
+use crate::{FFIError, Result};
+
+/// Attach tags to a file; tags it already has are left alone.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `volume_serial` - Serial number of the file's volume
+/// * `file_ref` - File reference of the file
+/// * `tags` - Tags to attach
+/// * `now` - Unix timestamp recorded with new tags
+pub fn add_tags(conn: &mut Connection, volume_serial: &str, file_ref: i64, tags: &[String], now: i64) -> Result<()> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+    {
+        let mut stmt = tx
+            .prepare_cached("INSERT OR IGNORE INTO tags (volume_serial, file_ref, tag, created) VALUES (?1, ?2, ?3, ?4)")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare tag insert: {}", e)))?;
+        for tag in tags {
+            stmt.execute(params![volume_serial, file_ref, tag, now])
+                .map_err(|e| FFIError::Database(format!("Failed to add tag: {}", e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit tags: {}", e)))
+}
+
+/// Remove tags from a file.
+///
+/// # Returns
+/// The number of tags the file had and no longer has.
+pub fn remove_tags(conn: &mut Connection, volume_serial: &str, file_ref: i64, tags: &[String]) -> Result<usize> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| FFIError::Database(format!("Failed to begin transaction: {}", e)))?;
+    let mut removed = 0;
+    {
+        let mut stmt = tx
+            .prepare_cached("DELETE FROM tags WHERE volume_serial = ?1 AND file_ref = ?2 AND tag = ?3")
+            .map_err(|e| FFIError::Database(format!("Failed to prepare tag delete: {}", e)))?;
+        for tag in tags {
+            removed += stmt
+                .execute(params![volume_serial, file_ref, tag])
+                .map_err(|e| FFIError::Database(format!("Failed to remove tag: {}", e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| FFIError::Database(format!("Failed to commit tags: {}", e)))?;
+    Ok(removed)
+}
+
+/// Get a file's tags, in alphabetical order.
+pub fn get_tags(conn: &Connection, volume_serial: &str, file_ref: i64) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare_cached("SELECT tag FROM tags WHERE volume_serial = ?1 AND file_ref = ?2 ORDER BY tag")
+        .map_err(|e| FFIError::Database(format!("Failed to prepare tag query: {}", e)))?;
+    let rows = stmt
+        .query_map(params![volume_serial, file_ref], |row| row.get(0))
+        .map_err(|e| FFIError::Database(format!("Failed to query tags: {}", e)))?;
+
+    rows.collect::<rusqlite::Result<Vec<String>>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read tag: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema;
+
+    #[test]
+    fn test_tags() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let tags = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        add_tags(&mut conn, "1234", 10, &tags(&["work", "Urgent"]), 1_700_000_000).unwrap();
+        add_tags(&mut conn, "1234", 10, &tags(&["WORK", "taxes 2024"]), 1_700_000_001).unwrap();
+        add_tags(&mut conn, "5678", 10, &tags(&["photos"]), 1_700_000_000).unwrap();
+        assert_eq!(get_tags(&conn, "1234", 10).unwrap(), tags(&["taxes 2024", "Urgent", "work"]));
+        assert_eq!(get_tags(&conn, "5678", 10).unwrap(), tags(&["photos"]));
+        assert!(get_tags(&conn, "1234", 11).unwrap().is_empty());
+
+        assert_eq!(remove_tags(&mut conn, "1234", 10, &tags(&["urgent", "missing"])).unwrap(), 1);
+        assert_eq!(get_tags(&conn, "1234", 10).unwrap(), tags(&["taxes 2024", "work"]));
+    }
+}
@@ -0,0 +1,149 @@
+//! Disk usage summaries: where the space under a scope goes.
+//!
+//! Sums the sizes of the files matching a query by extension or by the
+//! folder directly below the query's `path:` scope (see
+//! [`build_usage_query`]). Unlike searches, hidden and system files and
+//! the Recycle Bin are counted, since they take space like anything else.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::search::{build_usage_query, ParsedQuery};
+use crate::{FFIError, Result};
+
+/// What file sizes are summed by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGroup {
+    /// Lowercase extension, empty for files without one
+    Extension,
+    /// Folder directly below the scope
+    #[default]
+    Folder,
+}
+
+impl UsageGroup {
+    /// Grouping named on a command line ("ext", "folder").
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ext" | "extension" => Some(UsageGroup::Extension),
+            "folder" | "dir" => Some(UsageGroup::Folder),
+            _ => None,
+        }
+    }
+}
+
+/// Files of one group and the space they take.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageRow {
+    /// Extension, or full path of the folder
+    pub key: String,
+    /// Files in the group
+    pub files: i64,
+    /// Sum of their sizes in bytes
+    pub bytes: i64,
+}
+
+/// Sum the sizes of the files matching a query by extension or folder.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `parsed` - Query selecting the files; its first `path:` filter is the
+///   scope folders are grouped below
+/// * `group` - What sizes are summed by
+/// * `limit` - Most groups returned
+///
+/// # Returns
+/// The largest groups first.
+pub fn disk_usage(conn: &Connection, parsed: &ParsedQuery, group: UsageGroup, limit: usize) -> Result<Vec<UsageRow>> {
+    let (sql, params) = build_usage_query(parsed, group, limit as i64);
+    let mut stmt = conn
+        .prepare_cached(&sql)
+        .map_err(|e| FFIError::Database(format!("Failed to prepare disk usage query: {}", e)))?;
+
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            Ok(UsageRow {
+                key: row.get(0)?,
+                files: row.get(1)?,
+                bytes: row.get(2)?,
+            })
+        })
+        .map_err(|e| FFIError::Database(format!("Failed to query disk usage: {}", e)))?;
+
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| FFIError::Database(format!("Failed to read disk usage row: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, insert_volume, schema, FileEntry};
+    use crate::search::parse_query;
+
+    #[test]
+    fn test_disk_usage() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let c = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let d = insert_volume(&conn, "D:", "5678", "NTFS").unwrap();
+        let entry = |volume_id: i64, file_ref: i64, parent_ref: i64, name: &str, size: i64| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            size,
+            is_dir: size == 0,
+            ..Default::default()
+        };
+        batch_insert_files(
+            &mut conn,
+            &[
+                entry(c, 5, 5, ".", 0),
+                entry(c, 10, 5, "Users", 0),
+                entry(c, 11, 10, "me", 0),
+                entry(c, 12, 11, "movie.mkv", 4000),
+                entry(c, 13, 11, "notes.txt", 10),
+                entry(c, 14, 10, "Public", 0),
+                entry(c, 15, 14, "setup.exe", 500),
+                entry(c, 16, 10, "ntuser.txt", 20),
+                entry(c, 20, 5, "Windows", 0),
+                entry(c, 21, 20, "kernel.exe", 3000),
+                entry(c, 22, 5, "pagefile", 2000),
+                entry(d, 5, 5, ".", 0),
+                entry(d, 30, 5, "Backup", 0),
+                entry(d, 31, 30, "disk.img", 9000),
+            ],
+        )
+        .unwrap();
+
+        let usage = |query: &str, group: UsageGroup| {
+            disk_usage(&conn, &parse_query(query).unwrap(), group, 10)
+                .unwrap()
+                .into_iter()
+                .map(|row| (row.key, row.files, row.bytes))
+                .collect::<Vec<_>>()
+        };
+        let row = |key: &str, files: i64, bytes: i64| (key.to_string(), files, bytes);
+
+        assert_eq!(
+            usage("", UsageGroup::Folder),
+            vec![
+                row(r"D:\Backup", 1, 9000),
+                row(r"C:\Users", 4, 4530),
+                row(r"C:\Windows", 1, 3000),
+                row(r"C:\", 1, 2000)
+            ]
+        );
+        assert_eq!(
+            usage(r"path:c:\users", UsageGroup::Folder),
+            vec![row(r"C:\Users\me", 2, 4010), row(r"C:\Users\Public", 1, 500), row(r"C:\Users", 1, 20)]
+        );
+        assert_eq!(
+            usage(r"path:C:\Users (ext:exe OR ext:txt)", UsageGroup::Extension),
+            vec![row("exe", 1, 500), row("txt", 2, 30)]
+        );
+        assert_eq!(usage("drive:D:", UsageGroup::Extension), vec![row("img", 1, 9000)]);
+        assert_eq!(usage("", UsageGroup::Extension)[3], row("", 1, 2000));
+    }
+}
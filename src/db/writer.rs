@@ -0,0 +1,291 @@
+//! Single writer thread for index updates.
+//!
+//! SQLite runs one write transaction at a time. Rather than every scanner
+//! and USN monitor holding its own connection and taking turns on the write
+//! lock, they send [`WriteOp`]s to one thread that owns the writable
+//! connection and applies them in arrival order. Searches read through
+//! their own read-only connections (see [`DatabasePool`](super::DatabasePool))
+//! and never wait for it.
+//!
+//! The service's writer is registered for the process while it runs:
+//! [`apply_write`] sends an operation to it and waits for the result, or
+//! applies it on the caller's own connection when no writer runs (the
+//! command-line tools and tests).
+
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use super::{
+    apply_file_diff, begin_scan_tracking, checkpoint_wal, clear_scan_checkpoint, finish_scan_tracking, mark_scanned,
+    open_database, save_scan_checkpoint, update_volume_state, update_volume_usn, Database, FileEntry, ScanCheckpoint,
+    Store,
+};
+use crate::indexer::UsnChange;
+use crate::service::ExcludeMatcher;
+use crate::{FFIError, Result, VolumeState};
+
+/// Writer of the running service, if any.
+static WRITER: Mutex<Option<DbWriter>> = Mutex::new(None);
+
+/// A write to the index, applied by the writer thread.
+#[derive(Debug)]
+pub enum WriteOp {
+    /// Insert or update scanned entries
+    InsertBatch(Vec<FileEntry>),
+    /// Start tracking a full scan of a volume (see [`begin_scan_tracking`])
+    BeginScan { volume_id: i64 },
+    /// Insert or update entries found by a tracked scan and record them as seen
+    ScanBatch(Vec<FileEntry>),
+    /// Stop tracking a scan, deleting the entries it did not see if it completed
+    FinishScan { volume_id: i64, complete: bool },
+    /// Write a reconciliation's new and changed entries and delete the gone ones
+    ApplyFileDiff {
+        volume_id: i64,
+        upserts: Vec<FileEntry>,
+        deletes: Vec<i64>,
+    },
+    /// Apply deduplicated USN changes to a volume's entries
+    ApplyChanges {
+        volume_id: i64,
+        changes: Vec<UsnChange>,
+        exclude: Arc<ExcludeMatcher>,
+    },
+    /// Save the journal position a volume's monitor has processed
+    SaveUsnPosition { volume_id: i64, last_usn: i64, journal_id: u64 },
+    /// Change a volume's state
+    SetVolumeState { volume_id: i64, state: VolumeState },
+    /// Save how far a full scan of a volume got
+    SaveScanCheckpoint { volume_id: i64, checkpoint: ScanCheckpoint },
+    /// Forget a volume's scan checkpoint once the scan completed
+    ClearScanCheckpoint { volume_id: i64 },
+    /// Write everything committed so far into the database file
+    Flush,
+}
+
+impl WriteOp {
+    /// Apply the operation on a connection.
+    ///
+    /// # Returns
+    /// Entries or changes written, as counted by the underlying operation;
+    /// 0 for bookkeeping operations.
+    pub fn apply(self, db: &mut Database) -> Result<usize> {
+        match self {
+            WriteOp::InsertBatch(files) => db.insert_batch(&files),
+            WriteOp::BeginScan { volume_id } => begin_scan_tracking(db.conn(), volume_id).map(|_| 0),
+            WriteOp::ScanBatch(files) => {
+                let written = db.insert_batch(&files)?;
+                mark_scanned(db.conn_mut(), &files)?;
+                Ok(written)
+            }
+            WriteOp::FinishScan { volume_id, complete } => finish_scan_tracking(db.conn(), volume_id, complete),
+            WriteOp::ApplyFileDiff {
+                volume_id,
+                upserts,
+                deletes,
+            } => apply_file_diff(db.conn_mut(), volume_id, &upserts, &deletes),
+            WriteOp::ApplyChanges {
+                volume_id,
+                changes,
+                exclude,
+            } => db.apply_changes(volume_id, &changes, &exclude),
+            WriteOp::SaveUsnPosition {
+                volume_id,
+                last_usn,
+                journal_id,
+            } => update_volume_usn(db.conn(), volume_id, last_usn, journal_id as i64).map(|_| 0),
+            WriteOp::SetVolumeState { volume_id, state } => update_volume_state(db.conn(), volume_id, state).map(|_| 0),
+            WriteOp::SaveScanCheckpoint { volume_id, checkpoint } => {
+                save_scan_checkpoint(db.conn(), volume_id, &checkpoint).map(|_| 0)
+            }
+            WriteOp::ClearScanCheckpoint { volume_id } => clear_scan_checkpoint(db.conn(), volume_id).map(|_| 0),
+            WriteOp::Flush => checkpoint_wal(db.conn()).map(|_| 0),
+        }
+    }
+}
+
+/// Message to the writer thread.
+enum Request {
+    Write(WriteOp, Sender<Result<usize>>),
+    Stop,
+}
+
+/// Handle for sending writes to a writer thread; cheap to clone.
+#[derive(Clone)]
+pub struct DbWriter {
+    tx: Arc<Sender<Request>>,
+}
+
+impl DbWriter {
+    /// Apply an operation on the writer thread and wait for its result.
+    ///
+    /// # Errors
+    /// The operation's own error, or an error if the writer has stopped.
+    pub fn execute(&self, op: WriteOp) -> Result<usize> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.tx
+            .send(Request::Write(op, reply_tx))
+            .map_err(|_| FFIError::Database("Database writer has stopped".to_string()))?;
+        reply_rx
+            .recv()
+            .map_err(|_| FFIError::Database("Database writer stopped before applying the write".to_string()))?
+    }
+}
+
+/// The running service's writer, if any.
+pub fn current_writer() -> Option<DbWriter> {
+    WRITER.lock().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+/// Apply a write through the running writer, or on `db` if none runs.
+///
+/// # Arguments
+/// * `db` - The caller's connection, used only without a writer
+/// * `op` - The write to apply
+pub fn apply_write(db: &mut Database, op: WriteOp) -> Result<usize> {
+    match current_writer() {
+        Some(writer) => writer.execute(op),
+        None => op.apply(db),
+    }
+}
+
+/// Handle to a writer thread.
+pub struct WriterThread {
+    writer: DbWriter,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl WriterThread {
+    /// Start a writer thread owning `db`, without registering it for the
+    /// process (see [`start_db_writer`]).
+    pub fn spawn(db: Database) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::spawn(move || run_writer(db, rx));
+        Self {
+            writer: DbWriter { tx: Arc::new(tx) },
+            handle: Some(handle),
+        }
+    }
+
+    /// A handle for sending writes to this thread.
+    pub fn writer(&self) -> DbWriter {
+        self.writer.clone()
+    }
+
+    /// Stop the thread once the writes already sent are applied; later
+    /// writes fail.
+    pub fn stop(&mut self) {
+        if let Ok(mut current) = WRITER.lock() {
+            if current.as_ref().is_some_and(|writer| Arc::ptr_eq(&writer.tx, &self.writer.tx)) {
+                *current = None;
+            }
+        }
+
+        let _ = self.writer.tx.send(Request::Stop);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                tracing::error!("Database writer thread panicked");
+            }
+        }
+    }
+}
+
+/// Start the service's writer thread and register it for [`apply_write`].
+///
+/// # Arguments
+/// * `db_path` - Path to the database; the thread opens the only writable
+///   connection the indexers use
+///
+/// # Returns
+/// A `WriterThread` that can be used to stop the writer.
+pub fn start_db_writer(db_path: &Path) -> Result<WriterThread> {
+    let thread = WriterThread::spawn(open_database(db_path)?);
+    *WRITER.lock().unwrap_or_else(PoisonError::into_inner) = Some(thread.writer());
+    tracing::info!("Database writer started");
+    Ok(thread)
+}
+
+/// Apply writes in arrival order until stopped.
+fn run_writer(mut db: Database, rx: Receiver<Request>) {
+    for request in rx {
+        match request {
+            Request::Write(op, reply) => {
+                // The sender may have given up waiting
+                let _ = reply.send(op.apply(&mut db));
+            }
+            Request::Stop => break,
+        }
+    }
+    tracing::info!("Database writer stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_file_count, get_volume_usn, insert_volume, open_database_read_only};
+    use std::fs;
+
+    #[test]
+    fn test_writer_serializes_writes() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_writer");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let db_path = temp_dir.join("test.db");
+        let db = open_database(&db_path).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let mut thread = WriterThread::spawn(db);
+
+        // Several senders, one connection
+        let senders: Vec<_> = (0..4)
+            .map(|n| {
+                let writer = thread.writer();
+                thread::spawn(move || {
+                    let files = (0..250).map(|i| FileEntry::test(volume_id, n * 1000 + i, 5, "file.txt", false)).collect();
+                    writer.execute(WriteOp::InsertBatch(files)).unwrap()
+                })
+            })
+            .collect();
+        let written: usize = senders.into_iter().map(|sender| sender.join().unwrap()).sum();
+        assert_eq!(written, 1000);
+
+        let writer = thread.writer();
+        writer
+            .execute(WriteOp::SaveUsnPosition {
+                volume_id,
+                last_usn: 4096,
+                journal_id: 7,
+            })
+            .unwrap();
+        writer.execute(WriteOp::Flush).unwrap();
+        let reader = open_database_read_only(&db_path).unwrap();
+        assert_eq!(get_file_count(reader.conn(), Some(volume_id)).unwrap(), 1000);
+        assert_eq!(get_volume_usn(reader.conn(), volume_id).unwrap(), Some((4096, 7)));
+
+        thread.stop();
+        assert!(writer.execute(WriteOp::InsertBatch(vec![FileEntry::test(volume_id, 1, 5, "file.txt", false)])).is_err());
+
+        drop(reader);
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_tracked_scan_through_writer() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_writer_scan");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        WriteOp::InsertBatch((100..110).map(|i| FileEntry::test(volume_id, i, 5, "file.txt", false)).collect())
+            .apply(&mut db)
+            .unwrap();
+
+        let mut thread = WriterThread::spawn(db);
+        let writer = thread.writer();
+        writer.execute(WriteOp::BeginScan { volume_id }).unwrap();
+        let seen = (100..103).map(|i| FileEntry::test(volume_id, i, 5, "file.txt", false)).collect();
+        assert_eq!(writer.execute(WriteOp::ScanBatch(seen)).unwrap(), 3);
+        assert_eq!(writer.execute(WriteOp::FinishScan { volume_id, complete: true }).unwrap(), 7);
+
+        thread.stop();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
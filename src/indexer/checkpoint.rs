@@ -0,0 +1,200 @@
+//! Resumable full scans.
+//!
+//! A full scan saves how far it got after each batch it writes, so a scan
+//! cut short by a service stop or crash resumes there on the next start
+//! instead of starting over. While an initial index runs the volume shows
+//! as [`VolumeState::Indexing`] with the share done so far, and every scan
+//! reports its entry counts to the [`IndexingProgress`] registry.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use super::progress::{IndexingProgress, VolumeProgress};
+use crate::db::{apply_write, get_scan_checkpoint, get_volume_state, Database, ScanCheckpoint, WriteOp};
+use crate::{Result, VolumeState};
+
+/// Checkpoints and progress of one full scan of a volume; the scan leaves
+/// the progress registry when this is dropped.
+pub(crate) struct ScanProgress {
+    volume_id: i64,
+    /// Where the previous, interrupted scan stopped
+    resume: ScanCheckpoint,
+    /// Whether this scan shows the volume as indexing; rescans and shadow
+    /// copies keep their own state
+    indexing: bool,
+}
+
+impl ScanProgress {
+    /// Load the checkpoint of an interrupted scan of a volume and mark it
+    /// as indexing.
+    pub(crate) fn start(db: &mut Database, volume_id: i64) -> Result<Self> {
+        let resume = get_scan_checkpoint(db.conn(), volume_id)?;
+        let indexing = matches!(
+            get_volume_state(db.conn(), volume_id)?,
+            VolumeState::Online | VolumeState::Indexing { .. }
+        );
+        if indexing {
+            let state = VolumeState::Indexing { percent: resume.percent };
+            apply_write(db, WriteOp::SetVolumeState { volume_id, state })?;
+        }
+        IndexingProgress::global().report(
+            volume_id,
+            VolumeProgress {
+                processed: resume.processed,
+                total: resume.total,
+            },
+        );
+
+        Ok(Self {
+            volume_id,
+            resume,
+            indexing,
+        })
+    }
+
+    /// Where the previous scan stopped; the default if it completed.
+    pub(crate) fn resume(&self) -> &ScanCheckpoint {
+        &self.resume
+    }
+
+    /// Report how many entries this scan has processed so far, out of how
+    /// many it expects.
+    pub(crate) fn report(&self, processed: u64, total: u64) {
+        IndexingProgress::global().report(self.volume_id, VolumeProgress { processed, total });
+    }
+
+    /// Save how far this scan got; `checkpoint` must only cover entries
+    /// already written.
+    pub(crate) fn save(&self, db: &mut Database, checkpoint: ScanCheckpoint) -> Result<()> {
+        apply_write(
+            db,
+            WriteOp::SaveScanCheckpoint {
+                volume_id: self.volume_id,
+                checkpoint,
+            },
+        )
+        .map(|_| ())
+    }
+
+    /// Forget the checkpoint once the scan completed, and show the volume
+    /// as online again. An interrupted scan keeps its last checkpoint.
+    pub(crate) fn finish(self, db: &mut Database, complete: bool) -> Result<()> {
+        if !complete {
+            return Ok(());
+        }
+        apply_write(db, WriteOp::ClearScanCheckpoint { volume_id: self.volume_id })?;
+        if self.indexing {
+            let state = VolumeState::Online;
+            apply_write(db, WriteOp::SetVolumeState { volume_id: self.volume_id, state })?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ScanProgress {
+    fn drop(&mut self) {
+        IndexingProgress::global().finish(self.volume_id);
+    }
+}
+
+/// Share of `total` that `done` is, held below 100 until the scan finishes.
+pub(crate) fn percent_of(done: u64, total: u64) -> u8 {
+    match total {
+        0 => 0,
+        total => (done.saturating_mul(100) / total).min(99) as u8,
+    }
+}
+
+/// Lowest MFT record not yet written, as chunks parsed in parallel are
+/// written out of order.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) struct RecordWatermark {
+    /// Every record below this one is written
+    done_below: u64,
+    /// Written chunks above `done_below`, start to end
+    ahead: BTreeMap<u64, u64>,
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+impl RecordWatermark {
+    /// Start below the first record the scan reads.
+    pub(crate) fn new(start: u64) -> Self {
+        Self {
+            done_below: start,
+            ahead: BTreeMap::new(),
+        }
+    }
+
+    /// Record a chunk of records as written.
+    ///
+    /// # Returns
+    /// The record below which every record is now written.
+    pub(crate) fn written(&mut self, records: Range<u64>) -> u64 {
+        self.ahead.insert(records.start, records.end);
+        while let Some(end) = self.ahead.remove(&self.done_below) {
+            self.done_below = end;
+        }
+        self.done_below
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_volume, open_database, update_volume_state};
+
+    #[test]
+    fn test_watermark_waits_for_gaps() {
+        let mut watermark = RecordWatermark::new(100);
+        assert_eq!(watermark.written(200..300), 100);
+        assert_eq!(watermark.written(300..350), 100);
+        assert_eq!(watermark.written(100..200), 350);
+        assert_eq!(watermark.written(350..400), 400);
+    }
+
+    #[test]
+    fn test_percent_of() {
+        assert_eq!(percent_of(0, 0), 0);
+        assert_eq!(percent_of(1, 4), 25);
+        assert_eq!(percent_of(4, 4), 99);
+    }
+
+    #[test]
+    fn test_progress_state() {
+        let temp_dir = std::env::temp_dir().join("ffi_test_scan_progress");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let mut db = open_database(&temp_dir.join("test.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+
+        // Interrupted: the volume stays indexing with the checkpoint's progress
+        let progress = ScanProgress::start(&mut db, volume_id).unwrap();
+        let checkpoint = ScanCheckpoint {
+            next_record: Some(16384),
+            last_path: None,
+            percent: 25,
+            processed: 16384,
+            total: 65536,
+        };
+        progress.save(&mut db, checkpoint.clone()).unwrap();
+        progress.finish(&mut db, false).unwrap();
+        assert_eq!(
+            get_volume_state(db.conn(), volume_id).unwrap(),
+            VolumeState::Indexing { percent: 25 }
+        );
+
+        // The next scan picks up there and clears it when done
+        let progress = ScanProgress::start(&mut db, volume_id).unwrap();
+        assert_eq!(progress.resume(), &checkpoint);
+        progress.finish(&mut db, true).unwrap();
+        assert_eq!(get_volume_state(db.conn(), volume_id).unwrap(), VolumeState::Online);
+        assert_eq!(get_scan_checkpoint(db.conn(), volume_id).unwrap(), ScanCheckpoint::default());
+
+        // Rescans keep their own state
+        update_volume_state(db.conn(), volume_id, VolumeState::Rescanning).unwrap();
+        ScanProgress::start(&mut db, volume_id).unwrap().finish(&mut db, true).unwrap();
+        assert_eq!(get_volume_state(db.conn(), volume_id).unwrap(), VolumeState::Rescanning);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
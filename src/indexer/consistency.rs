@@ -0,0 +1,505 @@
+//! Background consistency pass over the indexed file tree.
+//!
+//! Run as `JobKind::ConsistencyCheck` at the maintenance cadence. For each
+//! online volume, the entries no path leads to (see
+//! [`find_tree_issues`]) are looked up on disk by file reference:
+//! - still there: the entry, and any directory above it the index is
+//!   missing or has elsewhere, is written again where it is now, like a USN
+//!   rename, which also fixes the paths of everything below it
+//! - gone: the entry and everything indexed below it are deleted
+//! - not found by reference (FAT volumes and shares, whose references are
+//!   hashes of paths, or a failed lookup): the volume is rescanned, by the
+//!   reconciler's next walk or a rescan job
+//!
+//! Repaired directories, and the folder holding a parent indexed as a file,
+//! are then walked again with [`rescan_subtree`], so what is below them on
+//! disk is indexed too without rescanning the volume.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+
+use super::fat::rescan_subtree;
+use super::fat_reconciler::request_catch_up;
+use super::rescan::request_rescan;
+use super::usn_monitor::{apply_changes_batch, ChangeType, UsnChange};
+use crate::db::{
+    delete_subtrees, find_tree_issues, get_all_volumes, get_full_path, get_volume_state, refresh_full_paths, Database,
+    TreeIssue, TreeIssueKind, VolumeInfo,
+};
+use crate::service::config::Config;
+use crate::service::ExcludeMatcher;
+use crate::{Result, VolumeState};
+
+/// Most issues handled per volume and pass; the rest wait for the next pass.
+const MAX_ISSUES_PER_PASS: usize = 10_000;
+
+/// Issues looked up on disk between checks for shutdown.
+const SHUTDOWN_CHECK_INTERVAL: usize = 256;
+
+/// Outcome of a consistency pass over one volume.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsistencyReport {
+    /// Entries found without a path
+    pub found: usize,
+    /// Entries put back where they are on disk
+    pub repaired: usize,
+    /// Entries found gone from disk, deleted with what was below them
+    pub deleted: usize,
+    /// Entries that could not be looked up, left for a rescan of the volume
+    pub unresolved: usize,
+    /// Folders to walk again, relative to the volume root; none is below
+    /// another
+    pub folders: Vec<String>,
+}
+
+/// An entry on disk, as the index stores it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiskEntry {
+    pub file_ref: i64,
+    pub parent_ref: i64,
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Where an entry is on disk now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) enum Located {
+    /// The entry and the directories above it, from the top one down
+    At(Vec<DiskEntry>),
+    /// No file has the entry's reference any more
+    Gone,
+    /// The entry can't be looked up by reference
+    Unknown,
+}
+
+/// Check and repair the file tree of every online volume.
+///
+/// Volumes with entries that could not be looked up are queued for a
+/// rescan.
+///
+/// # Arguments
+/// * `db` - The running worker's connection
+/// * `config` - Service configuration, for excludes
+/// * `shutdown_rx` - Shutdown channel of the running worker
+pub fn check_consistency(db: &mut Database, config: &Config, shutdown_rx: &Receiver<()>) {
+    tracing::debug!("Checking the consistency of the file tree...");
+    let volumes = match get_all_volumes(db.conn()) {
+        Ok(volumes) => volumes,
+        Err(e) => {
+            tracing::error!("Consistency check failed: {}", e);
+            return;
+        }
+    };
+
+    for volume in volumes {
+        if shutdown_rx.try_recv().is_ok() {
+            return;
+        }
+        if !matches!(get_volume_state(db.conn(), volume.id), Ok(VolumeState::Online)) {
+            continue;
+        }
+
+        let ntfs = volume.fs_type == "NTFS";
+        let root = format!("{}\\", volume.drive_letter.trim_end_matches('\\'));
+        let locate = |issue: &TreeIssue| {
+            if ntfs {
+                locate_on_disk(&root, issue.file_ref)
+            } else {
+                Located::Unknown
+            }
+        };
+        let exclude = config.exclude_matcher(volume.drive_letter.as_str());
+        match check_volume(db, volume.id, &exclude, locate, shutdown_rx) {
+            Ok(report) if report.found > 0 => {
+                tracing::warn!(
+                    "File tree of {} had {} entries without a path: {} repaired, {} deleted, {} left for a rescan, \
+                     {} folders to walk again",
+                    volume.drive_letter,
+                    report.found,
+                    report.repaired,
+                    report.deleted,
+                    report.unresolved,
+                    report.folders.len()
+                );
+                for folder in &report.folders {
+                    if shutdown_rx.try_recv().is_ok() {
+                        return;
+                    }
+                    if let Err(e) =
+                        rescan_subtree(db, &volume.drive_letter, Path::new(folder), &exclude, shutdown_rx)
+                    {
+                        tracing::error!("Rescan of {} on {} failed: {}", folder, volume.drive_letter, e);
+                    }
+                }
+                if report.unresolved > 0 {
+                    schedule_rescan(db, &volume);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Consistency check of {} failed: {}", volume.drive_letter, e),
+        }
+    }
+}
+
+/// Check and repair the file tree of one volume.
+///
+/// # Arguments
+/// * `db` - Database connection
+/// * `volume_id` - Volume to check
+/// * `exclude` - Exclusions applied to repaired entries
+/// * `locate` - Looks an entry up on disk
+/// * `shutdown_rx` - Stops the lookups early; what was looked up is still applied
+pub(crate) fn check_volume(
+    db: &mut Database,
+    volume_id: i64,
+    exclude: &ExcludeMatcher,
+    mut locate: impl FnMut(&TreeIssue) -> Located,
+    shutdown_rx: &Receiver<()>,
+) -> Result<ConsistencyReport> {
+    let mut issues = find_tree_issues(db.conn(), volume_id, MAX_ISSUES_PER_PASS)?;
+
+    // A path may just be stale; only what refreshing can't fix is looked up
+    let unreachable: Vec<i64> = issues
+        .iter()
+        .filter(|issue| issue.kind == TreeIssueKind::Unreachable)
+        .map(|issue| issue.file_ref)
+        .collect();
+    if !unreachable.is_empty() {
+        refresh_full_paths(db.conn(), volume_id, &unreachable)?;
+        issues = find_tree_issues(db.conn(), volume_id, MAX_ISSUES_PER_PASS)?;
+    }
+
+    let mut report = ConsistencyReport {
+        found: issues.len(),
+        ..Default::default()
+    };
+    let mut changes: Vec<UsnChange> = Vec::new();
+    let mut written: HashSet<i64> = HashSet::new();
+    let mut gone: Vec<i64> = Vec::new();
+    let mut repaired_dirs: Vec<i64> = Vec::new();
+    let mut folders: Vec<String> = Vec::new();
+    for (n, issue) in issues.iter().enumerate() {
+        if n % SHUTDOWN_CHECK_INTERVAL == 0 && n > 0 && shutdown_rx.try_recv().is_ok() {
+            break;
+        }
+        match locate(issue) {
+            Located::At(chain) => {
+                report.repaired += 1;
+                if issue.is_dir {
+                    repaired_dirs.push(issue.file_ref);
+                }
+                for entry in chain {
+                    // Directories above the entry that the index already has
+                    // in the right place are left alone
+                    let is_issue = entry.file_ref == issue.file_ref;
+                    if !written.insert(entry.file_ref) || (!is_issue && is_indexed_at(db, volume_id, &entry)) {
+                        continue;
+                    }
+                    changes.push(UsnChange {
+                        file_ref: entry.file_ref,
+                        parent_ref: entry.parent_ref,
+                        name: entry.name,
+                        change_type: ChangeType::Rename,
+                        is_dir: entry.is_dir,
+                        old_parent_ref: is_issue.then_some(issue.parent_ref),
+                        old_name: is_issue.then(|| issue.name.clone()),
+                    });
+                }
+            }
+            Located::Gone => gone.push(issue.file_ref),
+            // A walk of the folder holding the parent finds what it really is
+            Located::Unknown if issue.kind == TreeIssueKind::ParentNotDirectory => {
+                match get_full_path(db.conn(), volume_id, issue.parent_ref)? {
+                    Some(parent) => folders.push(parent.rsplit_once('\\').map_or("", |(folder, _)| folder).to_string()),
+                    None => report.unresolved += 1,
+                }
+            }
+            Located::Unknown => report.unresolved += 1,
+        }
+    }
+
+    apply_changes_batch(db, volume_id, &changes, exclude)?;
+    report.deleted = gone.len();
+    delete_subtrees(db.conn_mut(), volume_id, &gone)?;
+
+    for file_ref in repaired_dirs {
+        if let Some(path) = get_full_path(db.conn(), volume_id, file_ref)? {
+            folders.push(path);
+        }
+    }
+    report.folders = outermost(folders);
+    Ok(report)
+}
+
+/// Drop duplicate folders and those below another one.
+fn outermost(mut folders: Vec<String>) -> Vec<String> {
+    folders.sort();
+    folders.dedup();
+    let mut kept: Vec<String> = Vec::new();
+    for folder in folders {
+        let covered = kept.iter().any(|outer| {
+            outer.is_empty() || folder.strip_prefix(outer.as_str()).is_some_and(|rest| rest.starts_with('\\'))
+        });
+        if !covered {
+            kept.push(folder);
+        }
+    }
+    kept
+}
+
+/// Whether the index has an entry's primary name where it is on disk.
+fn is_indexed_at(db: &Database, volume_id: i64, entry: &DiskEntry) -> bool {
+    db.conn()
+        .query_row(
+            "SELECT 1 FROM files
+             WHERE volume_id = ?1 AND file_ref = ?2 AND parent_ref = ?3 AND name = ?4 AND link = 0 AND stream = ''",
+            rusqlite::params![volume_id, entry.file_ref, entry.parent_ref, entry.name],
+            |_| Ok(()),
+        )
+        .is_ok()
+}
+
+/// Rescan a volume whose entries could not be looked up on disk.
+fn schedule_rescan(db: &Database, volume: &VolumeInfo) {
+    if volume.fs_type != "NTFS" {
+        // Walks rebuild every parent reference
+        request_catch_up();
+        return;
+    }
+    match volume.drive_letter.strip_suffix(':').and_then(|letter| letter.chars().next()) {
+        Some(letter) if volume.drive_letter.len() == 2 => request_rescan(db.conn(), letter),
+        _ => tracing::warn!(
+            "Volume {} can't be rescanned in the background; its index is repaired on the next full index",
+            volume.drive_letter
+        ),
+    }
+}
+
+/// Look an NTFS entry up by file reference and find where it is.
+///
+/// # Arguments
+/// * `root` - Root directory of the volume, e.g. `C:\`
+/// * `file_ref` - MFT reference of the entry
+#[cfg(windows)]
+fn locate_on_disk(root: &str, file_ref: i64) -> Located {
+    use std::path::PathBuf;
+    use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_INVALID_PARAMETER};
+    use windows::Win32::Storage::FileSystem::{
+        FileIdType, GetFinalPathNameByHandleW, OpenFileById, FILE_FLAG_BACKUP_SEMANTICS, FILE_ID_DESCRIPTOR,
+        FILE_ID_DESCRIPTOR_0, FILE_READ_ATTRIBUTES, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE,
+        VOLUME_NAME_NONE,
+    };
+
+    let Some((volume, root_ref, _)) = FileHandle::open(root) else {
+        return Located::Unknown;
+    };
+
+    // A reference without a sequence number matches whichever file has the record
+    let descriptor = FILE_ID_DESCRIPTOR {
+        dwSize: std::mem::size_of::<FILE_ID_DESCRIPTOR>() as u32,
+        Type: FileIdType,
+        Anonymous: FILE_ID_DESCRIPTOR_0 { FileId: file_ref },
+    };
+    let opened = unsafe {
+        OpenFileById(
+            volume.0,
+            &descriptor,
+            FILE_READ_ATTRIBUTES.0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            FILE_FLAG_BACKUP_SEMANTICS,
+        )
+    };
+    let file = match opened {
+        Ok(handle) => FileHandle(handle),
+        Err(e) if e.code() == ERROR_INVALID_PARAMETER.to_hresult() || e.code() == ERROR_FILE_NOT_FOUND.to_hresult() => {
+            return Located::Gone;
+        }
+        Err(e) => {
+            tracing::debug!("Failed to open file_ref {} on {}: {}", file_ref, root, e);
+            return Located::Unknown;
+        }
+    };
+
+    // The path from the volume root, e.g. \Users\notes.txt
+    let mut buffer = vec![0u16; 32768];
+    let len = unsafe { GetFinalPathNameByHandleW(file.0, &mut buffer, VOLUME_NAME_NONE) } as usize;
+    if len == 0 || len > buffer.len() {
+        return Located::Unknown;
+    }
+    let relative = String::from_utf16_lossy(&buffer[..len]);
+
+    // Each directory on the way down, by its own reference
+    let mut chain = Vec::new();
+    let mut path = PathBuf::from(root);
+    let mut parent_ref = root_ref;
+    for name in relative.split('\\').filter(|name| !name.is_empty()) {
+        path.push(name);
+        let Some((file_ref, is_dir)) = file_id(&path) else {
+            return Located::Unknown;
+        };
+        chain.push(DiskEntry {
+            file_ref,
+            parent_ref,
+            name: name.to_string(),
+            is_dir,
+        });
+        parent_ref = file_ref;
+    }
+    Located::At(chain)
+}
+
+/// Stub for non-Windows platforms: NTFS entries can't be looked up.
+#[cfg(not(windows))]
+fn locate_on_disk(_root: &str, _file_ref: i64) -> Located {
+    Located::Unknown
+}
+
+/// MFT reference of the file or directory at `path`, as the scanner stores
+/// it, and whether it is a directory.
+#[cfg(windows)]
+pub(super) fn file_id(path: &Path) -> Option<(i64, bool)> {
+    FileHandle::open(&path.to_string_lossy()).map(|(_, file_ref, is_dir)| (file_ref, is_dir))
+}
+
+/// Stub for non-Windows platforms: MFT references can't be read.
+#[cfg(not(windows))]
+pub(super) fn file_id(_path: &Path) -> Option<(i64, bool)> {
+    None
+}
+
+/// An open file or directory, closed when dropped.
+#[cfg(windows)]
+struct FileHandle(windows::Win32::Foundation::HANDLE);
+
+#[cfg(windows)]
+impl FileHandle {
+    /// Open a file or directory without following reparse points.
+    ///
+    /// # Returns
+    /// The handle, the entry's MFT reference as the scanner stores it (the
+    /// record number, without sequence number) and whether it is a directory.
+    fn open(path: &str) -> Option<(Self, i64, bool)> {
+        use std::ffi::OsStr;
+        use std::os::windows::ffi::OsStrExt;
+        use windows::core::PCWSTR;
+        use windows::Win32::Storage::FileSystem::{
+            CreateFileW, GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_ATTRIBUTE_DIRECTORY,
+            FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT, FILE_READ_ATTRIBUTES, FILE_SHARE_DELETE,
+            FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+        };
+
+        let path_wide: Vec<u16> = OsStr::new(path).encode_wide().chain(std::iter::once(0)).collect();
+        let handle = unsafe {
+            CreateFileW(
+                PCWSTR::from_raw(path_wide.as_ptr()),
+                FILE_READ_ATTRIBUTES.0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT,
+                None,
+            )
+        }
+        .ok()?;
+        let file = Self(handle);
+
+        let mut info = BY_HANDLE_FILE_INFORMATION::default();
+        unsafe { GetFileInformationByHandle(file.0, &mut info) }.ok()?;
+        let index = ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64;
+        let file_ref = (index & 0xFFFF_FFFF_FFFF) as i64;
+        let is_dir = info.dwFileAttributes & FILE_ATTRIBUTE_DIRECTORY.0 != 0;
+        Some((file, file_ref, is_dir))
+    }
+}
+
+#[cfg(windows)]
+impl Drop for FileHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = windows::Win32::Foundation::CloseHandle(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, get_full_path, insert_volume, open_database, FileEntry};
+    use std::sync::mpsc;
+
+    fn disk(file_ref: i64, parent_ref: i64, name: &str) -> DiskEntry {
+        DiskEntry {
+            file_ref,
+            parent_ref,
+            name: name.to_string(),
+            is_dir: true,
+        }
+    }
+
+    #[test]
+    fn test_check_volume_repairs_from_disk() {
+        let dir = std::env::temp_dir().join("ffi_test_consistency");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        batch_insert_files(
+            db.conn_mut(),
+            &[
+                FileEntry::test(1, 5, 5, ".", true),
+                FileEntry::test(1, 100, 5, "Users", true),
+                // Below an entry indexed as a file
+                FileEntry::test(1, 101, 100, "notes", false),
+                FileEntry::test(1, 102, 101, "draft.txt", false),
+                // Moved under a directory the index never saw
+                FileEntry::test(1, 201, 200, "Projects", true),
+                FileEntry::test(1, 202, 201, "plan.txt", false),
+                // Deleted along with its parent
+                FileEntry::test(1, 301, 300, "Old", true),
+                FileEntry::test(1, 302, 301, "old.txt", false),
+                // On a path that can't be looked up
+                FileEntry::test(1, 401, 400, "Lost", true),
+            ],
+        )
+        .unwrap();
+
+        let (_shutdown_tx, shutdown_rx) = mpsc::channel();
+        let report = check_volume(
+            &mut db,
+            1,
+            &ExcludeMatcher::default(),
+            |issue| match issue.file_ref {
+                201 => Located::At(vec![disk(100, 5, "Users"), disk(200, 100, "Work"), disk(201, 200, "Projects")]),
+                301 => Located::Gone,
+                _ => Located::Unknown,
+            },
+            &shutdown_rx,
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            ConsistencyReport {
+                found: 4,
+                repaired: 1,
+                deleted: 1,
+                unresolved: 1,
+                // Users\\Work\\Projects was repaired, but is below Users
+                folders: vec!["Users".to_string()],
+            }
+        );
+
+        let path = |file_ref| get_full_path(db.conn(), 1, file_ref).unwrap();
+        assert_eq!(path(200).as_deref(), Some("Users\\Work"));
+        assert_eq!(path(202).as_deref(), Some("Users\\Work\\Projects\\plan.txt"));
+        assert_eq!(path(301), None);
+        assert_eq!(path(302), None);
+
+        let remaining = find_tree_issues(db.conn(), 1, 100).unwrap();
+        assert_eq!(remaining.iter().map(|i| i.file_ref).collect::<Vec<_>>(), vec![102, 401]);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
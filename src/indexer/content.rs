@@ -0,0 +1,223 @@
+//! Background indexing of the text of small files.
+//!
+//! Run as `JobKind::ContentIndex` every `[content] interval_mins` while
+//! content indexing is enabled. A pass first drops the text of files no
+//! longer covered (other extensions, or grown past the size cap), then
+//! reads the files whose text is missing or changed, a batch at a time.
+//! Files that aren't text or can't be read are stored without text, so
+//! they are only read again once they change. With content indexing
+//! turned off, a pass deletes all stored text.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+
+use super::throttle::BackgroundIo;
+use crate::db::{clear_contents, content_candidates, prune_contents, store_contents, Database, FileContent};
+use crate::service::config::ContentConfig;
+use crate::Result;
+
+/// Files read between writes and checks for shutdown.
+const BATCH_SIZE: usize = 256;
+
+/// Leading bytes searched for a NUL byte, which marks a file as binary.
+const BINARY_CHECK_BYTES: usize = 8192;
+
+/// Outcome of a content indexing pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentReport {
+    /// Files whose text was indexed
+    pub indexed: usize,
+    /// Files read that aren't text or couldn't be read
+    pub skipped: usize,
+    /// Files whose stored text was deleted
+    pub removed: usize,
+}
+
+/// Index the text of new and changed files, or delete all stored text if
+/// content indexing is off.
+///
+/// # Arguments
+/// * `db` - The running worker's connection
+/// * `config` - Content indexing settings
+/// * `shutdown_rx` - Shutdown channel of the running worker
+pub fn index_contents(db: &mut Database, config: &ContentConfig, shutdown_rx: &Receiver<()>) {
+    let _background = BackgroundIo::enter();
+    let max_size = config.max_file_size();
+    match index_pass(db, config, |path| read_text(path, max_size), shutdown_rx) {
+        Ok(report) if report.indexed + report.skipped + report.removed > 0 => tracing::info!(
+            "Content index: {} files indexed, {} skipped, {} removed",
+            report.indexed,
+            report.skipped,
+            report.removed
+        ),
+        Ok(_) => {}
+        Err(e) => tracing::error!("Content indexing failed: {}", e),
+    }
+}
+
+/// Run one content indexing pass.
+///
+/// # Arguments
+/// * `db` - Database connection
+/// * `config` - Content indexing settings
+/// * `read` - Reads a file's text; None if it isn't text or can't be read
+/// * `shutdown_rx` - Stops the pass between batches
+pub(crate) fn index_pass(
+    db: &mut Database,
+    config: &ContentConfig,
+    mut read: impl FnMut(&Path) -> Option<String>,
+    shutdown_rx: &Receiver<()>,
+) -> Result<ContentReport> {
+    let mut report = ContentReport::default();
+    if !config.enabled {
+        report.removed = clear_contents(db.conn())?;
+        return Ok(report);
+    }
+
+    let extensions = config.extensions();
+    let max_size = config.max_file_size();
+    report.removed = prune_contents(db.conn(), &extensions, max_size)?;
+
+    loop {
+        if shutdown_rx.try_recv().is_ok() {
+            break;
+        }
+        let candidates = content_candidates(db.conn(), &extensions, max_size, BATCH_SIZE)?;
+        if candidates.is_empty() {
+            break;
+        }
+
+        let contents: Vec<FileContent> = candidates
+            .into_iter()
+            .map(|candidate| {
+                let text = read(Path::new(&candidate.path));
+                if text.is_some() {
+                    report.indexed += 1;
+                } else {
+                    report.skipped += 1;
+                }
+                FileContent {
+                    id: candidate.id,
+                    size: candidate.size,
+                    modified: candidate.modified,
+                    text: text.unwrap_or_default(),
+                }
+            })
+            .collect();
+        store_contents(db.conn_mut(), &contents)?;
+    }
+    Ok(report)
+}
+
+/// Read a file's text, unless it outgrew the size cap since it was indexed.
+fn read_text(path: &Path, max_size: i64) -> Option<String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    if bytes.len() as i64 > max_size {
+        return None;
+    }
+    decode_text(&bytes)
+}
+
+/// Decode a file's bytes as text: UTF-16 with a byte order mark, otherwise
+/// UTF-8 (invalid sequences replaced).
+///
+/// # Returns
+/// None for binary files, which have a NUL byte near the start.
+fn decode_text(bytes: &[u8]) -> Option<String> {
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        let units: Vec<u16> = rest.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+        return Some(String::from_utf16_lossy(&units));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = rest.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+        return Some(String::from_utf16_lossy(&units));
+    }
+
+    if bytes[..bytes.len().min(BINARY_CHECK_BYTES)].contains(&0) {
+        return None;
+    }
+    let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, count_query_matches, insert_volume, open_database, FileEntry};
+    use crate::search::parse_query;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(decode_text(b"TODO refactor").as_deref(), Some("TODO refactor"));
+        assert_eq!(decode_text(b"\xEF\xBB\xBFbom").as_deref(), Some("bom"));
+        assert_eq!(decode_text(b"\xFF\xFEh\0i\0").as_deref(), Some("hi"));
+        assert_eq!(decode_text(b"MZ\x90\0\x03"), None);
+        assert_eq!(decode_text(b"").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_index_pass() {
+        let dir = std::env::temp_dir().join("ffi_test_content");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let file = |file_ref: i64, name: &str| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(5),
+            name: name.to_string(),
+            size: 100,
+            modified: Some(1_700_000_000),
+            is_dir: name == ".",
+            ..Default::default()
+        };
+        batch_insert_files(
+            db.conn_mut(),
+            &[file(5, "."), file(10, "lib.rs"), file(11, "data.bin"), file(12, "locked.txt")],
+        )
+        .unwrap();
+
+        let mut config = ContentConfig {
+            enabled: true,
+            ..ContentConfig::default()
+        };
+        let (_shutdown_tx, shutdown_rx) = mpsc::channel();
+        let mut read_paths = Vec::new();
+        let report = index_pass(
+            &mut db,
+            &config,
+            |path| {
+                read_paths.push(path.to_string_lossy().to_string());
+                path.ends_with(r"C:\lib.rs").then(|| "// TODO refactor the parser".to_string())
+            },
+            &shutdown_rx,
+        )
+        .unwrap();
+        assert_eq!(report, ContentReport { indexed: 1, skipped: 1, removed: 0 });
+        assert_eq!(read_paths, vec![r"C:\lib.rs", r"C:\locked.txt"]);
+
+        let count = |db: &Database, query: &str| count_query_matches(db.conn(), &parse_query(query).unwrap()).unwrap();
+        assert_eq!(count(&db, r#"content:"TODO refactor" ext:rs"#), 1);
+        assert_eq!(count(&db, r#"content:"refactor TODO""#), 0);
+
+        // Nothing changed, nothing read again
+        let report = index_pass(&mut db, &config, |_| panic!("read again"), &shutdown_rx).unwrap();
+        assert_eq!(report, ContentReport::default());
+
+        config.enabled = false;
+        let report = index_pass(&mut db, &config, |_| None, &shutdown_rx).unwrap();
+        assert_eq!(report.removed, 2);
+        assert_eq!(count(&db, "content:todo"), 0);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
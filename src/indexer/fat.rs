@@ -2,16 +2,31 @@
 //!
 //! This module provides indexing for FAT32/exFAT volumes that don't have
 //! an MFT. Uses directory traversal which is slower but works universally.
+//! Periodic reconciliation walks the same way but diffs the walk against
+//! the index and writes only what changed.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Receiver;
-use std::time::UNIX_EPOCH;
+use std::time::{Instant, UNIX_EPOCH};
 
+use rusqlite::Connection;
 use walkdir::WalkDir;
 
-use crate::db::{batch_insert_files, insert_volume, Database, FileEntry};
-use crate::Result;
+use crate::db::{
+    apply_write, clear_path_failure, get_file_signatures, get_skipped_paths, get_subtree_signatures, get_volume,
+    get_volume_stats, insert_volume, rebuild_facet_counts, record_path_failure, set_volume_truncated,
+    update_volume_stats, Database, FileEntry, FileSignature, ScanCheckpoint, SkippedPath, WriteOp,
+    FILE_ATTRIBUTE_REPARSE_POINT,
+};
+use crate::service::ExcludeMatcher;
+use crate::{FFIError, Result};
+
+use super::checkpoint::{percent_of, ScanProgress};
+use super::consistency::file_id;
+use super::network::ShareThrottle;
+use super::throttle::{BackgroundIo, ScanLimits, ScanThrottle};
 
 /// Batch size for database inserts
 const BATCH_SIZE: usize = 100_000;
@@ -22,18 +37,164 @@ const PROGRESS_INTERVAL: usize = 50_000;
 /// Shutdown check interval
 const SHUTDOWN_CHECK_INTERVAL: usize = 10_000;
 
+/// Consecutive access-denied scans before a directory is skipped.
+const SKIP_AFTER_FAILURES: i64 = 3;
+
+/// Skipped directories are retried after this long, in case permissions changed.
+const SKIP_RETRY_SECS: i64 = 7 * 86400;
+
+/// FNV-1a 64-bit offset basis.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a 64-bit prime.
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Path of an entry as exclude patterns see it, e.g. `D:\Photos\a.jpg`.
+fn display_path(volume_name: &str, relative: &Path) -> String {
+    let mut path = volume_name.to_string();
+    for component in relative.components() {
+        path.push('\\');
+        path.push_str(&component.as_os_str().to_string_lossy());
+    }
+    path
+}
+
+/// Windows attribute flags (hidden, system, ...) of an entry.
+///
+/// Other platforms have no such flags, so entries scanned there have none.
+pub(super) fn file_attributes(metadata: &std::fs::Metadata) -> u32 {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        metadata.file_attributes()
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// Derive a stable synthetic file reference from a path relative to the root.
+///
+/// FAT has no MFT references, so the ref is an FNV-1a hash of the lowercased
+/// relative path (FAT names are case-insensitive), with `/` separators on
+/// every platform. The same file therefore keeps its ref across rescans,
+/// and a rescan updates existing rows instead of adding new ones.
+/// Never returns 0, which is reserved for the root directory.
+fn stable_file_ref(relative: &Path) -> i64 {
+    let normalized = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let mut hash = FNV_OFFSET;
+    for byte in normalized.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    match hash as i64 {
+        0 => 1,
+        r => r,
+    }
+}
+
+/// Synthetic refs of the directories on the path currently being walked.
+///
+/// `WalkDir` yields each directory before its contents, so an entry's parent
+/// is always the last directory seen one level up. Only that chain of
+/// ancestors is kept (a directory is dropped once the walk leaves its
+/// subtree), so memory is bounded by tree depth instead of file count.
+struct AncestorRefs {
+    /// Ref of the current directory at each depth (root at index 0);
+    /// `None` for a directory that was skipped because of an error.
+    refs: Vec<Option<i64>>,
+}
+
+impl AncestorRefs {
+    /// Start a walk at the root directory.
+    fn new(root_ref: i64) -> Self {
+        Self {
+            refs: vec![Some(root_ref)],
+        }
+    }
+
+    /// Parent ref for an entry at `depth`, pruning finished subtrees.
+    fn parent_of(&mut self, depth: usize) -> Option<i64> {
+        self.refs.truncate(depth);
+        depth.checked_sub(1).and_then(|d| self.refs.get(d).copied().flatten())
+    }
+
+    /// Record the directory at `depth` as the current one at that level.
+    fn enter_dir(&mut self, depth: usize, file_ref: Option<i64>) {
+        self.refs.truncate(depth);
+        self.refs.push(file_ref);
+    }
+}
+
+/// Whether a recorded directory is currently skipped rather than retried.
+fn is_skipped(skipped: &SkippedPath, now: i64) -> bool {
+    skipped.failures >= SKIP_AFTER_FAILURES && now - skipped.last_failed < SKIP_RETRY_SECS
+}
+
+/// Update the skip list after a complete scan.
+///
+/// Directories denied this scan get another failure recorded; previously
+/// failing directories that were attempted and not denied are forgotten.
+fn update_skip_list(
+    conn: &Connection,
+    volume_id: i64,
+    previous: &[SkippedPath],
+    skip: &HashSet<String>,
+    denied: &[(String, String)],
+) -> Result<()> {
+    for (path, error) in denied {
+        let failures = record_path_failure(conn, volume_id, path, error)?;
+        if failures == SKIP_AFTER_FAILURES {
+            tracing::info!(
+                "Skipping {} after {} access-denied scans (retried in {} days)",
+                path,
+                failures,
+                SKIP_RETRY_SECS / 86400
+            );
+        }
+    }
+
+    for old in previous {
+        let attempted = !skip.contains(&old.path.to_lowercase());
+        if attempted && !denied.iter().any(|(path, _)| path.eq_ignore_ascii_case(&old.path)) {
+            tracing::info!("{} is readable again, removed from skip list", old.path);
+            clear_path_failure(conn, volume_id, &old.path)?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Scan a FAT volume using directory walking.
 ///
 /// This function:
 /// 1. Walks the directory tree starting from root
-/// 2. Generates synthetic file references for FAT (no MFT refs)
+/// 2. Derives stable synthetic file references from paths (FAT has no MFT refs)
 /// 3. Tracks parent-child relationships for path reconstruction
+///    (only the current directory chain is kept, so memory stays flat)
 /// 4. Batches entries for database insertion
 /// 5. Checks for shutdown signal periodically
+/// 6. Skips directories that were access-denied on several consecutive scans
+///    (recorded in the `skipped_paths` table)
+/// 7. Skips excluded entries (excluded directories are not walked)
+/// 8. Saves a checkpoint after each batch and resumes an interrupted scan
+///    after the last entry it wrote
+/// 9. Reads at background I/O priority, pacing directory reads by `limits`
+///    and the load of the disk and CPU (see [`ScanThrottle`])
 ///
 /// # Arguments
 /// * `drive_letter` - The drive letter to scan (e.g., 'D')
 /// * `db` - Database instance for persisting indexed files
+/// * `exclude` - Excludes of the volume, kept out of the index
+/// * `limits` - Read rate and load limits the scan paces itself by
 /// * `shutdown_rx` - Channel receiver for shutdown signals
 ///
 /// # Returns
@@ -41,71 +202,697 @@ const SHUTDOWN_CHECK_INTERVAL: usize = 10_000;
 pub fn scan_fat_volume(
     drive_letter: char,
     db: &mut Database,
+    exclude: &ExcludeMatcher,
+    limits: ScanLimits,
     shutdown_rx: &Receiver<()>,
 ) -> Result<usize> {
-    // Construct root path
+    let root_path = fat_root_path(drive_letter);
+    tracing::info!("Starting FAT volume scan for {}", root_path);
+
+    // Could be FAT32 or exFAT, generic label
+    scan_directory_tree(&root_path, &format!("{}:", drive_letter), "FAT", db, exclude, limits, shutdown_rx)
+        .map(|(count, _)| count)
+}
+
+/// Reconcile an indexed FAT volume with the disk.
+///
+/// Walks the volume like [`scan_fat_volume`] but only writes what changed;
+/// see [`reconcile_directory_tree`].
+///
+/// # Arguments
+/// * `drive_letter` - The drive letter to reconcile (e.g., 'D')
+/// * `db` - Database instance holding the volume's index
+/// * `exclude` - Excludes of the volume, kept out of the index
+/// * `shutdown_rx` - Channel receiver for shutdown signals
+///
+/// # Returns
+/// Counts of the entries inserted, updated, deleted and left unchanged.
+pub fn reconcile_fat_volume(
+    drive_letter: char,
+    db: &mut Database,
+    exclude: &ExcludeMatcher,
+    shutdown_rx: &Receiver<()>,
+) -> Result<ReconcileStats> {
+    let root_path = fat_root_path(drive_letter);
+    tracing::info!("Starting FAT volume reconciliation for {}", root_path);
+
+    reconcile_directory_tree(&root_path, &format!("{}:", drive_letter), "FAT", db, exclude, shutdown_rx)
+}
+
+/// Root directory of a FAT volume.
+fn fat_root_path(drive_letter: char) -> String {
     #[cfg(windows)]
     let root_path = format!("{}:\\", drive_letter);
     #[cfg(not(windows))]
     let root_path = format!("/mnt/{}", drive_letter.to_lowercase());
+    root_path
+}
 
-    tracing::info!("Starting FAT volume scan for {}", root_path);
+/// Walk a directory tree into the volume named `volume_name`.
+///
+/// Shared by FAT volumes and other sources without an MFT (e.g. VSS shadow
+/// copies). The volume record is created or updated with `fs_type`.
+/// Exclude patterns are matched against `volume_name` joined with the
+/// path relative to `root_path`. The walk stops at the exclude settings'
+/// depth and entry count limits.
+///
+/// Directories are walked in name order, so a scan interrupted by a stop or
+/// crash resumes after the last entry it wrote, skipping what it indexed.
+/// Each directory found counts as one read against `limits`.
+///
+/// # Returns
+/// The total number of files indexed, and whether the walk completed
+/// (false when shutdown interrupted it).
+pub fn scan_directory_tree(
+    root_path: &str,
+    volume_name: &str,
+    fs_type: &str,
+    db: &mut Database,
+    exclude: &ExcludeMatcher,
+    limits: ScanLimits,
+    shutdown_rx: &Receiver<()>,
+) -> Result<(usize, bool)> {
+    let start = Instant::now();
+    let _background = BackgroundIo::enter();
+    let mut throttle = ScanThrottle::new(root_path, limits, 1);
 
     // Insert or update volume record
     let volume_id = insert_volume(
         db.conn(),
-        &format!("{}:", drive_letter),
+        volume_name,
         "", // Serial from volume detection
-        "FAT", // Could be FAT32 or exFAT, generic label
+        fs_type,
     )?;
 
-    // Synthetic file reference counter
-    // FAT doesn't have MFT references, so we generate sequential IDs
-    let mut next_file_ref: i64 = 1;
-
-    // Track path -> file_ref mapping for parent reference lookups
-    let mut path_to_ref: HashMap<PathBuf, i64> = HashMap::new();
+    let progress = ScanProgress::start(db, volume_id)?;
+    let mut skip_list = SkipList::load(db.conn(), volume_id, root_path)?;
+    skip_list.indexed_through = progress.resume().last_path.as_ref().map(PathBuf::from);
+    if let Some(last) = &skip_list.indexed_through {
+        tracing::info!("Resuming interrupted scan of {} after {}", volume_name, last.display());
+    }
 
-    // Root directory gets ref 0 (like MFT root entry 5)
-    let root = PathBuf::from(&root_path);
-    path_to_ref.insert(root.clone(), 0);
+    // Progress is estimated against what the last complete scan found
+    let stats = get_volume_stats(db.conn(), volume_id)?;
+    let expected = (stats.file_count + stats.dir_count).max(0) as u64;
+    let mut done = match progress.resume().processed {
+        0 => expected * progress.resume().percent as u64 / 100,
+        processed => processed,
+    };
 
     let mut batch: Vec<FileEntry> = Vec::with_capacity(BATCH_SIZE);
+    let mut last_path = PathBuf::new();
     let mut total_indexed = 0;
-    let mut errors = 0;
+
+    let scope = WalkScope::volume();
+    let walk = walk_tree(root_path, volume_name, volume_id, exclude, &skip_list, &scope, shutdown_rx, |entry, relative| {
+        if entry.is_dir {
+            throttle.pace(1);
+        }
+        batch.push(entry);
+        last_path.clear();
+        last_path.push(relative);
+
+        // Flush batch when full
+        if batch.len() >= BATCH_SIZE {
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+            done += full.len() as u64;
+            total_indexed += write_batch(db, &progress, full, &last_path, done, expected)?;
+        }
+        Ok(())
+    })?;
+
+    // Insert remaining entries, also when stopping for shutdown
+    if !batch.is_empty() {
+        done += batch.len() as u64;
+        total_indexed += write_batch(db, &progress, batch, &last_path, done, expected)?;
+    }
+    if !walk.complete {
+        return Ok((total_indexed, false));
+    }
+
+    finish_walk(db, volume_id, volume_name, &skip_list, &walk, start, true)?;
+    progress.finish(db, true)?;
+
+    tracing::info!(
+        "Directory scan complete for {}: {} files indexed",
+        root_path,
+        total_indexed
+    );
+
+    Ok((total_indexed, true))
+}
+
+/// Write a batch of walked entries, then checkpoint the walk after the last
+/// of them, `done` of `expected` entries in.
+fn write_batch(
+    db: &mut Database,
+    progress: &ScanProgress,
+    batch: Vec<FileEntry>,
+    last_path: &Path,
+    done: u64,
+    expected: u64,
+) -> Result<usize> {
+    let written = apply_write(db, WriteOp::InsertBatch(batch))?;
+    progress.report(done, expected);
+    progress.save(
+        db,
+        ScanCheckpoint {
+            next_record: None,
+            last_path: Some(last_path.to_string_lossy().to_string()),
+            percent: percent_of(done, expected),
+            processed: done,
+            total: expected,
+        },
+    )?;
+    Ok(written)
+}
+
+/// Counts of the changes a reconciliation applied.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReconcileStats {
+    /// Entries new on disk
+    pub inserted: usize,
+    /// Entries whose name, parent, size, times or attributes changed
+    pub updated: usize,
+    /// Indexed entries no longer on disk
+    pub deleted: usize,
+    /// Entries already indexed as they are
+    pub unchanged: usize,
+}
+
+impl ReconcileStats {
+    /// Number of rows written or deleted.
+    pub fn changed(&self) -> usize {
+        self.inserted + self.updated + self.deleted
+    }
+
+    /// Compare a walked entry with its indexed row, taking the row out of
+    /// `indexed`, and queue it for writing if it is new or changed.
+    fn diff(&mut self, indexed: &mut HashMap<i64, FileSignature>, entry: FileEntry, upserts: &mut Vec<FileEntry>) {
+        match indexed.remove(&entry.file_ref.unwrap_or_default()) {
+            None => {
+                self.inserted += 1;
+                upserts.push(entry);
+            }
+            Some(signature) if signature.matches(&entry) => self.unchanged += 1,
+            Some(_) => {
+                self.updated += 1;
+                upserts.push(entry);
+            }
+        }
+    }
+}
+
+/// References of the indexed entries a complete walk did not see, which
+/// are gone, unless they lie where the walk could not look.
+fn unseen(
+    indexed: HashMap<i64, FileSignature>,
+    walk: &WalkOutcome,
+    skip_list: &SkipList,
+    volume_name: &str,
+) -> Vec<i64> {
+    if walk.unknown_errors > 0 {
+        tracing::warn!("Keeping unseen entries of {} after walk errors", volume_name);
+        return Vec::new();
+    }
+    indexed
+        .into_iter()
+        .filter(|(_, signature)| {
+            !signature
+                .full_path
+                .as_deref()
+                .is_some_and(|path| walk.is_unreadable(path) || skip_list.contains_relative(path))
+        })
+        .map(|(file_ref, _)| file_ref)
+        .collect()
+}
+
+/// Bring a walked volume's index up to date, writing only what changed.
+///
+/// Walks the tree like [`scan_directory_tree`] and compares every entry with
+/// the indexed row of the same path (file references are derived from
+/// paths) by name, parent, size, modified time and attributes. New and
+/// changed entries are written and rows for entries no longer on disk are
+/// deleted, all in one transaction, so an unchanged tree costs one walk and
+/// no writes.
+///
+/// Rows under directories that could not be read this time (skipped,
+/// access-denied or failing) are kept. An interrupted walk applies nothing.
+///
+/// # Returns
+/// Counts of the entries inserted, updated, deleted and left unchanged.
+pub fn reconcile_directory_tree(
+    root_path: &str,
+    volume_name: &str,
+    fs_type: &str,
+    db: &mut Database,
+    exclude: &ExcludeMatcher,
+    shutdown_rx: &Receiver<()>,
+) -> Result<ReconcileStats> {
+    reconcile_directory_tree_throttled(root_path, volume_name, fs_type, db, exclude, shutdown_rx, None)
+}
+
+/// Like [`reconcile_directory_tree`], reading at most as fast as `throttle`
+/// allows (used for network shares).
+pub fn reconcile_directory_tree_throttled(
+    root_path: &str,
+    volume_name: &str,
+    fs_type: &str,
+    db: &mut Database,
+    exclude: &ExcludeMatcher,
+    shutdown_rx: &Receiver<()>,
+    mut throttle: Option<&mut ShareThrottle>,
+) -> Result<ReconcileStats> {
+    let start = Instant::now();
+
+    let volume_id = insert_volume(db.conn(), volume_name, "", fs_type)?;
+    let skip_list = SkipList::load(db.conn(), volume_id, root_path)?;
+    let mut indexed = get_file_signatures(db.conn(), volume_id)?;
+
+    let mut stats = ReconcileStats::default();
+    let mut upserts: Vec<FileEntry> = Vec::new();
+
+    let scope = WalkScope::volume();
+    let walk = walk_tree(root_path, volume_name, volume_id, exclude, &skip_list, &scope, shutdown_rx, |entry, _| {
+        if let Some(throttle) = throttle.as_mut() {
+            throttle.pace();
+        }
+        stats.diff(&mut indexed, entry, &mut upserts);
+        Ok(())
+    })?;
+
+    if !walk.complete {
+        tracing::info!("Reconciliation of {} interrupted, no changes applied", volume_name);
+        return Ok(ReconcileStats::default());
+    }
+
+    let deletes = unseen(indexed, &walk, &skip_list, volume_name);
+    stats.deleted = deletes.len();
+
+    if stats.changed() > 0 {
+        apply_write(db, WriteOp::ApplyFileDiff { volume_id, upserts, deletes })?;
+    }
+
+    finish_walk(db, volume_id, volume_name, &skip_list, &walk, start, stats.changed() > 0)?;
+
+    tracing::info!(
+        "Reconciliation of {} complete: {} inserted, {} updated, {} deleted, {} unchanged",
+        volume_name,
+        stats.inserted,
+        stats.updated,
+        stats.deleted,
+        stats.unchanged
+    );
+
+    Ok(stats)
+}
+
+/// Walk one directory of an indexed volume again and reconcile it with the
+/// index, instead of the whole volume.
+///
+/// For a folder a user noticed is stale, and for the consistency check.
+/// Entries of NTFS volumes are told apart by their MFT references, read
+/// from disk one by one, which only Windows can do.
+///
+/// # Arguments
+/// * `db` - Database instance holding the volume's index
+/// * `volume_name` - The indexed volume, e.g. `D:` or a mount folder
+/// * `path` - The directory, relative to the volume root or below it
+/// * `exclude` - Excludes of the volume, kept out of the index
+/// * `shutdown_rx` - Channel receiver for shutdown signals
+///
+/// # Returns
+/// Counts of the entries inserted, updated, deleted and left unchanged.
+pub fn rescan_subtree(
+    db: &mut Database,
+    volume_name: &str,
+    path: &Path,
+    exclude: &ExcludeMatcher,
+    shutdown_rx: &Receiver<()>,
+) -> Result<ReconcileStats> {
+    let root_path = volume_root_path(volume_name);
+    let relative = path.strip_prefix(&root_path).unwrap_or(path);
+    reconcile_subtree(&root_path, volume_name, relative, db, exclude, shutdown_rx)
+}
+
+/// Root directory of an indexed volume: a drive, mount folder or share.
+fn volume_root_path(volume_name: &str) -> String {
+    match volume_name.strip_suffix(':').filter(|letter| letter.len() == 1) {
+        Some(letter) => fat_root_path(letter.chars().next().unwrap_or_default()),
+        None => format!("{}\\", volume_name.trim_end_matches('\\')),
+    }
+}
+
+/// Reconcile the directory `relative` below `root_path`, and everything
+/// indexed below it, with the disk.
+///
+/// The directory's own entry is written too. Like
+/// [`reconcile_directory_tree`], an interrupted walk applies nothing and
+/// rows under directories that could not be read are kept.
+fn reconcile_subtree(
+    root_path: &str,
+    volume_name: &str,
+    relative: &Path,
+    db: &mut Database,
+    exclude: &ExcludeMatcher,
+    shutdown_rx: &Receiver<()>,
+) -> Result<ReconcileStats> {
+    let _background = BackgroundIo::enter();
+    let volume = get_volume(db.conn(), volume_name)?
+        .ok_or_else(|| FFIError::Indexer(format!("Volume {} is not indexed", volume_name)))?;
+    let refs = match volume.fs_type.as_str() {
+        "NTFS" => RefSource::Mft,
+        _ => RefSource::Stable,
+    };
+
+    let root = Path::new(root_path);
+    let dir = root.join(relative);
+    let metadata = std::fs::symlink_metadata(&dir)
+        .map_err(|e| FFIError::Indexer(format!("Cannot read {}: {}", dir.display(), e)))?;
+    if !metadata.is_dir() {
+        return Err(FFIError::Indexer(format!("{} is not a directory", dir.display())));
+    }
+    let unreadable = |path: &Path| FFIError::Indexer(format!("Cannot read the file reference of {}", path.display()));
+    let root_ref = refs.root_ref(root).ok_or_else(|| unreadable(root))?;
+
+    let mut stats = ReconcileStats::default();
+    let mut upserts: Vec<FileEntry> = Vec::new();
+    let (dir_ref, dir_entry) = match relative.parent() {
+        // The root itself is not walked and, without an MFT, not indexed
+        None => (root_ref, None),
+        Some(parent) => {
+            let dir_ref = refs.file_ref(&dir, relative).ok_or_else(|| unreadable(&dir))?;
+            let parent_ref = if parent.as_os_str().is_empty() {
+                root_ref
+            } else {
+                let parent_path = root.join(parent);
+                refs.file_ref(&parent_path, parent).ok_or_else(|| unreadable(&parent_path))?
+            };
+            let mut entry = disk_entry(&dir, &metadata, false, volume.id, dir_ref);
+            entry.parent_ref = Some(parent_ref);
+            (dir_ref, Some(entry))
+        }
+    };
+
+    let skip_list = SkipList::load(db.conn(), volume.id, root_path)?;
+    let mut indexed = get_subtree_signatures(db.conn(), volume.id, dir_ref)?;
+    match dir_entry {
+        Some(entry) => stats.diff(&mut indexed, entry, &mut upserts),
+        None => {
+            indexed.remove(&dir_ref);
+        }
+    }
+
+    let scope = WalkScope {
+        from: relative,
+        from_ref: dir_ref,
+        refs,
+    };
+    let walk = walk_tree(root_path, volume_name, volume.id, exclude, &skip_list, &scope, shutdown_rx, |entry, _| {
+        stats.diff(&mut indexed, entry, &mut upserts);
+        Ok(())
+    })?;
+
+    if !walk.complete {
+        tracing::info!("Rescan of {} interrupted, no changes applied", dir.display());
+        return Ok(ReconcileStats::default());
+    }
+
+    let deletes = unseen(indexed, &walk, &skip_list, volume_name);
+    stats.deleted = deletes.len();
+    if stats.changed() > 0 {
+        apply_write(db, WriteOp::ApplyFileDiff { volume_id: volume.id, upserts, deletes })?;
+        rebuild_facet_counts(db.conn_mut(), volume.id)?;
+    }
+
+    tracing::info!(
+        "Rescan of {} complete: {} inserted, {} updated, {} deleted, {} unchanged",
+        dir.display(),
+        stats.inserted,
+        stats.updated,
+        stats.deleted,
+        stats.unchanged
+    );
+    Ok(stats)
+}
+
+/// Directories skipped after repeated access-denied scans.
+struct SkipList {
+    /// Recorded failures before this walk
+    previous: Vec<SkippedPath>,
+    /// Lowercased full paths not walked this time
+    paths: HashSet<String>,
+    /// Lowercased keys of the same paths relative to the root
+    relative: Vec<String>,
+    /// Last entry, relative to the root, written by the interrupted scan
+    /// this walk resumes; it and everything before it in walk order is
+    /// already indexed
+    indexed_through: Option<PathBuf>,
+}
+
+impl SkipList {
+    /// Load the skip list of a volume walked from `root_path`.
+    fn load(conn: &Connection, volume_id: i64, root_path: &str) -> Result<Self> {
+        let now = chrono::Utc::now().timestamp();
+        let previous = get_skipped_paths(conn, Some(volume_id))?;
+        let paths: HashSet<String> = previous
+            .iter()
+            .filter(|s| is_skipped(s, now))
+            .map(|s| s.path.to_lowercase())
+            .collect();
+        if !paths.is_empty() {
+            tracing::info!("Skipping {} access-denied directories on {}", paths.len(), root_path);
+        }
+
+        let root = PathBuf::from(root_path.to_lowercase());
+        let relative = paths
+            .iter()
+            .filter_map(|path| Path::new(path).strip_prefix(&root).ok())
+            .map(relative_key)
+            .collect();
+
+        Ok(Self {
+            previous,
+            paths,
+            relative,
+            indexed_through: None,
+        })
+    }
+
+    /// Whether a stored path lies in a skipped directory.
+    fn contains_relative(&self, path: &str) -> bool {
+        self.relative.iter().any(|key| is_within(path, key))
+    }
+
+    /// Whether the interrupted scan being resumed already indexed an entry.
+    ///
+    /// Paths compare component by component, which is the walk's name order.
+    fn already_indexed(&self, relative: &Path) -> bool {
+        self.indexed_through.as_deref().is_some_and(|last| relative <= last)
+    }
+
+    /// Whether the interrupted scan being resumed already indexed an entry
+    /// and everything below it, so the walk need not enter it.
+    fn already_indexed_subtree(&self, relative: &Path) -> bool {
+        self.indexed_through
+            .as_deref()
+            .is_some_and(|last| relative <= last && !last.starts_with(relative))
+    }
+}
+
+/// What a directory walk found besides its entries.
+struct WalkOutcome {
+    /// Whether the walk ran to the end rather than stopping for shutdown
+    complete: bool,
+    /// Directories denied this walk: (full path, error)
+    denied: Vec<(String, String)>,
+    /// Lowercased keys, relative to the root, of entries that could not be read
+    unreadable: Vec<String>,
+    /// Errors that could not be tied to a path
+    unknown_errors: usize,
+    /// All errors other than access-denied directories
+    errors: usize,
+    /// Limits that left entries out (e.g. "max_files = 1000"), if any
+    truncated: Option<String>,
+}
+
+impl WalkOutcome {
+    /// Whether a stored path lies in or is an entry the walk could not read.
+    fn is_unreadable(&self, path: &str) -> bool {
+        self.unreadable.iter().any(|key| is_within(path, key))
+    }
+}
+
+/// Key of a path relative to the root as stored in `full_path`, lowercased.
+fn relative_key(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+        .collect::<Vec<_>>()
+        .join("\\")
+}
+
+/// Whether `path` is the entry `key` or lies below it (case-insensitive).
+fn is_within(path: &str, key: &str) -> bool {
+    let path = path.to_lowercase();
+    path == key || path.strip_prefix(key).is_some_and(|rest| rest.starts_with('\\'))
+}
+
+/// How a walk tells entries apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RefSource {
+    /// Synthetic references hashed from paths, for file systems without an MFT
+    Stable,
+    /// The entries' own MFT references, read from disk one by one
+    Mft,
+}
+
+impl RefSource {
+    /// Reference of an entry, or `None` if it could not be read.
+    fn file_ref(self, path: &Path, relative: &Path) -> Option<i64> {
+        match self {
+            RefSource::Stable => Some(stable_file_ref(relative)),
+            RefSource::Mft => file_id(path).map(|(file_ref, _)| file_ref),
+        }
+    }
+
+    /// Reference of the root directory.
+    fn root_ref(self, root: &Path) -> Option<i64> {
+        match self {
+            // The walked root is not indexed; top-level entries point at 0
+            RefSource::Stable => Some(0),
+            RefSource::Mft => file_id(root).map(|(file_ref, _)| file_ref),
+        }
+    }
+}
+
+/// The part of a volume a walk covers.
+struct WalkScope<'a> {
+    /// Directory the walk starts in, relative to the root (empty for all of it)
+    from: &'a Path,
+    /// Reference of that directory
+    from_ref: i64,
+    /// How entries are told apart
+    refs: RefSource,
+}
+
+impl WalkScope<'static> {
+    /// The whole of a volume without an MFT.
+    fn volume() -> Self {
+        Self {
+            from: Path::new(""),
+            from_ref: 0,
+            refs: RefSource::Stable,
+        }
+    }
+}
+
+/// Walk a directory tree in name order, passing each entry and its path
+/// relative to the root to `on_entry`.
+///
+/// Only the part of the tree in `scope` is walked, not counting the
+/// directory it starts in. Skipped and excluded directories are not
+/// walked, nor are those past the depth limit, and entries indexed by the
+/// interrupted scan being resumed are passed over. Stops early, with
+/// `complete` unset, when shutdown is signalled. A walk of a whole volume
+/// also stops, still complete, at the entry count limit; entries beyond
+/// it are left out as if excluded.
+#[allow(clippy::too_many_arguments)]
+fn walk_tree(
+    root_path: &str,
+    volume_name: &str,
+    volume_id: i64,
+    exclude: &ExcludeMatcher,
+    skip_list: &SkipList,
+    scope: &WalkScope<'_>,
+    shutdown_rx: &Receiver<()>,
+    mut on_entry: impl FnMut(FileEntry, &Path) -> Result<()>,
+) -> Result<WalkOutcome> {
+    // Track the current directory chain for parent reference lookups,
+    // starting from the directory the walk starts in
+    let root = PathBuf::from(root_path);
+    let start = if scope.from.as_os_str().is_empty() {
+        root.clone()
+    } else {
+        root.join(scope.from)
+    };
+    let mut ancestors = AncestorRefs::new(scope.from_ref);
+
+    let mut outcome = WalkOutcome {
+        complete: true,
+        denied: Vec::new(),
+        unreadable: Vec::new(),
+        unknown_errors: 0,
+        errors: 0,
+        truncated: None,
+    };
     let mut count = 0;
 
+    // Entries counted against the limit, including those a resumed scan
+    // already indexed
+    let max_files = exclude.max_files().filter(|_| scope.from.as_os_str().is_empty());
+    let mut kept: u64 = 0;
+    let too_deep = Cell::new(false);
+    let mut too_many = false;
+
     // Walk the directory tree
-    for entry_result in WalkDir::new(&root_path)
+    for entry_result in WalkDir::new(&start)
         .follow_links(false)
+        .sort_by_file_name()
         .into_iter()
+        .filter_entry(|e| {
+            if skip_list.paths.contains(&e.path().to_string_lossy().to_lowercase()) {
+                return false;
+            }
+            let relative = e.path().strip_prefix(&root).unwrap_or(e.path());
+            if skip_list.already_indexed_subtree(relative) {
+                return false;
+            }
+            if exclude.is_too_deep(relative.components().count()) {
+                too_deep.set(true);
+                return false;
+            }
+            exclude.is_empty()
+                || relative.as_os_str().is_empty()
+                || !exclude.should_exclude(&display_path(volume_name, relative), e.file_type().is_dir())
+        })
     {
         count += 1;
 
         // Check for shutdown periodically
-        if count % SHUTDOWN_CHECK_INTERVAL == 0 {
-            if shutdown_rx.try_recv().is_ok() {
-                tracing::info!("Shutdown signal received during FAT scan");
-                // Flush any remaining entries
-                if !batch.is_empty() {
-                    let inserted = batch_insert_files(db.conn_mut(), &batch)?;
-                    total_indexed += inserted;
-                }
-                return Ok(total_indexed);
-            }
+        if count % SHUTDOWN_CHECK_INTERVAL == 0
+            && (super::wait_while_paused(shutdown_rx) || shutdown_rx.try_recv().is_ok())
+        {
+            tracing::info!("Shutdown signal received during scan of {}", volume_name);
+            outcome.complete = false;
+            return Ok(outcome);
         }
 
         // Log progress
         if count % PROGRESS_INTERVAL == 0 {
-            tracing::info!("FAT scan progress: {} entries processed", count);
+            tracing::info!("{} scan progress: {} entries processed", volume_name, count);
         }
 
         // Handle entry
         let entry = match entry_result {
             Ok(e) => e,
             Err(e) => {
-                errors += 1;
-                if errors <= 10 {
+                let permission_denied = e
+                    .io_error()
+                    .is_some_and(|io| io.kind() == std::io::ErrorKind::PermissionDenied);
+                if let Some(path) = e.path() {
+                    let relative = path.strip_prefix(&root).unwrap_or(path);
+                    outcome.unreadable.push(relative_key(relative));
+                    if permission_denied {
+                        outcome.denied.push((path.to_string_lossy().to_string(), e.to_string()));
+                        continue;
+                    }
+                } else {
+                    outcome.unknown_errors += 1;
+                }
+
+                outcome.errors += 1;
+                if outcome.errors <= 10 {
                     tracing::debug!("Error walking directory: {}", e);
                 }
                 continue;
@@ -114,8 +901,25 @@ pub fn scan_fat_volume(
 
         let path = entry.path().to_path_buf();
 
-        // Skip root directory itself (already tracked)
-        if path == root {
+        // Skip the directory the walk starts in (already tracked)
+        let depth = entry.depth();
+        if depth == 0 {
+            continue;
+        }
+
+        let relative = path.strip_prefix(&root).unwrap_or(&path);
+
+        if max_files.is_some_and(|max| kept >= max) {
+            too_many = true;
+            break;
+        }
+        kept += 1;
+
+        // Only the way down to where the resumed scan stopped is walked again
+        if skip_list.already_indexed(relative) {
+            if entry.file_type().is_dir() {
+                ancestors.enter_dir(depth, scope.refs.file_ref(&path, relative));
+            }
             continue;
         }
 
@@ -123,91 +927,151 @@ pub fn scan_fat_volume(
         let metadata = match entry.metadata() {
             Ok(m) => m,
             Err(e) => {
-                errors += 1;
-                if errors <= 10 {
+                outcome.errors += 1;
+                outcome.unreadable.push(relative_key(relative));
+                if outcome.errors <= 10 {
                     tracing::debug!("Cannot get metadata for {:?}: {}", path, e);
                 }
+                // Children of a skipped directory must not attach to a sibling
+                if entry.file_type().is_dir() {
+                    ancestors.enter_dir(depth, None);
+                }
                 continue;
             }
         };
 
-        // Extract file information
-        let name = entry
-            .file_name()
-            .to_string_lossy()
-            .to_string();
+        // Synthetic references are stable across rescans; MFT references
+        // need the entry opened
+        let Some(file_ref) = scope.refs.file_ref(&path, relative) else {
+            outcome.errors += 1;
+            outcome.unreadable.push(relative_key(relative));
+            if metadata.is_dir() {
+                ancestors.enter_dir(depth, None);
+            }
+            continue;
+        };
+
+        // Get parent reference, then make this the current directory at its depth
+        let parent_ref = ancestors.parent_of(depth);
+        if metadata.is_dir() {
+            ancestors.enter_dir(depth, Some(file_ref));
+        }
+
+        let mut file = disk_entry(&path, &metadata, entry.path_is_symlink(), volume_id, file_ref);
+        file.parent_ref = parent_ref;
+        on_entry(file, relative)?;
+    }
+
+    outcome.truncated = exclude.limit_reason(too_deep.get(), too_many);
+    Ok(outcome)
+}
+
+/// The index entry of a file or directory read from disk, without a parent.
+fn disk_entry(path: &Path, metadata: &std::fs::Metadata, is_symlink: bool, volume_id: i64, file_ref: i64) -> FileEntry {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
 
-        let is_dir = metadata.is_dir();
-        let size = if is_dir { 0 } else { metadata.len() as i64 };
+    let is_dir = metadata.is_dir();
+    let size = if is_dir { 0 } else { metadata.len() as i64 };
 
-        // Get modified time
-        let modified = metadata
-            .modified()
-            .ok()
+    // Get modified and creation times
+    let unix_secs = |time: std::io::Result<std::time::SystemTime>| {
+        time.ok()
             .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-            .map(|d| d.as_secs() as i64);
-
-        // Assign synthetic file reference
-        let file_ref = next_file_ref;
-        next_file_ref += 1;
-
-        // Store path -> ref mapping
-        path_to_ref.insert(path.clone(), file_ref);
-
-        // Get parent reference
-        let parent_ref = path
-            .parent()
-            .and_then(|p| path_to_ref.get(p))
-            .copied();
-
-        // Add to batch
-        batch.push(FileEntry {
-            volume_id,
-            file_ref: Some(file_ref),
-            parent_ref,
-            name,
-            size,
-            modified,
-            is_dir,
-        });
+            .map(|d| d.as_secs() as i64)
+    };
+    let modified = unix_secs(metadata.modified());
+    let created = unix_secs(metadata.created());
+    let mut attributes = file_attributes(metadata);
 
-        // Flush batch when full
-        if batch.len() >= BATCH_SIZE {
-            let inserted = batch_insert_files(db.conn_mut(), &batch)?;
-            total_indexed += inserted;
-            batch.clear();
+    // Links are recorded, never followed: the walk doesn't follow links,
+    // so a link back up the tree can't loop it. A link to a directory is
+    // indexed as the link itself.
+    let link_target = if is_symlink {
+        attributes |= FILE_ATTRIBUTE_REPARSE_POINT;
+        std::fs::read_link(path).ok().map(|target| target.to_string_lossy().to_string())
+    } else {
+        None
+    };
 
-            // Clear path_to_ref to limit memory usage
-            // Keep only entries that might still have children
-            // (directories in the last level of the batch)
-            // For simplicity, we'll keep all - this may use more memory
-            // but ensures accurate parent refs
-        }
+    FileEntry {
+        volume_id,
+        file_ref: Some(file_ref),
+        parent_ref: None,
+        name,
+        size,
+        modified,
+        created,
+        is_dir,
+        attributes,
+        link: 0,
+        link_target,
+        stream: None,
     }
+}
 
-    // Insert remaining entries
-    if !batch.is_empty() {
-        let inserted = batch_insert_files(db.conn_mut(), &batch)?;
-        total_indexed += inserted;
+/// Record a complete walk: skip list, volume stats, limits that left
+/// entries out and, if the walk wrote anything, facet counts.
+fn finish_walk(
+    db: &mut Database,
+    volume_id: i64,
+    volume_name: &str,
+    skip_list: &SkipList,
+    walk: &WalkOutcome,
+    start: Instant,
+    changed: bool,
+) -> Result<()> {
+    // A resumed walk did not retry the failing directories it passed over
+    let previous = match skip_list.indexed_through {
+        Some(_) => &[][..],
+        None => &skip_list.previous[..],
+    };
+    update_skip_list(db.conn(), volume_id, previous, &skip_list.paths, &walk.denied)?;
+    update_volume_stats(db.conn(), volume_id, start.elapsed().as_millis() as i64)?;
+    set_volume_truncated(db.conn(), volume_id, walk.truncated.as_deref())?;
+    if changed {
+        rebuild_facet_counts(db.conn_mut(), volume_id)?;
     }
 
-    if errors > 0 {
-        tracing::warn!("Encountered {} errors during FAT scan", errors);
+    if walk.errors > 0 {
+        tracing::warn!("Encountered {} errors during scan of {}", walk.errors, volume_name);
     }
-
-    tracing::info!(
-        "FAT volume scan complete for {}: {} files indexed",
-        root_path,
-        total_indexed
-    );
-
-    Ok(total_indexed)
+    if !walk.denied.is_empty() {
+        tracing::info!("{} directories were access-denied during scan of {}", walk.denied.len(), volume_name);
+    }
+    if let Some(reason) = &walk.truncated {
+        tracing::info!("Scan of {} stopped at {}", volume_name, reason);
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Gives every folder under `root` the same fixed mtime, so adding or
+    /// removing a file doesn't make its folder look changed depending on
+    /// whether the test crossed a second boundary.
+    fn pin_dir_mtimes(root: &Path) {
+        let mtime = UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let mut options = std::fs::OpenOptions::new();
+            options.read(true);
+            #[cfg(windows)]
+            {
+                use std::os::windows::fs::OpenOptionsExt;
+                // FILE_FLAG_BACKUP_SEMANTICS, needed to open a directory
+                options.write(true).custom_flags(0x0200_0000);
+            }
+            options.open(entry.path()).unwrap().set_modified(mtime).unwrap();
+        }
+    }
+
     #[test]
     fn test_batch_size() {
         assert_eq!(BATCH_SIZE, 100_000);
@@ -222,4 +1086,409 @@ mod tests {
     fn test_shutdown_check_interval() {
         assert_eq!(SHUTDOWN_CHECK_INTERVAL, 10_000);
     }
+
+    #[test]
+    fn test_skip_list_lifecycle() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::init(&conn).unwrap();
+        let volume_id = insert_volume(&conn, "D:", "1234-ABCD", "FAT").unwrap();
+        let denied_dir = r"D:\System Volume Information".to_string();
+        let denied = vec![(denied_dir.clone(), "Access is denied".to_string())];
+
+        // Skipped only after several consecutive failures
+        for scan in 1..=SKIP_AFTER_FAILURES {
+            let previous = get_skipped_paths(&conn, Some(volume_id)).unwrap();
+            update_skip_list(&conn, volume_id, &previous, &HashSet::new(), &denied).unwrap();
+            let recorded = get_skipped_paths(&conn, Some(volume_id)).unwrap();
+            assert_eq!(recorded[0].failures, scan);
+            assert_eq!(is_skipped(&recorded[0], recorded[0].last_failed), scan == SKIP_AFTER_FAILURES);
+        }
+
+        // Retried after the retry window
+        let recorded = get_skipped_paths(&conn, Some(volume_id)).unwrap();
+        assert!(!is_skipped(&recorded[0], recorded[0].last_failed + SKIP_RETRY_SECS));
+
+        // While skipped the path is not attempted, so it stays recorded
+        let skip: HashSet<String> = [denied_dir.to_lowercase()].into_iter().collect();
+        update_skip_list(&conn, volume_id, &recorded, &skip, &[]).unwrap();
+        assert_eq!(get_skipped_paths(&conn, Some(volume_id)).unwrap().len(), 1);
+
+        // Attempted and readable again: forgotten
+        update_skip_list(&conn, volume_id, &recorded, &HashSet::new(), &[]).unwrap();
+        assert!(get_skipped_paths(&conn, Some(volume_id)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stable_file_ref() {
+        let a = stable_file_ref(Path::new("Photos").join("beach.jpg").as_path());
+
+        // Same path, different case: same ref (FAT is case-insensitive)
+        assert_eq!(a, stable_file_ref(Path::new("photos").join("BEACH.JPG").as_path()));
+        assert_ne!(a, stable_file_ref(Path::new("Photos").join("beach2.jpg").as_path()));
+        assert_ne!(a, 0);
+
+        // Fixed value so refs never change between builds
+        assert_eq!(stable_file_ref(Path::new("a")), 0xaf63_dc4c_8601_ec8c_u64 as i64);
+    }
+
+    #[test]
+    fn test_scan_skips_excluded_entries() {
+        let dir = std::env::temp_dir().join("ffi_test_fat_exclude");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("Photos")).unwrap();
+        std::fs::create_dir_all(root.join("Cache").join("deep")).unwrap();
+        std::fs::write(root.join("Photos").join("beach.jpg"), b"jpg").unwrap();
+        std::fs::write(root.join("Photos").join("scratch.TMP"), b"tmp").unwrap();
+        std::fs::write(root.join("Cache").join("deep").join("blob.bin"), b"bin").unwrap();
+        std::fs::create_dir_all(root.join("Photos").join("node_modules")).unwrap();
+        std::fs::write(root.join("Photos").join("node_modules").join("index.js"), b"js").unwrap();
+        std::fs::write(root.join("Photos").join("backup.iso"), b"iso").unwrap();
+
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let exclude = crate::service::config::ExcludeConfig {
+            paths: vec![r"X:\cache".to_string()],
+            extensions: vec!["tmp".to_string()],
+            directories: vec!["node_modules".to_string()],
+            patterns: vec!["*.iso".to_string()],
+            ..Default::default()
+        }
+        .matcher();
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let indexed =
+            scan_directory_tree(&root.to_string_lossy(), "X:", "FAT", &mut db, &exclude, ScanLimits::default(), &shutdown_rx).unwrap().0;
+        assert_eq!(indexed, 2);
+
+        let names: Vec<String> = db
+            .conn()
+            .prepare("SELECT full_path FROM files ORDER BY full_path")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(names, vec!["Photos".to_string(), r"Photos\beach.jpg".to_string()]);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_scan_keeps_recycle_bin_by_default() {
+        use crate::db::Store;
+        use crate::search::parse_query;
+
+        let dir = std::env::temp_dir().join("ffi_test_fat_recycle_bin");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("root");
+        let bin = root.join("$Recycle.Bin").join("S-1-5-21-1000");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("$IAB12CD.txt"), b"info").unwrap();
+        std::fs::write(bin.join("$RAB12CD.txt"), b"notes").unwrap();
+        std::fs::write(root.join("pagefile.sys"), b"swap").unwrap();
+
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let exclude = crate::service::config::Config::default().exclude_matcher("X:");
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        scan_directory_tree(&root.to_string_lossy(), "X:", "FAT", &mut db, &exclude, ScanLimits::default(), &shutdown_rx).unwrap();
+
+        // System files are left out, deleted items are found with in:recyclebin
+        assert_eq!(db.count(&parse_query("pagefile.sys").unwrap()).unwrap(), 0);
+        assert_eq!(db.count(&parse_query("in:recyclebin").unwrap()).unwrap(), 1);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_walk_stops_at_limits() {
+        let dir = std::env::temp_dir().join("ffi_test_fat_limits");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("a").join("b")).unwrap();
+        std::fs::write(root.join("a").join("1.txt"), b"1").unwrap();
+        std::fs::write(root.join("a").join("b").join("2.txt"), b"2").unwrap();
+        std::fs::write(root.join("c.txt"), b"c").unwrap();
+        std::fs::write(root.join("d.txt"), b"d").unwrap();
+        let root_path = root.to_string_lossy().to_string();
+        let paths = |db: &Database| -> Vec<String> {
+            db.conn()
+                .prepare("SELECT full_path FROM files ORDER BY full_path")
+                .unwrap()
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .collect::<rusqlite::Result<_>>()
+                .unwrap()
+        };
+
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let exclude = ExcludeMatcher::default().with_limits(Some(2), Some(4));
+        let indexed = scan_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, ScanLimits::default(), &shutdown_rx).unwrap().0;
+        assert_eq!(indexed, 4);
+        assert_eq!(paths(&db), vec!["a", r"a\1.txt", r"a\b", "c.txt"]);
+        let volume_id = get_volume(db.conn(), "X:").unwrap().unwrap().id;
+        let truncated = get_volume_stats(db.conn(), volume_id).unwrap().truncated;
+        assert_eq!(truncated.as_deref(), Some("max_depth = 2, max_files = 4"));
+
+        // A lower limit drops the entries past it; none clears the truncation
+        let exclude = ExcludeMatcher::default().with_limits(None, Some(2));
+        let stats = reconcile_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, &shutdown_rx).unwrap();
+        assert_eq!(stats, ReconcileStats { deleted: 2, unchanged: 2, ..Default::default() });
+        assert_eq!(paths(&db), vec!["a", r"a\1.txt"]);
+        let exclude = ExcludeMatcher::default();
+        reconcile_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, &shutdown_rx).unwrap();
+        assert_eq!(get_volume_stats(db.conn(), volume_id).unwrap().truncated, None);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_scan_resumes_after_checkpoint() {
+        use crate::db::{get_scan_checkpoint, get_volume_state, save_scan_checkpoint};
+        use crate::VolumeState;
+
+        let dir = std::env::temp_dir().join("ffi_test_fat_resume");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::create_dir_all(root.join("b")).unwrap();
+        std::fs::write(root.join("a").join("0.txt"), b"0").unwrap();
+        std::fs::write(root.join("a").join("1.txt"), b"1").unwrap();
+        std::fs::write(root.join("b").join("2.txt"), b"2").unwrap();
+        std::fs::write(root.join("c.txt"), b"c").unwrap();
+        let root_path = root.to_string_lossy().to_string();
+
+        // An earlier scan stopped after writing a\1.txt
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "X:", "", "FAT").unwrap();
+        let checkpoint = ScanCheckpoint {
+            next_record: None,
+            last_path: Some(Path::new("a").join("1.txt").to_string_lossy().to_string()),
+            percent: 50,
+            processed: 3,
+            total: 6,
+        };
+        save_scan_checkpoint(db.conn(), volume_id, &checkpoint).unwrap();
+
+        let exclude = ExcludeMatcher::default();
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let indexed = scan_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, ScanLimits::default(), &shutdown_rx).unwrap().0;
+        assert_eq!(indexed, 3);
+
+        // Entries after the checkpoint still hang off their directories
+        let parent: i64 = db
+            .conn()
+            .query_row("SELECT parent_ref FROM files WHERE name = '2.txt'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(parent, stable_file_ref(Path::new("b")));
+        assert_eq!(get_scan_checkpoint(db.conn(), volume_id).unwrap(), ScanCheckpoint::default());
+        assert_eq!(get_volume_state(db.conn(), volume_id).unwrap(), VolumeState::Online);
+
+        // Without a checkpoint the whole tree is walked
+        let indexed = scan_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, ScanLimits::default(), &shutdown_rx).unwrap().0;
+        assert_eq!(indexed, 6);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reconcile_applies_only_changes() {
+        let dir = std::env::temp_dir().join("ffi_test_fat_reconcile");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("Photos")).unwrap();
+        std::fs::write(root.join("Photos").join("beach.jpg"), b"jpg").unwrap();
+        std::fs::write(root.join("Photos").join("old.jpg"), b"old").unwrap();
+        std::fs::write(root.join("notes.txt"), b"notes").unwrap();
+        pin_dir_mtimes(&root);
+
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let exclude = ExcludeMatcher::default();
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let root_path = root.to_string_lossy().to_string();
+        scan_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, ScanLimits::default(), &shutdown_rx).unwrap();
+
+        // Nothing changed: nothing written
+        let stats = reconcile_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, &shutdown_rx).unwrap();
+        assert_eq!(stats, ReconcileStats { unchanged: 4, ..Default::default() });
+
+        std::fs::write(root.join("notes.txt"), b"longer notes").unwrap();
+        std::fs::remove_file(root.join("Photos").join("old.jpg")).unwrap();
+        std::fs::write(root.join("Photos").join("new.jpg"), b"new").unwrap();
+        pin_dir_mtimes(&root);
+
+        let stats = reconcile_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, &shutdown_rx).unwrap();
+        assert_eq!(
+            stats,
+            ReconcileStats {
+                inserted: 1,
+                updated: 1,
+                deleted: 1,
+                unchanged: 2,
+            }
+        );
+
+        let rows: Vec<(String, i64)> = db
+            .conn()
+            .prepare("SELECT full_path, size FROM files ORDER BY full_path")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("Photos".to_string(), 0),
+                (r"Photos\beach.jpg".to_string(), 3),
+                (r"Photos\new.jpg".to_string(), 3),
+                ("notes.txt".to_string(), 12),
+            ]
+        );
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reconcile_subtree() {
+        let dir = std::env::temp_dir().join("ffi_test_fat_subtree");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("Photos").join("2024")).unwrap();
+        std::fs::write(root.join("Photos").join("2024").join("beach.jpg"), b"jpg").unwrap();
+        std::fs::write(root.join("notes.txt"), b"notes").unwrap();
+        pin_dir_mtimes(&root);
+
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let exclude = ExcludeMatcher::default();
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let root_path = root.to_string_lossy().to_string();
+        scan_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, ScanLimits::default(), &shutdown_rx).unwrap();
+
+        // The index lost a folder; changes outside the subtree wait for the next walk
+        db.conn()
+            .execute("DELETE FROM files WHERE name IN ('Photos', 'beach.jpg')", [])
+            .unwrap();
+        std::fs::write(root.join("Photos").join("2024").join("dunes.jpg"), b"dunes").unwrap();
+        std::fs::write(root.join("todo.txt"), b"todo").unwrap();
+        pin_dir_mtimes(&root);
+
+        let photos = Path::new("Photos");
+        let stats = reconcile_subtree(&root_path, "X:", photos, &mut db, &exclude, &shutdown_rx).unwrap();
+        assert_eq!(
+            stats,
+            ReconcileStats {
+                inserted: 3,
+                updated: 0,
+                deleted: 0,
+                unchanged: 1,
+            }
+        );
+
+        std::fs::remove_file(root.join("Photos").join("2024").join("beach.jpg")).unwrap();
+        pin_dir_mtimes(&root);
+        let stats = reconcile_subtree(&root_path, "X:", photos, &mut db, &exclude, &shutdown_rx).unwrap();
+        assert_eq!(stats, ReconcileStats { deleted: 1, unchanged: 3, ..Default::default() });
+
+        let paths: Vec<String> = db
+            .conn()
+            .prepare("SELECT full_path FROM files ORDER BY full_path")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(paths, vec!["Photos", r"Photos\2024", r"Photos\2024\dunes.jpg", "notes.txt"]);
+
+        assert!(reconcile_subtree(&root_path, "X:", Path::new("notes.txt"), &mut db, &exclude, &shutdown_rx).is_err());
+        assert!(reconcile_subtree(&root_path, "Y:", photos, &mut db, &exclude, &shutdown_rx).is_err());
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_records_links_without_following() {
+        let dir = std::env::temp_dir().join("ffi_test_fat_links");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("Photos")).unwrap();
+        std::fs::write(root.join("Photos").join("beach.jpg"), b"jpg").unwrap();
+        // A link back up the tree would loop a walk that followed it
+        std::os::unix::fs::symlink(&root, root.join("Photos").join("loop")).unwrap();
+
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let exclude = ExcludeMatcher::default();
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let root_path = root.to_string_lossy().to_string();
+        let indexed = scan_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, ScanLimits::default(), &shutdown_rx).unwrap().0;
+        assert_eq!(indexed, 3);
+
+        let (is_dir, attributes, target): (bool, u32, Option<String>) = db
+            .conn()
+            .query_row(
+                "SELECT is_dir, attributes, link_target FROM files WHERE full_path = ?1",
+                [r"Photos\loop"],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert!(!is_dir);
+        assert_eq!(attributes & FILE_ATTRIBUTE_REPARSE_POINT, FILE_ATTRIBUTE_REPARSE_POINT);
+        assert_eq!(target, Some(root_path.clone()));
+
+        // Unchanged links reconcile as unchanged
+        let stats = reconcile_directory_tree(&root_path, "X:", "FAT", &mut db, &exclude, &shutdown_rx).unwrap();
+        assert_eq!(stats, ReconcileStats { unchanged: 3, ..Default::default() });
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_unreadable_paths_are_kept() {
+        assert!(is_within(r"Private\a.txt", "private"));
+        assert!(is_within("Private", "private"));
+        assert!(!is_within("Private2", "private"));
+        assert_eq!(relative_key(&Path::new("Photos").join("Beach")), r"photos\beach");
+    }
+
+    #[test]
+    fn test_ancestor_refs_follow_walk_order() {
+        // root/
+        //   a/        ref 1
+        //     x.txt   ref 2
+        //     b/      ref 3
+        //       y.txt ref 4
+        //   z.txt     ref 5
+        let mut ancestors = AncestorRefs::new(0);
+
+        assert_eq!(ancestors.parent_of(1), Some(0));
+        ancestors.enter_dir(1, Some(1));
+        assert_eq!(ancestors.parent_of(2), Some(1));
+        assert_eq!(ancestors.parent_of(2), Some(1));
+        ancestors.enter_dir(2, Some(3));
+        assert_eq!(ancestors.parent_of(3), Some(3));
+        assert_eq!(ancestors.parent_of(1), Some(0));
+
+        // Only the root remains once the walk is back at the top level
+        assert_eq!(ancestors.refs.len(), 1);
+    }
+
+    #[test]
+    fn test_ancestor_refs_skipped_dir() {
+        let mut ancestors = AncestorRefs::new(0);
+
+        ancestors.enter_dir(1, Some(1));
+        assert_eq!(ancestors.parent_of(2), Some(1));
+
+        // Sibling directory skipped after a metadata error: its children get
+        // no parent instead of being attached to the previous sibling
+        ancestors.enter_dir(1, None);
+        assert_eq!(ancestors.parent_of(2), None);
+    }
 }
@@ -1,17 +1,32 @@
 //! FAT volume periodic reconciliation scheduler.
 //!
-//! FAT volumes don't have USN Journal, so we use periodic full scans
-//! to keep the index current. This module manages the scheduling and
-//! execution of those reconciliation scans.
+//! FAT volumes don't have USN Journal, so we periodically walk them to
+//! keep the index current. Each walk is diffed against the indexed rows
+//! and only inserts, updates and deletes are written. This module manages
+//! the scheduling and execution of those reconciliation passes, for
+//! configured network shares as well (see [`super::network`]), and queues
+//! the daily offline cleanup, periodic database maintenance and consistency
+//! checks, pruning of an index grown past its size budget, content
+//! indexing passes and reads of the Recycle Bin.
 
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Receiver;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use crate::db::{open_database, get_volume, update_volume_state, cleanup_old_offline_volumes};
-use crate::indexer::{scan_fat_volume, detect_volumes, VolumeType};
-use crate::service::config::Config;
+use crate::db::{
+    open_database, get_volume, update_volume_state, cleanup_old_offline_volumes, get_offline_volumes,
+    prune_to_budget, run_maintenance, RetentionPolicy,
+};
+use crate::indexer::jobs::{submit_job, JobKind};
+use crate::indexer::network::{configured_shares, reconcile_share, NetworkShare};
+use crate::indexer::{
+    check_consistency, index_contents, index_recycle_bin, reconcile_directory_tree, detect_volumes, wait_while_paused, VolumeInfo, VolumeType,
+};
+use crate::service::config::{Config, SharedConfig};
+use crate::service::ExcludeMatcher;
 use crate::{Result, VolumeState};
 
 /// Interval between reconciler loop iterations (checks if any volume is due for scan).
@@ -20,19 +35,51 @@ const LOOP_INTERVAL: Duration = Duration::from_secs(60);
 /// Interval between offline volume cleanup checks (once per day).
 const CLEANUP_INTERVAL: Duration = Duration::from_secs(86400);
 
+/// Interval between checks of the database file against its size budget.
+const BUDGET_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Interval between reads of new items in the Recycle Bin.
+const RECYCLE_BIN_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Set by [`request_catch_up`] until the reconciler loop picks it up.
+static CATCH_UP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Reconcile every FAT volume and network share on the reconciler's next
+/// pass instead of at their next interval, e.g. after the machine wakes
+/// from sleep and they may have changed elsewhere.
+pub fn request_catch_up() {
+    CATCH_UP_REQUESTED.store(true, Ordering::SeqCst);
+}
+
 /// FAT volume reconciliation scheduler.
 ///
-/// Manages periodic full scans for FAT32/exFAT volumes which don't support
-/// USN Journal change tracking.
+/// Manages periodic reconciliation of FAT32/exFAT volumes and network shares,
+/// which don't support USN Journal change tracking.
 pub struct FatReconciler {
-    /// Map of drive letter to reconciliation interval.
-    volumes: HashMap<char, Duration>,
+    /// Map of volume name (e.g. `D:` or a mount folder) to its schedule.
+    volumes: HashMap<String, ScheduledVolume>,
+    /// Network shares, each on its own schedule and throttle.
+    shares: Vec<NetworkShare>,
     /// When each volume was last scanned.
-    last_scan: HashMap<char, Instant>,
+    last_scan: HashMap<String, Instant>,
     /// Path to the database.
     db_path: PathBuf,
     /// Offline retention period from config.
     offline_retention_days: u32,
+    /// Excludes of shares and hot-added volumes; scheduled volumes carry
+    /// their own.
+    exclude: Arc<ExcludeMatcher>,
+}
+
+/// A FAT volume due for reconciliation every `interval`.
+#[derive(Debug, Clone)]
+struct ScheduledVolume {
+    /// Directory the volume is walked from.
+    root_path: String,
+    /// Time between reconciliation passes.
+    interval: Duration,
+    /// Excludes of the volume, with its own on top of the global ones.
+    exclude: Arc<ExcludeMatcher>,
 }
 
 impl FatReconciler {
@@ -53,49 +100,80 @@ impl FatReconciler {
             .collect();
 
         for vol in fat_volumes {
-            let drive_letter = vol.drive_letter;
+            let name = vol.mount_point.as_str();
 
             // Check if volume is configured and enabled
-            if config.is_volume_enabled(drive_letter) {
-                let interval_mins = config.reconcile_interval_mins(drive_letter);
+            if config.is_volume_enabled(name) {
+                let interval_mins = config.reconcile_interval_mins(name);
                 let interval = Duration::from_secs(interval_mins * 60);
 
                 tracing::info!(
                     "FAT reconciler: volume {} configured with {}min interval",
-                    drive_letter,
+                    name,
                     interval_mins
                 );
 
-                volumes.insert(drive_letter, interval);
+                volumes.insert(
+                    name.to_string(),
+                    ScheduledVolume {
+                        root_path: vol.root_path(),
+                        interval,
+                        exclude: Arc::new(config.exclude_matcher(name)),
+                    },
+                );
                 // Don't scan immediately on start - wait for first interval
-                last_scan.insert(drive_letter, now);
+                last_scan.insert(name.to_string(), now);
             }
         }
 
+        // Shares are walked right away: they may have changed while the service was down
+        let shares = configured_shares(&config.network);
+        for share in &shares {
+            tracing::info!(
+                "FAT reconciler: network share {} configured with {:?} interval, {} entries/s",
+                share.name,
+                share.interval,
+                share.max_entries_per_sec
+            );
+        }
+
         Self {
             volumes,
+            shares,
             last_scan,
             db_path,
             offline_retention_days: config.general.offline_retention_days,
+            exclude: Arc::new(config.exclude.matcher()),
         }
     }
 
     /// Add a volume to the reconciler (for hot-adding mounted volumes).
-    pub fn add_volume(&mut self, drive_letter: char, interval: Duration) {
-        self.volumes.insert(drive_letter, interval);
-        self.last_scan.insert(drive_letter, Instant::now());
+    pub fn add_volume(&mut self, volume: &VolumeInfo, interval: Duration) {
+        let name = volume.mount_point.clone();
+        self.volumes.insert(
+            name.clone(),
+            ScheduledVolume {
+                root_path: volume.root_path(),
+                interval,
+                exclude: Arc::clone(&self.exclude),
+            },
+        );
+        self.last_scan.insert(name, Instant::now());
         tracing::info!(
             "FAT reconciler: added volume {} with {:?} interval",
-            drive_letter,
+            volume.mount_point,
             interval
         );
     }
 
     /// Remove a volume from the reconciler (for unmounted volumes).
-    pub fn remove_volume(&mut self, drive_letter: char) {
-        self.volumes.remove(&drive_letter);
-        self.last_scan.remove(&drive_letter);
-        tracing::info!("FAT reconciler: removed volume {}", drive_letter);
+    ///
+    /// # Arguments
+    /// * `volume_name` - Name the volume is indexed under (e.g. `D:`)
+    pub fn remove_volume(&mut self, volume_name: &str) {
+        self.volumes.remove(volume_name);
+        self.last_scan.remove(volume_name);
+        tracing::info!("FAT reconciler: removed volume {}", volume_name);
     }
 
     /// Check all volumes and run reconciliation for any that are due.
@@ -103,9 +181,9 @@ impl FatReconciler {
     /// Checks shutdown_rx between volumes to allow graceful shutdown.
     pub fn check_and_reconcile(&mut self, shutdown_rx: &Receiver<()>) -> Result<()> {
         let now = Instant::now();
-        let volumes: Vec<_> = self.volumes.iter().map(|(k, v)| (*k, *v)).collect();
+        let volumes: Vec<_> = self.volumes.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
 
-        for (drive_letter, interval) in volumes {
+        for (name, volume) in volumes {
             // Check for shutdown
             if shutdown_rx.try_recv().is_ok() {
                 tracing::info!("FAT reconciler shutdown signal received");
@@ -113,72 +191,142 @@ impl FatReconciler {
             }
 
             // Check if this volume is due for scan
-            if let Some(last) = self.last_scan.get(&drive_letter) {
-                if now.duration_since(*last) < interval {
+            if let Some(last) = self.last_scan.get(&name) {
+                if now.duration_since(*last) < volume.interval {
                     continue; // Not due yet
                 }
             }
 
-            tracing::info!("FAT reconciler: starting scan for volume {}", drive_letter);
+            tracing::info!("FAT reconciler: starting scan for volume {}", name);
 
             // Open database connection for this scan
             let mut db = open_database(&self.db_path)?;
 
             // Get volume ID and set state to Rescanning
-            let drive_str = format!("{}:", drive_letter);
-            if let Ok(Some(vol)) = get_volume(db.conn(), &drive_str) {
+            if let Ok(Some(vol)) = get_volume(db.conn(), &name) {
                 let _ = update_volume_state(db.conn(), vol.id, VolumeState::Rescanning);
             }
 
-            // Run the scan
-            match scan_fat_volume(drive_letter, &mut db, shutdown_rx) {
-                Ok(count) => {
+            // Diff the volume against its index, writing only what changed
+            let result = reconcile_directory_tree(
+                &volume.root_path,
+                &name,
+                "FAT",
+                &mut db,
+                &volume.exclude,
+                shutdown_rx,
+            );
+            match result {
+                Ok(stats) => {
                     tracing::info!(
-                        "FAT reconciler: volume {} scan complete, {} files",
-                        drive_letter,
-                        count
+                        "FAT reconciler: volume {} reconciled, {} of {} entries changed",
+                        name,
+                        stats.changed(),
+                        stats.changed() + stats.unchanged
                     );
                 }
                 Err(e) => {
-                    tracing::error!("FAT reconciler: volume {} scan failed: {}", drive_letter, e);
+                    tracing::error!("FAT reconciler: volume {} scan failed: {}", name, e);
                 }
             }
 
             // Set state back to Online
-            if let Ok(Some(vol)) = get_volume(db.conn(), &drive_str) {
+            if let Ok(Some(vol)) = get_volume(db.conn(), &name) {
                 let _ = update_volume_state(db.conn(), vol.id, VolumeState::Online);
             }
 
             // Update last scan time
-            self.last_scan.insert(drive_letter, Instant::now());
+            self.last_scan.insert(name, Instant::now());
+        }
+
+        for share in self.shares.clone() {
+            if shutdown_rx.try_recv().is_ok() {
+                tracing::info!("FAT reconciler shutdown signal received");
+                return Ok(());
+            }
+
+            if let Some(last) = self.last_scan.get(&share.name) {
+                if now.duration_since(*last) < share.interval {
+                    continue; // Not due yet
+                }
+            }
+
+            tracing::info!("FAT reconciler: starting scan for network share {}", share.name);
+
+            let mut db = open_database(&self.db_path)?;
+            match reconcile_share(&share, &mut db, &self.exclude, shutdown_rx) {
+                Ok(Some(stats)) => {
+                    tracing::info!(
+                        "FAT reconciler: network share {} reconciled, {} of {} entries changed",
+                        share.name,
+                        stats.changed(),
+                        stats.changed() + stats.unchanged
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::error!("FAT reconciler: network share {} scan failed: {}", share.name, e);
+                }
+            }
+
+            // Unreachable shares are retried at their next interval too
+            self.last_scan.insert(share.name, Instant::now());
         }
 
         Ok(())
     }
 
-    /// Check if there are any volumes to reconcile.
+    /// Follow a changed configuration: schedule volumes and shares newly
+    /// enabled, drop those no longer enabled, and take the new intervals
+    /// and excludes. Newly scheduled volumes are reconciled on the next
+    /// pass; the others keep their last scan time.
+    pub fn reconfigure(&mut self, config: &Config) {
+        let db_path = self.db_path.clone();
+        let previous = std::mem::replace(self, Self::new(config, db_path));
+
+        self.last_scan.retain(|name, _| previous.volumes.contains_key(name));
+        for (name, scanned) in previous.last_scan {
+            if self.volumes.contains_key(&name) || self.shares.iter().any(|share| share.name == name) {
+                self.last_scan.insert(name, scanned);
+            }
+        }
+    }
+
+    /// Make every volume and share due for reconciliation now.
+    pub fn mark_all_due(&mut self) {
+        self.last_scan.clear();
+    }
+
+    /// Check if there are any volumes or shares to reconcile.
     pub fn has_volumes(&self) -> bool {
-        !self.volumes.is_empty()
+        !self.volumes.is_empty() || !self.shares.is_empty()
     }
 }
 
 /// Run the FAT reconciler loop in a background thread.
 ///
 /// This function:
-/// 1. Creates a FatReconciler from config
+/// 1. Creates a FatReconciler from config, rescheduling its volumes
+///    whenever the shared configuration is replaced
 /// 2. Loops every 60 seconds checking for due volumes
-/// 3. Runs cleanup_old_offline_volumes once per day
+/// 3. Queues offline volume cleanup to the job pool once per day, or runs
+///    it itself when no pool is running
 /// 4. Exits when shutdown signal received
 pub fn fat_reconciler_loop(
-    config: Config,
+    shared: SharedConfig,
     db_path: PathBuf,
     shutdown_rx: Receiver<()>,
 ) {
+    let mut config = shared.get();
     let mut reconciler = FatReconciler::new(&config, db_path.clone());
     let mut last_cleanup = Instant::now();
+    let mut last_maintenance = Instant::now();
+    let mut last_budget_check: Option<Instant> = None;
+    let mut last_content_pass: Option<Instant> = None;
+    let mut last_recycle_bin_pass: Option<Instant> = None;
 
     if !reconciler.has_volumes() {
-        tracing::info!("FAT reconciler: no FAT volumes configured, loop idle");
+        tracing::info!("FAT reconciler: no FAT volumes or network shares configured, loop idle");
     }
 
     loop {
@@ -195,6 +343,25 @@ pub fn fat_reconciler_loop(
             Err(std::sync::mpsc::TryRecvError::Empty) => {}
         }
 
+        // Passes and jobs that fall due while indexing is paused run once
+        // it resumes
+        if wait_while_paused(&shutdown_rx) {
+            tracing::info!("FAT reconciler loop shutting down");
+            return;
+        }
+
+        // Follow config.toml changes picked up by the service
+        let latest = shared.get();
+        if !Arc::ptr_eq(&latest, &config) {
+            reconciler.reconfigure(&latest);
+            config = latest;
+        }
+
+        if CATCH_UP_REQUESTED.swap(false, Ordering::SeqCst) {
+            tracing::info!("FAT reconciler: catching up on all volumes and shares");
+            reconciler.mark_all_due();
+        }
+
         // Check and reconcile volumes
         if let Err(e) = reconciler.check_and_reconcile(&shutdown_rx) {
             tracing::error!("FAT reconciler error: {}", e);
@@ -202,27 +369,179 @@ pub fn fat_reconciler_loop(
 
         // Run offline volume cleanup once per day
         if last_cleanup.elapsed() >= CLEANUP_INTERVAL {
-            tracing::debug!("Running offline volume cleanup...");
-            match open_database(&db_path) {
-                Ok(db) => {
-                    match cleanup_old_offline_volumes(
-                        db.conn(),
-                        config.general.offline_retention_days,
-                    ) {
-                        Ok(deleted) if deleted > 0 => {
-                            tracing::info!("Cleaned up {} files from old offline volumes", deleted);
+            if !submit_job(JobKind::OfflineCleanup) {
+                match open_database(&db_path) {
+                    Ok(db) => cleanup_offline_volumes(db.conn(), config.offline_retention()),
+                    Err(e) => tracing::error!("Failed to open database for cleanup: {}", e),
+                }
+            }
+            last_cleanup = Instant::now();
+        }
+
+        // Maintain the database and check the file tree at the configured cadence
+        if let Some(interval) = config.database.maintenance_interval() {
+            if last_maintenance.elapsed() >= interval {
+                if !submit_job(JobKind::Maintenance) {
+                    match open_database(&db_path) {
+                        Ok(db) => {
+                            if maintain_database(db.conn()) {
+                                tracing::warn!("Index rebuilt; volumes are indexed again on the next start");
+                            }
                         }
-                        Ok(_) => {}
-                        Err(e) => tracing::error!("Offline cleanup failed: {}", e),
+                        Err(e) => tracing::error!("Failed to open database for maintenance: {}", e),
                     }
                 }
-                Err(e) => tracing::error!("Failed to open database for cleanup: {}", e),
+                if !submit_job(JobKind::ConsistencyCheck) {
+                    match open_database(&db_path) {
+                        Ok(mut db) => check_consistency(&mut db, &config, &shutdown_rx),
+                        Err(e) => tracing::error!("Failed to open database for the consistency check: {}", e),
+                    }
+                }
+                last_maintenance = Instant::now();
             }
-            last_cleanup = Instant::now();
         }
 
-        // Sleep for loop interval
-        std::thread::sleep(LOOP_INTERVAL);
+        // Prune once the database file outgrows its budget; the file also
+        // holds free pages, so the job checks the size it actually uses
+        if let Some(max_bytes) = config.database.max_size_bytes() {
+            if last_budget_check.is_none_or(|checked| checked.elapsed() >= BUDGET_CHECK_INTERVAL) {
+                let size = std::fs::metadata(&db_path).map(|m| m.len()).unwrap_or(0);
+                if size > max_bytes && !submit_job(JobKind::Prune) {
+                    match open_database(&db_path) {
+                        Ok(mut db) => prune_database(db.conn_mut(), &config),
+                        Err(e) => tracing::error!("Failed to open database for pruning: {}", e),
+                    }
+                }
+                last_budget_check = Some(Instant::now());
+            }
+        }
+
+        // Index file contents while enabled; the first pass also deletes
+        // text left over from when it was
+        if last_content_pass
+            .is_none_or(|ran| config.content.enabled && ran.elapsed() >= config.content.interval())
+        {
+            if !submit_job(JobKind::ContentIndex) {
+                match open_database(&db_path) {
+                    Ok(mut db) => index_contents(&mut db, &config.content, &shutdown_rx),
+                    Err(e) => tracing::error!("Failed to open database for content indexing: {}", e),
+                }
+            }
+            last_content_pass = Some(Instant::now());
+        }
+
+        // Read where new items in the Recycle Bin were deleted from; the
+        // first pass waits for the initial index
+        if last_recycle_bin_pass.is_none_or(|ran| ran.elapsed() >= RECYCLE_BIN_INTERVAL) {
+            if !submit_job(JobKind::RecycleBin) {
+                match open_database(&db_path) {
+                    Ok(mut db) => index_recycle_bin(&mut db, &shutdown_rx),
+                    Err(e) => tracing::error!("Failed to open database for the Recycle Bin: {}", e),
+                }
+            }
+            last_recycle_bin_pass = Some(Instant::now());
+        }
+
+        // Sleep for loop interval, waking early on shutdown
+        match shutdown_rx.recv_timeout(LOOP_INTERVAL) {
+            Ok(()) | Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                tracing::info!("FAT reconciler loop shutting down");
+                return;
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+        }
+    }
+}
+
+/// Delete the index of volumes offline past their retention.
+///
+/// Run daily, as [`JobKind::OfflineCleanup`] when the job pool is running.
+/// Warns first about volumes the following cleanup will delete.
+pub fn cleanup_offline_volumes(conn: &rusqlite::Connection, retention: RetentionPolicy) {
+    tracing::debug!("Running offline volume cleanup...");
+    warn_pending_purges(conn, retention.clone());
+    match cleanup_old_offline_volumes(conn, retention) {
+        Ok(deleted) if deleted > 0 => {
+            tracing::info!("Cleaned up {} files from old offline volumes", deleted);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Offline cleanup failed: {}", e),
+    }
+}
+
+/// Run database maintenance and log its outcome.
+///
+/// Run as [`JobKind::Maintenance`] when the job pool is running.
+///
+/// # Returns
+/// true if the index was found corrupt and rebuilt empty, so every volume
+/// needs indexing again.
+pub fn maintain_database(conn: &rusqlite::Connection) -> bool {
+    tracing::debug!("Running database maintenance...");
+    match run_maintenance(conn, chrono::Utc::now().timestamp()) {
+        Ok(report) => {
+            if !report.is_healthy() {
+                tracing::error!(
+                    "Database integrity check still fails after rebuilding the index: {}",
+                    report.integrity_errors.join("; ")
+                );
+            } else if report.rebuilt {
+                tracing::warn!("Database was corrupt; the index was rebuilt and will be filled again");
+            }
+            tracing::info!(
+                "Database maintenance finished in {} ms, {} pages freed",
+                report.duration_ms,
+                report.freed_pages
+            );
+            report.rebuilt
+        }
+        Err(e) => {
+            tracing::error!("Database maintenance failed: {}", e);
+            false
+        }
+    }
+}
+
+/// Prune the index to `database.max_size_mb` if it outgrew it, and log
+/// what was deleted.
+///
+/// Run as [`JobKind::Prune`] when the job pool is running.
+pub fn prune_database(conn: &mut rusqlite::Connection, config: &Config) {
+    let Some(max_bytes) = config.database.max_size_bytes() else {
+        return;
+    };
+    match prune_to_budget(conn, max_bytes, config, chrono::Utc::now().timestamp()) {
+        Ok(Some(report)) => tracing::warn!(
+            "Pruned index from {} to {} bytes: {} offline volumes, {} excluded entries, {} least recently modified files",
+            report.size_before,
+            report.size_after,
+            report.offline_volumes.len(),
+            report.excluded_entries,
+            report.stale_entries
+        ),
+        Ok(None) => tracing::debug!("Index is within its size budget"),
+        Err(e) => tracing::error!("Pruning the index failed: {}", e),
+    }
+}
+
+/// Warn about offline volumes whose index the next daily cleanup deletes.
+fn warn_pending_purges(conn: &rusqlite::Connection, retention: RetentionPolicy) {
+    let now = chrono::Utc::now().timestamp();
+    let next_cleanup = now + CLEANUP_INTERVAL.as_secs() as i64;
+
+    match get_offline_volumes(conn, retention) {
+        Ok(volumes) => {
+            for offline in volumes {
+                if offline.purge_after.is_some_and(|t| t >= now && t < next_cleanup) {
+                    tracing::warn!(
+                        "Index of offline volume {} will be deleted by the next cleanup; \
+                         keep it from the search settings to retain it",
+                        offline.volume.drive_letter
+                    );
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Failed to check offline volumes: {}", e),
     }
 }
 
@@ -247,7 +566,7 @@ impl FatReconcilerHandle {
 ///
 /// Returns a handle for lifecycle management and the shutdown sender.
 pub fn start_fat_reconciler(
-    config: Config,
+    config: SharedConfig,
     db_path: PathBuf,
 ) -> (FatReconcilerHandle, std::sync::mpsc::Sender<()>) {
     let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
@@ -277,4 +596,9 @@ mod tests {
     fn test_cleanup_interval() {
         assert_eq!(CLEANUP_INTERVAL, Duration::from_secs(86400));
     }
+
+    #[test]
+    fn test_budget_check_interval() {
+        assert_eq!(BUDGET_CHECK_INTERVAL, Duration::from_secs(900));
+    }
 }
@@ -0,0 +1,607 @@
+//! Prioritized background jobs and the worker pool that runs them.
+//!
+//! Long-running index maintenance is queued here as a [`JobKind`] instead
+//! of each component spawning its own thread and opening its own database:
+//! the initial index of all volumes, rescans of volumes whose USN journal
+//! was lost or that a client asked to rescan, rescans of single folders,
+//! deletion of volumes offline
+//! past their retention, database maintenance, pruning the index to its
+//! size budget, repairing entries no path leads to, indexing the text
+//! of small files and reading the Recycle Bin.
+//!
+//! A fixed number of workers, each owning one database connection for its
+//! lifetime, take the most urgent queued job that does not scan volumes
+//! another worker is already scanning. Jobs for the same volume are merged
+//! while queued, keeping the higher priority.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use super::consistency::check_consistency;
+use super::content::index_contents;
+use super::recycle_bin::index_recycle_bin;
+use super::rescan::{rescan_pending_paths, rescan_volume};
+use super::fat_reconciler::{cleanup_offline_volumes, maintain_database, prune_database};
+use super::{run_initial_index, wait_while_paused, UsnMonitors};
+use crate::db::{current_writer, get_volume, open_database, Database};
+use crate::service::config::{Config, SharedConfig};
+use crate::Result;
+
+/// Upper bound on `job_workers`; scans are disk-bound, so more rarely help.
+pub const MAX_JOB_WORKERS: usize = 8;
+
+/// Queue of the running job pool, if any.
+static JOB_QUEUE: Mutex<Option<Arc<JobQueue>>> = Mutex::new(None);
+
+/// A unit of background indexing work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    /// Scan every detected volume, then purge excluded entries, refresh
+    /// exclusion suggestions and start monitoring the NTFS volumes' journals
+    InitialIndex,
+    /// Rescan a volume whose USN journal wrapped or was recreated
+    JournalRescan(char),
+    /// Rescan a volume at a client's request
+    UserRescan(char),
+    /// Walk the folders queued with `request_path_rescan` again
+    PathRescan,
+    /// Delete the index of volumes offline past their retention
+    OfflineCleanup,
+    /// Vacuum, analyze and check the database, re-indexing if it was corrupt
+    Maintenance,
+    /// Delete low-value entries until the index fits its size budget
+    Prune,
+    /// Repair entries whose parent is missing or that no path leads to
+    ConsistencyCheck,
+    /// Index the text of new and changed files, or delete it once content
+    /// indexing is turned off
+    ContentIndex,
+    /// Read where new items in the Recycle Bin were deleted from
+    RecycleBin,
+}
+
+impl JobKind {
+    /// Scheduling priority; higher runs first.
+    ///
+    /// The initial index comes first since nothing is searchable without it,
+    /// then volumes whose index is known to be stale, then requested rescans.
+    /// Cleanup, maintenance, pruning, consistency checks, content indexing
+    /// and Recycle Bin reads can always wait.
+    pub fn priority(&self) -> u8 {
+        match self {
+            JobKind::InitialIndex => 3,
+            JobKind::JournalRescan(_) => 2,
+            JobKind::UserRescan(_) | JobKind::PathRescan => 1,
+            JobKind::OfflineCleanup
+            | JobKind::Maintenance
+            | JobKind::Prune
+            | JobKind::ConsistencyCheck
+            | JobKind::ContentIndex
+            | JobKind::RecycleBin => 0,
+        }
+    }
+
+    /// The volume the job rescans, if it targets a single one.
+    pub fn volume(&self) -> Option<char> {
+        match self {
+            JobKind::JournalRescan(letter) | JobKind::UserRescan(letter) => Some(*letter),
+            JobKind::InitialIndex
+            | JobKind::PathRescan
+            | JobKind::OfflineCleanup
+            | JobKind::Maintenance
+            | JobKind::Prune
+            | JobKind::ConsistencyCheck
+            | JobKind::ContentIndex
+            | JobKind::RecycleBin => None,
+        }
+    }
+
+    /// Whether the two jobs do the same work, so only one needs queueing.
+    fn same_work(&self, other: &JobKind) -> bool {
+        match (self.volume(), other.volume()) {
+            (Some(a), Some(b)) => a == b,
+            _ => self == other,
+        }
+    }
+
+    /// Whether the two jobs scan the same volumes and must not run at once.
+    ///
+    /// Maintenance, pruning and consistency checks wait for every scan:
+    /// vacuuming holds the write lock for long, a rebuild or pruning would
+    /// drop the rows a scan is writing, and a scan in progress leaves
+    /// entries whose parent is not written yet. Folder rescans may cover
+    /// any volume, so they wait too.
+    fn conflicts_with(&self, other: &JobKind) -> bool {
+        match (self, other) {
+            _ if self.same_work(other) => true,
+            (JobKind::OfflineCleanup, _) | (_, JobKind::OfflineCleanup) => false,
+            (
+                JobKind::InitialIndex
+                | JobKind::PathRescan
+                | JobKind::Maintenance
+                | JobKind::Prune
+                | JobKind::ConsistencyCheck,
+                _,
+            )
+            | (
+                _,
+                JobKind::InitialIndex
+                | JobKind::PathRescan
+                | JobKind::Maintenance
+                | JobKind::Prune
+                | JobKind::ConsistencyCheck,
+            ) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobKind::InitialIndex => write!(f, "initial index"),
+            JobKind::JournalRescan(letter) => write!(f, "journal rescan of {}:", letter),
+            JobKind::UserRescan(letter) => write!(f, "requested rescan of {}:", letter),
+            JobKind::PathRescan => write!(f, "requested folder rescans"),
+            JobKind::OfflineCleanup => write!(f, "offline volume cleanup"),
+            JobKind::Maintenance => write!(f, "database maintenance"),
+            JobKind::Prune => write!(f, "index size pruning"),
+            JobKind::ConsistencyCheck => write!(f, "file tree consistency check"),
+            JobKind::ContentIndex => write!(f, "content indexing"),
+            JobKind::RecycleBin => write!(f, "Recycle Bin read"),
+        }
+    }
+}
+
+/// A queued job and its arrival order.
+#[derive(Debug)]
+struct QueuedJob {
+    kind: JobKind,
+    seq: u64,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    pending: Vec<QueuedJob>,
+    running: Vec<JobKind>,
+    next_seq: u64,
+    closed: bool,
+}
+
+impl QueueState {
+    /// Index of the most urgent pending job that can start now.
+    ///
+    /// Equal priorities run in arrival order.
+    fn runnable(&self) -> Option<usize> {
+        self.pending
+            .iter()
+            .enumerate()
+            .filter(|(_, job)| !self.running.iter().any(|running| running.conflicts_with(&job.kind)))
+            .max_by_key(|(_, job)| (job.kind.priority(), std::cmp::Reverse(job.seq)))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Prioritized queue of background jobs shared by the pool's workers.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+impl JobQueue {
+    /// Create an empty, open queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queue a job.
+    ///
+    /// A job doing the same work as one already queued is merged into it,
+    /// raising the queued job's priority if the new one is more urgent.
+    ///
+    /// # Returns
+    /// false if the queue is closed and the job was dropped.
+    pub fn push(&self, kind: JobKind) -> bool {
+        let mut state = self.state();
+        if state.closed {
+            return false;
+        }
+
+        match state.pending.iter_mut().find(|job| job.kind.same_work(&kind)) {
+            Some(job) => {
+                if kind.priority() > job.kind.priority() {
+                    job.kind = kind;
+                }
+            }
+            None => {
+                let seq = state.next_seq;
+                state.next_seq += 1;
+                state.pending.push(QueuedJob { kind, seq });
+            }
+        }
+
+        self.changed.notify_all();
+        true
+    }
+
+    /// Take the most urgent job that can start now, without waiting.
+    ///
+    /// The job counts as running until passed to [`JobQueue::finish`].
+    pub fn try_next(&self) -> Option<JobKind> {
+        let mut state = self.state();
+        if state.closed {
+            return None;
+        }
+        Self::take(&mut state)
+    }
+
+    /// Wait for a job that can start and take it.
+    ///
+    /// # Returns
+    /// `None` once the queue is closed.
+    pub fn next(&self) -> Option<JobKind> {
+        let mut state = self.state();
+        loop {
+            if state.closed {
+                return None;
+            }
+            if let Some(kind) = Self::take(&mut state) {
+                return Some(kind);
+            }
+            state = self.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    fn take(state: &mut QueueState) -> Option<JobKind> {
+        let index = state.runnable()?;
+        let kind = state.pending.remove(index).kind;
+        state.running.push(kind);
+        Some(kind)
+    }
+
+    /// Mark a job taken with [`JobQueue::next`] as done, letting jobs it
+    /// conflicted with start.
+    pub fn finish(&self, kind: JobKind) {
+        let mut state = self.state();
+        if let Some(index) = state.running.iter().position(|running| *running == kind) {
+            state.running.remove(index);
+        }
+        self.changed.notify_all();
+    }
+
+    /// Queued jobs in the order they would run if none were running.
+    pub fn pending(&self) -> Vec<JobKind> {
+        let state = self.state();
+        let mut pending: Vec<_> = state.pending.iter().collect();
+        pending.sort_by_key(|job| (std::cmp::Reverse(job.kind.priority()), job.seq));
+        pending.into_iter().map(|job| job.kind).collect()
+    }
+
+    /// Close the queue: drop pending jobs, refuse new ones and wake idle workers.
+    pub fn close(&self) {
+        let mut state = self.state();
+        state.closed = true;
+        state.pending.clear();
+        self.changed.notify_all();
+    }
+
+    /// Whether the queue was closed, meaning running jobs should stop.
+    pub fn is_closed(&self) -> bool {
+        self.state().closed
+    }
+}
+
+/// Queue a job on the running job pool.
+///
+/// # Returns
+/// false if no job pool is running (the service is not indexing).
+pub fn submit_job(kind: JobKind) -> bool {
+    let queue = JOB_QUEUE.lock().ok().and_then(|queue| queue.clone());
+    let queued = queue.is_some_and(|queue| queue.push(kind));
+
+    if queued {
+        tracing::debug!("Queued {}", kind);
+    }
+    queued
+}
+
+/// Whether a job pool is running to take submitted jobs.
+pub fn is_job_pool_running() -> bool {
+    JOB_QUEUE.lock().map(|queue| queue.is_some()).unwrap_or(false)
+}
+
+/// What a worker needs besides its own connection.
+struct WorkerContext {
+    config: SharedConfig,
+    queue: Arc<JobQueue>,
+    monitors: Arc<Mutex<UsnMonitors>>,
+}
+
+/// Handle to the background job pool.
+pub struct JobPool {
+    queue: Arc<JobQueue>,
+    handles: Vec<JoinHandle<()>>,
+    shutdown_txs: Vec<Sender<()>>,
+    monitors: Arc<Mutex<UsnMonitors>>,
+}
+
+impl JobPool {
+    /// Number of workers in the pool.
+    pub fn workers(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// The USN monitors started by the pool's jobs, for applying a changed
+    /// configuration to them.
+    pub fn monitors(&self) -> Arc<Mutex<UsnMonitors>> {
+        Arc::clone(&self.monitors)
+    }
+
+    /// Stop the pool, interrupting running jobs, and the monitors its rescans started.
+    pub fn stop(&mut self) {
+        if let Ok(mut queue) = JOB_QUEUE.lock() {
+            if queue.as_ref().is_some_and(|queue| Arc::ptr_eq(queue, &self.queue)) {
+                *queue = None;
+            }
+        }
+
+        // Closing wakes idle workers; running scans stop on their signal
+        self.queue.close();
+        for tx in self.shutdown_txs.drain(..) {
+            let _ = tx.send(());
+        }
+
+        for handle in self.handles.drain(..) {
+            if handle.join().is_err() {
+                tracing::error!("Job worker panicked");
+            }
+        }
+
+        self.monitors.lock().unwrap_or_else(PoisonError::into_inner).stop_all();
+        tracing::info!("Job pool stopped");
+    }
+}
+
+/// Start the worker pool that runs jobs queued by [`submit_job`].
+///
+/// # Arguments
+/// * `db_path` - Path to the database; each worker opens one connection
+/// * `config` - Service configuration, taken anew by each job;
+///   `general.job_workers` at start sets the pool size, clamped to
+///   `1..=MAX_JOB_WORKERS`
+///
+/// # Returns
+/// A `JobPool` that can be used to stop the workers.
+///
+/// # Errors
+/// Returns an error if a worker's database connection cannot be opened.
+pub fn start_job_pool(db_path: &Path, config: SharedConfig) -> Result<JobPool> {
+    let workers = config.get().general.job_workers.clamp(1, MAX_JOB_WORKERS);
+    let connections = (0..workers).map(|_| open_database(db_path)).collect::<Result<Vec<_>>>()?;
+
+    let queue = Arc::new(JobQueue::new());
+    let monitors = Arc::new(Mutex::new(UsnMonitors::new()));
+
+    let mut handles = Vec::with_capacity(workers);
+    let mut shutdown_txs = Vec::with_capacity(workers);
+    for (index, db) in connections.into_iter().enumerate() {
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let context = WorkerContext {
+            config: config.clone(),
+            queue: Arc::clone(&queue),
+            monitors: Arc::clone(&monitors),
+        };
+
+        handles.push(thread::spawn(move || run_worker(index, db, context, shutdown_rx)));
+        shutdown_txs.push(shutdown_tx);
+    }
+
+    if let Ok(mut current) = JOB_QUEUE.lock() {
+        *current = Some(Arc::clone(&queue));
+    }
+    tracing::info!("Job pool started with {} workers", workers);
+
+    Ok(JobPool {
+        queue,
+        handles,
+        shutdown_txs,
+        monitors,
+    })
+}
+
+/// Run queued jobs until the queue closes.
+fn run_worker(index: usize, mut db: Database, context: WorkerContext, shutdown_rx: Receiver<()>) {
+    tracing::debug!("Job worker {} started", index);
+
+    while let Some(job) = context.queue.next() {
+        // Jobs wait to start while indexing is paused
+        if wait_while_paused(&shutdown_rx) {
+            context.queue.finish(job);
+            break;
+        }
+        tracing::info!("Job worker {} running {}", index, job);
+        run_job(job, &mut db, &context.config.get(), &context, &shutdown_rx);
+        context.queue.finish(job);
+    }
+
+    tracing::debug!("Job worker {} finished", index);
+}
+
+/// Run one job on the worker's connection with the configuration current
+/// when it started.
+fn run_job(job: JobKind, db: &mut Database, config: &Config, context: &WorkerContext, shutdown_rx: &Receiver<()>) {
+    match job {
+        JobKind::InitialIndex => {
+            if run_initial_index(db, config, shutdown_rx) {
+                start_monitors(db, config, context);
+            }
+        }
+        JobKind::JournalRescan(drive_letter) | JobKind::UserRescan(drive_letter) => {
+            match rescan_volume(drive_letter, db, config, shutdown_rx, &context.queue) {
+                Ok(Some(resume_usn)) => start_monitor(drive_letter, resume_usn, db, config, context),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Background rescan of volume {} failed: {}", drive_letter, e),
+            }
+        }
+        JobKind::PathRescan => rescan_pending_paths(db, config, shutdown_rx),
+        JobKind::OfflineCleanup => cleanup_offline_volumes(db.conn(), config.offline_retention()),
+        JobKind::Maintenance => {
+            if maintain_database(db.conn()) {
+                context.queue.push(JobKind::InitialIndex);
+            }
+        }
+        JobKind::Prune => prune_database(db.conn_mut(), config),
+        JobKind::ConsistencyCheck => check_consistency(db, config, shutdown_rx),
+        JobKind::ContentIndex => index_contents(db, &config.content, shutdown_rx),
+        JobKind::RecycleBin => index_recycle_bin(db, shutdown_rx),
+    }
+}
+
+/// Monitor the journals of the NTFS volumes just indexed, resuming from the
+/// positions their scans saved; volumes already monitored are left alone.
+fn start_monitors(db: &Database, config: &Config, context: &WorkerContext) {
+    let Some(writer) = current_writer() else {
+        tracing::debug!("No database writer running, not starting USN monitors");
+        return;
+    };
+    context
+        .monitors
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .start_detected(db, &writer, config);
+}
+
+/// Resume monitoring a rescanned volume unless its monitor is still running.
+fn start_monitor(drive_letter: char, resume_usn: (i64, u64), db: &Database, config: &Config, context: &WorkerContext) {
+    let mut monitors = context.monitors.lock().unwrap_or_else(PoisonError::into_inner);
+    if monitors.is_monitoring(drive_letter) {
+        return;
+    }
+
+    // The monitor outlives the job, so it writes through the database writer
+    let Some(writer) = current_writer() else {
+        tracing::error!("No database writer running, not monitoring volume {}", drive_letter);
+        return;
+    };
+    match get_volume(db.conn(), &format!("{}:", drive_letter)) {
+        Ok(Some(vol)) => monitors.start(drive_letter, vol.id, writer, config, Some(resume_usn)),
+        Ok(None) => tracing::error!("Volume {} not found in database", drive_letter),
+        Err(e) => tracing::error!("Failed to get volume {}: {}", drive_letter, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_job_priority_order() {
+        let queue = JobQueue::new();
+        queue.push(JobKind::OfflineCleanup);
+        queue.push(JobKind::Maintenance);
+        queue.push(JobKind::Prune);
+        queue.push(JobKind::ConsistencyCheck);
+        queue.push(JobKind::ContentIndex);
+        queue.push(JobKind::RecycleBin);
+        queue.push(JobKind::UserRescan('D'));
+        queue.push(JobKind::JournalRescan('E'));
+        queue.push(JobKind::PathRescan);
+        queue.push(JobKind::UserRescan('C'));
+        queue.push(JobKind::InitialIndex);
+
+        assert_eq!(
+            queue.pending(),
+            vec![
+                JobKind::InitialIndex,
+                JobKind::JournalRescan('E'),
+                JobKind::UserRescan('D'),
+                JobKind::PathRescan,
+                JobKind::UserRescan('C'),
+                JobKind::OfflineCleanup,
+                JobKind::Maintenance,
+                JobKind::Prune,
+                JobKind::ConsistencyCheck,
+                JobKind::ContentIndex,
+                JobKind::RecycleBin,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_job_merging() {
+        let queue = JobQueue::new();
+        queue.push(JobKind::UserRescan('C'));
+        queue.push(JobKind::UserRescan('D'));
+        queue.push(JobKind::UserRescan('C'));
+        assert_eq!(queue.pending(), vec![JobKind::UserRescan('C'), JobKind::UserRescan('D')]);
+
+        // A lost journal makes the queued request urgent
+        queue.push(JobKind::JournalRescan('D'));
+        queue.push(JobKind::UserRescan('D'));
+        assert_eq!(queue.pending(), vec![JobKind::JournalRescan('D'), JobKind::UserRescan('C')]);
+
+        queue.push(JobKind::OfflineCleanup);
+        queue.push(JobKind::OfflineCleanup);
+        assert_eq!(queue.pending().len(), 3);
+    }
+
+    #[test]
+    fn test_conflicting_jobs_wait() {
+        let queue = JobQueue::new();
+        queue.push(JobKind::InitialIndex);
+        queue.push(JobKind::JournalRescan('C'));
+        queue.push(JobKind::OfflineCleanup);
+
+        // Rescans wait for the initial index; cleanup runs alongside it
+        assert_eq!(queue.try_next(), Some(JobKind::InitialIndex));
+        assert_eq!(queue.try_next(), Some(JobKind::OfflineCleanup));
+        assert_eq!(queue.try_next(), None);
+
+        queue.finish(JobKind::InitialIndex);
+        assert_eq!(queue.try_next(), Some(JobKind::JournalRescan('C')));
+
+        // A volume lost again while rescanning is queued but runs afterwards
+        queue.push(JobKind::JournalRescan('C'));
+        queue.push(JobKind::UserRescan('D'));
+        assert_eq!(queue.try_next(), Some(JobKind::UserRescan('D')));
+        assert_eq!(queue.try_next(), None);
+
+        queue.finish(JobKind::JournalRescan('C'));
+        assert_eq!(queue.try_next(), Some(JobKind::JournalRescan('C')));
+
+        // Maintenance waits for running scans and holds back new ones
+        queue.push(JobKind::Maintenance);
+        assert_eq!(queue.try_next(), None);
+        queue.finish(JobKind::UserRescan('D'));
+        queue.finish(JobKind::JournalRescan('C'));
+        assert_eq!(queue.try_next(), Some(JobKind::Maintenance));
+        queue.push(JobKind::UserRescan('D'));
+        assert_eq!(queue.try_next(), None);
+    }
+
+    #[test]
+    fn test_close_wakes_workers() {
+        let queue = Arc::new(JobQueue::new());
+        let worker = {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || queue.next())
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        queue.close();
+        assert_eq!(worker.join().unwrap(), None);
+
+        assert!(queue.is_closed());
+        assert!(!queue.push(JobKind::InitialIndex));
+        assert!(queue.pending().is_empty());
+    }
+}
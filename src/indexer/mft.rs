@@ -9,12 +9,29 @@
 use std::sync::mpsc::Receiver;
 
 use crate::db::Database;
+use crate::service::ExcludeMatcher;
 use crate::Result;
 
+use super::throttle::ScanLimits;
+
+#[cfg(windows)]
+use crate::db::{
+    apply_write, get_unresolved_links, insert_volume, purge_excluded_paths, purge_excluded_patterns,
+    purge_over_file_limit, purge_too_deep, rebuild_facet_counts, set_link_targets, set_volume_truncated,
+    update_volume_stats, FacetDeltas, FileEntry, ScanCheckpoint, WriteOp,
+};
+#[cfg(windows)]
+use super::checkpoint::{percent_of, RecordWatermark, ScanProgress};
 #[cfg(windows)]
-use crate::db::{batch_insert_files, insert_volume, FileEntry};
+use super::throttle::{BackgroundIo, ScanThrottle};
 #[cfg(windows)]
 use crate::FFIError;
+#[cfg(windows)]
+use mft::attribute::header::ResidentialHeader;
+#[cfg(windows)]
+use mft::attribute::x30::FileNamespace;
+#[cfg(windows)]
+use mft::attribute::{MftAttributeContent, MftAttributeType};
 
 /// Batch size for database inserts
 #[cfg(windows)]
@@ -24,17 +41,93 @@ const BATCH_SIZE: usize = 100_000;
 #[cfg(windows)]
 const PROGRESS_INTERVAL: usize = 100_000;
 
-/// Scan an NTFS volume using MFT enumeration.
+/// MFT records per disk read: 1 KiB records through a 64 KiB buffer
+#[cfg(windows)]
+const RECORDS_PER_READ: u64 = 64;
+
+/// Number of MFT records a worker parses per chunk
+#[cfg(windows)]
+const CHUNK_RECORDS: u64 = 16_384;
+
+/// Upper bound on automatically chosen parser workers (the MFT reads share one disk)
+#[cfg(windows)]
+const MAX_WORKERS: usize = 4;
+
+/// Upper bound on configured parser workers, for fast disks with many cores
+#[cfg(windows)]
+const MAX_CONFIGURED_WORKERS: usize = 32;
+
+/// MFT parser over a buffered handle to the live `$MFT` stream.
+#[cfg(windows)]
+type LiveMftParser = mft::MftParser<std::io::BufReader<std::fs::File>>;
+
+/// Scan the NTFS volume at a drive letter using MFT enumeration.
+///
+/// See [`scan_ntfs_mount`], which also scans volumes without a letter.
+///
+/// # Arguments
+/// * `drive_letter` - The drive letter to scan (e.g., 'C')
+/// * `db` - Database instance for persisting indexed files
+/// * `exclude` - Excludes of the volume, kept out of the index
+/// * `max_workers` - Parser threads to use; 0 picks one per core, up to 4
+/// * `index_streams` - Whether to index alternate data streams
+/// * `limits` - Read rate and load limits the scan paces itself by
+/// * `shutdown_rx` - Channel receiver for shutdown signals
+///
+/// # Returns
+/// The total number of files indexed.
+pub fn scan_ntfs_volume(
+    drive_letter: char,
+    db: &mut Database,
+    exclude: &ExcludeMatcher,
+    max_workers: usize,
+    index_streams: bool,
+    limits: ScanLimits,
+    shutdown_rx: &Receiver<()>,
+) -> Result<usize> {
+    scan_ntfs_mount(
+        &format!("{}:", drive_letter),
+        &format!("{}:\\", drive_letter),
+        db,
+        exclude,
+        max_workers,
+        index_streams,
+        limits,
+        shutdown_rx,
+    )
+}
+
+/// Scan an NTFS volume, lettered or not, using MFT enumeration.
 ///
 /// This function:
 /// 1. Opens the MFT directly using Windows raw disk access
-/// 2. Uses the mft crate to parse MFT entries
-/// 3. Batches entries for database insertion
-/// 4. Checks for shutdown signal periodically
+/// 2. Splits the record range into chunks parsed by a small worker pool,
+///    each worker with its own MFT handle and parser
+/// 3. Batches parsed entries on this thread and hands them to the database
+///    writer (see [`crate::db::apply_write`])
+/// 4. Checks for shutdown signal between chunks, reading at background I/O
+///    priority and pacing chunks by `limits` and the load of the disk and
+///    CPU (see [`ScanThrottle`])
+/// 5. Drops files with excluded extensions while parsing, then deletes
+///    entries under excluded paths and directories or matching excluded
+///    patterns (MFT records arrive in no path order)
+/// 6. Updates existing rows in place and, once every record was read,
+///    deletes the volume's rows the scan did not see, so rescanning an
+///    indexed volume never wipes it
+/// 7. Resolves the targets of symlinks and junctions, which the MFT only
+///    flags as reparse points
+/// 8. Saves the record below which everything is written after each batch,
+///    and resumes an interrupted scan from there; a resumed scan deletes
+///    nothing, as the records seen before the interruption are not known
 ///
 /// # Arguments
-/// * `drive_letter` - The drive letter to scan (e.g., 'C')
+/// * `volume_name` - Name the volume is indexed under (e.g. `C:` or `C:\Mount\Data`)
+/// * `root_path` - Root of the volume, `C:\` or its `\\?\Volume{...}\` path
 /// * `db` - Database instance for persisting indexed files
+/// * `exclude` - Excludes of the volume, kept out of the index
+/// * `max_workers` - Parser threads to use; 0 picks one per core, up to 4
+/// * `index_streams` - Whether to index alternate data streams as `file:stream` entries
+/// * `limits` - Read rate and load limits the scan paces itself by
 /// * `shutdown_rx` - Channel receiver for shutdown signals
 ///
 /// # Returns
@@ -43,178 +136,468 @@ const PROGRESS_INTERVAL: usize = 100_000;
 /// # Errors
 /// Returns an error if the volume cannot be opened or if MFT parsing fails.
 #[cfg(windows)]
-pub fn scan_ntfs_volume(
-    drive_letter: char,
+#[allow(clippy::too_many_arguments)]
+pub fn scan_ntfs_mount(
+    volume_name: &str,
+    root_path: &str,
     db: &mut Database,
+    exclude: &ExcludeMatcher,
+    max_workers: usize,
+    index_streams: bool,
+    limits: ScanLimits,
     shutdown_rx: &Receiver<()>,
 ) -> Result<usize> {
-    use mft::MftParser;
-    use std::fs::File;
-    use std::io::BufReader;
-
-    tracing::info!("Starting NTFS MFT scan for volume {}", drive_letter);
+    use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+    use std::sync::mpsc::sync_channel;
 
-    // Open the MFT file directly
-    // On Windows, we can access $MFT via \\.\X: path (requires admin)
-    let mft_path = format!("\\\\.\\{}:", drive_letter);
+    tracing::info!("Starting NTFS MFT scan for volume {}", volume_name);
+    let start = std::time::Instant::now();
 
-    // First try to open the raw $MFT stream
-    let mft_stream_path = format!("\\\\?\\{}:\\$MFT", drive_letter);
-
-    let file = match File::open(&mft_stream_path) {
-        Ok(f) => f,
-        Err(e) => {
-            tracing::warn!("Cannot open $MFT directly ({}), trying alternate method", e);
-            // Fallback: try opening the volume directly
-            // This requires raw volume access which needs admin privileges
-            return Err(FFIError::Indexer(format!(
-                "Cannot open MFT for volume {}: {}. Administrator privileges required.",
-                drive_letter, e
-            )));
-        }
-    };
-
-    // Get file size for MFT parser
-    let metadata = file.metadata()
-        .map_err(|e| FFIError::Indexer(format!("Cannot get MFT metadata: {}", e)))?;
-    let size = metadata.len();
-
-    // Create buffered reader for MFT parser
-    let reader = BufReader::with_capacity(64 * 1024, file);
-
-    // Create MFT parser
-    let mut parser = MftParser::from_read_seek(reader, Some(size))
-        .map_err(|e| FFIError::Indexer(format!("Failed to create MFT parser: {}", e)))?;
+    let parser = open_mft_parser(volume_name, root_path)?;
 
     // Insert or update volume record
     let volume_id = insert_volume(
         db.conn(),
-        &format!("{}:", drive_letter),
+        volume_name,
         "", // Serial will be populated from volume detection
         "NTFS",
     )?;
 
+    let progress = ScanProgress::start(db, volume_id)?;
+    apply_write(db, WriteOp::BeginScan { volume_id })?;
+
     let total_entries = parser.get_entry_count();
-    tracing::info!("MFT has {} entries", total_entries);
-
-    let mut batch: Vec<FileEntry> = Vec::with_capacity(BATCH_SIZE);
-    let mut total_indexed = 0;
-    let mut errors = 0;
-
-    for i in 0..total_entries {
-        // Check for shutdown periodically
-        if i % PROGRESS_INTERVAL as u64 == 0 {
-            if shutdown_rx.try_recv().is_ok() {
-                tracing::info!("Shutdown signal received during MFT scan");
-                // Flush any remaining entries
-                if !batch.is_empty() {
-                    let inserted = batch_insert_files(db.conn_mut(), &batch)?;
-                    total_indexed += inserted;
-                }
-                return Ok(total_indexed);
-            }
-            if i > 0 {
-                tracing::info!("MFT scan progress: {}/{} entries", i, total_entries);
+    let first_record = progress.resume().next_record.filter(|&record| record < total_entries).unwrap_or(0);
+    if first_record > 0 {
+        tracing::info!("Resuming interrupted MFT scan of {} at entry {}", volume_name, first_record);
+    }
+    let workers = worker_count(total_entries - first_record, max_workers);
+    tracing::info!("MFT has {} entries, parsing with {} workers", total_entries, workers);
+
+    // Workers parse with their own handle; the first reuses the parser above
+    let mut parsers = vec![parser];
+    for _ in 1..workers {
+        match open_mft_parser(volume_name, root_path) {
+            Ok(p) => parsers.push(p),
+            Err(e) => {
+                tracing::warn!("Cannot open extra MFT parser, using {} workers: {}", parsers.len(), e);
+                break;
             }
         }
+    }
 
-        // Parse MFT entry
-        let entry = match parser.get_entry(i) {
-            Ok(e) => e,
-            Err(e) => {
-                errors += 1;
-                if errors <= 10 {
-                    tracing::debug!("Error reading MFT entry {}: {}", i, e);
-                }
-                continue;
-            }
-        };
-
-        // Skip entries without filename attributes
-        let filename_attr = match entry.find_best_name_attribute() {
-            Some(attr) => attr,
-            None => continue,
-        };
-
-        // Extract file information
-        let file_ref = entry.header.record_number as i64;
-        let parent_ref = filename_attr.parent.entry as i64;
-        let name = filename_attr.name.clone();
-        let is_dir = entry.is_dir();
-
-        // Get standard info for timestamps and data attribute for size
-        // Iterate attributes to find StandardInfo (AttrX10) and Data (AttrX80)
-        let mut modified: Option<i64> = None;
-        let mut size: i64 = 0;
-
-        for attr_result in entry.iter_attributes() {
-            if let Ok(attr) = attr_result {
-                match &attr.data {
-                    mft::attribute::MftAttributeContent::AttrX10(_std_info) => {
-                        // Note: Timestamp extraction requires version-specific API
-                        // For now, we skip timestamp to ensure cross-platform build compatibility
-                        // The modified timestamp will be None for MFT-indexed files
+    let next_record = AtomicU64::new(first_record);
+    let stop = AtomicBool::new(false);
+    let errors = AtomicUsize::new(0);
+    let mut throttle = ScanThrottle::new(root_path, limits, parsers.len());
+
+    let result = std::thread::scope(|s| {
+        // Bounded so fast parsers can't run far ahead of the writer
+        let (tx, rx) = sync_channel::<(std::ops::Range<u64>, Vec<FileEntry>)>(parsers.len() * 2);
+
+        for mut parser in parsers {
+            let tx = tx.clone();
+            let (next_record, stop, errors) = (&next_record, &stop, &errors);
+            s.spawn(move || {
+                let _background = BackgroundIo::enter();
+                while !stop.load(Ordering::Relaxed) {
+                    let Some(range) = next_chunk(next_record, total_entries) else {
+                        break;
+                    };
+                    let mut chunk = Vec::with_capacity((range.end - range.start) as usize);
+
+                    for i in range.clone() {
+                        match parse_record(&mut parser, i, volume_id, index_streams) {
+                            Ok(entries) => {
+                                // A file's streams are kept or dropped with it
+                                let file_excluded = entries
+                                    .first()
+                                    .is_some_and(|file| !file.is_dir && exclude.should_exclude_name(&file.name));
+                                chunk.extend(entries.into_iter().filter(|entry| match entry.stream {
+                                    Some(_) => !file_excluded,
+                                    None => entry.is_dir || !exclude.should_exclude_name(&entry.name),
+                                }));
+                            }
+                            Err(e) => {
+                                if errors.fetch_add(1, Ordering::Relaxed) < 10 {
+                                    tracing::debug!("Error reading MFT entry {}: {}", i, e);
+                                }
+                            }
+                        }
                     }
-                    mft::attribute::MftAttributeContent::AttrX80(_data_attr) => {
-                        // Data attribute - get size from the attribute header
-                        size = attr.header.record_length as i64;
+
+                    // Writer gone (shutdown or error)
+                    if tx.send((range, chunk)).is_err() {
+                        break;
                     }
-                    _ => {}
                 }
+            });
+        }
+        drop(tx);
+
+        let mut batch: Vec<FileEntry> = Vec::with_capacity(BATCH_SIZE);
+        // Chunks in `batch`, checkpointed once it is written
+        let mut batch_chunks: Vec<std::ops::Range<u64>> = Vec::new();
+        let mut watermark = RecordWatermark::new(first_record);
+        let mut total_indexed = 0;
+        let mut records_done: u64 = first_record;
+        let mut next_progress = first_record + PROGRESS_INTERVAL as u64;
+
+        // Write the batch, then save the record below which all are written
+        let mut write_batch =
+            |db: &mut Database, batch: Vec<FileEntry>, chunks: Vec<std::ops::Range<u64>>| -> Result<usize> {
+                let written = apply_write(db, WriteOp::ScanBatch(batch))?;
+                let mut next_record = first_record;
+                for chunk in chunks {
+                    next_record = watermark.written(chunk);
+                }
+                progress.save(
+                    db,
+                    ScanCheckpoint {
+                        next_record: Some(next_record),
+                        last_path: None,
+                        percent: percent_of(next_record, total_entries),
+                        processed: next_record,
+                        total: total_entries,
+                    },
+                )?;
+                Ok(written)
+            };
+
+        let written = (|| -> Result<(usize, bool)> {
+            let mut complete = true;
+            for (range, chunk) in rx.iter() {
+                let reads = (range.end - range.start).div_ceil(RECORDS_PER_READ);
+                if super::wait_while_paused(shutdown_rx)
+                    || throttle.pace_or_shutdown(reads, shutdown_rx)
+                    || shutdown_rx.try_recv().is_ok()
+                {
+                    tracing::info!("Shutdown signal received during MFT scan");
+                    complete = false;
+                    break;
+                }
+
+                records_done += range.end - range.start;
+                progress.report(records_done, total_entries);
+                batch.extend(chunk);
+                batch_chunks.push(range);
+                if batch.len() >= BATCH_SIZE {
+                    let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                    total_indexed += write_batch(db, full, std::mem::take(&mut batch_chunks))?;
+                }
+
+                if records_done >= next_progress {
+                    tracing::info!("MFT scan progress: {}/{} entries", records_done, total_entries);
+                    next_progress += PROGRESS_INTERVAL as u64;
+                }
+            }
+
+            // Insert remaining entries, also when stopping for shutdown
+            if !batch_chunks.is_empty() {
+                total_indexed += write_batch(db, std::mem::take(&mut batch), std::mem::take(&mut batch_chunks))?;
             }
+            Ok((total_indexed, complete))
+        })();
+
+        // Stop workers early on shutdown or write failure; dropping the
+        // receiver unblocks any worker waiting to send
+        stop.store(true, Ordering::Relaxed);
+        drop(rx);
+        written
+    });
+
+    let (mut total_indexed, complete) = match result {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = apply_write(db, WriteOp::FinishScan { volume_id, complete: false });
+            return Err(e);
         }
+    };
 
-        // Add to batch
-        batch.push(FileEntry {
+    // Entries deleted while the volume was not monitored; a resumed scan
+    // only saw part of the volume and leaves that to the next full scan
+    let removed = apply_write(
+        db,
+        WriteOp::FinishScan {
             volume_id,
-            file_ref: Some(file_ref),
-            parent_ref: Some(parent_ref),
-            name,
-            size,
-            modified,
-            is_dir,
-        });
-
-        // Flush batch when full
-        if batch.len() >= BATCH_SIZE {
-            let inserted = batch_insert_files(db.conn_mut(), &batch)?;
-            total_indexed += inserted;
-            batch.clear();
-        }
+            complete: complete && first_record == 0,
+        },
+    )?;
+    if removed > 0 {
+        tracing::info!("Removed {} entries no longer on {}", removed, volume_name);
     }
 
-    // Insert remaining entries
-    if !batch.is_empty() {
-        let inserted = batch_insert_files(db.conn_mut(), &batch)?;
-        total_indexed += inserted;
+    // Paths are only known once parents are inserted; counts are rebuilt below
+    let mut facets = FacetDeltas::new();
+    let too_deep = match exclude.max_depth() {
+        Some(max_depth) => purge_too_deep(db.conn(), volume_id, max_depth, &mut facets)?,
+        None => 0,
+    };
+    let excluded = purge_excluded_paths(db.conn(), volume_id, exclude, &mut facets)?
+        + purge_excluded_patterns(db.conn(), volume_id, exclude, &mut facets)?;
+    if excluded > 0 {
+        tracing::info!("Removed {} excluded entries on {}", excluded, volume_name);
+    }
+
+    // The MFT is read in record order, so the limits are applied once the
+    // tree is known, keeping the levels nearest the root
+    let too_many = match exclude.max_files() {
+        Some(max_files) => purge_over_file_limit(db.conn(), volume_id, max_files, &mut facets)?,
+        None => 0,
+    };
+    let truncated = exclude.limit_reason(too_deep > 0, too_many > 0);
+    if let Some(reason) = &truncated {
+        tracing::info!("Left {} entries of {} unindexed for {}", too_deep + too_many, volume_name, reason);
     }
+    if complete {
+        set_volume_truncated(db.conn(), volume_id, truncated.as_deref())?;
+    }
+    total_indexed = total_indexed.saturating_sub(too_deep + excluded + too_many);
+
+    resolve_link_targets(db, volume_id, root_path)?;
+
+    update_volume_stats(db.conn(), volume_id, start.elapsed().as_millis() as i64)?;
+    rebuild_facet_counts(db.conn_mut(), volume_id)?;
+    progress.finish(db, complete)?;
 
+    let errors = errors.into_inner();
     if errors > 0 {
         tracing::warn!("Encountered {} errors during MFT scan", errors);
     }
 
     tracing::info!(
         "NTFS MFT scan complete for volume {}: {} files indexed",
-        drive_letter,
+        volume_name,
         total_indexed
     );
 
     Ok(total_indexed)
 }
 
+/// Open a parser over the live `$MFT` stream of a volume.
+#[cfg(windows)]
+fn open_mft_parser(volume_name: &str, root_path: &str) -> Result<LiveMftParser> {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    // Open the raw $MFT stream (requires admin)
+    let file = File::open(mft_stream_path(root_path)).map_err(|e| {
+        FFIError::Indexer(format!(
+            "Cannot open MFT for volume {}: {}. Administrator privileges required.",
+            volume_name, e
+        ))
+    })?;
+
+    // Get file size for MFT parser
+    let metadata = file.metadata()
+        .map_err(|e| FFIError::Indexer(format!("Cannot get MFT metadata: {}", e)))?;
+    let size = metadata.len();
+
+    // Create buffered reader for MFT parser
+    let reader = BufReader::with_capacity(64 * 1024, file);
+
+    mft::MftParser::from_read_seek(reader, Some(size))
+        .map_err(|e| FFIError::Indexer(format!("Failed to create MFT parser: {}", e)))
+}
+
+/// Path of the `$MFT` stream below a volume root (`C:\` or a GUID path).
+#[cfg(windows)]
+fn mft_stream_path(root_path: &str) -> String {
+    let root = root_path.trim_end_matches('\\');
+    if root.starts_with(r"\\?\") {
+        format!(r"{}\$MFT", root)
+    } else {
+        format!(r"\\?\{}\$MFT", root)
+    }
+}
+
+/// Record the targets of the volume's symlinks and junctions.
+///
+/// The MFT only flags links as reparse points, so targets are read through
+/// the filesystem once paths are known. Links that can't be read keep no
+/// target and are tried again on the next scan.
+#[cfg(windows)]
+fn resolve_link_targets(db: &mut Database, volume_id: i64, root_path: &str) -> Result<()> {
+    let root = std::path::Path::new(root_path);
+    let targets: Vec<(i64, String)> = get_unresolved_links(db.conn(), volume_id)?
+        .into_iter()
+        .filter_map(|(file_ref, path)| {
+            let target = std::fs::read_link(root.join(path)).ok()?;
+            Some((file_ref, target.to_string_lossy().to_string()))
+        })
+        .collect();
+
+    if !targets.is_empty() {
+        set_link_targets(db.conn_mut(), volume_id, &targets)?;
+    }
+    Ok(())
+}
+
+/// Parse one MFT record into file entries, one per name.
+///
+/// The best name (Win32 over DOS) is link 0; further hard links of the
+/// file follow as links 1, 2, ..., sharing its file reference. With
+/// `index_streams`, each named $DATA stream follows as an entry named
+/// `file:stream` beside the file. Records without a filename attribute
+/// give no entries.
+#[cfg(windows)]
+fn parse_record(parser: &mut LiveMftParser, i: u64, volume_id: i64, index_streams: bool) -> Result<Vec<FileEntry>> {
+    let entry = parser
+        .get_entry(i)
+        .map_err(|e| FFIError::Indexer(e.to_string()))?;
+
+    // Skip entries without filename attributes
+    let filename_attr = match entry.find_best_name_attribute() {
+        Some(attr) => attr,
+        None => return Ok(Vec::new()),
+    };
+
+    // Extract file information
+    let file_ref = entry.header.record_number as i64;
+    let parent_ref = filename_attr.parent.entry as i64;
+    let name = filename_attr.name.clone();
+    let is_dir = entry.is_dir();
+
+    // Modified and creation times and attribute flags from
+    // $STANDARD_INFORMATION (0x10) and size from the unnamed $DATA stream
+    // (0x80); alternate data streams are not counted
+    let mut modified: Option<i64> = None;
+    let mut created: Option<i64> = None;
+    let mut attributes: u32 = 0;
+    let mut size: i64 = 0;
+    // Further hard links: other $FILE_NAME (0x30) attributes, skipping the
+    // short DOS aliases of long names
+    let mut other_names: Vec<(i64, String)> = Vec::new();
+    // Alternate data streams: named $DATA attributes, sized like the unnamed one
+    let mut streams: Vec<(String, i64)> = Vec::new();
+
+    for attr in entry.iter_attributes().flatten() {
+        match &attr.data {
+            MftAttributeContent::AttrX30(file_name) if !matches!(file_name.namespace, FileNamespace::DOS) => {
+                let link = (file_name.parent.entry as i64, file_name.name.clone());
+                if (link.0, &link.1) != (parent_ref, &name) && !other_names.contains(&link) {
+                    other_names.push(link);
+                }
+            }
+            MftAttributeContent::AttrX10(std_info) => {
+                modified = Some(std_info.modified.as_second());
+                created = Some(std_info.created.as_second());
+                attributes = std_info.file_flags.bits();
+            }
+            _ if attr.header.type_code == MftAttributeType::DATA && attr.header.name.is_empty() => {
+                if let Some(data_size) = data_stream_size(&attr.header.residential_header) {
+                    size = data_size;
+                }
+            }
+            _ if attr.header.type_code == MftAttributeType::DATA && index_streams => {
+                if let Some(data_size) = data_stream_size(&attr.header.residential_header) {
+                    streams.push((attr.header.name.clone(), data_size));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let primary = FileEntry {
+        volume_id,
+        file_ref: Some(file_ref),
+        parent_ref: Some(parent_ref),
+        name,
+        size,
+        modified,
+        created,
+        is_dir,
+        attributes,
+        link: 0,
+        link_target: None,
+        stream: None,
+    };
+    let links: Vec<FileEntry> = other_names
+        .into_iter()
+        .enumerate()
+        .map(|(n, (parent_ref, name))| FileEntry {
+            parent_ref: Some(parent_ref),
+            name,
+            link: n as u32 + 1,
+            ..primary.clone()
+        })
+        .collect();
+    let streams: Vec<FileEntry> = streams
+        .into_iter()
+        .map(|(stream, size)| FileEntry {
+            name: format!("{}:{}", primary.name, stream),
+            size,
+            is_dir: false,
+            stream: Some(stream),
+            ..primary.clone()
+        })
+        .collect();
+
+    let mut entries = vec![primary];
+    entries.extend(links);
+    entries.extend(streams);
+    Ok(entries)
+}
+
+/// Real size of a $DATA stream from its attribute header.
+///
+/// Resident data lives inside the MFT record, so its length is the size.
+/// Non-resident data reports the real (not allocated) size in the first
+/// segment of the stream; later segments of fragmented files return `None`.
+#[cfg(windows)]
+fn data_stream_size(header: &ResidentialHeader) -> Option<i64> {
+    match header {
+        ResidentialHeader::Resident(resident) => Some(resident.data_size as i64),
+        ResidentialHeader::NonResident(non_resident) if non_resident.vnc_first == 0 => {
+            Some(non_resident.file_size as i64)
+        }
+        ResidentialHeader::NonResident(_) => None,
+    }
+}
+
+/// Number of parser workers for an MFT of `total_entries` records.
+///
+/// `configured` workers are used as given (0 picks one per core, up to
+/// `MAX_WORKERS`), but never more than there are chunks to parse.
+#[cfg(windows)]
+fn worker_count(total_entries: u64, configured: usize) -> usize {
+    let workers = match configured {
+        0 => std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(MAX_WORKERS),
+        n => n.min(MAX_CONFIGURED_WORKERS),
+    };
+    let chunks = total_entries.div_ceil(CHUNK_RECORDS) as usize;
+    workers.min(chunks).max(1)
+}
+
+/// Claim the next chunk of record numbers, or None when all are claimed.
+#[cfg(windows)]
+fn next_chunk(next_record: &std::sync::atomic::AtomicU64, total_entries: u64) -> Option<std::ops::Range<u64>> {
+    let start = next_record.fetch_add(CHUNK_RECORDS, std::sync::atomic::Ordering::Relaxed);
+    (start < total_entries).then(|| start..(start + CHUNK_RECORDS).min(total_entries))
+}
+
 /// Stub for non-Windows platforms.
 ///
 /// NTFS MFT scanning requires Windows APIs and is not available
 /// on other platforms.
 #[cfg(not(windows))]
-pub fn scan_ntfs_volume(
-    drive_letter: char,
+#[allow(clippy::too_many_arguments)]
+pub fn scan_ntfs_mount(
+    volume_name: &str,
+    _root_path: &str,
     _db: &mut Database,
+    _exclude: &ExcludeMatcher,
+    _max_workers: usize,
+    _index_streams: bool,
+    _limits: ScanLimits,
     _shutdown_rx: &Receiver<()>,
 ) -> Result<usize> {
     tracing::warn!(
         "NTFS MFT scanning is only available on Windows (volume {} skipped)",
-        drive_letter
+        volume_name
     );
     Ok(0)
 }
@@ -232,4 +615,37 @@ mod tests {
     fn test_progress_interval() {
         assert_eq!(PROGRESS_INTERVAL, 100_000);
     }
+
+    #[test]
+    fn test_next_chunk_covers_all_records() {
+        let next = std::sync::atomic::AtomicU64::new(0);
+        let total = CHUNK_RECORDS * 2 + 5;
+
+        assert_eq!(next_chunk(&next, total), Some(0..CHUNK_RECORDS));
+        assert_eq!(next_chunk(&next, total), Some(CHUNK_RECORDS..CHUNK_RECORDS * 2));
+        assert_eq!(next_chunk(&next, total), Some(CHUNK_RECORDS * 2..total));
+        assert_eq!(next_chunk(&next, total), None);
+    }
+
+    #[test]
+    fn test_worker_count_bounds() {
+        assert_eq!(worker_count(0, 0), 1);
+        assert_eq!(worker_count(10, 0), 1);
+        assert!(worker_count(u64::MAX / 2, 0) <= MAX_WORKERS);
+
+        // Configured counts may exceed the automatic bound, not the chunk count
+        assert_eq!(worker_count(u64::MAX / 2, 8), 8);
+        assert_eq!(worker_count(u64::MAX / 2, 1000), MAX_CONFIGURED_WORKERS);
+        assert_eq!(worker_count(CHUNK_RECORDS * 3, 8), 3);
+    }
+
+    #[test]
+    fn test_mft_stream_path() {
+        assert_eq!(mft_stream_path(r"C:\"), r"\\?\C:\$MFT");
+        assert_eq!(
+            mft_stream_path(r"\\?\Volume{3e2f6a10-5b7c-4f7e-9a1d-2c8b7e6f5a41}\"),
+            r"\\?\Volume{3e2f6a10-5b7c-4f7e-9a1d-2c8b7e6f5a41}\$MFT"
+        );
+    }
 }
+
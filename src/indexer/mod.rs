@@ -3,77 +3,74 @@
 //! This module coordinates volume detection and file scanning,
 //! dispatching to the appropriate scanner (MFT for NTFS, walkdir for FAT).
 //! Also provides USN Journal monitoring for real-time NTFS updates,
-//! and FAT volume periodic reconciliation.
+//! a prioritized job pool running the initial index, rescans and offline
+//! cleanup, periodic reconciliation of FAT volumes and network shares,
+//! pausing all of these at runtime, live progress and throttling of full
+//! scans, optional indexing of the text of small files, the original
+//! paths of items in the Recycle Bin, and applying a configuration
+//! reloaded while running.
 
 mod volume;
 mod mft;
 mod fat;
+mod checkpoint;
+mod consistency;
+mod content;
+mod recycle_bin;
+mod reload;
+pub mod shadow;
 pub mod usn_monitor;
 pub mod fat_reconciler;
+pub mod network;
+pub mod rescan;
+pub mod jobs;
+pub mod pause;
+pub mod progress;
+pub mod throttle;
 
 pub use volume::*;
 pub use mft::*;
 pub use fat::*;
 pub use usn_monitor::{
     ChangeType, UsnChange, UsnError, UsnMonitor,
-    AdaptiveThrottle, UsnMonitorHandle,
-    deduplicate_changes, apply_changes_batch, usn_monitor_loop, trigger_background_rescan,
+    AdaptivePoll, AdaptiveThrottle, BatchStats, ChangeApplier, UsnMonitorHandle,
+    deduplicate_changes, apply_changes_batch, usn_monitor_loop,
 };
-pub use fat_reconciler::{FatReconciler, FatReconcilerHandle, start_fat_reconciler};
+pub use fat_reconciler::{FatReconciler, FatReconcilerHandle, request_catch_up, start_fat_reconciler};
+pub use network::{NetworkShare, ShareThrottle, configured_shares, reconcile_share};
+pub use rescan::{request_path_rescan, request_rescan, trigger_background_rescan, trigger_monitor_rescan};
+pub use jobs::{JobKind, JobPool, JobQueue, is_job_pool_running, start_job_pool, submit_job};
+pub use pause::{is_indexing_paused, pause_indexing, resume_indexing, wait_while_paused};
+pub use progress::{IndexingProgress, VolumeProgress};
+pub use throttle::{ScanLimits, ScanThrottle};
+pub use consistency::{check_consistency, ConsistencyReport};
+pub use content::{index_contents, ContentReport};
+pub use recycle_bin::index_recycle_bin;
+pub use reload::apply_config_changes;
 
 use std::sync::mpsc::Receiver;
-use std::thread::{self, JoinHandle};
 
-use crate::db::Database;
+use crate::db::{analyze_exclusions, mount_volume, purge_excluded, save_exclusion_suggestions, Database, DbWriter};
+use crate::service::config::{Config, ExcludeConfig};
 
-/// Background indexer that scans volumes and populates the database.
-pub struct Indexer {
-    handle: Option<JoinHandle<()>>,
-}
-
-impl Indexer {
-    /// Signal the indexer to stop.
-    pub fn stop(&mut self) {
-        // The indexer checks shutdown_rx; dropping it will cause try_recv to return Disconnected
-        // which we interpret as a shutdown signal. The actual signaling is done via the channel.
-        // Here we just wait for the thread to finish.
-        if let Some(handle) = self.handle.take() {
-            // Wait for the indexer thread to finish (it checks shutdown_rx)
-            match handle.join() {
-                Ok(()) => tracing::info!("Indexer thread stopped gracefully"),
-                Err(_) => tracing::error!("Indexer thread panicked"),
-            }
-        }
-    }
-}
-
-/// Start a background indexer that scans all detected volumes.
+/// Index all detected volumes; run by the job pool as [`JobKind::InitialIndex`].
 ///
-/// The indexer runs in a separate thread and:
-/// 1. Detects available volumes
-/// 2. For each volume, chooses the appropriate scanner (MFT for NTFS, walkdir for FAT)
-/// 3. Streams file entries to the database in batches
-/// 4. Checks for shutdown signal periodically
+/// For each volume not turned off in the configuration this chooses the
+/// appropriate scanner (MFT for NTFS, walkdir for FAT) and streams file
+/// entries to the database in batches, checking for shutdown between
+/// volumes. Afterwards it purges entries
+/// indexed before they were excluded, refreshes
+/// exclusion suggestions and optionally indexes shadow copies.
 ///
 /// # Arguments
-/// * `db` - Database instance for persisting indexed files
-/// * `shutdown_rx` - Channel receiver for shutdown signals
+/// * `db` - The running worker's connection
+/// * `config` - Service configuration for excludes and shadow copies
+/// * `shutdown_rx` - Shutdown channel of the running worker
 ///
 /// # Returns
-/// An `Indexer` instance that can be used to stop the background task.
-pub fn start_background_indexer(db: Database, shutdown_rx: Receiver<()>) -> Indexer {
-    let handle = thread::spawn(move || {
-        run_indexer(db, shutdown_rx);
-    });
-
-    Indexer {
-        handle: Some(handle),
-    }
-}
-
-/// Internal function that runs the indexing loop.
-fn run_indexer(mut db: Database, shutdown_rx: Receiver<()>) {
-    tracing::info!("Background indexer started");
+/// Whether the index ran to the end rather than stopping for shutdown.
+pub(crate) fn run_initial_index(db: &mut Database, config: &Config, shutdown_rx: &Receiver<()>) -> bool {
+    tracing::info!("Initial index started");
 
     // Detect available volumes
     let volumes = detect_volumes();
@@ -83,25 +80,58 @@ fn run_indexer(mut db: Database, shutdown_rx: Receiver<()>) {
         // Check for shutdown before processing each volume
         if shutdown_rx.try_recv().is_ok() {
             tracing::info!("Shutdown signal received, stopping indexer");
-            return;
+            return false;
+        }
+
+        if config.is_volume_disabled(volume.mount_point.as_str()) {
+            tracing::info!("Skipping volume {}, disabled in the configuration", volume.mount_point);
+            continue;
         }
 
         tracing::info!(
             "Indexing volume {}: {:?} ({:?})",
-            volume.drive_letter,
+            volume.mount_point,
             volume.fs_type,
             volume.volume_serial
         );
 
-        let result = match volume.fs_type {
-            VolumeType::NTFS => scan_ntfs_volume(volume.drive_letter, &mut db, &shutdown_rx),
-            VolumeType::FAT32 | VolumeType::ExFAT => {
-                scan_fat_volume(volume.drive_letter, &mut db, &shutdown_rx)
+        // Identify the volume by serial before scanning, so a disk that came
+        // back under another letter keeps its index
+        if volume.fs_type != VolumeType::Unknown {
+            let fs_type = volume.fs_type.fs_label();
+            if let Err(e) = mount_volume(db.conn(), &volume.mount_point, &volume.volume_serial, fs_type) {
+                tracing::warn!("Failed to record volume {}: {}", volume.mount_point, e);
             }
+        }
+
+        let root_path = volume.root_path();
+        let limits = ScanLimits::for_volume(config, volume.mount_point.as_str());
+        let exclude = config.exclude_matcher(volume.mount_point.as_str());
+        let result = match volume.fs_type {
+            VolumeType::NTFS => scan_ntfs_mount(
+                &volume.mount_point,
+                &root_path,
+                db,
+                &exclude,
+                config.general.mft_scan_workers,
+                config.general.index_alternate_streams,
+                limits,
+                shutdown_rx,
+            ),
+            VolumeType::FAT32 | VolumeType::ExFAT => scan_directory_tree(
+                &root_path,
+                &volume.mount_point,
+                "FAT",
+                db,
+                &exclude,
+                limits,
+                shutdown_rx,
+            )
+            .map(|(count, _)| count),
             VolumeType::Unknown => {
                 tracing::warn!(
                     "Skipping volume {} with unknown filesystem type",
-                    volume.drive_letter
+                    volume.mount_point
                 );
                 continue;
             }
@@ -111,23 +141,64 @@ fn run_indexer(mut db: Database, shutdown_rx: Receiver<()>) {
             Ok(count) => {
                 tracing::info!(
                     "Volume {} indexing complete: {} files",
-                    volume.drive_letter,
+                    volume.mount_point,
                     count
                 );
             }
             Err(e) => {
-                tracing::error!("Failed to index volume {}: {}", volume.drive_letter, e);
+                tracing::error!("Failed to index volume {}: {}", volume.mount_point, e);
             }
         }
     }
 
-    tracing::info!("Background indexer finished");
+    // Remove entries indexed before they were excluded
+    if shutdown_rx.try_recv().is_ok() {
+        tracing::info!("Shutdown signal received, stopping indexer");
+        return false;
+    }
+    match purge_excluded(db.conn_mut(), config) {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Purged {} excluded entries from the index", count),
+        Err(e) => tracing::error!("Failed to purge excluded entries: {}", e),
+    }
+
+    // Suggest excludes for large low-value or high-churn trees
+    if shutdown_rx.try_recv().is_ok() {
+        tracing::info!("Shutdown signal received, stopping indexer");
+        return false;
+    }
+    if let Err(e) = refresh_exclusion_suggestions(db, &config.exclude) {
+        tracing::error!("Failed to analyze index for exclusion suggestions: {}", e);
+    }
+
+    // Optionally index VSS shadow copies (previous versions) as virtual volumes
+    if config.shadow_copies.enabled {
+        match shadow::index_shadow_copies(db, config, shutdown_rx) {
+            Ok(count) => tracing::info!("Shadow copy indexing complete: {} files", count),
+            Err(e) => tracing::error!("Failed to index shadow copies: {}", e),
+        }
+    }
+
+    tracing::info!("Initial index finished");
+    true
+}
+
+/// Re-run the exclusion analysis and store its suggestions for the settings UI.
+fn refresh_exclusion_suggestions(db: &mut Database, exclude: &ExcludeConfig) -> crate::Result<()> {
+    let suggestions = analyze_exclusions(db.conn(), exclude)?;
+    save_exclusion_suggestions(db.conn_mut(), &suggestions)?;
+
+    if !suggestions.is_empty() {
+        tracing::info!("{} exclusion suggestions available in settings", suggestions.len());
+    }
+    Ok(())
 }
 
 /// Collection of active USN monitor handles for managing lifecycle.
 pub struct UsnMonitors {
     handles: Vec<UsnMonitorHandle>,
     shutdown_txs: Vec<std::sync::mpsc::Sender<()>>,
+    drive_letters: Vec<char>,
 }
 
 impl UsnMonitors {
@@ -136,6 +207,75 @@ impl UsnMonitors {
         Self {
             handles: Vec::new(),
             shutdown_txs: Vec::new(),
+            drive_letters: Vec::new(),
+        }
+    }
+
+    /// Whether a started monitor for the volume is still running.
+    pub fn is_monitoring(&self, drive_letter: char) -> bool {
+        self.drive_letters
+            .iter()
+            .zip(&self.handles)
+            .any(|(letter, handle)| *letter == drive_letter && !handle.is_finished())
+    }
+
+    /// Start monitoring a volume.
+    ///
+    /// # Arguments
+    /// * `drive_letter` - The volume to monitor
+    /// * `volume_id` - ID of the volume's record
+    /// * `writer` - Database writer the monitor sends its changes to
+    /// * `config` - Service configuration for polling, throttling and excludes
+    /// * `resume_usn` - Optional (last_usn, journal_id) to resume from
+    pub fn start(
+        &mut self,
+        drive_letter: char,
+        volume_id: i64,
+        writer: DbWriter,
+        config: &Config,
+        resume_usn: Option<(i64, u64)>,
+    ) {
+        let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
+
+        let handle = usn_monitor_loop(
+            drive_letter,
+            volume_id,
+            writer,
+            AdaptivePoll::new(
+                config.usn_poll_interval_secs(drive_letter),
+                config.general.usn_poll_min_secs,
+                config.general.usn_poll_max_secs,
+            ),
+            config.throttle_cpu_percent(drive_letter),
+            config.exclude_matcher(drive_letter),
+            shutdown_rx,
+            resume_usn,
+        );
+
+        self.handles.push(handle);
+        self.shutdown_txs.push(shutdown_tx);
+        self.drive_letters.push(drive_letter);
+
+        tracing::info!("Started USN monitor for volume {}", drive_letter);
+    }
+
+    /// Drive letters of the volumes whose monitor is still running.
+    pub fn monitored(&self) -> Vec<char> {
+        self.drive_letters
+            .iter()
+            .zip(&self.handles)
+            .filter(|(_, handle)| !handle.is_finished())
+            .map(|(letter, _)| *letter)
+            .collect()
+    }
+
+    /// Stop monitoring a volume and wait for its monitor to finish.
+    pub fn stop(&mut self, drive_letter: char) {
+        while let Some(index) = self.drive_letters.iter().position(|letter| *letter == drive_letter) {
+            self.drive_letters.remove(index);
+            let _ = self.shutdown_txs.remove(index).send(());
+            self.handles.remove(index).stop();
+            tracing::info!("Stopped USN monitor for volume {}", drive_letter);
         }
     }
 
@@ -149,12 +289,80 @@ impl UsnMonitors {
         }
 
         // Wait for all threads to finish
+        self.drive_letters.clear();
         for mut handle in self.handles.drain(..) {
             handle.stop();
         }
 
         tracing::info!("All USN monitors stopped");
     }
+
+    /// Start a monitor for each detected NTFS volume not already monitored
+    /// or turned off in the configuration, resuming from the journal
+    /// position saved for it.
+    ///
+    /// # Arguments
+    /// * `db` - Connection the volumes' saved state is read from
+    /// * `writer` - Database writer the monitors send their changes to
+    /// * `config` - Service configuration
+    pub fn start_detected(&mut self, db: &Database, writer: &DbWriter, config: &Config) {
+        // Detect NTFS volumes; the USN journal is opened by drive letter, so
+        // volumes mounted only in folders are left to rescans
+        let volumes = detect_volumes();
+        let ntfs_volumes: Vec<_> = volumes
+            .iter()
+            .filter(|v| v.fs_type == VolumeType::NTFS)
+            .filter_map(|v| v.drive_letter)
+            .filter(|&letter| !self.is_monitoring(letter) && !config.is_volume_disabled(letter))
+            .collect();
+
+        if ntfs_volumes.is_empty() {
+            tracing::info!("No unmonitored NTFS volumes found, skipping USN monitoring");
+            return;
+        }
+
+        tracing::info!("Starting USN monitors for {} NTFS volumes", ntfs_volumes.len());
+
+        for drive_letter in ntfs_volumes {
+            self.resume(drive_letter, db, writer, config);
+        }
+    }
+
+    /// Start monitoring an indexed volume from the journal position saved
+    /// for it.
+    ///
+    /// # Arguments
+    /// * `drive_letter` - The volume to monitor
+    /// * `db` - Connection the volume's saved state is read from
+    /// * `writer` - Database writer the monitor sends its changes to
+    /// * `config` - Service configuration
+    pub fn resume(&mut self, drive_letter: char, db: &Database, writer: &DbWriter, config: &Config) {
+        use crate::db::{get_volume_usn, get_volume};
+
+        let drive_str = format!("{}:", drive_letter);
+        let volume = match get_volume(db.conn(), &drive_str) {
+            Ok(Some(vol)) => vol,
+            Ok(None) => {
+                tracing::error!("Volume {} not found in database", drive_letter);
+                return;
+            }
+            Err(e) => {
+                tracing::error!("Failed to get volume {}: {}", drive_letter, e);
+                return;
+            }
+        };
+
+        // Check for saved USN state to resume from
+        let resume_usn = match get_volume_usn(db.conn(), volume.id) {
+            Ok(usn_state) => usn_state,
+            Err(e) => {
+                tracing::warn!("Failed to get USN state for {}: {}", drive_letter, e);
+                None
+            }
+        };
+
+        self.start(drive_letter, volume.id, writer.clone(), config, resume_usn);
+    }
 }
 
 impl Default for UsnMonitors {
@@ -170,76 +378,24 @@ impl Default for UsnMonitors {
 /// USN journal support.
 ///
 /// # Arguments
-/// * `db_path` - Path to the database (each monitor opens its own connection)
-/// * `poll_interval_secs` - Polling interval in seconds (from config)
+/// * `db_path` - Path to the database, read for each volume's saved state
+/// * `writer` - Database writer the monitors send their changes to
+/// * `config` - Service configuration; polling and throttling follow each
+///   volume's class, and intervals adapt to its change rate
 ///
 /// # Returns
 /// A `UsnMonitors` instance for managing the monitor lifecycle.
 pub fn start_usn_monitors(
     db_path: &std::path::Path,
-    poll_interval_secs: u64,
+    writer: &DbWriter,
+    config: &Config,
 ) -> UsnMonitors {
-    use crate::db::{open_database, get_volume_usn, get_volume};
-
     let mut monitors = UsnMonitors::new();
 
-    // Detect NTFS volumes
-    let volumes = detect_volumes();
-    let ntfs_volumes: Vec<_> = volumes
-        .iter()
-        .filter(|v| v.fs_type == VolumeType::NTFS)
-        .collect();
-
-    if ntfs_volumes.is_empty() {
-        tracing::info!("No NTFS volumes found, skipping USN monitoring");
-        return monitors;
-    }
-
-    tracing::info!("Starting USN monitors for {} NTFS volumes", ntfs_volumes.len());
-
-    for volume in ntfs_volumes {
-        let drive_letter = volume.drive_letter;
-
-        // Each monitor needs its own database connection
-        let db = match open_database(db_path) {
-            Ok(db) => db,
-            Err(e) => {
-                tracing::error!("Failed to open database for USN monitor {}: {}", drive_letter, e);
-                continue;
-            }
-        };
-
-        // Check for saved USN state to resume from
-        let drive_str = format!("{}:", drive_letter);
-        let resume_usn = match get_volume(db.conn(), &drive_str) {
-            Ok(Some(vol)) => {
-                match get_volume_usn(db.conn(), vol.id) {
-                    Ok(usn_state) => usn_state,
-                    Err(e) => {
-                        tracing::warn!("Failed to get USN state for {}: {}", drive_letter, e);
-                        None
-                    }
-                }
-            }
-            _ => None,
-        };
-
-        // Create shutdown channel for this monitor
-        let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
-
-        // Start the monitor
-        let handle = usn_monitor_loop(
-            drive_letter,
-            db,
-            poll_interval_secs,
-            shutdown_rx,
-            resume_usn,
-        );
-
-        monitors.handles.push(handle);
-        monitors.shutdown_txs.push(shutdown_tx);
-
-        tracing::info!("Started USN monitor for volume {}", drive_letter);
+    // Monitors write through the writer; this connection only reads their state
+    match crate::db::open_database(db_path) {
+        Ok(db) => monitors.start_detected(&db, writer, config),
+        Err(e) => tracing::error!("Failed to open database for USN monitors: {}", e),
     }
 
     monitors
@@ -249,8 +405,10 @@ pub fn start_usn_monitors(
 ///
 /// This function:
 /// 1. Checks if volume is configured for indexing
-/// 2. Compares volume serial to detect volume swaps
-/// 3. Sets volume state to Online
+/// 2. Looks the volume up by serial, so a disk that comes back under another
+///    letter keeps its index and moves to the new letter
+/// 3. Sets volume state to Online, and marks a volume swapped out of the
+///    letter Offline
 /// 4. Triggers appropriate indexing (NTFS USN monitor or FAT reconciliation)
 ///
 /// # Arguments
@@ -265,8 +423,7 @@ pub fn handle_volume_mount(
     config: &crate::service::config::Config,
     db_path: &std::path::Path,
 ) -> crate::Result<()> {
-    use crate::db::{open_database, get_volume, update_volume_state, get_volume_serial};
-    use crate::VolumeState;
+    use crate::db::{open_database, get_volume, get_volume_by_serial, get_volume_serial};
 
     // Check if volume is configured for indexing
     if !config.is_volume_enabled(drive_letter) {
@@ -283,44 +440,54 @@ pub fn handle_volume_mount(
     // Open database
     let db = open_database(db_path)?;
 
-    // Get existing volume record
+    // The volume this disk was indexed as, wherever it was mounted, and
+    // the one last seen at this letter
     let drive_str = format!("{}:", drive_letter);
-    let existing = get_volume(db.conn(), &drive_str)?;
-
-    if let Some(vol) = existing {
-        // Check if serial matches (same physical volume)
-        if vol.volume_serial != serial_str && !serial_str.is_empty() {
-            // Different volume at same drive letter!
-            tracing::warn!(
-                "Volume swap detected at {}: {} -> {}",
-                drive_letter,
-                vol.volume_serial,
-                serial_str
+    let known = match serial_str.is_empty() {
+        true => None,
+        false => get_volume_by_serial(db.conn(), &serial_str)?,
+    };
+    let previous = get_volume(db.conn(), &drive_str)?;
+
+    let fs_type = match &known {
+        Some(vol) if vol.drive_letter != drive_str => {
+            tracing::info!(
+                "Volume {} moved from {} to {}, keeping its index",
+                serial_str,
+                vol.drive_letter,
+                drive_str
             );
-
-            // Mark old data as offline
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs() as i64)
-                .unwrap_or(0);
-            update_volume_state(db.conn(), vol.id, VolumeState::Offline { since: now })?;
-
-            tracing::info!("Old volume {} marked offline, fresh index needed", vol.volume_serial);
-
-            // Note: A fresh index will be triggered by the next indexing run
-            // The FAT reconciler or USN monitor setup will create a new volume record
-        } else {
-            // Same volume reconnected - set to Online and trigger reconciliation
+            vol.fs_type.clone()
+        }
+        Some(vol) => {
+            // Same volume reconnected - quick reconciliation will happen on
+            // the next FAT reconciler cycle, or the USN monitor will catch
+            // up from the stored last_usn
             tracing::info!("Volume {} reconnected (serial: {})", drive_letter, serial_str);
-            update_volume_state(db.conn(), vol.id, VolumeState::Online)?;
-
-            // Note: Quick reconciliation will happen on next FAT reconciler cycle
-            // or USN monitor will catch up from stored last_usn
+            vol.fs_type.clone()
         }
-    } else {
-        tracing::info!("New volume {} detected (serial: {}), will be indexed", drive_letter, serial_str);
-        // New volume - will be picked up by indexer
-    }
+        None => {
+            if let Some(vol) = previous.filter(|vol| !vol.volume_serial.is_empty() && !serial_str.is_empty()) {
+                // Different volume at same drive letter; the old one's
+                // index is kept while it is offline
+                tracing::warn!(
+                    "Volume swap detected at {}: {} -> {}",
+                    drive_letter,
+                    vol.volume_serial,
+                    serial_str
+                );
+            }
+            // New volume - will be picked up by indexer
+            tracing::info!("New volume {} detected (serial: {}), will be indexed", drive_letter, serial_str);
+            detect_volumes()
+                .into_iter()
+                .find(|volume| volume.drive_letter == Some(drive_letter))
+                .map_or("Unknown", |volume| volume.fs_type.fs_label())
+                .to_string()
+        }
+    };
+
+    mount_volume(db.conn(), &drive_str, &serial_str, &fs_type)?;
 
     Ok(())
 }
@@ -380,7 +547,7 @@ pub fn handle_volume_unmount(
 ///
 /// # Arguments
 /// * `event_rx` - Receiver for volume events
-/// * `config` - Service configuration
+/// * `config` - Service configuration, taken anew for each mount
 /// * `db_path` - Path to the database
 /// * `shutdown_rx` - Shutdown signal receiver
 ///
@@ -388,7 +555,7 @@ pub fn handle_volume_unmount(
 /// Thread handle for the event handler.
 pub fn start_volume_event_handler(
     event_rx: std::sync::mpsc::Receiver<crate::service::VolumeEvent>,
-    config: crate::service::config::Config,
+    config: crate::service::config::SharedConfig,
     db_path: std::path::PathBuf,
     shutdown_rx: std::sync::mpsc::Receiver<()>,
 ) -> std::thread::JoinHandle<()> {
@@ -420,7 +587,7 @@ pub fn start_volume_event_handler(
 
             for drive in ready {
                 pending_mounts.remove(&drive);
-                if let Err(e) = handle_volume_mount(drive, &config, &db_path) {
+                if let Err(e) = handle_volume_mount(drive, &config.get(), &db_path) {
                     tracing::error!("Failed to handle mount for {}: {}", drive, e);
                 }
             }
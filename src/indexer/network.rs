@@ -0,0 +1,273 @@
+//! Network share indexing.
+//!
+//! Opt-in via `[network]` in config. Shares (UNC paths or mapped drives)
+//! have no change journal, so the FAT reconciler walks them like FAT
+//! volumes on their own interval, each walk paced by a [`ShareThrottle`] so
+//! indexing doesn't saturate the LAN. A share that can't be reached is
+//! marked offline and its index kept until the offline retention ends.
+
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use crate::db::{get_volume, get_volume_state, update_volume_state, Database};
+use crate::service::config::NetworkConfig;
+use crate::service::ExcludeMatcher;
+use crate::{Result, VolumeState};
+
+use super::fat::{reconcile_directory_tree_throttled, ReconcileStats};
+
+/// Filesystem type recorded for network shares.
+pub const SHARE_FS_TYPE: &str = "NET";
+
+/// A configured network share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkShare {
+    /// Name the share is indexed under (e.g. `\\nas\media` or `Z:`)
+    pub name: String,
+    /// Directory the share is walked from
+    pub root_path: String,
+    /// Time between reconciliation passes
+    pub interval: Duration,
+    /// Entries read per second, 0 for no limit
+    pub max_entries_per_sec: u32,
+    /// Deepest folder level indexed, if limited
+    pub max_depth: Option<usize>,
+    /// Most entries indexed, if limited
+    pub max_files: Option<u64>,
+}
+
+/// Shares to index from `[network]`, none if network indexing is disabled.
+///
+/// Shares whose path is neither a UNC path nor a drive letter are skipped
+/// with a warning.
+pub fn configured_shares(config: &NetworkConfig) -> Vec<NetworkShare> {
+    if !config.enabled {
+        return Vec::new();
+    }
+
+    config
+        .shares
+        .iter()
+        .filter_map(|share| {
+            let (Some(name), Some(root_path)) = (share.volume_name(), share.root_path()) else {
+                tracing::warn!("Ignoring network share {:?}: not a UNC path or drive letter", share.path);
+                return None;
+            };
+            Some(NetworkShare {
+                name,
+                root_path,
+                interval: Duration::from_secs(config.reconcile_interval_mins(share) * 60),
+                max_entries_per_sec: config.max_entries_per_sec(share),
+                max_depth: share.max_depth,
+                max_files: share.max_files,
+            })
+        })
+        .collect()
+}
+
+/// Caps the rate entries are read from a share.
+///
+/// Counts entries in one-second windows and sleeps out the rest of a
+/// window once its budget is spent.
+#[derive(Debug)]
+pub struct ShareThrottle {
+    max_per_sec: u32,
+    window_start: Instant,
+    in_window: u32,
+}
+
+impl ShareThrottle {
+    /// Create a throttle allowing `max_per_sec` entries per second (0 for no limit).
+    pub fn new(max_per_sec: u32) -> Self {
+        Self {
+            max_per_sec,
+            window_start: Instant::now(),
+            in_window: 0,
+        }
+    }
+
+    /// Count one entry, sleeping first if this window's budget is spent.
+    pub fn pace(&mut self) {
+        if let Some(delay) = self.delay(Instant::now()) {
+            std::thread::sleep(delay);
+            self.window_start = Instant::now();
+        }
+    }
+
+    /// Count one entry at `now`, returning how long to wait before the next
+    /// window if this one is full.
+    fn delay(&mut self, now: Instant) -> Option<Duration> {
+        if self.max_per_sec == 0 {
+            return None;
+        }
+
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= Duration::from_secs(1) {
+            self.window_start = now;
+            self.in_window = 0;
+        }
+
+        self.in_window += 1;
+        if self.in_window < self.max_per_sec {
+            return None;
+        }
+        self.in_window = 0;
+        self.window_start = now;
+        Duration::from_secs(1).checked_sub(elapsed).filter(|d| !d.is_zero())
+    }
+}
+
+/// Reconcile a share's index with the share, or mark it offline.
+///
+/// # Arguments
+/// * `share` - The share to walk
+/// * `db` - Database holding the share's index
+/// * `exclude` - Excludes kept out of the index; the share's own depth and
+///   file limits apply on top
+/// * `shutdown_rx` - Channel receiver for shutdown signals
+///
+/// # Returns
+/// The reconciliation counts, or `None` if the share could not be reached.
+pub fn reconcile_share(
+    share: &NetworkShare,
+    db: &mut Database,
+    exclude: &ExcludeMatcher,
+    shutdown_rx: &Receiver<()>,
+) -> Result<Option<ReconcileStats>> {
+    let existing = get_volume(db.conn(), &share.name)?;
+
+    if !Path::new(&share.root_path).is_dir() {
+        if let Some(volume) = existing {
+            if !matches!(get_volume_state(db.conn(), volume.id)?, VolumeState::Offline { .. }) {
+                let now = chrono::Utc::now().timestamp();
+                update_volume_state(db.conn(), volume.id, VolumeState::Offline { since: now })?;
+                tracing::info!("Network share {} is unreachable, marked offline", share.name);
+            }
+        } else {
+            tracing::info!("Network share {} is unreachable, not indexed yet", share.name);
+        }
+        return Ok(None);
+    }
+
+    if let Some(volume) = &existing {
+        update_volume_state(db.conn(), volume.id, VolumeState::Rescanning)?;
+    }
+
+    let exclude = exclude.clone().with_limits(share.max_depth, share.max_files);
+    let mut throttle = ShareThrottle::new(share.max_entries_per_sec);
+    let result = reconcile_directory_tree_throttled(
+        &share.root_path,
+        &share.name,
+        SHARE_FS_TYPE,
+        db,
+        &exclude,
+        shutdown_rx,
+        Some(&mut throttle),
+    );
+
+    // Reachable again: back online, whether or not the walk succeeded
+    if let Some(volume) = get_volume(db.conn(), &share.name)? {
+        update_volume_state(db.conn(), volume.id, VolumeState::Online)?;
+    }
+
+    result.map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::config::ShareConfig;
+
+    #[test]
+    fn test_configured_shares() {
+        let share = |path: &str| ShareConfig {
+            path: path.to_string(),
+            reconcile_interval_mins: None,
+            max_entries_per_sec: None,
+            max_depth: None,
+            max_files: Some(1000),
+        };
+        let mut config = NetworkConfig {
+            shares: vec![share(r"\\nas\media"), share("not a share")],
+            ..Default::default()
+        };
+        assert!(configured_shares(&config).is_empty());
+
+        config.enabled = true;
+        assert_eq!(
+            configured_shares(&config),
+            vec![NetworkShare {
+                name: r"\\nas\media".to_string(),
+                root_path: r"\\nas\media\".to_string(),
+                interval: Duration::from_secs(120 * 60),
+                max_entries_per_sec: 500,
+                max_depth: None,
+                max_files: Some(1000),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_throttle_delay() {
+        let mut throttle = ShareThrottle::new(3);
+        let start = throttle.window_start;
+
+        assert_eq!(throttle.delay(start), None);
+        assert_eq!(throttle.delay(start + Duration::from_millis(100)), None);
+        assert_eq!(
+            throttle.delay(start + Duration::from_millis(250)),
+            Some(Duration::from_millis(750))
+        );
+
+        // Entries a second or more apart start a new window
+        let later = start + Duration::from_secs(5);
+        assert_eq!(throttle.delay(later), None);
+        assert_eq!(throttle.delay(later), None);
+        assert_eq!(throttle.delay(later + Duration::from_secs(2)), None);
+
+        let mut unlimited = ShareThrottle::new(0);
+        assert!((0..1000).all(|_| unlimited.delay(start).is_none()));
+    }
+
+    #[test]
+    fn test_unreachable_share_goes_offline() {
+        let dir = std::env::temp_dir().join("ffi_test_network_share");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("share");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("movie.mkv"), b"mkv").unwrap();
+
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let exclude = ExcludeMatcher::default();
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let share = NetworkShare {
+            name: r"\\nas\media".to_string(),
+            root_path: root.to_string_lossy().to_string(),
+            interval: Duration::from_secs(60),
+            max_entries_per_sec: 0,
+            max_depth: None,
+            max_files: None,
+        };
+
+        let stats = reconcile_share(&share, &mut db, &exclude, &shutdown_rx).unwrap().unwrap();
+        assert_eq!(stats.inserted, 1);
+        let volume = get_volume(db.conn(), &share.name).unwrap().unwrap();
+        assert_eq!(get_volume_state(db.conn(), volume.id).unwrap(), VolumeState::Online);
+
+        // Unreachable: the index is kept and the share marked offline
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(reconcile_share(&share, &mut db, &exclude, &shutdown_rx).unwrap(), None);
+        assert!(matches!(
+            get_volume_state(db.conn(), volume.id).unwrap(),
+            VolumeState::Offline { .. }
+        ));
+        let files: i64 = db
+            .conn()
+            .query_row("SELECT COUNT(*) FROM files WHERE volume_id = ?1", [volume.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(files, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,55 @@
+//! Pausing and resuming indexing at runtime.
+//!
+//! Indexing is paused from an IPC command or by pausing the service in the
+//! Services console. While paused, full scans, rescans and FAT
+//! reconciliation wait between batches, queued jobs and reconciliation
+//! passes wait to start, and USN monitors stop polling; journal changes
+//! accumulate and are applied after [`resume_indexing`]. Searches are not
+//! affected.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How often a paused scan or monitor checks for resume or shutdown.
+const PAUSE_POLL: Duration = Duration::from_millis(500);
+
+/// Whether indexing is paused, shared by all indexing threads.
+static INDEXING_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Pause indexing until [`resume_indexing`] is called.
+pub fn pause_indexing() {
+    if !INDEXING_PAUSED.swap(true, Ordering::SeqCst) {
+        tracing::info!("Indexing paused");
+    }
+}
+
+/// Resume indexing paused by [`pause_indexing`].
+pub fn resume_indexing() {
+    if INDEXING_PAUSED.swap(false, Ordering::SeqCst) {
+        tracing::info!("Indexing resumed");
+    }
+}
+
+/// Whether indexing is currently paused.
+pub fn is_indexing_paused() -> bool {
+    INDEXING_PAUSED.load(Ordering::SeqCst)
+}
+
+/// Block while indexing is paused.
+///
+/// # Arguments
+/// * `shutdown_rx` - Shutdown channel of the calling scan or monitor
+///
+/// # Returns
+/// true if shutdown was signalled while waiting (the signal is consumed),
+/// false once indexing may continue.
+pub fn wait_while_paused(shutdown_rx: &Receiver<()>) -> bool {
+    while is_indexing_paused() {
+        match shutdown_rx.recv_timeout(PAUSE_POLL) {
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => return true,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
+    false
+}
@@ -0,0 +1,96 @@
+//! Live progress of full scans.
+//!
+//! Scanners report how many entries they have processed, and how many they
+//! expect, to the process-wide [`IndexingProgress`] registry as they go.
+//! The status command reads it to show "Indexing C: 42% (1.2M/2.9M files)";
+//! the same counts are saved with each scan checkpoint, so the status of an
+//! interrupted scan is known before it resumes.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use super::checkpoint::percent_of;
+
+/// Registry of the scans running in this process.
+static PROGRESS: IndexingProgress = IndexingProgress::new();
+
+/// Entries a scan of one volume has processed, out of those it expects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeProgress {
+    /// Entries processed so far
+    pub processed: u64,
+    /// Entries expected in all: MFT records, or what the last complete walk
+    /// found (0 if unknown)
+    pub total: u64,
+}
+
+impl VolumeProgress {
+    /// Share of the expected entries processed, held below 100 until the
+    /// scan finishes.
+    pub fn percent(&self) -> u8 {
+        percent_of(self.processed, self.total)
+    }
+}
+
+/// Progress of the full scans running in this process, by volume ID.
+pub struct IndexingProgress {
+    volumes: Mutex<BTreeMap<i64, VolumeProgress>>,
+}
+
+impl IndexingProgress {
+    const fn new() -> Self {
+        Self {
+            volumes: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// The registry the scanners of this process report to.
+    pub fn global() -> &'static Self {
+        &PROGRESS
+    }
+
+    /// Record how far the scan of a volume got.
+    pub fn report(&self, volume_id: i64, progress: VolumeProgress) {
+        self.volumes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(volume_id, progress);
+    }
+
+    /// Forget a volume's scan once it has stopped, completed or not.
+    pub fn finish(&self, volume_id: i64) {
+        self.volumes.lock().unwrap_or_else(PoisonError::into_inner).remove(&volume_id);
+    }
+
+    /// Progress of the scan of a volume, if one is running.
+    pub fn get(&self, volume_id: i64) -> Option<VolumeProgress> {
+        self.volumes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&volume_id)
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = IndexingProgress::new();
+        assert_eq!(registry.get(1), None);
+
+        registry.report(1, VolumeProgress { processed: 10, total: 40 });
+        registry.report(1, VolumeProgress { processed: 20, total: 40 });
+        registry.report(2, VolumeProgress { processed: 5, total: 0 });
+        assert_eq!(registry.get(1).map(|p| p.percent()), Some(50));
+        assert_eq!(registry.get(2).map(|p| p.percent()), Some(0));
+
+        registry.finish(1);
+        assert_eq!(registry.get(1), None);
+        assert!(registry.get(2).is_some());
+    }
+}
@@ -0,0 +1,217 @@
+//! Background reading of the Recycle Bin's `$I` files.
+//!
+//! Run as `JobKind::RecycleBin` after the initial index and every few
+//! minutes. Items in the Recycle Bin are indexed like any other entry (as
+//! `$R...` under `$Recycle.Bin\<SID>`) and left out of results unless a
+//! search asks for them with `in:recyclebin`. A pass first forgets items
+//! no longer there, then reads the `$I` file of each new item for the path
+//! it was deleted from, so results can show it.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+
+use crate::db::{prune_recycled_items, store_recycled_items, unread_recycle_info, Database, RecycledItem};
+use crate::Result;
+
+/// `$I` files read between writes and checks for shutdown.
+const BATCH_SIZE: usize = 256;
+
+/// Largest `$I` file read: the header and a path of the longest length
+/// Windows supports.
+const MAX_INFO_SIZE: u64 = 28 + 2 * 32_768;
+
+/// Seconds between the FILETIME epoch (1601) and the Unix epoch.
+const FILETIME_UNIX_OFFSET: i64 = 11_644_473_600;
+
+/// Read the original paths of items put in the Recycle Bin since the last
+/// pass.
+///
+/// # Arguments
+/// * `db` - The running worker's connection
+/// * `shutdown_rx` - Shutdown channel of the running worker
+pub fn index_recycle_bin(db: &mut Database, shutdown_rx: &Receiver<()>) {
+    match recycle_bin_pass(db, read_info, shutdown_rx) {
+        Ok(0) => {}
+        Ok(read) => tracing::debug!("Read the original paths of {} recycled items", read),
+        Err(e) => tracing::error!("Reading the Recycle Bin failed: {}", e),
+    }
+}
+
+/// Run one pass over the Recycle Bins of the online volumes.
+///
+/// # Arguments
+/// * `db` - Database connection
+/// * `read` - Reads a `$I` file; None if it can't be read
+/// * `shutdown_rx` - Stops the pass between batches
+///
+/// # Returns
+/// The number of `$I` files read.
+pub(crate) fn recycle_bin_pass(
+    db: &mut Database,
+    mut read: impl FnMut(&Path) -> Option<Vec<u8>>,
+    shutdown_rx: &Receiver<()>,
+) -> Result<usize> {
+    prune_recycled_items(db.conn())?;
+
+    let mut read_count = 0;
+    loop {
+        if shutdown_rx.try_recv().is_ok() {
+            break;
+        }
+        let unread = unread_recycle_info(db.conn(), BATCH_SIZE)?;
+        if unread.is_empty() {
+            break;
+        }
+
+        // Unreadable files are stored without a path, so they are not read
+        // again until replaced
+        let items: Vec<RecycledItem> = unread
+            .into_iter()
+            .map(|info| {
+                let parsed = read(Path::new(&info.path)).and_then(|bytes| parse_info(&bytes));
+                if parsed.is_some() {
+                    read_count += 1;
+                }
+                let (original_path, deleted_at) = parsed.unwrap_or_default();
+                RecycledItem {
+                    volume_id: info.volume_id,
+                    item_ref: info.item_ref,
+                    info_ref: info.info_ref,
+                    original_path,
+                    deleted_at,
+                }
+            })
+            .collect();
+        store_recycled_items(db.conn_mut(), &items)?;
+    }
+    Ok(read_count)
+}
+
+/// Read a `$I` file.
+fn read_info(path: &Path) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    File::open(path).ok()?.take(MAX_INFO_SIZE).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Parse a `$I` file: a version, the item's size and deletion time, then
+/// its original path in UTF-16 (a fixed 260 characters in version 1, and
+/// after a character count in version 2, used since Windows 10).
+///
+/// # Returns
+/// The original path and deletion time as Unix timestamp, or None if the
+/// file is not a `$I` file.
+fn parse_info(bytes: &[u8]) -> Option<(String, Option<i64>)> {
+    let field = |at: usize| bytes.get(at..at + 8).map(|b| i64::from_le_bytes(b.try_into().unwrap()));
+    let version = field(0)?;
+    let filetime = field(16)?;
+    let path = match version {
+        1 => bytes.get(24..)?,
+        2 => {
+            let chars = u32::from_le_bytes(bytes.get(24..28)?.try_into().unwrap()) as usize;
+            bytes.get(28..28 + 2 * chars)?
+        }
+        _ => return None,
+    };
+
+    let units: Vec<u16> = path
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    if units.is_empty() {
+        return None;
+    }
+    let deleted_at = (filetime > 0).then(|| filetime / 10_000_000 - FILETIME_UNIX_OFFSET);
+    Some((String::from_utf16_lossy(&units), deleted_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{batch_insert_files, insert_volume, open_database, recycled_original_path, FileEntry};
+    use std::sync::mpsc;
+
+    /// A `$I` file of the given version.
+    fn info(version: i64, path: &str, deleted_at: i64) -> Vec<u8> {
+        let filetime = (deleted_at + FILETIME_UNIX_OFFSET) * 10_000_000;
+        let mut bytes = [version, 1234, filetime].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        let mut units: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+        if version == 1 {
+            units.resize(260, 0);
+        } else {
+            bytes.extend((units.len() as u32).to_le_bytes());
+        }
+        bytes.extend(units.iter().flat_map(|u| u.to_le_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let expected = Some((r"C:\Users\me\Documents\notes.txt".to_string(), Some(1_700_000_000)));
+        assert_eq!(parse_info(&info(1, r"C:\Users\me\Documents\notes.txt", 1_700_000_000)), expected);
+        assert_eq!(parse_info(&info(2, r"C:\Users\me\Documents\notes.txt", 1_700_000_000)), expected);
+
+        assert_eq!(parse_info(&info(3, r"C:\notes.txt", 1_700_000_000)), None);
+        assert_eq!(parse_info(&info(2, "", 1_700_000_000)), None);
+        let truncated = info(2, r"C:\notes.txt", 1_700_000_000);
+        assert_eq!(parse_info(&truncated[..30]), None);
+    }
+
+    #[test]
+    fn test_recycle_bin_pass() {
+        let dir = std::env::temp_dir().join("ffi_test_recycle_bin");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let entry = |file_ref: i64, parent_ref: i64, name: &str| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            is_dir: file_ref < 20,
+            ..Default::default()
+        };
+        batch_insert_files(
+            db.conn_mut(),
+            &[
+                entry(5, 5, "."),
+                entry(10, 5, "$Recycle.Bin"),
+                entry(11, 10, "S-1-5-21-1000"),
+                entry(20, 11, "$IAB12CD.txt"),
+                entry(21, 11, "$RAB12CD.txt"),
+                entry(30, 11, "$IXY34ZW.log"),
+                entry(31, 11, "$RXY34ZW.log"),
+            ],
+        )
+        .unwrap();
+
+        let (_shutdown_tx, shutdown_rx) = mpsc::channel();
+        let mut read_paths = Vec::new();
+        let read = recycle_bin_pass(
+            &mut db,
+            |path| {
+                let path = path.to_string_lossy().to_string();
+                let read = path.ends_with("$IAB12CD.txt").then(|| info(2, r"C:\Users\me\notes.txt", 1_700_000_000));
+                read_paths.push(path);
+                read
+            },
+            &shutdown_rx,
+        )
+        .unwrap();
+        assert_eq!(read, 1);
+        assert_eq!(read_paths.len(), 2);
+
+        let original = |path: &str| recycled_original_path(db.conn(), volume_id, path).unwrap();
+        assert_eq!(original(r"$Recycle.Bin\S-1-5-21-1000\$RAB12CD.txt").as_deref(), Some(r"C:\Users\me\notes.txt"));
+        assert_eq!(original(r"$Recycle.Bin\S-1-5-21-1000\$RXY34ZW.log"), None);
+
+        // Neither is read again
+        assert_eq!(recycle_bin_pass(&mut db, |_| panic!("read again"), &shutdown_rx).unwrap(), 0);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
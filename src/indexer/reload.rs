@@ -0,0 +1,136 @@
+//! Bringing running indexing in line with a reloaded configuration.
+//!
+//! When the service's config watcher replaces the shared configuration,
+//! jobs, reconciliation passes and volume events pick it up by themselves.
+//! The rest is applied here: volumes newly enabled are recorded and
+//! indexed, volumes newly disabled stop being monitored and go offline
+//! (keeping their index for the retention period), and running USN
+//! monitors restart with new polling, throttling or excludes.
+
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use rusqlite::Connection;
+
+use super::jobs::{submit_job, JobKind};
+use super::{detect_volumes, handle_volume_mount, UsnMonitors, VolumeType};
+use crate::db::{current_writer, get_all_volumes, get_volume_state, open_database, update_volume_state};
+use crate::service::config::{volume_drive_letter, Config, VolumeName};
+use crate::service::ConfigChanges;
+use crate::{Result, VolumeState};
+
+/// Apply what changed in the configuration to the running indexing.
+///
+/// # Arguments
+/// * `config` - The configuration just loaded
+/// * `changes` - What changed from the previous one
+/// * `monitors` - USN monitors of the job pool
+/// * `db_path` - Path to the database
+pub fn apply_config_changes(config: &Config, changes: &ConfigChanges, monitors: &Mutex<UsnMonitors>, db_path: &Path) {
+    if changes.is_empty() {
+        return;
+    }
+    let db = match open_database(db_path) {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("Failed to open database to apply configuration changes: {}", e);
+            return;
+        }
+    };
+    let mut monitors = monitors.lock().unwrap_or_else(PoisonError::into_inner);
+
+    // Detach volumes turned off
+    for key in &changes.disabled {
+        if let Some(drive_letter) = volume_drive_letter(key) {
+            monitors.stop(drive_letter);
+        }
+    }
+    match detach_volumes(db.conn(), &changes.disabled) {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Detached {} disabled volumes; their index is kept while offline", count),
+        Err(e) => tracing::error!("Failed to detach disabled volumes: {}", e),
+    }
+
+    // Restart running monitors with their new settings, from the position
+    // each saved
+    if changes.monitors {
+        match current_writer() {
+            Some(writer) => {
+                for drive_letter in monitors.monitored() {
+                    monitors.stop(drive_letter);
+                    monitors.resume(drive_letter, &db, &writer, config);
+                }
+            }
+            None => tracing::debug!("No database writer running, not restarting USN monitors"),
+        }
+    }
+
+    // Index volumes turned on. NTFS volumes are rescanned, after which they
+    // are monitored; FAT volumes are picked up by the reconciler
+    let detected = detect_volumes();
+    for key in &changes.enabled {
+        let Some(volume) = detected.iter().find(|v| v.mount_point.as_str().volume_key() == *key) else {
+            tracing::info!("Volume {} enabled; it is indexed once mounted", key);
+            continue;
+        };
+        let Some(drive_letter) = volume.drive_letter else {
+            tracing::info!("Volume {} enabled; it is indexed on the next full index", key);
+            continue;
+        };
+        if let Err(e) = handle_volume_mount(drive_letter, config, db_path) {
+            tracing::error!("Failed to record enabled volume {}: {}", drive_letter, e);
+            continue;
+        }
+        if volume.fs_type == VolumeType::NTFS && !monitors.is_monitoring(drive_letter) {
+            submit_job(JobKind::UserRescan(drive_letter));
+        }
+    }
+}
+
+/// Mark the online volumes with the given keys offline, as if unmounted.
+///
+/// # Arguments
+/// * `conn` - Database connection
+/// * `keys` - Normalized volume keys (`D:`)
+///
+/// # Returns
+/// The number of volumes marked offline.
+fn detach_volumes(conn: &Connection, keys: &[String]) -> Result<usize> {
+    let now = chrono::Utc::now().timestamp();
+    let mut detached = 0;
+    for volume in get_all_volumes(conn)? {
+        if !keys.contains(&volume.drive_letter.as_str().volume_key()) {
+            continue;
+        }
+        if matches!(get_volume_state(conn, volume.id)?, VolumeState::Offline { .. }) {
+            continue;
+        }
+        update_volume_state(conn, volume.id, VolumeState::Offline { since: now })?;
+        detached += 1;
+    }
+    Ok(detached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_volume, schema};
+
+    #[test]
+    fn test_detach_volumes() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let c = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let d = insert_volume(&conn, "D:", "5678", "NTFS").unwrap();
+        let mount = insert_volume(&conn, r"C:\Mount\Data", "9ABC", "NTFS").unwrap();
+
+        let keys = vec!["D:".to_string(), r"C:\MOUNT\DATA".to_string()];
+        assert_eq!(detach_volumes(&conn, &keys).unwrap(), 2);
+        assert_eq!(get_volume_state(&conn, c).unwrap(), VolumeState::Online);
+        assert!(matches!(get_volume_state(&conn, d).unwrap(), VolumeState::Offline { .. }));
+        assert!(matches!(get_volume_state(&conn, mount).unwrap(), VolumeState::Offline { .. }));
+
+        // Volumes already offline keep the time they went offline
+        assert_eq!(detach_volumes(&conn, &keys).unwrap(), 0);
+    }
+}
@@ -0,0 +1,235 @@
+//! Rescans of NTFS volumes whose USN journal was lost.
+//!
+//! When a journal wraps or is recreated, the monitor can no longer tell
+//! what changed, so it calls [`trigger_background_rescan`] and exits. The
+//! volume is marked `Rescanning` and a rescan job is queued to the job
+//! pool, which re-reads the MFT over the existing rows (updating them in
+//! place and removing entries that are gone, so searches keep working
+//! meanwhile), then starts a new monitor from the journal position taken
+//! before the scan. Changes made during the scan are replayed by that monitor.
+//!
+//! Clients can also request a rescan over IPC with [`request_rescan`]; the
+//! volume's running monitor, if any, is kept. A single stale folder is
+//! walked again on its own with [`request_path_rescan`] instead.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::{Mutex, PoisonError};
+
+use rusqlite::Connection;
+
+use super::jobs::{is_job_pool_running, submit_job, JobKind, JobQueue};
+use super::{rescan_subtree, scan_ntfs_volume, ScanLimits, UsnMonitor};
+use crate::db::{get_volume, update_volume_state, update_volume_usn, Database, DbWriter, WriteOp};
+use crate::service::config::Config;
+use crate::{Result, VolumeState};
+
+/// Trigger a background rescan of a volume whose USN journal was lost.
+///
+/// Called when the USN journal has wrapped or been recreated, meaning some
+/// file changes were missed. Marks the volume `Rescanning` and queues an
+/// urgent rescan job.
+///
+/// # Arguments
+/// * `conn` - Database connection used to update the volume state
+/// * `drive_letter` - The volume to rescan
+pub fn trigger_background_rescan(conn: &Connection, drive_letter: char) {
+    queue_rescan(conn, JobKind::JournalRescan(drive_letter));
+}
+
+/// Like [`trigger_background_rescan`], for a USN monitor, which writes
+/// through the database writer instead of its own connection.
+///
+/// # Arguments
+/// * `writer` - Database writer used to update the volume state
+/// * `volume_id` - ID of the monitored volume
+/// * `drive_letter` - The volume to rescan
+pub fn trigger_monitor_rescan(writer: &DbWriter, volume_id: i64, drive_letter: char) {
+    let state = WriteOp::SetVolumeState {
+        volume_id,
+        state: VolumeState::Rescanning,
+    };
+    if let Err(e) = writer.execute(state) {
+        tracing::warn!("Failed to mark volume {} as rescanning: {}", drive_letter, e);
+    }
+    submit_rescan(JobKind::JournalRescan(drive_letter), drive_letter);
+}
+
+/// Queue a rescan of a volume at a client's request.
+///
+/// Marks the volume `Rescanning` and queues a rescan job behind any
+/// volumes whose journal was lost.
+///
+/// # Arguments
+/// * `conn` - Database connection used to update the volume state
+/// * `drive_letter` - The volume to rescan
+pub fn request_rescan(conn: &Connection, drive_letter: char) {
+    queue_rescan(conn, JobKind::UserRescan(drive_letter));
+}
+
+/// Directories queued for [`JobKind::PathRescan`]: volume name and path
+/// relative to the volume root.
+static PENDING_PATHS: Mutex<VecDeque<(String, PathBuf)>> = Mutex::new(VecDeque::new());
+
+/// Queue a rescan of one directory of an indexed volume.
+///
+/// A directory already queued, or below one that is, is not queued again.
+///
+/// # Arguments
+/// * `volume_name` - The indexed volume, e.g. `D:` or a mount folder
+/// * `path` - The directory, relative to the volume root
+///
+/// # Returns
+/// false if no job pool is running to take it.
+pub fn request_path_rescan(volume_name: &str, path: &Path) -> bool {
+    if !is_job_pool_running() {
+        return false;
+    }
+    {
+        let mut pending = PENDING_PATHS.lock().unwrap_or_else(PoisonError::into_inner);
+        if !pending.iter().any(|(volume, queued)| volume == volume_name && path.starts_with(queued)) {
+            pending.retain(|(volume, queued)| volume != volume_name || !queued.starts_with(path));
+            pending.push_back((volume_name.to_string(), path.to_path_buf()));
+        }
+    }
+    if !submit_job(JobKind::PathRescan) {
+        return false;
+    }
+    tracing::info!("Rescan of {} on {} queued", path.display(), volume_name);
+    true
+}
+
+/// Rescan the directories queued with [`request_path_rescan`], in order.
+///
+/// # Arguments
+/// * `db` - The running worker's connection
+/// * `config` - Service configuration, for excludes
+/// * `shutdown_rx` - Shutdown channel of the running worker
+pub(crate) fn rescan_pending_paths(db: &mut Database, config: &Config, shutdown_rx: &Receiver<()>) {
+    loop {
+        let next = PENDING_PATHS.lock().unwrap_or_else(PoisonError::into_inner).pop_front();
+        let Some((volume_name, path)) = next else {
+            return;
+        };
+        let exclude = config.exclude_matcher(volume_name.as_str());
+        if let Err(e) = rescan_subtree(db, &volume_name, &path, &exclude, shutdown_rx) {
+            tracing::error!("Rescan of {} on {} failed: {}", path.display(), volume_name, e);
+        }
+        if shutdown_rx.try_recv().is_ok() {
+            return;
+        }
+    }
+}
+
+/// Mark the job's volume `Rescanning` and submit the job.
+fn queue_rescan(conn: &Connection, job: JobKind) {
+    let Some(drive_letter) = job.volume() else {
+        return;
+    };
+
+    match get_volume(conn, &format!("{}:", drive_letter)) {
+        Ok(Some(vol)) => {
+            if let Err(e) = update_volume_state(conn, vol.id, VolumeState::Rescanning) {
+                tracing::warn!("Failed to mark volume {} as rescanning: {}", drive_letter, e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to get volume {}: {}", drive_letter, e),
+    }
+
+    submit_rescan(job, drive_letter);
+}
+
+/// Submit a rescan job, logging whether a job pool took it.
+fn submit_rescan(job: JobKind, drive_letter: char) {
+    if submit_job(job) {
+        tracing::info!("Background rescan queued for volume {}", drive_letter);
+    } else {
+        tracing::warn!(
+            "No job pool running, volume {} will be rescanned on the next full index",
+            drive_letter
+        );
+    }
+}
+
+/// Rescan one volume in place and bring it back online.
+///
+/// # Arguments
+/// * `drive_letter` - The volume to rescan
+/// * `db` - The running worker's connection
+/// * `config` - Service configuration for excludes, MFT scan workers and scan limits
+/// * `shutdown_rx` - Shutdown channel of the running worker
+/// * `queue` - The pool's queue; once closed, the scan counts as interrupted
+///
+/// # Returns
+/// The journal position to resume monitoring from, or `None` if the volume
+/// is not indexed, the scan was interrupted or the journal is unavailable.
+pub(crate) fn rescan_volume(
+    drive_letter: char,
+    db: &mut Database,
+    config: &Config,
+    shutdown_rx: &Receiver<()>,
+    queue: &JobQueue,
+) -> Result<Option<(i64, u64)>> {
+    let Some(vol) = get_volume(db.conn(), &format!("{}:", drive_letter))? else {
+        tracing::info!("Volume {} is no longer indexed, skipping rescan", drive_letter);
+        return Ok(None);
+    };
+
+    // Taken before scanning so changes made during the scan are replayed
+    let journal = UsnMonitor::new(drive_letter).and_then(|m| m.next_usn().map(|usn| (usn, m.journal_id())));
+
+    tracing::info!("Background rescan of volume {} started", drive_letter);
+    let count = scan_ntfs_volume(
+        drive_letter,
+        db,
+        &config.exclude_matcher(drive_letter),
+        config.general.mft_scan_workers,
+        config.general.index_alternate_streams,
+        ScanLimits::for_volume(config, drive_letter),
+        shutdown_rx,
+    )?;
+
+    if queue.is_closed() {
+        tracing::info!("Background rescan of volume {} interrupted", drive_letter);
+        return Ok(None);
+    }
+
+    update_volume_state(db.conn(), vol.id, VolumeState::Online)?;
+    tracing::info!("Background rescan of volume {} complete: {} files", drive_letter, count);
+
+    match journal {
+        Ok((next_usn, journal_id)) => {
+            update_volume_usn(db.conn(), vol.id, next_usn, journal_id as i64)?;
+            Ok(Some((next_usn, journal_id)))
+        }
+        Err(e) => {
+            tracing::warn!("USN journal unavailable on {}, not monitoring: {}", drive_letter, e);
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{get_volume_state, insert_volume, schema};
+
+    #[test]
+    fn test_trigger_marks_volume_rescanning() {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let volume_id = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+
+        trigger_background_rescan(&conn, 'C');
+        assert_eq!(get_volume_state(&conn, volume_id).unwrap(), VolumeState::Rescanning);
+
+        update_volume_state(&conn, volume_id, VolumeState::Online).unwrap();
+        request_rescan(&conn, 'C');
+        assert_eq!(get_volume_state(&conn, volume_id).unwrap(), VolumeState::Rescanning);
+
+        // Unknown volumes are ignored
+        trigger_background_rescan(&conn, 'Z');
+    }
+}
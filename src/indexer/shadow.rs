@@ -0,0 +1,322 @@
+//! VSS shadow copy (previous versions) indexing.
+//!
+//! Opt-in via `[shadow_copies]` in config. Selected shadow copies are walked
+//! like FAT volumes and stored as read-only virtual volumes named after the
+//! source drive and snapshot date (e.g. `C:@2024-05-01`), so results from a
+//! snapshot are clearly labeled and searches can answer "did this file exist
+//! last week". Virtual volumes are removed once their shadow copy is gone.
+
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
+
+use chrono::{Local, TimeZone};
+use serde::Deserialize;
+
+use crate::db::{
+    delete_volume, get_all_volumes, get_volume, get_volume_state, update_volume_state, Database,
+};
+use crate::service::config::{Config, ShadowCopyConfig};
+use crate::{FFIError, Result, VolumeState};
+
+use super::fat::scan_directory_tree;
+use super::throttle::ScanLimits;
+
+/// A VSS shadow copy of a volume.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowCopy {
+    /// Device path (e.g., `\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopy3`)
+    pub device: String,
+    /// Drive letter of the original volume
+    pub drive_letter: char,
+    /// Unix timestamp when the shadow copy was created
+    pub created: i64,
+}
+
+/// Shadow copy as reported by the enumeration script.
+#[derive(Deserialize)]
+struct RawShadowCopy {
+    device: String,
+    drive: Option<String>,
+    created: i64,
+}
+
+/// Parse the JSON array produced by the shadow copy enumeration script.
+///
+/// Shadow copies of volumes without a drive letter are ignored.
+pub fn parse_shadow_copies(json: &str) -> Result<Vec<ShadowCopy>> {
+    let raw: Vec<RawShadowCopy> = serde_json::from_str(json)
+        .map_err(|e| FFIError::Indexer(format!("Failed to parse shadow copy list: {}", e)))?;
+
+    Ok(raw
+        .into_iter()
+        .filter_map(|s| {
+            let drive_letter = s.drive?.chars().next()?.to_ascii_uppercase();
+            Some(ShadowCopy {
+                device: s.device,
+                drive_letter,
+                created: s.created,
+            })
+        })
+        .collect())
+}
+
+/// Enumerate the shadow copies available on this machine.
+#[cfg(windows)]
+pub fn list_shadow_copies() -> Result<Vec<ShadowCopy>> {
+    const SCRIPT: &str = "$ErrorActionPreference = 'Stop'; \
+        $out = @(foreach ($s in Get-CimInstance Win32_ShadowCopy) { \
+            $v = Get-CimInstance Win32_Volume | Where-Object { $_.DeviceID -eq $s.VolumeName }; \
+            [pscustomobject]@{ device = $s.DeviceObject; drive = $v.DriveLetter; \
+                created = [DateTimeOffset]::new($s.InstallDate).ToUnixTimeSeconds() } \
+        }); \
+        ConvertTo-Json -Compress -InputObject $out";
+
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .output()
+        .map_err(|e| FFIError::Indexer(format!("Failed to enumerate shadow copies: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(FFIError::Indexer(format!(
+            "Failed to enumerate shadow copies: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    parse_shadow_copies(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Stub for non-Windows platforms (no VSS).
+#[cfg(not(windows))]
+pub fn list_shadow_copies() -> Result<Vec<ShadowCopy>> {
+    Ok(Vec::new())
+}
+
+/// Pick the shadow copies to index and name their virtual volumes.
+///
+/// Keeps the newest `max_per_volume` copies of each configured drive (all
+/// drives if none are configured). Names use the local creation date, with
+/// the time added when a drive has several copies on the same day.
+pub fn select_shadow_copies(shadows: &[ShadowCopy], config: &ShadowCopyConfig) -> Vec<(String, ShadowCopy)> {
+    let mut by_drive: HashMap<char, Vec<&ShadowCopy>> = HashMap::new();
+    for shadow in shadows {
+        let drive = shadow.drive_letter.to_string();
+        let wanted = config.volumes.is_empty()
+            || config
+                .volumes
+                .iter()
+                .any(|v| v.trim_end_matches(':').eq_ignore_ascii_case(&drive));
+        if wanted {
+            by_drive.entry(shadow.drive_letter).or_default().push(shadow);
+        }
+    }
+
+    let mut selected = Vec::new();
+    for (drive_letter, mut copies) in by_drive {
+        copies.sort_by_key(|s| std::cmp::Reverse(s.created));
+        copies.truncate(config.max_per_volume);
+
+        let local = |s: &ShadowCopy| Local.timestamp_opt(s.created, 0).single();
+        for shadow in &copies {
+            let Some(created) = local(shadow) else {
+                continue;
+            };
+            let date = created.format("%Y-%m-%d").to_string();
+            let same_day = copies
+                .iter()
+                .filter(|other| local(other).map(|d| d.format("%Y-%m-%d").to_string()) == Some(date.clone()))
+                .count();
+
+            let name = if same_day > 1 {
+                format!("{}:@{}", drive_letter, created.format("%Y-%m-%d %H:%M"))
+            } else {
+                format!("{}:@{}", drive_letter, date)
+            };
+            selected.push((name, (*shadow).clone()));
+        }
+    }
+
+    selected.sort_by(|a, b| a.0.cmp(&b.0));
+    selected
+}
+
+/// Index configured shadow copies and drop virtual volumes whose copy is gone.
+///
+/// Shadow copies are immutable, so a copy already indexed is not scanned again.
+/// Each is walked within the scan limits of its source volume; a walk stopped
+/// by shutdown resumes from its checkpoint on the next run.
+///
+/// # Arguments
+/// * `db` - Database instance for persisting indexed files
+/// * `config` - Service configuration for `[shadow_copies]`, excludes and scan limits
+/// * `shutdown_rx` - Channel receiver for shutdown signals
+///
+/// # Returns
+/// The number of files indexed from newly added shadow copies.
+pub fn index_shadow_copies(db: &mut Database, config: &Config, shutdown_rx: &Receiver<()>) -> Result<usize> {
+    let selected = select_shadow_copies(&list_shadow_copies()?, &config.shadow_copies);
+    tracing::info!("{} shadow copies selected for indexing", selected.len());
+
+    // Remove virtual volumes for shadow copies that were deleted or deselected
+    for volume in get_all_volumes(db.conn())? {
+        let is_shadow = get_volume_state(db.conn(), volume.id)? == VolumeState::Shadow;
+        if is_shadow && !selected.iter().any(|(name, _)| *name == volume.drive_letter) {
+            tracing::info!("Removing index of shadow copy {}", volume.drive_letter);
+            delete_volume(db.conn_mut(), volume.id)?;
+        }
+    }
+
+    let mut total_indexed = 0;
+    for (name, shadow) in selected {
+        if shutdown_rx.try_recv().is_ok() {
+            tracing::info!("Shutdown signal received during shadow copy indexing");
+            break;
+        }
+
+        let root = format!("{}\\", shadow.device);
+        match index_shadow_copy(db, config, &name, &root, shadow.drive_letter, shutdown_rx) {
+            Ok(count) => total_indexed += count,
+            Err(e) => tracing::error!("Failed to index shadow copy {}: {}", name, e),
+        }
+    }
+
+    Ok(total_indexed)
+}
+
+/// Walk one shadow copy into its virtual volume unless it is fully indexed.
+///
+/// The volume is only marked [`VolumeState::Shadow`] once the walk completes,
+/// so a copy whose walk was interrupted is picked up again.
+fn index_shadow_copy(
+    db: &mut Database,
+    config: &Config,
+    name: &str,
+    root: &str,
+    drive_letter: char,
+    shutdown_rx: &Receiver<()>,
+) -> Result<usize> {
+    if let Some(existing) = get_volume(db.conn(), name)? {
+        if get_volume_state(db.conn(), existing.id)? == VolumeState::Shadow {
+            return Ok(0);
+        }
+    }
+
+    tracing::info!("Indexing shadow copy {} from {}", name, root);
+    let limits = ScanLimits::for_volume(config, drive_letter);
+    let exclude = config.exclude_matcher(drive_letter);
+    let (count, complete) = scan_directory_tree(root, name, "VSS", db, &exclude, limits, shutdown_rx)?;
+    if complete {
+        if let Some(volume) = get_volume(db.conn(), name)? {
+            update_volume_state(db.conn(), volume.id, VolumeState::Shadow)?;
+        }
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shadow(drive_letter: char, n: u32, created: i64) -> ShadowCopy {
+        ShadowCopy {
+            device: format!(r"\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopy{}", n),
+            drive_letter,
+            created,
+        }
+    }
+
+    fn config(volumes: &[&str], max_per_volume: usize) -> ShadowCopyConfig {
+        ShadowCopyConfig {
+            enabled: true,
+            volumes: volumes.iter().map(|v| v.to_string()).collect(),
+            max_per_volume,
+        }
+    }
+
+    #[test]
+    fn test_parse_shadow_copies() {
+        let json = r#"[
+            {"device":"\\\\?\\GLOBALROOT\\Device\\HarddiskVolumeShadowCopy1","drive":"C:","created":1714550400},
+            {"device":"\\\\?\\GLOBALROOT\\Device\\HarddiskVolumeShadowCopy2","drive":null,"created":1714550400}
+        ]"#;
+
+        let shadows = parse_shadow_copies(json).unwrap();
+        assert_eq!(shadows, vec![shadow('C', 1, 1714550400)]);
+        assert!(parse_shadow_copies("[]").unwrap().is_empty());
+        assert!(parse_shadow_copies("not json").is_err());
+    }
+
+    #[test]
+    fn test_select_newest_per_volume() {
+        let day = 86400;
+        let base = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap().timestamp();
+        let shadows = vec![
+            shadow('C', 1, base),
+            shadow('C', 2, base + day),
+            shadow('C', 3, base + 2 * day),
+            shadow('D', 4, base),
+        ];
+
+        let names: Vec<String> = select_shadow_copies(&shadows, &config(&["C"], 2))
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["C:@2024-05-02", "C:@2024-05-03"]);
+
+        // No volumes configured means all drives
+        assert_eq!(select_shadow_copies(&shadows, &config(&[], 1)).len(), 2);
+    }
+
+    #[test]
+    fn test_select_same_day_adds_time() {
+        let base = Local.with_ymd_and_hms(2024, 5, 1, 9, 30, 0).unwrap().timestamp();
+        let shadows = vec![shadow('C', 1, base), shadow('C', 2, base + 3600)];
+
+        let names: Vec<String> = select_shadow_copies(&shadows, &config(&["c:"], 5))
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["C:@2024-05-01 09:30", "C:@2024-05-01 10:30"]);
+    }
+
+    #[test]
+    fn test_interrupted_shadow_copy_is_resumed() {
+        let dir = std::env::temp_dir().join("ffi_test_shadow_resume");
+        let _ = std::fs::remove_dir_all(&dir);
+        let root = dir.join("root");
+        std::fs::create_dir_all(&root).unwrap();
+        // Enough entries for the walk to check for shutdown part way through
+        for i in 0..10_050 {
+            std::fs::write(root.join(format!("{:05}.txt", i)), b"").unwrap();
+        }
+        let root_path = root.to_string_lossy().to_string();
+        let name = "C:@2024-05-01";
+
+        let mut db = crate::db::open_database(&dir.join("index.db")).unwrap();
+        let config = Config::default();
+        let state = |db: &Database| {
+            let volume = get_volume(db.conn(), name).unwrap().unwrap();
+            get_volume_state(db.conn(), volume.id).unwrap()
+        };
+
+        // Shutdown during the walk leaves the copy unmarked
+        let (tx, shutdown_rx) = std::sync::mpsc::channel();
+        tx.send(()).unwrap();
+        let indexed = index_shadow_copy(&mut db, &config, name, &root_path, 'C', &shutdown_rx).unwrap();
+        assert!(indexed > 0 && indexed < 10_050);
+        assert_ne!(state(&db), VolumeState::Shadow);
+
+        // The next run resumes the walk and marks the copy
+        let (_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let resumed = index_shadow_copy(&mut db, &config, name, &root_path, 'C', &shutdown_rx).unwrap();
+        assert_eq!(indexed + resumed, 10_050);
+        assert_eq!(state(&db), VolumeState::Shadow);
+
+        // A fully indexed copy is not walked again
+        assert_eq!(index_shadow_copy(&mut db, &config, name, &root_path, 'C', &shutdown_rx).unwrap(), 0);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
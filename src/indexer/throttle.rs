@@ -0,0 +1,366 @@
+//! Throttling of full scans.
+//!
+//! An initial index reads the whole MFT or walks every directory of a
+//! volume, competing with the user's own work for the disk. Scanner threads
+//! therefore run with background I/O priority, so Windows serves other
+//! programs' reads first, and pace themselves with a [`ScanThrottle`]:
+//! reads are capped at `[general] max_scan_iops`, and the scan sleeps
+//! between chunks while the volume has other requests queued or the CPU is
+//! busy (the volume's `throttle_cpu_percent`, as for USN polling).
+
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::service::config::{Config, VolumeName};
+
+/// How often the disk queue and CPU load are sampled.
+const LOAD_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Requests queued on the volume, besides the scan's own reads, at which
+/// the disk counts as busy.
+const BUSY_QUEUE_DEPTH: u32 = 2;
+
+/// Sleep between chunks while the disk or CPU is busy.
+const BUSY_BACKOFF: Duration = Duration::from_millis(250);
+
+/// How hard a full scan may load the machine.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScanLimits {
+    /// Disk reads per second, 0 for no limit
+    pub max_iops: u32,
+    /// CPU usage, in percent, above which the scan backs off; `None` never
+    /// backs off for CPU load
+    pub cpu_percent: Option<f32>,
+}
+
+impl ScanLimits {
+    /// Limits for scanning a volume: `[general] max_scan_iops` and the
+    /// volume's CPU threshold.
+    pub fn for_volume(config: &Config, volume: impl VolumeName) -> Self {
+        Self {
+            max_iops: config.general.max_scan_iops,
+            cpu_percent: Some(config.throttle_cpu_percent(volume)),
+        }
+    }
+}
+
+/// Paces the reads of one full scan.
+pub struct ScanThrottle {
+    limits: ScanLimits,
+    rate: ReadSchedule,
+    load: LoadProbe,
+    /// Threads reading the volume for this scan, whose own requests are
+    /// in the disk queue
+    readers: u32,
+    next_sample: Instant,
+    busy: bool,
+}
+
+impl ScanThrottle {
+    /// Create a throttle for a scan of the volume at `root_path`.
+    ///
+    /// # Arguments
+    /// * `root_path` - Root of the volume, `C:\` or its `\\?\Volume{...}\` path
+    /// * `limits` - Read rate and CPU limits
+    /// * `readers` - Threads reading the volume for the scan
+    pub fn new(root_path: &str, limits: ScanLimits, readers: usize) -> Self {
+        Self {
+            limits,
+            rate: ReadSchedule::new(limits.max_iops, Instant::now()),
+            load: LoadProbe::open(root_path),
+            readers: readers as u32,
+            next_sample: Instant::now(),
+            busy: false,
+        }
+    }
+
+    /// Count `reads` disk reads, sleeping as long as the limits ask.
+    pub fn pace(&mut self, reads: u64) {
+        if let Some(delay) = self.delay(reads) {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Like [`pace`](Self::pace), but wakes up for shutdown.
+    ///
+    /// # Returns
+    /// true if shutdown was signalled while sleeping (the signal is
+    /// consumed), false once the scan may continue.
+    pub fn pace_or_shutdown(&mut self, reads: u64, shutdown_rx: &Receiver<()>) -> bool {
+        match self.delay(reads) {
+            Some(delay) => !matches!(shutdown_rx.recv_timeout(delay), Err(RecvTimeoutError::Timeout)),
+            None => false,
+        }
+    }
+
+    /// Time to sleep before reading on, after `reads` more reads.
+    fn delay(&mut self, reads: u64) -> Option<Duration> {
+        let now = Instant::now();
+        let rate_delay = self.rate.delay(reads, now).unwrap_or_default();
+
+        if now >= self.next_sample {
+            let busy = is_busy(
+                self.load.queue_depth(),
+                self.readers,
+                self.limits.cpu_percent.map(|_| self.load.cpu_usage()),
+                self.limits.cpu_percent,
+            );
+            if busy != self.busy {
+                tracing::debug!("Scan {} for system load", if busy { "backing off" } else { "resuming full speed" });
+            }
+            self.busy = busy;
+            self.next_sample = now + LOAD_SAMPLE_INTERVAL;
+        }
+
+        let delay = rate_delay + if self.busy { BUSY_BACKOFF } else { Duration::ZERO };
+        (!delay.is_zero()).then_some(delay)
+    }
+}
+
+/// Whether the disk or the CPU is too busy to scan at full speed.
+///
+/// # Arguments
+/// * `queue_depth` - Requests queued on the volume, if known
+/// * `readers` - How many of those requests may be the scan's own
+/// * `cpu_usage` - Current CPU usage in percent, if sampled
+/// * `cpu_percent` - CPU usage above which the scan backs off
+fn is_busy(queue_depth: Option<u32>, readers: u32, cpu_usage: Option<f32>, cpu_percent: Option<f32>) -> bool {
+    let disk_busy = queue_depth.is_some_and(|depth| depth >= readers + BUSY_QUEUE_DEPTH);
+    let cpu_busy = cpu_usage.zip(cpu_percent).is_some_and(|(usage, limit)| usage > limit);
+    disk_busy || cpu_busy
+}
+
+/// Spreads reads evenly at a maximum rate.
+///
+/// Counts reads since the schedule started and asks callers that got ahead
+/// of `max_per_sec` to wait until it catches up. A scan that fell more than
+/// a second behind (paused, or slowed by the disk) starts a new schedule,
+/// so it doesn't make up for lost time with a burst.
+#[derive(Debug)]
+struct ReadSchedule {
+    max_per_sec: u32,
+    start: Instant,
+    reads: u64,
+}
+
+impl ReadSchedule {
+    fn new(max_per_sec: u32, now: Instant) -> Self {
+        Self {
+            max_per_sec,
+            start: now,
+            reads: 0,
+        }
+    }
+
+    /// Time the reads counted so far take at the maximum rate.
+    fn due(&self) -> Duration {
+        Duration::from_secs_f64(self.reads as f64 / self.max_per_sec as f64)
+    }
+
+    /// Count `reads` more reads at `now`, returning how long to wait
+    /// before reading on.
+    fn delay(&mut self, reads: u64, now: Instant) -> Option<Duration> {
+        if self.max_per_sec == 0 {
+            return None;
+        }
+
+        if now.duration_since(self.start) > self.due() + Duration::from_secs(1) {
+            self.start = now;
+            self.reads = 0;
+        }
+        self.reads += reads;
+        self.due()
+            .checked_sub(now.duration_since(self.start))
+            .filter(|delay| !delay.is_zero())
+    }
+}
+
+/// Background I/O and memory priority for the current thread while held.
+///
+/// Windows schedules the thread's disk requests behind everyone else's;
+/// a no-op elsewhere.
+pub(crate) struct BackgroundIo {
+    #[cfg(windows)]
+    entered: bool,
+}
+
+impl BackgroundIo {
+    /// Lower the priority of the current thread until the guard is dropped.
+    #[cfg(windows)]
+    pub(crate) fn enter() -> Self {
+        use windows::Win32::System::Threading::{GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN};
+
+        // Fails if the thread already is in background mode; leave that to
+        // whoever entered it
+        let entered = unsafe { SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN) }.is_ok();
+        Self { entered }
+    }
+
+    /// No-op on non-Windows platforms.
+    #[cfg(not(windows))]
+    pub(crate) fn enter() -> Self {
+        Self {}
+    }
+}
+
+#[cfg(windows)]
+impl Drop for BackgroundIo {
+    fn drop(&mut self) {
+        use windows::Win32::System::Threading::{GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_END};
+
+        if self.entered {
+            let _ = unsafe { SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_END) };
+        }
+    }
+}
+
+/// Samples the disk queue of a volume and the CPU load.
+struct LoadProbe {
+    system: sysinfo::System,
+    /// Handle to the volume device, if it could be opened
+    #[cfg(windows)]
+    volume: Option<windows::Win32::Foundation::HANDLE>,
+}
+
+impl LoadProbe {
+    /// Open the volume at `root_path` for queue depth queries.
+    #[cfg(windows)]
+    fn open(root_path: &str) -> Self {
+        use std::ffi::OsStr;
+        use std::os::windows::ffi::OsStrExt;
+        use windows::core::PCWSTR;
+        use windows::Win32::Storage::FileSystem::{
+            CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+        };
+
+        // `\\.\C:` for lettered volumes, else the volume path without its
+        // trailing backslash
+        let root = root_path.trim_end_matches('\\');
+        let device = match crate::service::config::volume_drive_letter(root) {
+            Some(letter) => format!("\\\\.\\{}:", letter),
+            None => root.to_string(),
+        };
+        let device_wide: Vec<u16> = OsStr::new(&device).encode_wide().chain(std::iter::once(0)).collect();
+
+        // No access rights are needed to query performance counters
+        let volume = unsafe {
+            CreateFileW(
+                PCWSTR::from_raw(device_wide.as_ptr()),
+                0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAGS_AND_ATTRIBUTES(0),
+                None,
+            )
+        };
+        if let Err(e) = &volume {
+            tracing::debug!("Cannot watch the disk queue of {}: {}", device, e);
+        }
+
+        Self {
+            system: sysinfo::System::new(),
+            volume: volume.ok(),
+        }
+    }
+
+    /// No disk queue to watch on non-Windows platforms.
+    #[cfg(not(windows))]
+    fn open(_root_path: &str) -> Self {
+        Self {
+            system: sysinfo::System::new(),
+        }
+    }
+
+    /// Requests currently queued on the volume.
+    #[cfg(windows)]
+    fn queue_depth(&self) -> Option<u32> {
+        use windows::Win32::System::Ioctl::{DISK_PERFORMANCE, IOCTL_DISK_PERFORMANCE};
+        use windows::Win32::System::IO::DeviceIoControl;
+
+        let volume = self.volume?;
+        let mut performance = DISK_PERFORMANCE::default();
+        let mut returned = 0u32;
+        unsafe {
+            DeviceIoControl(
+                volume,
+                IOCTL_DISK_PERFORMANCE,
+                None,
+                0,
+                Some(&mut performance as *mut DISK_PERFORMANCE as *mut std::ffi::c_void),
+                std::mem::size_of::<DISK_PERFORMANCE>() as u32,
+                Some(&mut returned),
+                None,
+            )
+        }
+        .ok()?;
+        Some(performance.QueueDepth)
+    }
+
+    /// Queue depth is unknown on non-Windows platforms.
+    #[cfg(not(windows))]
+    fn queue_depth(&self) -> Option<u32> {
+        None
+    }
+
+    /// CPU usage since the previous sample, in percent.
+    fn cpu_usage(&mut self) -> f32 {
+        self.system
+            .refresh_cpu_specifics(sysinfo::CpuRefreshKind::nothing().with_cpu_usage());
+        self.system.global_cpu_usage()
+    }
+}
+
+#[cfg(windows)]
+impl Drop for LoadProbe {
+    fn drop(&mut self) {
+        if let Some(volume) = self.volume {
+            unsafe {
+                let _ = windows::Win32::Foundation::CloseHandle(volume);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_schedule() {
+        let start = Instant::now();
+        let mut schedule = ReadSchedule::new(100, start);
+
+        // Reads ahead of the rate wait for it to catch up
+        assert_eq!(schedule.delay(50, start), Some(Duration::from_millis(500)));
+        assert_eq!(schedule.delay(50, start + Duration::from_millis(500)), Some(Duration::from_millis(500)));
+        assert_eq!(schedule.delay(10, start + Duration::from_millis(1100)), None);
+
+        // Time spent far behind is not made up with a burst
+        let later = start + Duration::from_secs(10);
+        assert_eq!(schedule.delay(10, later), Some(Duration::from_millis(100)));
+
+        // No limit
+        assert_eq!(ReadSchedule::new(0, start).delay(1_000_000, start), None);
+    }
+
+    #[test]
+    fn test_is_busy() {
+        // The scan's own reads don't count as load
+        assert!(!is_busy(Some(4), 4, None, None));
+        assert!(is_busy(Some(6), 4, None, None));
+        assert!(!is_busy(None, 1, Some(95.0), None));
+        assert!(is_busy(None, 1, Some(95.0), Some(80.0)));
+        assert!(!is_busy(Some(1), 1, Some(50.0), Some(80.0)));
+    }
+
+    #[test]
+    fn test_limits_from_config() {
+        let config: Config = toml::from_str(
+            "[general]\nmax_scan_iops = 400\n\n[volumes.D]\nclass = \"archive\"\n\n[classes.archive]\nthrottle_cpu_percent = 50\n",
+        )
+        .unwrap();
+        assert_eq!(ScanLimits::for_volume(&config, 'D'), ScanLimits { max_iops: 400, cpu_percent: Some(50.0) });
+        assert_eq!(ScanLimits::for_volume(&Config::default(), "C:").max_iops, 0);
+    }
+}
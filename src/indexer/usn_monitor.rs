@@ -4,14 +4,26 @@
 //! NTFS USN Change Journal. It handles:
 //! - Journal wrap detection (when old entries are overwritten)
 //! - Journal recreation detection (when journal ID changes)
-//! - Change deduplication (rapid changes to same file)
-//! - Batched database updates
+//! - Change deduplication (rapid changes to same file), pairing the old and
+//!   new name records of renames and moves
+//! - Batched database updates in bounded transactions, deferring Modify
+//!   records under heavy churn so names stay accurate first
+//! - Catch-up mode for large backlogs after service downtime
 
 use std::collections::HashMap;
-
-use crate::db::Database;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::db::{
+    file_extension, get_full_path, purge_excluded_entries, purge_excluded_paths, record_dir_churn, refresh_full_paths,
+    Database, DbWriter, FacetDeltas, WriteOp,
+};
+use crate::service::ExcludeMatcher;
 use crate::{FFIError, Result};
 
+use super::fat::file_attributes;
+
 /// Type of filesystem change detected.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChangeType {
@@ -21,6 +33,9 @@ pub enum ChangeType {
     Delete,
     /// File or directory was renamed/moved
     Rename,
+    /// Old name of a file or directory being renamed/moved;
+    /// [`deduplicate_changes`] pairs it with the `Rename` of its new name
+    RenameOld,
     /// File content or metadata was modified
     Modify,
 }
@@ -38,6 +53,168 @@ pub struct UsnChange {
     pub change_type: ChangeType,
     /// Whether this is a directory
     pub is_dir: bool,
+    /// Parent directory before a rename or move, once paired with its old name
+    pub old_parent_ref: Option<i64>,
+    /// Filename before a rename or move, once paired with its old name
+    pub old_name: Option<String>,
+}
+
+/// A record decoded from an `FSCTL_READ_USN_JOURNAL` output buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsnRecord {
+    /// USN of this record
+    pub usn: i64,
+    /// MFT record number (file reference without its sequence number)
+    pub file_ref: i64,
+    /// Parent directory MFT record number
+    pub parent_ref: i64,
+    /// `USN_REASON_*` flags
+    pub reason: u32,
+    /// `FILE_ATTRIBUTE_*` flags
+    pub file_attributes: u32,
+    /// Filename
+    pub name: String,
+}
+
+impl UsnRecord {
+    /// Whether the record describes a directory (`FILE_ATTRIBUTE_DIRECTORY`).
+    pub fn is_dir(&self) -> bool {
+        const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+        self.file_attributes & FILE_ATTRIBUTE_DIRECTORY != 0
+    }
+}
+
+/// Decode the output buffer of `FSCTL_READ_USN_JOURNAL`.
+///
+/// The buffer starts with the USN to continue reading from, followed by
+/// `USN_RECORD_V2` or `USN_RECORD_V3` records. V3 records carry 128-bit
+/// file IDs, of which the low 64 bits are the MFT reference on NTFS.
+/// References are reduced to their 48-bit record number, as the MFT scan
+/// stores them, so changes match the indexed rows. Other record versions
+/// are skipped and a truncated record ends decoding.
+///
+/// # Returns
+/// The next USN and the decoded records, or `None` if the buffer is too
+/// short to hold the next USN.
+pub fn parse_usn_buffer(buf: &[u8]) -> Option<(i64, Vec<UsnRecord>)> {
+    fn u16_at(buf: &[u8], at: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(buf.get(at..at + 2)?.try_into().ok()?))
+    }
+    fn u32_at(buf: &[u8], at: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(buf.get(at..at + 4)?.try_into().ok()?))
+    }
+    fn i64_at(buf: &[u8], at: usize) -> Option<i64> {
+        Some(i64::from_le_bytes(buf.get(at..at + 8)?.try_into().ok()?))
+    }
+    // The high 16 bits of a file reference are the sequence number
+    fn record_number_at(buf: &[u8], at: usize) -> Option<i64> {
+        Some(i64_at(buf, at)? & 0xFFFF_FFFF_FFFF)
+    }
+
+    let next_usn = i64_at(buf, 0)?;
+    let mut records = Vec::new();
+    let mut offset = 8;
+
+    while let Some(length) = u32_at(buf, offset) {
+        let length = length as usize;
+        let Some(record) = buf.get(offset..offset + length).filter(|_| length >= 8) else {
+            break;
+        };
+        offset += length;
+
+        // Field offsets of USN_RECORD_V2 and USN_RECORD_V3
+        let (file_ref, parent_ref, usn, reason, attributes, name_length, name_offset) =
+            match u16_at(record, 4) {
+                Some(2) => (8, 16, 24, 40, 52, 56, 58),
+                Some(3) => (8, 24, 40, 56, 68, 72, 74),
+                _ => continue,
+            };
+
+        let decoded = (|| {
+            let name_length = u16_at(record, name_length)? as usize;
+            let name_offset = u16_at(record, name_offset)? as usize;
+            let name_bytes = record.get(name_offset..name_offset + name_length)?;
+            let name: Vec<u16> = name_bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+
+            Some(UsnRecord {
+                usn: i64_at(record, usn)?,
+                file_ref: record_number_at(record, file_ref)?,
+                parent_ref: record_number_at(record, parent_ref)?,
+                reason: u32_at(record, reason)?,
+                file_attributes: u32_at(record, attributes)?,
+                name: String::from_utf16_lossy(&name),
+            })
+        })();
+
+        match decoded {
+            Some(record) => records.push(record),
+            None => break,
+        }
+    }
+
+    Some((next_usn, records))
+}
+
+/// Journal backlog, in bytes of USN records, above which startup switches
+/// to catch-up mode (about 150,000 changes).
+pub const CATCH_UP_THRESHOLD: i64 = 16 * 1024 * 1024;
+
+/// Changes read and applied per batch in catch-up mode.
+pub const CATCH_UP_BATCH: usize = 100_000;
+
+/// Most changes applied in one write transaction.
+pub const APPLY_CHUNK: usize = 10_000;
+
+/// Changes in one poll at or above which the monitor counts as behind and
+/// defers Modify records.
+pub const HIGH_WATER_MARK: usize = 50_000;
+
+/// Most Modify records held back; beyond this they are applied anyway.
+pub const MAX_DEFERRED: usize = 500_000;
+
+/// Progress through a journal backlog.
+///
+/// USNs are byte offsets into the journal, so the distance between the
+/// last processed USN and the journal's next USN measures the backlog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CatchUpProgress {
+    /// Last USN processed before catching up
+    start: i64,
+    /// Next USN of the journal when catching up started
+    target: i64,
+}
+
+impl CatchUpProgress {
+    /// Track progress from the last processed USN to the journal's next USN.
+    pub fn new(start: i64, target: i64) -> Self {
+        Self { start, target }
+    }
+
+    /// Backlog in bytes of USN records.
+    pub fn backlog(&self) -> i64 {
+        (self.target - self.start).max(0)
+    }
+
+    /// Whether the backlog is large enough for catch-up mode.
+    pub fn needs_catch_up(&self) -> bool {
+        self.backlog() >= CATCH_UP_THRESHOLD
+    }
+
+    /// Whether processing has reached the target USN.
+    pub fn is_done(&self, current: i64) -> bool {
+        current >= self.target
+    }
+
+    /// Percentage of the backlog processed once `current` is reached.
+    pub fn percent(&self, current: i64) -> f64 {
+        if self.backlog() == 0 {
+            return 100.0;
+        }
+        ((current - self.start) as f64 / self.backlog() as f64 * 100.0).clamp(0.0, 100.0)
+    }
 }
 
 /// Errors specific to USN Journal operations.
@@ -96,6 +273,58 @@ pub struct UsnMonitor {
     journal_id: u64,
 }
 
+/// Output buffer size for `FSCTL_READ_USN_JOURNAL` reads.
+#[cfg(windows)]
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Open handle to a volume device (`\\.\C:`), closed on drop.
+#[cfg(windows)]
+struct VolumeHandle(windows::Win32::Foundation::HANDLE);
+
+#[cfg(windows)]
+impl VolumeHandle {
+    fn open(drive_letter: char) -> std::result::Result<Self, UsnError> {
+        use std::ffi::OsStr;
+        use std::os::windows::ffi::OsStrExt;
+        use windows::Win32::Foundation::GENERIC_READ;
+        use windows::Win32::Storage::FileSystem::{
+            CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE,
+            OPEN_EXISTING,
+        };
+        use windows::core::PCWSTR;
+
+        let path = format!("\\\\.\\{}:", drive_letter);
+        let path_wide: Vec<u16> = OsStr::new(&path)
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+
+        let handle = unsafe {
+            CreateFileW(
+                PCWSTR::from_raw(path_wide.as_ptr()),
+                GENERIC_READ.0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAGS_AND_ATTRIBUTES(0),
+                None,
+            )
+        }
+        .map_err(|e| UsnError::Other(format!("Failed to open volume: {}", e)))?;
+
+        Ok(Self(handle))
+    }
+}
+
+#[cfg(windows)]
+impl Drop for VolumeHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = windows::Win32::Foundation::CloseHandle(self.0);
+        }
+    }
+}
+
 #[cfg(windows)]
 impl UsnMonitor {
     /// Create a new USN monitor for the specified drive.
@@ -195,10 +424,24 @@ impl UsnMonitor {
 
     /// Poll for changes since last_usn.
     ///
+    /// Reads the journal with `FSCTL_READ_USN_JOURNAL` starting at last_usn,
+    /// so the cost is proportional to the number of new changes.
     /// Returns a list of changes or an error if the journal has wrapped/recreated.
     pub fn poll_changes(&mut self) -> std::result::Result<Vec<UsnChange>, UsnError> {
+        self.poll_changes_up_to(usize::MAX)
+    }
+
+    /// Poll for at most `limit` changes since last_usn.
+    ///
+    /// `last_usn` only advances past the changes returned, so the next call
+    /// continues where this one stopped.
+    pub fn poll_changes_up_to(&mut self, limit: usize) -> std::result::Result<Vec<UsnChange>, UsnError> {
+        use std::ffi::c_void;
         use usn_journal_rs::volume::Volume;
         use usn_journal_rs::journal::UsnJournal;
+        use windows::Win32::Foundation::ERROR_JOURNAL_ENTRY_DELETED;
+        use windows::Win32::System::IO::DeviceIoControl;
+        use windows::Win32::System::Ioctl::{FSCTL_READ_USN_JOURNAL, READ_USN_JOURNAL_DATA_V0};
 
         let volume = Volume::from_drive_letter(self.volume)
             .map_err(|e| UsnError::Other(format!("Failed to open volume: {}", e)))?;
@@ -226,47 +469,76 @@ impl UsnMonitor {
 
         // Read changes from last_usn
         let mut changes = Vec::new();
-
-        // usn_journal_rs 0.4 API: iter() returns Result<UsnJournalIter>
-        // UsnJournalIter implements Iterator<Item = UsnRecord>
-        let iter = journal.iter()
-            .map_err(|e| UsnError::Other(format!("Failed to iterate journal: {}", e)))?;
+        let handle = VolumeHandle::open(self.volume)?;
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+        let mut read = READ_USN_JOURNAL_DATA_V0 {
+            StartUsn: self.last_usn,
+            ReasonMask: u32::MAX,
+            ReturnOnlyOnClose: 0,
+            Timeout: 0,
+            BytesToWaitFor: 0,
+            UsnJournalID: self.journal_id,
+        };
 
         let starting_usn = self.last_usn;
-        for result in iter {
-            let record = match result {
-                Ok(r) => r,
-                Err(e) => {
-                    tracing::warn!("Error reading USN record: {}", e);
-                    continue;
-                }
+        'read: loop {
+            let mut returned = 0u32;
+            let result = unsafe {
+                DeviceIoControl(
+                    handle.0,
+                    FSCTL_READ_USN_JOURNAL,
+                    Some(&read as *const READ_USN_JOURNAL_DATA_V0 as *const c_void),
+                    std::mem::size_of::<READ_USN_JOURNAL_DATA_V0>() as u32,
+                    Some(buffer.as_mut_ptr() as *mut c_void),
+                    buffer.len() as u32,
+                    Some(&mut returned),
+                    None,
+                )
             };
 
-            // Skip records older than our starting position
-            if record.usn <= starting_usn {
-                continue;
+            if let Err(e) = result {
+                // Records were overwritten between the metadata check and the read
+                if e.code() == ERROR_JOURNAL_ENTRY_DELETED.to_hresult() {
+                    return Err(UsnError::JournalWrapped {
+                        last_processed: self.last_usn,
+                        lowest_valid: metadata.lowest_valid_usn,
+                    });
+                }
+                return Err(UsnError::Other(format!("Failed to read journal: {}", e)));
             }
 
-            // Update our position
-            self.last_usn = record.usn;
-
-            // Convert USN reason flags to ChangeType
-            let change_type = Self::reason_to_change_type(record.reason);
+            let Some((next_usn, records)) = parse_usn_buffer(&buffer[..returned as usize]) else {
+                break;
+            };
+            if records.is_empty() || next_usn <= read.StartUsn {
+                break;
+            }
 
-            // Convert file_name from OsString to String
-            let name = record.file_name.to_string_lossy().into_owned();
+            for record in records {
+                // The read starts at last_usn, which was already processed
+                if record.usn <= starting_usn {
+                    continue;
+                }
 
-            // Note: usn_journal_rs 0.4 UsnEntry doesn't expose file_attrs
-            // We'll determine directory status during database lookup/update
-            let is_dir = false;
+                // Update our position
+                self.last_usn = record.usn;
+
+                changes.push(UsnChange {
+                    file_ref: record.file_ref,
+                    parent_ref: record.parent_ref,
+                    change_type: Self::reason_to_change_type(record.reason),
+                    is_dir: record.is_dir(),
+                    name: record.name,
+                    old_parent_ref: None,
+                    old_name: None,
+                });
+
+                if changes.len() >= limit {
+                    break 'read;
+                }
+            }
 
-            changes.push(UsnChange {
-                file_ref: record.fid as i64,
-                parent_ref: record.parent_fid as i64,
-                name,
-                change_type,
-                is_dir,
-            });
+            read.StartUsn = next_usn;
         }
 
         if !changes.is_empty() {
@@ -286,6 +558,20 @@ impl UsnMonitor {
         self.last_usn
     }
 
+    /// Get the USN the journal will assign to its next record.
+    pub fn next_usn(&self) -> std::result::Result<i64, UsnError> {
+        use usn_journal_rs::volume::Volume;
+        use usn_journal_rs::journal::UsnJournal;
+
+        let volume = Volume::from_drive_letter(self.volume)
+            .map_err(|e| UsnError::Other(format!("Failed to open volume: {}", e)))?;
+
+        let metadata = UsnJournal::new(&volume).query(false)
+            .map_err(|e| UsnError::Other(format!("Failed to query journal metadata: {}", e)))?;
+
+        Ok(metadata.next_usn)
+    }
+
     /// Get the journal ID for persistence.
     pub fn journal_id(&self) -> u64 {
         self.journal_id
@@ -311,8 +597,10 @@ impl UsnMonitor {
             ChangeType::Delete
         } else if reason & USN_REASON_FILE_CREATE != 0 {
             ChangeType::Create
-        } else if reason & (USN_REASON_RENAME_NEW_NAME | USN_REASON_RENAME_OLD_NAME) != 0 {
+        } else if reason & USN_REASON_RENAME_NEW_NAME != 0 {
             ChangeType::Rename
+        } else if reason & USN_REASON_RENAME_OLD_NAME != 0 {
+            ChangeType::RenameOld
         } else {
             ChangeType::Modify
         }
@@ -345,10 +633,18 @@ impl UsnMonitor {
         Err(UsnError::JournalNotActive)
     }
 
+    pub fn poll_changes_up_to(&mut self, _limit: usize) -> std::result::Result<Vec<UsnChange>, UsnError> {
+        Err(UsnError::JournalNotActive)
+    }
+
     pub fn last_usn(&self) -> i64 {
         self.last_usn
     }
 
+    pub fn next_usn(&self) -> std::result::Result<i64, UsnError> {
+        Err(UsnError::JournalNotActive)
+    }
+
     pub fn journal_id(&self) -> u64 {
         self.journal_id
     }
@@ -361,21 +657,47 @@ impl UsnMonitor {
 /// Deduplicate rapid changes to the same file within a batch.
 ///
 /// When multiple changes occur to the same file within a polling interval,
-/// only the final state is kept. Special case: Create followed by Delete
-/// removes the entry entirely (file never needed to exist in index).
+/// only the final state is kept. Special cases:
+/// - Create followed by Delete removes the entry entirely (file never
+///   needed to exist in index)
+/// - The old name record of a rename or move is paired with the record of
+///   its new name into one `Rename` carrying both locations; further renames
+///   keep the first old location
+/// - A Modify after a Create or Rename keeps that change, with the newest
+///   name and parent
+///
+/// An old name whose new name is not in the batch (the records were split
+/// between polls) is dropped; the new name alone moves the entry.
 pub fn deduplicate_changes(changes: Vec<UsnChange>) -> Vec<UsnChange> {
     let mut final_state: HashMap<i64, UsnChange> = HashMap::new();
 
-    for change in changes {
+    for mut change in changes {
         let file_ref = change.file_ref;
 
-        // Check for Create-then-Delete pattern
         if let Some(existing) = final_state.get(&file_ref) {
-            if existing.change_type == ChangeType::Create && change.change_type == ChangeType::Delete
-            {
+            match (existing.change_type, change.change_type) {
                 // File was created then deleted within batch - remove entirely
-                final_state.remove(&file_ref);
-                continue;
+                (ChangeType::Create, ChangeType::Delete) => {
+                    final_state.remove(&file_ref);
+                    continue;
+                }
+                // Where a new or already renamed file came from doesn't matter
+                (ChangeType::Create | ChangeType::Rename | ChangeType::RenameOld, ChangeType::RenameOld) => continue,
+                (ChangeType::RenameOld, ChangeType::Rename) => {
+                    change.old_parent_ref = Some(existing.parent_ref);
+                    change.old_name = Some(existing.name.clone());
+                }
+                (ChangeType::Rename, ChangeType::Rename) => {
+                    change.old_parent_ref = existing.old_parent_ref;
+                    change.old_name = existing.old_name.clone();
+                }
+                (ChangeType::Create | ChangeType::Rename, ChangeType::Modify) => {
+                    change.change_type = existing.change_type;
+                    change.old_parent_ref = existing.old_parent_ref;
+                    change.old_name = existing.old_name.clone();
+                }
+                (ChangeType::RenameOld, ChangeType::Modify) => continue,
+                _ => {}
             }
         }
 
@@ -383,16 +705,131 @@ pub fn deduplicate_changes(changes: Vec<UsnChange>) -> Vec<UsnChange> {
         final_state.insert(file_ref, change);
     }
 
-    final_state.into_values().collect()
+    final_state
+        .into_values()
+        .filter(|change| change.change_type != ChangeType::RenameOld)
+        .collect()
+}
+
+/// Rename the alternate data stream rows of a renamed or moved file, which
+/// are named `file:stream` and sit beside it.
+fn rename_streams(conn: &rusqlite::Connection, volume_id: i64, change: &UsnChange) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE files SET name = ?1 || ':' || stream, parent_ref = ?2
+         WHERE volume_id = ?3 AND file_ref = ?4 AND stream <> ''",
+        rusqlite::params![change.name, change.parent_ref, volume_id, change.file_ref],
+    )
+}
+
+/// Size, times and attributes of a file, read from disk.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct DiskMetadata {
+    size: i64,
+    modified: Option<i64>,
+    created: Option<i64>,
+    attributes: u32,
+}
+
+impl DiskMetadata {
+    /// Read the metadata of the file at `path`, without following links.
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        let unix_secs = |time: std::io::Result<SystemTime>| {
+            time.ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+        };
+        Some(Self {
+            size: if metadata.is_dir() { 0 } else { metadata.len() as i64 },
+            modified: unix_secs(metadata.modified()),
+            created: unix_secs(metadata.created()),
+            attributes: file_attributes(&metadata),
+        })
+    }
+
+    /// Read the metadata of a changed file the index is missing, found
+    /// through its parent's indexed path.
+    ///
+    /// # Returns
+    /// `None` if the parent is not indexed either or the file is gone.
+    fn of_change(conn: &rusqlite::Connection, volume_name: &str, volume_id: i64, change: &UsnChange) -> Option<Self> {
+        let parent_path = get_full_path(conn, volume_id, change.parent_ref).ok().flatten()?;
+        Self::read(&disk_path(volume_name, &parent_path, &change.name))
+    }
+}
+
+/// Path on disk of an entry below a volume, from its parent's indexed path
+/// (empty for the root).
+fn disk_path(volume_name: &str, parent_path: &str, name: &str) -> PathBuf {
+    let volume = volume_name.trim_end_matches('\\');
+    if parent_path.is_empty() {
+        PathBuf::from(format!("{}\\{}", volume, name))
+    } else {
+        PathBuf::from(format!("{}\\{}\\{}", volume, parent_path, name))
+    }
+}
+
+/// Insert the entry of a created file, or of a changed one the index is missing.
+fn insert_change(
+    conn: &rusqlite::Connection,
+    volume_id: i64,
+    change: &UsnChange,
+    metadata: &DiskMetadata,
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO files (volume_id, file_ref, parent_ref, name, size, modified, created, is_dir, ext, attributes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        rusqlite::params![
+            volume_id,
+            change.file_ref,
+            change.parent_ref,
+            change.name,
+            metadata.size,
+            metadata.modified,
+            metadata.created,
+            change.is_dir as i32,
+            file_extension(&change.name),
+            metadata.attributes,
+        ],
+    )
+}
+
+/// Insert the entry of a renamed or modified file the index is missing
+/// (e.g. created while the service was stopped, or moved in from an
+/// excluded path), with its metadata read from disk when it can be found.
+fn insert_missing(
+    conn: &rusqlite::Connection,
+    volume_name: Option<&str>,
+    volume_id: i64,
+    change: &UsnChange,
+) -> rusqlite::Result<DiskMetadata> {
+    let metadata = volume_name
+        .and_then(|volume_name| DiskMetadata::of_change(conn, volume_name, volume_id, change))
+        .unwrap_or_default();
+    insert_change(conn, volume_id, change, &metadata)?;
+    tracing::debug!("Added {} (file_ref {}), missing from the index", change.name, change.file_ref);
+    Ok(metadata)
 }
 
 /// Apply a batch of changes to the database.
 ///
 /// All changes are applied in a single transaction for atomicity.
+/// Files that gain an excluded extension are removed, and entries created
+/// or moved under an excluded path or directory, or matching an excluded
+/// pattern, are deleted once their paths are known.
+///
+/// A rename paired with its old name updates the hard link of that name,
+/// otherwise the file's first name. Moving a directory moves the paths of
+/// everything below it. A renamed or modified entry missing from the index
+/// is added with its size, times and attributes, so the index heals itself.
+/// References must be record numbers, as [`parse_usn_buffer`] decodes
+/// them, or no change would find the scanned rows and each would be healed
+/// into a duplicate.
 pub fn apply_changes_batch(
     db: &mut Database,
     volume_id: i64,
     changes: &[UsnChange],
+    exclude: &ExcludeMatcher,
 ) -> Result<usize> {
     // FileEntry is re-exported from crate::db via pub use ops::*
     use rusqlite::params;
@@ -407,22 +844,58 @@ pub fn apply_changes_batch(
         .map_err(|e| FFIError::Database(format!("Failed to start transaction: {}", e)))?;
 
     let mut applied = 0;
+    let mut facets = FacetDeltas::new();
+    let mut renamed: Vec<i64> = Vec::new();
+    // Name of the volume, to find files the index is missing on disk
+    let volume_name: Option<String> = tx
+        .query_row("SELECT drive_letter FROM volumes WHERE id = ?1", params![volume_id], |row| row.get(0))
+        .ok();
 
     for change in changes {
-        let result = match change.change_type {
-            ChangeType::Create => {
-                tx.execute(
-                    "INSERT OR REPLACE INTO files (volume_id, file_ref, parent_ref, name, is_dir)
-                     VALUES (?1, ?2, ?3, ?4, ?5)",
-                    params![
-                        volume_id,
-                        change.file_ref,
-                        change.parent_ref,
-                        change.name,
-                        change.is_dir as i32,
-                    ],
+        // An excluded file is dropped whatever happened to it; an old name
+        // only says where the file was
+        let change_type = if change.change_type != ChangeType::RenameOld
+            && !change.is_dir
+            && exclude.should_exclude_name(&change.name)
+        {
+            ChangeType::Delete
+        } else {
+            change.change_type
+        };
+
+        // A renamed hard link is found by its old name; other changes apply
+        // to the file's first name
+        let link: i64 = match (change_type, change.old_parent_ref, &change.old_name) {
+            (ChangeType::Rename, Some(old_parent_ref), Some(old_name)) => tx
+                .query_row(
+                    "SELECT link FROM files
+                     WHERE volume_id = ?1 AND file_ref = ?2 AND parent_ref = ?3 AND name = ?4 AND stream = ''",
+                    params![volume_id, change.file_ref, old_parent_ref, old_name],
+                    |row| row.get(0),
                 )
-            }
+                .unwrap_or(0),
+            _ => 0,
+        };
+
+        // Previous row, so cached facet counts can be moved rather than recounted
+        let previous: Option<(String, i64, bool)> = tx
+            .query_row(
+                "SELECT name, size, is_dir FROM files WHERE volume_id = ?1 AND file_ref = ?2 AND link = ?3 AND stream = ''",
+                params![volume_id, change.file_ref, link],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .ok();
+
+        // Set when a renamed or modified entry was not indexed and is
+        // inserted instead
+        let mut missing: Option<DiskMetadata> = None;
+        let mut heal = || -> rusqlite::Result<usize> {
+            let metadata = insert_missing(&tx, volume_name.as_deref(), volume_id, change)?;
+            missing = Some(metadata);
+            Ok(1)
+        };
+        let result = match change_type {
+            ChangeType::Create => insert_change(&tx, volume_id, change, &DiskMetadata::default()),
             ChangeType::Delete => {
                 tx.execute(
                     "DELETE FROM files WHERE volume_id = ?1 AND file_ref = ?2",
@@ -431,23 +904,73 @@ pub fn apply_changes_batch(
             }
             ChangeType::Rename => {
                 tx.execute(
-                    "UPDATE files SET name = ?1, parent_ref = ?2
-                     WHERE volume_id = ?3 AND file_ref = ?4",
-                    params![change.name, change.parent_ref, volume_id, change.file_ref],
+                    "UPDATE files SET name = ?1, parent_ref = ?2, is_dir = ?3, ext = ?4
+                     WHERE volume_id = ?5 AND file_ref = ?6 AND link = ?7 AND stream = ''",
+                    params![
+                        change.name,
+                        change.parent_ref,
+                        change.is_dir as i32,
+                        file_extension(&change.name),
+                        volume_id,
+                        change.file_ref,
+                        link,
+                    ],
                 )
+                .and_then(|rows| match rows {
+                    0 => heal(),
+                    // Streams sit beside the file's first name
+                    rows if link == 0 => rename_streams(&tx, volume_id, change).map(|_| rows),
+                    rows => Ok(rows),
+                })
             }
+            ChangeType::RenameOld => Ok(0),
             ChangeType::Modify => {
                 // For modify, we mainly update name in case it changed
-                // Size and modified time would require additional file queries
+                // Size and modified time would require additional file queries,
+                // made only for files missing from the index.
+                // The directory flag also repairs folders stored as files.
                 tx.execute(
-                    "UPDATE files SET name = ?1 WHERE volume_id = ?2 AND file_ref = ?3",
-                    params![change.name, volume_id, change.file_ref],
+                    "UPDATE files SET name = ?1, is_dir = ?2, ext = ?3
+                     WHERE volume_id = ?4 AND file_ref = ?5 AND link = 0 AND stream = ''",
+                    params![
+                        change.name,
+                        change.is_dir as i32,
+                        file_extension(&change.name),
+                        volume_id,
+                        change.file_ref,
+                    ],
                 )
+                .and_then(|rows| match rows {
+                    0 => heal(),
+                    rows => rename_streams(&tx, volume_id, change).map(|_| rows),
+                })
             }
         };
 
         match result {
-            Ok(_) => applied += 1,
+            Ok(_) if change_type == ChangeType::RenameOld => {}
+            Ok(rows) => {
+                applied += 1;
+                if let Some((name, size, is_dir)) = previous.as_ref().filter(|_| rows > 0) {
+                    facets.remove(name, *size, *is_dir);
+                }
+                match change_type {
+                    _ if missing.is_some() => {
+                        let size = missing.map_or(0, |metadata| metadata.size);
+                        facets.add(&change.name, size, change.is_dir);
+                    }
+                    ChangeType::Create => facets.add(&change.name, 0, change.is_dir),
+                    ChangeType::Delete | ChangeType::RenameOld => {}
+                    ChangeType::Rename | ChangeType::Modify => {
+                        if let Some((_, size, _)) = previous.filter(|_| rows > 0) {
+                            facets.add(&change.name, size, change.is_dir);
+                        }
+                    }
+                }
+                if change_type != ChangeType::Delete {
+                    renamed.push(change.file_ref);
+                }
+            }
             Err(e) => {
                 tracing::warn!(
                     "Failed to apply change for file_ref {}: {}",
@@ -458,6 +981,38 @@ pub fn apply_changes_batch(
         }
     }
 
+    // Paths of new, moved and renamed entries, cascading to their descendants
+    if let Err(e) = refresh_full_paths(&tx, volume_id, &renamed) {
+        tracing::warn!("Failed to update full paths: {}", e);
+    }
+    if !renamed.is_empty() {
+        if let Err(e) = purge_excluded_paths(&tx, volume_id, exclude, &mut facets) {
+            tracing::warn!("Failed to remove excluded paths: {}", e);
+        }
+        if let Err(e) = purge_excluded_entries(&tx, volume_id, &renamed, exclude, &mut facets) {
+            tracing::warn!("Failed to remove excluded entries: {}", e);
+        }
+    }
+
+    // Per-directory change counts feed the exclusion suggestions; a move
+    // counts in the directory it left as well
+    let mut churn: HashMap<i64, i64> = HashMap::new();
+    for change in changes {
+        *churn.entry(change.parent_ref).or_insert(0) += 1;
+        if let Some(old_parent_ref) = change.old_parent_ref.filter(|&old| old != change.parent_ref) {
+            *churn.entry(old_parent_ref).or_insert(0) += 1;
+        }
+    }
+    if let Err(e) = record_dir_churn(&tx, volume_id, &churn) {
+        tracing::warn!("Failed to record directory churn: {}", e);
+    }
+
+    if !facets.is_empty() {
+        if let Err(e) = facets.apply(&tx, volume_id) {
+            tracing::warn!("Failed to update cached facet counts: {}", e);
+        }
+    }
+
     tx.commit()
         .map_err(|e| FFIError::Database(format!("Failed to commit changes: {}", e)))?;
 
@@ -466,6 +1021,156 @@ pub fn apply_changes_batch(
     Ok(applied)
 }
 
+/// What applying one polled batch took.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BatchStats {
+    /// Changes sent to the writer, deferred ones included once applied
+    pub changes: usize,
+    /// Changes the writer applied
+    pub applied: usize,
+    /// Modify records held back for a quieter poll
+    pub deferred: usize,
+    /// Write transactions used
+    pub chunks: usize,
+    /// Chunks the writer failed to apply
+    pub failed: usize,
+    /// Time spent in the writer
+    pub elapsed: Duration,
+}
+
+impl BatchStats {
+    /// Changes applied per second.
+    pub fn rate(&self) -> f64 {
+        self.applied as f64 / self.elapsed.as_secs_f64().max(0.001)
+    }
+}
+
+/// Applies a volume's polled changes through the database writer.
+///
+/// Changes go to the writer in transactions of at most [`APPLY_CHUNK`], so
+/// a poll of hundreds of thousands of changes (a build, an antivirus scan)
+/// doesn't hold the write lock for one huge transaction, and other writes
+/// get their turn in between.
+///
+/// While the monitor is behind, Modify records are held back and Creates,
+/// Deletes and Renames applied first, which keeps name search accurate.
+/// They go in once a batch arrives below [`HIGH_WATER_MARK`], or when more
+/// than [`MAX_DEFERRED`] pile up. A later change to the same file replaces
+/// its deferred record. Deferred records live in memory only, so while any
+/// are held the monitor saves the [`position`](Self::position) from before
+/// the first of them, and a crash replays them from the journal; it
+/// [`flush`](Self::flush)es them before saving its position while paused
+/// and before it exits. The Create and Rename records that matter for names
+/// are never deferred.
+pub struct ChangeApplier {
+    volume_id: i64,
+    exclude: Arc<ExcludeMatcher>,
+    deferred: HashMap<i64, UsnChange>,
+    /// Journal position the batch holding the oldest deferred record was
+    /// read from
+    held_from: Option<i64>,
+}
+
+impl ChangeApplier {
+    /// Create an applier for a volume.
+    pub fn new(volume_id: i64, exclude: Arc<ExcludeMatcher>) -> Self {
+        Self {
+            volume_id,
+            exclude,
+            deferred: HashMap::new(),
+            held_from: None,
+        }
+    }
+
+    /// Modify records currently held back.
+    pub fn deferred(&self) -> usize {
+        self.deferred.len()
+    }
+
+    /// Journal position that is safe to save: `last_usn`, or while Modify
+    /// records are held back, the position from before the first of them.
+    pub fn position(&self, last_usn: i64) -> i64 {
+        self.held_from.unwrap_or(last_usn)
+    }
+
+    /// Apply a deduplicated batch.
+    ///
+    /// # Arguments
+    /// * `writer` - The database writer
+    /// * `changes` - Changes from [`deduplicate_changes`]
+    /// * `behind` - Whether the monitor is behind the journal, to defer
+    ///   Modify records
+    /// * `from_usn` - Journal position the batch was read from
+    pub fn apply(&mut self, writer: &DbWriter, changes: Vec<UsnChange>, behind: bool, from_usn: i64) -> BatchStats {
+        let now = self.prioritize(changes, behind);
+        self.held_from = match self.held_from {
+            _ if self.deferred.is_empty() => None,
+            Some(held_from) => Some(held_from),
+            None => Some(from_usn),
+        };
+        let mut stats = self.send(writer, now);
+        stats.deferred = self.deferred.len();
+        stats
+    }
+
+    /// Apply every deferred Modify record now.
+    pub fn flush(&mut self, writer: &DbWriter) -> BatchStats {
+        let pending = self.deferred.drain().map(|(_, change)| change).collect();
+        self.held_from = None;
+        self.send(writer, pending)
+    }
+
+    /// Split a batch into the changes to apply now, setting aside Modify
+    /// records while behind.
+    fn prioritize(&mut self, changes: Vec<UsnChange>, behind: bool) -> Vec<UsnChange> {
+        let mut now = Vec::with_capacity(changes.len());
+        for change in changes {
+            if behind && change.change_type == ChangeType::Modify {
+                self.deferred.insert(change.file_ref, change);
+            } else {
+                // The newer change also sets what the deferred one would
+                self.deferred.remove(&change.file_ref);
+                now.push(change);
+            }
+        }
+
+        if !behind || self.deferred.len() > MAX_DEFERRED {
+            now.extend(self.deferred.drain().map(|(_, change)| change));
+        }
+        now
+    }
+
+    /// Send changes to the writer in bounded transactions.
+    fn send(&self, writer: &DbWriter, changes: Vec<UsnChange>) -> BatchStats {
+        let start = Instant::now();
+        let mut stats = BatchStats {
+            changes: changes.len(),
+            ..Default::default()
+        };
+
+        let mut changes = changes.into_iter().peekable();
+        while changes.peek().is_some() {
+            let chunk: Vec<UsnChange> = changes.by_ref().take(APPLY_CHUNK).collect();
+            stats.chunks += 1;
+            let op = WriteOp::ApplyChanges {
+                volume_id: self.volume_id,
+                changes: chunk,
+                exclude: Arc::clone(&self.exclude),
+            };
+            match writer.execute(op) {
+                Ok(applied) => stats.applied += applied,
+                Err(e) => {
+                    stats.failed += 1;
+                    tracing::error!("Failed to apply changes: {}", e);
+                }
+            }
+        }
+
+        stats.elapsed = start.elapsed();
+        stats
+    }
+}
+
 /// Adaptive throttling based on system CPU load.
 ///
 /// Reduces polling frequency when the system is under heavy load
@@ -473,7 +1178,6 @@ pub fn apply_changes_batch(
 pub struct AdaptiveThrottle {
     system: sysinfo::System,
     normal_interval: std::time::Duration,
-    throttled_interval: std::time::Duration,
     cpu_threshold: f32,
 }
 
@@ -485,15 +1189,27 @@ impl AdaptiveThrottle {
         Self {
             system: sysinfo::System::new(),
             normal_interval: std::time::Duration::from_secs(normal_secs),
-            throttled_interval: std::time::Duration::from_secs(normal_secs * 4),
             cpu_threshold: 80.0,
         }
     }
 
+    /// Set the CPU usage, in percent, above which polling backs off.
+    pub fn with_cpu_threshold(mut self, cpu_threshold: f32) -> Self {
+        self.cpu_threshold = cpu_threshold;
+        self
+    }
+
     /// Get the current polling interval based on CPU load.
     ///
-    /// Returns throttled interval if CPU > 80%, otherwise normal interval.
+    /// Returns throttled interval if CPU is above the threshold, otherwise normal interval.
     pub fn get_interval(&mut self) -> std::time::Duration {
+        self.adjust(self.normal_interval)
+    }
+
+    /// Stretch a polling interval based on CPU load.
+    ///
+    /// Returns 4x `interval` if CPU is above the threshold, otherwise `interval` unchanged.
+    pub fn adjust(&mut self, interval: std::time::Duration) -> std::time::Duration {
         use sysinfo::CpuRefreshKind;
 
         // Refresh CPU info - use nothing() and add CPU usage
@@ -508,13 +1224,79 @@ impl AdaptiveThrottle {
 
         if cpu_usage > self.cpu_threshold {
             tracing::debug!("CPU at {:.1}%, throttling USN polling", cpu_usage);
-            self.throttled_interval
+            interval * 4
         } else {
-            self.normal_interval
+            interval
         }
     }
 }
 
+/// Per-volume polling interval that follows the volume's change rate.
+///
+/// The interval is `normal / rate`, with the rate in changes per second
+/// smoothed over recent polls: a volume seeing one change per second polls
+/// at the normal interval, a busy OS drive during updates polls faster and
+/// an idle data drive slower, always within `[min, max]`.
+#[derive(Debug, Clone)]
+pub struct AdaptivePoll {
+    normal: std::time::Duration,
+    min: std::time::Duration,
+    max: std::time::Duration,
+    /// Smoothed change rate, in changes per second
+    rate: f64,
+}
+
+impl AdaptivePoll {
+    /// Weight of the newest sample in the smoothed change rate.
+    const SMOOTHING: f64 = 0.5;
+
+    /// Create a poller starting at the normal interval.
+    ///
+    /// # Arguments
+    /// * `normal_secs` - Interval for a volume seeing one change per second
+    /// * `min_secs` - Shortest interval for busy volumes
+    /// * `max_secs` - Longest interval for idle volumes
+    pub fn new(normal_secs: u64, min_secs: u64, max_secs: u64) -> Self {
+        let normal_secs = normal_secs.max(1);
+        Self {
+            normal: std::time::Duration::from_secs(normal_secs),
+            min: std::time::Duration::from_secs(min_secs.clamp(1, normal_secs)),
+            max: std::time::Duration::from_secs(max_secs.max(normal_secs)),
+            rate: 1.0,
+        }
+    }
+
+    /// The configured normal interval.
+    pub fn normal(&self) -> std::time::Duration {
+        self.normal
+    }
+
+    /// Record the number of changes seen by a poll.
+    ///
+    /// # Arguments
+    /// * `changes` - Changes returned by the poll
+    /// * `elapsed` - Time since the previous poll
+    pub fn record(&mut self, changes: usize, elapsed: std::time::Duration) {
+        let secs = elapsed.as_secs_f64().max(1.0);
+        let sample = changes as f64 / secs;
+        self.rate = Self::SMOOTHING * sample + (1.0 - Self::SMOOTHING) * self.rate;
+    }
+
+    /// Current smoothed change rate, in changes per second.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Interval until the next poll.
+    pub fn interval(&self) -> std::time::Duration {
+        let secs = self.normal.as_secs_f64() / self.rate;
+        if !secs.is_finite() || secs >= self.max.as_secs_f64() {
+            return self.max;
+        }
+        std::time::Duration::from_secs_f64(secs).clamp(self.min, self.max)
+    }
+}
+
 /// Monitor handle for managing USN monitor lifecycle.
 pub struct UsnMonitorHandle {
     /// Thread handle for joining
@@ -531,6 +1313,11 @@ impl UsnMonitorHandle {
             }
         }
     }
+
+    /// Whether the monitor thread has exited (e.g., after losing its journal).
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_none_or(|handle| handle.is_finished())
+    }
 }
 
 /// Start the USN monitor loop for a volume.
@@ -540,27 +1327,33 @@ impl UsnMonitorHandle {
 /// 2. Deduplicates rapid changes
 /// 3. Applies batched updates to the database
 /// 4. Handles journal wrap by triggering background rescan
-/// 5. Adapts polling frequency based on CPU load
+/// 5. Adapts polling frequency to the volume's change rate and CPU load
 ///
 /// # Arguments
 /// * `drive_letter` - The volume to monitor (e.g., 'C')
-/// * `db` - Database instance for persisting changes
-/// * `poll_interval_secs` - Normal polling interval in seconds
+/// * `volume_id` - ID of the volume's record
+/// * `writer` - Database writer that applies the changes
+/// * `poll` - Polling interval bounds for this volume
+/// * `cpu_threshold` - CPU usage in percent above which polling backs off
+/// * `exclude` - Excludes of the volume, kept out of the index
 /// * `shutdown_rx` - Channel receiver for shutdown signals
 /// * `resume_usn` - Optional (last_usn, journal_id) tuple for resuming from saved state
 ///
 /// # Returns
 /// A handle that can be used to stop the monitor.
 #[cfg(windows)]
+#[allow(clippy::too_many_arguments)]
 pub fn usn_monitor_loop(
     drive_letter: char,
-    mut db: Database,
-    poll_interval_secs: u64,
+    volume_id: i64,
+    writer: DbWriter,
+    mut poll: AdaptivePoll,
+    cpu_threshold: f32,
+    exclude: ExcludeMatcher,
     shutdown_rx: std::sync::mpsc::Receiver<()>,
     resume_usn: Option<(i64, u64)>,
 ) -> UsnMonitorHandle {
-    use std::time::Instant;
-    use crate::db::{get_volume_usn, update_volume_usn, get_volume};
+    use super::trigger_monitor_rescan;
 
     let handle = std::thread::spawn(move || {
         tracing::info!("Starting USN monitor for volume {}: ", drive_letter);
@@ -593,7 +1386,7 @@ pub fn usn_monitor_loop(
                     last_processed,
                     lowest_valid
                 );
-                trigger_background_rescan(drive_letter);
+                trigger_monitor_rescan(&writer, volume_id, drive_letter);
                 return;
             }
             Err(UsnError::JournalRecreated { old_id, new_id }) => {
@@ -603,7 +1396,7 @@ pub fn usn_monitor_loop(
                     old_id,
                     new_id
                 );
-                trigger_background_rescan(drive_letter);
+                trigger_monitor_rescan(&writer, volume_id, drive_letter);
                 return;
             }
             Err(e) => {
@@ -612,23 +1405,42 @@ pub fn usn_monitor_loop(
             }
         };
 
-        // Get volume ID from database for updates
-        let volume_id = {
-            let drive_str = format!("{}:", drive_letter);
-            match get_volume(db.conn(), &drive_str) {
-                Ok(Some(vol)) => vol.id,
-                Ok(None) => {
-                    tracing::error!("Volume {} not found in database", drive_letter);
-                    return;
-                }
-                Err(e) => {
-                    tracing::error!("Failed to get volume {}: {}", drive_letter, e);
-                    return;
-                }
+        let mut applier = ChangeApplier::new(volume_id, Arc::new(exclude));
+
+        // Fast-forward through a large backlog before normal polling
+        match catch_up(&mut monitor, &writer, &mut applier) {
+            Ok(()) => {}
+            Err(UsnError::JournalWrapped { last_processed, lowest_valid }) => {
+                tracing::warn!(
+                    "USN Journal wrapped on volume {} during catch-up (last={}, lowest={}). Triggering rescan.",
+                    drive_letter,
+                    last_processed,
+                    lowest_valid
+                );
+                applier.flush(&writer);
+                trigger_monitor_rescan(&writer, volume_id, drive_letter);
+                return;
             }
-        };
+            Err(UsnError::JournalRecreated { old_id, new_id }) => {
+                tracing::warn!(
+                    "USN Journal recreated on volume {} during catch-up (old={}, new={}). Triggering rescan.",
+                    drive_letter,
+                    old_id,
+                    new_id
+                );
+                applier.flush(&writer);
+                trigger_monitor_rescan(&writer, volume_id, drive_letter);
+                return;
+            }
+            Err(e) => {
+                tracing::error!("Catch-up failed on volume {}, continuing with normal polling: {}", drive_letter, e);
+            }
+        }
 
-        let mut throttle = AdaptiveThrottle::new(poll_interval_secs);
+        let mut throttle =
+            AdaptiveThrottle::new(poll.normal().as_secs()).with_cpu_threshold(cpu_threshold);
+        let mut last_poll = Instant::now();
+        let mut saved_usn = None;
 
         loop {
             // Check for shutdown signal
@@ -640,40 +1452,81 @@ pub fn usn_monitor_loop(
                 Err(std::sync::mpsc::TryRecvError::Empty) => {}
             }
 
+            // Save the position reached before waiting out a pause, so a
+            // machine suspended while paused resumes from it
+            if super::is_indexing_paused() && saved_usn != Some(monitor.last_usn()) {
+                applier.flush(&writer);
+                if let Err(e) = writer.execute(WriteOp::SaveUsnPosition {
+                    volume_id,
+                    last_usn: monitor.last_usn(),
+                    journal_id: monitor.journal_id(),
+                }) {
+                    tracing::error!("Failed to persist USN position: {}", e);
+                }
+                saved_usn = Some(monitor.last_usn());
+            }
+
+            // Changes made while paused stay in the journal until resumed
+            if super::wait_while_paused(&shutdown_rx) {
+                tracing::info!("USN monitor for {} received shutdown signal", drive_letter);
+                break;
+            }
+
             let start = Instant::now();
 
             // Poll for changes
-            match monitor.poll_changes() {
-                Ok(changes) if !changes.is_empty() => {
+            let from_usn = monitor.last_usn();
+            let polled = monitor.poll_changes();
+            let since_last = last_poll.elapsed();
+            last_poll = Instant::now();
+            match polled {
+                // Deferred records go in once a poll comes back below the mark
+                Ok(changes) if !changes.is_empty() || applier.deferred() > 0 => {
+                    poll.record(changes.len(), since_last);
+                    let polled = changes.len();
+                    let behind = polled >= HIGH_WATER_MARK;
                     let deduped = deduplicate_changes(changes);
-                    tracing::info!(
-                        "Volume {}: processing {} changes ({} after dedup)",
+                    if polled > 0 {
+                        tracing::info!(
+                            "Volume {}: processing {} changes ({} after dedup)",
+                            drive_letter,
+                            polled,
+                            deduped.len()
+                        );
+                    }
+
+                    let stats = applier.apply(&writer, deduped, behind, from_usn);
+                    tracing::debug!(
+                        "Applied {} changes to volume {} in {} transactions, {:.1}s ({:.0}/s), {} deferred",
+                        stats.applied,
                         drive_letter,
-                        deduped.len(),
-                        deduped.len()
+                        stats.chunks,
+                        stats.elapsed.as_secs_f64(),
+                        stats.rate(),
+                        stats.deferred
                     );
-
-                    match apply_changes_batch(&mut db, volume_id, &deduped) {
-                        Ok(applied) => {
-                            tracing::debug!("Applied {} changes to volume {}", applied, drive_letter);
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to apply changes: {}", e);
-                        }
+                    if behind {
+                        tracing::info!(
+                            "Volume {}: heavy churn, {} Modify records deferred",
+                            drive_letter,
+                            stats.deferred
+                        );
                     }
 
-                    // Update persisted USN position
-                    if let Err(e) = update_volume_usn(
-                        db.conn(),
+                    // Update persisted USN position, short of any deferred records
+                    let position = applier.position(monitor.last_usn());
+                    if let Err(e) = writer.execute(WriteOp::SaveUsnPosition {
                         volume_id,
-                        monitor.last_usn(),
-                        monitor.journal_id() as i64,
-                    ) {
+                        last_usn: position,
+                        journal_id: monitor.journal_id(),
+                    }) {
                         tracing::error!("Failed to persist USN position: {}", e);
                     }
+                    saved_usn = Some(position);
                 }
                 Ok(_) => {
                     // No changes this poll cycle
+                    poll.record(0, since_last);
                 }
                 Err(UsnError::JournalWrapped { last_processed, lowest_valid }) => {
                     tracing::warn!(
@@ -682,7 +1535,8 @@ pub fn usn_monitor_loop(
                         last_processed,
                         lowest_valid
                     );
-                    trigger_background_rescan(drive_letter);
+                    applier.flush(&writer);
+                    trigger_monitor_rescan(&writer, volume_id, drive_letter);
                     break;
                 }
                 Err(UsnError::JournalRecreated { old_id, new_id }) => {
@@ -692,7 +1546,8 @@ pub fn usn_monitor_loop(
                         old_id,
                         new_id
                     );
-                    trigger_background_rescan(drive_letter);
+                    applier.flush(&writer);
+                    trigger_monitor_rescan(&writer, volume_id, drive_letter);
                     break;
                 }
                 Err(e) => {
@@ -700,8 +1555,14 @@ pub fn usn_monitor_loop(
                 }
             }
 
-            // Get adaptive interval based on CPU load
-            let interval = throttle.get_interval();
+            // Get adaptive interval based on change rate and CPU load
+            let interval = throttle.adjust(poll.interval());
+            tracing::trace!(
+                "Volume {}: {:.2} changes/s, next poll in {:?}",
+                drive_letter,
+                poll.rate(),
+                interval
+            );
 
             // Sleep for remainder of interval
             let elapsed = start.elapsed();
@@ -710,6 +1571,19 @@ pub fn usn_monitor_loop(
             }
         }
 
+        // The saved position is still short of the deferred records
+        if applier.deferred() > 0 {
+            let stats = applier.flush(&writer);
+            tracing::info!("Volume {}: applied {} deferred changes before exiting", drive_letter, stats.applied);
+            if let Err(e) = writer.execute(WriteOp::SaveUsnPosition {
+                volume_id,
+                last_usn: monitor.last_usn(),
+                journal_id: monitor.journal_id(),
+            }) {
+                tracing::error!("Failed to persist USN position: {}", e);
+            }
+        }
+
         tracing::info!("USN monitor for {} exiting", drive_letter);
     });
 
@@ -718,12 +1592,83 @@ pub fn usn_monitor_loop(
     }
 }
 
+/// Apply a large journal backlog in catch-up mode.
+///
+/// After long downtime the journal can hold weeks of changes. Instead of
+/// working through them one throttled poll at a time, this reads and
+/// applies [`CATCH_UP_BATCH`] changes per batch without sleeping, logging
+/// progress after each batch, until the USN the journal had reached when
+/// catch-up started. Small backlogs are left to normal polling.
+///
+/// The monitor is behind throughout, so Modify records are deferred until
+/// normal polling (see [`ChangeApplier`]).
+#[cfg(windows)]
+fn catch_up(
+    monitor: &mut UsnMonitor,
+    writer: &DbWriter,
+    applier: &mut ChangeApplier,
+) -> std::result::Result<(), UsnError> {
+    let volume_id = applier.volume_id;
+    let progress = CatchUpProgress::new(monitor.last_usn(), monitor.next_usn()?);
+    if !progress.needs_catch_up() {
+        return Ok(());
+    }
+
+    tracing::info!(
+        "Volume {}: catching up on {} MB of USN journal backlog",
+        monitor.volume(),
+        progress.backlog() / (1024 * 1024)
+    );
+
+    let start = Instant::now();
+    let mut applied_total = 0;
+    while !progress.is_done(monitor.last_usn()) {
+        let from_usn = monitor.last_usn();
+        let changes = monitor.poll_changes_up_to(CATCH_UP_BATCH)?;
+        if changes.is_empty() {
+            break;
+        }
+
+        let stats = applier.apply(writer, deduplicate_changes(changes), true, from_usn);
+        applied_total += stats.applied;
+
+        if let Err(e) = writer.execute(WriteOp::SaveUsnPosition {
+            volume_id,
+            last_usn: applier.position(monitor.last_usn()),
+            journal_id: monitor.journal_id(),
+        }) {
+            tracing::error!("Failed to persist USN position: {}", e);
+        }
+
+        tracing::info!(
+            "Volume {}: catch-up {:.0}% ({} changes applied at {:.0}/s, {} deferred)",
+            monitor.volume(),
+            progress.percent(monitor.last_usn()),
+            applied_total,
+            stats.rate(),
+            stats.deferred
+        );
+    }
+
+    tracing::info!(
+        "Volume {}: caught up, {} changes applied in {:.1}s",
+        monitor.volume(),
+        applied_total,
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
 /// Stub for non-Windows platforms.
 #[cfg(not(windows))]
+#[allow(clippy::too_many_arguments)]
 pub fn usn_monitor_loop(
     _drive_letter: char,
-    _db: Database,
-    _poll_interval_secs: u64,
+    _volume_id: i64,
+    _writer: DbWriter,
+    _poll: AdaptivePoll,
+    _cpu_threshold: f32,
+    _exclude: ExcludeMatcher,
     _shutdown_rx: std::sync::mpsc::Receiver<()>,
     _resume_usn: Option<(i64, u64)>,
 ) -> UsnMonitorHandle {
@@ -731,23 +1676,123 @@ pub fn usn_monitor_loop(
     UsnMonitorHandle { handle: None }
 }
 
-/// Trigger a background rescan of a volume.
-///
-/// Called when the USN journal has wrapped or been recreated,
-/// meaning some file changes were missed.
-pub fn trigger_background_rescan(drive_letter: char) {
-    // TODO: Implement full background rescan integration with indexer
-    // For now, just log the event
-    tracing::warn!(
-        "Background rescan triggered for volume {}: - full rescan needed",
-        drive_letter
-    );
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Encode a USN_RECORD_V2 the way FSCTL_READ_USN_JOURNAL returns it.
+    fn usn_record_v2(usn: i64, file_ref: u64, parent_ref: u64, reason: u32, attributes: u32, name: &str) -> Vec<u8> {
+        let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let length = (60 + name.len()).next_multiple_of(8);
+        let mut record = Vec::with_capacity(length);
+        record.extend_from_slice(&(length as u32).to_le_bytes());
+        record.extend_from_slice(&2u16.to_le_bytes());
+        record.extend_from_slice(&0u16.to_le_bytes());
+        record.extend_from_slice(&file_ref.to_le_bytes());
+        record.extend_from_slice(&parent_ref.to_le_bytes());
+        record.extend_from_slice(&usn.to_le_bytes());
+        record.extend_from_slice(&0i64.to_le_bytes()); // timestamp
+        record.extend_from_slice(&reason.to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes()); // source info
+        record.extend_from_slice(&0u32.to_le_bytes()); // security id
+        record.extend_from_slice(&attributes.to_le_bytes());
+        record.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        record.extend_from_slice(&60u16.to_le_bytes());
+        record.extend_from_slice(&name);
+        record.resize(length, 0);
+        record
+    }
+
+    #[test]
+    fn test_parse_usn_buffer() {
+        let mut buf = 4096i64.to_le_bytes().to_vec();
+        buf.extend(usn_record_v2(1024, 300, 5, 0x100, 0x10, "Projects"));
+        buf.extend(usn_record_v2(1104, 301, 300, 0x8000_0002, 0x20, "naïve.txt"));
+
+        let (next_usn, records) = parse_usn_buffer(&buf).unwrap();
+        assert_eq!(next_usn, 4096);
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0],
+            UsnRecord {
+                usn: 1024,
+                file_ref: 300,
+                parent_ref: 5,
+                reason: 0x100,
+                file_attributes: 0x10,
+                name: "Projects".to_string(),
+            }
+        );
+        assert!(records[0].is_dir());
+        assert_eq!(records[1].name, "naïve.txt");
+        assert_eq!(records[1].parent_ref, 300);
+        assert!(!records[1].is_dir());
+
+        // A truncated trailing record is dropped, an empty read has no records
+        let truncated = &buf[..buf.len() - 10];
+        assert_eq!(parse_usn_buffer(truncated).unwrap().1.len(), 1);
+        assert_eq!(parse_usn_buffer(&4096i64.to_le_bytes()), Some((4096, Vec::new())));
+        assert_eq!(parse_usn_buffer(&[0; 4]), None);
+    }
+
+    #[test]
+    fn test_parse_usn_buffer_drops_sequence_numbers() {
+        // Record 300 at sequence 7 in directory 5 at sequence 5
+        let mut buf = 4096i64.to_le_bytes().to_vec();
+        buf.extend(usn_record_v2(1024, (7 << 48) | 300, (5 << 48) | 5, 0x100, 0x20, "a.txt"));
+
+        let (_, records) = parse_usn_buffer(&buf).unwrap();
+        assert_eq!(records[0].file_ref, 300);
+        assert_eq!(records[0].parent_ref, 5);
+    }
+
+    #[test]
+    fn test_catch_up_progress() {
+        let small = CatchUpProgress::new(1_000, 1_000 + CATCH_UP_THRESHOLD - 1);
+        assert!(!small.needs_catch_up());
+
+        let start = 5_000_000;
+        let large = CatchUpProgress::new(start, start + 4 * CATCH_UP_THRESHOLD);
+        assert!(large.needs_catch_up());
+        assert_eq!(large.percent(start), 0.0);
+        assert_eq!(large.percent(start + 2 * CATCH_UP_THRESHOLD), 50.0);
+        assert!(!large.is_done(start + CATCH_UP_THRESHOLD));
+        assert!(large.is_done(start + 4 * CATCH_UP_THRESHOLD));
+
+        // A stored USN ahead of the journal has no backlog
+        let ahead = CatchUpProgress::new(9_000, 100);
+        assert_eq!(ahead.backlog(), 0);
+        assert_eq!(ahead.percent(9_000), 100.0);
+    }
+
+    #[test]
+    fn test_adaptive_poll_follows_change_rate() {
+        use std::time::Duration;
+
+        let mut poll = AdaptivePoll::new(30, 5, 120);
+        assert_eq!(poll.interval(), Duration::from_secs(30));
+
+        // Busy volume: thousands of changes per poll shorten to the minimum
+        for _ in 0..4 {
+            poll.record(5_000, Duration::from_secs(30));
+        }
+        assert_eq!(poll.interval(), Duration::from_secs(5));
+
+        // Idle volume: empty polls lengthen gradually up to the maximum
+        poll.record(0, Duration::from_secs(5));
+        assert!(poll.interval() < Duration::from_secs(120));
+        for _ in 0..20 {
+            poll.record(0, Duration::from_secs(120));
+        }
+        assert_eq!(poll.interval(), Duration::from_secs(120));
+
+        // Bounds are widened to include the normal interval
+        let mut odd = AdaptivePoll::new(30, 60, 10);
+        assert_eq!(odd.interval(), Duration::from_secs(30));
+        odd.record(10_000, Duration::from_secs(1));
+        assert_eq!(odd.interval(), Duration::from_secs(30));
+    }
+
     #[test]
     fn test_deduplicate_removes_create_delete() {
         let changes = vec![
@@ -757,6 +1802,8 @@ mod tests {
                 name: "test.txt".to_string(),
                 change_type: ChangeType::Create,
                 is_dir: false,
+                old_parent_ref: None,
+                old_name: None,
             },
             UsnChange {
                 file_ref: 100,
@@ -764,6 +1811,8 @@ mod tests {
                 name: "test.txt".to_string(),
                 change_type: ChangeType::Delete,
                 is_dir: false,
+                old_parent_ref: None,
+                old_name: None,
             },
         ];
 
@@ -780,6 +1829,8 @@ mod tests {
                 name: "old.txt".to_string(),
                 change_type: ChangeType::Create,
                 is_dir: false,
+                old_parent_ref: None,
+                old_name: None,
             },
             UsnChange {
                 file_ref: 100,
@@ -787,6 +1838,8 @@ mod tests {
                 name: "new.txt".to_string(),
                 change_type: ChangeType::Rename,
                 is_dir: false,
+                old_parent_ref: None,
+                old_name: None,
             },
         ];
 
@@ -805,6 +1858,8 @@ mod tests {
                 name: "file1.txt".to_string(),
                 change_type: ChangeType::Create,
                 is_dir: false,
+                old_parent_ref: None,
+                old_name: None,
             },
             UsnChange {
                 file_ref: 200,
@@ -812,6 +1867,8 @@ mod tests {
                 name: "file2.txt".to_string(),
                 change_type: ChangeType::Create,
                 is_dir: false,
+                old_parent_ref: None,
+                old_name: None,
             },
             UsnChange {
                 file_ref: 100,
@@ -819,10 +1876,486 @@ mod tests {
                 name: "file1.txt".to_string(),
                 change_type: ChangeType::Modify,
                 is_dir: false,
+                old_parent_ref: None,
+                old_name: None,
             },
         ];
 
         let deduped = deduplicate_changes(changes);
         assert_eq!(deduped.len(), 2);
     }
+
+    #[test]
+    fn test_deduplicate_pairs_renames() {
+        let change = |file_ref: i64, parent_ref: i64, name: &str, change_type: ChangeType| UsnChange {
+            file_ref,
+            parent_ref,
+            name: name.to_string(),
+            change_type,
+            is_dir: false,
+            old_parent_ref: None,
+            old_name: None,
+        };
+
+        // Moved, renamed again and written: one rename from the first location
+        let changes = vec![
+            change(100, 5, "draft.txt", ChangeType::RenameOld),
+            change(100, 7, "report.txt", ChangeType::Rename),
+            change(100, 7, "report.txt", ChangeType::RenameOld),
+            change(100, 7, "final.txt", ChangeType::Rename),
+            change(100, 7, "final.txt", ChangeType::Modify),
+            // Only the old name made it into this batch
+            change(200, 5, "notes.txt", ChangeType::RenameOld),
+        ];
+        let deduped = deduplicate_changes(changes);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].change_type, ChangeType::Rename);
+        assert_eq!((deduped[0].parent_ref, deduped[0].name.as_str()), (7, "final.txt"));
+        assert_eq!(deduped[0].old_parent_ref, Some(5));
+        assert_eq!(deduped[0].old_name.as_deref(), Some("draft.txt"));
+
+        // A file created in the batch stays new under its final name
+        let changes = vec![
+            change(300, 5, "tmp123", ChangeType::Create),
+            change(300, 5, "tmp123", ChangeType::RenameOld),
+            change(300, 7, "data.json", ChangeType::Rename),
+            change(300, 7, "data.json", ChangeType::Modify),
+        ];
+        let deduped = deduplicate_changes(changes);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].change_type, ChangeType::Rename);
+        assert_eq!((deduped[0].parent_ref, deduped[0].name.as_str()), (7, "data.json"));
+    }
+
+    #[test]
+    fn test_apply_changes_updates_facet_counts() {
+        use crate::db::{get_facet_counts, insert_volume, open_database, Facet};
+
+        let dir = std::env::temp_dir().join("ffi_test_usn_facets");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+
+        let change = |file_ref: i64, name: &str, change_type: ChangeType| UsnChange {
+            file_ref,
+            parent_ref: 5,
+            name: name.to_string(),
+            change_type,
+            is_dir: false,
+            old_parent_ref: None,
+            old_name: None,
+        };
+        let created = vec![
+            change(100, "a.pdf", ChangeType::Create),
+            change(101, "b.pdf", ChangeType::Create),
+        ];
+        apply_changes_batch(&mut db, volume_id, &created, &ExcludeMatcher::default()).unwrap();
+
+        let renamed = vec![
+            change(100, "a.docx", ChangeType::Rename),
+            change(101, "b.pdf", ChangeType::Delete),
+        ];
+        apply_changes_batch(&mut db, volume_id, &renamed, &ExcludeMatcher::default()).unwrap();
+
+        let exts = get_facet_counts(db.conn(), Facet::Extension, Some(volume_id)).unwrap();
+        let values: Vec<(&str, i64)> = exts.iter().map(|f| (f.value.as_str(), f.count)).collect();
+        assert_eq!(values, vec![("docx", 1)]);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_changes_persists_dir_flag() {
+        use crate::db::{get_facet_counts, insert_volume, open_database, Facet};
+
+        let dir = std::env::temp_dir().join("ffi_test_usn_dir_flag");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+
+        let change = |file_ref: i64, name: &str, change_type: ChangeType, is_dir: bool| UsnChange {
+            file_ref,
+            parent_ref: 5,
+            name: name.to_string(),
+            change_type,
+            is_dir,
+            old_parent_ref: None,
+            old_name: None,
+        };
+        let is_dir = |db: &Database, file_ref: i64| -> bool {
+            db.conn()
+                .query_row(
+                    "SELECT is_dir FROM files WHERE volume_id = ?1 AND file_ref = ?2",
+                    rusqlite::params![volume_id, file_ref],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+
+        // New folder, plus a folder that an older version stored as a file
+        let created = vec![
+            change(100, "Projects", ChangeType::Create, true),
+            change(101, "Archive", ChangeType::Create, false),
+        ];
+        apply_changes_batch(&mut db, volume_id, &created, &ExcludeMatcher::default()).unwrap();
+        assert!(is_dir(&db, 100));
+        assert!(!is_dir(&db, 101));
+
+        let modified = vec![change(101, "Archive", ChangeType::Modify, true)];
+        apply_changes_batch(&mut db, volume_id, &modified, &ExcludeMatcher::default()).unwrap();
+        assert!(is_dir(&db, 101));
+
+        let types = get_facet_counts(db.conn(), Facet::Type, Some(volume_id)).unwrap();
+        let values: Vec<(&str, i64)> = types.iter().map(|f| (f.value.as_str(), f.count)).collect();
+        assert_eq!(values, vec![("folder", 2)]);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_changes_cascades_full_paths() {
+        use crate::db::{get_full_path, insert_volume, open_database};
+
+        let dir = std::env::temp_dir().join("ffi_test_usn_full_path");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+
+        let change = |file_ref: i64, parent_ref: i64, name: &str, change_type: ChangeType| UsnChange {
+            file_ref,
+            parent_ref,
+            name: name.to_string(),
+            change_type,
+            is_dir: file_ref < 300,
+            old_parent_ref: None,
+            old_name: None,
+        };
+        let created = vec![
+            change(5, 5, ".", ChangeType::Create),
+            change(100, 5, "Projects", ChangeType::Create),
+            change(200, 100, "ffi", ChangeType::Create),
+            change(300, 200, "main.rs", ChangeType::Create),
+        ];
+        apply_changes_batch(&mut db, volume_id, &created, &ExcludeMatcher::default()).unwrap();
+        assert_eq!(
+            get_full_path(db.conn(), volume_id, 300).unwrap().as_deref(),
+            Some(r"Projects\ffi\main.rs")
+        );
+
+        // Renaming a directory updates everything below it
+        let renamed = [change(100, 5, "Code", ChangeType::Rename)];
+        apply_changes_batch(&mut db, volume_id, &renamed, &ExcludeMatcher::default()).unwrap();
+        assert_eq!(
+            get_full_path(db.conn(), volume_id, 300).unwrap().as_deref(),
+            Some(r"Code\ffi\main.rs")
+        );
+
+        // A file's streams follow it when it is renamed and moved
+        db.conn()
+            .execute(
+                "INSERT INTO files (volume_id, file_ref, parent_ref, name, stream)
+                 VALUES (?1, 300, 200, 'main.rs:Zone.Identifier', 'Zone.Identifier')",
+                [volume_id],
+            )
+            .unwrap();
+        let moved = [change(300, 100, "lib.rs", ChangeType::Rename)];
+        apply_changes_batch(&mut db, volume_id, &moved, &ExcludeMatcher::default()).unwrap();
+        let stream_path: String = db
+            .conn()
+            .query_row("SELECT full_path FROM files WHERE file_ref = 300 AND stream <> ''", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stream_path, r"Code\lib.rs:Zone.Identifier");
+        assert_eq!(get_full_path(db.conn(), volume_id, 300).unwrap().as_deref(), Some(r"Code\lib.rs"));
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_changes_skips_excluded_entries() {
+        use crate::db::{get_file_count, insert_volume, open_database};
+        use crate::service::config::ExcludeConfig;
+
+        let dir = std::env::temp_dir().join("ffi_test_usn_exclude");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let exclude = ExcludeConfig {
+            paths: vec![r"C:\Code\ffi".to_string()],
+            extensions: vec!["log".to_string()],
+            directories: vec!["target".to_string()],
+            ..Default::default()
+        }
+        .matcher();
+
+        let change = |file_ref: i64, parent_ref: i64, name: &str, change_type: ChangeType| UsnChange {
+            file_ref,
+            parent_ref,
+            name: name.to_string(),
+            change_type,
+            is_dir: file_ref < 300,
+            old_parent_ref: None,
+            old_name: None,
+        };
+        let created = vec![
+            change(5, 5, ".", ChangeType::Create),
+            change(100, 5, "Projects", ChangeType::Create),
+            change(200, 100, "ffi", ChangeType::Create),
+            change(300, 200, "main.rs", ChangeType::Create),
+            change(301, 100, "build.log", ChangeType::Create),
+        ];
+        apply_changes_batch(&mut db, volume_id, &created, &exclude).unwrap();
+        assert_eq!(get_file_count(db.conn(), Some(volume_id)).unwrap(), 4);
+
+        // Renamed to an excluded extension: removed
+        let renamed = [change(300, 200, "main.log", ChangeType::Rename)];
+        apply_changes_batch(&mut db, volume_id, &renamed, &exclude).unwrap();
+        assert_eq!(get_file_count(db.conn(), Some(volume_id)).unwrap(), 3);
+
+        // Moved under an excluded path: the whole tree is removed
+        let renamed = [change(100, 5, "Code", ChangeType::Rename)];
+        apply_changes_batch(&mut db, volume_id, &renamed, &exclude).unwrap();
+        assert_eq!(get_file_count(db.conn(), Some(volume_id)).unwrap(), 2);
+
+        // Created in an excluded directory: removed with it
+        let created = [change(210, 5, "Target", ChangeType::Create), change(310, 210, "app.exe", ChangeType::Create)];
+        apply_changes_batch(&mut db, volume_id, &created, &exclude).unwrap();
+        assert_eq!(get_file_count(db.conn(), Some(volume_id)).unwrap(), 2);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_paired_renames() {
+        use crate::db::{get_full_path, insert_volume, open_database};
+
+        let dir = std::env::temp_dir().join("ffi_test_usn_paired_rename");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+
+        let change = |file_ref: i64, parent_ref: i64, name: &str, change_type: ChangeType| UsnChange {
+            file_ref,
+            parent_ref,
+            name: name.to_string(),
+            change_type,
+            is_dir: file_ref < 300,
+            old_parent_ref: None,
+            old_name: None,
+        };
+        let created = vec![
+            change(5, 5, ".", ChangeType::Create),
+            change(100, 5, "Projects", ChangeType::Create),
+            change(101, 5, "Archive", ChangeType::Create),
+            change(200, 100, "ffi", ChangeType::Create),
+            change(300, 200, "main.rs", ChangeType::Create),
+        ];
+        apply_changes_batch(&mut db, volume_id, &created, &ExcludeMatcher::default()).unwrap();
+
+        // A second hard link of main.rs
+        db.conn()
+            .execute(
+                "INSERT INTO files (volume_id, file_ref, parent_ref, name, link) VALUES (?1, 300, 100, 'main-link.rs', 1)",
+                [volume_id],
+            )
+            .unwrap();
+        let names = |db: &Database| -> Vec<String> {
+            let mut stmt = db
+                .conn()
+                .prepare("SELECT full_path FROM files WHERE file_ref = 300 ORDER BY link")
+                .unwrap();
+            stmt.query_map([], |row| row.get(0)).unwrap().map(|r| r.unwrap()).collect()
+        };
+
+        // Moving a directory to another parent moves its subtree, and
+        // renaming the hard link leaves the file's first name alone
+        let moved = deduplicate_changes(vec![
+            change(200, 100, "ffi", ChangeType::RenameOld),
+            change(200, 101, "ffi-old", ChangeType::Rename),
+            change(300, 100, "main-link.rs", ChangeType::RenameOld),
+            change(300, 101, "main-copy.rs", ChangeType::Rename),
+        ]);
+        apply_changes_batch(&mut db, volume_id, &moved, &ExcludeMatcher::default()).unwrap();
+        assert_eq!(names(&db), vec![r"Archive\ffi-old\main.rs", r"Archive\main-copy.rs"]);
+
+        // Moved in from outside the index: added
+        let moved_in = [change(301, 101, "README.md", ChangeType::Rename)];
+        apply_changes_batch(&mut db, volume_id, &moved_in, &ExcludeMatcher::default()).unwrap();
+        assert_eq!(get_full_path(db.conn(), volume_id, 301).unwrap().as_deref(), Some(r"Archive\README.md"));
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_changes_adds_missing_entries() {
+        use crate::db::{get_file_count, get_full_path, insert_volume, open_database};
+
+        let dir = std::env::temp_dir().join("ffi_test_usn_missing");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+
+        let change = |file_ref: i64, parent_ref: i64, name: &str, change_type: ChangeType| UsnChange {
+            file_ref,
+            parent_ref,
+            name: name.to_string(),
+            change_type,
+            is_dir: file_ref < 300,
+            old_parent_ref: None,
+            old_name: None,
+        };
+        let created = vec![change(5, 5, ".", ChangeType::Create), change(100, 5, "Projects", ChangeType::Create)];
+        apply_changes_batch(&mut db, volume_id, &created, &ExcludeMatcher::default()).unwrap();
+
+        // Written to and renamed while the service was stopped
+        let changes = [
+            change(300, 100, "notes.txt", ChangeType::Modify),
+            change(301, 5, "todo.md", ChangeType::Rename),
+        ];
+        assert_eq!(apply_changes_batch(&mut db, volume_id, &changes, &ExcludeMatcher::default()).unwrap(), 2);
+        assert_eq!(get_file_count(db.conn(), Some(volume_id)).unwrap(), 4);
+        assert_eq!(get_full_path(db.conn(), volume_id, 300).unwrap().as_deref(), Some(r"Projects\notes.txt"));
+        assert_eq!(get_full_path(db.conn(), volume_id, 301).unwrap().as_deref(), Some("todo.md"));
+
+        // Paths on disk come from the parent's indexed path
+        assert_eq!(disk_path("C:", "", "todo.md"), PathBuf::from(r"C:\todo.md"));
+        assert_eq!(disk_path(r"C:\Mount\Data\", "Projects", "a.txt"), PathBuf::from(r"C:\Mount\Data\Projects\a.txt"));
+
+        std::fs::write(dir.join("data.bin"), [0u8; 42]).unwrap();
+        let metadata = DiskMetadata::read(&dir.join("data.bin")).unwrap();
+        assert_eq!(metadata.size, 42);
+        assert!(metadata.modified.is_some());
+        assert_eq!(DiskMetadata::read(&dir.join("gone.bin")), None);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_changes_matches_mft_rows() {
+        use crate::db::{batch_insert_files, get_file_count, get_full_path, insert_volume, open_database, FileEntry};
+
+        let dir = std::env::temp_dir().join("ffi_test_usn_mft_refs");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+
+        // Stored by the MFT scan as 48-bit record numbers
+        let scanned = [
+            FileEntry::test(volume_id, 5, 5, ".", true),
+            FileEntry::test(volume_id, 100, 5, "Projects", true),
+            FileEntry::test(volume_id, 300, 100, "notes.txt", false),
+            FileEntry::test(volume_id, 301, 100, "draft.txt", false),
+            FileEntry::test(volume_id, 302, 100, "old.txt", false),
+        ];
+        batch_insert_files(db.conn_mut(), &scanned).unwrap();
+
+        // The journal reports full references, sequence number included
+        let seq = |sequence: u64, record: u64| (sequence << 48) | record;
+        let mut buf = 4096i64.to_le_bytes().to_vec();
+        buf.extend(usn_record_v2(1024, seq(3, 300), seq(2, 100), 0x1, 0x20, "notes.txt"));
+        buf.extend(usn_record_v2(1104, seq(4, 301), seq(5, 5), 0x2000, 0x20, "todo.md"));
+        buf.extend(usn_record_v2(1184, seq(6, 302), seq(2, 100), 0x200, 0x20, "old.txt"));
+        let (_, records) = parse_usn_buffer(&buf).unwrap();
+
+        let change_types = [ChangeType::Modify, ChangeType::Rename, ChangeType::Delete];
+        let changes: Vec<UsnChange> = records
+            .into_iter()
+            .zip(change_types)
+            .map(|(record, change_type)| UsnChange {
+                file_ref: record.file_ref,
+                parent_ref: record.parent_ref,
+                change_type,
+                is_dir: record.is_dir(),
+                name: record.name,
+                old_parent_ref: None,
+                old_name: None,
+            })
+            .collect();
+        assert_eq!(apply_changes_batch(&mut db, volume_id, &changes, &ExcludeMatcher::default()).unwrap(), 3);
+
+        // The scanned rows were updated in place, not duplicated by the heal path
+        assert_eq!(get_file_count(db.conn(), Some(volume_id)).unwrap(), 4);
+        assert_eq!(get_full_path(db.conn(), volume_id, 300).unwrap().as_deref(), Some(r"Projects\notes.txt"));
+        assert_eq!(get_full_path(db.conn(), volume_id, 301).unwrap().as_deref(), Some("todo.md"));
+        assert_eq!(get_full_path(db.conn(), volume_id, 302).unwrap(), None);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_change_applier_defers_modify_while_behind() {
+        use crate::db::{get_file_count, insert_volume, open_database, WriterThread};
+
+        let dir = std::env::temp_dir().join("ffi_test_usn_applier");
+        let _ = std::fs::remove_dir_all(&dir);
+        let db = open_database(&dir.join("index.db")).unwrap();
+        let volume_id = insert_volume(db.conn(), "C:", "1234", "NTFS").unwrap();
+        let mut writer_thread = WriterThread::spawn(db);
+        let writer = writer_thread.writer();
+
+        let change = |file_ref: i64, name: &str, change_type: ChangeType| UsnChange {
+            file_ref,
+            parent_ref: 5,
+            name: name.to_string(),
+            change_type,
+            is_dir: false,
+            old_parent_ref: None,
+            old_name: None,
+        };
+        let mut applier = ChangeApplier::new(volume_id, Arc::new(ExcludeMatcher::default()));
+
+        // More changes than fit one transaction
+        let created: Vec<UsnChange> = (0..APPLY_CHUNK as i64 + 1)
+            .map(|i| change(100 + i, &format!("file{}.txt", i), ChangeType::Create))
+            .collect();
+        let stats = applier.apply(&writer, created, false, 0);
+        assert_eq!(applier.position(1024), 1024);
+        assert_eq!((stats.changes, stats.applied, stats.chunks, stats.failed), (APPLY_CHUNK + 1, APPLY_CHUNK + 1, 2, 0));
+
+        // Behind: names first, Modify records later
+        let stats = applier.apply(
+            &writer,
+            vec![
+                change(100, "file0.txt", ChangeType::Modify),
+                change(101, "file1.txt", ChangeType::Modify),
+                change(101, "file1.txt", ChangeType::Delete),
+                change(1, "new.txt", ChangeType::Create),
+            ],
+            true,
+            1024,
+        );
+        assert_eq!((stats.applied, stats.deferred), (2, 1));
+        assert_eq!(applier.deferred(), 1);
+
+        // The saved position stays before the deferred record, so a crash
+        // reads it again
+        applier.apply(&writer, vec![change(1, "new.txt", ChangeType::Modify)], true, 2048);
+        assert_eq!(applier.position(4096), 1024);
+
+        // A quiet poll applies what was deferred; the deleted file stays deleted
+        let stats = applier.apply(&writer, Vec::new(), false, 4096);
+        assert_eq!((stats.applied, stats.deferred), (2, 0));
+        assert_eq!(applier.position(4096), 4096);
+
+        // Records still deferred when the monitor exits are flushed
+        applier.apply(&writer, vec![change(102, "file2.txt", ChangeType::Modify)], true, 4096);
+        assert_eq!((applier.deferred(), applier.position(8192)), (1, 4096));
+        let stats = applier.flush(&writer);
+        assert_eq!((stats.changes, stats.applied, applier.deferred()), (1, 1, 0));
+        assert_eq!(applier.position(8192), 8192);
+        assert_eq!(applier.flush(&writer).changes, 0);
+
+        writer_thread.stop();
+        let db = open_database(&dir.join("index.db")).unwrap();
+        assert_eq!(get_file_count(db.conn(), Some(volume_id)).unwrap(), APPLY_CHUNK as i64 + 1);
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
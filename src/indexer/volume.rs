@@ -2,6 +2,8 @@
 //!
 //! This module detects available volumes and determines their filesystem type
 //! to choose the appropriate indexing strategy (MFT for NTFS, walkdir for FAT).
+//! Volumes without a drive letter, mounted in a folder or not at all, are
+//! detected too and named by their mount point or volume GUID path.
 
 /// Filesystem type of a volume.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,11 +18,28 @@ pub enum VolumeType {
     Unknown,
 }
 
+impl VolumeType {
+    /// Filesystem type as the scanners record it in the index.
+    pub fn fs_label(&self) -> &'static str {
+        match self {
+            VolumeType::NTFS => "NTFS",
+            VolumeType::FAT32 | VolumeType::ExFAT => "FAT",
+            VolumeType::Unknown => "Unknown",
+        }
+    }
+}
+
 /// Information about a detected volume.
 #[derive(Debug, Clone)]
 pub struct VolumeInfo {
-    /// Drive letter (e.g., 'C')
-    pub drive_letter: char,
+    /// Drive letter (e.g., 'C'), if the volume has one
+    pub drive_letter: Option<char>,
+    /// Name the volume is indexed under: `C:` for lettered volumes, the
+    /// folder it is mounted at (e.g. `C:\Mount\Data`), or its volume GUID
+    /// path (`\\?\Volume{...}`) if it is not mounted anywhere
+    pub mount_point: String,
+    /// Volume GUID path with a trailing backslash, used for all access
+    pub device_path: String,
     /// Volume serial number as hex string
     pub volume_serial: String,
     /// Filesystem type
@@ -31,105 +50,207 @@ pub struct VolumeInfo {
     pub free_space: u64,
 }
 
+impl VolumeInfo {
+    /// Directory to walk or open for the volume's root: `C:\` for lettered
+    /// volumes, else the GUID path, which reaches every volume whether or
+    /// not it is mounted.
+    pub fn root_path(&self) -> String {
+        match self.drive_letter {
+            Some(letter) => format!("{}:\\", letter),
+            None => self.device_path.clone(),
+        }
+    }
+}
+
 /// Check if a volume is NTFS (supports MFT enumeration).
 pub fn is_ntfs(info: &VolumeInfo) -> bool {
     info.fs_type == VolumeType::NTFS
 }
 
+/// Choose the drive letter and index name of a volume from its mount points.
+///
+/// A drive letter wins over folder mount points, and a folder mount point
+/// over the GUID path, so volumes that have letters keep their `C:` names.
+///
+/// # Arguments
+/// * `device_path` - Volume GUID path, e.g. `\\?\Volume{...}\`
+/// * `mount_points` - Paths the volume is mounted at, each ending in `\`
+///
+/// # Returns
+/// The drive letter, if any, and the name without a trailing backslash.
+pub fn volume_name(device_path: &str, mount_points: &[String]) -> (Option<char>, String) {
+    let letter = mount_points.iter().find_map(|mount| {
+        let mut chars = mount.chars();
+        match (chars.next(), chars.next(), chars.as_str()) {
+            (Some(letter), Some(':'), "\\" | "") if letter.is_ascii_alphabetic() => {
+                Some(letter.to_ascii_uppercase())
+            }
+            _ => None,
+        }
+    });
+
+    let name = match (letter, mount_points.first()) {
+        (Some(letter), _) => format!("{}:", letter),
+        (None, Some(mount)) => mount.trim_end_matches('\\').to_string(),
+        (None, None) => device_path.trim_end_matches('\\').to_string(),
+    };
+    (letter, name)
+}
+
+/// Split a double-NUL-terminated list of UTF-16 strings.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn split_multi_sz(buffer: &[u16]) -> Vec<String> {
+    buffer
+        .split(|&c| c == 0)
+        .take_while(|s| !s.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect()
+}
+
 /// Detect all available volumes on the system.
 ///
-/// On Windows, iterates through drive letters A-Z and queries each for
-/// filesystem information using Windows API calls.
+/// On Windows, enumerates every volume with `FindFirstVolumeW`, including
+/// volumes mounted in folders or not mounted at all, and queries each for
+/// filesystem information through its volume GUID path.
 ///
 /// On non-Windows platforms, returns an empty vector.
 #[cfg(windows)]
 pub fn detect_volumes() -> Vec<VolumeInfo> {
+    use windows::Win32::Storage::FileSystem::{FindFirstVolumeW, FindNextVolumeW, FindVolumeClose};
+
+    let mut volumes = Vec::new();
+    let mut name_buf = [0u16; 64];
+
+    let handle = match unsafe { FindFirstVolumeW(&mut name_buf) } {
+        Ok(handle) => handle,
+        Err(e) => {
+            tracing::warn!("Failed to enumerate volumes: {}", e);
+            return volumes;
+        }
+    };
+
+    loop {
+        let len = name_buf.iter().position(|&c| c == 0).unwrap_or(name_buf.len());
+        let device_path = String::from_utf16_lossy(&name_buf[..len]);
+        if let Some(volume) = query_volume(&device_path) {
+            volumes.push(volume);
+        }
+
+        name_buf.fill(0);
+        if unsafe { FindNextVolumeW(handle, &mut name_buf) }.is_err() {
+            break;
+        }
+    }
+
+    let _ = unsafe { FindVolumeClose(handle) };
+
+    // Lettered volumes first, in letter order, as before
+    volumes.sort_by_key(|v| (v.drive_letter.is_none(), v.mount_point.clone()));
+    volumes
+}
+
+/// Query the mount points, filesystem and size of a volume.
+///
+/// Returns `None` for volumes that cannot be queried (e.g. empty card
+/// readers or unformatted partitions).
+#[cfg(windows)]
+fn query_volume(device_path: &str) -> Option<VolumeInfo> {
     use std::ffi::OsStr;
     use std::os::windows::ffi::OsStrExt;
     use windows::Win32::Storage::FileSystem::{
-        GetDiskFreeSpaceExW, GetVolumeInformationW,
+        GetDiskFreeSpaceExW, GetVolumeInformationW, GetVolumePathNamesForVolumeNameW,
     };
     use windows::core::PCWSTR;
 
-    let mut volumes = Vec::new();
+    let root_wide: Vec<u16> = OsStr::new(device_path)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
 
-    for letter in 'A'..='Z' {
-        let root_path = format!("{}:\\", letter);
-        let root_wide: Vec<u16> = OsStr::new(&root_path)
-            .encode_wide()
-            .chain(std::iter::once(0))
-            .collect();
-
-        // Buffer for filesystem name
-        let mut fs_name_buf: [u16; 256] = [0; 256];
-        let mut volume_serial: u32 = 0;
-
-        // Get volume information
-        let result = unsafe {
-            GetVolumeInformationW(
-                PCWSTR::from_raw(root_wide.as_ptr()),
-                None,                          // Volume name buffer (we don't need it)
-                Some(&mut volume_serial),      // Serial number
-                None,                          // Max component length (we don't need it)
-                None,                          // Filesystem flags (we don't need it)
-                Some(&mut fs_name_buf),        // Filesystem name buffer
-            )
-        };
+    // Buffer for filesystem name
+    let mut fs_name_buf: [u16; 256] = [0; 256];
+    let mut volume_serial: u32 = 0;
 
-        if result.is_err() {
-            // Drive doesn't exist or isn't accessible, skip it
-            continue;
-        }
+    // Get volume information
+    let result = unsafe {
+        GetVolumeInformationW(
+            PCWSTR::from_raw(root_wide.as_ptr()),
+            None,                          // Volume name buffer (we don't need it)
+            Some(&mut volume_serial),      // Serial number
+            None,                          // Max component length (we don't need it)
+            None,                          // Filesystem flags (we don't need it)
+            Some(&mut fs_name_buf),        // Filesystem name buffer
+        )
+    };
 
-        // Parse filesystem name
-        let fs_name_len = fs_name_buf.iter().position(|&c| c == 0).unwrap_or(fs_name_buf.len());
-        let fs_name = String::from_utf16_lossy(&fs_name_buf[..fs_name_len]);
+    if result.is_err() {
+        // No media or not accessible, skip it
+        return None;
+    }
 
-        let fs_type = match fs_name.to_uppercase().as_str() {
-            "NTFS" => VolumeType::NTFS,
-            "FAT32" => VolumeType::FAT32,
-            "EXFAT" => VolumeType::ExFAT,
-            _ => VolumeType::Unknown,
+    // Drive letters and folders the volume is mounted at
+    let mut paths_buf = vec![0u16; 1024];
+    let mut needed: u32 = 0;
+    let mut paths = unsafe {
+        GetVolumePathNamesForVolumeNameW(PCWSTR::from_raw(root_wide.as_ptr()), Some(&mut paths_buf), &mut needed)
+    };
+    if paths.is_err() && needed as usize > paths_buf.len() {
+        paths_buf = vec![0u16; needed as usize];
+        paths = unsafe {
+            GetVolumePathNamesForVolumeNameW(PCWSTR::from_raw(root_wide.as_ptr()), Some(&mut paths_buf), &mut needed)
         };
+    }
+    let mount_points = if paths.is_ok() { split_multi_sz(&paths_buf) } else { Vec::new() };
+    let (drive_letter, mount_point) = volume_name(device_path, &mount_points);
 
-        // Get disk space information
-        let mut total_bytes: u64 = 0;
-        let mut free_bytes: u64 = 0;
-
-        let space_result = unsafe {
-            GetDiskFreeSpaceExW(
-                PCWSTR::from_raw(root_wide.as_ptr()),
-                None,                        // Free bytes available to caller
-                Some(&mut total_bytes),      // Total bytes
-                Some(&mut free_bytes),       // Total free bytes
-            )
-        };
+    // Parse filesystem name
+    let fs_name_len = fs_name_buf.iter().position(|&c| c == 0).unwrap_or(fs_name_buf.len());
+    let fs_name = String::from_utf16_lossy(&fs_name_buf[..fs_name_len]);
 
-        if space_result.is_err() {
-            // Could get volume info but not space info, use zeros
-            total_bytes = 0;
-            free_bytes = 0;
-        }
+    let fs_type = match fs_name.to_uppercase().as_str() {
+        "NTFS" => VolumeType::NTFS,
+        "FAT32" => VolumeType::FAT32,
+        "EXFAT" => VolumeType::ExFAT,
+        _ => VolumeType::Unknown,
+    };
 
-        volumes.push(VolumeInfo {
-            drive_letter: letter,
-            volume_serial: format!("{:08X}", volume_serial),
-            fs_type,
-            total_size: total_bytes,
-            free_space: free_bytes,
-        });
-
-        tracing::debug!(
-            "Detected volume {}: {} (serial: {:08X}, total: {} GB, free: {} GB)",
-            letter,
-            fs_name,
-            volume_serial,
-            total_bytes / 1_073_741_824,
-            free_bytes / 1_073_741_824
-        );
+    // Get disk space information
+    let mut total_bytes: u64 = 0;
+    let mut free_bytes: u64 = 0;
+
+    let space_result = unsafe {
+        GetDiskFreeSpaceExW(
+            PCWSTR::from_raw(root_wide.as_ptr()),
+            None,                        // Free bytes available to caller
+            Some(&mut total_bytes),      // Total bytes
+            Some(&mut free_bytes),       // Total free bytes
+        )
+    };
+
+    if space_result.is_err() {
+        // Could get volume info but not space info, use zeros
+        total_bytes = 0;
+        free_bytes = 0;
     }
 
-    volumes
+    tracing::debug!(
+        "Detected volume {}: {} (serial: {:08X}, total: {} GB, free: {} GB)",
+        mount_point,
+        fs_name,
+        volume_serial,
+        total_bytes / 1_073_741_824,
+        free_bytes / 1_073_741_824
+    );
+
+    Some(VolumeInfo {
+        drive_letter,
+        mount_point,
+        device_path: device_path.to_string(),
+        volume_serial: format!("{:08X}", volume_serial),
+        fs_type,
+        total_size: total_bytes,
+        free_space: free_bytes,
+    })
 }
 
 /// Stub for non-Windows platforms - returns empty list.
@@ -146,7 +267,9 @@ mod tests {
     #[test]
     fn test_is_ntfs() {
         let ntfs_volume = VolumeInfo {
-            drive_letter: 'C',
+            drive_letter: Some('C'),
+            mount_point: "C:".to_string(),
+            device_path: r"\\?\Volume{00000000-0000-0000-0000-000000000001}\".to_string(),
             volume_serial: "12345678".to_string(),
             fs_type: VolumeType::NTFS,
             total_size: 1_000_000_000,
@@ -155,7 +278,9 @@ mod tests {
         assert!(is_ntfs(&ntfs_volume));
 
         let fat32_volume = VolumeInfo {
-            drive_letter: 'D',
+            drive_letter: None,
+            mount_point: r"C:\Mount\Card".to_string(),
+            device_path: r"\\?\Volume{00000000-0000-0000-0000-000000000002}\".to_string(),
             volume_serial: "ABCDEF01".to_string(),
             fs_type: VolumeType::FAT32,
             total_size: 100_000_000,
@@ -164,6 +289,33 @@ mod tests {
         assert!(!is_ntfs(&fat32_volume));
     }
 
+    #[test]
+    fn test_volume_name() {
+        let guid = r"\\?\Volume{3e2f6a10-5b7c-4f7e-9a1d-2c8b7e6f5a41}\";
+        let paths = |list: &[&str]| list.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(volume_name(guid, &paths(&[r"C:\"])), (Some('C'), "C:".to_string()));
+        assert_eq!(
+            volume_name(guid, &paths(&[r"D:\Mount\Data\", r"e:\"])),
+            (Some('E'), "E:".to_string())
+        );
+        assert_eq!(
+            volume_name(guid, &paths(&[r"D:\Mount\Data\"])),
+            (None, r"D:\Mount\Data".to_string())
+        );
+        assert_eq!(
+            volume_name(guid, &[]),
+            (None, r"\\?\Volume{3e2f6a10-5b7c-4f7e-9a1d-2c8b7e6f5a41}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_split_multi_sz() {
+        let buffer: Vec<u16> = "C:\\\0D:\\Mount\\\0\0\0".encode_utf16().collect();
+        assert_eq!(split_multi_sz(&buffer), vec![r"C:\".to_string(), r"D:\Mount\".to_string()]);
+        assert!(split_multi_sz(&[0, 0]).is_empty());
+    }
+
     #[test]
     fn test_volume_type_equality() {
         assert_eq!(VolumeType::NTFS, VolumeType::NTFS);
@@ -0,0 +1,130 @@
+//! Stopping IPC searches that run too long or whose client gave up.
+//!
+//! The server cancels a request's [`CancelToken`] when the client sends
+//! [`Command::Cancel`](crate::ipc::Command::Cancel) or disconnects, and the
+//! token expires at the request's deadline. [`run_cancellable`] interrupts
+//! SQLite work once either happens, so the database lock is released
+//! instead of being held by a runaway query.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rusqlite::Connection;
+
+use crate::{FFIError, Result};
+
+/// SQLite virtual machine instructions between cancellation checks.
+const PROGRESS_OPS: i32 = 10_000;
+
+/// Cancellation state of one request, shared with the task watching its client.
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Instant,
+}
+
+impl CancelToken {
+    /// Create a token that expires after `timeout`.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Instant::now() + timeout,
+        }
+    }
+
+    /// Cancel the request.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the request was cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.timed_out()
+    }
+
+    /// Whether the deadline has passed.
+    pub fn timed_out(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    /// Fail if the request was cancelled or timed out.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(cancelled());
+        }
+        Ok(())
+    }
+}
+
+/// Error returned for cancelled and timed out requests.
+fn cancelled() -> FFIError {
+    FFIError::Ipc("Request cancelled or timed out".to_string())
+}
+
+/// Run database work, interrupting its queries once the token is cancelled.
+///
+/// # Arguments
+/// * `conn` - Connection the work runs on (its lock must be held)
+/// * `token` - Cancellation state of the request
+/// * `work` - Queries to run
+///
+/// # Returns
+/// The work's result, or an error if the request was cancelled or timed out.
+pub fn run_cancellable<T>(conn: &Connection, token: &CancelToken, work: impl FnOnce() -> Result<T>) -> Result<T> {
+    token.check()?;
+
+    let watched = token.clone();
+    conn.progress_handler(PROGRESS_OPS, Some(move || watched.is_cancelled()))
+        .map_err(|e| FFIError::Database(format!("Failed to install progress handler: {}", e)))?;
+    let result = work();
+    conn.progress_handler(0, None::<fn() -> bool>)
+        .map_err(|e| FFIError::Database(format!("Failed to remove progress handler: {}", e)))?;
+
+    // Interrupted queries fail with a database error; report why instead
+    match result {
+        Err(_) if token.is_cancelled() => Err(cancelled()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A query that counts to a billion, far longer than any test waits.
+    fn runaway_query(conn: &Connection) -> Result<i64> {
+        conn.query_row(
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1000000000)
+             SELECT COUNT(*) FROM n",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| FFIError::Database(format!("Failed to count: {}", e)))
+    }
+
+    #[test]
+    fn test_run_cancellable() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        let token = CancelToken::new(Duration::from_secs(60));
+        let count = run_cancellable(&conn, &token, || {
+            conn.query_row("SELECT 42", [], |row| row.get::<_, i64>(0))
+                .map_err(|e| FFIError::Database(e.to_string()))
+        });
+        assert_eq!(count.unwrap(), 42);
+
+        // Runaway queries stop at the deadline
+        let token = CancelToken::new(Duration::from_millis(50));
+        let started = Instant::now();
+        let err = run_cancellable(&conn, &token, || runaway_query(&conn)).unwrap_err();
+        assert!(err.to_string().contains("cancelled or timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // Cancelled requests don't start, and the handler is removed afterwards
+        let token = CancelToken::new(Duration::from_secs(60));
+        token.cancel();
+        assert!(run_cancellable(&conn, &token, || Ok(())).is_err());
+        assert_eq!(conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0)).unwrap(), 1);
+    }
+}
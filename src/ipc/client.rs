@@ -1,13 +1,18 @@
 //! Named pipe client for the search UI.
 //!
-//! Connects to the FFI service to execute search queries.
-//! The client is stateless - it connects per request.
+//! Connects to the FFI service to execute search queries and control
+//! commands. The client is stateless - it connects per request, and opens
+//! each connection with a [`Hello`] so the service can check its protocol
+//! version.
 
-use tokio::net::windows::named_pipe::ClientOptions;
+use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeClient};
 
 use crate::ipc::protocol::{
-    read_message, write_message, SearchRequest, SearchResponse, PIPE_NAME,
+    read_export_stream, read_message, read_search_stream, write_message, Command, CommandResponse,
+    DetectedVolume, FileResult, Hello, HelloResponse, SearchRequest, SearchResponse, ServiceStatus, PIPE_NAME,
 };
+use crate::db::{ExportFormat, SavedSearch, UsageGroup, UsageRow};
+use crate::search::Ranking;
 use crate::{FFIError, Result};
 
 /// IPC client for sending search requests to the FFI service.
@@ -57,23 +62,32 @@ impl IpcClient {
         limit: usize,
         offset: usize,
     ) -> Result<SearchResponse> {
-        // Connect to named pipe
-        let mut client = ClientOptions::new().open(PIPE_NAME).map_err(|e| {
-            FFIError::Ipc(format!(
-                "Failed to connect to FFI service at {}: {}. Is the service running?",
-                PIPE_NAME, e
-            ))
-        })?;
-
-        // Build request
         let request = SearchRequest {
             query: query.to_string(),
             limit,
             offset,
+            count_queries: Vec::new(),
+            sort: Vec::new(),
+            show_all_links: false,
+            ranking: Ranking::default(),
+            stream: false,
         };
 
+        self.send_search(&request).await
+    }
+
+    /// Send a fully specified search request.
+    ///
+    /// Use this when the request needs options beyond query/limit/offset,
+    /// such as additional queries to count.
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn send_search(&self, request: &SearchRequest) -> Result<SearchResponse> {
+        let mut client = connect().await?;
+
         // Send request
-        write_message(&mut client, &request).await?;
+        write_message(&mut client, request).await?;
 
         // Read response
         let response: SearchResponse = read_message(&mut client).await?;
@@ -81,9 +95,317 @@ impl IpcClient {
         Ok(response)
     }
 
+    /// Send a search and receive its results in chunks as the service
+    /// resolves them.
+    ///
+    /// # Arguments
+    /// * `request` - Search request; it is sent with `stream` set
+    /// * `on_results` - Called with each chunk of results as it arrives
+    ///
+    /// # Returns
+    /// The complete response once the service has sent every chunk
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn stream_search<F>(&self, request: &SearchRequest, on_results: F) -> Result<SearchResponse>
+    where
+        F: FnMut(&[FileResult]),
+    {
+        let mut client = connect().await?;
+        let request = SearchRequest {
+            stream: true,
+            ..request.clone()
+        };
+        write_message(&mut client, &request).await?;
+        read_search_stream(&mut client, on_results).await
+    }
+
+    /// Send a control command (e.g., purge an offline volume).
+    ///
+    /// # Returns
+    /// The service's response; a failed command is reported in it, not as an error.
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn send_command(&self, command: &Command) -> Result<CommandResponse> {
+        let mut client = connect().await?;
+        write_message(&mut client, command).await?;
+        read_message(&mut client).await
+    }
+
+    /// Get the service's volume states, file counts and last scan times.
+    ///
+    /// # Errors
+    /// Returns error if communication fails or the service could not read its status
+    pub async fn get_status(&self) -> Result<ServiceStatus> {
+        let response = self.send_command(&Command::GetStatus).await?;
+        match response.status {
+            Some(status) if response.success => Ok(status),
+            _ => Err(FFIError::Ipc(response.message)),
+        }
+    }
+
+    /// Ask the service to rescan an NTFS volume in the background.
+    ///
+    /// # Arguments
+    /// * `drive_letter` - Volume to rescan (e.g., "C:")
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn trigger_rescan(&self, drive_letter: &str) -> Result<CommandResponse> {
+        self.send_command(&Command::TriggerRescan {
+            drive_letter: drive_letter.to_string(),
+        })
+        .await
+    }
+
+    /// Ask the service to walk one folder again and update its index.
+    ///
+    /// # Arguments
+    /// * `path` - Full path of the folder (e.g., "D:\\Photos")
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn rescan_path(&self, path: &str) -> Result<CommandResponse> {
+        self.send_command(&Command::RescanPath { path: path.to_string() }).await
+    }
+
+    /// Ask the service to re-read its configuration file.
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn reload_config(&self) -> Result<CommandResponse> {
+        self.send_command(&Command::ReloadConfig).await
+    }
+
+    /// Pause indexing; searches keep working.
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn pause_indexing(&self) -> Result<CommandResponse> {
+        self.send_command(&Command::PauseIndexing).await
+    }
+
+    /// Resume paused indexing.
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn resume_indexing(&self) -> Result<CommandResponse> {
+        self.send_command(&Command::ResumeIndexing).await
+    }
+
+    /// Change the service's log filter until it restarts.
+    ///
+    /// # Arguments
+    /// * `level` - A level or filter directives, as in `[logging] level`
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn set_log_level(&self, level: &str) -> Result<CommandResponse> {
+        self.send_command(&Command::SetLogLevel {
+            level: level.to_string(),
+        })
+        .await
+    }
+
+    /// List the saved searches, oldest first.
+    ///
+    /// # Errors
+    /// Returns error if communication fails or the service could not read them
+    pub async fn saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        self.saved_search_command(&Command::ListSavedSearches).await
+    }
+
+    /// Save a named query, replacing one with the same name.
+    ///
+    /// # Arguments
+    /// * `name` - Name shown in the search UI
+    /// * `query` - Search query, as typed
+    ///
+    /// # Returns
+    /// The saved searches after the change
+    ///
+    /// # Errors
+    /// Returns error if communication fails, or the name is empty or the query invalid
+    pub async fn save_search(&self, name: &str, query: &str) -> Result<Vec<SavedSearch>> {
+        self.saved_search_command(&Command::SaveSearch {
+            name: name.to_string(),
+            query: query.to_string(),
+        })
+        .await
+    }
+
+    /// Delete a saved search.
+    ///
+    /// # Returns
+    /// The saved searches after the change
+    ///
+    /// # Errors
+    /// Returns error if communication fails or no search has that name
+    pub async fn delete_saved_search(&self, name: &str) -> Result<Vec<SavedSearch>> {
+        self.saved_search_command(&Command::DeleteSavedSearch { name: name.to_string() })
+            .await
+    }
+
+    /// Send a saved search command and return the searches it replies with.
+    async fn saved_search_command(&self, command: &Command) -> Result<Vec<SavedSearch>> {
+        let response = self.send_command(command).await?;
+        match response.saved_searches {
+            Some(saved) if response.success => Ok(saved),
+            _ => Err(FFIError::Ipc(response.message)),
+        }
+    }
+
+    /// List the tags of an indexed file or folder.
+    ///
+    /// # Arguments
+    /// * `path` - Full path of the file
+    ///
+    /// # Errors
+    /// Returns error if communication fails or the file is not indexed
+    pub async fn tags(&self, path: &str) -> Result<Vec<String>> {
+        self.tag_command(&Command::GetTags { path: path.to_string() }).await
+    }
+
+    /// Attach tags to an indexed file or folder.
+    ///
+    /// # Returns
+    /// The file's tags after the change
+    ///
+    /// # Errors
+    /// Returns error if communication fails, the file is not indexed or no tag is valid
+    pub async fn add_tags(&self, path: &str, tags: &[String]) -> Result<Vec<String>> {
+        self.tag_command(&Command::AddTags {
+            path: path.to_string(),
+            tags: tags.to_vec(),
+        })
+        .await
+    }
+
+    /// Remove tags from an indexed file or folder.
+    ///
+    /// # Returns
+    /// The file's tags after the change
+    ///
+    /// # Errors
+    /// Returns error if communication fails or the file is not indexed
+    pub async fn remove_tags(&self, path: &str, tags: &[String]) -> Result<Vec<String>> {
+        self.tag_command(&Command::RemoveTags {
+            path: path.to_string(),
+            tags: tags.to_vec(),
+        })
+        .await
+    }
+
+    /// Send a tag command and return the tags it replies with.
+    async fn tag_command(&self, command: &Command) -> Result<Vec<String>> {
+        let response = self.send_command(command).await?;
+        match response.tags {
+            Some(tags) if response.success => Ok(tags),
+            _ => Err(FFIError::Ipc(response.message)),
+        }
+    }
+
+    /// Sum the sizes of the files matching a query by extension or folder.
+    ///
+    /// # Arguments
+    /// * `query` - Search query; its `path:` filter is the folder whose
+    ///   subfolders are summed
+    /// * `group_by` - What sizes are summed by
+    /// * `limit` - Most groups returned
+    ///
+    /// # Returns
+    /// The largest groups first
+    ///
+    /// # Errors
+    /// Returns error if communication fails, the query is invalid or times out
+    pub async fn aggregate(&self, query: &str, group_by: UsageGroup, limit: usize) -> Result<Vec<UsageRow>> {
+        let response = self
+            .send_command(&Command::Aggregate {
+                query: query.to_string(),
+                group_by,
+                limit,
+            })
+            .await?;
+        match response.usage {
+            Some(usage) if response.success => Ok(usage),
+            _ => Err(FFIError::Ipc(response.message)),
+        }
+    }
+
+    /// List the files modified most recently, newest first.
+    ///
+    /// # Arguments
+    /// * `since` - Unix timestamp; only files modified at or after it are listed
+    /// * `limit` - Most files returned
+    ///
+    /// # Errors
+    /// Returns error if communication fails or the query times out
+    pub async fn recent_files(&self, since: i64, limit: usize) -> Result<Vec<FileResult>> {
+        let response = self.send_command(&Command::RecentFiles { since, limit }).await?;
+        match response.files {
+            Some(files) if response.success => Ok(files),
+            _ => Err(FFIError::Ipc(response.message)),
+        }
+    }
+
+    /// List the volumes the service sees and whether each is indexed.
+    ///
+    /// # Errors
+    /// Returns error if communication fails or the service could not read
+    /// its configuration
+    pub async fn list_detected_volumes(&self) -> Result<Vec<DetectedVolume>> {
+        let response = self.send_command(&Command::ListDetectedVolumes).await?;
+        match response.detected_volumes {
+            Some(volumes) if response.success => Ok(volumes),
+            _ => Err(FFIError::Ipc(response.message)),
+        }
+    }
+
+    /// Turn indexing of a volume on or off in the service's configuration.
+    ///
+    /// # Arguments
+    /// * `volume` - Volume name as listed by [`Self::list_detected_volumes`]
+    /// * `enabled` - Whether the volume is indexed
+    ///
+    /// # Errors
+    /// Returns error if connection fails or communication error occurs
+    pub async fn set_volume_enabled(&self, volume: &str, enabled: bool) -> Result<CommandResponse> {
+        self.send_command(&Command::SetVolumeEnabled {
+            volume: volume.to_string(),
+            enabled,
+        })
+        .await
+    }
+
+    /// Export every match of a query.
+    ///
+    /// # Arguments
+    /// * `query` - Search query string
+    /// * `format` - Output format
+    /// * `out` - Destination of the exported text, written as it arrives
+    ///
+    /// # Returns
+    /// Number of matches exported
+    ///
+    /// # Errors
+    /// Returns error if communication fails, writing fails or the service
+    /// could not finish the export
+    pub async fn export<W: std::io::Write>(&self, query: &str, format: ExportFormat, out: &mut W) -> Result<usize> {
+        let mut client = connect().await?;
+        let command = Command::Export {
+            query: query.to_string(),
+            format,
+        };
+        write_message(&mut client, &command).await?;
+        read_export_stream(&mut client, out).await
+    }
+
     /// Check if the FFI service is available.
     ///
-    /// Attempts to connect to the named pipe without sending a request.
+    /// Attempts to connect to the named pipe without sending a request;
+    /// the service takes a connection closed before its handshake as a
+    /// quiet disconnect.
     ///
     /// # Returns
     /// true if the service is reachable, false otherwise
@@ -93,6 +415,30 @@ impl IpcClient {
     }
 }
 
+/// Connect to the service's named pipe and complete the handshake.
+async fn connect() -> Result<NamedPipeClient> {
+    let mut client = ClientOptions::new().open(PIPE_NAME).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            FFIError::Ipc(format!(
+                "Access to the FFI service at {} was denied; ask an administrator to add your account to [ipc] allowed_sids",
+                PIPE_NAME
+            ))
+        } else {
+            FFIError::Ipc(format!(
+                "Failed to connect to FFI service at {}: {}. Is the service running?",
+                PIPE_NAME, e
+            ))
+        }
+    })?;
+
+    write_message(&mut client, &Hello::current()).await?;
+    let response: HelloResponse = read_message(&mut client).await?;
+    if !response.accepted {
+        return Err(FFIError::Ipc(response.message));
+    }
+    Ok(client)
+}
+
 impl Default for IpcClient {
     fn default() -> Self {
         Self::new()
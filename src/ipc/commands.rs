@@ -0,0 +1,692 @@
+//! Control command handling for the IPC server.
+//!
+//! Kept separate from the named pipe server so commands can be executed
+//! (and tested) against any database connection. Rescans of volumes and
+//! folders, and pausing, act on
+//! the indexing threads of the current process, and log level changes on
+//! its log; reloading the
+//! configuration needs the running server and is handled there. Enabling a
+//! volume writes `config.toml`, which the service's config watcher then
+//! applies like any other edit.
+
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use crate::db::{
+    add_tags, delete_saved_search, delete_volume, disk_usage, get_all_volumes, get_file_ref_by_path,
+    get_last_maintenance, get_last_pruning, get_saved_searches, get_scan_checkpoint, get_tags, get_volume,
+    get_volume_state, get_volume_stats, record_open, remove_tags, save_search, set_volume_kept, UsageGroup, UsageRow,
+    VolumeInfo,
+};
+use crate::indexer::{
+    detect_volumes, is_indexing_paused, is_job_pool_running, pause_indexing, request_path_rescan, request_rescan,
+    resume_indexing, IndexingProgress, VolumeProgress,
+};
+use crate::ipc::protocol::{Command, CommandResponse, DetectedVolume, ServiceStatus, VolumeStatus};
+use crate::search::{parse_query, syntax_help};
+use crate::service::config::Config;
+use crate::service::set_log_level;
+use crate::{FFIError, Result, VolumeState};
+
+/// Execute a control command.
+///
+/// Failures are reported in the response rather than as errors, so the
+/// client always gets an answer.
+pub fn execute_command(conn: &mut Connection, command: &Command) -> CommandResponse {
+    let result = match command {
+        Command::KeepVolume { drive_letter, keep } => keep_volume(conn, drive_letter, *keep),
+        Command::PurgeVolume { drive_letter } => purge_volume(conn, drive_letter),
+        Command::RecordOpen { path } => {
+            record_open(conn, path, chrono::Utc::now().timestamp()).map(|()| format!("Recorded {}", path))
+        }
+        Command::GetSyntaxHelp => {
+            return CommandResponse {
+                syntax: Some(syntax_help()),
+                ..CommandResponse::from_result(Ok("Search syntax".to_string()))
+            };
+        }
+        Command::GetStatus => {
+            return match service_status(conn) {
+                Ok(status) => CommandResponse {
+                    status: Some(status),
+                    ..CommandResponse::from_result(Ok("Service status".to_string()))
+                },
+                Err(e) => CommandResponse::from_result(Err(e)),
+            };
+        }
+        Command::TriggerRescan { drive_letter } => trigger_rescan(conn, drive_letter),
+        Command::RescanPath { path } => rescan_path(conn, path),
+        Command::ReloadConfig => Err(FFIError::Ipc(
+            "The configuration can only be reloaded by the running service".to_string(),
+        )),
+        Command::PauseIndexing => {
+            pause_indexing();
+            Ok("Indexing paused".to_string())
+        }
+        Command::ResumeIndexing => {
+            resume_indexing();
+            Ok("Indexing resumed".to_string())
+        }
+        Command::SetLogLevel { level } => set_log_level(level).map(|()| format!("Log level set to {}", level)),
+        Command::Cancel => Err(FFIError::Ipc("No search is running on this connection".to_string())),
+        Command::Export { .. } => Err(FFIError::Ipc(
+            "Exports are streamed only by the running service".to_string(),
+        )),
+        Command::RecentFiles { .. } => Err(FFIError::Ipc(
+            "Recent files are listed only by the running service".to_string(),
+        )),
+        Command::SaveSearch { name, query } => {
+            return saved_searches_response(conn, save_named_search(conn, name, query));
+        }
+        Command::ListSavedSearches => {
+            return saved_searches_response(conn, Ok("Saved searches".to_string()));
+        }
+        Command::DeleteSavedSearch { name } => {
+            let result = delete_saved_search(conn, name).and_then(|deleted| {
+                if deleted {
+                    Ok(format!("Deleted saved search {}", name))
+                } else {
+                    Err(FFIError::Ipc(format!("No saved search named {}", name)))
+                }
+            });
+            return saved_searches_response(conn, result);
+        }
+        Command::GetTags { path } | Command::AddTags { path, .. } | Command::RemoveTags { path, .. } => {
+            return tag_command(conn, path, command);
+        }
+        Command::Aggregate { query, group_by, limit } => {
+            return usage_response(aggregate_usage(conn, query, *group_by, *limit));
+        }
+        Command::ListDetectedVolumes => {
+            return match Config::load() {
+                Ok(config) => CommandResponse {
+                    detected_volumes: Some(detected_volumes(&config, &detect_volumes())),
+                    ..CommandResponse::from_result(Ok("Detected volumes".to_string()))
+                },
+                Err(e) => CommandResponse::from_result(Err(e)),
+            };
+        }
+        Command::SetVolumeEnabled { volume, enabled } => {
+            set_volume_enabled(&Config::config_path(), volume, *enabled)
+        }
+    };
+
+    CommandResponse::from_result(result)
+}
+
+/// Describe the volumes on this machine and whether they are indexed.
+///
+/// # Arguments
+/// * `config` - Configuration deciding which volumes are enabled
+/// * `volumes` - Volumes as detected
+pub fn detected_volumes(config: &Config, volumes: &[crate::indexer::VolumeInfo]) -> Vec<DetectedVolume> {
+    volumes
+        .iter()
+        .map(|volume| DetectedVolume {
+            name: volume.mount_point.clone(),
+            fs_type: volume.fs_type.fs_label().to_string(),
+            total_size: volume.total_size,
+            free_space: volume.free_space,
+            enabled: config.is_volume_enabled(volume.mount_point.as_str()),
+            configured: config.is_volume_configured(volume.mount_point.as_str()),
+        })
+        .collect()
+}
+
+/// Turn indexing of a volume on or off in a configuration file.
+///
+/// The running service picks the change up through its config watcher.
+///
+/// # Arguments
+/// * `path` - Path to `config.toml`
+/// * `volume` - Volume name (`D:`, `D` or a mount folder)
+/// * `enabled` - Whether the volume is indexed
+fn set_volume_enabled(path: &Path, volume: &str, enabled: bool) -> Result<String> {
+    if volume.trim().is_empty() {
+        return Err(FFIError::Ipc("No volume given".to_string()));
+    }
+    Config::set_volume_enabled(path, volume, enabled)?;
+    Ok(format!(
+        "{} indexing of {}; the service picks it up shortly",
+        if enabled { "Enabled" } else { "Disabled" },
+        volume
+    ))
+}
+
+/// Collect each volume's state and the counters stored by its last scan,
+/// and the outcome of the last database maintenance and pruning.
+fn service_status(conn: &Connection) -> Result<ServiceStatus> {
+    let mut volumes = Vec::new();
+    for volume in get_all_volumes(conn)? {
+        let state = get_volume_state(conn, volume.id)?;
+        let stats = get_volume_stats(conn, volume.id)?;
+        // A scan running here, or else where an interrupted initial index stopped
+        let scan = match IndexingProgress::global().get(volume.id) {
+            Some(scan) => Some(scan),
+            None if state.progress().is_some() => {
+                let checkpoint = get_scan_checkpoint(conn, volume.id)?;
+                (checkpoint.processed > 0).then_some(VolumeProgress {
+                    processed: checkpoint.processed,
+                    total: checkpoint.total,
+                })
+            }
+            None => None,
+        };
+        volumes.push(VolumeStatus {
+            drive_letter: volume.drive_letter,
+            fs_type: volume.fs_type,
+            state: state.to_db_str().to_string(),
+            file_count: stats.file_count,
+            dir_count: stats.dir_count,
+            last_scan_time: stats.last_scan_time,
+            progress: state.progress().or(scan.map(|scan| scan.percent())),
+            scan,
+            truncated: stats.truncated,
+        });
+    }
+
+    Ok(ServiceStatus {
+        indexing_paused: is_indexing_paused(),
+        volumes,
+        maintenance: get_last_maintenance(conn)?,
+        pruning: get_last_pruning(conn)?,
+    })
+}
+
+/// Save a named query after checking that it parses.
+fn save_named_search(conn: &Connection, name: &str, query: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(FFIError::Ipc("A saved search needs a name".to_string()));
+    }
+    if query.trim().is_empty() {
+        return Err(FFIError::Ipc("A saved search needs a query".to_string()));
+    }
+    parse_query(query)?;
+
+    save_search(conn, name, query.trim(), chrono::Utc::now().timestamp())?;
+    Ok(format!("Saved search {}", name))
+}
+
+/// Response to a saved search command, carrying the saved searches.
+fn saved_searches_response(conn: &Connection, result: Result<String>) -> CommandResponse {
+    match get_saved_searches(conn) {
+        Ok(saved) => CommandResponse {
+            saved_searches: Some(saved),
+            ..CommandResponse::from_result(result)
+        },
+        Err(e) => CommandResponse::from_result(result.and(Err(e))),
+    }
+}
+
+/// Run a tag command on the file at `path`; the reply carries its tags.
+fn tag_command(conn: &mut Connection, path: &str, command: &Command) -> CommandResponse {
+    let result = tagged_file(conn, path).and_then(|(serial, file_ref)| {
+        let message = match command {
+            Command::AddTags { tags, .. } => {
+                let tags = clean_tags(tags)?;
+                add_tags(conn, &serial, file_ref, &tags, chrono::Utc::now().timestamp())?;
+                format!("Tagged {} with {}", path, tags.join(", "))
+            }
+            Command::RemoveTags { tags, .. } => {
+                let removed = remove_tags(conn, &serial, file_ref, &clean_tags(tags)?)?;
+                format!("Removed {} tags from {}", removed, path)
+            }
+            _ => format!("Tags of {}", path),
+        };
+        Ok((message, get_tags(conn, &serial, file_ref)?))
+    });
+
+    match result {
+        Ok((message, tags)) => CommandResponse {
+            tags: Some(tags),
+            ..CommandResponse::from_result(Ok(message))
+        },
+        Err(e) => CommandResponse::from_result(Err(e)),
+    }
+}
+
+/// Most groups a [`Command::Aggregate`] returns.
+pub const MAX_USAGE_ROWS: usize = 1000;
+
+/// Sum the sizes of the files matching a query for [`Command::Aggregate`].
+///
+/// Only reads, so the server runs it on a reader connection.
+pub fn aggregate_usage(conn: &Connection, query: &str, group_by: UsageGroup, limit: usize) -> Result<Vec<UsageRow>> {
+    let parsed = parse_query(query)?;
+    disk_usage(conn, &parsed, group_by, limit.min(MAX_USAGE_ROWS))
+}
+
+/// Response to [`Command::Aggregate`], carrying the groups.
+pub fn usage_response(result: Result<Vec<UsageRow>>) -> CommandResponse {
+    match result {
+        Ok(usage) => CommandResponse {
+            usage: Some(usage),
+            ..CommandResponse::from_result(Ok("Disk usage".to_string()))
+        },
+        Err(e) => CommandResponse::from_result(Err(e)),
+    }
+}
+
+/// Find the indexed file at a full path, as tags key it.
+///
+/// # Returns
+/// The serial number of the file's volume and the file's reference.
+fn tagged_file(conn: &Connection, path: &str) -> Result<(String, i64)> {
+    let (volume, relative) = volume_of_path(conn, path)?;
+    if volume.volume_serial.is_empty() {
+        return Err(FFIError::Ipc(format!(
+            "{} has no serial number, so its files can't be tagged",
+            volume.drive_letter
+        )));
+    }
+
+    let relative = relative.replace('/', "\\");
+    let file_ref = get_file_ref_by_path(conn, volume.id, relative.trim_end_matches('\\'))?
+        .ok_or_else(|| FFIError::Ipc(format!("{} is not indexed", path)))?;
+    Ok((volume.volume_serial, file_ref))
+}
+
+/// Trim tags and drop empty and repeated ones (case-insensitive).
+///
+/// Tags are searched with `tag:"..."`, so they can't contain quotes.
+fn clean_tags(tags: &[String]) -> Result<Vec<String>> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|tag| tag.trim()).filter(|tag| !tag.is_empty()) {
+        if tag.contains('"') {
+            return Err(FFIError::Ipc(format!("Tags can't contain quotes: {}", tag)));
+        }
+        if !cleaned.iter().any(|seen| seen.eq_ignore_ascii_case(tag)) {
+            cleaned.push(tag.to_string());
+        }
+    }
+    if cleaned.is_empty() {
+        return Err(FFIError::Ipc("No tags given".to_string()));
+    }
+    Ok(cleaned)
+}
+
+/// Queue an online NTFS volume for a background rescan.
+fn trigger_rescan(conn: &Connection, drive_letter: &str) -> Result<String> {
+    let volume = find_volume(conn, drive_letter)?;
+    if volume.fs_type != "NTFS" {
+        return Err(FFIError::Ipc(format!(
+            "{} is {}; only NTFS volumes can be rescanned, others are reconciled periodically",
+            volume.drive_letter, volume.fs_type
+        )));
+    }
+
+    let state = get_volume_state(conn, volume.id)?;
+    if state != VolumeState::Online {
+        return Err(FFIError::Ipc(format!(
+            "{} is {}; only online volumes can be rescanned",
+            volume.drive_letter,
+            state.to_db_str()
+        )));
+    }
+    if !is_job_pool_running() {
+        return Err(FFIError::Ipc("Rescans are not available: the service is not indexing".to_string()));
+    }
+
+    let letter = volume.drive_letter.chars().next().unwrap_or_default();
+    request_rescan(conn, letter);
+    Ok(format!("Rescan of {} queued", volume.drive_letter))
+}
+
+/// Queue one folder of an online volume to be walked again.
+fn rescan_path(conn: &Connection, path: &str) -> Result<String> {
+    let (volume, relative) = volume_of_path(conn, path)?;
+    let state = get_volume_state(conn, volume.id)?;
+    if state != VolumeState::Online {
+        return Err(FFIError::Ipc(format!(
+            "{} is {}; only folders of online volumes can be rescanned",
+            volume.drive_letter,
+            state.to_db_str()
+        )));
+    }
+    if !request_path_rescan(&volume.drive_letter, Path::new(relative)) {
+        return Err(FFIError::Ipc("Rescans are not available: the service is not indexing".to_string()));
+    }
+    Ok(format!("Rescan of {} queued", path))
+}
+
+/// Find the indexed volume holding a full path.
+///
+/// Volumes mounted in a folder of another are named by that folder, so the
+/// longest matching name wins.
+///
+/// # Returns
+/// The volume, and the path relative to its root.
+fn volume_of_path<'a>(conn: &Connection, path: &'a str) -> Result<(VolumeInfo, &'a str)> {
+    get_all_volumes(conn)?
+        .into_iter()
+        .filter_map(|volume| {
+            let name = volume.drive_letter.trim_end_matches(['\\', '/']);
+            let prefix = path.get(..name.len()).filter(|prefix| prefix.eq_ignore_ascii_case(name))?;
+            let rest = &path[prefix.len()..];
+            if !rest.is_empty() && !rest.starts_with(['\\', '/']) {
+                return None;
+            }
+            Some((name.len(), volume, rest.trim_start_matches(['\\', '/'])))
+        })
+        .max_by_key(|(len, _, _)| *len)
+        .map(|(_, volume, rest)| (volume, rest))
+        .ok_or_else(|| FFIError::Ipc(format!("{} is not on an indexed volume", path)))
+}
+
+/// Set or clear the keep-forever flag on a volume.
+fn keep_volume(conn: &Connection, drive_letter: &str, keep: bool) -> Result<String> {
+    let volume = find_volume(conn, drive_letter)?;
+    set_volume_kept(conn, volume.id, keep)?;
+
+    Ok(if keep {
+        format!("{} will be kept while offline", volume.drive_letter)
+    } else {
+        format!("{} follows the normal offline retention", volume.drive_letter)
+    })
+}
+
+/// Delete an offline volume's index immediately.
+fn purge_volume(conn: &mut Connection, drive_letter: &str) -> Result<String> {
+    let volume = find_volume(conn, drive_letter)?;
+    if !matches!(get_volume_state(conn, volume.id)?, VolumeState::Offline { .. }) {
+        return Err(FFIError::Ipc(format!(
+            "{} is not offline; only offline volumes can be purged",
+            volume.drive_letter
+        )));
+    }
+
+    let deleted = delete_volume(conn, volume.id)?;
+    tracing::info!("Purged offline volume {} ({} files)", volume.drive_letter, deleted);
+    Ok(format!("Purged {} ({} files)", volume.drive_letter, deleted))
+}
+
+/// Look up a volume by drive letter, accepting `E` as well as `E:`.
+fn find_volume(conn: &Connection, drive_letter: &str) -> Result<VolumeInfo> {
+    let name = if drive_letter.len() == 1 {
+        format!("{}:", drive_letter.to_ascii_uppercase())
+    } else {
+        drive_letter.to_string()
+    };
+
+    get_volume(conn, &name)?
+        .ok_or_else(|| FFIError::Ipc(format!("Unknown volume {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        batch_insert_files, get_offline_volumes, insert_volume, run_maintenance, schema, update_volume_state, FileEntry,
+    };
+    use crate::indexer::VolumeType;
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let id = insert_volume(&conn, "E:", "1234", "FAT32").unwrap();
+        update_volume_state(&conn, id, VolumeState::Offline { since: 1_700_000_000 }).unwrap();
+        insert_volume(&conn, "C:", "5678", "NTFS").unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_keep_volume() {
+        let mut conn = setup_test_db();
+
+        let response = execute_command(
+            &mut conn,
+            &Command::KeepVolume {
+                drive_letter: "e".to_string(),
+                keep: true,
+            },
+        );
+        assert!(response.success, "{}", response.message);
+
+        let offline = get_offline_volumes(&conn, 7).unwrap();
+        assert_eq!(offline.len(), 1);
+        assert!(offline[0].keep_forever);
+        assert_eq!(offline[0].purge_after, None);
+    }
+
+    #[test]
+    fn test_purge_volume() {
+        let mut conn = setup_test_db();
+
+        // Online volumes are refused
+        let purge = |drive_letter: &str| Command::PurgeVolume {
+            drive_letter: drive_letter.to_string(),
+        };
+        assert!(!execute_command(&mut conn, &purge("C:")).success);
+        assert!(!execute_command(&mut conn, &purge("Z:")).success);
+
+        assert!(execute_command(&mut conn, &purge("E:")).success);
+        assert!(get_volume(&conn, "E:").unwrap().is_none());
+        assert!(get_volume(&conn, "C:").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_get_status() {
+        let mut conn = setup_test_db();
+
+        let response = execute_command(&mut conn, &Command::GetStatus);
+        assert!(response.success, "{}", response.message);
+        let status = response.status.unwrap();
+        let states: Vec<(&str, &str)> = status
+            .volumes
+            .iter()
+            .map(|v| (v.drive_letter.as_str(), v.state.as_str()))
+            .collect();
+        assert_eq!(states, vec![("C:", "online"), ("E:", "offline")]);
+        assert!(status.volumes[0].last_scan_time.is_some());
+        assert_eq!((status.maintenance, status.pruning), (None, None));
+
+        run_maintenance(&conn, 1_700_000_000).unwrap();
+        let status = execute_command(&mut conn, &Command::GetStatus).status.unwrap();
+        let maintenance = status.maintenance.unwrap();
+        assert_eq!(maintenance.ran_at, 1_700_000_000);
+        assert!(maintenance.is_healthy());
+    }
+
+    #[test]
+    fn test_pause_and_resume_indexing() {
+        let mut conn = setup_test_db();
+
+        assert!(execute_command(&mut conn, &Command::PauseIndexing).success);
+        let paused = execute_command(&mut conn, &Command::GetStatus).status.unwrap();
+        // Resume before asserting so other tests' scans are never left waiting
+        assert!(execute_command(&mut conn, &Command::ResumeIndexing).success);
+
+        assert!(paused.indexing_paused);
+        assert!(!is_indexing_paused());
+    }
+
+    #[test]
+    fn test_set_log_level() {
+        let mut conn = setup_test_db();
+
+        let set = |level: &str| Command::SetLogLevel { level: level.to_string() };
+        // Invalid filters are refused; valid ones need the service's logging
+        let response = execute_command(&mut conn, &set("loud"));
+        assert!(!response.success);
+        assert!(response.message.contains("Invalid log level"), "{}", response.message);
+        assert!(!execute_command(&mut conn, &set("debug")).success);
+    }
+
+    #[test]
+    fn test_trigger_rescan_refused() {
+        let mut conn = setup_test_db();
+
+        let rescan = |drive_letter: &str| Command::TriggerRescan {
+            drive_letter: drive_letter.to_string(),
+        };
+        // FAT and offline volumes, unknown volumes, and no rescan worker here
+        assert!(!execute_command(&mut conn, &rescan("E:")).success);
+        assert!(!execute_command(&mut conn, &rescan("Z:")).success);
+        let response = execute_command(&mut conn, &rescan("c"));
+        assert!(!response.success);
+        assert!(response.message.contains("not indexing"), "{}", response.message);
+        let volume = get_volume(&conn, "C:").unwrap().unwrap();
+        assert_eq!(get_volume_state(&conn, volume.id).unwrap(), VolumeState::Online);
+
+        assert!(!execute_command(&mut conn, &Command::ReloadConfig).success);
+    }
+
+    #[test]
+    fn test_rescan_path_refused() {
+        let mut conn = setup_test_db();
+        insert_volume(&conn, r"C:\Mount\Data", "9abc", "NTFS").unwrap();
+
+        let (volume, relative) = volume_of_path(&conn, r"c:\mount\data\Photos").unwrap();
+        assert_eq!(volume.drive_letter, r"C:\Mount\Data");
+        assert_eq!(relative, "Photos");
+        let (volume, relative) = volume_of_path(&conn, r"C:\Mount\Database").unwrap();
+        assert_eq!(volume.drive_letter, "C:");
+        assert_eq!(relative, r"Mount\Database");
+
+        let rescan = |path: &str| Command::RescanPath { path: path.to_string() };
+        // Offline volumes, unknown volumes, and no rescan worker here
+        assert!(!execute_command(&mut conn, &rescan(r"E:\Photos")).success);
+        assert!(!execute_command(&mut conn, &rescan(r"Z:\Photos")).success);
+        let response = execute_command(&mut conn, &rescan(r"C:\Users"));
+        assert!(!response.success);
+        assert!(response.message.contains("not indexing"), "{}", response.message);
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut conn = setup_test_db();
+        let entry = |file_ref: i64, parent_ref: i64, name: &str| FileEntry {
+            volume_id: 2,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            is_dir: file_ref != 20,
+            ..Default::default()
+        };
+        batch_insert_files(&mut conn, &[entry(5, 5, "."), entry(10, 5, "Docs"), entry(20, 10, "plan.txt")]).unwrap();
+
+        let tags = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let add = |path: &str, names: &[&str]| Command::AddTags { path: path.to_string(), tags: tags(names) };
+        let response = execute_command(&mut conn, &add(r"c:\docs\PLAN.txt", &[" work ", "Q3", "WORK", ""]));
+        assert!(response.success, "{}", response.message);
+        assert_eq!(response.tags, Some(tags(&["Q3", "work"])));
+
+        // Refused: no tags, quotes, files not indexed
+        assert!(!execute_command(&mut conn, &add(r"C:\Docs\plan.txt", &[" "])).success);
+        assert!(!execute_command(&mut conn, &add(r"C:\Docs\plan.txt", &["say \"hi\""])).success);
+        assert!(!execute_command(&mut conn, &add(r"C:\Docs\missing.txt", &["work"])).success);
+
+        // Tags follow the disk to another drive letter
+        conn.execute("UPDATE volumes SET drive_letter = 'D:' WHERE id = 2", []).unwrap();
+        let remove = Command::RemoveTags { path: "D:/Docs/plan.txt".to_string(), tags: tags(&["q3"]) };
+        let response = execute_command(&mut conn, &remove);
+        assert!(response.success, "{}", response.message);
+        assert_eq!(response.tags, Some(tags(&["work"])));
+        let response = execute_command(&mut conn, &Command::GetTags { path: r"D:\Docs\".to_string() });
+        assert_eq!(response.tags, Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_saved_searches() {
+        let mut conn = setup_test_db();
+
+        let save = |name: &str, query: &str| Command::SaveSearch {
+            name: name.to_string(),
+            query: query.to_string(),
+        };
+        let response = execute_command(&mut conn, &save(" Big installers ", "ext:msi;exe size:>100mb"));
+        assert!(response.success, "{}", response.message);
+        let saved = response.saved_searches.unwrap();
+        assert_eq!((saved[0].name.as_str(), saved.len()), ("Big installers", 1));
+
+        // Unnamed, empty and invalid queries are refused
+        assert!(!execute_command(&mut conn, &save("", "ext:md")).success);
+        assert!(!execute_command(&mut conn, &save("Empty", " ")).success);
+        assert!(!execute_command(&mut conn, &save("Broken", "size:>")).success);
+
+        let delete = |name: &str| Command::DeleteSavedSearch { name: name.to_string() };
+        assert!(!execute_command(&mut conn, &delete("Notes")).success);
+        let response = execute_command(&mut conn, &delete("big installers"));
+        assert!(response.success, "{}", response.message);
+        assert_eq!(response.saved_searches, Some(Vec::new()));
+        assert!(execute_command(&mut conn, &Command::ListSavedSearches).saved_searches.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let mut conn = setup_test_db();
+        let entry = |file_ref: i64, parent_ref: i64, name: &str, size: i64| FileEntry {
+            volume_id: 2,
+            file_ref: Some(file_ref),
+            parent_ref: Some(parent_ref),
+            name: name.to_string(),
+            size,
+            is_dir: size == 0,
+            ..Default::default()
+        };
+        batch_insert_files(
+            &mut conn,
+            &[
+                entry(5, 5, ".", 0),
+                entry(10, 5, "Docs", 0),
+                entry(20, 10, "plan.txt", 300),
+                entry(21, 10, "a.pdf", 900),
+            ],
+        )
+        .unwrap();
+
+        let aggregate = |query: &str, limit: usize| Command::Aggregate {
+            query: query.to_string(),
+            group_by: UsageGroup::Extension,
+            limit,
+        };
+        let response = execute_command(&mut conn, &aggregate(r"path:C:\Docs", 1));
+        assert!(response.success, "{}", response.message);
+        assert_eq!(response.usage, Some(vec![UsageRow { key: "pdf".to_string(), files: 1, bytes: 900 }]));
+
+        let response = execute_command(&mut conn, &aggregate("size:>>", 10));
+        assert!(!response.success);
+        assert_eq!(response.usage, None);
+    }
+
+    #[test]
+    fn test_detected_volumes() {
+        let config: Config = toml::from_str("[volumes]\nC = {}\nD = { enabled = false }\n").unwrap();
+        let volume = |mount_point: &str, fs_type: VolumeType| crate::indexer::VolumeInfo {
+            drive_letter: mount_point.chars().next(),
+            mount_point: mount_point.to_string(),
+            device_path: String::new(),
+            volume_serial: "1234".to_string(),
+            fs_type,
+            total_size: 1000,
+            free_space: 400,
+        };
+        let detected = detected_volumes(
+            &config,
+            &[volume("C:", VolumeType::NTFS), volume("D:", VolumeType::NTFS), volume("E:", VolumeType::ExFAT)],
+        );
+        let flags: Vec<_> = detected.iter().map(|v| (v.name.as_str(), v.enabled, v.configured)).collect();
+        assert_eq!(flags, vec![("C:", true, true), ("D:", false, true), ("E:", false, false)]);
+        assert_eq!(detected[2].fs_type, "FAT");
+
+        let dir = std::env::temp_dir().join("ffi_test_set_volume_command");
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("config.toml");
+        assert!(set_volume_enabled(&path, "E:", true).unwrap().starts_with("Enabled indexing of E:"));
+        assert!(Config::load_from(&path).unwrap().is_volume_enabled('E'));
+        assert!(set_volume_enabled(&path, " ", true).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_syntax_help() {
+        let mut conn = setup_test_db();
+
+        let response = execute_command(&mut conn, &Command::GetSyntaxHelp);
+        assert!(response.success);
+        let syntax = response.syntax.unwrap();
+        assert!(syntax.filters.iter().any(|f| f.name == "ext"));
+    }
+}
@@ -2,8 +2,13 @@
 //!
 //! Uses Windows named pipes for efficient, secure local IPC.
 //! The service runs a named pipe server, and the UI connects as a client.
+//! Only the accounts in `[ipc] allowed_sids` may open the pipe (see
+//! [`security`]).
 
+pub mod cancel;
+pub mod commands;
 pub mod protocol;
+pub mod security;
 
 #[cfg(windows)]
 pub mod server;
@@ -45,6 +50,124 @@ impl IpcClient {
         Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
     }
 
+    /// Send search request stub - returns error on non-Windows.
+    pub async fn send_search(&self, _request: &SearchRequest) -> crate::Result<SearchResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Stream search stub - returns error on non-Windows.
+    pub async fn stream_search<F>(&self, _request: &SearchRequest, _on_results: F) -> crate::Result<SearchResponse>
+    where
+        F: FnMut(&[FileResult]),
+    {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Send command stub - returns error on non-Windows.
+    pub async fn send_command(&self, _command: &Command) -> crate::Result<CommandResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Get status stub - returns error on non-Windows.
+    pub async fn get_status(&self) -> crate::Result<ServiceStatus> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Trigger rescan stub - returns error on non-Windows.
+    pub async fn trigger_rescan(&self, _drive_letter: &str) -> crate::Result<CommandResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Rescan path stub - returns error on non-Windows.
+    pub async fn rescan_path(&self, _path: &str) -> crate::Result<CommandResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Reload config stub - returns error on non-Windows.
+    pub async fn reload_config(&self) -> crate::Result<CommandResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Pause indexing stub - returns error on non-Windows.
+    pub async fn pause_indexing(&self) -> crate::Result<CommandResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Resume indexing stub - returns error on non-Windows.
+    pub async fn resume_indexing(&self) -> crate::Result<CommandResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Set log level stub - returns error on non-Windows.
+    pub async fn set_log_level(&self, _level: &str) -> crate::Result<CommandResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Saved searches stub - returns error on non-Windows.
+    pub async fn saved_searches(&self) -> crate::Result<Vec<crate::db::SavedSearch>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Save search stub - returns error on non-Windows.
+    pub async fn save_search(&self, _name: &str, _query: &str) -> crate::Result<Vec<crate::db::SavedSearch>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Delete saved search stub - returns error on non-Windows.
+    pub async fn delete_saved_search(&self, _name: &str) -> crate::Result<Vec<crate::db::SavedSearch>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Tags stub - returns error on non-Windows.
+    pub async fn tags(&self, _path: &str) -> crate::Result<Vec<String>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Add tags stub - returns error on non-Windows.
+    pub async fn add_tags(&self, _path: &str, _tags: &[String]) -> crate::Result<Vec<String>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Remove tags stub - returns error on non-Windows.
+    pub async fn remove_tags(&self, _path: &str, _tags: &[String]) -> crate::Result<Vec<String>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Aggregate stub - returns error on non-Windows.
+    pub async fn aggregate(
+        &self,
+        _query: &str,
+        _group_by: crate::db::UsageGroup,
+        _limit: usize,
+    ) -> crate::Result<Vec<crate::db::UsageRow>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Recent files stub - returns error on non-Windows.
+    pub async fn recent_files(&self, _since: i64, _limit: usize) -> crate::Result<Vec<FileResult>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// List detected volumes stub - returns error on non-Windows.
+    pub async fn list_detected_volumes(&self) -> crate::Result<Vec<DetectedVolume>> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Set volume enabled stub - returns error on non-Windows.
+    pub async fn set_volume_enabled(&self, _volume: &str, _enabled: bool) -> crate::Result<CommandResponse> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
+    /// Export stub - returns error on non-Windows.
+    pub async fn export<W: std::io::Write>(
+        &self,
+        _query: &str,
+        _format: crate::db::ExportFormat,
+        _out: &mut W,
+    ) -> crate::Result<usize> {
+        Err(crate::FFIError::Ipc("IPC only supported on Windows".to_string()))
+    }
+
     /// Check if service is available (always false on non-Windows).
     pub fn is_service_available(&self) -> bool {
         false
@@ -1,17 +1,466 @@
-//! IPC protocol types for search requests and responses.
+//! IPC protocol types for searches, status queries and service control.
 //!
 //! Uses length-prefixed JSON messages for reliable framing over named pipes.
 //! Format: 4-byte little-endian length prefix followed by JSON bytes.
+//!
+//! A client opens each connection with a [`Hello`] carrying its protocol
+//! version and name; the service answers with a [`HelloResponse`] before
+//! reading the request. Clients that skip it are refused.
+//!
+//! Search-as-you-type opens a connection per keystroke, so framing reuses
+//! pooled buffers instead of allocating one per message. Searches can also
+//! be answered as a stream of [`SearchFrame`]s, so the first matches show
+//! before every path has been resolved.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
 
 use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use crate::{FFIError, Result};
+use crate::db::{ExportFormat, MaintenanceReport, PruneReport, SavedSearch, UsageGroup, UsageRow};
+use crate::indexer::VolumeProgress;
+use crate::search::{Ranking, SortSpec, SyntaxHelp};
+use crate::{FFIError, Result, VolumeState};
 
 /// Named pipe path for the FFI search service.
 /// Uses Windows named pipe format: \\.\pipe\<name>
 pub const PIPE_NAME: &str = r"\\.\pipe\FFI_Search";
 
+/// Version of this protocol, sent in [`Hello`]. Bumped when a change
+/// would break clients; the service serves clients up to its own version.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Largest message accepted, to reject corrupt length prefixes.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Number of idle framing buffers kept for reuse.
+const BUFFER_POOL_SIZE: usize = 8;
+
+/// Buffers that grew beyond this are freed rather than pooled, so one
+/// large response doesn't keep its memory alive.
+const MAX_POOLED_CAPACITY: usize = 1024 * 1024;
+
+/// Idle framing buffers shared by all connections.
+static BUFFER_POOL: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+/// Framing buffer taken from the pool and returned, emptied, when dropped.
+struct PooledBuffer(Vec<u8>);
+
+impl PooledBuffer {
+    /// Take an idle buffer, or allocate one if the pool is empty.
+    fn take() -> Self {
+        let buf = BUFFER_POOL.lock().ok().and_then(|mut pool| pool.pop());
+        Self(buf.unwrap_or_default())
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if self.0.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+        if let Ok(mut pool) = BUFFER_POOL.lock() {
+            if pool.len() < BUFFER_POOL_SIZE {
+                let mut buf = std::mem::take(&mut self.0);
+                buf.clear();
+                pool.push(buf);
+            }
+        }
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+}
+
+/// Message read by the service: a control command or a search.
+///
+/// Commands are tagged with `type`; anything else is a handshake or a
+/// search request, so clients that only search keep working unchanged.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Request {
+    /// Control command, answered with a [`CommandResponse`]
+    Command(Command),
+    /// Handshake before the request, answered with a [`HelloResponse`]
+    Hello(Hello),
+    /// Search, answered with a [`SearchResponse`]
+    Search(SearchRequest),
+}
+
+/// Control command from a client to the service.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Command {
+    /// Keep an offline volume's index forever, or return it to normal retention
+    KeepVolume {
+        /// Volume drive letter (e.g., "E:")
+        drive_letter: String,
+        /// Whether to keep the index
+        keep: bool,
+    },
+    /// Delete an offline volume's index now instead of after retention
+    PurgeVolume {
+        /// Volume drive letter (e.g., "E:")
+        drive_letter: String,
+    },
+    /// Record that the user opened a result, for frecency ranking
+    RecordOpen {
+        /// Full path of the opened file
+        path: String,
+    },
+    /// Describe the search syntax the service's parser accepts
+    GetSyntaxHelp,
+    /// Report volume states, file counts and last scan times
+    GetStatus,
+    /// Rescan an NTFS volume in the background, keeping it searchable
+    TriggerRescan {
+        /// Volume drive letter (e.g., "C:")
+        drive_letter: String,
+    },
+    /// Walk one folder again and bring its index up to date, instead of
+    /// rescanning the whole volume
+    RescanPath {
+        /// Full path of the folder (e.g., "D:\\Photos")
+        path: String,
+    },
+    /// Re-read the configuration file and apply the search settings
+    ReloadConfig,
+    /// Pause scans and USN monitoring until resumed; searches keep working
+    PauseIndexing,
+    /// Resume indexing paused by [`Command::PauseIndexing`]
+    ResumeIndexing,
+    /// Change the service's log filter until it restarts
+    SetLogLevel {
+        /// A level ("debug") or filter directives ("info,ffi::indexer=debug")
+        level: String,
+    },
+    /// Stop the search running on this connection; sent after its request.
+    /// Closing the connection does the same.
+    Cancel,
+    /// Export every match of a query, answered with [`ExportFrame`]s
+    Export {
+        /// Search query, as typed
+        query: String,
+        /// Output format
+        format: ExportFormat,
+    },
+    /// Save a named query, replacing one with the same name
+    SaveSearch {
+        /// Name shown in the search UI (e.g., "Big installers")
+        name: String,
+        /// Search query, as typed
+        query: String,
+    },
+    /// List the saved searches
+    ListSavedSearches,
+    /// Delete a saved search
+    DeleteSavedSearch {
+        /// Name of the search (case-insensitive)
+        name: String,
+    },
+    /// List the tags of an indexed file or folder
+    GetTags {
+        /// Full path of the file (e.g., "D:\\Docs\\plan.txt")
+        path: String,
+    },
+    /// Attach tags to an indexed file or folder
+    AddTags {
+        /// Full path of the file
+        path: String,
+        /// Tags to attach; tags the file already has are ignored
+        tags: Vec<String>,
+    },
+    /// Remove tags from an indexed file or folder
+    RemoveTags {
+        /// Full path of the file
+        path: String,
+        /// Tags to remove (case-insensitive)
+        tags: Vec<String>,
+    },
+    /// Sum the sizes of the files matching a query by extension or folder,
+    /// for a disk usage view. Hidden files and the Recycle Bin are counted.
+    Aggregate {
+        /// Search query selecting the files; its `path:` filter is the
+        /// folder whose subfolders are summed (e.g., "path:C:\\Users")
+        query: String,
+        /// What sizes are summed by
+        group_by: UsageGroup,
+        /// Most groups returned, largest first
+        limit: usize,
+    },
+    /// List the files modified most recently, newest first, for the view of
+    /// an empty search box. Hidden entries are left out as in searches.
+    RecentFiles {
+        /// Unix timestamp; only files modified at or after it are listed
+        since: i64,
+        /// Most files returned
+        limit: usize,
+    },
+    /// List the volumes the service can see, with whether each is enabled
+    /// for indexing, so a client can offer to enable them
+    ListDetectedVolumes,
+    /// Turn indexing of a volume on or off in the service's `config.toml`.
+    /// The running service applies the change like any edit of the file.
+    SetVolumeEnabled {
+        /// Volume name (e.g., "D:" or "C:\\Mount\\Data")
+        volume: String,
+        /// Whether the volume is indexed
+        enabled: bool,
+    },
+}
+
+impl Command {
+    /// Whether only administrators may send this command.
+    ///
+    /// Commands changing what the service indexes or how it runs are
+    /// refused to other clients; searches, status and the user's own data
+    /// (saved searches, tags, opened files) stay open to every client
+    /// allowed on the pipe.
+    pub fn requires_admin(&self) -> bool {
+        matches!(
+            self,
+            Command::KeepVolume { .. }
+                | Command::PurgeVolume { .. }
+                | Command::TriggerRescan { .. }
+                | Command::RescanPath { .. }
+                | Command::ReloadConfig
+                | Command::PauseIndexing
+                | Command::ResumeIndexing
+                | Command::SetLogLevel { .. }
+                | Command::SetVolumeEnabled { .. }
+        )
+    }
+}
+
+/// Result of a control command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommandResponse {
+    /// Whether the command succeeded
+    pub success: bool,
+    /// Human-readable outcome
+    pub message: String,
+    /// Search syntax, in reply to [`Command::GetSyntaxHelp`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub syntax: Option<SyntaxHelp>,
+    /// Service status, in reply to [`Command::GetStatus`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ServiceStatus>,
+    /// Saved searches after the change, in reply to the saved search commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_searches: Option<Vec<SavedSearch>>,
+    /// The file's tags after the change, in reply to the tag commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Space taken by each group, in reply to [`Command::Aggregate`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Vec<UsageRow>>,
+    /// Files, in reply to [`Command::RecentFiles`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<FileResult>>,
+    /// Volumes found on the machine, in reply to
+    /// [`Command::ListDetectedVolumes`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_volumes: Option<Vec<DetectedVolume>>,
+}
+
+impl CommandResponse {
+    /// Response carrying a command's outcome message or error.
+    pub fn from_result(result: Result<String>) -> Self {
+        let (success, message) = match result {
+            Ok(message) => (true, message),
+            Err(e) => (false, e.to_string()),
+        };
+        Self {
+            success,
+            message,
+            syntax: None,
+            status: None,
+            saved_searches: None,
+            tags: None,
+            usage: None,
+            files: None,
+            detected_volumes: None,
+        }
+    }
+}
+
+/// Handshake a client sends before its request on the same connection.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Hello {
+    /// Protocol version the client speaks ([`PROTOCOL_VERSION`])
+    pub protocol_version: u32,
+    /// Client program and version, for the service log (e.g. "ffi-ui 0.1.0")
+    pub client: String,
+}
+
+impl Hello {
+    /// Handshake for this process at the current protocol version.
+    pub fn current() -> Self {
+        let program = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "ffi".to_string());
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            client: format!("{} {}", program, env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// The service's answer: clients speaking a newer protocol are refused.
+    pub fn answer(&self) -> HelloResponse {
+        let accepted = (1..=PROTOCOL_VERSION).contains(&self.protocol_version);
+        let message = if accepted {
+            format!("FFI service {}", env!("CARGO_PKG_VERSION"))
+        } else {
+            format!(
+                "Client speaks protocol version {}, but the service only supports up to {}; update the service",
+                self.protocol_version, PROTOCOL_VERSION
+            )
+        };
+        HelloResponse {
+            protocol_version: PROTOCOL_VERSION,
+            accepted,
+            message,
+        }
+    }
+}
+
+/// Service reply to a [`Hello`]; the connection is closed if not accepted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HelloResponse {
+    /// Protocol version the service speaks
+    pub protocol_version: u32,
+    /// Whether the service will read the client's request
+    pub accepted: bool,
+    /// Service version, or why the client was refused
+    pub message: String,
+}
+
+impl HelloResponse {
+    /// Refusal of a client that sent its request without a [`Hello`].
+    pub fn missing() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            accepted: false,
+            message: format!(
+                "Client sent no protocol version, but the service requires protocol version 1 to {}; update the client",
+                PROTOCOL_VERSION
+            ),
+        }
+    }
+}
+
+/// State of the service's index, in reply to [`Command::GetStatus`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ServiceStatus {
+    /// Whether indexing is paused
+    pub indexing_paused: bool,
+    /// Indexed volumes, in drive letter order
+    pub volumes: Vec<VolumeStatus>,
+    /// Outcome of the last database maintenance run (None if none ran yet)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceReport>,
+    /// What the last pruning to the index size budget deleted (None if the
+    /// index was never pruned)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pruning: Option<PruneReport>,
+}
+
+/// One indexed volume in a [`ServiceStatus`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VolumeStatus {
+    /// Volume drive letter (e.g., "C:")
+    pub drive_letter: String,
+    /// Filesystem type ("NTFS", "FAT32", "exFAT")
+    pub fs_type: String,
+    /// Lifecycle state ("online", "offline", "indexing", "rescanning", ...)
+    pub state: String,
+    /// Files counted by the last full scan
+    pub file_count: i64,
+    /// Directories counted by the last full scan
+    pub dir_count: i64,
+    /// Unix timestamp of the last scan (None if never scanned)
+    pub last_scan_time: Option<i64>,
+    /// Share of the volume indexed so far while the state is "indexing" or
+    /// a full scan runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<u8>,
+    /// Entries the running or interrupted full scan has processed, and how
+    /// many it expects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan: Option<VolumeProgress>,
+    /// Limits that left entries out on the last full scan (e.g.
+    /// "max_files = 1000"), None if everything was indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<String>,
+}
+
+impl VolumeStatus {
+    /// State for display, with the progress of a full scan
+    /// (e.g. "indexing 42% (1.2M/2.9M files)") and the limits the volume
+    /// was cut short by (e.g. "online, limited by max_depth = 3").
+    pub fn state_label(&self) -> String {
+        let counts = match self.scan {
+            Some(scan) if scan.total > 0 => {
+                format!(" ({}/{} files)", short_count(scan.processed), short_count(scan.total))
+            }
+            Some(scan) => format!(" ({} files)", short_count(scan.processed)),
+            None => String::new(),
+        };
+        let limited = match &self.truncated {
+            Some(limits) => format!(", limited by {}", limits),
+            None => String::new(),
+        };
+        match self.progress {
+            Some(percent) => format!("{} {}%{}{}", self.state, percent, counts, limited),
+            None => format!("{}{}{}", self.state, counts, limited),
+        }
+    }
+}
+
+/// A volume found on the machine, in reply to
+/// [`Command::ListDetectedVolumes`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DetectedVolume {
+    /// Name the volume is indexed under (e.g., "C:" or "C:\\Mount\\Data")
+    pub name: String,
+    /// Filesystem type ("NTFS", "FAT")
+    pub fs_type: String,
+    /// Total size in bytes
+    pub total_size: u64,
+    /// Free space in bytes
+    pub free_space: u64,
+    /// Whether the volume is enabled under `[volumes]`
+    pub enabled: bool,
+    /// Whether the volume is listed under `[volumes]` at all, enabled or
+    /// not, so the user has already decided about it
+    pub configured: bool,
+}
+
+/// A count in at most four characters plus a unit ("950", "12.3K", "1.2M").
+fn short_count(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}K", count as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.1}M", count as f64 / 1e6),
+        _ => format!("{:.1}B", count as f64 / 1e9),
+    }
+}
+
 /// Search request from UI to service.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SearchRequest {
@@ -19,8 +468,28 @@ pub struct SearchRequest {
     pub query: String,
     /// Maximum number of results to return
     pub limit: usize,
-    /// Offset for pagination
+    /// Matches to skip, for fetching later pages; Windows Search
+    /// fallback results only come with the first page
     pub offset: usize,
+    /// Additional queries to count without fetching rows (batch count API).
+    /// Counts are returned in `SearchResponse::counts` in the same order.
+    #[serde(default)]
+    pub count_queries: Vec<String>,
+    /// Sort keys, primary first (empty = name order); `sort:` terms in the
+    /// query replace them
+    #[serde(default)]
+    pub sort: Vec<SortSpec>,
+    /// Return every matching row, even when several resolve to the same
+    /// path (hardlinks, virtual entries). By default they are collapsed.
+    #[serde(default)]
+    pub show_all_links: bool,
+    /// How the returned page is ordered after the sort keys select it
+    #[serde(default)]
+    pub ranking: Ranking,
+    /// Answer with [`SearchFrame`]s sent as results are resolved instead of
+    /// one [`SearchResponse`] (see [`read_search_stream`])
+    #[serde(default)]
+    pub stream: bool,
 }
 
 /// Search response from service to UI.
@@ -28,14 +497,90 @@ pub struct SearchRequest {
 pub struct SearchResponse {
     /// List of matching files
     pub results: Vec<FileResult>,
-    /// Total count of matches (may be more than results.len() if paginated)
+    /// Indexed matches across all pages (may be more than results.len() if paginated)
     pub total_count: usize,
     /// Time taken to execute search in milliseconds
     pub search_time_ms: u64,
+    /// Match counts aligned with `SearchRequest::count_queries`
+    #[serde(default)]
+    pub counts: Vec<usize>,
 }
 
-/// A single file result returned from search.
+/// One frame of a streamed search response.
+///
+/// Results come in one or more `Results` frames, each ranked on its own
+/// (scores are comparable across frames), followed by one `Done` frame. A
+/// row collapsed into a result from an earlier frame is dropped without
+/// being counted in that result's `duplicates`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "frame", rename_all = "snake_case")]
+pub enum SearchFrame {
+    /// Next chunk of results
+    Results {
+        /// Matching files, ranked within the chunk
+        results: Vec<FileResult>,
+    },
+    /// Terminator; no results follow
+    Done {
+        /// Indexed matches across all pages
+        total_count: usize,
+        /// Time taken to execute the whole search in milliseconds
+        search_time_ms: u64,
+        /// Match counts aligned with `SearchRequest::count_queries`
+        counts: Vec<usize>,
+    },
+}
+
+/// One frame of an export, in reply to [`Command::Export`].
+///
+/// The exported text comes in `Data` frames, to be concatenated in order,
+/// followed by one `Done` frame, or a `Failed` frame if the export stopped.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "frame", rename_all = "snake_case")]
+pub enum ExportFrame {
+    /// Next part of the exported text
+    Data {
+        /// Lines in the requested format
+        text: String,
+    },
+    /// Terminator; the export is complete
+    Done {
+        /// Number of matches exported
+        rows: usize,
+    },
+    /// Terminator; the export stopped early
+    Failed {
+        /// Why the export stopped
+        message: String,
+    },
+}
+
+/// Read an export's frames, writing the exported text to `out`.
+///
+/// # Returns
+/// Number of matches exported
+///
+/// # Errors
+/// Returns error if reading or writing fails or the service reports a failure.
+pub async fn read_export_stream<R, W>(reader: &mut R, out: &mut W) -> Result<usize>
+where
+    R: AsyncReadExt + Unpin,
+    W: std::io::Write,
+{
+    loop {
+        match read_message(reader).await? {
+            ExportFrame::Data { text } => out.write_all(text.as_bytes())?,
+            ExportFrame::Done { rows } => {
+                out.flush()?;
+                return Ok(rows);
+            }
+            ExportFrame::Failed { message } => return Err(FFIError::Ipc(format!("Export failed: {}", message))),
+        }
+    }
+}
+
+/// A single file result returned from search.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct FileResult {
     /// Database ID of the file
     pub id: i64,
@@ -49,6 +594,117 @@ pub struct FileResult {
     pub modified: i64,
     /// Whether this is a directory
     pub is_dir: bool,
+    /// Number of other rows with the same path collapsed into this one
+    #[serde(default)]
+    pub duplicates: usize,
+    /// Where the result came from
+    #[serde(default)]
+    pub source: ResultSource,
+    /// Score the ranker gave the result (higher ranks first), if ranked
+    #[serde(default)]
+    pub rank: Option<f64>,
+    /// Lifecycle state of the volume the result is on ("online", "offline",
+    /// ...), for results from the index
+    #[serde(default)]
+    pub volume_state: Option<String>,
+    /// Full path the result was deleted from, for results in the Recycle Bin
+    #[serde(default)]
+    pub original_path: Option<String>,
+}
+
+impl FileResult {
+    /// Whether the result is on a disconnected volume, so it can't be
+    /// opened until the volume is back.
+    pub fn is_offline(&self) -> bool {
+        self.volume_state.as_deref() == Some(VolumeState::Offline { since: 0 }.to_db_str())
+    }
+}
+
+/// Origin of a search result.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultSource {
+    /// The FFI index
+    #[default]
+    Index,
+    /// Windows Search, for volumes FFI does not index
+    WindowsSearch,
+}
+
+/// Collapse results that resolve to the same display path.
+///
+/// Paths are compared case-insensitively, as on Windows. The first result
+/// for each path is kept, in order, and counts the rows folded into it.
+pub fn dedup_by_path(results: Vec<FileResult>) -> Vec<FileResult> {
+    let mut seen: HashMap<String, usize> = HashMap::with_capacity(results.len());
+    let mut deduped: Vec<FileResult> = Vec::with_capacity(results.len());
+
+    for result in results {
+        match seen.entry(result.path.to_lowercase()) {
+            Entry::Occupied(first) => deduped[*first.get()].duplicates += 1,
+            Entry::Vacant(slot) => {
+                slot.insert(deduped.len());
+                deduped.push(result);
+            }
+        }
+    }
+
+    deduped
+}
+
+/// Add a chunk of streamed results to those already received.
+///
+/// Ranked results are inserted after every result scoring at least as
+/// high, so chunks merge into one ranking; unranked results are appended.
+pub fn merge_ranked(results: &mut Vec<FileResult>, chunk: Vec<FileResult>) {
+    for result in chunk {
+        match result.rank {
+            Some(rank) => {
+                let at = results.partition_point(|r| r.rank.is_none_or(|other| other >= rank));
+                results.insert(at, result);
+            }
+            None => results.push(result),
+        }
+    }
+}
+
+/// Read a streamed search response up to its `Done` frame.
+///
+/// # Arguments
+/// * `reader` - Connection the search request was sent on
+/// * `on_results` - Called with each chunk of results as it arrives
+///
+/// # Returns
+/// All chunks merged with [`merge_ranked`], with the totals from the `Done` frame.
+///
+/// # Errors
+/// Returns error if a frame cannot be read or the stream ends early.
+pub async fn read_search_stream<R, F>(reader: &mut R, mut on_results: F) -> Result<SearchResponse>
+where
+    R: AsyncReadExt + Unpin,
+    F: FnMut(&[FileResult]),
+{
+    let mut results = Vec::new();
+    loop {
+        match read_message(reader).await? {
+            SearchFrame::Results { results: chunk } => {
+                on_results(&chunk);
+                merge_ranked(&mut results, chunk);
+            }
+            SearchFrame::Done {
+                total_count,
+                search_time_ms,
+                counts,
+            } => {
+                return Ok(SearchResponse {
+                    results,
+                    total_count,
+                    search_time_ms,
+                    counts,
+                });
+            }
+        }
+    }
 }
 
 /// Read a length-prefixed JSON message from an async reader.
@@ -70,10 +726,40 @@ where
         FFIError::Ipc(format!("Failed to read message length: {}", e))
     })?;
 
-    let len = u32::from_le_bytes(len_buf) as usize;
+    read_message_body(reader, u32::from_le_bytes(len_buf) as usize).await
+}
 
+/// Read a client's first length-prefixed JSON message.
+///
+/// # Returns
+/// `None` if the client disconnects before sending anything, as a check
+/// whether the service is running does.
+pub async fn read_first_message<T, R>(reader: &mut R) -> Result<Option<T>>
+where
+    T: for<'de> Deserialize<'de>,
+    R: AsyncReadExt + Unpin,
+{
+    let mut len_buf = [0u8; 4];
+    match reader.read(&mut len_buf[..1]).await {
+        Ok(0) => return Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Ok(None),
+        Err(e) => return Err(FFIError::Ipc(format!("Failed to read message length: {}", e))),
+        Ok(_) => {}
+    }
+    reader.read_exact(&mut len_buf[1..]).await.map_err(|e| {
+        FFIError::Ipc(format!("Failed to read message length: {}", e))
+    })?;
+
+    read_message_body(reader, u32::from_le_bytes(len_buf) as usize).await.map(Some)
+}
+
+/// Read the JSON body of a message whose length prefix was read.
+async fn read_message_body<T, R>(reader: &mut R, len: usize) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+    R: AsyncReadExt + Unpin,
+{
     // Sanity check: reject messages over 16MB
-    const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
     if len > MAX_MESSAGE_SIZE {
         return Err(FFIError::Ipc(format!(
             "Message too large: {} bytes (max {})",
@@ -81,8 +767,9 @@ where
         )));
     }
 
-    // Read message body
-    let mut buf = vec![0u8; len];
+    // Read message body into a reused buffer
+    let mut buf = PooledBuffer::take();
+    buf.resize(len, 0);
     reader.read_exact(&mut buf).await.map_err(|e| {
         FFIError::Ipc(format!("Failed to read message body: {}", e))
     })?;
@@ -99,27 +786,35 @@ where
 /// - 4 bytes: little-endian u32 message length
 /// - N bytes: JSON-encoded message
 ///
+/// The message is serialized straight into a reused buffer after a
+/// placeholder prefix, then sent with a single write.
+///
 /// # Errors
-/// Returns error if serialization or write fails.
+/// Returns error if serialization or write fails, or the message is too large.
 pub async fn write_message<T, W>(writer: &mut W, message: &T) -> Result<()>
 where
     T: Serialize,
     W: AsyncWriteExt + Unpin,
 {
-    // Serialize to JSON
-    let json = serde_json::to_vec(message).map_err(|e| {
+    let mut buf = PooledBuffer::take();
+    buf.extend_from_slice(&[0u8; 4]);
+
+    // Serialize to JSON after the length prefix
+    serde_json::to_writer(&mut *buf, message).map_err(|e| {
         FFIError::Ipc(format!("Failed to serialize message: {}", e))
     })?;
 
-    // Write length prefix
-    let len = json.len() as u32;
-    writer.write_all(&len.to_le_bytes()).await.map_err(|e| {
-        FFIError::Ipc(format!("Failed to write message length: {}", e))
-    })?;
+    let len = buf.len() - 4;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(FFIError::Ipc(format!(
+            "Message too large: {} bytes (max {})",
+            len, MAX_MESSAGE_SIZE
+        )));
+    }
+    buf[..4].copy_from_slice(&(len as u32).to_le_bytes());
 
-    // Write message body
-    writer.write_all(&json).await.map_err(|e| {
-        FFIError::Ipc(format!("Failed to write message body: {}", e))
+    writer.write_all(&buf).await.map_err(|e| {
+        FFIError::Ipc(format!("Failed to write message: {}", e))
     })?;
 
     Ok(())
@@ -135,6 +830,11 @@ mod tests {
             query: "test*.txt".to_string(),
             limit: 100,
             offset: 0,
+            count_queries: vec!["test ext:pdf".to_string()],
+            sort: Vec::new(),
+            show_all_links: true,
+            ranking: Ranking::Fuzzy,
+            stream: false,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -143,6 +843,19 @@ mod tests {
         assert_eq!(parsed.query, "test*.txt");
         assert_eq!(parsed.limit, 100);
         assert_eq!(parsed.offset, 0);
+        assert_eq!(parsed.count_queries, vec!["test ext:pdf".to_string()]);
+        assert!(parsed.show_all_links);
+        assert_eq!(parsed.ranking, Ranking::Fuzzy);
+    }
+
+    #[test]
+    fn test_search_request_without_count_queries() {
+        // Older clients don't send count queries
+        let json = r#"{"query":"test","limit":10,"offset":0}"#;
+        let parsed: SearchRequest = serde_json::from_str(json).unwrap();
+        assert!(parsed.count_queries.is_empty());
+        assert!(!parsed.show_all_links);
+        assert!(!parsed.stream);
     }
 
     #[test]
@@ -156,10 +869,16 @@ mod tests {
                     size: 1024,
                     modified: 1700000000,
                     is_dir: false,
+                    duplicates: 0,
+                    source: ResultSource::Index,
+                    rank: None,
+                    volume_state: None,
+                    original_path: None,
                 },
             ],
             total_count: 1,
             search_time_ms: 5,
+            counts: vec![1],
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -169,6 +888,7 @@ mod tests {
         assert_eq!(parsed.results[0].name, "test.txt");
         assert_eq!(parsed.total_count, 1);
         assert_eq!(parsed.search_time_ms, 5);
+        assert_eq!(parsed.counts, vec![1]);
     }
 
     #[test]
@@ -180,13 +900,363 @@ mod tests {
             size: 2048,
             modified: 1700000000,
             is_dir: false,
+            duplicates: 2,
+            source: ResultSource::WindowsSearch,
+            rank: None,
+            volume_state: None,
+            original_path: None,
         };
 
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("document.pdf"));
+        assert!(!result.is_offline());
 
         let parsed: FileResult = serde_json::from_str(&json).unwrap();
         assert_eq!(parsed.id, 42);
         assert!(!parsed.is_dir);
+        assert_eq!(parsed.duplicates, 2);
+        assert_eq!(parsed.source, ResultSource::WindowsSearch);
+    }
+
+    #[test]
+    fn test_request_routing() {
+        let json = r#"{"type":"purge_volume","drive_letter":"E:"}"#;
+        match serde_json::from_str::<Request>(json).unwrap() {
+            Request::Command(command) => assert_eq!(
+                command,
+                Command::PurgeVolume {
+                    drive_letter: "E:".to_string()
+                }
+            ),
+            request => panic!("parsed command as {:?}", request),
+        }
+
+        let json = r#"{"type":"get_syntax_help"}"#;
+        assert!(matches!(
+            serde_json::from_str::<Request>(json).unwrap(),
+            Request::Command(Command::GetSyntaxHelp)
+        ));
+
+        for (json, expected) in [
+            (r#"{"type":"get_status"}"#, Command::GetStatus),
+            (r#"{"type":"trigger_rescan","drive_letter":"C:"}"#, Command::TriggerRescan { drive_letter: "C:".to_string() }),
+            (
+                r#"{"type":"rescan_path","path":"D:\\Photos"}"#,
+                Command::RescanPath { path: r"D:\Photos".to_string() },
+            ),
+            (r#"{"type":"reload_config"}"#, Command::ReloadConfig),
+            (r#"{"type":"pause_indexing"}"#, Command::PauseIndexing),
+            (r#"{"type":"resume_indexing"}"#, Command::ResumeIndexing),
+            (r#"{"type":"set_log_level","level":"debug"}"#, Command::SetLogLevel { level: "debug".to_string() }),
+            (r#"{"type":"cancel"}"#, Command::Cancel),
+            (
+                r#"{"type":"export","query":"ext:pdf","format":"json_lines"}"#,
+                Command::Export { query: "ext:pdf".to_string(), format: ExportFormat::JsonLines },
+            ),
+            (
+                r#"{"type":"save_search","name":"Notes","query":"ext:md"}"#,
+                Command::SaveSearch { name: "Notes".to_string(), query: "ext:md".to_string() },
+            ),
+            (r#"{"type":"list_saved_searches"}"#, Command::ListSavedSearches),
+            (
+                r#"{"type":"add_tags","path":"D:\\Docs\\plan.txt","tags":["work"]}"#,
+                Command::AddTags { path: r"D:\Docs\plan.txt".to_string(), tags: vec!["work".to_string()] },
+            ),
+            (
+                r#"{"type":"aggregate","query":"path:C:\\Users","group_by":"folder","limit":20}"#,
+                Command::Aggregate { query: r"path:C:\Users".to_string(), group_by: UsageGroup::Folder, limit: 20 },
+            ),
+            (
+                r#"{"type":"recent_files","since":1700000000,"limit":100}"#,
+                Command::RecentFiles { since: 1_700_000_000, limit: 100 },
+            ),
+            (r#"{"type":"list_detected_volumes"}"#, Command::ListDetectedVolumes),
+            (
+                r#"{"type":"set_volume_enabled","volume":"D:","enabled":true}"#,
+                Command::SetVolumeEnabled { volume: "D:".to_string(), enabled: true },
+            ),
+        ] {
+            match serde_json::from_str::<Request>(json).unwrap() {
+                Request::Command(command) => assert_eq!(command, expected),
+                request => panic!("parsed {} as {:?}", json, request),
+            }
+        }
+
+        // Plain search requests from older clients
+        let json = r#"{"query":"test","limit":10,"offset":0}"#;
+        assert!(matches!(
+            serde_json::from_str::<Request>(json).unwrap(),
+            Request::Search(_)
+        ));
+
+        let json = r#"{"protocol_version":1,"client":"ffi-cli 0.1.0"}"#;
+        assert!(matches!(
+            serde_json::from_str::<Request>(json).unwrap(),
+            Request::Hello(hello) if hello.protocol_version == 1 && hello.client == "ffi-cli 0.1.0"
+        ));
+    }
+
+    #[test]
+    fn test_command_requires_admin() {
+        let enable = Command::SetVolumeEnabled {
+            volume: "D:".to_string(),
+            enabled: true,
+        };
+        assert!(enable.requires_admin());
+        assert!(Command::PauseIndexing.requires_admin());
+        assert!(!Command::GetStatus.requires_admin());
+        assert!(!Command::ListDetectedVolumes.requires_admin());
+        assert!(!Command::RecordOpen { path: r"C:\a.txt".to_string() }.requires_admin());
+    }
+
+    #[test]
+    fn test_hello() {
+        let hello = Hello::current();
+        assert_eq!(hello.protocol_version, PROTOCOL_VERSION);
+        assert!(hello.client.ends_with(env!("CARGO_PKG_VERSION")));
+        assert!(hello.answer().accepted);
+
+        let newer = Hello {
+            protocol_version: PROTOCOL_VERSION + 1,
+            ..hello.clone()
+        };
+        let answer = newer.answer();
+        assert!(!answer.accepted);
+        assert_eq!(answer.protocol_version, PROTOCOL_VERSION);
+        assert!(answer.message.contains("update the service"));
+
+        let unversioned = Hello {
+            protocol_version: 0,
+            ..hello
+        };
+        assert!(!unversioned.answer().accepted);
+
+        let missing = HelloResponse::missing();
+        assert!(!missing.accepted);
+        assert_eq!(missing.protocol_version, PROTOCOL_VERSION);
+        assert!(missing.message.contains("update the client"));
+    }
+
+    #[test]
+    fn test_state_label() {
+        let mut volume = VolumeStatus {
+            drive_letter: "C:".to_string(),
+            fs_type: "NTFS".to_string(),
+            state: "indexing".to_string(),
+            file_count: 0,
+            dir_count: 0,
+            last_scan_time: None,
+            progress: Some(42),
+            scan: None,
+            truncated: None,
+        };
+        assert_eq!(volume.state_label(), "indexing 42%");
+
+        volume.scan = Some(VolumeProgress { processed: 1_218_000, total: 2_900_000 });
+        assert_eq!(volume.state_label(), "indexing 42% (1.2M/2.9M files)");
+
+        // Walks of volumes never scanned before don't know the total
+        volume.state = "rescanning".to_string();
+        volume.progress = None;
+        volume.scan = Some(VolumeProgress { processed: 950, total: 0 });
+        assert_eq!(volume.state_label(), "rescanning (950 files)");
+
+        volume.state = "online".to_string();
+        volume.scan = None;
+        volume.truncated = Some("max_files = 1000".to_string());
+        assert_eq!(volume.state_label(), "online, limited by max_files = 1000");
+    }
+
+    #[test]
+    fn test_command_response_status() {
+        let response = CommandResponse {
+            success: true,
+            message: "1 volume".to_string(),
+            syntax: None,
+            saved_searches: None,
+            tags: None,
+            usage: None,
+            files: None,
+            detected_volumes: None,
+            status: Some(ServiceStatus {
+                indexing_paused: true,
+                volumes: vec![VolumeStatus {
+                    drive_letter: "C:".to_string(),
+                    fs_type: "NTFS".to_string(),
+                    state: "online".to_string(),
+                    file_count: 120,
+                    dir_count: 8,
+                    last_scan_time: Some(1700000000),
+                    progress: None,
+                    scan: None,
+                    truncated: None,
+                }],
+                maintenance: Some(MaintenanceReport {
+                    ran_at: 1700000000,
+                    duration_ms: 850,
+                    freed_pages: 12,
+                    integrity_errors: Vec::new(),
+                    rebuilt: false,
+                }),
+                pruning: Some(PruneReport {
+                    ran_at: 1700000000,
+                    size_before: 600 << 20,
+                    size_after: 450 << 20,
+                    offline_volumes: vec!["E:".to_string()],
+                    modified_cutoff: None,
+                    ..Default::default()
+                }),
+            }),
+        };
+
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(!json.contains("syntax"));
+        assert_eq!(serde_json::from_str::<CommandResponse>(&json).unwrap(), response);
+
+        // Responses from services without status support
+        let json = r#"{"success":true,"message":"ok"}"#;
+        assert!(serde_json::from_str::<CommandResponse>(json).unwrap().status.is_none());
+
+        // ... or without maintenance and pruning
+        let json = r#"{"indexing_paused":false,"volumes":[]}"#;
+        let status = serde_json::from_str::<ServiceStatus>(json).unwrap();
+        assert!(status.maintenance.is_none() && status.pruning.is_none());
+    }
+
+    #[test]
+    fn test_dedup_by_path() {
+        let result = |id: i64, path: &str| FileResult {
+            id,
+            name: "report.docx".to_string(),
+            path: path.to_string(),
+            size: 100,
+            modified: 1700000000,
+            is_dir: false,
+            duplicates: 0,
+            source: ResultSource::Index,
+            rank: None,
+            volume_state: None,
+            original_path: None,
+        };
+
+        let deduped = dedup_by_path(vec![
+            result(1, "C:\\Docs\\report.docx"),
+            result(2, "C:\\Backup\\report.docx"),
+            result(3, "C:\\docs\\REPORT.docx"),
+            result(4, "C:\\Docs\\report.docx"),
+        ]);
+
+        let ids: Vec<i64> = deduped.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(deduped[0].duplicates, 2);
+        assert_eq!(deduped[1].duplicates, 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_stream() {
+        let result = |id: i64, rank: Option<f64>| FileResult {
+            id,
+            name: format!("{}.txt", id),
+            path: format!("C:\\{}.txt", id),
+            size: 0,
+            modified: 0,
+            is_dir: false,
+            duplicates: 0,
+            source: ResultSource::Index,
+            rank,
+            volume_state: None,
+            original_path: None,
+        };
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+
+        let frames = [
+            SearchFrame::Results {
+                results: vec![result(1, Some(0.9)), result(2, Some(0.4))],
+            },
+            SearchFrame::Results {
+                results: vec![result(3, Some(0.6)), result(4, Some(0.4)), result(5, None)],
+            },
+            SearchFrame::Done {
+                total_count: 12,
+                search_time_ms: 3,
+                counts: vec![2],
+            },
+        ];
+        for frame in &frames {
+            write_message(&mut server, frame).await.unwrap();
+        }
+
+        let mut chunks = Vec::new();
+        let response = read_search_stream(&mut client, |chunk| chunks.push(chunk.len())).await.unwrap();
+        assert_eq!(chunks, vec![2, 3]);
+        let ids: Vec<i64> = response.results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 3, 2, 4, 5]);
+        assert_eq!(response.total_count, 12);
+        assert_eq!(response.counts, vec![2]);
+
+        // A stream cut off before its terminator is an error
+        write_message(&mut server, &frames[0]).await.unwrap();
+        drop(server);
+        assert!(read_search_stream(&mut client, |_| {}).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_stream() {
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+        let data = |text: &str| ExportFrame::Data { text: text.to_string() };
+        for frame in [data("path,name\r\n"), data("C:\\a.txt,a.txt\r\n"), ExportFrame::Done { rows: 1 }] {
+            write_message(&mut server, &frame).await.unwrap();
+        }
+
+        let mut out = Vec::new();
+        assert_eq!(read_export_stream(&mut client, &mut out).await.unwrap(), 1);
+        assert_eq!(out, b"path,name\r\nC:\\a.txt,a.txt\r\n");
+
+        let failed = ExportFrame::Failed {
+            message: "Invalid query".to_string(),
+        };
+        write_message(&mut server, &failed).await.unwrap();
+        let err = read_export_stream(&mut client, &mut Vec::new()).await.unwrap_err();
+        assert!(err.to_string().contains("Invalid query"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_message_framing_round_trip() {
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+
+        for query in ["first", "second, with a longer query"] {
+            let request = Request::Command(Command::GetSyntaxHelp);
+            write_message(&mut client, &request).await.unwrap();
+            let search = serde_json::json!({"query": query, "limit": 10, "offset": 0});
+            write_message(&mut client, &search).await.unwrap();
+
+            let parsed: Request = read_message(&mut server).await.unwrap();
+            assert!(matches!(parsed, Request::Command(Command::GetSyntaxHelp)));
+            let parsed: SearchRequest = read_message(&mut server).await.unwrap();
+            assert_eq!(parsed.query, query);
+        }
+
+        // Corrupt length prefixes are rejected before allocating
+        client.write_all(&u32::MAX.to_le_bytes()).await.unwrap();
+        assert!(read_message::<Request, _>(&mut server).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_first_message() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        write_message(&mut client, &Request::Command(Command::GetSyntaxHelp)).await.unwrap();
+        let first: Option<Request> = read_first_message(&mut server).await.unwrap();
+        assert!(matches!(first, Some(Request::Command(Command::GetSyntaxHelp))));
+
+        // A probe connects and leaves without a word
+        let (client, mut server) = tokio::io::duplex(1024);
+        drop(client);
+        assert!(read_first_message::<Request, _>(&mut server).await.unwrap().is_none());
+
+        // Leaving partway through a message is still an error
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client.write_all(&[8, 0]).await.unwrap();
+        drop(client);
+        assert!(read_first_message::<Request, _>(&mut server).await.is_err());
     }
 }
@@ -0,0 +1,160 @@
+//! Access control for the service's named pipe.
+//!
+//! The pipe is created with a DACL allowing only LocalSystem, the account
+//! running the service and the configured accounts, so other local users cannot search
+//! the index. Remote clients are always refused. Commands that change what
+//! the service indexes are further limited to administrators (see
+//! [`is_client_admin`]).
+
+use crate::{FFIError, Result};
+
+/// Accounts allowed to connect by default: Administrators and users
+/// logged on interactively, as SDDL aliases.
+pub const DEFAULT_ALLOWED_SIDS: &[&str] = &["BA", "IU"];
+
+/// Access granted to allowed accounts: read and write, but not
+/// `FILE_CREATE_PIPE_INSTANCE`, so they cannot serve the pipe themselves.
+const CLIENT_ACCESS: &str = "0x12019b";
+
+/// Build the pipe's security descriptor in SDDL.
+///
+/// # Arguments
+/// * `allowed_sids` - Accounts that may connect, as SIDs (`S-1-5-...`) or
+///   SDDL aliases (`BA`, `IU`, `AU`, ...)
+///
+/// # Returns
+/// A protected DACL denying network logons, giving LocalSystem and the
+/// pipe's owner (the service account) full control and each allowed
+/// account read/write access.
+///
+/// # Errors
+/// Returns error if an entry is neither a SID nor an alias.
+pub fn pipe_sddl(allowed_sids: &[String]) -> Result<String> {
+    let mut sddl = String::from("D:P(D;;GA;;;NU)(A;;GA;;;SY)(A;;GA;;;OW)");
+    for sid in allowed_sids {
+        if !is_sid(sid) {
+            return Err(FFIError::Config(format!(
+                "ipc.allowed_sids: {:?} is not a SID (S-1-...) or SDDL alias (e.g. BA)",
+                sid
+            )));
+        }
+        sddl.push_str(&format!("(A;;{};;;{})", CLIENT_ACCESS, sid));
+    }
+    Ok(sddl)
+}
+
+/// Whether a string is a SID or a two-letter SDDL account alias.
+fn is_sid(sid: &str) -> bool {
+    if sid.len() == 2 {
+        return sid.chars().all(|c| c.is_ascii_uppercase());
+    }
+    sid.strip_prefix("S-1-").is_some_and(|rest| {
+        rest.split('-').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+/// Create a pipe instance protected by an SDDL security descriptor.
+///
+/// # Errors
+/// Returns error if the descriptor is invalid or the pipe cannot be created.
+#[cfg(windows)]
+pub fn create_pipe_with_sddl(
+    options: &tokio::net::windows::named_pipe::ServerOptions,
+    name: &str,
+    sddl: &str,
+) -> Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+    use windows::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows::Win32::Security::{PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES};
+
+    let wide: Vec<u16> = sddl.encode_utf16().chain(std::iter::once(0)).collect();
+    let mut descriptor = PSECURITY_DESCRIPTOR::default();
+    unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            PCWSTR(wide.as_ptr()),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            None,
+        )
+    }
+    .map_err(|e| FFIError::Ipc(format!("Invalid pipe security descriptor {:?}: {}", sddl, e)))?;
+
+    let mut attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: descriptor.0,
+        bInheritHandle: false.into(),
+    };
+    // The attributes and the descriptor they point to outlive the call
+    let pipe = unsafe {
+        options.create_with_security_attributes_raw(name, &mut attributes as *mut SECURITY_ATTRIBUTES as *mut _)
+    };
+    unsafe {
+        let _ = LocalFree(Some(HLOCAL(descriptor.0)));
+    }
+
+    pipe.map_err(|e| FFIError::Ipc(format!("Failed to create named pipe: {}", e)))
+}
+
+/// Check whether the client connected to a pipe instance is a member of
+/// the local Administrators group.
+///
+/// Impersonates the client for the check, so with UAC only an elevated
+/// client counts. The client must have sent a message on the pipe first.
+///
+/// # Errors
+/// Returns error if the client cannot be impersonated or its groups checked.
+#[cfg(windows)]
+pub fn is_client_admin(pipe: &tokio::net::windows::named_pipe::NamedPipeServer) -> Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::core::{w, BOOL};
+    use windows::Win32::Foundation::{LocalFree, HANDLE, HLOCAL};
+    use windows::Win32::Security::Authorization::ConvertStringSidToSidW;
+    use windows::Win32::Security::{CheckTokenMembership, RevertToSelf, PSID};
+    use windows::Win32::System::Pipes::ImpersonateNamedPipeClient;
+
+    let mut administrators = PSID::default();
+    unsafe { ConvertStringSidToSidW(w!("S-1-5-32-544"), &mut administrators) }
+        .map_err(|e| FFIError::Ipc(format!("Failed to build the Administrators SID: {}", e)))?;
+
+    let is_member = unsafe { ImpersonateNamedPipeClient(HANDLE(pipe.as_raw_handle())) }
+        .map_err(|e| FFIError::Ipc(format!("Failed to impersonate the pipe client: {}", e)))
+        .and_then(|()| {
+            // Without a token, the check uses the impersonation token
+            let mut is_member = BOOL::default();
+            let checked = unsafe { CheckTokenMembership(None, administrators, &mut is_member) };
+            // Back to the service's own token before this thread runs anything else
+            unsafe { RevertToSelf() }
+                .map_err(|e| FFIError::Ipc(format!("Failed to stop impersonating the pipe client: {}", e)))?;
+            checked.map_err(|e| FFIError::Ipc(format!("Failed to check the pipe client's groups: {}", e)))?;
+            Ok(is_member.as_bool())
+        });
+    unsafe {
+        let _ = LocalFree(Some(HLOCAL(administrators.0)));
+    }
+
+    is_member
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipe_sddl() {
+        let allowed: Vec<String> = DEFAULT_ALLOWED_SIDS.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            pipe_sddl(&allowed).unwrap(),
+            "D:P(D;;GA;;;NU)(A;;GA;;;SY)(A;;GA;;;OW)(A;;0x12019b;;;BA)(A;;0x12019b;;;IU)"
+        );
+
+        let user = "S-1-5-21-1004336348-1177238915-682003330-512".to_string();
+        assert!(pipe_sddl(std::slice::from_ref(&user)).unwrap().ends_with(&format!("(A;;0x12019b;;;{})", user)));
+
+        for invalid in ["ba", "S-1-", "S-1-5-x", "BA)(A;;GA;;;WD", "Everyone"] {
+            assert!(pipe_sddl(&[invalid.to_string()]).is_err(), "{}", invalid);
+        }
+    }
+}
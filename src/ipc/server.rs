@@ -1,153 +1,679 @@
 //! Named pipe server for the FFI service.
 //!
-//! Listens for search requests from the UI client and returns results
-//! from the database. Uses the loop pattern from RESEARCH.md for handling
-//! multiple sequential client connections.
+//! Listens for search requests and control commands from clients and
+//! answers them from the database. Uses the loop pattern from RESEARCH.md
+//! for handling multiple sequential client connections.
+//!
+//! Reads and writes time out and searches stop at a deadline (see
+//! [`IpcConfig`]), and a search is cancelled when its client sends
+//! [`Command::Cancel`] or disconnects, so no client can hold a server task
+//! or the database lock indefinitely. Database work runs on the blocking
+//! thread pool, so a slow query doesn't stall other connections.
+//!
+//! Pipe instances carry a DACL built from `[ipc] allowed_sids`, and the
+//! server creates the pipe's first instance, so no other process can own
+//! the name. Every client must open with a [`Hello`], checked for a
+//! compatible protocol version and logged with its process id.
 
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
+use serde::Serialize;
+use tokio::io::{AsyncWriteExt, ReadHalf, WriteHalf};
 use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
 use tokio::sync::broadcast;
 
-use crate::db::Database;
-use crate::db::{search_files, reconstruct_path};
+use crate::db::{
+    count_query_matches_batch, get_open_history, get_open_samples, recycled_original_path, DatabasePool, ExportFormat,
+    Exporter, FileEntry, Store,
+};
+use crate::ipc::cancel::{run_cancellable, CancelToken};
+use crate::ipc::commands::{aggregate_usage, execute_command, usage_response};
 use crate::ipc::protocol::{
-    read_message, write_message, FileResult, SearchRequest, SearchResponse, PIPE_NAME,
+    dedup_by_path, read_first_message, read_message, write_message, Command, CommandResponse, ExportFrame,
+    FileResult, Hello, HelloResponse, Request, ResultSource, SearchFrame, SearchRequest, SearchResponse, PIPE_NAME,
 };
+use crate::ipc::security::{create_pipe_with_sddl, is_client_admin, pipe_sddl};
+use crate::search::{
+    parse_query, FrecencyCache, FrecencyRanker, FuzzyRanker, ParsedQuery, Ranker, Ranking, RelevanceRanker,
+    WindowsSearchFallback,
+};
+use crate::service::config::{Config, IpcConfig};
 use crate::{FFIError, Result};
 
+/// Pipe instances kept waiting for clients, so a client being served
+/// doesn't keep others from connecting.
+const PIPE_INSTANCES: usize = 4;
+
+/// Results in the first frame of a streamed search.
+const FIRST_CHUNK_SIZE: usize = 20;
+
+/// Results in each later frame of a streamed search.
+const STREAM_CHUNK_SIZE: usize = 200;
+
+/// Windows Search fallback, replaced when the configuration is reloaded.
+type SharedFallback = Arc<RwLock<Option<Arc<WindowsSearchFallback>>>>;
+
+/// Sending side of a client connection that is being searched for.
+type PipeWriter = WriteHalf<NamedPipeServer>;
+
 /// IPC server for handling search requests over named pipes.
 ///
 /// The server runs in the FFI service process and responds to search
 /// queries from the UI client.
+#[derive(Clone)]
 pub struct IpcServer {
-    db: Arc<Mutex<Database>>,
+    db: Arc<DatabasePool>,
+    windows_search: SharedFallback,
+    custom_ranker: Option<Arc<dyn Ranker>>,
+    /// Frecency ranker built from the open history, kept between searches
+    frecency: Arc<FrecencyCache>,
+    limits: IpcConfig,
 }
 
 impl IpcServer {
-    /// Create a new IPC server with a database connection.
+    /// Create a new IPC server with a database connection pool.
+    ///
+    /// # Arguments
+    /// * `db` - Shared pool; searches use its readers, commands its writer
+    pub fn new(db: Arc<DatabasePool>) -> Self {
+        Self {
+            db,
+            windows_search: Arc::new(RwLock::new(None)),
+            custom_ranker: None,
+            frecency: Arc::new(FrecencyCache::new()),
+            limits: IpcConfig::default(),
+        }
+    }
+
+    /// Merge Windows Search results for non-indexed volumes into searches.
     ///
     /// # Arguments
-    /// * `db` - Shared database connection (thread-safe)
-    pub fn new(db: Arc<Mutex<Database>>) -> Self {
-        Self { db }
+    /// * `fallback` - Windows Search fallback, or None to disable it
+    pub fn with_windows_search(mut self, fallback: Option<WindowsSearchFallback>) -> Self {
+        self.windows_search = Arc::new(RwLock::new(fallback.map(Arc::new)));
+        self
+    }
+
+    /// Apply the search settings of a reloaded configuration: the
+    /// attributes hidden from results and the Windows Search fallback.
+    pub fn apply_search_settings(&self, config: &Config) -> Result<()> {
+        apply_search_settings(&self.db, &self.windows_search, config)
+    }
+
+    /// Register the ranker used by requests asking for [`Ranking::Custom`].
+    ///
+    /// Without one, such requests keep the alphabetical order.
+    pub fn with_ranker(mut self, ranker: Arc<dyn Ranker>) -> Self {
+        self.custom_ranker = Some(ranker);
+        self
+    }
+
+    /// Set the time limits and the accounts allowed to connect
+    /// (defaults otherwise).
+    pub fn with_limits(mut self, limits: IpcConfig) -> Self {
+        self.limits = limits;
+        self
     }
 
     /// Run the IPC server, accepting client connections until shutdown.
     ///
-    /// Uses the loop pattern from RESEARCH.md:
-    /// 1. Create new server instance
-    /// 2. Wait for client connection
-    /// 3. Spawn handler for this client
-    /// 4. Repeat
+    /// Creates the pipe instances with [`bind`](Self::bind), then serves
+    /// them with [`serve`](Self::serve).
     ///
     /// # Arguments
-    /// * `mut shutdown` - Broadcast receiver for shutdown signal
+    /// * `shutdown` - Broadcast receiver for shutdown signal
     ///
     /// # Errors
     /// Returns error if pipe creation fails. Individual client errors are logged
     /// but don't stop the server.
-    pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) -> Result<()> {
-        tracing::info!("Starting IPC server on {}", PIPE_NAME);
+    pub async fn run(&self, shutdown: broadcast::Receiver<()>) -> Result<()> {
+        let pipes = self.bind()?;
+        self.serve(pipes, shutdown).await
+    }
 
-        loop {
-            // Create new server instance
-            let server = match ServerOptions::new()
-                .first_pipe_instance(false)
-                .create(PIPE_NAME)
-            {
-                Ok(s) => s,
-                Err(e) => {
-                    tracing::error!("Failed to create named pipe: {}", e);
-                    return Err(FFIError::Ipc(format!("Failed to create named pipe: {}", e)));
-                }
+    /// Create the [`PIPE_INSTANCES`] pipe instances clients connect to.
+    ///
+    /// The first instance must create the pipe: if another process already
+    /// holds the name (and could answer clients in the service's place),
+    /// this fails instead of adding instances next to it. Must be called
+    /// within a tokio runtime.
+    ///
+    /// # Errors
+    /// Returns error if the pipe name is taken or pipe creation fails.
+    pub fn bind(&self) -> Result<Vec<NamedPipeServer>> {
+        let sddl = pipe_sddl(&self.limits.allowed_sids)?;
+        tracing::info!("Starting IPC server on {} ({} instances, {})", PIPE_NAME, PIPE_INSTANCES, sddl);
+
+        let first = create_pipe(&sddl, true).map_err(|e| {
+            FFIError::Ipc(format!(
+                "Could not create {} as its first instance, another process may own it: {}",
+                PIPE_NAME, e
+            ))
+        })?;
+        let mut pipes = vec![first];
+        for _ in 1..PIPE_INSTANCES {
+            pipes.push(create_pipe(&sddl, false)?);
+        }
+        Ok(pipes)
+    }
+
+    /// Accept clients on pipe instances from [`bind`](Self::bind) until
+    /// shutdown.
+    ///
+    /// Each instance gets a listener, so several clients (UI windows, CLI
+    /// tools) can connect at once. Each listener uses the loop pattern from
+    /// RESEARCH.md, creating its next instance before the connected client
+    /// is handled:
+    /// 1. Wait for client connection
+    /// 2. Create new server instance
+    /// 3. Spawn handler for the connected client
+    /// 4. Repeat
+    ///
+    /// # Arguments
+    /// * `pipes` - Pipe instances, all waiting before the first client
+    /// * `shutdown` - Broadcast receiver for shutdown signal
+    pub async fn serve(&self, pipes: Vec<NamedPipeServer>, shutdown: broadcast::Receiver<()>) -> Result<()> {
+        let sddl = pipe_sddl(&self.limits.allowed_sids)?;
+
+        let mut listeners = tokio::task::JoinSet::new();
+        for pipe in pipes {
+            let server = self.clone();
+            let shutdown = shutdown.resubscribe();
+            let sddl = sddl.clone();
+            listeners.spawn(async move { server.accept_loop(pipe, &sddl, shutdown).await });
+        }
+
+        // A failed listener is logged; the others keep serving until shutdown
+        let mut result = Ok(());
+        while let Some(joined) = listeners.join_next().await {
+            let error = match joined {
+                Ok(Ok(())) => continue,
+                Ok(Err(e)) => e,
+                Err(e) => FFIError::Ipc(format!("IPC listener task failed: {}", e)),
             };
+            tracing::error!("IPC listener stopped: {}", error);
+            if result.is_ok() {
+                result = Err(error);
+            }
+        }
+
+        tracing::info!("IPC server shut down");
+        result
+    }
 
+    /// Accept clients on one pipe instance at a time until shutdown.
+    async fn accept_loop(
+        &self,
+        mut pipe: NamedPipeServer,
+        sddl: &str,
+        mut shutdown: broadcast::Receiver<()>,
+    ) -> Result<()> {
+        loop {
             // Wait for client connection or shutdown signal
-            tokio::select! {
-                _ = shutdown.recv() => {
-                    tracing::info!("IPC server shutting down");
-                    return Ok(());
-                }
-                result = server.connect() => {
-                    match result {
-                        Ok(()) => {
-                            tracing::debug!("Client connected to IPC server");
-                            // Spawn handler for this client
-                            let db = self.db.clone();
-                            tokio::spawn(async move {
-                                if let Err(e) = handle_client(server, db).await {
-                                    tracing::warn!("Client handler error: {}", e);
-                                }
-                            });
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to accept client connection: {}", e);
-                            // Continue listening for new connections
+            let connected = tokio::select! {
+                _ = shutdown.recv() => return Ok(()),
+                result = pipe.connect() => result,
+            };
+
+            // Keep an instance waiting while this client is served
+            let client = std::mem::replace(&mut pipe, create_pipe(sddl, false)?);
+
+            match connected {
+                Ok(()) => {
+                    tracing::debug!("Client connected to IPC server");
+                    // Spawn handler for this client
+                    let db = self.db.clone();
+                    let windows_search = self.windows_search.clone();
+                    let custom_ranker = self.custom_ranker.clone();
+                    let frecency = self.frecency.clone();
+                    let limits = self.limits.clone();
+                    tokio::spawn(async move {
+                        let handled = handle_client(client, db, windows_search, custom_ranker, frecency, limits).await;
+                        if let Err(e) = handled {
+                            tracing::warn!("Client handler error: {}", e);
                         }
-                    }
+                    });
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to accept client connection: {}", e);
+                    // Continue listening for new connections
                 }
             }
         }
     }
 }
 
+/// Create a pipe instance for the next client, restricted by `sddl`.
+///
+/// With `first`, creation fails if the pipe already exists.
+fn create_pipe(sddl: &str, first: bool) -> Result<NamedPipeServer> {
+    let mut options = ServerOptions::new();
+    options.first_pipe_instance(first).reject_remote_clients(true);
+    create_pipe_with_sddl(&options, PIPE_NAME, sddl).inspect_err(|e| tracing::error!("{}", e))
+}
+
+/// Process id of the client connected to a pipe instance, for logging.
+fn client_process_id(pipe: &NamedPipeServer) -> Option<u32> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::Pipes::GetNamedPipeClientProcessId;
+
+    let mut pid = 0u32;
+    unsafe { GetNamedPipeClientProcessId(HANDLE(pipe.as_raw_handle()), &mut pid) }.ok()?;
+    Some(pid)
+}
+
+/// Whether the client connected to a pipe instance is an administrator;
+/// false if that cannot be checked.
+fn client_is_admin(pipe: &NamedPipeServer) -> bool {
+    is_client_admin(pipe).unwrap_or_else(|e| {
+        tracing::warn!("{}", e);
+        false
+    })
+}
+
+/// Answer a client's [`Hello`].
+///
+/// # Returns
+/// true if the client's request should be read, false if it was refused.
+async fn handshake(pipe: &mut NamedPipeServer, hello: &Hello, limits: &IpcConfig) -> Result<bool> {
+    let pid = client_process_id(pipe).map_or_else(|| "unknown".to_string(), |pid| pid.to_string());
+    let response = hello.answer();
+    if response.accepted {
+        tracing::debug!(
+            "Client {} (pid {}) connected with protocol version {}",
+            hello.client,
+            pid,
+            hello.protocol_version
+        );
+    } else {
+        tracing::warn!("Refused client {} (pid {}): {}", hello.client, pid, response.message);
+    }
+    send(pipe, &response, limits).await?;
+    Ok(response.accepted)
+}
+
 /// Handle a single client connection.
 ///
-/// Reads SearchRequest, executes search, reconstructs paths, and returns SearchResponse.
-async fn handle_client(mut pipe: NamedPipeServer, db: Arc<Mutex<Database>>) -> Result<()> {
-    // Read request
-    let request: SearchRequest = read_message(&mut pipe).await?;
+/// Answers the client's [`Hello`], refusing clients that send anything
+/// else first, then reads one request and dispatches it to the search or
+/// command handler.
+async fn handle_client(
+    mut pipe: NamedPipeServer,
+    db: Arc<DatabasePool>,
+    windows_search: SharedFallback,
+    custom_ranker: Option<Arc<dyn Ranker>>,
+    frecency: Arc<FrecencyCache>,
+    limits: IpcConfig,
+) -> Result<()> {
+    // Checks whether the service is running connect and leave without a word
+    let Some(first) = timed(limits.read_timeout(), "reading the handshake", read_first_message(&mut pipe)).await? else {
+        tracing::debug!("Client disconnected before its handshake");
+        return Ok(());
+    };
+    let Request::Hello(hello) = first else {
+        let pid = client_process_id(&pipe).map_or_else(|| "unknown".to_string(), |pid| pid.to_string());
+        tracing::warn!("Refused client (pid {}) that sent a request without a handshake", pid);
+        return send(&mut pipe, &HelloResponse::missing(), &limits).await;
+    };
+    if !handshake(&mut pipe, &hello, &limits).await? {
+        return Ok(());
+    }
+    let request: Request = timed(limits.read_timeout(), "reading the request", read_message(&mut pipe)).await?;
+
+    // Commands changing what is indexed or how the service runs are for
+    // administrators only
+    if let Request::Command(command) = &request {
+        if command.requires_admin() && !client_is_admin(&pipe) {
+            tracing::warn!("Refused {:?} from a client that is not an administrator", command);
+            let response = CommandResponse::from_result(Err(FFIError::Ipc(
+                "This command requires an administrator; run the client elevated".to_string(),
+            )));
+            return send(&mut pipe, &response, &limits).await;
+        }
+    }
+
+    match request {
+        Request::Search(request) => {
+            let windows_search = windows_search.read().ok().and_then(|fallback| fallback.clone());
+
+            // Keep reading the connection to notice a Cancel or a disconnect
+            let (reader, mut writer) = tokio::io::split(pipe);
+            let cancel = CancelToken::new(limits.query_timeout());
+            let watcher = tokio::spawn(watch_for_cancel(reader, cancel.clone()));
+
+            let result = handle_search(
+                &mut writer,
+                request,
+                db,
+                windows_search,
+                custom_ranker,
+                frecency,
+                &limits,
+                &cancel,
+            )
+            .await;
+            watcher.abort();
+            match result {
+                Err(e) if cancel.is_cancelled() && !cancel.timed_out() => {
+                    tracing::debug!("Search cancelled by the client: {}", e);
+                    Ok(())
+                }
+                result => result,
+            }
+        }
+        Request::Command(Command::ReloadConfig) => {
+            tracing::info!("Command request: {:?}", Command::ReloadConfig);
+            let response =
+                CommandResponse::from_result(blocking(move || reload_config(&db, &windows_search)).await);
+            send(&mut pipe, &response, &limits).await
+        }
+        Request::Command(Command::Export { query, format }) => {
+            tracing::info!("Export request: {:?} as {:?}", query, format);
+            handle_export(&mut pipe, &db, &query, format, &limits).await
+        }
+        Request::Command(Command::Aggregate { query, group_by, limit }) => {
+            // Read-only and possibly slow over a large scope: run on a reader
+            // within the query time limit rather than holding the writer
+            tracing::info!("Aggregate request: {:?} by {:?}", query, group_by);
+            let cancel = CancelToken::new(limits.query_timeout());
+            let response = blocking(move || {
+                let conn = db.reader()?;
+                Ok(usage_response(run_cancellable(conn.conn(), &cancel, || {
+                    aggregate_usage(conn.conn(), &query, group_by, limit)
+                })))
+            })
+            .await?;
+            send(&mut pipe, &response, &limits).await
+        }
+        Request::Command(Command::RecentFiles { since, limit }) => {
+            tracing::debug!("Recent files request: since={}, limit={}", since, limit);
+            let cancel = CancelToken::new(limits.query_timeout());
+            let response = match blocking(move || recent_files(&db, since, limit, &cancel)).await {
+                Ok(files) => CommandResponse {
+                    files: Some(files),
+                    ..CommandResponse::from_result(Ok("Recent files".to_string()))
+                },
+                Err(e) => CommandResponse::from_result(Err(e)),
+            };
+            send(&mut pipe, &response, &limits).await
+        }
+        Request::Hello(hello) => {
+            let response = CommandResponse::from_result(Err(FFIError::Ipc(format!(
+                "Unexpected second handshake from {}",
+                hello.client
+            ))));
+            send(&mut pipe, &response, &limits).await
+        }
+        Request::Command(command) => {
+            tracing::info!("Command request: {:?}", command);
+            let records_open = matches!(command, Command::RecordOpen { .. });
+            let response = blocking(move || {
+                let mut conn = db.writer()?;
+                Ok(execute_command(conn.conn_mut(), &command))
+            })
+            .await?;
+            // Frecency follows the new open from the next search
+            if records_open {
+                frecency.invalidate();
+            }
+            send(&mut pipe, &response, &limits).await
+        }
+    }
+}
+
+/// Cancel a search when its client sends [`Command::Cancel`] or disconnects.
+async fn watch_for_cancel(mut reader: ReadHalf<NamedPipeServer>, cancel: CancelToken) {
+    loop {
+        match read_message::<Request, _>(&mut reader).await {
+            Ok(Request::Command(Command::Cancel)) => break,
+            Ok(request) => tracing::debug!("Ignoring {:?} sent during a search", request),
+            Err(_) => break,
+        }
+    }
+    cancel.cancel();
+}
+
+/// Await a read or write on a client connection, failing after `limit`.
+async fn timed<T>(limit: Duration, what: &str, io: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(limit, io)
+        .await
+        .map_err(|_| FFIError::Ipc(format!("Timed out {} after {:?}", what, limit)))?
+}
+
+/// Send one message, giving up if the client stops reading.
+async fn send<T, W>(pipe: &mut W, message: &T, limits: &IpcConfig) -> Result<()>
+where
+    T: Serialize,
+    W: AsyncWriteExt + Unpin,
+{
+    timed(limits.write_timeout(), "sending a response", write_message(pipe, message)).await
+}
+
+/// Run database work on the blocking thread pool, off the runtime's
+/// worker threads.
+async fn blocking<T, F>(work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| FFIError::Ipc(format!("Database task failed: {}", e)))?
+}
+
+/// Handle a search request.
+///
+/// Executes the search, reconstructs paths, merges any Windows Search
+/// fallback results, ranks the page, and returns SearchResponse. Streamed
+/// requests get the index results in chunks as their paths are resolved,
+/// then the fallback results and a terminating frame with the totals.
+#[allow(clippy::too_many_arguments)]
+async fn handle_search(
+    pipe: &mut PipeWriter,
+    request: SearchRequest,
+    db: Arc<DatabasePool>,
+    windows_search: Option<Arc<WindowsSearchFallback>>,
+    custom_ranker: Option<Arc<dyn Ranker>>,
+    frecency: Arc<FrecencyCache>,
+    limits: &IpcConfig,
+    cancel: &CancelToken,
+) -> Result<()> {
     tracing::debug!(
-        "Search request: query='{}', limit={}, offset={}",
+        "Search request: query='{}', limit={}, offset={}, stream={}",
         request.query,
         request.limit,
-        request.offset
+        request.offset,
+        request.stream
     );
 
     let start = Instant::now();
 
+    // Full filter syntax; text that does not parse yet (e.g. `size:>` while
+    // typing) is searched as a plain name, as before filters existed
+    let mut parsed = parse_query(&request.query).unwrap_or_else(|e| {
+        tracing::debug!("Searching {:?} as a name: {}", request.query, e);
+        ParsedQuery {
+            pattern: Some(request.query.trim().to_string()).filter(|p| !p.is_empty()),
+            ..Default::default()
+        }
+    });
+    // Sort keys typed in the query win over the client's
+    if parsed.sort.is_empty() {
+        parsed.sort = request.sort.clone();
+    }
+
+    // Relevance ranks by default; explicit sort keys keep their order
+    let ranking = if request.ranking == Ranking::Relevance && !parsed.sort.is_empty() {
+        Ranking::Alphabetical
+    } else {
+        request.ranking
+    };
+
     // Execute search
-    let (file_entries, total_count) = {
-        let conn = db.lock().map_err(|e| {
-            FFIError::Ipc(format!("Failed to acquire database lock: {}", e))
-        })?;
+    let (file_entries, ranker) = {
+        let (db, parsed, cancel) = (db.clone(), parsed.clone(), cancel.clone());
+        let (limit, offset) = (request.limit, request.offset);
+        blocking(move || {
+            let conn = db.reader()?;
+
+            run_cancellable(conn.conn(), &cancel, || {
+                // Search files (this returns db::ops::FileEntry); for relevance, the
+                // best name matches are selected so the limit does not cut them off
+                let entries = if ranking == Ranking::Relevance {
+                    conn.search_by_relevance(&parsed, limit, offset)?
+                } else {
+                    conn.search(&parsed, limit, offset)?
+                };
 
-        // Search files (this returns db::ops::FileEntry)
-        let entries = search_files(conn.conn(), &request.query, request.limit)?;
-        let total = entries.len(); // TODO: Implement total count query for pagination
+                let ranker = ranker_for(conn.conn(), ranking, custom_ranker, &frecency)?;
 
-        (entries, total)
+                Ok((entries, ranker))
+            })
+        })
+        .await?
     };
+    let page_len = file_entries.len();
 
-    // Convert FileEntry to FileResult with reconstructed paths
-    let mut results = Vec::with_capacity(file_entries.len());
-    for entry in file_entries {
-        // Reconstruct full path
-        let path = if let Some(file_ref) = entry.file_ref {
-            let conn = db.lock().map_err(|e| {
-                FFIError::Ipc(format!("Failed to acquire database lock: {}", e))
-            })?;
-
-            // Get volume info for drive letter
-            let volume_letter = {
-                // Query volume drive letter - we need to look it up
-                let vol_result = conn.conn().query_row(
-                    "SELECT drive_letter FROM volumes WHERE id = ?1",
-                    rusqlite::params![entry.volume_id],
-                    |row: &rusqlite::Row| row.get::<_, String>(0),
-                );
-                vol_result.ok()
-            };
+    // Convert FileEntry to FileResult with reconstructed paths; streamed
+    // results are sent as they are resolved
+    let mut results = if request.stream {
+        stream_results(pipe, &request, file_entries, &db, ranker.as_deref(), limits, cancel).await?;
+        Vec::new()
+    } else {
+        let (db, cancel) = (db.clone(), cancel.clone());
+        let results = blocking(move || file_results(&db, file_entries, &cancel)).await?;
+        // Hardlinks and virtual entries can resolve to the same path
+        if request.show_all_links {
+            results
+        } else {
+            dedup_by_path(results)
+        }
+    };
+
+    // Non-indexed volumes: append Windows Search results to the first page
+    if let Some(fallback) = windows_search.filter(|_| request.offset == 0) {
+        let query = request.query.clone();
+        match tokio::task::spawn_blocking(move || fallback.search(&query)).await {
+            Ok(Ok(extra)) => results.extend(extra),
+            Ok(Err(e)) => tracing::warn!("Windows Search fallback failed: {}", e),
+            Err(e) => tracing::warn!("Windows Search fallback task failed: {}", e),
+        }
+    }
+
+    if let Some(ranker) = ranker {
+        ranker.rank(&request.query, &mut results);
+    }
+
+    let (total_count, counts) = {
+        let (db, request, cancel) = (db.clone(), request.clone(), cancel.clone());
+        blocking(move || count_matches(&db, &request, &parsed, page_len, &cancel)).await?
+    };
+    let search_time_ms = start.elapsed().as_millis() as u64;
+
+    tracing::debug!("Search completed: {} results in {}ms", page_len, search_time_ms);
 
-            let reconstructed = reconstruct_path(conn.conn(), entry.volume_id, file_ref)?;
+    if request.stream {
+        if !results.is_empty() {
+            send(pipe, &SearchFrame::Results { results }, limits).await?;
+        }
+        let done = SearchFrame::Done {
+            total_count,
+            search_time_ms,
+            counts,
+        };
+        return send(pipe, &done, limits).await;
+    }
+
+    // Build response
+    let response = SearchResponse {
+        results,
+        total_count,
+        search_time_ms,
+        counts,
+    };
+
+    // Send response
+    send(pipe, &response, limits).await
+}
+
+/// Resolve and send a page of results in chunks, ranking each chunk.
+///
+/// The first chunk is small so clients can show it right away.
+async fn stream_results(
+    pipe: &mut PipeWriter,
+    request: &SearchRequest,
+    entries: Vec<FileEntry>,
+    db: &Arc<DatabasePool>,
+    ranker: Option<&dyn Ranker>,
+    limits: &IpcConfig,
+    cancel: &CancelToken,
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    let mut entries = entries.into_iter();
+    let mut chunk_size = FIRST_CHUNK_SIZE;
+
+    loop {
+        let chunk: Vec<FileEntry> = entries.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        chunk_size = STREAM_CHUNK_SIZE;
+
+        let (db, cancel) = (db.clone(), cancel.clone());
+        let mut results = blocking(move || file_results(&db, chunk, &cancel)).await?;
+        if !request.show_all_links {
+            results = dedup_by_path(results);
+            results.retain(|r| seen.insert(r.path.to_lowercase()));
+        }
+        if let Some(ranker) = ranker {
+            ranker.rank(&request.query, &mut results);
+        }
+        send(pipe, &SearchFrame::Results { results }, limits).await?;
+    }
+}
+
+/// The files modified at or after `since`, newest first, one per path.
+fn recent_files(db: &DatabasePool, since: i64, limit: usize, cancel: &CancelToken) -> Result<Vec<FileResult>> {
+    let entries = {
+        let conn = db.reader()?;
+        run_cancellable(conn.conn(), cancel, || conn.search(&ParsedQuery::recent_files(since), limit, 0))?
+    };
+    Ok(dedup_by_path(file_results(db, entries, cancel)?))
+}
+
+/// Convert entries to results with their full paths.
+fn file_results(db: &DatabasePool, entries: Vec<FileEntry>, cancel: &CancelToken) -> Result<Vec<FileResult>> {
+    let conn = db.reader()?;
+    let mut results = Vec::with_capacity(entries.len());
+    for entry in entries {
+        cancel.check()?;
+
+        // Drive letter and state of the volume
+        let (volume_letter, volume_state) = conn
+            .conn()
+            .query_row(
+                "SELECT drive_letter, state FROM volumes WHERE id = ?1",
+                rusqlite::params![entry.volume_id],
+                |row: &rusqlite::Row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .ok()
+            .unzip();
+
+        // Reconstruct full path, and where it was deleted from if it is in the Recycle Bin
+        let mut original_path = None;
+        let path = if entry.file_ref.is_some() {
+            let relative = conn.entry_path(&entry)?.unwrap_or_default();
+            original_path = recycled_original_path(conn.conn(), entry.volume_id, &relative)?;
 
             // Prepend drive letter if available
             if let Some(letter) = volume_letter {
-                format!("{}\\{}", letter, reconstructed.display())
+                format!("{}\\{}", letter, relative)
             } else {
-                reconstructed.display().to_string()
+                relative
             }
         } else {
             entry.name.clone()
@@ -160,30 +686,177 @@ async fn handle_client(mut pipe: NamedPipeServer, db: Arc<Mutex<Database>>) -> R
             size: entry.size,
             modified: entry.modified.unwrap_or(0),
             is_dir: entry.is_dir,
+            duplicates: 0,
+            source: ResultSource::Index,
+            rank: None,
+            volume_state,
+            original_path,
         });
     }
+    Ok(results)
+}
 
-    let search_time_ms = start.elapsed().as_millis() as u64;
+/// Count a search's matches across all pages, and each extra count query.
+fn count_matches(
+    db: &DatabasePool,
+    request: &SearchRequest,
+    parsed: &ParsedQuery,
+    page_len: usize,
+    cancel: &CancelToken,
+) -> Result<(usize, Vec<usize>)> {
+    let conn = db.reader()?;
 
-    // Build response
-    let response = SearchResponse {
-        results,
-        total_count,
-        search_time_ms,
+    run_cancellable(conn.conn(), cancel, || {
+        // Matches across all pages; skip the count when this page holds them all
+        let total = if request.offset == 0 && page_len < request.limit {
+            page_len
+        } else {
+            conn.count(parsed)?
+        };
+
+        // Batch count API: count each extra query without fetching rows
+        let counts = if request.count_queries.is_empty() {
+            Vec::new()
+        } else {
+            count_queries(conn.conn(), &request.count_queries)?
+        };
+
+        Ok((total, counts))
+    })
+}
+
+/// Stream every match of a query to the client as [`ExportFrame`]s.
+///
+/// A reader is checked out a page at a time, so a large export doesn't
+/// hold one for its whole run; each page must finish within the query
+/// time limit. Failures are reported to the client in the stream.
+async fn handle_export(
+    pipe: &mut NamedPipeServer,
+    db: &Arc<DatabasePool>,
+    query: &str,
+    format: ExportFormat,
+    limits: &IpcConfig,
+) -> Result<()> {
+    let prepared = {
+        let (db, query) = (db.clone(), query.to_string());
+        blocking(move || {
+            let parsed = parse_query(&query)?;
+            let conn = db.reader()?;
+            Exporter::new(&conn, parsed, format)
+        })
+        .await
+    };
+    let mut exporter = match prepared {
+        Ok(exporter) => exporter,
+        Err(e) => return send(pipe, &ExportFrame::Failed { message: e.to_string() }, limits).await,
     };
 
-    tracing::debug!(
-        "Search completed: {} results in {}ms",
-        response.results.len(),
-        response.search_time_ms
-    );
+    // The header goes out with the first page
+    let mut text = exporter.header().unwrap_or_default().into_bytes();
+    loop {
+        let (db, cancel) = (db.clone(), CancelToken::new(limits.query_timeout()));
+        let (returned, page_text, page) = blocking(move || {
+            let conn = db.reader()?;
+            let page = run_cancellable(conn.conn(), &cancel, || exporter.write_page(&conn, &mut text));
+            Ok((exporter, text, page))
+        })
+        .await?;
+        exporter = returned;
+        let more = match page {
+            Ok(more) => more,
+            Err(e) => {
+                tracing::warn!("Export of {:?} stopped after {} rows: {}", query, exporter.rows(), e);
+                return send(pipe, &ExportFrame::Failed { message: e.to_string() }, limits).await;
+            }
+        };
 
-    // Send response
-    write_message(&mut pipe, &response).await?;
+        // Rows are written from strings, so the text is valid UTF-8
+        let data = String::from_utf8(page_text)
+            .map_err(|e| FFIError::Ipc(format!("Export produced invalid text: {}", e)))?;
+        send(pipe, &ExportFrame::Data { text: data }, limits).await?;
+        if !more {
+            return send(pipe, &ExportFrame::Done { rows: exporter.rows() }, limits).await;
+        }
+        text = Vec::new();
+    }
+}
+
+/// Re-read the configuration and apply its search settings.
+///
+/// Indexing settings (volumes, excludes, polling, throttling) are applied
+/// by the service's config watcher once it sees `config.toml` change.
+fn reload_config(db: &DatabasePool, windows_search: &SharedFallback) -> Result<String> {
+    apply_search_settings(db, windows_search, &Config::load()?)?;
+
+    tracing::info!("Configuration reloaded");
+    Ok("Search settings reloaded; indexing settings follow changes to config.toml".to_string())
+}
 
+/// Hide the configured attributes from results and replace the Windows
+/// Search fallback.
+fn apply_search_settings(db: &DatabasePool, windows_search: &SharedFallback, config: &Config) -> Result<()> {
+    db.set_excluded_attributes(&config.search.hidden_attributes());
+
+    let fallback = WindowsSearchFallback::from_config(config);
+    if let Some(ref fallback) = fallback {
+        tracing::info!("Windows Search fallback enabled for volumes {:?}", fallback.volumes());
+    }
+    *windows_search
+        .write()
+        .map_err(|e| FFIError::Ipc(format!("Failed to acquire Windows Search lock: {}", e)))? =
+        fallback.map(Arc::new);
     Ok(())
 }
 
+/// Pick the ranker for a request, or None to keep the query order.
+///
+/// The frecency ranker comes from `frecency`, built from the open history
+/// only when it changed or the cached one is stale.
+fn ranker_for(
+    conn: &rusqlite::Connection,
+    ranking: Ranking,
+    custom_ranker: Option<Arc<dyn Ranker>>,
+    frecency: &FrecencyCache,
+) -> Result<Option<Arc<dyn Ranker>>> {
+    let now = chrono::Utc::now().timestamp();
+    let cached = || frecency.get_or_build(now, || frecency_ranker(conn, now));
+    Ok(match ranking {
+        Ranking::Relevance => Some(Arc::new(RelevanceRanker::new(cached()?, now))),
+        Ranking::Alphabetical => None,
+        Ranking::Fuzzy => Some(Arc::new(FuzzyRanker)),
+        Ranking::Frecency => Some(cached()?),
+        Ranking::Custom => custom_ranker,
+    })
+}
+
+/// Build a frecency ranker from the recorded open history.
+fn frecency_ranker(conn: &rusqlite::Connection, now: i64) -> Result<FrecencyRanker> {
+    let mut ranker = FrecencyRanker::new();
+    let samples = get_open_samples(conn)?;
+    for record in get_open_history(conn)? {
+        match samples.get(&record.path) {
+            Some(samples) => ranker.record_samples(&record.path, record.open_count, samples, now),
+            None => ranker.record(&record.path, record.open_count, record.last_opened, now),
+        }
+    }
+    Ok(ranker)
+}
+
+/// Count matches for each query string in one batch.
+///
+/// Queries that fail to parse count as zero rather than failing the whole search.
+fn count_queries(conn: &rusqlite::Connection, queries: &[String]) -> Result<Vec<usize>> {
+    let parsed: Vec<Option<ParsedQuery>> = queries.iter().map(|q| parse_query(q).ok()).collect();
+
+    let valid: Vec<ParsedQuery> = parsed.iter().flatten().cloned().collect();
+    let mut counts = count_query_matches_batch(conn, &valid)?.into_iter();
+
+    Ok(parsed
+        .iter()
+        .map(|p| if p.is_some() { counts.next().unwrap_or(0) } else { 0 })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
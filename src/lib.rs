@@ -22,23 +22,35 @@ pub enum VolumeState {
     Online,
     /// Volume unmounted, data preserved. Contains timestamp when it went offline.
     Offline { since: i64 },
-    /// Initial indexing in progress.
-    Indexing,
+    /// Initial indexing in progress, with the share of the volume done.
+    Indexing { percent: u8 },
     /// Background rescan in progress (after journal wrap or reconnect).
     Rescanning,
     /// Configured but not enabled for indexing.
     Disabled,
+    /// Imported from a snapshot of another machine's index: searchable,
+    /// never scanned and never cleaned up.
+    Imported,
+    /// Read-only index of a VSS shadow copy (previous version) of a volume;
+    /// removed when the shadow copy disappears.
+    Shadow,
 }
 
 impl VolumeState {
     /// Parse from database string representation.
-    pub fn from_db(state_str: &str, offline_since: Option<i64>) -> Self {
+    ///
+    /// `scan_percent` is the progress stored with an `indexing` state.
+    pub fn from_db(state_str: &str, offline_since: Option<i64>, scan_percent: Option<i64>) -> Self {
         match state_str {
             "online" => VolumeState::Online,
             "offline" => VolumeState::Offline { since: offline_since.unwrap_or(0) },
-            "indexing" => VolumeState::Indexing,
+            "indexing" => VolumeState::Indexing {
+                percent: scan_percent.unwrap_or(0).clamp(0, 100) as u8,
+            },
             "rescanning" => VolumeState::Rescanning,
             "disabled" => VolumeState::Disabled,
+            "imported" => VolumeState::Imported,
+            "shadow" => VolumeState::Shadow,
             _ => VolumeState::Online, // Default fallback
         }
     }
@@ -48,9 +60,11 @@ impl VolumeState {
         match self {
             VolumeState::Online => "online",
             VolumeState::Offline { .. } => "offline",
-            VolumeState::Indexing => "indexing",
+            VolumeState::Indexing { .. } => "indexing",
             VolumeState::Rescanning => "rescanning",
             VolumeState::Disabled => "disabled",
+            VolumeState::Imported => "imported",
+            VolumeState::Shadow => "shadow",
         }
     }
 
@@ -61,6 +75,14 @@ impl VolumeState {
             _ => None,
         }
     }
+
+    /// Get the indexing progress in percent if state is Indexing.
+    pub fn progress(&self) -> Option<u8> {
+        match self {
+            VolumeState::Indexing { percent } => Some(*percent),
+            _ => None,
+        }
+    }
 }
 
 /// FFI error types covering all failure modes.
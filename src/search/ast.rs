@@ -0,0 +1,388 @@
+//! Typed query AST for programmatic searches.
+//!
+//! [`Query`] is the public, stable representation of a search. Build one
+//! with [`Query::builder`] instead of formatting filter strings, or get one
+//! from [`Query::parse`]. It converts into the [`ParsedQuery`] the SQL
+//! builders take, and formats back to search syntax with `to_string()` for
+//! APIs that carry query text (such as the IPC search request).
+//!
+//! # Examples
+//!
+//! ```
+//! use ffi::search::ast::Query;
+//! use ffi::search::{build_sql_query, FileType};
+//!
+//! let query = Query::builder()
+//!     .name("report")
+//!     .extension("pdf")
+//!     .larger_than(10 * 1024 * 1024)
+//!     .file_type(FileType::File)
+//!     .build();
+//!
+//! assert_eq!(query.to_string(), "report ext:pdf size:>10485760b type:file");
+//! let (sql, _params) = build_sql_query(&query.into());
+//! assert!(sql.contains("name LIKE ?"));
+//! ```
+
+use std::fmt;
+
+use chrono::{Local, TimeZone};
+
+use crate::Result;
+
+use super::filters::{Condition, DateOp, FileAttribute, FileType, Filter, OfflineScope, SizeOp};
+use super::parser::{parse_query, ParsedQuery, SORT_KEYS};
+use super::sort::SortSpec;
+
+/// A search query: optional name pattern, filters, boolean conditions, and sort order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pattern: Option<String>,
+    filters: Vec<Filter>,
+    conditions: Vec<Condition>,
+    sort: Vec<SortSpec>,
+}
+
+impl Query {
+    /// Start building a query.
+    pub fn builder() -> QueryBuilder {
+        QueryBuilder::default()
+    }
+
+    /// Parse search syntax (e.g. `report ext:pdf size:>10mb`).
+    pub fn parse(input: &str) -> Result<Self> {
+        parse_query(input).map(Self::from)
+    }
+
+    /// Name pattern with wildcards (`*` and `?`), if any.
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_deref()
+    }
+
+    /// Filters, all of which must match.
+    pub fn filters(&self) -> &[Filter] {
+        &self.filters
+    }
+
+    /// Conditions using OR, NOT or grouping, all of which must match.
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    /// Sort order (primary first); empty means name order.
+    pub fn sort(&self) -> &[SortSpec] {
+        &self.sort
+    }
+
+    /// Whether the query has no pattern, filters or conditions.
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_none() && self.filters.is_empty() && self.conditions.is_empty()
+    }
+}
+
+impl From<ParsedQuery> for Query {
+    fn from(parsed: ParsedQuery) -> Self {
+        Self {
+            pattern: parsed.pattern,
+            filters: parsed.filters,
+            conditions: parsed.conditions,
+            sort: parsed.sort,
+        }
+    }
+}
+
+impl From<Query> for ParsedQuery {
+    fn from(query: Query) -> Self {
+        Self {
+            pattern: query.pattern,
+            filters: query.filters,
+            conditions: query.conditions,
+            sort: query.sort,
+        }
+    }
+}
+
+/// Formats the query as search syntax.
+///
+/// Sizes are written in bytes and dates as the local calendar day, so a
+/// date filter only survives re-parsing if it falls on local midnight.
+/// Sort keys come last, each with its direction.
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut terms: Vec<String> = Vec::new();
+        if let Some(ref pattern) = self.pattern {
+            terms.push(pattern.clone());
+        }
+
+        terms.extend(self.filters.iter().map(filter_term));
+        terms.extend(self.conditions.iter().map(condition_term));
+        terms.extend(self.sort.iter().map(sort_term));
+
+        write!(f, "{}", terms.join(" "))
+    }
+}
+
+/// Format a sort key as a `sort:` term.
+fn sort_term(spec: &SortSpec) -> String {
+    let key = SORT_KEYS
+        .iter()
+        .find(|(_, field)| *field == spec.field)
+        .map_or("name", |(key, _)| *key);
+    format!("sort:{}-{}", key, if spec.descending { "desc" } else { "asc" })
+}
+
+/// Format a filter as a search term.
+fn filter_term(filter: &Filter) -> String {
+    match filter {
+        Filter::Extension(ext) => format!("ext:{}", quote_value(ext)),
+        Filter::Size(op, bytes) => format!("size:{}{}b", op.to_sql(), bytes),
+        Filter::Type(FileType::File) => "type:file".to_string(),
+        Filter::Type(FileType::Folder) => "type:folder".to_string(),
+        Filter::Type(FileType::Link) => "type:link".to_string(),
+        Filter::Modified(op, timestamp) => format!("modified:{}{}", op.to_sql(), local_date(*timestamp)),
+        Filter::Created(op, timestamp) => format!("created:{}{}", op.to_sql(), local_date(*timestamp)),
+        Filter::PathScope(path) => format!("path:{}", quote_value(path)),
+        Filter::Volume(name) => format!("drive:{}", quote_value(name)),
+        Filter::Regex(pattern) => format!("regex:{}", quote_value(pattern)),
+        Filter::Attribute(attribute) => format!("attrib:{}", attribute.name()),
+        Filter::Stream(name) => format!("stream:{}", quote_value(name)),
+        Filter::Offline(scope) => format!("offline:{}", scope.name()),
+        Filter::Content(text) => format!("content:{}", quote_value(text)),
+        Filter::Tag(tag) => format!("tag:{}", quote_value(tag)),
+        Filter::RecycleBin => "in:recyclebin".to_string(),
+    }
+}
+
+/// Local calendar day of a Unix timestamp, as `YYYY-MM-DD`.
+fn local_date(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| "1970-01-01".to_string())
+}
+
+/// Format a condition as search syntax. OR binds tighter than the
+/// implicit AND, so only groups and negated alternatives need parentheses.
+fn condition_term(condition: &Condition) -> String {
+    let join = |conditions: &[Condition], separator: &str| {
+        conditions.iter().map(condition_term).collect::<Vec<_>>().join(separator)
+    };
+    match condition {
+        Condition::Name(pattern) => pattern.clone(),
+        Condition::Filter(filter) => filter_term(filter),
+        Condition::All(conditions) => format!("({})", join(conditions, " ")),
+        Condition::Any(conditions) => join(conditions, " OR "),
+        Condition::Not(inner) => match inner.as_ref() {
+            Condition::Any(conditions) => format!("-({})", join(conditions, " OR ")),
+            inner => format!("-{}", condition_term(inner)),
+        },
+    }
+}
+
+/// Quote a filter value containing whitespace.
+fn quote_value(value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Builder for [`Query`].
+#[derive(Debug, Clone, Default)]
+pub struct QueryBuilder {
+    query: Query,
+}
+
+impl QueryBuilder {
+    /// Match names against a pattern (substring, or `*`/`?` wildcards).
+    pub fn name(mut self, pattern: impl Into<String>) -> Self {
+        self.query.pattern = Some(pattern.into());
+        self
+    }
+
+    /// Match names ending in `.<ext>` (without the dot).
+    pub fn extension(mut self, ext: impl Into<String>) -> Self {
+        let ext: String = ext.into();
+        let ext = ext.trim_start_matches('.').to_string();
+        self.query.filters.push(Filter::Extension(ext));
+        self
+    }
+
+    /// Compare the size in bytes.
+    pub fn size(mut self, op: SizeOp, bytes: i64) -> Self {
+        self.query.filters.push(Filter::Size(op, bytes));
+        self
+    }
+
+    /// Files larger than `bytes`.
+    pub fn larger_than(self, bytes: i64) -> Self {
+        self.size(SizeOp::GreaterThan, bytes)
+    }
+
+    /// Files smaller than `bytes`.
+    pub fn smaller_than(self, bytes: i64) -> Self {
+        self.size(SizeOp::LessThan, bytes)
+    }
+
+    /// Only files, only folders or only links.
+    pub fn file_type(mut self, file_type: FileType) -> Self {
+        self.query.filters.push(Filter::Type(file_type));
+        self
+    }
+
+    /// Compare the last modified time (Unix timestamp).
+    pub fn modified(mut self, op: DateOp, timestamp: i64) -> Self {
+        self.query.filters.push(Filter::Modified(op, timestamp));
+        self
+    }
+
+    /// Modified at or after a Unix timestamp.
+    pub fn modified_since(self, timestamp: i64) -> Self {
+        self.modified(DateOp::GreaterEqual, timestamp)
+    }
+
+    /// Compare the creation time (Unix timestamp).
+    pub fn created(mut self, op: DateOp, timestamp: i64) -> Self {
+        self.query.filters.push(Filter::Created(op, timestamp));
+        self
+    }
+
+    /// Only entries below a folder (e.g. `C:\Projects`).
+    pub fn under(mut self, path: impl Into<String>) -> Self {
+        self.query.filters.push(Filter::PathScope(path.into()));
+        self
+    }
+
+    /// Only files whose indexed text contains a phrase.
+    pub fn content(mut self, text: impl Into<String>) -> Self {
+        self.query.filters.push(Filter::Content(text.into()));
+        self
+    }
+
+    /// Only files the user tagged with `tag`.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.query.filters.push(Filter::Tag(tag.into()));
+        self
+    }
+
+    /// Only deleted files and folders still in the Recycle Bin.
+    pub fn in_recycle_bin(mut self) -> Self {
+        self.query.filters.push(Filter::RecycleBin);
+        self
+    }
+
+    /// Only entries of one volume (e.g. `D:`).
+    pub fn volume(mut self, name: impl Into<String>) -> Self {
+        self.query.filters.push(Filter::Volume(name.into()));
+        self
+    }
+
+    /// Match names against a regular expression (case-insensitive).
+    pub fn regex(mut self, pattern: impl Into<String>) -> Self {
+        self.query.filters.push(Filter::Regex(pattern.into()));
+        self
+    }
+
+    /// Only entries with a file attribute (hidden, system, ...).
+    pub fn attribute(mut self, attribute: FileAttribute) -> Self {
+        self.query.filters.push(Filter::Attribute(attribute));
+        self
+    }
+
+    /// Only NTFS alternate data streams with a matching name (`*` for any).
+    pub fn stream(mut self, name: impl Into<String>) -> Self {
+        self.query.filters.push(Filter::Stream(name.into()));
+        self
+    }
+
+    /// Keep or drop entries of offline volumes.
+    pub fn offline(mut self, scope: OfflineScope) -> Self {
+        self.query.filters.push(Filter::Offline(scope));
+        self
+    }
+
+    /// Require a condition built from OR, NOT or grouping.
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.query.conditions.push(condition);
+        self
+    }
+
+    /// Add a sort key; the first added is the primary sort.
+    pub fn sort_by(mut self, spec: SortSpec) -> Self {
+        self.query.sort.push(spec);
+        self
+    }
+
+    /// Finish the query.
+    pub fn build(self) -> Query {
+        self.query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SortField;
+
+    #[test]
+    fn test_builder_matches_parser() {
+        let midnight = Local.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap().timestamp();
+        let built = Query::builder()
+            .name("*.log")
+            .extension(".txt")
+            .smaller_than(1024)
+            .file_type(FileType::Folder)
+            .modified(DateOp::GreaterThan, midnight)
+            .created(DateOp::LessThan, midnight)
+            .under(r"C:\My Projects")
+            .volume("C:")
+            .regex(r"^v\d")
+            .attribute(FileAttribute::Hidden)
+            .stream("Zone.*")
+            .offline(OfflineScope::Exclude)
+            .content("TODO refactor")
+            .tag("tax 2024")
+            .in_recycle_bin()
+            .build();
+
+        let text = built.to_string();
+        assert_eq!(
+            text,
+            r#"*.log ext:txt size:<1024b type:folder modified:>2024-01-15 created:<2024-01-15 path:"C:\My Projects" drive:C: regex:^v\d attrib:hidden stream:Zone.* offline:false content:"TODO refactor" tag:"tax 2024" in:recyclebin"#
+        );
+        assert_eq!(Query::parse(&text).unwrap(), built);
+    }
+
+    #[test]
+    fn test_conditions_round_trip() {
+        let query = Query::parse("(report OR invoice) ext:pdf -draft NOT (old OR bak) -(a b)").unwrap();
+        let text = query.to_string();
+        assert_eq!(text, "ext:pdf report OR invoice -draft -(old OR bak) -(a b)");
+        assert_eq!(Query::parse(&text).unwrap(), query);
+
+        let built = Query::builder()
+            .condition(Condition::Not(Box::new(Condition::Filter(Filter::Type(FileType::Folder)))))
+            .build();
+        assert_eq!(built.to_string(), "-type:folder");
+        assert!(!built.is_empty());
+    }
+
+    #[test]
+    fn test_sort_and_conversion() {
+        let query = Query::builder()
+            .extension("pdf")
+            .sort_by(SortSpec::desc(SortField::Size))
+            .build();
+        assert_eq!(query.sort(), &[SortSpec::desc(SortField::Size)]);
+        assert!(!query.is_empty());
+        assert_eq!(query.to_string(), "ext:pdf sort:size-desc");
+        assert_eq!(Query::parse(&query.to_string()).unwrap(), query);
+
+        let parsed: ParsedQuery = query.clone().into();
+        assert_eq!(parsed.filters, vec![Filter::Extension("pdf".to_string())]);
+        assert_eq!(Query::from(parsed), query);
+        assert!(Query::builder().build().is_empty());
+    }
+}
@@ -1,7 +1,12 @@
 //! Filter types for search queries.
 //!
 //! Defines the structured filter types that result from parsing
-//! search syntax like `ext:pdf`, `size:>10mb`, `type:folder`.
+//! search syntax like `ext:pdf`, `size:>10mb`, `type:folder`, `drive:D`,
+//! `attrib:hidden`, `stream:*`, `offline:false`, `content:"TODO refactor"`,
+//! `tag:work`, `in:recyclebin`.
+
+/// Folder at the root of each volume holding its Recycle Bin.
+pub const RECYCLE_BIN: &str = "$Recycle.Bin";
 
 /// A parsed search filter.
 #[derive(Debug, Clone, PartialEq)]
@@ -14,8 +19,45 @@ pub enum Filter {
     Type(FileType),
     /// Modified date filter: modified:>2024-01-01 (value as Unix timestamp)
     Modified(DateOp, i64),
+    /// Created date filter: created:>2024-01-01 (value as Unix timestamp)
+    Created(DateOp, i64),
     /// Path scope filter: path:C:\Projects
     PathScope(String),
+    /// Volume filter: drive:D, vol:D: (the indexed volume name, e.g. `D:`)
+    Volume(String),
+    /// Regular expression on the name: regex:^IMG_\d{4}\.jpg$ (case-insensitive)
+    Regex(String),
+    /// Attribute filter: attrib:hidden
+    Attribute(FileAttribute),
+    /// NTFS alternate data streams by stream name: stream:Zone.Identifier
+    /// (wildcards allowed, stream:* for any)
+    Stream(String),
+    /// Entries of disconnected volumes: offline:false
+    Offline(OfflineScope),
+    /// Phrase in the indexed text of a file: content:"TODO refactor"
+    /// (only files covered by content indexing match)
+    Content(String),
+    /// Files tagged by the user: tag:work, tag:"tax 2024" (case-insensitive)
+    Tag(String),
+    /// Deleted files and folders still in the Recycle Bin: in:recyclebin
+    /// (left out of results unless asked for)
+    RecycleBin,
+}
+
+/// A boolean combination of name words and filters, from OR, NOT and
+/// parentheses: `(report OR invoice) -draft`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Name word with optional wildcards, matched like the query pattern
+    Name(String),
+    /// A single filter
+    Filter(Filter),
+    /// All must match: `(a b)`
+    All(Vec<Condition>),
+    /// At least one must match: `a OR b`
+    Any(Vec<Condition>),
+    /// Must not match: `-a`, `NOT a`
+    Not(Box<Condition>),
 }
 
 /// Comparison operators for size filters.
@@ -75,6 +117,96 @@ pub enum FileType {
     File,
     /// Directory/folder
     Folder,
+    /// Symlink, junction or other reparse point
+    Link,
+}
+
+/// File attribute for attrib filters, stored as Windows `FILE_ATTRIBUTE_*` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAttribute {
+    /// attrib:readonly
+    ReadOnly,
+    /// attrib:hidden
+    Hidden,
+    /// attrib:system
+    System,
+    /// attrib:archive
+    Archive,
+    /// attrib:compressed
+    Compressed,
+    /// attrib:encrypted
+    Encrypted,
+}
+
+impl FileAttribute {
+    /// All attributes, in the order they are listed in help.
+    pub const ALL: [FileAttribute; 6] = [
+        FileAttribute::ReadOnly,
+        FileAttribute::Hidden,
+        FileAttribute::System,
+        FileAttribute::Archive,
+        FileAttribute::Compressed,
+        FileAttribute::Encrypted,
+    ];
+
+    /// Name used in search syntax.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileAttribute::ReadOnly => "readonly",
+            FileAttribute::Hidden => "hidden",
+            FileAttribute::System => "system",
+            FileAttribute::Archive => "archive",
+            FileAttribute::Compressed => "compressed",
+            FileAttribute::Encrypted => "encrypted",
+        }
+    }
+
+    /// Windows `FILE_ATTRIBUTE_*` flag stored in the index.
+    pub fn flag(&self) -> u32 {
+        match self {
+            FileAttribute::ReadOnly => 0x1,
+            FileAttribute::Hidden => 0x2,
+            FileAttribute::System => 0x4,
+            FileAttribute::Archive => 0x20,
+            FileAttribute::Compressed => 0x800,
+            FileAttribute::Encrypted => 0x4000,
+        }
+    }
+
+    /// Look up an attribute by its search syntax name (case-insensitive).
+    pub fn from_name(name: &str) -> Option<FileAttribute> {
+        Self::ALL.into_iter().find(|a| a.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Whether an offline filter keeps entries of disconnected volumes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflineScope {
+    /// offline:true - entries of every volume, as without the filter
+    Include,
+    /// offline:false - only entries of connected volumes
+    Exclude,
+    /// offline:only - only entries of disconnected volumes
+    Only,
+}
+
+impl OfflineScope {
+    /// All scopes, in the order they are listed in help.
+    pub const ALL: [OfflineScope; 3] = [OfflineScope::Include, OfflineScope::Exclude, OfflineScope::Only];
+
+    /// Name used in search syntax.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OfflineScope::Include => "true",
+            OfflineScope::Exclude => "false",
+            OfflineScope::Only => "only",
+        }
+    }
+
+    /// Look up a scope by its search syntax name (case-insensitive).
+    pub fn from_name(name: &str) -> Option<OfflineScope> {
+        Self::ALL.into_iter().find(|s| s.name().eq_ignore_ascii_case(name))
+    }
 }
 
 #[cfg(test)]
@@ -96,4 +228,22 @@ mod tests {
         assert_eq!(DateOp::LessThan.to_sql(), "<");
         assert_eq!(DateOp::LessEqual.to_sql(), "<=");
     }
+
+    #[test]
+    fn test_file_attribute_names() {
+        for attribute in FileAttribute::ALL {
+            assert_eq!(FileAttribute::from_name(attribute.name()), Some(attribute));
+        }
+        assert_eq!(FileAttribute::from_name("Hidden"), Some(FileAttribute::Hidden));
+        assert_eq!(FileAttribute::from_name("secret"), None);
+    }
+
+    #[test]
+    fn test_offline_scope_names() {
+        for scope in OfflineScope::ALL {
+            assert_eq!(OfflineScope::from_name(scope.name()), Some(scope));
+        }
+        assert_eq!(OfflineScope::from_name("Only"), Some(OfflineScope::Only));
+        assert_eq!(OfflineScope::from_name("yes"), None);
+    }
 }
@@ -3,10 +3,27 @@
 //! This module provides search syntax parsing using a pest grammar,
 //! enabling queries like `report ext:pdf size:>10mb modified:today`.
 
+pub mod ast;
 pub mod filters;
 pub mod parser;
+pub mod project;
+pub mod rank;
 pub mod query;
+pub mod sort;
+pub mod syntax;
+pub mod windows_search;
 
+pub use ast::{Query, QueryBuilder};
 pub use filters::*;
 pub use parser::{parse_query, ParsedQuery};
-pub use query::{build_sql_query, build_sql_query_with_limit, SqlParam};
+pub use project::{find_project_root, ProjectScope};
+pub use rank::{
+    AlphabeticalRanker, FrecencyCache, FrecencyRanker, FuzzyRanker, Ranker, Ranking, RelevanceRanker,
+};
+pub use query::{
+    build_count_query, build_relevance_query, build_sql_query, build_sql_query_page,
+    build_sql_query_with_limit, build_usage_query, SqlParam,
+};
+pub use sort::{order_by_clause, SortField, SortSpec};
+pub use syntax::{syntax_help, FilterSyntax, SyntaxHelp, SyntaxToken};
+pub use windows_search::WindowsSearchFallback;
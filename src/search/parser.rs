@@ -9,18 +9,130 @@ use pest_derive::Parser;
 
 use crate::{FFIError, Result};
 use super::filters::*;
+use super::sort::{SortField, SortSpec};
 
 #[derive(Parser)]
 #[grammar = "src/search/grammar.pest"]
 struct SearchParser;
 
+/// Size units with their multiplier in bytes.
+pub(crate) const SIZE_UNITS: &[(&str, i64)] = &[
+    ("b", 1),
+    ("kb", 1024),
+    ("mb", 1024 * 1024),
+    ("gb", 1024 * 1024 * 1024),
+    ("tb", 1024 * 1024 * 1024 * 1024),
+];
+
+/// Relative dates with the number of days before the start of today.
+pub(crate) const RELATIVE_DATES: &[(&str, i64)] = &[
+    ("today", 0),
+    ("yesterday", 1),
+    ("lastweek", 7),
+    ("lastmonth", 30),
+    ("lastyear", 365),
+];
+
+/// Comparison operators accepted before size and date values.
+pub(crate) const COMPARATORS: &[(&str, SizeOp)] = &[
+    (">=", SizeOp::GreaterEqual),
+    ("<=", SizeOp::LessEqual),
+    (">", SizeOp::GreaterThan),
+    ("<", SizeOp::LessThan),
+];
+
+/// Values accepted by `type:`.
+pub(crate) const TYPE_VALUES: &[(&str, FileType)] = &[
+    ("file", FileType::File),
+    ("folder", FileType::Folder),
+    ("dir", FileType::Folder),
+    ("directory", FileType::Folder),
+    ("link", FileType::Link),
+    ("symlink", FileType::Link),
+    ("junction", FileType::Link),
+];
+
+/// Keys accepted by `sort:`; the first one of each field is the canonical
+/// spelling.
+pub(crate) const SORT_KEYS: &[(&str, SortField)] = &[
+    ("name", SortField::Name),
+    ("ext", SortField::Extension),
+    ("extension", SortField::Extension),
+    ("size", SortField::Size),
+    ("modified", SortField::Modified),
+    ("date", SortField::Modified),
+];
+
 /// A parsed search query containing optional pattern and filters.
 #[derive(Debug, Clone, Default)]
 pub struct ParsedQuery {
     /// Name pattern with wildcards (* and ?)
     pub pattern: Option<String>,
-    /// Parsed filters (ext, size, type, modified, path)
+    /// Parsed filters (ext, size, type, modified, created, path, attrib)
     pub filters: Vec<Filter>,
+    /// Terms using OR, NOT or parentheses; like the filters, all must match
+    pub conditions: Vec<Condition>,
+    /// Sort order (primary first); empty means name order
+    pub sort: Vec<SortSpec>,
+}
+
+impl ParsedQuery {
+    /// Files modified at or after `since` (Unix timestamp), newest first:
+    /// the recent files shown for an empty search box. Alternate data
+    /// streams are left out.
+    pub fn recent_files(since: i64) -> Self {
+        Self {
+            pattern: None,
+            filters: vec![Filter::Type(FileType::File), Filter::Modified(DateOp::GreaterEqual, since)],
+            conditions: vec![Condition::Not(Box::new(Condition::Filter(Filter::Stream("*".to_string()))))],
+            sort: vec![SortSpec::desc(SortField::Modified)],
+        }
+    }
+
+    /// Leave out entries with any of `attributes`, unless the query
+    /// filters on attributes itself (`attrib:hidden` still finds hidden files).
+    pub fn exclude_attributes(&mut self, attributes: &[FileAttribute]) {
+        if attributes.is_empty() || self.mentions(&|f| matches!(f, Filter::Attribute(_)), true) {
+            return;
+        }
+        let any = attributes
+            .iter()
+            .map(|a| Condition::Filter(Filter::Attribute(*a)))
+            .collect();
+        self.conditions.push(Condition::Not(Box::new(Condition::Any(any))));
+    }
+
+    /// Leave out the Recycle Bin of every volume, unless the query asks for
+    /// it with `in:recyclebin` or a `path:` inside it (`-in:recyclebin`
+    /// doesn't).
+    pub fn exclude_recycle_bin(&mut self) {
+        let bin = RECYCLE_BIN.to_ascii_lowercase();
+        let asks = |f: &Filter| match f {
+            Filter::RecycleBin => true,
+            Filter::PathScope(path) => path.to_ascii_lowercase().contains(&bin),
+            _ => false,
+        };
+        if self.mentions(&asks, false) {
+            return;
+        }
+        // A scope without a drive covers the folder on every volume
+        let scope = Condition::Filter(Filter::PathScope(RECYCLE_BIN.to_string()));
+        self.conditions.push(Condition::Not(Box::new(scope)));
+    }
+
+    /// Whether any filter or condition matches `wanted`, counting negated
+    /// ones only if `negated`.
+    fn mentions(&self, wanted: &dyn Fn(&Filter) -> bool, negated: bool) -> bool {
+        fn mentions(condition: &Condition, wanted: &dyn Fn(&Filter) -> bool, negated: bool) -> bool {
+            match condition {
+                Condition::Name(_) => false,
+                Condition::Filter(filter) => wanted(filter),
+                Condition::All(all) | Condition::Any(all) => all.iter().any(|c| mentions(c, wanted, negated)),
+                Condition::Not(inner) => negated && mentions(inner, wanted, negated),
+            }
+        }
+        self.filters.iter().any(wanted) || self.conditions.iter().any(|c| mentions(c, wanted, negated))
+    }
 }
 
 /// Parse a search query string into structured query.
@@ -33,6 +145,11 @@ pub struct ParsedQuery {
 /// let query = parse_query("report ext:pdf").unwrap();
 /// assert_eq!(query.pattern, Some("report".to_string()));
 /// assert_eq!(query.filters.len(), 1);
+///
+/// // OR, NOT and parentheses become conditions
+/// let query = parse_query("(report OR invoice) ext:pdf -draft").unwrap();
+/// assert!(query.pattern.is_none());
+/// assert_eq!(query.conditions.len(), 2);
 /// ```
 pub fn parse_query(input: &str) -> Result<ParsedQuery> {
     let pairs = SearchParser::parse(Rule::query, input)
@@ -40,23 +157,21 @@ pub fn parse_query(input: &str) -> Result<ParsedQuery> {
 
     let mut pattern_parts: Vec<String> = Vec::new();
     let mut filters: Vec<Filter> = Vec::new();
+    let mut conditions: Vec<Condition> = Vec::new();
+    let mut sort: Vec<SortSpec> = Vec::new();
 
     for pair in pairs {
         if pair.as_rule() == Rule::query {
             for inner in pair.into_inner() {
-                if inner.as_rule() == Rule::term {
-                    for term_inner in inner.into_inner() {
-                        match term_inner.as_rule() {
-                            Rule::word => {
-                                pattern_parts.push(term_inner.as_str().to_string());
-                            }
-                            Rule::filter => {
-                                if let Some(filter) = parse_filter(term_inner)? {
-                                    filters.push(filter);
-                                }
-                            }
-                            _ => {}
-                        }
+                if inner.as_rule() == Rule::sort {
+                    sort.push(parse_sort(inner)?);
+                } else if inner.as_rule() == Rule::clause {
+                    // Plain top-level words and filters keep their flat form
+                    match parse_clause(inner)? {
+                        Some(Condition::Name(word)) => pattern_parts.push(word),
+                        Some(Condition::Filter(filter)) => filters.push(filter),
+                        Some(condition) => conditions.push(condition),
+                        None => {}
                     }
                 }
             }
@@ -69,7 +184,95 @@ pub fn parse_query(input: &str) -> Result<ParsedQuery> {
         Some(pattern_parts.join(" "))
     };
 
-    Ok(ParsedQuery { pattern, filters })
+    Ok(ParsedQuery {
+        pattern,
+        filters,
+        conditions,
+        sort,
+    })
+}
+
+/// Parse a `sort:` term: a key from [`SORT_KEYS`], optionally followed by
+/// `-asc` or `-desc`; without one, sizes and dates sort descending.
+fn parse_sort(pair: pest::iterators::Pair<Rule>) -> Result<SortSpec> {
+    let key = pair
+        .into_inner()
+        .find(|inner| inner.as_rule() == Rule::sort_key)
+        .map(|inner| inner.as_str().to_ascii_lowercase())
+        .unwrap_or_default();
+    let (name, direction) = match key.split_once('-') {
+        Some((name, direction)) => (name, Some(direction)),
+        None => (key.as_str(), None),
+    };
+
+    let field = SORT_KEYS
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, field)| *field)
+        .ok_or_else(|| FFIError::Search(format!("Unknown sort key: {}", name)))?;
+    let descending = match direction {
+        None => field.descending_by_default(),
+        Some("asc" | "ascending") => false,
+        Some("desc" | "descending") => true,
+        Some(other) => return Err(FFIError::Search(format!("Unknown sort direction: {}", other))),
+    };
+    Ok(SortSpec { field, descending })
+}
+
+/// Parse operands separated by OR.
+///
+/// # Returns
+/// `None` if the clause was only an unknown filter.
+fn parse_clause(pair: pest::iterators::Pair<Rule>) -> Result<Option<Condition>> {
+    let mut operands: Vec<Condition> = Vec::new();
+    for inner in pair.into_inner() {
+        if inner.as_rule() == Rule::operand {
+            operands.extend(parse_operand(inner)?);
+        }
+    }
+
+    Ok(match operands.len() {
+        0 => None,
+        1 => operands.pop(),
+        _ => Some(Condition::Any(operands)),
+    })
+}
+
+/// Parse a word, filter, group or negated operand.
+fn parse_operand(pair: pest::iterators::Pair<Rule>) -> Result<Option<Condition>> {
+    let mut negated = false;
+    for inner in pair.into_inner() {
+        let condition = match inner.as_rule() {
+            Rule::not_op => {
+                negated = true;
+                continue;
+            }
+            Rule::operand => parse_operand(inner)?,
+            Rule::group => {
+                let mut clauses: Vec<Condition> = Vec::new();
+                for clause in inner.into_inner() {
+                    clauses.extend(parse_clause(clause)?);
+                }
+                match clauses.len() {
+                    0 => None,
+                    1 => clauses.pop(),
+                    _ => Some(Condition::All(clauses)),
+                }
+            }
+            Rule::term => match inner.into_inner().next() {
+                Some(term) if term.as_rule() == Rule::word => Some(Condition::Name(term.as_str().to_string())),
+                Some(term) if term.as_rule() == Rule::filter => parse_filter(term)?.map(Condition::Filter),
+                _ => None,
+            },
+            _ => continue,
+        };
+
+        return Ok(match condition {
+            Some(condition) if negated => Some(Condition::Not(Box::new(condition))),
+            condition => condition,
+        });
+    }
+    Ok(None)
 }
 
 /// Parse a filter term into a Filter enum.
@@ -103,25 +306,92 @@ fn parse_filter(pair: pest::iterators::Pair<Rule>) -> Result<Option<Filter>> {
         }
         "type" => {
             let type_str = extract_value_string(&filter_value).to_lowercase();
-            let file_type = match type_str.as_str() {
-                "folder" | "dir" | "directory" => FileType::Folder,
-                "file" => FileType::File,
-                _ => return Err(FFIError::Search(format!("Unknown type: {}", type_str))),
-            };
+            let file_type = TYPE_VALUES
+                .iter()
+                .find(|(value, _)| *value == type_str)
+                .map(|(_, file_type)| *file_type)
+                .ok_or_else(|| FFIError::Search(format!("Unknown type: {}", type_str)))?;
             Ok(Some(Filter::Type(file_type)))
         }
         "modified" => {
             let (op, timestamp) = parse_date_filter(&filter_value)?;
             Ok(Some(Filter::Modified(op, timestamp)))
         }
+        "created" => {
+            let (op, timestamp) = parse_date_filter(&filter_value)?;
+            Ok(Some(Filter::Created(op, timestamp)))
+        }
         "path" => {
             let path = extract_value_string(&filter_value);
             Ok(Some(Filter::PathScope(path)))
         }
+        "drive" | "vol" => {
+            let name = extract_value_string(&filter_value);
+            Ok(Some(Filter::Volume(volume_name(&name))))
+        }
+        "attrib" => {
+            let name = extract_value_string(&filter_value);
+            let attribute = FileAttribute::from_name(&name)
+                .ok_or_else(|| FFIError::Search(format!("Unknown attribute: {}", name)))?;
+            Ok(Some(Filter::Attribute(attribute)))
+        }
+        "stream" => {
+            let name = extract_value_string(&filter_value);
+            Ok(Some(Filter::Stream(name)))
+        }
+        "offline" => {
+            let name = extract_value_string(&filter_value);
+            let scope = OfflineScope::from_name(&name)
+                .ok_or_else(|| FFIError::Search(format!("Unknown offline value: {}", name)))?;
+            Ok(Some(Filter::Offline(scope)))
+        }
+        "content" => {
+            let text = extract_value_string(&filter_value);
+            if text.trim().is_empty() {
+                return Err(FFIError::Search("Empty content search".to_string()));
+            }
+            Ok(Some(Filter::Content(text)))
+        }
+        "tag" => {
+            let tag = extract_value_string(&filter_value);
+            Ok(Some(Filter::Tag(tag.trim().to_string())))
+        }
+        "in" => {
+            let location = extract_value_string(&filter_value);
+            if !location.eq_ignore_ascii_case("recyclebin") {
+                return Err(FFIError::Search(format!("Unknown location: {}", location)));
+            }
+            Ok(Some(Filter::RecycleBin))
+        }
+        "regex" => {
+            // Taken verbatim: `<`, `>` and `C:` are ordinary regex text
+            let pattern = filter_value
+                .clone()
+                .into_inner()
+                .find(|inner| inner.as_rule() == Rule::quoted_string)
+                .map(|_| extract_value_string(&filter_value))
+                .unwrap_or_else(|| filter_value.as_str().to_string());
+            regex::Regex::new(&pattern)
+                .map_err(|e| FFIError::Search(format!("Invalid regex: {}", e)))?;
+            Ok(Some(Filter::Regex(pattern)))
+        }
         _ => Ok(None),
     }
 }
 
+/// Volume name as the index stores it: a drive letter becomes `D:`; mount
+/// folders and volume GUID paths are kept, without a trailing separator.
+fn volume_name(value: &str) -> String {
+    let value = value.trim_end_matches(['\\', '/']);
+    let mut chars = value.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(letter), None, None) | (Some(letter), Some(':'), None) if letter.is_ascii_alphabetic() => {
+            format!("{}:", letter.to_ascii_uppercase())
+        }
+        _ => value.to_string(),
+    }
+}
+
 /// Extract string value from filter_value pair.
 fn extract_value_string(pair: &pest::iterators::Pair<Rule>) -> String {
     for inner in pair.clone().into_inner() {
@@ -178,13 +448,7 @@ fn parse_size_comparison(pair: pest::iterators::Pair<Rule>) -> Result<(SizeOp, i
     for inner in pair.into_inner() {
         match inner.as_rule() {
             Rule::comparator => {
-                op = Some(match inner.as_str() {
-                    ">" => SizeOp::GreaterThan,
-                    ">=" => SizeOp::GreaterEqual,
-                    "<" => SizeOp::LessThan,
-                    "<=" => SizeOp::LessEqual,
-                    _ => return Err(FFIError::Search(format!("Unknown comparator: {}", inner.as_str()))),
-                });
+                op = Some(parse_comparator(inner.as_str())?);
             }
             Rule::size_value => {
                 bytes = Some(parse_size_value_pair(inner)?);
@@ -224,16 +488,26 @@ fn parse_size_value_pair(pair: pest::iterators::Pair<Rule>) -> Result<i64> {
     let number = number.ok_or_else(|| FFIError::Search("Missing size number".to_string()))?;
     let unit = unit.unwrap_or("b");
 
-    let multiplier = match unit.to_lowercase().as_str() {
-        "b" => 1i64,
-        "kb" => 1024i64,
-        "mb" => 1024i64 * 1024,
-        "gb" => 1024i64 * 1024 * 1024,
-        "tb" => 1024i64 * 1024 * 1024 * 1024,
-        _ => return Err(FFIError::Search(format!("Unknown size unit: {}", unit))),
-    };
+    Ok(number * unit_multiplier(unit)?)
+}
 
-    Ok(number * multiplier)
+/// Bytes per size unit (case-insensitive).
+fn unit_multiplier(unit: &str) -> Result<i64> {
+    let unit = unit.to_lowercase();
+    SIZE_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .map(|(_, multiplier)| *multiplier)
+        .ok_or_else(|| FFIError::Search(format!("Unknown size unit: {}", unit)))
+}
+
+/// Parse a comparison operator (>, >=, <, <=).
+fn parse_comparator(s: &str) -> Result<SizeOp> {
+    COMPARATORS
+        .iter()
+        .find(|(token, _)| *token == s)
+        .map(|(_, op)| *op)
+        .ok_or_else(|| FFIError::Search(format!("Unknown comparator: {}", s)))
 }
 
 /// Parse size value from string (e.g., "10mb").
@@ -251,16 +525,8 @@ fn parse_size_value(s: &str) -> Result<i64> {
         .map_err(|_| FFIError::Search(format!("Invalid size number: {}", num_str)))?;
 
     let unit = if unit_str.is_empty() { "b" } else { unit_str };
-    let multiplier = match unit {
-        "b" => 1i64,
-        "kb" => 1024i64,
-        "mb" => 1024i64 * 1024,
-        "gb" => 1024i64 * 1024 * 1024,
-        "tb" => 1024i64 * 1024 * 1024 * 1024,
-        _ => return Err(FFIError::Search(format!("Unknown size unit: {}", unit))),
-    };
 
-    Ok(number * multiplier)
+    Ok(number * unit_multiplier(unit)?)
 }
 
 /// Parse date filter value into operator and Unix timestamp.
@@ -286,12 +552,11 @@ fn parse_date_comparison(pair: pest::iterators::Pair<Rule>) -> Result<(DateOp, i
     for inner in pair.into_inner() {
         match inner.as_rule() {
             Rule::comparator => {
-                op = Some(match inner.as_str() {
-                    ">" => DateOp::GreaterThan,
-                    ">=" => DateOp::GreaterEqual,
-                    "<" => DateOp::LessThan,
-                    "<=" => DateOp::LessEqual,
-                    _ => return Err(FFIError::Search(format!("Unknown comparator: {}", inner.as_str()))),
+                op = Some(match parse_comparator(inner.as_str())? {
+                    SizeOp::GreaterThan => DateOp::GreaterThan,
+                    SizeOp::GreaterEqual => DateOp::GreaterEqual,
+                    SizeOp::LessThan => DateOp::LessThan,
+                    SizeOp::LessEqual => DateOp::LessEqual,
                 });
             }
             Rule::date_value => {
@@ -351,16 +616,14 @@ fn parse_relative_date(s: &str) -> Result<i64> {
         .single()
         .ok_or_else(|| FFIError::Search("Ambiguous datetime".to_string()))?;
 
-    let timestamp = match s.to_lowercase().as_str() {
-        "today" => today_start.timestamp(),
-        "yesterday" => (today_start - Duration::days(1)).timestamp(),
-        "lastweek" => (today_start - Duration::days(7)).timestamp(),
-        "lastmonth" => (today_start - Duration::days(30)).timestamp(),
-        "lastyear" => (today_start - Duration::days(365)).timestamp(),
-        _ => return Err(FFIError::Search(format!("Unknown relative date: {}", s))),
-    };
+    let s = s.to_lowercase();
+    let days = RELATIVE_DATES
+        .iter()
+        .find(|(name, _)| *name == s)
+        .map(|(_, days)| *days)
+        .ok_or_else(|| FFIError::Search(format!("Unknown relative date: {}", s)))?;
 
-    Ok(timestamp)
+    Ok((today_start - Duration::days(days)).timestamp())
 }
 
 #[cfg(test)]
@@ -435,6 +698,75 @@ mod tests {
         assert_eq!(query.filters[0], Filter::Type(FileType::File));
     }
 
+    #[test]
+    fn test_parse_type_link() {
+        for query in ["type:link", "type:symlink", "type:junction"] {
+            let query = parse_query(query).unwrap();
+            assert_eq!(query.filters, vec![Filter::Type(FileType::Link)]);
+        }
+    }
+
+    #[test]
+    fn test_parse_stream() {
+        let query = parse_query("report stream:Zone.Identifier").unwrap();
+        assert_eq!(query.pattern, Some("report".to_string()));
+        assert_eq!(query.filters, vec![Filter::Stream("Zone.Identifier".to_string())]);
+
+        let query = parse_query("stream:*").unwrap();
+        assert_eq!(query.filters, vec![Filter::Stream("*".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_volume() {
+        let query = parse_query("drive:d report").unwrap();
+        assert_eq!(query.pattern, Some("report".to_string()));
+        assert_eq!(query.filters, vec![Filter::Volume("D:".to_string())]);
+
+        for query in ["vol:D:", r"drive:D:\", "drive:d"] {
+            assert_eq!(parse_query(query).unwrap().filters, vec![Filter::Volume("D:".to_string())], "{}", query);
+        }
+        let query = parse_query(r#"vol:"C:\Mount\Data\""#).unwrap();
+        assert_eq!(query.filters, vec![Filter::Volume(r"C:\Mount\Data".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_offline() {
+        let query = parse_query("report offline:false").unwrap();
+        assert_eq!(query.pattern, Some("report".to_string()));
+        assert_eq!(query.filters, vec![Filter::Offline(OfflineScope::Exclude)]);
+
+        let query = parse_query("offline:ONLY").unwrap();
+        assert_eq!(query.filters, vec![Filter::Offline(OfflineScope::Only)]);
+        assert!(parse_query("offline:maybe").is_err());
+    }
+
+    #[test]
+    fn test_parse_content() {
+        let query = parse_query(r#"content:"TODO refactor" ext:rs"#).unwrap();
+        assert_eq!(query.pattern, None);
+        assert_eq!(
+            query.filters,
+            vec![Filter::Content("TODO refactor".to_string()), Filter::Extension("rs".to_string())]
+        );
+        assert_eq!(parse_query("content:fixme").unwrap().filters, vec![Filter::Content("fixme".to_string())]);
+        assert!(parse_query(r#"content:"  ""#).is_err());
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        let query = parse_query(r#"tag:work tag:"tax 2024" report"#).unwrap();
+        assert_eq!(query.pattern, Some("report".to_string()));
+        assert_eq!(query.filters, vec![Filter::Tag("work".to_string()), Filter::Tag("tax 2024".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_recycle_bin() {
+        let query = parse_query("report in:RecycleBin").unwrap();
+        assert_eq!(query.filters, vec![Filter::RecycleBin]);
+        assert!(parse_query("in:trash").is_err());
+        assert_eq!(parse_query("inbox").unwrap().pattern, Some("inbox".to_string()));
+    }
+
     #[test]
     fn test_parse_modified_today() {
         let query = parse_query("modified:today").unwrap();
@@ -507,6 +839,60 @@ mod tests {
         assert_eq!(query.filters[0], Filter::Size(SizeOp::GreaterThan, 1024i64 * 1024 * 1024 * 1024));
     }
 
+    #[test]
+    fn test_regex_filter() {
+        let query = parse_query(r"regex:^IMG_\d{4}\.jpg$").unwrap();
+        assert_eq!(query.filters, vec![Filter::Regex(r"^IMG_\d{4}\.jpg$".to_string())]);
+
+        // Comparators and drive-like prefixes are part of the pattern
+        let query = parse_query(r#"report regex:<h\d> regex:"a b|c:d""#).unwrap();
+        assert_eq!(query.pattern, Some("report".to_string()));
+        assert_eq!(
+            query.filters,
+            vec![Filter::Regex(r"<h\d>".to_string()), Filter::Regex("a b|c:d".to_string())]
+        );
+
+        assert!(parse_query("regex:(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        let name = |word: &str| Condition::Name(word.to_string());
+
+        let query = parse_query("(report OR invoice) ext:pdf -draft").unwrap();
+        assert!(query.pattern.is_none());
+        assert_eq!(query.filters, vec![Filter::Extension("pdf".to_string())]);
+        assert_eq!(
+            query.conditions,
+            vec![
+                Condition::Any(vec![name("report"), name("invoice")]),
+                Condition::Not(Box::new(name("draft"))),
+            ]
+        );
+
+        // OR binds tighter than the implicit AND
+        let query = parse_query("annual report OR invoice NOT (old ext:bak)").unwrap();
+        assert_eq!(query.pattern, Some("annual".to_string()));
+        assert_eq!(
+            query.conditions,
+            vec![
+                Condition::Any(vec![name("report"), name("invoice")]),
+                Condition::Not(Box::new(Condition::All(vec![
+                    name("old"),
+                    Condition::Filter(Filter::Extension("bak".to_string())),
+                ]))),
+            ]
+        );
+
+        // Literal parentheses, lowercase keywords and detached dashes are words
+        let query = parse_query("report(1) or - notes").unwrap();
+        assert_eq!(query.pattern, Some("report(1) or - notes".to_string()));
+        assert!(query.conditions.is_empty());
+
+        assert!(parse_query("(report OR invoice").is_err());
+        assert!(parse_query("report OR invoice)").is_err());
+    }
+
     #[test]
     fn test_relative_dates() {
         // These should not error - exact timestamps depend on current time
@@ -529,4 +915,79 @@ mod tests {
             panic!("Expected Modified filter");
         }
     }
+
+    #[test]
+    fn test_parse_attribute() {
+        let query = parse_query("attrib:Hidden").unwrap();
+        assert_eq!(query.filters, vec![Filter::Attribute(FileAttribute::Hidden)]);
+        assert!(parse_query("attrib:secret").is_err());
+    }
+
+    #[test]
+    fn test_exclude_attributes() {
+        let hidden = [FileAttribute::Hidden, FileAttribute::System];
+
+        let mut query = parse_query("report").unwrap();
+        query.exclude_attributes(&hidden);
+        assert_eq!(query.conditions.len(), 1);
+
+        // Asking for attributes turns the default off
+        for text in ["report attrib:system", "report (attrib:hidden OR ext:ini)"] {
+            let mut query = parse_query(text).unwrap();
+            let before = query.conditions.len();
+            query.exclude_attributes(&hidden);
+            assert_eq!(query.conditions.len(), before, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_sort() {
+        let query = parse_query("size:>1gb sort:size").unwrap();
+        assert!(query.pattern.is_none());
+        assert_eq!(query.filters, vec![Filter::Size(SizeOp::GreaterThan, 1024 * 1024 * 1024)]);
+        assert_eq!(query.sort, vec![SortSpec::desc(SortField::Size)]);
+
+        let query = parse_query("sort:ext report sort:Date-asc ext:pdf").unwrap();
+        assert_eq!(query.pattern, Some("report".to_string()));
+        assert_eq!(query.sort, vec![SortSpec::asc(SortField::Extension), SortSpec::asc(SortField::Modified)]);
+        assert_eq!(parse_query("sort:name-descending").unwrap().sort, vec![SortSpec::desc(SortField::Name)]);
+
+        for text in ["sort:owner", "sort:size-up", "sort:size*", "(a sort:size)", "-sort:size"] {
+            assert!(parse_query(text).is_err(), "{}", text);
+        }
+        // Only a whole `sort:` term sorts
+        assert_eq!(parse_query("sorted").unwrap().pattern, Some("sorted".to_string()));
+    }
+
+    #[test]
+    fn test_exclude_recycle_bin() {
+        let mut query = parse_query("report").unwrap();
+        query.exclude_recycle_bin();
+        assert_eq!(
+            query.conditions,
+            vec![Condition::Not(Box::new(Condition::Filter(Filter::PathScope("$Recycle.Bin".to_string()))))]
+        );
+
+        // Asking for the Recycle Bin turns the default off
+        for text in ["report in:recyclebin", "(report OR in:recyclebin)", r"path:C:\$RECYCLE.BIN\S-1-5-21"] {
+            let mut query = parse_query(text).unwrap();
+            let before = query.conditions.len();
+            query.exclude_recycle_bin();
+            assert_eq!(query.conditions.len(), before, "{}", text);
+        }
+        let mut query = parse_query("report -in:recyclebin").unwrap();
+        query.exclude_recycle_bin();
+        assert_eq!(query.conditions.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_created() {
+        let query = parse_query("report created:>=2024-01-01").unwrap();
+        assert_eq!(query.pattern, Some("report".to_string()));
+        assert!(matches!(query.filters[..], [Filter::Created(DateOp::GreaterEqual, ts)] if ts > 0));
+        assert!(matches!(
+            parse_query("created:today").unwrap().filters[..],
+            [Filter::Created(DateOp::GreaterEqual, _)]
+        ));
+    }
 }
@@ -0,0 +1,239 @@
+//! Project-scoped searches for developers.
+//!
+//! [`ProjectScope`] finds the version control root above a directory,
+//! limits a query to it with a `path:` filter, and drops results matched
+//! by the project's `.gitignore`-style files, so a search from inside a
+//! working copy behaves like `fd` but answers from the index.
+
+use std::path::{Component, Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use rusqlite::Connection;
+
+use super::filters::Filter;
+use super::parser::ParsedQuery;
+use crate::db::{get_all_volumes, get_full_path, query_files};
+use crate::Result;
+
+/// Directories marking the root of a working copy.
+pub const VCS_MARKERS: &[&str] = &[".git", ".hg", ".svn", ".jj"];
+
+/// Ignore files read at the project root, lowest precedence first.
+const ROOT_IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// Most nested ignore files looked up in the index.
+const MAX_NESTED_IGNORE_FILES: usize = 1_000;
+
+/// Find the nearest directory at or above `start` holding a VCS marker.
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| VCS_MARKERS.iter().any(|marker| dir.join(marker).exists()))
+        .map(Path::to_path_buf)
+}
+
+/// A working copy to search in, with its ignore rules.
+#[derive(Debug)]
+pub struct ProjectScope {
+    root: PathBuf,
+    /// Ignore rules, shallowest directory first
+    ignores: Vec<Gitignore>,
+}
+
+impl ProjectScope {
+    /// Scope to the working copy containing `start`, if any.
+    pub fn detect(start: &Path) -> Option<Self> {
+        find_project_root(start).map(Self::new)
+    }
+
+    /// Scope to `root`, reading `.git/info/exclude`, `.gitignore` and `.ignore` there.
+    pub fn new(root: PathBuf) -> Self {
+        let mut scope = Self {
+            root,
+            ignores: Vec::new(),
+        };
+        let root = scope.root.clone();
+        scope.add_ignore(&root, &root.join(".git").join("info").join("exclude"));
+        for name in ROOT_IGNORE_FILES {
+            scope.add_ignore(&root, &root.join(name));
+        }
+        scope
+    }
+
+    /// Root directory of the working copy.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Add an ignore file whose patterns are relative to its own directory.
+    pub fn add_ignore_file(&mut self, path: &Path) {
+        if let Some(dir) = path.parent() {
+            self.add_ignore(dir, path);
+        }
+    }
+
+    /// Add a `.gitignore` found below the root; the root's own file was
+    /// read in `new` and is skipped.
+    pub fn add_nested_ignore_file(&mut self, path: &Path) {
+        if path.parent().is_some_and(|dir| !same_path(dir, &self.root)) {
+            self.add_ignore_file(path);
+        }
+    }
+
+    /// Add the `.gitignore` files below the root, found through the index.
+    pub fn add_indexed_ignore_files(&mut self, conn: &Connection) -> Result<()> {
+        let mut parsed = ParsedQuery {
+            pattern: Some(".gitignore".to_string()),
+            ..Default::default()
+        };
+        self.apply(&mut parsed);
+
+        let volumes = get_all_volumes(conn)?;
+        for entry in query_files(conn, &parsed, MAX_NESTED_IGNORE_FILES)? {
+            let (Some(file_ref), false) = (entry.file_ref, entry.is_dir) else { continue };
+            if !entry.name.eq_ignore_ascii_case(".gitignore") {
+                continue;
+            }
+            let Some(volume) = volumes.iter().find(|v| v.id == entry.volume_id) else { continue };
+            if let Some(relative) = get_full_path(conn, entry.volume_id, file_ref)? {
+                self.add_nested_ignore_file(&PathBuf::from(format!("{}\\{}", volume.drive_letter, relative)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Limit a query to the working copy.
+    pub fn apply(&self, parsed: &mut ParsedQuery) {
+        parsed.filters.push(Filter::PathScope(self.root.display().to_string()));
+    }
+
+    /// Search term limiting a query to the working copy, for queries sent
+    /// as text (e.g. to the service).
+    pub fn filter_term(&self) -> String {
+        format!("path:\"{}\"", self.root.display())
+    }
+
+    /// Whether a path is outside the working copy, VCS metadata, or ignored.
+    ///
+    /// Rules in deeper directories take precedence, and a `!pattern`
+    /// re-includes what a shallower rule ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(relative) = relative_to(path, &self.root) else {
+            return true;
+        };
+        let in_metadata = relative
+            .components()
+            .any(|c| VCS_MARKERS.iter().any(|marker| c.as_os_str() == *marker));
+        if in_metadata {
+            return true;
+        }
+
+        for ignore in self.ignores.iter().rev() {
+            let Some(relative) = relative_to(path, ignore.path()) else { continue };
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+            match ignore.matched_path_or_any_parents(&relative, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    /// Read one ignore file into rules rooted at `dir`; missing files are skipped.
+    fn add_ignore(&mut self, dir: &Path, file: &Path) {
+        if !file.is_file() {
+            return;
+        }
+
+        // Names are matched case-insensitively, like the filesystem
+        let mut builder = GitignoreBuilder::new(dir);
+        let _ = builder.case_insensitive(true);
+        if let Some(e) = builder.add(file) {
+            tracing::warn!("Skipping invalid lines in {}: {}", file.display(), e);
+        }
+        match builder.build() {
+            Ok(ignore) => {
+                let depth = dir.components().count();
+                let at = self
+                    .ignores
+                    .partition_point(|other| other.path().components().count() <= depth);
+                self.ignores.insert(at, ignore);
+            }
+            Err(e) => tracing::warn!("Failed to read {}: {}", file.display(), e),
+        }
+    }
+}
+
+/// `path` below `dir`, comparing components case-insensitively.
+fn relative_to(path: &Path, dir: &Path) -> Option<PathBuf> {
+    let mut components = path.components();
+    for expected in dir.components() {
+        if !same_component(components.next()?, expected) {
+            return None;
+        }
+    }
+    Some(components.as_path().to_path_buf())
+}
+
+/// Whether two paths are the same, ignoring ASCII case.
+fn same_path(a: &Path, b: &Path) -> bool {
+    a.components().count() == b.components().count() && relative_to(a, b).is_some()
+}
+
+/// Whether two path components are equal, ignoring ASCII case.
+fn same_component(a: Component, b: Component) -> bool {
+    a.as_os_str()
+        .to_string_lossy()
+        .eq_ignore_ascii_case(&b.as_os_str().to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_project_scope_ignores() {
+        let root = std::env::temp_dir().join("ffi_test_project");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join(".git").join("info")).unwrap();
+        fs::create_dir_all(root.join("src").join("gen")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join(".git").join("info").join("exclude"), "scratch.txt\n").unwrap();
+        fs::write(root.join("src").join(".gitignore"), "gen/\n!keep.log\n").unwrap();
+
+        let mut scope = ProjectScope::detect(&root.join("src").join("gen")).unwrap();
+        assert_eq!(scope.root(), root.as_path());
+        scope.add_ignore_file(&root.join("src").join(".gitignore"));
+
+        let ignored = |relative: &str, is_dir: bool| scope.is_ignored(&root.join(relative), is_dir);
+        assert!(!ignored("src/main.rs", false));
+        assert!(!ignored("SRC/Main.rs", false));
+        assert!(ignored("target/debug/app", false));
+        assert!(ignored("build.log", false));
+        assert!(ignored("scratch.txt", false));
+        assert!(ignored(".git/HEAD", false));
+        assert!(ignored("src/gen/out.rs", false));
+        // Deeper rules re-include
+        assert!(!ignored("src/keep.log", false));
+        assert!(scope.is_ignored(&std::env::temp_dir().join("elsewhere.rs"), false));
+
+        let mut parsed = ParsedQuery::default();
+        scope.apply(&mut parsed);
+        assert_eq!(parsed.filters, vec![Filter::PathScope(root.display().to_string())]);
+        let term = crate::search::parse_query(&format!("{} report", scope.filter_term())).unwrap();
+        assert_eq!(term.filters, parsed.filters);
+
+        // The root's own ignore file is not read twice
+        let rules = scope.ignores.len();
+        scope.add_nested_ignore_file(&root.join(".gitignore"));
+        assert_eq!(scope.ignores.len(), rules);
+
+        let _ = fs::remove_dir_all(&root);
+        assert!(find_project_root(&std::env::temp_dir().join("ffi_test_no_project")).is_none());
+    }
+}
@@ -3,8 +3,12 @@
 //! Converts ParsedQuery into parameterized SQL WHERE clauses.
 //! Uses prepared statement parameters to prevent SQL injection.
 
+use crate::db::{UsageGroup, FILE_ATTRIBUTE_REPARSE_POINT};
+use crate::VolumeState;
+
 use super::filters::*;
 use super::parser::ParsedQuery;
+use super::sort::order_by_clause;
 
 /// SQL parameter value for prepared statements.
 #[derive(Debug, Clone, PartialEq)]
@@ -15,6 +19,19 @@ pub enum SqlParam {
     Integer(i64),
 }
 
+impl rusqlite::ToSql for SqlParam {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        match self {
+            SqlParam::Text(s) => s.to_sql(),
+            SqlParam::Integer(i) => i.to_sql(),
+        }
+    }
+}
+
+/// Condition restricting rows to names found by a [`fts_name_query`] in the
+/// trigram name index.
+pub(crate) const NAME_INDEX_SQL: &str = "id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH ?)";
+
 /// Build SQL query from parsed search query.
 ///
 /// Returns a tuple of (SQL SELECT statement, parameters).
@@ -31,52 +48,161 @@ pub enum SqlParam {
 /// assert!(sql.contains("name LIKE ?"));
 /// ```
 pub fn build_sql_query(parsed: &ParsedQuery) -> (String, Vec<SqlParam>) {
+    build_select(parsed, &order_by_clause(&parsed.sort), Vec::new())
+}
+
+/// Build a query for one page of results: up to `limit` rows after skipping `offset`.
+pub fn build_sql_query_page(parsed: &ParsedQuery, limit: i64, offset: i64) -> (String, Vec<SqlParam>) {
+    let (sql, params) = build_sql_query_with_limit(parsed, limit);
+    with_offset(sql, params, offset)
+}
+
+/// Build a query for one page of results, most relevant names first.
+///
+/// Names equal to the pattern come first, then names starting with it,
+/// then other matches; shorter names first within each group. The sort
+/// keys are ignored. Without a pattern, names are in alphabetical order.
+pub fn build_relevance_query(parsed: &ParsedQuery, limit: i64, offset: i64) -> (String, Vec<SqlParam>) {
+    let (order_by, order_params) = match parsed.pattern {
+        Some(ref pattern) => {
+            let exact = like_pattern(pattern);
+            (
+                format!(
+                    "CASE WHEN name LIKE ? ESCAPE '\\' THEN 0 WHEN name LIKE ? ESCAPE '\\' THEN 1 ELSE 2 END, \
+                     length(name), {}",
+                    order_by_clause(&[])
+                ),
+                vec![SqlParam::Text(exact.clone()), SqlParam::Text(format!("{}%", exact))],
+            )
+        }
+        None => (order_by_clause(&[]), Vec::new()),
+    };
+
+    let (sql, mut params) = build_select(parsed, &order_by, order_params);
+    if let Some(last) = params.last_mut() {
+        *last = SqlParam::Integer(limit);
+    }
+    with_offset(sql, params, offset)
+}
+
+/// Build the SELECT statement with an ORDER BY clause and its parameters.
+fn build_select(parsed: &ParsedQuery, order_by: &str, order_params: Vec<SqlParam>) -> (String, Vec<SqlParam>) {
+    let (where_clause, mut params) = build_where_clause(parsed);
+
+    // Build complete SQL
+    let sql = format!(
+        "SELECT id, volume_id, file_ref, parent_ref, name, size, modified, is_dir, created, attributes, \
+                link, link_target, NULLIF(stream, '') \
+         FROM files {} \
+         ORDER BY {} \
+         LIMIT ?",
+        where_clause, order_by
+    );
+    params.extend(order_params);
+
+    // Add limit parameter
+    params.push(SqlParam::Integer(100)); // Default limit
+
+    (sql, params)
+}
+
+/// Append an OFFSET to a query ending in `LIMIT ?`.
+fn with_offset(mut sql: String, mut params: Vec<SqlParam>, offset: i64) -> (String, Vec<SqlParam>) {
+    sql.push_str(" OFFSET ?");
+    params.push(SqlParam::Integer(offset));
+    (sql, params)
+}
+
+/// Build a `SELECT COUNT(*)` query matching the same rows as [`build_sql_query`].
+///
+/// Used for result count badges and pagination totals.
+pub fn build_count_query(parsed: &ParsedQuery) -> (String, Vec<SqlParam>) {
+    let (where_clause, params) = build_where_clause(parsed);
+    let sql = format!("SELECT COUNT(*) FROM files {}", where_clause);
+    (sql, params)
+}
+
+/// Build a query summing the sizes of the files among the matches, largest
+/// group first.
+///
+/// Rows are the group key, the number of files and their total size.
+/// Folders, hard links (counted once, under their first name) and entries
+/// without a path are left out. [`UsageGroup::Folder`] groups by the folder
+/// directly below the query's first `path:` scope, or below the volume root
+/// without one, keyed by its full path; files directly in the scope are
+/// keyed by the scope itself.
+pub fn build_usage_query(parsed: &ParsedQuery, group: UsageGroup, limit: i64) -> (String, Vec<SqlParam>) {
+    let (where_clause, where_params) = build_where_clause(parsed);
+    let conditions = match where_clause.strip_prefix("WHERE ") {
+        Some(conditions) => format!("WHERE {} AND ", conditions),
+        None => "WHERE ".to_string(),
+    };
+    let conditions = format!("{}is_dir = 0 AND link = 0 AND full_path IS NOT NULL", conditions);
+
+    let (sql, mut params) = match group {
+        UsageGroup::Extension => (
+            format!(
+                "SELECT COALESCE(ext, ''), COUNT(*), COALESCE(SUM(size), 0) FROM files {} \
+                 GROUP BY 1 ORDER BY 3 DESC, 1 LIMIT ?",
+                conditions
+            ),
+            Vec::new(),
+        ),
+        UsageGroup::Folder => {
+            let scope = parsed.filters.iter().find_map(|filter| match filter {
+                Filter::PathScope(path) => Some(split_path_scope(path).1),
+                _ => None,
+            });
+            let prefix_len = match scope.unwrap_or_default().chars().count() {
+                0 => 0,
+                len => len as i64 + 1,
+            };
+            // The path up to the first backslash after the scope, or the scope
+            // itself when there is none
+            (
+                format!(
+                    "SELECT v.drive_letter || '\\' || u.folder, u.files, u.bytes \
+                     FROM (SELECT volume_id, \
+                               CASE WHEN instr(substr(full_path, ? + 1), '\\') = 0 THEN substr(full_path, 1, max(? - 1, 0)) \
+                               ELSE substr(full_path, 1, ? + instr(substr(full_path, ? + 1), '\\') - 1) END AS folder, \
+                               COUNT(*) AS files, COALESCE(SUM(size), 0) AS bytes \
+                           FROM files {} GROUP BY 1, 2) u \
+                     JOIN volumes v ON v.id = u.volume_id \
+                     ORDER BY 3 DESC, 1 LIMIT ?",
+                    conditions
+                ),
+                vec![
+                    SqlParam::Integer(prefix_len),
+                    SqlParam::Integer(prefix_len),
+                    SqlParam::Integer(prefix_len),
+                    SqlParam::Integer(prefix_len),
+                ],
+            )
+        }
+    };
+    params.extend(where_params);
+    params.push(SqlParam::Integer(limit));
+    (sql, params)
+}
+
+/// Build the WHERE clause (including the `WHERE` keyword, or empty) and its parameters.
+fn build_where_clause(parsed: &ParsedQuery) -> (String, Vec<SqlParam>) {
     let mut conditions: Vec<String> = Vec::new();
     let mut params: Vec<SqlParam> = Vec::new();
 
     // Handle pattern (name search with wildcards)
     if let Some(ref pattern) = parsed.pattern {
-        let sql_pattern = convert_wildcards_to_sql(pattern);
-        conditions.push("name LIKE ? ESCAPE '\\'".to_string());
-        params.push(SqlParam::Text(sql_pattern));
+        conditions.extend(name_conditions(pattern, &mut params));
     }
 
     // Handle filters
     for filter in &parsed.filters {
-        match filter {
-            Filter::Extension(ext) => {
-                // Match files ending with .ext (case-insensitive via COLLATE NOCASE index)
-                conditions.push("name LIKE ?".to_string());
-                params.push(SqlParam::Text(format!("%.{}", ext)));
-            }
-            Filter::Size(op, bytes) => {
-                conditions.push(format!("size {} ?", op.to_sql()));
-                params.push(SqlParam::Integer(*bytes));
-            }
-            Filter::Type(file_type) => {
-                let is_dir = match file_type {
-                    FileType::Folder => 1,
-                    FileType::File => 0,
-                };
-                conditions.push("is_dir = ?".to_string());
-                params.push(SqlParam::Integer(is_dir));
-            }
-            Filter::Modified(op, timestamp) => {
-                conditions.push(format!("modified {} ?", op.to_sql()));
-                params.push(SqlParam::Integer(*timestamp));
-            }
-            Filter::PathScope(path) => {
-                // NOTE: Path scope filtering requires path reconstruction which is expensive.
-                // For now, we add a comment indicating this needs special handling.
-                // Options for future implementation:
-                // 1. Store computed full_path column with index
-                // 2. Use recursive CTE to filter by path prefix
-                // 3. Post-filter results in memory after initial search
-                // For now, we skip this in SQL and let caller handle post-filtering.
-                let _ = path; // Acknowledge unused for now
-                // Don't add to conditions - will be handled by post-filter
-            }
-        }
+        conditions.extend(filter_conditions(filter, &mut params));
+    }
+
+    // Handle OR / NOT / grouped terms
+    for condition in &parsed.conditions {
+        conditions.push(condition_sql(condition, &mut params));
     }
 
     // Build WHERE clause
@@ -86,19 +212,209 @@ pub fn build_sql_query(parsed: &ParsedQuery) -> (String, Vec<SqlParam>) {
         format!("WHERE {}", conditions.join(" AND "))
     };
 
-    // Build complete SQL
-    let sql = format!(
-        "SELECT id, volume_id, file_ref, parent_ref, name, size, modified, is_dir \
-         FROM files {} \
-         ORDER BY name COLLATE NOCASE \
-         LIMIT ?",
-        where_clause
-    );
+    (where_clause, params)
+}
 
-    // Add limit parameter
-    params.push(SqlParam::Integer(100)); // Default limit
+/// Conditions matching a name pattern, all of which must hold.
+fn name_conditions(pattern: &str, params: &mut Vec<SqlParam>) -> Vec<String> {
+    let mut conditions = vec!["name LIKE ? ESCAPE '\\'".to_string()];
+    params.push(SqlParam::Text(convert_wildcards_to_sql(pattern)));
 
-    (sql, params)
+    // Narrow the LIKE to candidates from the name index
+    if let Some(fts_query) = fts_name_query(pattern, &['*', '?']) {
+        conditions.push(NAME_INDEX_SQL.to_string());
+        params.push(SqlParam::Text(fts_query));
+    }
+    conditions
+}
+
+/// Conditions matching a filter, all of which must hold.
+fn filter_conditions(filter: &Filter, params: &mut Vec<SqlParam>) -> Vec<String> {
+    let mut conditions: Vec<String> = Vec::new();
+    match filter {
+        Filter::Extension(ext) if ext.contains('.') => {
+            // Multi-part extensions (e.g. `tar.gz`) span the stored one
+            conditions.push("name LIKE ?".to_string());
+            params.push(SqlParam::Text(format!("%.{}", ext)));
+        }
+        Filter::Extension(ext) => {
+            // Exact match on the indexed lowercase extension column
+            conditions.push("ext = ?".to_string());
+            params.push(SqlParam::Text(ext.to_ascii_lowercase()));
+        }
+        Filter::Size(op, bytes) => {
+            conditions.push(format!("size {} ?", op.to_sql()));
+            params.push(SqlParam::Integer(*bytes));
+        }
+        Filter::Type(FileType::Link) => {
+            conditions.push("(attributes & ?) != 0".to_string());
+            params.push(SqlParam::Integer(FILE_ATTRIBUTE_REPARSE_POINT as i64));
+        }
+        Filter::Type(file_type) => {
+            let is_dir = match file_type {
+                FileType::Folder => 1,
+                _ => 0,
+            };
+            conditions.push("is_dir = ?".to_string());
+            params.push(SqlParam::Integer(is_dir));
+        }
+        Filter::Modified(op, timestamp) => {
+            conditions.push(format!("modified {} ?", op.to_sql()));
+            params.push(SqlParam::Integer(*timestamp));
+        }
+        Filter::Created(op, timestamp) => {
+            conditions.push(format!("created {} ?", op.to_sql()));
+            params.push(SqlParam::Integer(*timestamp));
+        }
+        Filter::PathScope(path) => {
+            let (drive, folder) = split_path_scope(path);
+            if let Some(drive) = drive {
+                conditions.push(
+                    "volume_id IN (SELECT id FROM volumes WHERE drive_letter = ? COLLATE NOCASE)"
+                        .to_string(),
+                );
+                params.push(SqlParam::Text(drive));
+            }
+            if !folder.is_empty() {
+                // Everything below `folder\` sorts between it and `folder]`
+                // (']' is the character after '\'), which lets the path index serve the range
+                conditions.push(
+                    "full_path >= ? COLLATE NOCASE AND full_path < ? COLLATE NOCASE".to_string(),
+                );
+                params.push(SqlParam::Text(format!("{}\\", folder)));
+                params.push(SqlParam::Text(format!("{}]", folder)));
+            }
+        }
+        Filter::Volume(name) => {
+            // Served by the volume_id index, unlike a path: range
+            conditions.push(
+                "volume_id IN (SELECT id FROM volumes WHERE drive_letter = ? COLLATE NOCASE)".to_string(),
+            );
+            params.push(SqlParam::Text(name.clone()));
+        }
+        Filter::Regex(pattern) => {
+            // Evaluated by the `regexp` function registered on each connection
+            conditions.push("name REGEXP ?".to_string());
+            params.push(SqlParam::Text(format!("(?i){}", pattern)));
+        }
+        Filter::Attribute(attribute) => {
+            conditions.push("(attributes & ?) != 0".to_string());
+            params.push(SqlParam::Integer(attribute.flag() as i64));
+        }
+        Filter::Stream(name) => {
+            // Files themselves have an empty stream name
+            conditions.push("stream <> ''".to_string());
+            conditions.push("stream LIKE ? ESCAPE '\\'".to_string());
+            params.push(SqlParam::Text(like_pattern(name)));
+        }
+        Filter::Offline(OfflineScope::Include) => {}
+        Filter::Offline(scope) => {
+            let membership = if *scope == OfflineScope::Only { "IN" } else { "NOT IN" };
+            conditions.push(format!("volume_id {} (SELECT id FROM volumes WHERE state = ?)", membership));
+            params.push(SqlParam::Text(VolumeState::Offline { since: 0 }.to_db_str().to_string()));
+        }
+        Filter::Content(text) => {
+            // One FTS5 phrase: the words in order, whatever punctuation the text has
+            conditions.push("id IN (SELECT rowid FROM file_contents WHERE file_contents MATCH ?)".to_string());
+            params.push(SqlParam::Text(format!("\"{}\"", text.replace('"', "\"\""))));
+        }
+        Filter::Tag(tag) => {
+            // Tags are keyed by volume serial, so they match whatever letter the volume has now
+            conditions.push(
+                "(volume_id, file_ref) IN (SELECT v.id, t.file_ref FROM tags t \
+                 JOIN volumes v ON v.volume_serial = t.volume_serial WHERE t.tag = ?)"
+                    .to_string(),
+            );
+            params.push(SqlParam::Text(tag.clone()));
+        }
+        Filter::RecycleBin => {
+            // Each item is renamed to `$R...` in a folder per user; what is
+            // below a deleted folder matches too, the `$I...` files holding
+            // the original paths do not
+            conditions.push(
+                "full_path >= ? COLLATE NOCASE AND full_path < ? COLLATE NOCASE AND full_path LIKE ?".to_string(),
+            );
+            params.push(SqlParam::Text(format!("{}\\", RECYCLE_BIN)));
+            params.push(SqlParam::Text(format!("{}]", RECYCLE_BIN)));
+            params.push(SqlParam::Text(format!("{}\\%\\$R%", RECYCLE_BIN)));
+        }
+    }
+    conditions
+}
+
+/// Build a parenthesized SQL expression for a boolean condition.
+fn condition_sql(condition: &Condition, params: &mut Vec<SqlParam>) -> String {
+    // Parts, how they combine, and the value when there are none
+    let (parts, separator, empty): (Vec<String>, &str, &str) = match condition {
+        Condition::Name(pattern) => (name_conditions(pattern, params), " AND ", "1"),
+        Condition::Filter(filter) => (filter_conditions(filter, params), " AND ", "1"),
+        Condition::All(conditions) => (
+            conditions.iter().map(|c| condition_sql(c, params)).collect(),
+            " AND ",
+            "1",
+        ),
+        Condition::Any(conditions) => (
+            conditions.iter().map(|c| condition_sql(c, params)).collect(),
+            " OR ",
+            "0",
+        ),
+        Condition::Not(condition) => {
+            // A NULL size or date fails the filter, so it passes the negation
+            return format!("NOT coalesce({}, 0)", condition_sql(condition, params));
+        }
+    };
+
+    if parts.is_empty() {
+        empty.to_string()
+    } else {
+        format!("({})", parts.join(separator))
+    }
+}
+
+/// Split a `path:` scope into its drive (e.g. `C:` or the
+/// `\\?\Volume{GUID}` path of a volume without a letter) and the folder
+/// below the volume root, with backslash separators and no trailing separator.
+fn split_path_scope(path: &str) -> (Option<String>, String) {
+    let path = path.replace('/', "\\");
+    let mut chars = path.chars();
+    let (drive, rest) = match (chars.next(), chars.next()) {
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => {
+            (Some(format!("{}:", letter.to_ascii_uppercase())), &path[2..])
+        }
+        _ => match volume_guid_prefix(&path) {
+            Some(len) => (Some(path[..len].to_string()), &path[len..]),
+            None => (None, path.as_str()),
+        },
+    };
+    (drive, rest.trim_matches('\\').to_string())
+}
+
+/// Length of a leading `\\?\Volume{...}` path, if `path` starts with one.
+fn volume_guid_prefix(path: &str) -> Option<usize> {
+    const PREFIX: &str = r"\\?\Volume{";
+    let head = path.get(..PREFIX.len())?;
+    if !head.eq_ignore_ascii_case(PREFIX) {
+        return None;
+    }
+    path[PREFIX.len()..].find('}').map(|end| PREFIX.len() + end + 1)
+}
+
+/// Build an FTS5 query for names containing every literal run of `pattern`.
+///
+/// Runs are the text between `wildcards`; only runs of at least three
+/// characters can be looked up by trigram, so shorter ones are left to the
+/// caller's `LIKE`. Each run is quoted as a phrase.
+///
+/// # Returns
+/// `None` if no run is long enough to use the index.
+pub(crate) fn fts_name_query(pattern: &str, wildcards: &[char]) -> Option<String> {
+    let phrases: Vec<String> = pattern
+        .split(|c| wildcards.contains(&c))
+        .filter(|run| run.chars().count() >= 3)
+        .map(|run| format!("\"{}\"", run.replace('"', "\"\"")))
+        .collect();
+
+    (!phrases.is_empty()).then(|| phrases.join(" "))
 }
 
 /// Convert wildcard pattern to SQL LIKE pattern.
@@ -108,15 +424,20 @@ pub fn build_sql_query(parsed: &ParsedQuery) -> (String, Vec<SqlParam>) {
 /// - `%`, `_`, `\` in input are escaped with `\`
 /// - If no wildcards, wraps in `%..%` for substring match
 fn convert_wildcards_to_sql(pattern: &str) -> String {
-    let has_wildcards = pattern.contains('*') || pattern.contains('?');
-
-    let mut result = String::with_capacity(pattern.len() + 4);
+    let like = like_pattern(pattern);
 
     // If no wildcards, make it a substring search
-    if !has_wildcards {
-        result.push('%');
+    if pattern.contains('*') || pattern.contains('?') {
+        like
+    } else {
+        format!("%{}%", like)
     }
+}
 
+/// Translate `*` and `?` to LIKE wildcards and escape LIKE's own, matching
+/// the whole name.
+fn like_pattern(pattern: &str) -> String {
+    let mut result = String::with_capacity(pattern.len() + 4);
     for c in pattern.chars() {
         match c {
             '*' => result.push('%'),
@@ -136,12 +457,6 @@ fn convert_wildcards_to_sql(pattern: &str) -> String {
             _ => result.push(c),
         }
     }
-
-    // If no wildcards, close substring search
-    if !has_wildcards {
-        result.push('%');
-    }
-
     result
 }
 
@@ -190,11 +505,18 @@ mod tests {
 
     #[test]
     fn test_extension_filter() {
-        let parsed = parse_query("ext:pdf").unwrap();
+        let parsed = parse_query("ext:PDF").unwrap();
         let (sql, params) = build_sql_query(&parsed);
 
+        assert!(sql.contains("ext = ?"));
+        assert!(!sql.contains("LIKE"));
+        assert_eq!(params[0], SqlParam::Text("pdf".to_string()));
+
+        // Multi-part extensions match the end of the name
+        let parsed = parse_query("ext:tar.gz").unwrap();
+        let (sql, params) = build_sql_query(&parsed);
         assert!(sql.contains("name LIKE ?"));
-        assert_eq!(params[0], SqlParam::Text("%.pdf".to_string()));
+        assert_eq!(params[0], SqlParam::Text("%.tar.gz".to_string()));
     }
 
     #[test]
@@ -251,6 +573,156 @@ mod tests {
         assert_eq!(params[0], SqlParam::Integer(0));
     }
 
+    #[test]
+    fn test_type_link() {
+        let parsed = parse_query("type:link").unwrap();
+        let (sql, params) = build_sql_query(&parsed);
+
+        assert!(sql.contains("(attributes & ?) != 0"));
+        assert_eq!(params[0], SqlParam::Integer(0x400));
+    }
+
+    #[test]
+    fn test_page_queries() {
+        let parsed = parse_query("report ext:pdf").unwrap();
+        let (sql, params) = build_sql_query_page(&parsed, 50, 100);
+        assert!(sql.ends_with("LIMIT ? OFFSET ?"));
+        assert_eq!(params[params.len() - 2..], [SqlParam::Integer(50), SqlParam::Integer(100)]);
+
+        let (sql, params) = build_relevance_query(&parsed, 50, 0);
+        assert!(sql.contains("ORDER BY CASE WHEN name LIKE ?"));
+        assert_eq!(
+            params[params.len() - 4..],
+            [
+                SqlParam::Text("report".to_string()),
+                SqlParam::Text("report%".to_string()),
+                SqlParam::Integer(50),
+                SqlParam::Integer(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_created_filter() {
+        use crate::db::{batch_insert_files, count_query_matches, insert_volume, schema, FileEntry};
+
+        let parsed = parse_query("created:>2024-01-01").unwrap();
+        let (sql, _params) = build_sql_query(&parsed);
+        assert!(sql.contains("created > ?"));
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let volume_id = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let files: Vec<FileEntry> = [("old.txt", Some(1_600_000_000)), ("new.txt", Some(1_750_000_000)), ("unknown.txt", None)]
+            .iter()
+            .enumerate()
+            .map(|(i, (name, created))| FileEntry {
+                volume_id,
+                file_ref: Some(i as i64 + 1),
+                parent_ref: Some(0),
+                name: name.to_string(),
+                modified: Some(1_750_000_000),
+                created: *created,
+                ..Default::default()
+            })
+            .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        assert_eq!(count_query_matches(&conn, &parsed).unwrap(), 1);
+        let before = parse_query("created:<2024-01-01").unwrap();
+        assert_eq!(count_query_matches(&conn, &before).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_attribute_filter_matches_rows() {
+        use crate::db::{batch_insert_files, count_query_matches, insert_volume, schema, FileEntry};
+        use crate::search::FileAttribute;
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let volume_id = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let files: Vec<FileEntry> = [("notes.txt", 0x20), ("desktop.ini", 0x2 | 0x4), ("notes.bak", 0x2 | 0x1)]
+            .iter()
+            .enumerate()
+            .map(|(i, (name, attributes))| FileEntry {
+                volume_id,
+                file_ref: Some(i as i64 + 1),
+                parent_ref: Some(0),
+                name: name.to_string(),
+                attributes: *attributes,
+                ..Default::default()
+            })
+            .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        let count = |query: &ParsedQuery| count_query_matches(&conn, query).unwrap();
+        assert_eq!(count(&parse_query("attrib:hidden").unwrap()), 2);
+        assert_eq!(count(&parse_query("attrib:readonly").unwrap()), 1);
+        assert_eq!(count(&parse_query("-attrib:system").unwrap()), 2);
+
+        let mut visible = parse_query("notes").unwrap();
+        visible.exclude_attributes(&[FileAttribute::Hidden, FileAttribute::System]);
+        assert_eq!(count(&visible), 1);
+    }
+
+    #[test]
+    fn test_stream_filter_matches_rows() {
+        use crate::db::{batch_insert_files, count_query_matches, insert_volume, schema, FileEntry};
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let volume_id = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let file = FileEntry {
+            volume_id,
+            file_ref: Some(1),
+            parent_ref: Some(0),
+            name: "report.docx".to_string(),
+            size: 100,
+            ..Default::default()
+        };
+        let stream = |name: &str| FileEntry {
+            name: format!("report.docx:{}", name),
+            size: 10,
+            stream: Some(name.to_string()),
+            ..file.clone()
+        };
+        batch_insert_files(&mut conn, &[file.clone(), stream("Zone.Identifier"), stream("summary")]).unwrap();
+
+        let count = |query: &str| count_query_matches(&conn, &parse_query(query).unwrap()).unwrap();
+        assert_eq!(count("report"), 3);
+        assert_eq!(count("stream:*"), 2);
+        assert_eq!(count("stream:zone.identifier"), 1);
+        assert_eq!(count("stream:zone"), 0);
+        assert_eq!(count("report -stream:*"), 1);
+    }
+
+    #[test]
+    fn test_offline_filter_matches_rows() {
+        use crate::db::{batch_insert_files, count_query_matches, insert_volume, schema, update_volume_state, FileEntry};
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let online = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let offline = insert_volume(&conn, "E:", "5678", "FAT32").unwrap();
+        update_volume_state(&conn, offline, VolumeState::Offline { since: 1_700_000_000 }).unwrap();
+        let file = |volume_id: i64, name: &str| FileEntry {
+            volume_id,
+            file_ref: Some(1),
+            parent_ref: Some(0),
+            name: name.to_string(),
+            size: 100,
+            ..Default::default()
+        };
+        batch_insert_files(&mut conn, &[file(online, "report.pdf"), file(offline, "report.docx")]).unwrap();
+
+        let count = |query: &str| count_query_matches(&conn, &parse_query(query).unwrap()).unwrap();
+        assert_eq!(count("report"), 2);
+        assert_eq!(count("report offline:true"), 2);
+        assert_eq!(count("report offline:false"), 1);
+        assert_eq!(count("report offline:only"), 1);
+        assert_eq!(count("ext:pdf OR offline:only"), 2);
+    }
+
     #[test]
     fn test_modified_filter() {
         let parsed = parse_query("modified:>yesterday").unwrap();
@@ -271,15 +743,16 @@ mod tests {
         let (sql, params) = build_sql_query(&parsed);
 
         assert!(sql.contains("name LIKE ? ESCAPE '\\'"));
-        assert!(sql.contains("name LIKE ?"));
+        assert!(sql.contains("ext = ?"));
         assert!(sql.contains("size > ?"));
         assert!(sql.contains(" AND "));
 
-        // Check params order: pattern, extension, size, limit
+        // Check params order: pattern, name index, extension, size, limit
         assert_eq!(params[0], SqlParam::Text("%report%".to_string()));
-        assert_eq!(params[1], SqlParam::Text("%.pdf".to_string()));
-        assert_eq!(params[2], SqlParam::Integer(1024 * 1024));
-        assert_eq!(params[3], SqlParam::Integer(100)); // default limit
+        assert_eq!(params[1], SqlParam::Text("\"report\"".to_string()));
+        assert_eq!(params[2], SqlParam::Text("pdf".to_string()));
+        assert_eq!(params[3], SqlParam::Integer(1024 * 1024));
+        assert_eq!(params[4], SqlParam::Integer(100)); // default limit
     }
 
     #[test]
@@ -348,6 +821,70 @@ mod tests {
         assert!(!sql.contains("C:\\"));
     }
 
+    #[test]
+    fn test_path_scope_uses_full_path() {
+        let parsed = parse_query(r"path:c:\Projects\").unwrap();
+        let (sql, params) = build_sql_query(&parsed);
+
+        assert!(sql.contains("drive_letter = ?"));
+        assert!(sql.contains("full_path >= ?"));
+        assert_eq!(
+            params[..3],
+            [
+                SqlParam::Text("C:".to_string()),
+                SqlParam::Text(r"Projects\".to_string()),
+                SqlParam::Text("Projects]".to_string()),
+            ]
+        );
+
+        // A bare drive scopes to the volume only
+        let (sql, params) = build_sql_query(&parse_query(r"path:D:\").unwrap());
+        assert!(!sql.contains("full_path"));
+        assert_eq!(params[0], SqlParam::Text("D:".to_string()));
+
+        // Volumes without a letter are scoped by their GUID path
+        let guid = r"\\?\Volume{3e2f6a10-5b7c-4f7e-9a1d-2c8b7e6f5a41}";
+        let (_sql, params) = build_sql_query(&parse_query(&format!(r"path:{}\Docs", guid)).unwrap());
+        assert_eq!(params[0], SqlParam::Text(guid.to_string()));
+        assert_eq!(params[1], SqlParam::Text(r"Docs\".to_string()));
+    }
+
+    #[test]
+    fn test_volume_and_tag_filters_match_rows() {
+        use crate::db::{add_tags, batch_insert_files, count_query_matches, insert_volume, schema, FileEntry};
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let c = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let d = insert_volume(&conn, "D:", "5678", "NTFS").unwrap();
+        let file = |volume_id: i64, file_ref: i64, name: &str| FileEntry {
+            volume_id,
+            file_ref: Some(file_ref),
+            parent_ref: Some(0),
+            name: name.to_string(),
+            size: 100,
+            ..Default::default()
+        };
+        batch_insert_files(
+            &mut conn,
+            &[file(c, 1, "report.pdf"), file(d, 1, "report.docx"), file(d, 2, "notes.txt")],
+        )
+        .unwrap();
+
+        let count = |query: &str| count_query_matches(&conn, &parse_query(query).unwrap()).unwrap();
+        assert_eq!(count("drive:D report"), 1);
+        assert_eq!(count("vol:d:"), 2);
+        assert_eq!(count("report -drive:C"), 1);
+        assert_eq!(count("drive:E"), 0);
+
+        // The same file reference on another volume is not tagged
+        add_tags(&mut conn, "5678", 1, &["Work".to_string()], 1_700_000_000).unwrap();
+        let count = |query: &str| count_query_matches(&conn, &parse_query(query).unwrap()).unwrap();
+        assert_eq!(count("tag:work"), 1);
+        assert_eq!(count("report -tag:WORK"), 1);
+        assert_eq!(count("tag:home"), 0);
+    }
+
     #[test]
     fn test_wildcard_conversion() {
         assert_eq!(convert_wildcards_to_sql("*.pdf"), "%.pdf");
@@ -355,6 +892,7 @@ mod tests {
         assert_eq!(convert_wildcards_to_sql("document"), "%document%");
         assert_eq!(convert_wildcards_to_sql("100%"), "%100\\%%");
         assert_eq!(convert_wildcards_to_sql("file_name"), "%file\\_name%");
+        assert_eq!(like_pattern("100%"), "100\\%");
     }
 
     #[test]
@@ -365,6 +903,99 @@ mod tests {
         assert_eq!(params[0], SqlParam::Text("%test%".to_string()));
     }
 
+    #[test]
+    fn test_sort_order() {
+        use crate::search::{SortField, SortSpec};
+
+        let mut parsed = parse_query("report").unwrap();
+        let (sql, _params) = build_sql_query(&parsed);
+        assert!(sql.contains("ORDER BY name COLLATE NOCASE LIMIT"));
+
+        parsed.sort = vec![SortSpec::desc(SortField::Size), SortSpec::asc(SortField::Modified)];
+        let (sql, _params) = build_sql_query(&parsed);
+        assert!(sql.contains("ORDER BY size DESC, modified ASC, name COLLATE NOCASE LIMIT"));
+
+        // Filters alone, sorted from the query text
+        let (sql, _params) = build_sql_query(&parse_query("size:>1gb sort:size sort:date-asc").unwrap());
+        assert!(sql.contains("WHERE size > ?"));
+        assert!(sql.contains("ORDER BY size DESC, modified ASC, name COLLATE NOCASE LIMIT"));
+    }
+
+    #[test]
+    fn test_fts_name_query() {
+        assert_eq!(fts_name_query("report", &['*', '?']), Some(r#""report""#.to_string()));
+        // Runs shorter than a trigram are left to LIKE
+        assert_eq!(fts_name_query("do?.txt", &['*', '?']), Some(r#"".txt""#.to_string()));
+        assert_eq!(fts_name_query("a*b?c", &['*', '?']), None);
+        assert_eq!(fts_name_query(r#"say "hi" now"#, &['%', '_']), Some(r#""say ""hi"" now""#.to_string()));
+
+        let (sql, _) = build_sql_query(&parse_query("ab").unwrap());
+        assert!(!sql.contains("files_fts"));
+    }
+
+    #[test]
+    fn test_count_query() {
+        let parsed = parse_query("report ext:pdf").unwrap();
+        let (sql, params) = build_count_query(&parsed);
+
+        assert!(sql.starts_with("SELECT COUNT(*) FROM files WHERE"));
+        assert!(!sql.contains("LIMIT"));
+        assert_eq!(params.len(), 3);
+        assert_eq!(params[2], SqlParam::Text("pdf".to_string()));
+    }
+
+    #[test]
+    fn test_boolean_conditions() {
+        let parsed = parse_query("(report OR invoice) ext:pdf -size:>1mb").unwrap();
+        let (sql, params) = build_count_query(&parsed);
+
+        assert!(sql.ends_with(
+            "WHERE ext = ? AND ((name LIKE ? ESCAPE '\\' AND id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH ?)) \
+             OR (name LIKE ? ESCAPE '\\' AND id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH ?))) \
+             AND NOT coalesce((size > ?), 0)"
+        ));
+        assert_eq!(params[0], SqlParam::Text("pdf".to_string()));
+        assert_eq!(params[1], SqlParam::Text("%report%".to_string()));
+        assert_eq!(params[3], SqlParam::Text("%invoice%".to_string()));
+        assert_eq!(params[5], SqlParam::Integer(1024 * 1024));
+    }
+
+    #[test]
+    fn test_boolean_conditions_match_rows() {
+        use crate::db::{batch_insert_files, count_query_matches, insert_volume, schema, FileEntry};
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        schema::init(&conn).unwrap();
+        let volume_id = insert_volume(&conn, "C:", "1234", "NTFS").unwrap();
+        let files: Vec<FileEntry> = [
+            ("report.pdf", Some(10)),
+            ("report draft.pdf", Some(10)),
+            ("invoice.pdf", None),
+            ("invoice.doc", Some(10)),
+            ("notes.pdf", Some(10)),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (name, size))| FileEntry {
+            volume_id,
+            file_ref: Some(i as i64 + 1),
+            parent_ref: Some(0),
+            name: name.to_string(),
+            size: size.unwrap_or_default(),
+            modified: *size,
+            ..Default::default()
+        })
+        .collect();
+        batch_insert_files(&mut conn, &files).unwrap();
+
+        let count = |query: &str| count_query_matches(&conn, &parse_query(query).unwrap()).unwrap();
+        assert_eq!(count("(report OR invoice) ext:pdf -draft"), 2);
+        assert_eq!(count("report OR invoice"), 4);
+        assert_eq!(count("NOT (report OR ext:doc)"), 2);
+        // Entries without a date pass a negated date filter
+        assert_eq!(count("invoice -modified:<1970-01-02"), 1);
+    }
+
     #[test]
     fn test_quoted_literal() {
         let parsed = parse_query(r#"ext:"my file.txt""#).unwrap();
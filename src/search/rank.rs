@@ -0,0 +1,475 @@
+//! Result ranking.
+//!
+//! A [`Ranker`] reorders a page of results after the SQL query has selected
+//! it, so embedders can plug in their own scoring without touching the SQL
+//! builder. [`Ranking`] selects a built-in ranker per search request; the
+//! default, [`RelevanceRanker`], combines name match, path depth,
+//! modification recency and open frequency.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ipc::protocol::FileResult;
+use crate::Result;
+
+use super::parser::parse_query;
+
+/// Reorders search results.
+pub trait Ranker: Send + Sync {
+    /// Score a result for a query; higher scores rank first.
+    fn score(&self, query: &str, result: &FileResult) -> f64;
+
+    /// Reorder results by descending score, storing each score in `rank`.
+    ///
+    /// The sort is stable, so results with equal scores keep the order
+    /// they were selected in.
+    fn rank(&self, query: &str, results: &mut [FileResult]) {
+        let mut scored: Vec<(f64, FileResult)> = results
+            .iter()
+            .map(|r| {
+                let score = self.score(query, r);
+                (score, FileResult { rank: Some(score), ..r.clone() })
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        for (slot, (_, result)) in results.iter_mut().zip(scored) {
+            *slot = result;
+        }
+    }
+}
+
+/// Built-in ranking selectable per search request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ranking {
+    /// Best name matches first, then shallow, recently changed and often opened files
+    #[default]
+    Relevance,
+    /// Keep the requested sort order (by name unless sort keys are given)
+    Alphabetical,
+    /// Recently and frequently opened files first
+    Frecency,
+    /// Closest name matches first
+    Fuzzy,
+    /// The ranker the embedding application registered, if any
+    Custom,
+}
+
+impl Ranking {
+    /// Rankings offered in the UI, in display order.
+    pub const ALL: [Ranking; 4] = [
+        Ranking::Relevance,
+        Ranking::Alphabetical,
+        Ranking::Frecency,
+        Ranking::Fuzzy,
+    ];
+
+    /// Human-readable label for the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Ranking::Relevance => "Relevance",
+            Ranking::Alphabetical => "Alphabetical",
+            Ranking::Frecency => "Frecency",
+            Ranking::Fuzzy => "Best match",
+            Ranking::Custom => "Custom",
+        }
+    }
+}
+
+/// Default ranker: leaves results in the order the query returned them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlphabeticalRanker;
+
+impl Ranker for AlphabeticalRanker {
+    fn score(&self, _query: &str, _result: &FileResult) -> f64 {
+        0.0
+    }
+
+    fn rank(&self, _query: &str, _results: &mut [FileResult]) {}
+}
+
+/// Ranks names by how closely they match the query's name pattern.
+///
+/// Exact names beat exact stems, then prefixes, word starts, substrings
+/// and finally in-order character matches; shorter names win within a tier.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FuzzyRanker;
+
+impl FuzzyRanker {
+    /// Score a name against a lowercase needle.
+    fn score_name(needle: &str, name: &str) -> f64 {
+        if needle.is_empty() {
+            return 0.0;
+        }
+        let name = name.to_lowercase();
+        let stem = name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem);
+
+        let tier = if name == needle {
+            6.0
+        } else if stem == needle {
+            5.0
+        } else if name.starts_with(needle) {
+            4.0
+        } else if name
+            .match_indices(needle)
+            .any(|(i, _)| !name[..i].chars().next_back().is_some_and(char::is_alphanumeric))
+        {
+            3.0
+        } else if name.contains(needle) {
+            2.0
+        } else if is_subsequence(needle, &name) {
+            1.0
+        } else {
+            return 0.0;
+        };
+
+        // Less unmatched text ranks higher, without crossing into another tier
+        let extra = name.chars().count().saturating_sub(needle.chars().count()) as f64;
+        tier + 1.0 / (2.0 + extra)
+    }
+}
+
+impl Ranker for FuzzyRanker {
+    fn score(&self, query: &str, result: &FileResult) -> f64 {
+        let pattern = parse_query(query)
+            .ok()
+            .and_then(|parsed| parsed.pattern)
+            .unwrap_or_default();
+        let needle: String = pattern
+            .to_lowercase()
+            .chars()
+            .filter(|c| *c != '*' && *c != '?')
+            .collect();
+
+        Self::score_name(needle.trim(), &result.name)
+    }
+}
+
+/// Whether all characters of `needle` appear in `haystack` in order.
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut chars = haystack.chars();
+    needle.chars().all(|c| chars.any(|h| h == c))
+}
+
+/// Ranks recently and frequently opened files first.
+///
+/// Each open counts for less as it ages: full weight within a day, half
+/// within a week, a quarter within a month, and an eighth after that. Only
+/// the latest opens of a file are timed, so all of its opens are weighed by
+/// their average age: a file opened often long ago and once today doesn't
+/// outrank one opened every day this week.
+#[derive(Debug, Clone, Default)]
+pub struct FrecencyRanker {
+    /// Frecency score by lowercase path
+    scores: HashMap<String, f64>,
+}
+
+impl FrecencyRanker {
+    /// Create a ranker with no open history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the open history of a path.
+    ///
+    /// # Arguments
+    /// * `path` - Full path of the opened file
+    /// * `open_count` - How many times it was opened
+    /// * `last_opened` - Unix timestamp of the latest open
+    /// * `now` - Current Unix timestamp
+    pub fn record(&mut self, path: &str, open_count: i64, last_opened: i64, now: i64) {
+        self.record_samples(path, open_count, &[last_opened], now);
+    }
+
+    /// Add the open history of a path with the times of its latest opens.
+    ///
+    /// # Arguments
+    /// * `path` - Full path of the opened file
+    /// * `open_count` - How many times it was opened
+    /// * `samples` - Unix timestamps of its latest opens (ignored if empty)
+    /// * `now` - Current Unix timestamp
+    pub fn record_samples(&mut self, path: &str, open_count: i64, samples: &[i64], now: i64) {
+        if samples.is_empty() {
+            return;
+        }
+        let weight = samples.iter().map(|&opened| age_weight(now, opened)).sum::<f64>() / samples.len() as f64;
+        *self.scores.entry(path.to_lowercase()).or_insert(0.0) += open_count.max(0) as f64 * weight;
+    }
+}
+
+/// Weight of an open by its age.
+fn age_weight(now: i64, opened: i64) -> f64 {
+    match (now - opened).max(0) / 86400 {
+        0 => 1.0,
+        1..=6 => 0.5,
+        7..=29 => 0.25,
+        _ => 0.125,
+    }
+}
+
+impl Ranker for FrecencyRanker {
+    fn score(&self, _query: &str, result: &FileResult) -> f64 {
+        self.scores
+            .get(&result.path.to_lowercase())
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+/// Seconds a cached frecency ranker is used before it is rebuilt, so its
+/// age weights stay current.
+pub const FRECENCY_CACHE_SECS: i64 = 3600;
+
+/// Frecency ranker shared between searches.
+///
+/// Building one reads the whole open history, too much to repeat for each
+/// keystroke of a search, so the ranker is kept until the history changes
+/// ([`invalidate`](Self::invalidate)) or it is [`FRECENCY_CACHE_SECS`] old.
+#[derive(Debug, Default)]
+pub struct FrecencyCache {
+    /// Bumped by each invalidation
+    generation: AtomicU64,
+    /// Ranker with the generation and the time it was built in
+    cached: RwLock<Option<(u64, i64, Arc<FrecencyRanker>)>>,
+}
+
+impl FrecencyCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cached ranker, or one made by `build` if none is current.
+    ///
+    /// # Arguments
+    /// * `now` - Current Unix timestamp
+    /// * `build` - Builds a ranker from the open history
+    pub fn get_or_build(
+        &self,
+        now: i64,
+        build: impl FnOnce() -> Result<FrecencyRanker>,
+    ) -> Result<Arc<FrecencyRanker>> {
+        let generation = self.generation.load(Ordering::Acquire);
+        let cached = self.cached.read().ok().and_then(|cached| cached.clone());
+        if let Some((built_in, built_at, ranker)) = cached {
+            if built_in == generation && (0..FRECENCY_CACHE_SECS).contains(&(now - built_at)) {
+                return Ok(ranker);
+            }
+        }
+
+        let ranker = Arc::new(build()?);
+        // Opens recorded while building may be missing from this one
+        if let Ok(mut cached) = self.cached.write() {
+            if self.generation.load(Ordering::Acquire) == generation {
+                *cached = Some((generation, now, Arc::clone(&ranker)));
+            }
+        }
+        Ok(ranker)
+    }
+
+    /// Drop the cached ranker after the open history changed.
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// Half-life of the modification recency bonus, in days.
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// Largest bonus from depth, recency and opens; below the smallest gap
+/// between [`FuzzyRanker`] tiers, so the name match always decides first.
+const MAX_RELEVANCE_BONUS: f64 = 0.45;
+
+/// Ranks by overall relevance.
+///
+/// The name match scores as in [`FuzzyRanker`] (exact, then prefix, then
+/// substring). Within a match tier, shallower paths, recently modified
+/// files and frequently opened files rank higher.
+#[derive(Debug, Clone, Default)]
+pub struct RelevanceRanker {
+    frecency: Arc<FrecencyRanker>,
+    now: i64,
+}
+
+impl RelevanceRanker {
+    /// Create a ranker from open history, judging recency at `now` (Unix timestamp).
+    pub fn new(frecency: Arc<FrecencyRanker>, now: i64) -> Self {
+        Self { frecency, now }
+    }
+}
+
+impl Ranker for RelevanceRanker {
+    fn score(&self, query: &str, result: &FileResult) -> f64 {
+        let depth = result.path.matches(['\\', '/']).count() as f64;
+        let shallow = 1.0 / (1.0 + depth);
+
+        let recent = if result.modified > 0 {
+            let age_days = (self.now - result.modified).max(0) as f64 / 86400.0;
+            0.5f64.powf(age_days / RECENCY_HALF_LIFE_DAYS)
+        } else {
+            0.0
+        };
+
+        let opens = self.frecency.score(query, result);
+        let frequent = opens / (1.0 + opens);
+
+        FuzzyRanker.score(query, result)
+            + MAX_RELEVANCE_BONUS * (0.3 * shallow + 0.3 * recent + 0.4 * frequent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::protocol::ResultSource;
+
+    fn result(path: &str) -> FileResult {
+        FileResult {
+            id: 0,
+            name: path.rsplit('\\').next().unwrap().to_string(),
+            path: path.to_string(),
+            size: 0,
+            modified: 0,
+            is_dir: false,
+            duplicates: 0,
+            source: ResultSource::Index,
+            rank: None,
+            volume_state: None,
+            original_path: None,
+        }
+    }
+
+    fn names(results: &[FileResult]) -> Vec<&str> {
+        results.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_alphabetical_keeps_order() {
+        let mut results = vec![result(r"C:\b.txt"), result(r"C:\a.txt")];
+        AlphabeticalRanker.rank("txt", &mut results);
+        assert_eq!(names(&results), vec!["b.txt", "a.txt"]);
+        assert!(results[0].rank.is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        let mut results = vec![
+            result(r"C:\r_e_p_o_r_t.doc"),
+            result(r"C:\annual report 2024.pdf"),
+            result(r"C:\myreport.txt"),
+            result(r"C:\reports.xlsx"),
+            result(r"C:\report.pdf"),
+            result(r"C:\unrelated.txt"),
+        ];
+        FuzzyRanker.rank("report ext:pdf", &mut results);
+        assert_eq!(
+            names(&results),
+            vec![
+                "report.pdf",
+                "reports.xlsx",
+                "annual report 2024.pdf",
+                "myreport.txt",
+                "r_e_p_o_r_t.doc",
+                "unrelated.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frecency_ranking() {
+        let now = 1_700_000_000;
+        let mut ranker = FrecencyRanker::new();
+        ranker.record(r"C:\old.txt", 10, now - 60 * 86400, now);
+        ranker.record(r"C:\RECENT.txt", 2, now - 3600, now);
+
+        let mut results = vec![result(r"C:\never.txt"), result(r"C:\old.txt"), result(r"C:\recent.txt")];
+        ranker.rank("txt", &mut results);
+        assert_eq!(names(&results), vec!["recent.txt", "old.txt", "never.txt"]);
+
+        // Many opens long ago and one today lose to daily opens this week
+        let day = 86400;
+        let mut ranker = FrecencyRanker::new();
+        let mut old = vec![now - 90 * day; 9];
+        old.insert(0, now);
+        ranker.record_samples(r"C:\old.txt", 12, &old, now);
+        ranker.record_samples(r"C:\daily.txt", 7, &(0..7).map(|d| now - d * day).collect::<Vec<_>>(), now);
+        let mut results = vec![result(r"C:\old.txt"), result(r"C:\daily.txt")];
+        ranker.rank("txt", &mut results);
+        assert_eq!(names(&results), vec!["daily.txt", "old.txt"]);
+    }
+
+    #[test]
+    fn test_frecency_cache() {
+        let now = 1_700_000_000;
+        let cache = FrecencyCache::new();
+        let builds = std::cell::Cell::new(0);
+        let build = || {
+            builds.set(builds.get() + 1);
+            let mut ranker = FrecencyRanker::new();
+            ranker.record(r"C:\a.txt", builds.get(), now, now);
+            Ok(ranker)
+        };
+
+        let first = cache.get_or_build(now, build).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.get_or_build(now + 60, build).unwrap()));
+        assert_eq!(builds.get(), 1);
+
+        // A recorded open, or an hour passing, rebuilds it
+        cache.invalidate();
+        let second = cache.get_or_build(now + 60, build).unwrap();
+        assert_eq!(builds.get(), 2);
+        assert!(second.score("a", &result(r"C:\a.txt")) > first.score("a", &result(r"C:\a.txt")));
+        cache.get_or_build(now + 60 + FRECENCY_CACHE_SECS, build).unwrap();
+        assert_eq!(builds.get(), 3);
+
+        // Failures aren't cached
+        assert!(cache
+            .get_or_build(now, || Err(crate::FFIError::Database("locked".to_string())))
+            .is_err());
+    }
+
+    #[test]
+    fn test_relevance_ranking() {
+        let now = 1_700_000_000;
+        let mut frecency = FrecencyRanker::new();
+        frecency.record(r"C:\Work\notes\todo list.txt", 20, now, now);
+        let ranker = RelevanceRanker::new(Arc::new(frecency), now);
+
+        let mut results = vec![
+            result(r"C:\Work\notes\todo list.txt"),
+            result(r"C:\Work\archive\old\todo.md"),
+            result(r"C:\todo.md"),
+            result(r"C:\Work\todo-2024.md"),
+            result(r"C:\Work\mytodo.md"),
+        ];
+        results[3].modified = now - 86400;
+
+        ranker.rank("todo", &mut results);
+        assert_eq!(
+            names(&results),
+            vec!["todo.md", "todo.md", "todo list.txt", "todo-2024.md", "mytodo.md"]
+        );
+        // Shallower exact match first
+        assert_eq!(results[0].path, r"C:\todo.md");
+        assert!(results.iter().all(|r| r.rank.is_some()));
+        assert!(results[0].rank > results[1].rank);
+
+        // Open frequency beats recency between equal name matches
+        let mut results = vec![result(r"C:\Work\todo-2024.md"), result(r"C:\Work\todo list.txt")];
+        results[0].modified = now;
+        results[1].path = r"C:\Work\notes\todo list.txt".to_string();
+        ranker.rank("todo", &mut results);
+        assert_eq!(names(&results)[0], "todo list.txt");
+    }
+
+    #[test]
+    fn test_ranking_serde() {
+        assert_eq!(serde_json::to_string(&Ranking::Frecency).unwrap(), "\"frecency\"");
+        assert_eq!(Ranking::default(), Ranking::Relevance);
+        assert_eq!(Ranking::ALL[0], Ranking::Relevance);
+    }
+}
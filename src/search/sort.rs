@@ -0,0 +1,154 @@
+//! Result sort order for search queries.
+//!
+//! A sort is an ordered list of [`SortSpec`]s (primary, secondary, ...),
+//! converted into an SQL `ORDER BY` clause. Name is always used as the
+//! final tie-breaker so results are stable.
+
+use serde::{Deserialize, Serialize};
+
+/// SQL expression extracting the extension (text after the last dot) from `name`.
+const EXTENSION_SQL: &str = "CASE WHEN instr(name, '.') = 0 THEN '' \
+     ELSE replace(name, rtrim(name, replace(name, '.', '')), '') END COLLATE NOCASE";
+
+/// Column a search can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortField {
+    /// Filename (case-insensitive)
+    Name,
+    /// File extension (case-insensitive)
+    Extension,
+    /// File size in bytes
+    Size,
+    /// Last modified time
+    Modified,
+}
+
+impl SortField {
+    /// All sortable fields, in display order.
+    pub const ALL: [SortField; 4] = [
+        SortField::Name,
+        SortField::Extension,
+        SortField::Size,
+        SortField::Modified,
+    ];
+
+    /// Convert to the SQL expression used in ORDER BY.
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            SortField::Name => "name COLLATE NOCASE",
+            SortField::Extension => EXTENSION_SQL,
+            SortField::Size => "size",
+            SortField::Modified => "modified",
+        }
+    }
+
+    /// Whether `sort:` terms without a direction sort descending: sizes
+    /// and dates are browsed largest or newest first.
+    pub fn descending_by_default(&self) -> bool {
+        matches!(self, SortField::Size | SortField::Modified)
+    }
+
+    /// Human-readable label for the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortField::Name => "Name",
+            SortField::Extension => "Extension",
+            SortField::Size => "Size",
+            SortField::Modified => "Modified",
+        }
+    }
+}
+
+/// A single sort key with direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortSpec {
+    /// Field to sort by
+    pub field: SortField,
+    /// Sort descending instead of ascending
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl SortSpec {
+    /// Ascending sort on a field.
+    pub fn asc(field: SortField) -> Self {
+        Self { field, descending: false }
+    }
+
+    /// Descending sort on a field.
+    pub fn desc(field: SortField) -> Self {
+        Self { field, descending: true }
+    }
+}
+
+/// Build the ORDER BY clause body (without the `ORDER BY` keyword).
+///
+/// An empty sort falls back to name order. Name is appended as a final
+/// tie-breaker unless it is already part of the sort.
+pub fn order_by_clause(sort: &[SortSpec]) -> String {
+    let mut terms: Vec<String> = sort
+        .iter()
+        .map(|s| {
+            let dir = if s.descending { "DESC" } else { "ASC" };
+            format!("{} {}", s.field.to_sql(), dir)
+        })
+        .collect();
+
+    if !sort.iter().any(|s| s.field == SortField::Name) {
+        terms.push(SortField::Name.to_sql().to_string());
+    }
+
+    terms.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_order() {
+        assert_eq!(order_by_clause(&[]), "name COLLATE NOCASE");
+    }
+
+    #[test]
+    fn test_primary_and_secondary() {
+        let clause = order_by_clause(&[SortSpec::asc(SortField::Extension), SortSpec::desc(SortField::Size)]);
+        assert!(clause.starts_with("CASE WHEN"));
+        assert!(clause.contains("size DESC"));
+        assert!(clause.ends_with("name COLLATE NOCASE"));
+    }
+
+    #[test]
+    fn test_name_not_duplicated() {
+        let clause = order_by_clause(&[SortSpec::desc(SortField::Name)]);
+        assert_eq!(clause, "name COLLATE NOCASE DESC");
+    }
+
+    #[test]
+    fn test_sort_spec_serialization() {
+        let spec = SortSpec::desc(SortField::Modified);
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(json, r#"{"field":"modified","descending":true}"#);
+
+        let parsed: SortSpec = serde_json::from_str(r#"{"field":"size"}"#).unwrap();
+        assert_eq!(parsed, SortSpec::asc(SortField::Size));
+    }
+
+    #[test]
+    fn test_extension_sql_in_sqlite() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let ext = |name: &str| -> String {
+            conn.query_row(
+                &format!("SELECT {} FROM (SELECT ?1 AS name)", EXTENSION_SQL),
+                [name],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(ext("report.pdf"), "pdf");
+        assert_eq!(ext("archive.tar.gz"), "gz");
+        assert_eq!(ext("README"), "");
+    }
+}
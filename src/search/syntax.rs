@@ -0,0 +1,284 @@
+//! Machine-readable description of the search syntax.
+//!
+//! [`syntax_help`] lists the filters, operators, size units, relative
+//! dates and sort keys the parser accepts. Units, dates, comparators, sort
+//! keys and type values come
+//! from the tables the parser itself matches against, and every example is
+//! checked to parse, so help shown to users (the F1 window, the
+//! `get_syntax_help` IPC command) can't drift from what queries accept.
+
+use serde::{Deserialize, Serialize};
+
+use super::filters::{FileAttribute, FileType, OfflineScope, SizeOp};
+use super::parser::{COMPARATORS, RELATIVE_DATES, SIZE_UNITS, SORT_KEYS, TYPE_VALUES};
+use super::sort::SortField;
+
+/// One token of the syntax (a wildcard, operator, unit or date) and its meaning.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    /// Text as typed in a query (e.g. `>=`, `mb`, `lastweek`)
+    pub token: String,
+    /// What the token means
+    pub description: String,
+}
+
+/// A `name:value` filter.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FilterSyntax {
+    /// Filter name before the colon (e.g. `ext`)
+    pub name: String,
+    /// What the filter matches
+    pub description: String,
+    /// Example terms, each a valid query on its own
+    pub examples: Vec<String>,
+}
+
+/// Everything the query parser accepts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxHelp {
+    /// Wildcards in name patterns
+    pub wildcards: Vec<SyntaxToken>,
+    /// Filters, all of which must match
+    pub filters: Vec<FilterSyntax>,
+    /// Comparison operators for `size:`, `modified:` and `created:`
+    pub comparators: Vec<SyntaxToken>,
+    /// Units for `size:` values (case-insensitive)
+    pub size_units: Vec<SyntaxToken>,
+    /// Relative dates for `modified:` and `created:` (case-insensitive)
+    pub relative_dates: Vec<SyntaxToken>,
+    /// Format of absolute dates for `modified:` and `created:`
+    pub date_format: String,
+    /// `sort:` terms, each reversed by appending `-asc` or `-desc`
+    #[serde(default)]
+    pub sort_keys: Vec<SyntaxToken>,
+    /// General notes (word matching, quoting)
+    pub notes: Vec<String>,
+}
+
+/// Describe the search syntax accepted by [`parse_query`](super::parse_query).
+pub fn syntax_help() -> SyntaxHelp {
+    let token = |token: &str, description: String| SyntaxToken {
+        token: token.to_string(),
+        description,
+    };
+    let filter = |name: &str, description: String, examples: &[&str]| FilterSyntax {
+        name: name.to_string(),
+        description,
+        examples: examples.iter().map(|e| e.to_string()).collect(),
+    };
+
+    let type_values = |file_type: FileType| {
+        TYPE_VALUES
+            .iter()
+            .filter(|(_, t)| *t == file_type)
+            .map(|(value, _)| *value)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    SyntaxHelp {
+        wildcards: vec![
+            token("*", "Any number of characters".to_string()),
+            token("?", "Exactly one character".to_string()),
+        ],
+        filters: vec![
+            filter(
+                "ext",
+                "Names ending in .<ext> (without the dot)".to_string(),
+                &["ext:pdf"],
+            ),
+            filter(
+                "size",
+                "File size with an optional comparator and unit; without a comparator, at least the size"
+                    .to_string(),
+                &["size:>10mb", "size:<=512kb", "size:1gb"],
+            ),
+            filter(
+                "type",
+                format!(
+                    "Only files ({}), only folders ({}) or only symlinks and junctions ({})",
+                    type_values(FileType::File),
+                    type_values(FileType::Folder),
+                    type_values(FileType::Link)
+                ),
+                &["type:file", "type:folder", "type:link"],
+            ),
+            filter(
+                "modified",
+                "Last modified date, absolute or relative; without a comparator, on or after the date"
+                    .to_string(),
+                &["modified:today", "modified:>2024-01-15", "modified:<lastmonth"],
+            ),
+            filter(
+                "created",
+                "Creation date, written like modified".to_string(),
+                &["created:lastweek", "created:>2024-01-01"],
+            ),
+            filter(
+                "path",
+                "Only entries below a folder".to_string(),
+                &[r"path:C:\Projects", r#"path:"C:\My Projects""#],
+            ),
+            filter(
+                "drive",
+                "Only entries of one volume, by drive letter or mount folder; vol: is the same and \
+                 cheaper than path: for a whole volume"
+                    .to_string(),
+                &["drive:D", "vol:E:"],
+            ),
+            filter(
+                "regex",
+                "Names matching a regular expression (case-insensitive, anywhere in the name unless anchored)"
+                    .to_string(),
+                &[r"regex:^IMG_\d{4}\.jpe?g$", r#"regex:"(draft|final) v\d""#],
+            ),
+            filter(
+                "attrib",
+                format!(
+                    "Entries with a file attribute ({})",
+                    FileAttribute::ALL.map(|a| a.name()).join(", ")
+                ),
+                &["attrib:hidden", "attrib:readonly"],
+            ),
+            filter(
+                "stream",
+                "NTFS alternate data streams with a matching stream name, if stream indexing is enabled"
+                    .to_string(),
+                &["stream:*", "stream:Zone.Identifier"],
+            ),
+            filter(
+                "offline",
+                format!(
+                    "Entries of disconnected volumes: with them ({}), without them ({}) or only them ({})",
+                    OfflineScope::Include.name(),
+                    OfflineScope::Exclude.name(),
+                    OfflineScope::Only.name()
+                ),
+                &["offline:false", "offline:only"],
+            ),
+            filter(
+                "content",
+                "Files whose text contains the words, in order; only small text files are searched, and only \
+                 if content indexing is enabled"
+                    .to_string(),
+                &[r#"content:"TODO refactor""#, "content:deprecated"],
+            ),
+            filter(
+                "tag",
+                "Files and folders tagged from the result context menu (case-insensitive)".to_string(),
+                &["tag:work", r#"tag:"tax 2024""#],
+            ),
+            filter(
+                "in",
+                "Deleted files and folders still in the Recycle Bin, which other searches leave out".to_string(),
+                &["in:recyclebin"],
+            ),
+        ],
+        comparators: COMPARATORS
+            .iter()
+            .map(|(text, op)| {
+                let meaning = match op {
+                    SizeOp::GreaterThan => "Larger or later than",
+                    SizeOp::GreaterEqual => "At least, or on or after",
+                    SizeOp::LessThan => "Smaller or earlier than",
+                    SizeOp::LessEqual => "At most, or on or before",
+                };
+                token(text, meaning.to_string())
+            })
+            .collect(),
+        size_units: SIZE_UNITS
+            .iter()
+            .map(|(unit, bytes)| token(unit, format!("{} bytes", bytes)))
+            .collect(),
+        relative_dates: RELATIVE_DATES
+            .iter()
+            .map(|(name, days)| {
+                let description = match days {
+                    0 => "Start of today".to_string(),
+                    1 => "Start of yesterday".to_string(),
+                    n => format!("{} days before today", n),
+                };
+                token(name, description)
+            })
+            .collect(),
+        date_format: "YYYY-MM-DD".to_string(),
+        sort_keys: SORT_KEYS
+            .iter()
+            .map(|(key, field)| {
+                let order = match field {
+                    SortField::Name | SortField::Extension => "A to Z",
+                    SortField::Size => "largest first",
+                    SortField::Modified => "newest first",
+                };
+                token(&format!("sort:{}", key), format!("{}, {}", field.label(), order))
+            })
+            .collect(),
+        notes: vec![
+            "Text outside filters matches anywhere in the name; with * or ? the whole name must match"
+                .to_string(),
+            r#"Quote values containing spaces, e.g. path:"C:\My Projects""#.to_string(),
+            "Terms are combined with AND; use OR for alternatives, -term or NOT term to exclude, \
+             and parentheses to group, e.g. (report OR invoice) ext:pdf -draft"
+                .to_string(),
+            "A query may be filters alone, e.g. size:>1gb sort:size lists the largest files; \
+             several sort: terms sort by each in turn"
+                .to_string(),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{parse_query, Filter};
+
+    #[test]
+    fn test_examples_parse() {
+        let help = syntax_help();
+        for filter in &help.filters {
+            for example in &filter.examples {
+                let query = parse_query(example).unwrap_or_else(|e| panic!("{}: {}", example, e));
+                assert!(query.pattern.is_none(), "{} parsed as a name pattern", example);
+                let name = match &query.filters[..] {
+                    [Filter::Extension(_)] => "ext",
+                    [Filter::Size(..)] => "size",
+                    [Filter::Type(_)] => "type",
+                    [Filter::Modified(..)] => "modified",
+                    [Filter::Created(..)] => "created",
+                    [Filter::PathScope(_)] => "path",
+                    [Filter::Volume(_)] => "drive",
+                    [Filter::Regex(_)] => "regex",
+                    [Filter::Attribute(_)] => "attrib",
+                    [Filter::Stream(_)] => "stream",
+                    [Filter::Offline(_)] => "offline",
+                    [Filter::Content(_)] => "content",
+                    [Filter::Tag(_)] => "tag",
+                    [Filter::RecycleBin] => "in",
+                    other => panic!("{} parsed as {:?}", example, other),
+                };
+                assert_eq!(name, filter.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokens_parse() {
+        let help = syntax_help();
+        for op in &help.comparators {
+            parse_query(&format!("size:{}1mb modified:{}today", op.token, op.token)).unwrap();
+        }
+        for unit in &help.size_units {
+            parse_query(&format!("size:>1{}", unit.token.to_uppercase())).unwrap();
+        }
+        for date in &help.relative_dates {
+            parse_query(&format!("modified:{}", date.token)).unwrap();
+        }
+        for key in &help.sort_keys {
+            for suffix in ["", "-asc", "-desc"] {
+                let query = parse_query(&format!("{}{}", key.token, suffix)).unwrap();
+                assert_eq!(query.sort.len(), 1, "{}{}", key.token, suffix);
+            }
+        }
+        assert_eq!(help.size_units[1].description, "1024 bytes");
+    }
+}
@@ -0,0 +1,483 @@
+//! Windows Search fallback for volumes FFI does not index.
+//!
+//! Opt-in via `[windows_search]` in config. Queries are translated to
+//! Windows Search SQL against `SystemIndex`, scoped to the fallback volumes,
+//! and run through the `Search.CollatorDSO` OLE DB provider. Results are
+//! tagged with [`ResultSource::WindowsSearch`] so the UI can label them.
+
+use chrono::DateTime;
+use serde::Deserialize;
+
+use crate::ipc::protocol::{FileResult, ResultSource};
+use crate::service::config::{volume_drive_letter, Config};
+use crate::{FFIError, Result};
+
+use super::filters::{FileType, Filter, OfflineScope};
+use super::parser::{parse_query, ParsedQuery};
+
+/// Proxies searches on non-indexed volumes to Windows Search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowsSearchFallback {
+    /// Drive letters queried through Windows Search
+    volumes: Vec<char>,
+    /// Maximum number of results taken from Windows Search per query
+    max_results: usize,
+}
+
+impl WindowsSearchFallback {
+    /// Build the fallback from config.
+    ///
+    /// Uses the volumes listed in `[windows_search]`, or every volume the
+    /// user disabled under `[volumes]` if none are listed. Volumes FFI
+    /// indexes are never proxied.
+    ///
+    /// # Returns
+    /// `None` if the fallback is disabled or no volume qualifies.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let settings = &config.windows_search;
+        if !settings.enabled {
+            return None;
+        }
+
+        let candidates: Vec<String> = if settings.volumes.is_empty() {
+            config
+                .volumes
+                .iter()
+                .filter(|(_, v)| !v.enabled)
+                .map(|(letter, _)| letter.clone())
+                .collect()
+        } else {
+            settings.volumes.clone()
+        };
+
+        // Windows Search scopes by drive letter, so folder and GUID mounts are skipped
+        let mut volumes: Vec<char> = candidates
+            .iter()
+            .filter_map(|v| volume_drive_letter(v))
+            .filter(|c| !config.is_volume_enabled(*c))
+            .collect();
+        volumes.sort_unstable();
+        volumes.dedup();
+
+        if volumes.is_empty() {
+            None
+        } else {
+            Some(Self {
+                volumes,
+                max_results: settings.max_results,
+            })
+        }
+    }
+
+    /// Drive letters queried through Windows Search.
+    pub fn volumes(&self) -> &[char] {
+        &self.volumes
+    }
+
+    /// Search the fallback volumes.
+    ///
+    /// Queries that fail to parse, or whose path scope is outside the
+    /// fallback volumes, return no results.
+    pub fn search(&self, query: &str) -> Result<Vec<FileResult>> {
+        let Ok(parsed) = parse_query(query) else {
+            return Ok(Vec::new());
+        };
+        match self.build_sql(&parsed) {
+            Some(sql) => run_windows_search(&sql),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Translate a parsed query to Windows Search SQL.
+    ///
+    /// # Returns
+    /// `None` if there is nothing to search (empty query, or a path scope
+    /// or volume filter for another volume) or the query has a regex, attribute, link or
+    /// stream filter or OR/NOT conditions, which aren't translated.
+    pub fn build_sql(&self, parsed: &ParsedQuery) -> Option<String> {
+        if (parsed.pattern.is_none() && parsed.filters.is_empty()) || !parsed.conditions.is_empty() {
+            return None;
+        }
+
+        let mut conditions: Vec<String> = Vec::new();
+
+        if let Some(ref pattern) = parsed.pattern {
+            conditions.push(format!("System.FileName LIKE '{}'", like_pattern(pattern)));
+        }
+
+        let mut scopes: Vec<String> = self
+            .volumes
+            .iter()
+            .map(|letter| format!("file:{}:/", letter))
+            .collect();
+
+        for filter in &parsed.filters {
+            match filter {
+                Filter::Extension(ext) => {
+                    conditions.push(format!("System.FileExtension = '.{}'", quote(ext)));
+                }
+                Filter::Size(op, bytes) => {
+                    conditions.push(format!("System.Size {} {}", op.to_sql(), bytes));
+                }
+                Filter::Type(file_type) => {
+                    let op = match file_type {
+                        FileType::Folder => "=",
+                        FileType::File => "<>",
+                        FileType::Link => return None,
+                    };
+                    conditions.push(format!("System.ItemType {} 'Directory'", op));
+                }
+                Filter::Modified(op, timestamp) => {
+                    let date = DateTime::from_timestamp(*timestamp, 0)?;
+                    conditions.push(format!(
+                        "System.DateModified {} '{}'",
+                        op.to_sql(),
+                        date.format("%Y-%m-%d %H:%M:%S")
+                    ));
+                }
+                Filter::Created(op, timestamp) => {
+                    let date = DateTime::from_timestamp(*timestamp, 0)?;
+                    conditions.push(format!(
+                        "System.DateCreated {} '{}'",
+                        op.to_sql(),
+                        date.format("%Y-%m-%d %H:%M:%S")
+                    ));
+                }
+                Filter::PathScope(path) => {
+                    let letter = path.chars().next()?.to_ascii_uppercase();
+                    if !self.volumes.contains(&letter) {
+                        return None;
+                    }
+                    scopes = vec![format!("file:{}", quote(&path.replace('\\', "/")))];
+                }
+                Filter::Volume(name) => {
+                    let letter = name.chars().next()?.to_ascii_uppercase();
+                    if name.len() != 2 || !self.volumes.contains(&letter) {
+                        return None;
+                    }
+                    scopes.retain(|scope| scope[5..].starts_with(letter));
+                    if scopes.is_empty() {
+                        return None;
+                    }
+                }
+                Filter::Content(text) => {
+                    conditions.push(format!(
+                        "CONTAINS(System.Search.Contents, '\"{}\"')",
+                        quote(&text.replace('"', ""))
+                    ));
+                }
+                // Volumes Windows Search covers are connected
+                Filter::Offline(OfflineScope::Include | OfflineScope::Exclude) => {}
+                Filter::Regex(_)
+                | Filter::Attribute(_)
+                | Filter::Stream(_)
+                | Filter::Tag(_)
+                | Filter::RecycleBin
+                | Filter::Offline(OfflineScope::Only) => return None,
+            }
+        }
+
+        let scope = scopes
+            .iter()
+            .map(|s| format!("SCOPE = '{}'", s))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        conditions.push(format!("({})", scope));
+
+        Some(format!(
+            "SELECT TOP {} System.ItemPathDisplay, System.FileName, System.Size, \
+             System.DateModified, System.ItemType \
+             FROM SystemIndex WHERE {} ORDER BY System.FileName",
+            self.max_results,
+            conditions.join(" AND ")
+        ))
+    }
+}
+
+/// Row as reported by the Windows Search host.
+#[derive(Deserialize)]
+struct RawSearchRow {
+    path: String,
+    name: String,
+    size: i64,
+    modified: i64,
+    folder: bool,
+}
+
+/// Parse the JSON array produced by the Windows Search host.
+pub fn parse_windows_search_rows(json: &str) -> Result<Vec<FileResult>> {
+    let rows: Vec<RawSearchRow> = serde_json::from_str(json)
+        .map_err(|e| FFIError::Search(format!("Failed to parse Windows Search results: {}", e)))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| FileResult {
+            id: 0,
+            name: row.name,
+            path: row.path,
+            size: row.size,
+            modified: row.modified,
+            is_dir: row.folder,
+            duplicates: 0,
+            source: ResultSource::WindowsSearch,
+            rank: None,
+            volume_state: None,
+            original_path: None,
+        })
+        .collect())
+}
+
+/// Query loop run by the long-lived Windows Search host.
+///
+/// Opens the OLE DB connection once, then reads one SQL query per line
+/// from stdin and answers each with one line: the JSON rows, or the
+/// error message prefixed with `!`.
+#[cfg(windows)]
+const SEARCH_HOST_SCRIPT: &str = "$ErrorActionPreference = 'Stop'; \
+    $utf8 = New-Object Text.UTF8Encoding $false; \
+    [Console]::InputEncoding = $utf8; [Console]::OutputEncoding = $utf8; \
+    $conn = New-Object -ComObject ADODB.Connection; \
+    $conn.Open(\"Provider=Search.CollatorDSO;Extended Properties='Application=Windows';\"); \
+    while ($null -ne ($sql = [Console]::In.ReadLine())) { \
+        try { \
+            $rs = $conn.Execute($sql); \
+            $out = @(while (-not $rs.EOF) { \
+                $size = $rs.Fields.Item('System.Size').Value; \
+                $date = $rs.Fields.Item('System.DateModified').Value; \
+                [pscustomobject]@{ path = $rs.Fields.Item('System.ItemPathDisplay').Value; \
+                    name = $rs.Fields.Item('System.FileName').Value; \
+                    size = $(if ($size -is [DBNull]) { 0 } else { [int64]$size }); \
+                    modified = $(if ($date -is [DateTime]) { [DateTimeOffset]::new([DateTime]::SpecifyKind($date, 'Utc')).ToUnixTimeSeconds() } else { 0 }); \
+                    folder = ($rs.Fields.Item('System.ItemType').Value -eq 'Directory') }; \
+                $rs.MoveNext() \
+            }); \
+            $rs.Close(); \
+            [Console]::Out.WriteLine((ConvertTo-Json -Compress -InputObject $out)) \
+        } catch { \
+            [Console]::Out.WriteLine('!' + ($_.Exception.Message -replace '\\r?\\n', ' ')) \
+        } \
+        [Console]::Out.Flush() \
+    }";
+
+/// PowerShell process answering Windows Search queries.
+///
+/// Started on the first fallback search and kept for the life of the
+/// service, so a search doesn't pay for starting PowerShell and opening
+/// the OLE DB connection. It exits when the service closes its stdin.
+#[cfg(windows)]
+struct SearchHost {
+    child: std::process::Child,
+    stdin: std::process::ChildStdin,
+    stdout: std::io::BufReader<std::process::ChildStdout>,
+}
+
+#[cfg(windows)]
+impl SearchHost {
+    fn spawn() -> std::io::Result<Self> {
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", SEARCH_HOST_SCRIPT])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = std::io::BufReader::new(child.stdout.take().expect("stdout is piped"));
+        Ok(Self { child, stdin, stdout })
+    }
+
+    /// Send one query and read its answer line.
+    fn query(&mut self, sql: &str) -> std::io::Result<String> {
+        use std::io::{BufRead, Write};
+
+        // One query per line
+        let sql = sql.replace(['\r', '\n'], " ");
+        writeln!(self.stdin, "{}", sql)?;
+        self.stdin.flush()?;
+
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Windows Search host exited",
+            ));
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for SearchHost {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The running Windows Search host, shared by all searches.
+#[cfg(windows)]
+static SEARCH_HOST: std::sync::Mutex<Option<SearchHost>> = std::sync::Mutex::new(None);
+
+/// Run a Windows Search SQL query through the OLE DB provider.
+///
+/// Queries go to the long-lived [`SearchHost`], one at a time. A host
+/// that fails to answer is dropped and a new one started by the next
+/// search.
+#[cfg(windows)]
+fn run_windows_search(sql: &str) -> Result<Vec<FileResult>> {
+    let mut host = SEARCH_HOST.lock().unwrap_or_else(|e| e.into_inner());
+
+    if host.is_none() {
+        let spawned = SearchHost::spawn()
+            .map_err(|e| FFIError::Search(format!("Failed to start Windows Search host: {}", e)))?;
+        *host = Some(spawned);
+    }
+
+    let answer = match host.as_mut().map(|h| h.query(sql)) {
+        Some(Ok(answer)) => answer,
+        Some(Err(e)) => {
+            *host = None;
+            return Err(FFIError::Search(format!("Failed to query Windows Search: {}", e)));
+        }
+        None => unreachable!("host started above"),
+    };
+
+    match answer.strip_prefix('!') {
+        Some(message) => Err(FFIError::Search(format!("Windows Search query failed: {}", message))),
+        None => parse_windows_search_rows(&answer),
+    }
+}
+
+/// Stub for non-Windows platforms (no Windows Search).
+#[cfg(not(windows))]
+fn run_windows_search(_sql: &str) -> Result<Vec<FileResult>> {
+    Ok(Vec::new())
+}
+
+/// Escape single quotes for a Windows Search SQL string literal.
+fn quote(value: &str) -> String {
+    value.replace('\'', "''")
+}
+
+/// Convert a wildcard pattern to a Windows Search LIKE pattern.
+///
+/// Mirrors the index query: `*`/`?` map to `%`/`_`, and patterns without
+/// wildcards match as substrings. Literal `%`, `_` and `[` are bracketed.
+fn like_pattern(pattern: &str) -> String {
+    let has_wildcards = pattern.contains('*') || pattern.contains('?');
+    let mut result = String::with_capacity(pattern.len() + 4);
+
+    if !has_wildcards {
+        result.push('%');
+    }
+    for c in pattern.chars() {
+        match c {
+            '*' => result.push('%'),
+            '?' => result.push('_'),
+            '%' | '_' | '[' => {
+                result.push('[');
+                result.push(c);
+                result.push(']');
+            }
+            '\'' => result.push_str("''"),
+            _ => result.push(c),
+        }
+    }
+    if !has_wildcards {
+        result.push('%');
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::config::VolumeConfig;
+
+    fn fallback(volumes: &[char]) -> WindowsSearchFallback {
+        WindowsSearchFallback {
+            volumes: volumes.to_vec(),
+            max_results: 50,
+        }
+    }
+
+    #[test]
+    fn test_from_config_uses_declined_volumes() {
+        let mut config = Config::default();
+        assert!(WindowsSearchFallback::from_config(&config).is_none());
+
+        config.windows_search.enabled = true;
+        let volume = |enabled| VolumeConfig {
+            enabled,
+            ..VolumeConfig::default()
+        };
+        config.volumes.insert("C".to_string(), volume(true));
+        config.volumes.insert("D".to_string(), volume(false));
+        assert_eq!(
+            WindowsSearchFallback::from_config(&config).unwrap().volumes(),
+            &['D']
+        );
+
+        // Explicit list, minus volumes FFI already indexes
+        config.windows_search.volumes = vec!["c:".to_string(), "e".to_string()];
+        assert_eq!(
+            WindowsSearchFallback::from_config(&config).unwrap().volumes(),
+            &['E']
+        );
+    }
+
+    #[test]
+    fn test_build_sql() {
+        let parsed = parse_query("o'brien ext:pdf type:file").unwrap();
+        let sql = fallback(&['D', 'E']).build_sql(&parsed).unwrap();
+
+        assert!(sql.starts_with("SELECT TOP 50 System.ItemPathDisplay"));
+        assert!(sql.contains("System.FileName LIKE '%o''brien%'"));
+        assert!(sql.contains("System.FileExtension = '.pdf'"));
+        assert!(sql.contains("System.ItemType <> 'Directory'"));
+        assert!(sql.contains("(SCOPE = 'file:D:/' OR SCOPE = 'file:E:/')"));
+
+        let sql = fallback(&['D']).build_sql(&parse_query(r#"report content:"it's done""#).unwrap()).unwrap();
+        assert!(sql.contains(r#"CONTAINS(System.Search.Contents, '"it''s done"')"#));
+    }
+
+    #[test]
+    fn test_build_sql_path_scope() {
+        let ws = fallback(&['D']);
+
+        let parsed = parse_query(r"report path:D:\Projects").unwrap();
+        let sql = ws.build_sql(&parsed).unwrap();
+        assert!(sql.contains("(SCOPE = 'file:D:/Projects')"));
+
+        let sql = fallback(&['D', 'E']).build_sql(&parse_query("report drive:e").unwrap()).unwrap();
+        assert!(sql.contains("(SCOPE = 'file:E:/')"));
+        assert!(ws.build_sql(&parse_query("report drive:C").unwrap()).is_none());
+
+        // Scoped to an indexed volume: nothing to proxy
+        let parsed = parse_query(r"report path:C:\Projects").unwrap();
+        assert!(ws.build_sql(&parsed).is_none());
+        assert!(ws.build_sql(&parse_query("").unwrap()).is_none());
+        assert!(ws.build_sql(&parse_query("report OR invoice").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_like_pattern() {
+        assert_eq!(like_pattern("report"), "%report%");
+        assert_eq!(like_pattern("*.t?t"), "%.t_t");
+        assert_eq!(like_pattern("100%_[x]"), "%100[%][_][[]x]%");
+    }
+
+    #[test]
+    fn test_parse_windows_search_rows() {
+        let json = r#"[{"path":"D:\\Docs\\a.txt","name":"a.txt","size":12,"modified":1700000000,"folder":false}]"#;
+        let rows = parse_windows_search_rows(json).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].path, r"D:\Docs\a.txt");
+        assert_eq!(rows[0].source, ResultSource::WindowsSearch);
+        assert!(parse_windows_search_rows("[]").unwrap().is_empty());
+        assert!(parse_windows_search_rows("oops").is_err());
+    }
+}
@@ -3,12 +3,28 @@
 //! Provides TOML-based configuration for the FFI service including:
 //! - General settings (data directory, poll intervals, retention)
 //! - Per-volume configuration (enabled, reconciliation intervals)
-//! - Exclude patterns (paths and extensions)
+//! - Volume classes (system, data, archive) with shared defaults
+//! - Exclude settings (paths, extensions, directory names and glob
+//!   patterns), globally and per volume
+//! - Search UI preferences (sort order per scope)
+//! - Optional VSS shadow copy indexing
+//! - Optional Windows Search fallback for non-indexed volumes
+//! - Optional network share indexing with per-share throttling
+//! - SQLite memory and durability tuning
+//! - Log level, format and retention
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
 
+use super::exclude::ExcludeMatcher;
+use crate::db::RetentionPolicy;
+use crate::search::{FileAttribute, SortSpec};
+use crate::ui::actions::ClipboardFormat;
+use crate::ui::hotkey::DEFAULT_HOTKEY;
+use crate::ui::state::PopupMode;
 use crate::{FFIError, Result};
 
 /// Default USN polling interval in seconds (30 seconds per CONTEXT.md).
@@ -16,21 +32,137 @@ fn default_poll_interval() -> u64 {
     30
 }
 
+/// Default shortest USN polling interval for busy volumes, in seconds.
+fn default_poll_min() -> u64 {
+    5
+}
+
+/// Default longest USN polling interval for idle volumes, in seconds.
+fn default_poll_max() -> u64 {
+    120
+}
+
 /// Default offline retention period in days.
 fn default_offline_retention() -> u32 {
     7
 }
 
+/// Default number of background job workers.
+fn default_job_workers() -> usize {
+    2
+}
+
+/// Default global hotkey for the search popup.
+fn default_hotkey() -> String {
+    DEFAULT_HOTKEY.to_string()
+}
+
 /// Default volume enabled state.
 fn default_true() -> bool {
     true
 }
 
+/// Default number of shadow copies indexed per volume.
+fn default_max_shadow_copies() -> usize {
+    2
+}
+
+/// Default number of Windows Search fallback results per query.
+fn default_windows_search_results() -> usize {
+    50
+}
+
+/// Default extensions whose files' text is indexed.
+fn default_content_extensions() -> Vec<String> {
+    [
+        "txt", "md", "log", "csv", "ini", "cfg", "toml", "yaml", "yml", "json", "xml", "html", "css", "rs", "py",
+        "js", "ts", "c", "h", "cpp", "cs", "java", "go", "sql", "ps1", "bat",
+    ]
+    .iter()
+    .map(|ext| ext.to_string())
+    .collect()
+}
+
+/// Default largest file whose text is indexed, in KB.
+fn default_content_max_file_size_kb() -> u64 {
+    256
+}
+
+/// Default time between content indexing passes, in minutes.
+fn default_content_interval() -> u64 {
+    60
+}
+
 /// Default FAT reconciliation interval in minutes.
 fn default_reconcile_interval() -> u64 {
     30
 }
 
+/// Default network share reconciliation interval in minutes.
+fn default_share_reconcile_interval() -> u64 {
+    120
+}
+
+/// Default number of entries read per second from a network share.
+fn default_share_entries_per_sec() -> u32 {
+    500
+}
+
+/// Default CPU usage, in percent, above which USN polling backs off.
+fn default_throttle_cpu_percent() -> f32 {
+    80.0
+}
+
+/// Default SQLite memory-mapped I/O size in MB.
+fn default_mmap_size_mb() -> u64 {
+    256
+}
+
+/// Default SQLite page cache size in MB.
+fn default_cache_size_mb() -> u64 {
+    64
+}
+
+/// Default SQLite busy timeout in milliseconds.
+fn default_busy_timeout_ms() -> u64 {
+    5000
+}
+
+/// Default number of read-only connections the IPC server searches with.
+fn default_reader_connections() -> usize {
+    4
+}
+
+/// Default time between database maintenance runs, in hours.
+fn default_maintenance_interval_hours() -> u64 {
+    24
+}
+
+/// Default time an IPC client has to send its request or read a response, in milliseconds.
+fn default_ipc_io_timeout_ms() -> u64 {
+    5000
+}
+
+/// Default time an IPC search may run, in milliseconds.
+fn default_ipc_query_timeout_ms() -> u64 {
+    10_000
+}
+
+/// Default accounts allowed to connect to the IPC pipe.
+fn default_ipc_allowed_sids() -> Vec<String> {
+    crate::ipc::security::DEFAULT_ALLOWED_SIDS.iter().map(|sid| sid.to_string()).collect()
+}
+
+/// Default log filter.
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+/// Default number of days rotated log files are kept.
+fn default_log_retention_days() -> u32 {
+    14
+}
+
 /// Main configuration structure for the FFI service.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -38,13 +170,56 @@ pub struct Config {
     #[serde(default)]
     pub general: GeneralConfig,
 
-    /// Per-volume configuration, keyed by drive letter (e.g., "C", "D").
+    /// Per-volume configuration, keyed by drive letter (e.g., "C", "D") or,
+    /// for volumes mounted without a letter, by their folder mount point or
+    /// `\\?\Volume{GUID}\` path (quoted TOML keys).
     #[serde(default)]
     pub volumes: HashMap<String, VolumeConfig>,
 
+    /// Overrides of the volume class defaults, keyed by class
+    /// (e.g., `[classes.archive]`).
+    #[serde(default)]
+    pub classes: HashMap<VolumeClass, ClassConfig>,
+
     /// Path and extension exclusion patterns.
     #[serde(default)]
     pub exclude: ExcludeConfig,
+
+    /// Search UI preferences.
+    #[serde(default)]
+    pub ui: UiConfig,
+
+    /// Which entries searches return by default.
+    #[serde(default)]
+    pub search: SearchConfig,
+
+    /// VSS shadow copy (previous versions) indexing.
+    #[serde(default)]
+    pub shadow_copies: ShadowCopyConfig,
+
+    /// Windows Search fallback for volumes FFI does not index.
+    #[serde(default)]
+    pub windows_search: WindowsSearchConfig,
+
+    /// Network share indexing.
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// Text indexing of small files for `content:` searches.
+    #[serde(default)]
+    pub content: ContentConfig,
+
+    /// SQLite tuning.
+    #[serde(default)]
+    pub database: DatabaseConfig,
+
+    /// Time limits for IPC clients.
+    #[serde(default)]
+    pub ipc: IpcConfig,
+
+    /// Service log settings.
+    #[serde(default)]
+    pub logging: LoggingConfig,
 }
 
 impl Default for Config {
@@ -52,7 +227,17 @@ impl Default for Config {
         Self {
             general: GeneralConfig::default(),
             volumes: HashMap::new(),
+            classes: HashMap::new(),
             exclude: ExcludeConfig::default(),
+            ui: UiConfig::default(),
+            search: SearchConfig::default(),
+            shadow_copies: ShadowCopyConfig::default(),
+            windows_search: WindowsSearchConfig::default(),
+            network: NetworkConfig::default(),
+            content: ContentConfig::default(),
+            database: DatabaseConfig::default(),
+            ipc: IpcConfig::default(),
+            logging: LoggingConfig::default(),
         }
     }
 }
@@ -62,14 +247,17 @@ impl Config {
     ///
     /// Returns default configuration if the file doesn't exist or can't be parsed.
     pub fn load() -> Result<Self> {
-        let path = Self::config_path();
+        Self::load_from(&Self::config_path())
+    }
 
+    /// Load configuration from a file, or the defaults if it doesn't exist.
+    pub fn load_from(path: &Path) -> Result<Self> {
         if !path.exists() {
             tracing::info!("Config file not found at {:?}, using defaults", path);
             return Ok(Self::default());
         }
 
-        let contents = std::fs::read_to_string(&path)
+        let contents = std::fs::read_to_string(path)
             .map_err(|e| FFIError::Config(format!("Failed to read config file: {}", e)))?;
 
         toml::from_str(&contents)
@@ -134,22 +322,257 @@ impl Config {
     }
 
     /// Check if a volume is enabled for indexing.
-    pub fn is_volume_enabled(&self, drive_letter: char) -> bool {
-        let key = drive_letter.to_string();
-        self.volumes
-            .get(&key)
+    pub fn is_volume_enabled(&self, volume: impl VolumeName) -> bool {
+        self.volume_config(&volume.volume_key())
             .map(|v| v.enabled)
             .unwrap_or(false) // Volumes must be explicitly enabled per CONTEXT.md
     }
 
+    /// Turn indexing of a volume on or off under `[volumes]` in a
+    /// configuration file, adding an entry for it if it has none.
+    ///
+    /// Only the volume's `enabled` key is written; the rest of the file
+    /// keeps its comments and layout. The file is left as it was if the
+    /// edited configuration would not load.
+    ///
+    /// # Arguments
+    /// * `path` - Path to `config.toml`, created if missing
+    /// * `volume` - Name of the volume (`C:`, `C:\Mount\Data`)
+    /// * `enabled` - Whether the volume is indexed
+    pub fn set_volume_enabled(path: &Path, volume: &str, enabled: bool) -> Result<()> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(FFIError::Config(format!("Failed to read config file: {}", e))),
+        };
+        let mut document: toml_edit::Document = contents
+            .parse()
+            .map_err(|e| FFIError::Config(format!("Failed to parse config file: {}", e)))?;
+
+        let volumes = document
+            .entry("volumes")
+            .or_insert_with(|| {
+                let mut volumes = toml_edit::Table::new();
+                volumes.set_implicit(true);
+                toml_edit::Item::Table(volumes)
+            })
+            .as_table_like_mut()
+            .ok_or_else(|| FFIError::Config("volumes is not a table".to_string()))?;
+
+        let key = volume.volume_key();
+        let existing = volumes
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .find(|name| name.as_str().volume_key() == key);
+        match existing {
+            Some(name) => {
+                volumes
+                    .get_mut(&name)
+                    .and_then(toml_edit::Item::as_table_like_mut)
+                    .ok_or_else(|| FFIError::Config(format!("volumes.{} is not a table", name)))?
+                    .insert("enabled", toml_edit::value(enabled));
+            }
+            None => {
+                // Written the way users write them: `[volumes.D]`
+                let name = match volume_drive_letter(volume) {
+                    Some(letter) => letter.to_string(),
+                    None => volume.trim().trim_end_matches('\\').to_string(),
+                };
+                let mut entry = toml_edit::Table::new();
+                entry.insert("enabled", toml_edit::value(enabled));
+                volumes.insert(&name, toml_edit::Item::Table(entry));
+            }
+        }
+
+        let contents = document.to_string();
+        toml::from_str::<Config>(&contents)
+            .map_err(|e| FFIError::Config(format!("Edited config would not load: {}", e)))?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| FFIError::Config(format!("Failed to create config directory: {}", e)))?;
+        }
+        std::fs::write(path, contents)
+            .map_err(|e| FFIError::Config(format!("Failed to write config file: {}", e)))?;
+
+        tracing::info!("{} volume {} in {:?}", if enabled { "Enabled" } else { "Disabled" }, volume, path);
+        Ok(())
+    }
+
+    /// Check if a volume is listed under `[volumes]`, enabled or not.
+    pub fn is_volume_configured(&self, volume: impl VolumeName) -> bool {
+        self.volume_config(&volume.volume_key()).is_some()
+    }
+
+    /// Check if a volume is explicitly turned off with `enabled = false`.
+    ///
+    /// Volumes not listed under `[volumes]` are still indexed when detected,
+    /// so only these are left out of full indexes and journal monitoring.
+    pub fn is_volume_disabled(&self, volume: impl VolumeName) -> bool {
+        self.volume_config(&volume.volume_key()).is_some_and(|v| !v.enabled)
+    }
+
     /// Get reconciliation interval for a volume (FAT volumes only).
-    pub fn reconcile_interval_mins(&self, drive_letter: char) -> u64 {
-        let key = drive_letter.to_string();
-        self.volumes
-            .get(&key)
-            .map(|v| v.reconcile_interval_mins)
+    ///
+    /// The volume's own setting wins, then its class, then the default.
+    pub fn reconcile_interval_mins(&self, volume: impl VolumeName) -> u64 {
+        let key = volume.volume_key();
+        self.volume_config(&key)
+            .and_then(|v| v.reconcile_interval_mins)
+            .or_else(|| self.class_config(&key)?.reconcile_interval_mins)
             .unwrap_or_else(default_reconcile_interval)
     }
+
+    /// Get the USN polling interval for a volume, from its class or `[general]`.
+    pub fn usn_poll_interval_secs(&self, volume: impl VolumeName) -> u64 {
+        self.class_config(&volume.volume_key())
+            .and_then(|c| c.usn_poll_interval_secs)
+            .unwrap_or(self.general.usn_poll_interval_secs)
+    }
+
+    /// Get the CPU usage above which USN polling of a volume backs off.
+    pub fn throttle_cpu_percent(&self, volume: impl VolumeName) -> f32 {
+        self.class_config(&volume.volume_key())
+            .and_then(|c| c.throttle_cpu_percent)
+            .unwrap_or_else(default_throttle_cpu_percent)
+    }
+
+    /// Get the offline retention for a volume, from its class or `[general]`.
+    pub fn offline_retention_days(&self, volume: impl VolumeName) -> u32 {
+        self.class_config(&volume.volume_key())
+            .and_then(|c| c.offline_retention_days)
+            .unwrap_or(self.general.offline_retention_days)
+    }
+
+    /// Excludes for a volume: `[exclude]` extended by its
+    /// `[volumes.<name>.exclude]` section.
+    pub fn exclude_for(&self, volume: impl VolumeName) -> ExcludeConfig {
+        match self.volume_config(&volume.volume_key()) {
+            Some(v) if !v.exclude.is_empty() => self.exclude.merged(&v.exclude),
+            _ => self.exclude.clone(),
+        }
+    }
+
+    /// Deepest folder level indexed on a volume, if limited.
+    pub fn max_depth(&self, volume: impl VolumeName) -> Option<usize> {
+        self.volume_config(&volume.volume_key()).and_then(|v| v.max_depth)
+    }
+
+    /// Most entries indexed on a volume, if limited.
+    pub fn max_files(&self, volume: impl VolumeName) -> Option<u64> {
+        self.volume_config(&volume.volume_key()).and_then(|v| v.max_files)
+    }
+
+    /// Excludes and limits of a volume, compiled for a scan or monitor,
+    /// with the [`DEFAULT_EXCLUDES`] it does not opt out of.
+    pub fn exclude_matcher(&self, volume: impl VolumeName) -> ExcludeMatcher {
+        let key = volume.volume_key();
+        let mut exclude = self.exclude_for(key.as_str());
+        let defaults = exclude.default_paths(&key);
+        exclude.paths.extend(defaults);
+        exclude
+            .matcher()
+            .with_limits(self.max_depth(key.as_str()), self.max_files(key.as_str()))
+    }
+
+    /// Offline retention for every configured volume and network share,
+    /// for the cleanup.
+    pub fn offline_retention(&self) -> RetentionPolicy {
+        let mut retention = RetentionPolicy::from(self.general.offline_retention_days);
+        for key in self.volumes.keys() {
+            let name = key.as_str().volume_key();
+            let days = self.offline_retention_days(name.as_str());
+            retention.per_volume.insert(name, days);
+        }
+        if let Some(days) = self.network.offline_retention_days {
+            for share in &self.network.shares {
+                if let Some(name) = share.volume_name() {
+                    retention.per_volume.insert(name.to_uppercase(), days);
+                }
+            }
+        }
+        retention
+    }
+
+    /// Settings of a volume under `[volumes]`, by normalized key.
+    fn volume_config(&self, key: &str) -> Option<&VolumeConfig> {
+        self.volumes
+            .iter()
+            .find(|(name, _)| name.as_str().volume_key() == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Settings of a volume's class: `[classes.*]` overrides on top of the
+    /// class's built-in defaults. `None` if the volume has no class.
+    fn class_config(&self, key: &str) -> Option<ClassConfig> {
+        let class = self.volume_config(key)?.class?;
+        let mut config = class.defaults();
+        if let Some(overrides) = self.classes.get(&class) {
+            config.merge(overrides);
+        }
+        Some(config)
+    }
+}
+
+/// Configuration shared by the service's threads and replaced when
+/// `config.toml` changes (see [`super::config_watcher`]).
+///
+/// Threads take the current configuration when they start a unit of work,
+/// so a change applies from their next job, pass or event.
+#[derive(Debug, Clone, Default)]
+pub struct SharedConfig(Arc<RwLock<Arc<Config>>>);
+
+impl SharedConfig {
+    /// Share a configuration.
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    /// The current configuration.
+    pub fn get(&self) -> Arc<Config> {
+        Arc::clone(&self.0.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Replace the configuration for everything that takes it from now on.
+    pub fn set(&self, config: Config) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+    }
+}
+
+/// A volume as named in `[volumes]`: a drive letter, or the mount point or
+/// GUID path of a volume without one.
+pub trait VolumeName {
+    /// Normalized key of the volume, equal to its indexed name uppercased:
+    /// `C:` for drive letters (`C`, `c:` and `C:\` alike), else the path
+    /// without its trailing backslash.
+    fn volume_key(&self) -> String;
+}
+
+impl VolumeName for char {
+    fn volume_key(&self) -> String {
+        format!("{}:", self.to_ascii_uppercase())
+    }
+}
+
+impl VolumeName for &str {
+    fn volume_key(&self) -> String {
+        let name = self.trim().trim_end_matches('\\');
+        match volume_drive_letter(name) {
+            Some(letter) => letter.volume_key(),
+            None => name.to_uppercase(),
+        }
+    }
+}
+
+/// Drive letter of a volume name that is only a letter (`C`, `C:` or `C:\`).
+pub fn volume_drive_letter(name: &str) -> Option<char> {
+    let name = name.trim().trim_end_matches('\\');
+    let mut chars = name.chars();
+    let letter = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+    match chars.as_str() {
+        "" | ":" => Some(letter.to_ascii_uppercase()),
+        _ => None,
+    }
 }
 
 /// General service configuration.
@@ -159,15 +582,59 @@ pub struct GeneralConfig {
     /// Defaults to `%PROGRAMDATA%\FFI` on Windows.
     pub data_dir: Option<PathBuf>,
 
-    /// USN Journal polling interval in seconds.
+    /// USN Journal polling interval in seconds, adapted per volume to its change rate.
     /// Default: 30 seconds (per CONTEXT.md decision).
     #[serde(default = "default_poll_interval")]
     pub usn_poll_interval_secs: u64,
 
+    /// Shortest USN polling interval in seconds. Volumes with more than one
+    /// change per second poll faster than `usn_poll_interval_secs`, down to this.
+    /// Default: 5 seconds.
+    #[serde(default = "default_poll_min")]
+    pub usn_poll_min_secs: u64,
+
+    /// Longest USN polling interval in seconds. Quieter volumes poll slower
+    /// than `usn_poll_interval_secs`, up to this.
+    /// Default: 120 seconds.
+    #[serde(default = "default_poll_max")]
+    pub usn_poll_max_secs: u64,
+
     /// Days to keep offline volume data before auto-deletion.
     /// Default: 7 days (per CONTEXT.md decision).
     #[serde(default = "default_offline_retention")]
     pub offline_retention_days: u32,
+
+    /// Number of workers running background jobs (initial index, rescans,
+    /// offline cleanup). Jobs on different volumes run in parallel up to this.
+    /// Default: 2.
+    #[serde(default = "default_job_workers")]
+    pub job_workers: usize,
+
+    /// Number of threads parsing the MFT during NTFS scans, each with its
+    /// own handle to the volume; 0 picks one per core, up to 4.
+    /// Default: 0.
+    #[serde(default)]
+    pub mft_scan_workers: usize,
+
+    /// Index NTFS alternate data streams (e.g. `Zone.Identifier`) during MFT
+    /// scans, each as an entry named `file:stream`, searchable with `stream:`.
+    /// Streams added later are picked up by the next rescan.
+    /// Default: false.
+    #[serde(default)]
+    pub index_alternate_streams: bool,
+
+    /// Most disk reads per second a full scan (initial index or rescan) may
+    /// issue; 0 for no limit. Scans also run at background I/O priority and
+    /// back off while the disk or CPU is busy, whatever this is set to.
+    /// Default: 0.
+    #[serde(default)]
+    pub max_scan_iops: u32,
+
+    /// Read-only replica mode: open the database read-only, disable all
+    /// indexing, and only serve IPC searches.
+    /// Can also be enabled with the `--read-only` service start argument.
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 impl Default for GeneralConfig {
@@ -175,7 +642,14 @@ impl Default for GeneralConfig {
         Self {
             data_dir: None,
             usn_poll_interval_secs: default_poll_interval(),
+            usn_poll_min_secs: default_poll_min(),
+            usn_poll_max_secs: default_poll_max(),
             offline_retention_days: default_offline_retention(),
+            job_workers: default_job_workers(),
+            mft_scan_workers: 0,
+            index_alternate_streams: false,
+            max_scan_iops: 0,
+            read_only: false,
         }
     }
 }
@@ -189,22 +663,123 @@ pub struct VolumeConfig {
     pub enabled: bool,
 
     /// FAT reconciliation interval in minutes.
-    /// Default: 30 minutes (per CONTEXT.md decision).
-    #[serde(default = "default_reconcile_interval")]
-    pub reconcile_interval_mins: u64,
+    /// Default: from the volume class, else 30 minutes (per CONTEXT.md decision).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconcile_interval_mins: Option<u64>,
+
+    /// Class providing defaults for polling, reconciliation, throttling
+    /// and retention: "system", "data" or "archive".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<VolumeClass>,
+
+    /// Excludes for this volume only, in addition to `[exclude]`
+    /// (`[volumes.D.exclude]`).
+    #[serde(default, skip_serializing_if = "ExcludeConfig::is_empty")]
+    pub exclude: ExcludeConfig,
+
+    /// Deepest folder level indexed; 1 indexes only what is in the root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+
+    /// Most entries indexed. NTFS scans keep the shallowest entries;
+    /// directory walks stop once they have this many.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<u64>,
 }
 
 impl Default for VolumeConfig {
     fn default() -> Self {
         Self {
             enabled: default_true(),
-            reconcile_interval_mins: default_reconcile_interval(),
+            reconcile_interval_mins: None,
+            class: None,
+            exclude: ExcludeConfig::default(),
+            max_depth: None,
+            max_files: None,
         }
     }
 }
 
-/// Path and extension exclusion configuration.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Quality-of-service class of a volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeClass {
+    /// OS drive: changes constantly, results should stay fresh
+    System,
+    /// Everyday data drive
+    Data,
+    /// Rarely changing or rarely attached drive
+    Archive,
+}
+
+impl VolumeClass {
+    /// Built-in defaults of the class, before `[classes.*]` overrides.
+    ///
+    /// Unset values fall back to `[general]` and the global defaults.
+    pub fn defaults(self) -> ClassConfig {
+        match self {
+            VolumeClass::System => ClassConfig {
+                usn_poll_interval_secs: Some(10),
+                reconcile_interval_mins: Some(15),
+                throttle_cpu_percent: Some(90.0),
+                offline_retention_days: None,
+            },
+            VolumeClass::Data => ClassConfig::default(),
+            VolumeClass::Archive => ClassConfig {
+                usn_poll_interval_secs: Some(120),
+                reconcile_interval_mins: Some(240),
+                throttle_cpu_percent: Some(50.0),
+                offline_retention_days: Some(90),
+            },
+        }
+    }
+}
+
+/// Settings shared by all volumes of a class.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClassConfig {
+    /// USN polling interval in seconds (NTFS volumes).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usn_poll_interval_secs: Option<u64>,
+
+    /// Reconciliation interval in minutes (FAT volumes).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconcile_interval_mins: Option<u64>,
+
+    /// CPU usage in percent above which USN polling backs off. Lower
+    /// values throttle more aggressively.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttle_cpu_percent: Option<f32>,
+
+    /// Days to keep the index of an offline volume.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline_retention_days: Option<u32>,
+}
+
+impl ClassConfig {
+    /// Replace settings with those set in `overrides`.
+    fn merge(&mut self, overrides: &ClassConfig) {
+        self.usn_poll_interval_secs = overrides.usn_poll_interval_secs.or(self.usn_poll_interval_secs);
+        self.reconcile_interval_mins = overrides.reconcile_interval_mins.or(self.reconcile_interval_mins);
+        self.throttle_cpu_percent = overrides.throttle_cpu_percent.or(self.throttle_cpu_percent);
+        self.offline_retention_days = overrides.offline_retention_days.or(self.offline_retention_days);
+    }
+}
+
+/// Paths below every volume root excluded unless listed in
+/// `[exclude] index_defaults`: system files that take space in the index
+/// without anyone searching for them.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "pagefile.sys",
+    "hiberfil.sys",
+    "System Volume Information",
+    r"Windows\WinSxS\Backup",
+];
+
+/// Path, extension, directory name and pattern exclusion configuration.
+///
+/// Scanners match entries with the [`ExcludeMatcher`] compiled from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ExcludeConfig {
     /// Path prefixes to exclude from indexing.
     /// Example: `["C:\\Windows\\Temp", "C:\\$Recycle.Bin"]`
@@ -215,16 +790,34 @@ pub struct ExcludeConfig {
     /// Example: `["tmp", "log", "bak"]`
     #[serde(default)]
     pub extensions: Vec<String>,
+
+    /// Directory names excluded wherever they appear, with everything
+    /// below them.
+    /// Example: `["node_modules", ".git"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<String>,
+
+    /// Glob patterns matched case-insensitively against full paths, with
+    /// `/` and `\` alike; `*` also matches across folders.
+    /// Example: `["**/node_modules/**", "*.iso", "C:/Users/*/AppData/**"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+
+    /// [`DEFAULT_EXCLUDES`] to index anyway, on every volume or, with the
+    /// volume name, on one.
+    /// Example: `["pagefile.sys", "D:\\System Volume Information"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub index_defaults: Vec<String>,
 }
 
 impl ExcludeConfig {
-    /// Check if a path should be excluded based on prefix matching.
+    /// Check if a path is under an excluded prefix or directory, or
+    /// matches a pattern.
+    ///
+    /// Compiles the settings on each call; use [`ExcludeConfig::matcher`]
+    /// to check many paths.
     pub fn should_exclude_path(&self, path: &str) -> bool {
-        let path_lower = path.to_lowercase();
-        self.paths.iter().any(|prefix| {
-            let prefix_lower = prefix.to_lowercase();
-            path_lower.starts_with(&prefix_lower)
-        })
+        self.matcher().should_exclude_path(path)
     }
 
     /// Check if a file extension should be excluded.
@@ -232,6 +825,634 @@ impl ExcludeConfig {
         let ext_lower = ext.to_lowercase();
         self.extensions.iter().any(|e| e.to_lowercase() == ext_lower)
     }
+
+    /// Check if nothing is set, built-in exclusions aside.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+            && self.extensions.is_empty()
+            && self.directories.is_empty()
+            && self.patterns.is_empty()
+            && self.index_defaults.is_empty()
+    }
+
+    /// Full paths of the [`DEFAULT_EXCLUDES`] on a volume that
+    /// `index_defaults` does not opt out of.
+    ///
+    /// # Arguments
+    /// * `volume` - Name of the volume (`C:`, `C:\Mount\Data`)
+    pub fn default_paths(&self, volume: &str) -> Vec<String> {
+        let volume = volume.trim_end_matches(['\\', '/']);
+        let normalize = |path: &str| path.trim().trim_matches(['\\', '/']).replace('/', "\\").to_lowercase();
+        let kept: Vec<String> = self.index_defaults.iter().map(|path| normalize(path)).collect();
+        DEFAULT_EXCLUDES
+            .iter()
+            .map(|default| format!("{}\\{}", volume, default))
+            .filter(|path| {
+                let default = normalize(&path[volume.len()..]);
+                !kept.contains(&default) && !kept.contains(&normalize(path))
+            })
+            .collect()
+    }
+
+    /// Check if a file name has an excluded extension.
+    pub fn should_exclude_name(&self, name: &str) -> bool {
+        name.rsplit_once('.')
+            .is_some_and(|(_, ext)| self.should_exclude_extension(ext))
+    }
+
+    /// Check if an entry should be kept out of the index.
+    ///
+    /// Compiles the settings on each call, like
+    /// [`ExcludeConfig::should_exclude_path`].
+    ///
+    /// # Arguments
+    /// * `path` - Full path of the entry (e.g., `C:\Windows\Temp\a.tmp`)
+    /// * `is_dir` - Whether the entry is a directory
+    pub fn should_exclude(&self, path: &str, is_dir: bool) -> bool {
+        self.matcher().should_exclude(path, is_dir)
+    }
+
+    /// Compile the settings for matching many entries.
+    pub fn matcher(&self) -> ExcludeMatcher {
+        ExcludeMatcher::new(self)
+    }
+
+    /// These settings extended with another set, such as a volume's own.
+    pub fn merged(&self, other: &ExcludeConfig) -> ExcludeConfig {
+        let concat = |a: &[String], b: &[String]| a.iter().chain(b).cloned().collect();
+        ExcludeConfig {
+            paths: concat(&self.paths, &other.paths),
+            extensions: concat(&self.extensions, &other.extensions),
+            directories: concat(&self.directories, &other.directories),
+            patterns: concat(&self.patterns, &other.patterns),
+            index_defaults: concat(&self.index_defaults, &other.index_defaults),
+        }
+    }
+}
+
+/// Default search result configuration.
+///
+/// Hidden entries are still indexed, and a query with an `attrib:` filter
+/// (e.g. `attrib:hidden`) finds them.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SearchConfig {
+    /// Leave files and folders with the hidden attribute out of results.
+    #[serde(default)]
+    pub hide_hidden: bool,
+
+    /// Leave files and folders with the system attribute out of results.
+    #[serde(default)]
+    pub hide_system: bool,
+}
+
+impl SearchConfig {
+    /// Attributes whose entries are left out of results.
+    pub fn hidden_attributes(&self) -> Vec<FileAttribute> {
+        let mut attributes = Vec::new();
+        if self.hide_hidden {
+            attributes.push(FileAttribute::Hidden);
+        }
+        if self.hide_system {
+            attributes.push(FileAttribute::System);
+        }
+        attributes
+    }
+}
+
+/// VSS shadow copy indexing configuration (opt-in).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowCopyConfig {
+    /// Index shadow copies as read-only virtual volumes (e.g. `C:@2024-05-01`).
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Drive letters whose shadow copies are indexed (e.g., `["C"]`).
+    /// Empty means all volumes.
+    #[serde(default)]
+    pub volumes: Vec<String>,
+
+    /// Number of most recent shadow copies indexed per volume.
+    /// Default: 2
+    #[serde(default = "default_max_shadow_copies")]
+    pub max_per_volume: usize,
+}
+
+impl Default for ShadowCopyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volumes: Vec::new(),
+            max_per_volume: default_max_shadow_copies(),
+        }
+    }
+}
+
+/// Windows Search fallback configuration (opt-in).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowsSearchConfig {
+    /// Proxy searches on non-indexed volumes to Windows Search and merge
+    /// the results, labeled as such.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Drive letters to query through Windows Search (e.g., `["D"]`).
+    /// Empty means every volume disabled under `[volumes]`.
+    #[serde(default)]
+    pub volumes: Vec<String>,
+
+    /// Maximum Windows Search results merged into each search.
+    /// Default: 50
+    #[serde(default = "default_windows_search_results")]
+    pub max_results: usize,
+}
+
+impl Default for WindowsSearchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volumes: Vec::new(),
+            max_results: default_windows_search_results(),
+        }
+    }
+}
+
+/// Content indexing configuration (opt-in).
+///
+/// A background pass reads files with the listed extensions up to the size
+/// cap on online volumes and indexes their text, so `content:` filters
+/// match inside them. It costs disk reads and index space in proportion to
+/// the files covered; turning it off again deletes the indexed text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentConfig {
+    /// Index the text of small files.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Extensions (without the dot) of the files read.
+    /// Default: common text, configuration and source code extensions
+    #[serde(default = "default_content_extensions")]
+    pub extensions: Vec<String>,
+
+    /// Largest file read, in KB.
+    /// Default: 256 KB
+    #[serde(default = "default_content_max_file_size_kb")]
+    pub max_file_size_kb: u64,
+
+    /// Time between passes picking up new and changed files, in minutes.
+    /// Default: 60
+    #[serde(default = "default_content_interval")]
+    pub interval_mins: u64,
+}
+
+impl Default for ContentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            extensions: default_content_extensions(),
+            max_file_size_kb: default_content_max_file_size_kb(),
+            interval_mins: default_content_interval(),
+        }
+    }
+}
+
+impl ContentConfig {
+    /// Largest file read, in bytes.
+    pub fn max_file_size(&self) -> i64 {
+        self.max_file_size_kb.saturating_mul(1024).min(i64::MAX as u64) as i64
+    }
+
+    /// Extensions read, lowercase and without the dot.
+    pub fn extensions(&self) -> Vec<String> {
+        self.extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+            .collect()
+    }
+
+    /// Time between passes (at least a minute).
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_mins.max(1).saturating_mul(60))
+    }
+}
+
+/// Network share indexing configuration (opt-in).
+///
+/// Shares are walked like FAT volumes on their own schedule, each read at a
+/// capped rate so indexing doesn't saturate the LAN. A share that can't be
+/// reached is marked offline and kept for `offline_retention_days`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Index the shares listed under `[[network.shares]]`.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Reconciliation interval in minutes for shares without their own.
+    /// Default: 120
+    #[serde(default = "default_share_reconcile_interval")]
+    pub reconcile_interval_mins: u64,
+
+    /// Entries read per second from a share without its own limit, 0 for no
+    /// limit. Default: 500
+    #[serde(default = "default_share_entries_per_sec")]
+    pub max_entries_per_sec: u32,
+
+    /// Days the index of an unreachable share is kept.
+    /// Default: `offline_retention_days` from `[general]`
+    #[serde(default)]
+    pub offline_retention_days: Option<u32>,
+
+    /// Shares to index.
+    #[serde(default)]
+    pub shares: Vec<ShareConfig>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reconcile_interval_mins: default_share_reconcile_interval(),
+            max_entries_per_sec: default_share_entries_per_sec(),
+            offline_retention_days: None,
+            shares: Vec::new(),
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Reconciliation interval of a share, in minutes.
+    pub fn reconcile_interval_mins(&self, share: &ShareConfig) -> u64 {
+        share.reconcile_interval_mins.unwrap_or(self.reconcile_interval_mins)
+    }
+
+    /// Entries read per second from a share, 0 for no limit.
+    pub fn max_entries_per_sec(&self, share: &ShareConfig) -> u32 {
+        share.max_entries_per_sec.unwrap_or(self.max_entries_per_sec)
+    }
+}
+
+/// A network share to index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareConfig {
+    /// UNC path (e.g. `\\nas\media`) or mapped drive (e.g. `Z:`). The
+    /// service account must be able to reach it; drives mapped in a user's
+    /// session are not visible to the service, so prefer UNC paths.
+    pub path: String,
+
+    /// Reconciliation interval in minutes, overriding `[network]`.
+    #[serde(default)]
+    pub reconcile_interval_mins: Option<u64>,
+
+    /// Entries read per second, overriding `[network]`.
+    #[serde(default)]
+    pub max_entries_per_sec: Option<u32>,
+
+    /// Deepest folder level indexed; 1 indexes only the share's root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+
+    /// Most entries indexed; the walk stops once it has this many.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<u64>,
+}
+
+impl ShareConfig {
+    /// Name the share is indexed under: the UNC path without a trailing
+    /// backslash, or `Z:` for a mapped drive.
+    ///
+    /// # Returns
+    /// `None` if `path` is neither a UNC path nor a drive letter.
+    pub fn volume_name(&self) -> Option<String> {
+        let path = self.path.trim().replace('/', "\\");
+        if let Some(letter) = volume_drive_letter(&path) {
+            return Some(format!("{}:", letter));
+        }
+
+        let unc = path.strip_prefix(r"\\")?.trim_end_matches('\\');
+        let mut parts = unc.split('\\');
+        match (parts.next(), parts.next()) {
+            (Some(server), Some(share)) if !server.is_empty() && !share.is_empty() => {
+                Some(format!(r"\\{}", unc))
+            }
+            _ => None,
+        }
+    }
+
+    /// Directory the share is walked from.
+    pub fn root_path(&self) -> Option<String> {
+        self.volume_name().map(|name| format!("{}\\", name))
+    }
+}
+
+/// SQLite `synchronous` level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SynchronousLevel {
+    /// Never sync. Fastest, but power loss can corrupt the index.
+    Off,
+    /// Sync at WAL checkpoints. Power loss can lose the last few changes,
+    /// which the next USN poll or rescan picks up again.
+    #[default]
+    Normal,
+    /// Sync on every commit.
+    Full,
+    /// Like `full`, and also sync the directory after deleting WAL files.
+    Extra,
+}
+
+impl SynchronousLevel {
+    /// Value for `PRAGMA synchronous`.
+    pub fn as_pragma(self) -> &'static str {
+        match self {
+            SynchronousLevel::Off => "OFF",
+            SynchronousLevel::Normal => "NORMAL",
+            SynchronousLevel::Full => "FULL",
+            SynchronousLevel::Extra => "EXTRA",
+        }
+    }
+}
+
+/// SQLite tuning, applied to every database connection.
+///
+/// The defaults suit a typical desktop. On low-RAM machines lower
+/// `mmap_size_mb` and `cache_size_mb`; for indexes of tens of millions of
+/// files raise them so searches stay in memory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    /// Memory-mapped I/O size in MB (0 disables it). Mapped pages are shared
+    /// with the OS file cache, so reads avoid a copy, but a large value makes
+    /// the service's working set look larger.
+    /// Default: 256 MB
+    #[serde(default = "default_mmap_size_mb")]
+    pub mmap_size_mb: u64,
+
+    /// Page cache size in MB, per connection. Private memory that speeds up
+    /// repeated searches and large index updates.
+    /// Default: 64 MB
+    #[serde(default = "default_cache_size_mb")]
+    pub cache_size_mb: u64,
+
+    /// How long a connection waits for a lock held by another connection,
+    /// in milliseconds, before failing with "database is locked". Longer
+    /// values make searches wait out big index commits instead of failing.
+    /// Default: 5000 ms
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64,
+
+    /// Durability of commits: "off", "normal", "full" or "extra".
+    /// Higher levels survive power loss better but slow down indexing.
+    /// Default: "normal"
+    #[serde(default)]
+    pub synchronous: SynchronousLevel,
+
+    /// Hours between maintenance runs (optimize, vacuum, analyze, an
+    /// integrity check that rebuilds a corrupt index and a check for
+    /// entries whose parent directory is gone); 0 disables them.
+    /// Default: 24 hours
+    #[serde(default = "default_maintenance_interval_hours")]
+    pub maintenance_interval_hours: u64,
+
+    /// Largest the index may grow, in MB (0 for no limit). Beyond it the
+    /// index of offline volumes, entries matching the exclude settings and
+    /// then the least recently modified files are deleted until it is back
+    /// under 90% of the limit.
+    /// Default: 0 (no limit)
+    #[serde(default)]
+    pub max_size_mb: u64,
+
+    /// Read-only connections the IPC server runs searches on, so that many
+    /// searches run at once without waiting for index updates. Each has
+    /// its own page cache of `cache_size_mb`.
+    /// Default: 4
+    #[serde(default = "default_reader_connections")]
+    pub reader_connections: usize,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            mmap_size_mb: default_mmap_size_mb(),
+            cache_size_mb: default_cache_size_mb(),
+            busy_timeout_ms: default_busy_timeout_ms(),
+            synchronous: SynchronousLevel::default(),
+            maintenance_interval_hours: default_maintenance_interval_hours(),
+            max_size_mb: 0,
+            reader_connections: default_reader_connections(),
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// Largest accepted `mmap_size_mb` (64 GB).
+    pub const MAX_MMAP_SIZE_MB: u64 = 64 * 1024;
+    /// Largest accepted `cache_size_mb` (16 GB).
+    pub const MAX_CACHE_SIZE_MB: u64 = 16 * 1024;
+    /// Largest accepted `busy_timeout_ms` (10 minutes).
+    pub const MAX_BUSY_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+    /// Largest accepted `reader_connections`.
+    pub const MAX_READER_CONNECTIONS: usize = 32;
+
+    /// Size budget of the index in bytes, or None if unlimited.
+    pub fn max_size_bytes(&self) -> Option<u64> {
+        (self.max_size_mb > 0).then(|| self.max_size_mb.saturating_mul(1024 * 1024))
+    }
+
+    /// Time between maintenance runs, or None if disabled.
+    pub fn maintenance_interval(&self) -> Option<Duration> {
+        (self.maintenance_interval_hours > 0).then(|| Duration::from_secs(self.maintenance_interval_hours.saturating_mul(3600)))
+    }
+
+    /// Check that the values are in range.
+    pub fn validate(&self) -> Result<()> {
+        if self.mmap_size_mb > Self::MAX_MMAP_SIZE_MB {
+            return Err(FFIError::Config(format!(
+                "database.mmap_size_mb must be at most {}, got {}",
+                Self::MAX_MMAP_SIZE_MB,
+                self.mmap_size_mb
+            )));
+        }
+        if !(1..=Self::MAX_CACHE_SIZE_MB).contains(&self.cache_size_mb) {
+            return Err(FFIError::Config(format!(
+                "database.cache_size_mb must be between 1 and {}, got {}",
+                Self::MAX_CACHE_SIZE_MB,
+                self.cache_size_mb
+            )));
+        }
+        if self.busy_timeout_ms > Self::MAX_BUSY_TIMEOUT_MS {
+            return Err(FFIError::Config(format!(
+                "database.busy_timeout_ms must be at most {}, got {}",
+                Self::MAX_BUSY_TIMEOUT_MS,
+                self.busy_timeout_ms
+            )));
+        }
+        if !(1..=Self::MAX_READER_CONNECTIONS).contains(&self.reader_connections) {
+            return Err(FFIError::Config(format!(
+                "database.reader_connections must be between 1 and {}, got {}",
+                Self::MAX_READER_CONNECTIONS,
+                self.reader_connections
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// IPC server settings: who may connect, and time limits so a client that
+/// stops responding or a runaway query doesn't hold a server task and the
+/// database lock.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpcConfig {
+    /// How long a client may take to send its request, in milliseconds.
+    /// Default: 5000 ms
+    #[serde(default = "default_ipc_io_timeout_ms")]
+    pub read_timeout_ms: u64,
+
+    /// How long sending one response message may take, in milliseconds;
+    /// a client that stops reading is dropped after this.
+    /// Default: 5000 ms
+    #[serde(default = "default_ipc_io_timeout_ms")]
+    pub write_timeout_ms: u64,
+
+    /// How long a search may run before it is stopped, in milliseconds.
+    /// Default: 10000 ms
+    #[serde(default = "default_ipc_query_timeout_ms")]
+    pub query_timeout_ms: u64,
+
+    /// Accounts allowed to connect to the pipe, as SIDs ("S-1-5-21-...")
+    /// or SDDL aliases ("BA" Administrators, "IU" interactive users,
+    /// "AU" authenticated users). LocalSystem is always allowed.
+    /// Default: ["BA", "IU"]
+    #[serde(default = "default_ipc_allowed_sids")]
+    pub allowed_sids: Vec<String>,
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self {
+            read_timeout_ms: default_ipc_io_timeout_ms(),
+            write_timeout_ms: default_ipc_io_timeout_ms(),
+            query_timeout_ms: default_ipc_query_timeout_ms(),
+            allowed_sids: default_ipc_allowed_sids(),
+        }
+    }
+}
+
+impl IpcConfig {
+    /// Time limit for reading a client's request.
+    pub fn read_timeout(&self) -> Duration {
+        Duration::from_millis(self.read_timeout_ms)
+    }
+
+    /// Time limit for writing one response message.
+    pub fn write_timeout(&self) -> Duration {
+        Duration::from_millis(self.write_timeout_ms)
+    }
+
+    /// Time limit for running a search.
+    pub fn query_timeout(&self) -> Duration {
+        Duration::from_millis(self.query_timeout_ms)
+    }
+}
+
+/// Service log settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Log filter: a level ("error", "warn", "info", "debug", "trace") or
+    /// per-module directives ("info,ffi::indexer=debug"). Can be changed
+    /// while the service runs with `ffi-cli log-level`.
+    /// Default: "info"
+    #[serde(default = "default_log_level")]
+    pub level: String,
+
+    /// Line format of the log files.
+    /// Default: text
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Days rotated log files are kept before they are deleted; 0 keeps
+    /// them forever.
+    /// Default: 14
+    #[serde(default = "default_log_retention_days")]
+    pub retention_days: u32,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            format: LogFormat::default(),
+            retention_days: default_log_retention_days(),
+        }
+    }
+}
+
+/// Line format of the service log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+/// Scope key used when a search has no path scope.
+pub const DEFAULT_SORT_SCOPE: &str = "default";
+
+/// Search UI preferences.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiConfig {
+    /// Global hotkey showing the popup, e.g. "Alt+Space" or "Ctrl+Shift+F".
+    /// Changes are picked up by the running popup without a restart.
+    #[serde(default = "default_hotkey")]
+    pub hotkey: String,
+
+    /// Last sort choice per scope, keyed by lowercased path scope
+    /// (or saved search name), with `"default"` for unscoped searches.
+    #[serde(default)]
+    pub sort: HashMap<String, Vec<SortSpec>>,
+
+    /// Format used by Ctrl+Shift+C: "text", "powershell", "uri" or "files".
+    /// Holding Alt as well always copies as files.
+    #[serde(default)]
+    pub copy_format: ClipboardFormat,
+
+    /// What the popup shows when reopened: "resume" (last query, selection
+    /// and scroll position) or "fresh" (empty search box).
+    #[serde(default)]
+    pub popup: PopupMode,
+
+    /// Use the high-contrast theme (white on black, yellow focus outlines).
+    /// Toggled with Ctrl+Shift+H.
+    #[serde(default)]
+    pub high_contrast: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            hotkey: default_hotkey(),
+            sort: HashMap::new(),
+            copy_format: ClipboardFormat::default(),
+            popup: PopupMode::default(),
+            high_contrast: false,
+        }
+    }
+}
+
+impl UiConfig {
+    /// Get the sort for a scope, falling back to the default scope.
+    pub fn sort_for_scope(&self, scope: &str) -> Vec<SortSpec> {
+        self.sort
+            .get(&scope.to_lowercase())
+            .or_else(|| self.sort.get(DEFAULT_SORT_SCOPE))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Remember the sort for a scope.
+    pub fn set_sort_for_scope(&mut self, scope: &str, sort: Vec<SortSpec>) {
+        self.sort.insert(scope.to_lowercase(), sort);
+    }
 }
 
 // Legacy ServiceConfig for backward compatibility during transition
@@ -276,9 +1497,33 @@ mod tests {
     fn test_config_default() {
         let config = Config::default();
         assert_eq!(config.general.usn_poll_interval_secs, 30);
+        assert_eq!(config.general.usn_poll_min_secs, 5);
+        assert_eq!(config.general.usn_poll_max_secs, 120);
         assert_eq!(config.general.offline_retention_days, 7);
+        assert_eq!(config.general.job_workers, 2);
+        assert_eq!(config.general.mft_scan_workers, 0);
+        assert!(!config.general.index_alternate_streams);
+        assert_eq!(config.general.max_scan_iops, 0);
         assert!(config.volumes.is_empty());
         assert!(config.exclude.paths.is_empty());
+        assert!(!config.general.read_only);
+        assert!(!config.shadow_copies.enabled);
+        assert_eq!(config.shadow_copies.max_per_volume, 2);
+        assert!(!config.windows_search.enabled);
+        assert_eq!(config.windows_search.max_results, 50);
+        assert!(!config.content.enabled);
+        assert_eq!(config.content.max_file_size(), 256 * 1024);
+        assert!(config.content.extensions().contains(&"rs".to_string()));
+        assert!(config.search.hidden_attributes().is_empty());
+    }
+
+    #[test]
+    fn test_search_config_hidden_attributes() {
+        let config: Config = toml::from_str("[search]\nhide_hidden = true\nhide_system = true\n").unwrap();
+        assert_eq!(
+            config.search.hidden_attributes(),
+            vec![FileAttribute::Hidden, FileAttribute::System]
+        );
     }
 
     #[test]
@@ -289,7 +1534,11 @@ mod tests {
             "C".to_string(),
             VolumeConfig {
                 enabled: true,
-                reconcile_interval_mins: 45,
+                reconcile_interval_mins: Some(45),
+                class: None,
+                exclude: ExcludeConfig::default(),
+                max_depth: None,
+                max_files: None,
             },
         );
         config.exclude.paths.push(r"C:\Windows\Temp".to_string());
@@ -301,7 +1550,7 @@ mod tests {
 
         assert_eq!(parsed.general.usn_poll_interval_secs, 60);
         assert!(parsed.volumes.get("C").unwrap().enabled);
-        assert_eq!(parsed.volumes.get("C").unwrap().reconcile_interval_mins, 45);
+        assert_eq!(parsed.volumes.get("C").unwrap().reconcile_interval_mins, Some(45));
         assert!(parsed.exclude.paths.contains(&r"C:\Windows\Temp".to_string()));
         assert!(parsed.exclude.extensions.contains(&"tmp".to_string()));
     }
@@ -321,7 +1570,11 @@ mod tests {
             "C".to_string(),
             VolumeConfig {
                 enabled: true,
-                reconcile_interval_mins: 30,
+                reconcile_interval_mins: Some(30),
+                class: None,
+                exclude: ExcludeConfig::default(),
+                max_depth: None,
+                max_files: None,
             },
         );
 
@@ -334,6 +1587,7 @@ mod tests {
         let exclude = ExcludeConfig {
             paths: vec![r"C:\Windows\Temp".to_string(), r"C:\$Recycle.Bin".to_string()],
             extensions: vec![],
+            ..Default::default()
         };
 
         assert!(exclude.should_exclude_path(r"C:\Windows\Temp\file.txt"));
@@ -346,12 +1600,301 @@ mod tests {
         let exclude = ExcludeConfig {
             paths: vec![],
             extensions: vec!["tmp".to_string(), "log".to_string()],
+            ..Default::default()
         };
 
         assert!(exclude.should_exclude_extension("tmp"));
         assert!(exclude.should_exclude_extension("TMP")); // case-insensitive
         assert!(exclude.should_exclude_extension("log"));
         assert!(!exclude.should_exclude_extension("txt"));
+        assert!(exclude.should_exclude_name("debug.LOG"));
+        assert!(!exclude.should_exclude_name("log"));
+        assert!(exclude.should_exclude(r"C:\Users\a.tmp", false));
+        assert!(!exclude.should_exclude(r"C:\Users\cache.tmp", true));
+    }
+
+    #[test]
+    fn test_default_excludes() {
+        let mut config = toml::from_str::<Config>("[volumes]\nC = {}\nD = {}\n").unwrap();
+        let c = config.exclude_matcher('C');
+        assert!(c.should_exclude(r"C:\pagefile.sys", false));
+        assert!(c.should_exclude(r"C:\System Volume Information\tracking.log", false));
+        assert!(c.should_exclude(r"c:\windows\winsxs\backup", true));
+        assert!(!c.should_exclude(r"C:\Windows\WinSxS\amd64_a.dll", false));
+        assert!(!c.should_exclude(r"C:\Users\me\pagefile.sys", false));
+        let mount = config.exclude_matcher(r"C:\Mount\Data\");
+        assert!(mount.should_exclude(r"C:\Mount\Data\System Volume Information\tracking.log", false));
+        // The Recycle Bin stays indexed for `in:recyclebin`
+        assert!(!c.should_exclude(r"C:\$Recycle.Bin\S-1-5\$R1.txt", false));
+
+        // Opted out of everywhere, or on one volume
+        config.exclude.index_defaults = vec!["system volume information\\".to_string(), "D:/hiberfil.sys".to_string()];
+        let c = config.exclude_matcher('C');
+        let d = config.exclude_matcher('D');
+        assert!(!c.should_exclude(r"C:\System Volume Information\tracking.log", false));
+        assert!(!d.should_exclude(r"D:\System Volume Information\tracking.log", false));
+        assert!(c.should_exclude(r"C:\hiberfil.sys", false));
+        assert!(!d.should_exclude(r"D:\hiberfil.sys", false));
+        assert_eq!(config.exclude.default_paths("D:").len(), DEFAULT_EXCLUDES.len() - 2);
+    }
+
+    #[test]
+    fn test_sort_per_scope() {
+        use crate::search::SortField;
+
+        let mut config = Config::default();
+        assert!(config.ui.sort_for_scope(DEFAULT_SORT_SCOPE).is_empty());
+
+        config.ui.set_sort_for_scope(DEFAULT_SORT_SCOPE, vec![SortSpec::desc(SortField::Modified)]);
+        config.ui.set_sort_for_scope(
+            r"C:\Projects",
+            vec![SortSpec::asc(SortField::Extension), SortSpec::asc(SortField::Name)],
+        );
+
+        let toml_str = toml::to_string_pretty(&config).unwrap();
+        let parsed: Config = toml::from_str(&toml_str).unwrap();
+
+        assert_eq!(parsed.ui.sort_for_scope(r"c:\projects").len(), 2);
+        assert_eq!(
+            parsed.ui.sort_for_scope(r"D:\Other"),
+            vec![SortSpec::desc(SortField::Modified)]
+        );
+    }
+
+    #[test]
+    fn test_parse_copy_format() {
+        let config: Config = toml::from_str("[ui]\ncopy_format = \"powershell\"\n").unwrap();
+        assert_eq!(config.ui.copy_format, ClipboardFormat::PowerShell);
+        assert_eq!(Config::default().ui.copy_format, ClipboardFormat::Text);
+    }
+
+    #[test]
+    fn test_parse_popup_mode() {
+        let config: Config = toml::from_str("[ui]\npopup = \"fresh\"\n").unwrap();
+        assert_eq!(config.ui.popup, PopupMode::Fresh);
+        assert_eq!(Config::default().ui.popup, PopupMode::Resume);
+    }
+
+    #[test]
+    fn test_parse_hotkey() {
+        let config: Config = toml::from_str("[ui]\nhotkey = \"Alt+Space\"\n").unwrap();
+        assert_eq!(config.ui.hotkey, "Alt+Space");
+        assert_eq!(Config::default().ui.hotkey, DEFAULT_HOTKEY);
+        let config: Config = toml::from_str("[ui]\nhigh_contrast = true\n").unwrap();
+        assert_eq!(config.ui.hotkey, DEFAULT_HOTKEY);
+    }
+
+    #[test]
+    fn test_database_config() {
+        let config: Config = toml::from_str(
+            "[database]\nmmap_size_mb = 0\ncache_size_mb = 16\nsynchronous = \"full\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.database.mmap_size_mb, 0);
+        assert_eq!(config.database.cache_size_mb, 16);
+        assert_eq!(config.database.busy_timeout_ms, 5000);
+        assert_eq!(config.database.synchronous, SynchronousLevel::Full);
+        assert_eq!(config.database.maintenance_interval(), Some(Duration::from_secs(24 * 3600)));
+        let disabled: Config = toml::from_str("[database]\nmaintenance_interval_hours = 0\n").unwrap();
+        assert_eq!(disabled.database.maintenance_interval(), None);
+        assert_eq!(config.database.max_size_bytes(), None);
+        let limited: Config = toml::from_str("[database]\nmax_size_mb = 512\n").unwrap();
+        assert_eq!(limited.database.max_size_bytes(), Some(512 * 1024 * 1024));
+        assert!(config.database.validate().is_ok());
+        assert!(DatabaseConfig::default().validate().is_ok());
+
+        let invalid = [
+            DatabaseConfig { cache_size_mb: 0, ..Default::default() },
+            DatabaseConfig { mmap_size_mb: DatabaseConfig::MAX_MMAP_SIZE_MB + 1, ..Default::default() },
+            DatabaseConfig { busy_timeout_ms: DatabaseConfig::MAX_BUSY_TIMEOUT_MS + 1, ..Default::default() },
+            DatabaseConfig { reader_connections: 0, ..Default::default() },
+        ];
+        for database in invalid {
+            assert!(database.validate().is_err(), "{:?}", database);
+        }
+        assert!(toml::from_str::<Config>("[database]\nsynchronous = \"sometimes\"\n").is_err());
+    }
+
+    #[test]
+    fn test_ipc_config() {
+        let config: Config = toml::from_str("[ipc]\nquery_timeout_ms = 2500\n").unwrap();
+        assert_eq!(config.ipc.query_timeout(), Duration::from_millis(2500));
+        assert_eq!(config.ipc.read_timeout(), Duration::from_secs(5));
+        assert_eq!(Config::default().ipc, IpcConfig::default());
+        assert_eq!(config.ipc.allowed_sids, vec!["BA", "IU"]);
+    }
+
+    #[test]
+    fn test_logging_config() {
+        let config: Config = toml::from_str("[logging]\nformat = \"json\"\nretention_days = 3\n").unwrap();
+        assert_eq!(config.logging.format, LogFormat::Json);
+        assert_eq!(config.logging.retention_days, 3);
+        assert_eq!(config.logging.level, "info");
+        assert_eq!(Config::default().logging, LoggingConfig::default());
+        assert!(toml::from_str::<Config>("[logging]\nformat = \"xml\"\n").is_err());
+    }
+
+    #[test]
+    fn test_volume_classes() {
+        let toml_str = r#"
+[general]
+usn_poll_interval_secs = 45
+offline_retention_days = 14
+
+[volumes.C]
+class = "system"
+
+[volumes.D]
+class = "data"
+
+[volumes.E]
+class = "archive"
+reconcile_interval_mins = 60
+
+[volumes.F]
+enabled = true
+
+[classes.archive]
+offline_retention_days = 365
+"#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+
+        // Built-in class defaults
+        assert_eq!(config.usn_poll_interval_secs('C'), 10);
+        assert_eq!(config.reconcile_interval_mins('C'), 15);
+        assert_eq!(config.throttle_cpu_percent('C'), 90.0);
+        assert_eq!(config.offline_retention_days('C'), 14);
+
+        // The data class keeps the general settings
+        assert_eq!(config.usn_poll_interval_secs('D'), 45);
+        assert_eq!(config.throttle_cpu_percent('D'), 80.0);
+
+        // Class overrides and per-volume settings take precedence
+        assert_eq!(config.offline_retention_days('E'), 365);
+        assert_eq!(config.reconcile_interval_mins('E'), 60);
+        assert_eq!(config.usn_poll_interval_secs('E'), 120);
+
+        // Volumes without a class use the general settings
+        assert_eq!(config.usn_poll_interval_secs('F'), 45);
+        assert_eq!(config.reconcile_interval_mins('F'), 30);
+        assert_eq!(config.offline_retention_days('F'), 14);
+
+        let retention = config.offline_retention();
+        assert_eq!(retention.days("E:"), 365);
+        assert_eq!(retention.days("F:"), 14);
+        assert_eq!(retention.days("G:"), 14);
+    }
+
+    #[test]
+    fn test_volumes_without_letters() {
+        let toml_str = r#"
+[volumes.d]
+enabled = true
+
+[volumes.'C:\Mount\Data\']
+enabled = true
+class = "archive"
+
+[volumes.'\\?\Volume{3e2f6a10-5b7c-4f7e-9a1d-2c8b7e6f5a41}\']
+enabled = true
+reconcile_interval_mins = 90
+"#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.is_volume_enabled('D'));
+        assert!(config.is_volume_enabled("D:"));
+        assert!(config.is_volume_enabled(r"c:\mount\data"));
+        assert!(!config.is_volume_enabled(r"C:\Mount"));
+        assert_eq!(
+            config.reconcile_interval_mins(r"\\?\Volume{3E2F6A10-5B7C-4F7E-9A1D-2C8B7E6F5A41}"),
+            90
+        );
+
+        // Retention is keyed like the indexed volume names
+        let retention = config.offline_retention();
+        assert_eq!(retention.days(r"C:\Mount\Data"), 90);
+        assert_eq!(retention.days("D:"), 7);
+
+        assert_eq!(volume_drive_letter(r"e:\"), Some('E'));
+        assert_eq!(volume_drive_letter(r"E:\Mount"), None);
+    }
+
+    #[test]
+    fn test_set_volume_enabled() {
+        let dir = std::env::temp_dir().join("ffi_test_set_volume_enabled");
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("config.toml");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        let original = "# Indexed volumes\n[volumes]\nd = { enabled = false, class = \"archive\" } # USB disk\n";
+        std::fs::write(&path, original).unwrap();
+        Config::set_volume_enabled(&path, "D:", true).unwrap();
+        Config::set_volume_enabled(&path, "C:\\", true).unwrap();
+        Config::set_volume_enabled(&path, r"C:\Mount\Data\", false).unwrap();
+
+        // Only the enabled keys change; comments and layout stay
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# Indexed volumes\n[volumes]\nd = { enabled = true, class = \"archive\" } # USB disk\n"));
+        assert!(contents.contains("[volumes.C]\nenabled = true\n"));
+
+        // Existing entries keep their settings; new ones are keyed like users write them
+        let saved = Config::load_from(&path).unwrap();
+        assert!(saved.is_volume_enabled('D'));
+        assert_eq!(saved.volumes["d"].class, Some(VolumeClass::Archive));
+        assert!(saved.volumes["C"].enabled);
+        assert!(saved.is_volume_disabled(r"c:\mount\data"));
+        assert!(saved.is_volume_configured(r"C:\Mount\Data"));
+        assert!(!saved.is_volume_configured('E'));
+
+        // A missing file is created
+        let _ = std::fs::remove_dir_all(&dir);
+        Config::set_volume_enabled(&path, "E", true).unwrap();
+        assert!(Config::load_from(&path).unwrap().is_volume_enabled('E'));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_network_shares() {
+        let toml_str = r#"
+[network]
+enabled = true
+offline_retention_days = 30
+
+[[network.shares]]
+path = '\\nas\media\'
+max_entries_per_sec = 100
+
+[[network.shares]]
+path = "z:"
+reconcile_interval_mins = 15
+
+[[network.shares]]
+path = '\\nas'
+"#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let network = &config.network;
+        assert!(network.enabled);
+
+        let [media, mapped, invalid] = &network.shares[..] else {
+            panic!("expected three shares");
+        };
+        assert_eq!(media.volume_name().as_deref(), Some(r"\\nas\media"));
+        assert_eq!(media.root_path().as_deref(), Some(r"\\nas\media\"));
+        assert_eq!(mapped.volume_name().as_deref(), Some("Z:"));
+        assert_eq!(invalid.volume_name(), None);
+
+        assert_eq!(network.reconcile_interval_mins(media), 120);
+        assert_eq!(network.reconcile_interval_mins(mapped), 15);
+        assert_eq!(network.max_entries_per_sec(media), 100);
+        assert_eq!(network.max_entries_per_sec(mapped), 500);
+
+        let retention = config.offline_retention();
+        assert_eq!(retention.days(r"\\NAS\Media"), 30);
+        assert_eq!(retention.days("C:"), 7);
+        assert!(!Config::default().network.enabled);
     }
 
     #[test]
@@ -367,6 +1910,12 @@ enabled = true
 [volumes.D]
 enabled = true
 reconcile_interval_mins = 60
+max_depth = 4
+max_files = 500000
+
+[volumes.D.exclude]
+directories = ["node_modules"]
+patterns = ["**/*.iso"]
 
 [volumes.E]
 enabled = false
@@ -387,5 +1936,20 @@ extensions = ["tmp", "log", "bak"]
         assert_eq!(config.reconcile_interval_mins('D'), 60);
         assert_eq!(config.exclude.paths.len(), 2);
         assert_eq!(config.exclude.extensions.len(), 3);
+
+        // Volume excludes add to the global ones
+        let d = config.exclude_matcher('D');
+        assert!(d.should_exclude(r"D:\Code\node_modules", true));
+        assert!(d.should_exclude(r"D:\Images\disk.ISO", false));
+        assert!(d.should_exclude(r"D:\Work\build.log", false));
+        let c = config.exclude_matcher('C');
+        assert!(!c.should_exclude(r"C:\Code\node_modules", true));
+        assert!(c.should_exclude(r"C:\Windows\Temp\a.txt", false));
+        assert_eq!(config.exclude_for('E'), config.exclude);
+
+        // Limits apply to their volume only
+        assert_eq!(d.max_depth(), Some(4));
+        assert_eq!(d.max_files(), Some(500_000));
+        assert_eq!(c.max_depth(), None);
     }
 }
@@ -0,0 +1,257 @@
+//! Live reloading of `config.toml` in the running service.
+//!
+//! The watcher polls the file's modification time and, when it changes,
+//! loads the file and replaces the [`SharedConfig`] the indexing threads
+//! read. What needs more than a new configuration to take effect is
+//! described by [`ConfigChanges`] and handed to the service to apply:
+//! volumes newly enabled are indexed, volumes newly disabled are detached,
+//! and journal monitors restart when their polling, throttling or excludes
+//! change. A file that fails to parse is logged and ignored until it
+//! changes again.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use super::config::{Config, SharedConfig, VolumeName};
+
+/// Interval between checks of the file's modification time.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What changed between two configurations beyond the settings read anew
+/// by each job, reconciliation pass or volume event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigChanges {
+    /// Volumes newly enabled for indexing, by normalized key (`D:`)
+    pub enabled: Vec<String>,
+    /// Volumes newly turned off with `enabled = false`, by normalized key
+    pub disabled: Vec<String>,
+    /// Whether journal polling, throttling or excludes changed, so running
+    /// USN monitors must restart to pick them up
+    pub monitors: bool,
+}
+
+impl ConfigChanges {
+    /// Compare the configuration in use with the one just loaded.
+    pub fn between(old: &Config, new: &Config) -> Self {
+        let mut keys: Vec<String> = old
+            .volumes
+            .keys()
+            .chain(new.volumes.keys())
+            .map(|name| name.as_str().volume_key())
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        let enabled = keys
+            .iter()
+            .filter(|key| new.is_volume_enabled(key.as_str()) && !old.is_volume_enabled(key.as_str()))
+            .cloned()
+            .collect();
+        let disabled = keys
+            .iter()
+            .filter(|key| new.is_volume_disabled(key.as_str()) && !old.is_volume_disabled(key.as_str()))
+            .cloned()
+            .collect();
+
+        // Monitors only run on volumes with a drive letter
+        let monitors = old.general.usn_poll_min_secs != new.general.usn_poll_min_secs
+            || old.general.usn_poll_max_secs != new.general.usn_poll_max_secs
+            || ('A'..='Z').any(|letter| {
+                old.usn_poll_interval_secs(letter) != new.usn_poll_interval_secs(letter)
+                    || old.throttle_cpu_percent(letter) != new.throttle_cpu_percent(letter)
+                    || old.exclude_for(letter) != new.exclude_for(letter)
+                    || old.max_depth(letter) != new.max_depth(letter)
+            });
+
+        Self {
+            enabled,
+            disabled,
+            monitors,
+        }
+    }
+
+    /// Whether nothing beyond the shared configuration needs applying.
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_empty() && self.disabled.is_empty() && !self.monitors
+    }
+}
+
+/// Handle for a running config watcher thread.
+pub struct ConfigWatcherHandle {
+    handle: Option<JoinHandle<()>>,
+    shutdown_tx: Sender<()>,
+}
+
+impl ConfigWatcherHandle {
+    /// Stop the watcher and wait for its thread to finish.
+    pub fn stop(&mut self) {
+        let _ = self.shutdown_tx.send(());
+        if let Some(handle) = self.handle.take() {
+            match handle.join() {
+                Ok(()) => tracing::info!("Config watcher thread stopped"),
+                Err(_) => tracing::error!("Config watcher thread panicked"),
+            }
+        }
+    }
+}
+
+/// Start watching `config.toml` in a background thread.
+///
+/// # Arguments
+/// * `config` - Configuration shared with the indexing threads; replaced
+///   whenever the file changes
+/// * `apply` - Called on the watcher thread with each new configuration
+///   and what changed from the previous one
+///
+/// # Returns
+/// A handle that stops the watcher.
+pub fn start_config_watcher(
+    config: SharedConfig,
+    mut apply: impl FnMut(&Config, &ConfigChanges) + Send + 'static,
+) -> ConfigWatcherHandle {
+    let (shutdown_tx, shutdown_rx) = mpsc::channel();
+
+    let handle = std::thread::spawn(move || {
+        let mut watch = ConfigFile::new(Config::config_path());
+        config_watcher_loop(&mut watch, &config, &mut apply, &shutdown_rx);
+    });
+
+    ConfigWatcherHandle {
+        handle: Some(handle),
+        shutdown_tx,
+    }
+}
+
+/// Check the file every [`POLL_INTERVAL`] until shutdown.
+fn config_watcher_loop(
+    watch: &mut ConfigFile,
+    config: &SharedConfig,
+    apply: &mut impl FnMut(&Config, &ConfigChanges),
+    shutdown_rx: &Receiver<()>,
+) {
+    tracing::info!("Watching {:?} for changes", watch.path);
+    loop {
+        match shutdown_rx.recv_timeout(POLL_INTERVAL) {
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        if let Some((new, changes)) = watch.reload(config) {
+            apply(&new, &changes);
+        }
+    }
+}
+
+/// The watched file and the modification time last loaded.
+struct ConfigFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigFile {
+    /// Watch a file, taking its current contents as already loaded.
+    fn new(path: PathBuf) -> Self {
+        let modified = modified_time(&path);
+        Self { path, modified }
+    }
+
+    /// Load the file into the shared configuration if it changed since the
+    /// last call.
+    ///
+    /// # Returns
+    /// The new configuration and what changed, or None if the file did not
+    /// change or could not be loaded.
+    fn reload(&mut self, config: &SharedConfig) -> Option<(Arc<Config>, ConfigChanges)> {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let new = match Config::load_from(&self.path) {
+            Ok(new) => new,
+            Err(e) => {
+                tracing::warn!("Ignoring changed configuration until it is fixed: {}", e);
+                return None;
+            }
+        };
+
+        let changes = ConfigChanges::between(&config.get(), &new);
+        config.set(new);
+        tracing::info!("Configuration reloaded from {:?}", self.path);
+        Some((config.get(), changes))
+    }
+}
+
+/// Modification time of a file, or None if it doesn't exist.
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_changes() {
+        let parse = |toml: &str| toml::from_str::<Config>(toml).unwrap();
+        let old = parse("[volumes]\nC = {}\nD = { enabled = false }\nE = {}\n");
+
+        let new = parse("[volumes]\nc = {}\n\"D:\" = {}\nE = { enabled = false }\nF = {}\n");
+        let changes = ConfigChanges::between(&old, &new);
+        assert_eq!(changes.enabled, vec!["D:", "F:"]);
+        assert_eq!(changes.disabled, vec!["E:"]);
+        assert!(!changes.monitors);
+        assert!(ConfigChanges::between(&new, &new).is_empty());
+
+        // Monitors restart for changed excludes or polling, including through a class
+        let excluded = parse("[volumes]\nC = {}\nD = { enabled = false }\nE = {}\n[exclude]\nextensions = [\"tmp\"]\n");
+        assert!(ConfigChanges::between(&old, &excluded).monitors);
+        let volume_excluded =
+            parse("[volumes]\nC = {}\nD = { enabled = false }\n[volumes.E.exclude]\ndirectories = [\".git\"]\n");
+        assert!(ConfigChanges::between(&old, &volume_excluded).monitors);
+        let archive = parse("[volumes]\nC = { class = \"archive\" }\nD = { enabled = false }\nE = {}\n");
+        assert_eq!(
+            ConfigChanges::between(&old, &archive),
+            ConfigChanges {
+                monitors: true,
+                ..ConfigChanges::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_file_reload() {
+        let dir = std::env::temp_dir().join("ffi_test_config_watcher");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let write = |contents: &str, age_secs: u64| {
+            std::fs::write(&path, contents).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::now() - Duration::from_secs(age_secs)).unwrap();
+        };
+
+        write("[volumes]\nC = {}\n", 60);
+        let shared = SharedConfig::new(Config::load_from(&path).unwrap());
+        let mut watch = ConfigFile::new(path.clone());
+        assert!(watch.reload(&shared).is_none());
+
+        write("[volumes]\nC = {}\nD = {}\n", 30);
+        let (new, changes) = watch.reload(&shared).unwrap();
+        assert_eq!(changes.enabled, vec!["D:"]);
+        assert!(new.is_volume_enabled('D'));
+        assert!(shared.get().is_volume_enabled('D'));
+        assert!(watch.reload(&shared).is_none());
+
+        // A broken file keeps the configuration in use
+        write("[volumes\n", 10);
+        assert!(watch.reload(&shared).is_none());
+        assert!(shared.get().is_volume_enabled('D'));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
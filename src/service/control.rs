@@ -1,52 +1,106 @@
 //! Service control handler for Windows service events.
 //!
-//! Handles Stop, Shutdown, and Interrogate control events from the
-//! Windows Service Control Manager (SCM).
+//! Handles Stop, PreShutdown, Shutdown, Pause, Continue, PowerEvent and
+//! Interrogate control events from the Windows Service Control Manager (SCM).
 
 use std::sync::mpsc::Sender;
 
 #[cfg(windows)]
-use windows_service::service::ServiceControl;
+use windows_service::service::{PowerEventParam, ServiceControl};
 #[cfg(windows)]
 use windows_service::service_control_handler::ServiceControlHandlerResult;
 
+/// Request passed from the control handler to the main service loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlRequest {
+    /// Stop the service (Stop or system Shutdown)
+    Stop,
+    /// Suspend indexing; searches keep working
+    Pause,
+    /// Resume indexing suspended by `Pause`
+    Continue,
+    /// The machine is about to sleep or hibernate
+    Suspend,
+    /// The machine woke up from sleep or hibernation
+    Resume,
+}
+
 /// State shared with the service control event handler.
 ///
-/// Contains the shutdown signal sender to notify the main service
-/// loop when a stop/shutdown event is received.
+/// Contains the control request sender to notify the main service
+/// loop when a stop, shutdown, pause or continue event is received.
 pub struct ServiceState {
-    /// Sender to signal service shutdown
-    pub shutdown_tx: Sender<()>,
+    /// Sender of requests to the main service loop
+    pub control_tx: Sender<ControlRequest>,
 }
 
 impl ServiceState {
-    /// Create a new ServiceState with the given shutdown channel sender.
-    pub fn new(shutdown_tx: Sender<()>) -> Self {
-        Self { shutdown_tx }
+    /// Create a new ServiceState with the given control channel sender.
+    pub fn new(control_tx: Sender<ControlRequest>) -> Self {
+        Self { control_tx }
     }
 }
 
 /// Create a service control event handler function.
 ///
 /// Returns a closure that handles control events from the SCM:
-/// - Stop: Sends shutdown signal, returns NoError
-/// - Shutdown: Sends shutdown signal, returns NoError
+/// - Stop: Sends a stop request, returns NoError
+/// - PreShutdown: Sends a stop request, returns NoError
+/// - Shutdown: Sends a stop request, returns NoError
+/// - Pause: Sends a pause request, returns NoError
+/// - Continue: Sends a continue request, returns NoError
+/// - PowerEvent: Sends a suspend or resume request for sleep and wake-up,
+///   returns NoError
 /// - Interrogate: Returns NoError (no action needed)
 /// - Other: Returns NotImplemented
+///
+/// The main service loop carries out the request and reports the new state
+/// to the SCM.
 #[cfg(windows)]
 pub fn create_event_handler(
-    shutdown_tx: Sender<()>,
+    control_tx: Sender<ControlRequest>,
 ) -> impl FnMut(ServiceControl) -> ServiceControlHandlerResult {
     move |control_event| -> ServiceControlHandlerResult {
         match control_event {
             ServiceControl::Stop => {
                 tracing::info!("Received Stop control event");
-                shutdown_tx.send(()).ok();
+                control_tx.send(ControlRequest::Stop).ok();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Preshutdown => {
+                tracing::info!("Received PreShutdown control event");
+                control_tx.send(ControlRequest::Stop).ok();
                 ServiceControlHandlerResult::NoError
             }
             ServiceControl::Shutdown => {
                 tracing::info!("Received Shutdown control event");
-                shutdown_tx.send(()).ok();
+                control_tx.send(ControlRequest::Stop).ok();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Pause => {
+                tracing::info!("Received Pause control event");
+                control_tx.send(ControlRequest::Pause).ok();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Continue => {
+                tracing::info!("Received Continue control event");
+                control_tx.send(ControlRequest::Continue).ok();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::PowerEvent(PowerEventParam::Suspend) => {
+                tracing::info!("Received suspend power event");
+                control_tx.send(ControlRequest::Suspend).ok();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::PowerEvent(
+                PowerEventParam::ResumeAutomatic | PowerEventParam::ResumeSuspend | PowerEventParam::ResumeCritical,
+            ) => {
+                tracing::info!("Received resume power event");
+                control_tx.send(ControlRequest::Resume).ok();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::PowerEvent(event) => {
+                tracing::debug!("Received power event: {:?}", event);
                 ServiceControlHandlerResult::NoError
             }
             ServiceControl::Interrogate => {
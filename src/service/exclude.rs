@@ -0,0 +1,262 @@
+//! Compiled exclude settings, as used by the scanners and the USN monitor.
+//!
+//! [`ExcludeConfig`] holds what `config.toml` says; an [`ExcludeMatcher`] is
+//! built from it once per scan or monitor, with prefixes and names
+//! lowercased and the glob patterns compiled into one `GlobSet`, so
+//! checking an entry costs no allocation beyond its lowercased path.
+//! Matchers for a volume include its `[volumes.<name>.exclude]` section
+//! and its `max_depth` and `max_files` limits (see
+//! [`Config::exclude_matcher`](super::config::Config::exclude_matcher)).
+
+use std::collections::HashSet;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use super::config::ExcludeConfig;
+
+/// Exclude settings compiled for matching many entries.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeMatcher {
+    /// Path prefixes as configured, for range deletes of indexed entries
+    paths: Vec<String>,
+    /// Path prefixes, lowercased with backslashes
+    prefixes: Vec<String>,
+    /// Lowercased extensions without the dot
+    extensions: HashSet<String>,
+    /// Lowercased directory names
+    directories: HashSet<String>,
+    /// Glob patterns, matched against lowercased paths with forward slashes
+    patterns: GlobSet,
+    /// Deepest folder level indexed; entries in the root are at depth 1
+    max_depth: Option<usize>,
+    /// Most entries indexed
+    max_files: Option<u64>,
+}
+
+impl ExcludeMatcher {
+    /// Compile exclude settings. Invalid glob patterns are logged and
+    /// ignored.
+    pub fn new(exclude: &ExcludeConfig) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &exclude.patterns {
+            let glob = GlobBuilder::new(&pattern.replace('\\', "/"))
+                .case_insensitive(true)
+                .literal_separator(false)
+                .backslash_escape(false)
+                .build();
+            match glob {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => tracing::warn!("Ignoring invalid exclude pattern {:?}: {}", pattern, e),
+            }
+        }
+        let patterns = builder.build().unwrap_or_else(|e| {
+            tracing::warn!("Ignoring exclude patterns: {}", e);
+            GlobSet::empty()
+        });
+
+        Self {
+            paths: exclude.paths.clone(),
+            prefixes: exclude.paths.iter().map(|p| p.replace('/', "\\").to_lowercase()).collect(),
+            extensions: exclude.extensions.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect(),
+            directories: exclude.directories.iter().map(|d| d.trim_matches(['\\', '/']).to_lowercase()).collect(),
+            patterns,
+            max_depth: None,
+            max_files: None,
+        }
+    }
+
+    /// Add a volume's depth and entry count limits.
+    ///
+    /// # Arguments
+    /// * `max_depth` - Deepest folder level indexed (1 for the root's own
+    ///   entries), or None for no limit
+    /// * `max_files` - Most entries indexed, or None for no limit
+    pub fn with_limits(mut self, max_depth: Option<usize>, max_files: Option<u64>) -> Self {
+        self.max_depth = max_depth;
+        self.max_files = max_files;
+        self
+    }
+
+    /// Whether nothing is excluded.
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+            && self.extensions.is_empty()
+            && !self.has_patterns()
+            && self.max_depth.is_none()
+            && self.max_files.is_none()
+    }
+
+    /// Deepest folder level indexed, if limited.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Most entries indexed, if limited.
+    pub fn max_files(&self) -> Option<u64> {
+        self.max_files
+    }
+
+    /// Whether an entry `depth` levels below the volume root is past the
+    /// depth limit.
+    pub fn is_too_deep(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max| depth > max)
+    }
+
+    /// Describe the limits that left entries out, for the volume status.
+    ///
+    /// # Arguments
+    /// * `too_deep` - Whether entries were past the depth limit
+    /// * `too_many` - Whether entries were past the entry count limit
+    ///
+    /// # Returns
+    /// E.g. "max_depth = 3, max_files = 1000", or None if neither applied.
+    pub fn limit_reason(&self, too_deep: bool, too_many: bool) -> Option<String> {
+        let limits: Vec<String> = [
+            self.max_depth.filter(|_| too_deep).map(|max| format!("max_depth = {}", max)),
+            self.max_files.filter(|_| too_many).map(|max| format!("max_files = {}", max)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!limits.is_empty()).then(|| limits.join(", "))
+    }
+
+    /// Excluded path prefixes as configured.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Excluded extensions, lowercased.
+    pub fn extensions(&self) -> impl Iterator<Item = &str> {
+        self.extensions.iter().map(String::as_str)
+    }
+
+    /// Whether directory names or glob patterns are excluded, which can
+    /// only be checked against each entry's full path.
+    pub fn has_patterns(&self) -> bool {
+        !self.directories.is_empty() || !self.patterns.is_empty()
+    }
+
+    /// Check if a file name has an excluded extension.
+    pub fn should_exclude_name(&self, name: &str) -> bool {
+        !self.extensions.is_empty()
+            && name
+                .rsplit_once('.')
+                .is_some_and(|(_, ext)| self.extensions.contains(&ext.to_lowercase()))
+    }
+
+    /// Check if a path is under an excluded prefix or directory, or matches
+    /// a pattern.
+    pub fn should_exclude_path(&self, path: &str) -> bool {
+        self.excludes_path(path, false)
+    }
+
+    /// Check if an entry should be kept out of the index.
+    ///
+    /// A directory is also excluded by its own name, and by patterns
+    /// matching what is below it (`**/build/**`); a file by its extension.
+    /// The depth limit is not checked here, since the depth depends on
+    /// where the volume root is (see [`Self::is_too_deep`]).
+    ///
+    /// # Arguments
+    /// * `path` - Full path of the entry (e.g., `C:\Windows\Temp\a.tmp`)
+    /// * `is_dir` - Whether the entry is a directory
+    pub fn should_exclude(&self, path: &str, is_dir: bool) -> bool {
+        self.excludes_path(path, is_dir) || (!is_dir && self.should_exclude_name(path))
+    }
+
+    fn excludes_path(&self, path: &str, is_dir: bool) -> bool {
+        if self.prefixes.is_empty() && !self.has_patterns() {
+            return false;
+        }
+        let lower = path.replace('/', "\\").to_lowercase();
+        if self.prefixes.iter().any(|prefix| lower.starts_with(prefix.as_str())) {
+            return true;
+        }
+
+        if !self.directories.is_empty() {
+            let mut components = lower.split('\\').filter(|c| !c.is_empty());
+            if !is_dir {
+                components.next_back();
+            }
+            if components.any(|c| self.directories.contains(c)) {
+                return true;
+            }
+        }
+
+        if !self.patterns.is_empty() {
+            let slashed = lower.replace('\\', "/");
+            if self.patterns.is_match(&slashed) || (is_dir && self.patterns.is_match(format!("{}/", slashed))) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(paths: &[&str], extensions: &[&str], directories: &[&str], patterns: &[&str]) -> ExcludeMatcher {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        ExcludeMatcher::new(&ExcludeConfig {
+            paths: strings(paths),
+            extensions: strings(extensions),
+            directories: strings(directories),
+            patterns: strings(patterns),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_prefixes_and_extensions() {
+        let exclude = matcher(&[r"C:\Windows\Temp"], &["tmp", ".LOG"], &[], &[]);
+        assert!(exclude.should_exclude(r"c:\windows\temp\a.txt", false));
+        assert!(exclude.should_exclude(r"D:\Work\notes.TMP", false));
+        assert!(exclude.should_exclude(r"D:\Work\build.log", false));
+        assert!(!exclude.should_exclude(r"D:\Work\logs.tmp", true));
+        assert!(!exclude.should_exclude(r"D:\Work\notes.txt", false));
+        assert!(!exclude.has_patterns());
+        assert!(matcher(&[], &[], &[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_directory_names() {
+        let exclude = matcher(&[], &[], &["node_modules", ".git"], &[]);
+        assert!(exclude.should_exclude(r"C:\Code\app\node_modules", true));
+        assert!(exclude.should_exclude(r"C:\Code\app\Node_Modules\left-pad\index.js", false));
+        assert!(exclude.should_exclude(r"C:\Code\app\.git\HEAD", false));
+        assert!(!exclude.should_exclude(r"C:\Code\app\node_modules", false));
+        assert!(!exclude.should_exclude(r"C:\Code\app\src\main.rs", false));
+        assert!(exclude.has_patterns());
+    }
+
+    #[test]
+    fn test_glob_patterns() {
+        let patterns = ["**/node_modules/**", "*.iso", "C:/Users/*/Downloads/*.tmp", "[invalid"];
+        let exclude = matcher(&[], &[], &[], &patterns);
+        assert!(exclude.should_exclude(r"C:\Code\app\node_modules\a\b.js", false));
+        assert!(exclude.should_exclude(r"C:\Code\app\node_modules", true));
+        assert!(exclude.should_exclude(r"D:\Images\Windows.ISO", false));
+        assert!(exclude.should_exclude(r"c:\users\me\downloads\setup.tmp", false));
+        assert!(!exclude.should_exclude(r"D:\Downloads\setup.tmp", false));
+        assert!(!exclude.should_exclude(r"C:\Code\app\src\main.rs", false));
+    }
+
+    #[test]
+    fn test_limits() {
+        let exclude = matcher(&[], &[], &[], &[]).with_limits(Some(2), Some(1000));
+        assert!(!exclude.is_empty());
+        assert!(!exclude.has_patterns());
+        assert!(!exclude.is_too_deep(2));
+        assert!(exclude.is_too_deep(3));
+        assert!(!exclude.should_exclude(r"C:\a\b\c\d.txt", false));
+        assert_eq!(exclude.limit_reason(true, true).as_deref(), Some("max_depth = 2, max_files = 1000"));
+        assert_eq!(exclude.limit_reason(false, true).as_deref(), Some("max_files = 1000"));
+        assert_eq!(exclude.limit_reason(false, false), None);
+        assert_eq!(matcher(&[], &[], &[], &[]).limit_reason(true, true), None);
+    }
+}
@@ -0,0 +1,268 @@
+//! Registering the service with the Service Control Manager.
+//!
+//! `ffi-service install` registers the running executable as a delayed
+//! automatic LocalSystem service that restarts after failures, keeps only
+//! the privileges indexing needs and gets time to save its state before
+//! the machine shuts down. `uninstall`, `start` and `stop` round it off, so
+//! no `sc.exe` invocations are needed. All of them require an elevated
+//! prompt.
+
+use std::time::Duration;
+
+use crate::Result;
+
+#[cfg(windows)]
+use std::ffi::OsString;
+#[cfg(windows)]
+use std::time::Instant;
+
+#[cfg(windows)]
+use windows_service::service::{
+    Service, ServiceAccess, ServiceAction, ServiceActionType, ServiceErrorControl, ServiceFailureActions,
+    ServiceFailureResetPeriod, ServiceInfo, ServiceStartType, ServiceState as WinServiceState, ServiceType,
+};
+#[cfg(windows)]
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+#[cfg(windows)]
+use super::{SERVICE_DISPLAY_NAME, SERVICE_NAME};
+#[cfg(windows)]
+use crate::FFIError;
+
+/// Description shown for the service in the Services console.
+pub const SERVICE_DESCRIPTION: &str =
+    "Keeps the FastFileIndex file name index up to date and answers searches from FFI clients.";
+
+/// Privileges the service keeps; Windows removes every other privilege of
+/// LocalSystem from its token. Backup reads files regardless of their ACLs,
+/// manage-volume opens volumes for the MFT and USN journal.
+#[cfg_attr(not(windows), allow(dead_code))]
+const REQUIRED_PRIVILEGES: [&str; 3] = ["SeBackupPrivilege", "SeChangeNotifyPrivilege", "SeManageVolumePrivilege"];
+
+/// Delays before restarting after the first, second and later failures.
+#[cfg_attr(not(windows), allow(dead_code))]
+const RESTART_DELAYS: [Duration; 3] = [Duration::from_secs(5), Duration::from_secs(30), Duration::from_secs(120)];
+
+/// The failure count starts over after a day without failures.
+#[cfg_attr(not(windows), allow(dead_code))]
+const FAILURE_RESET_PERIOD: Duration = Duration::from_secs(86400);
+
+/// Time the service gets on pre-shutdown to flush the index and stop.
+#[cfg_attr(not(windows), allow(dead_code))]
+const PRESHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest wait for the service to reach the requested state.
+#[cfg_attr(not(windows), allow(dead_code))]
+const STATE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Register the current executable as the FFI service.
+///
+/// The service starts automatically, delayed until the rest of the system
+/// is up, runs as LocalSystem and is restarted by the SCM if it fails.
+///
+/// # Errors
+/// Returns an error if the service is already installed or the prompt is
+/// not elevated.
+#[cfg(windows)]
+pub fn install_service() -> Result<()> {
+    let executable_path = std::env::current_exe()?;
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)
+        .map_err(|e| FFIError::Service(format!("Failed to connect to the service manager: {}", e)))?;
+
+    let info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(SERVICE_DISPLAY_NAME),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path,
+        launch_arguments: Vec::new(),
+        dependencies: Vec::new(),
+        // LocalSystem
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)
+        .map_err(|e| FFIError::Service(format!("Failed to create service {}: {}", SERVICE_NAME, e)))?;
+
+    service
+        .set_description(SERVICE_DESCRIPTION)
+        .map_err(|e| FFIError::Service(format!("Failed to set service description: {}", e)))?;
+    service
+        .set_delayed_auto_start(true)
+        .map_err(|e| FFIError::Service(format!("Failed to set delayed start: {}", e)))?;
+    service
+        .set_preshutdown_timeout(PRESHUTDOWN_TIMEOUT)
+        .map_err(|e| FFIError::Service(format!("Failed to set pre-shutdown timeout: {}", e)))?;
+
+    // Restart after crashes and after exits with an error alike
+    let actions = RESTART_DELAYS
+        .iter()
+        .map(|&delay| ServiceAction {
+            action_type: ServiceActionType::Restart,
+            delay,
+        })
+        .collect();
+    service
+        .update_failure_actions(ServiceFailureActions {
+            reset_period: ServiceFailureResetPeriod::After(FAILURE_RESET_PERIOD),
+            reboot_msg: None,
+            command: None,
+            actions: Some(actions),
+        })
+        .map_err(|e| FFIError::Service(format!("Failed to set recovery actions: {}", e)))?;
+    service
+        .set_failure_actions_on_non_crash_failures(true)
+        .map_err(|e| FFIError::Service(format!("Failed to set recovery actions: {}", e)))?;
+
+    set_required_privileges(&service)?;
+
+    tracing::info!("Installed service {} for {:?}", SERVICE_NAME, info.executable_path);
+    Ok(())
+}
+
+/// Stop the FFI service if it runs and remove its registration.
+///
+/// The index and configuration in the data directory are kept.
+#[cfg(windows)]
+pub fn uninstall_service() -> Result<()> {
+    let service = open_service(ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)?;
+    stop(&service)?;
+    service
+        .delete()
+        .map_err(|e| FFIError::Service(format!("Failed to delete service {}: {}", SERVICE_NAME, e)))?;
+
+    tracing::info!("Uninstalled service {}", SERVICE_NAME);
+    Ok(())
+}
+
+/// Start the FFI service and wait until it runs.
+#[cfg(windows)]
+pub fn start_service() -> Result<()> {
+    let service = open_service(ServiceAccess::QUERY_STATUS | ServiceAccess::START)?;
+    if query_state(&service)? != WinServiceState::Running {
+        service
+            .start::<&str>(&[])
+            .map_err(|e| FFIError::Service(format!("Failed to start service {}: {}", SERVICE_NAME, e)))?;
+        wait_for_state(&service, WinServiceState::Running)?;
+    }
+    Ok(())
+}
+
+/// Stop the FFI service and wait until it has stopped.
+#[cfg(windows)]
+pub fn stop_service() -> Result<()> {
+    let service = open_service(ServiceAccess::QUERY_STATUS | ServiceAccess::STOP)?;
+    stop(&service)
+}
+
+/// Open the installed FFI service with the given access.
+#[cfg(windows)]
+fn open_service(access: ServiceAccess) -> Result<Service> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(|e| FFIError::Service(format!("Failed to connect to the service manager: {}", e)))?;
+    manager
+        .open_service(SERVICE_NAME, access)
+        .map_err(|e| FFIError::Service(format!("Failed to open service {}: {}", SERVICE_NAME, e)))
+}
+
+/// Ask a service to stop unless it already has, and wait for it.
+#[cfg(windows)]
+fn stop(service: &Service) -> Result<()> {
+    if query_state(service)? == WinServiceState::Stopped {
+        return Ok(());
+    }
+    service
+        .stop()
+        .map_err(|e| FFIError::Service(format!("Failed to stop service {}: {}", SERVICE_NAME, e)))?;
+    wait_for_state(service, WinServiceState::Stopped)
+}
+
+/// Current state of a service.
+#[cfg(windows)]
+fn query_state(service: &Service) -> Result<WinServiceState> {
+    service
+        .query_status()
+        .map(|status| status.current_state)
+        .map_err(|e| FFIError::Service(format!("Failed to query service {}: {}", SERVICE_NAME, e)))
+}
+
+/// Poll a service until it reaches `state`, giving up after [`STATE_TIMEOUT`].
+#[cfg(windows)]
+fn wait_for_state(service: &Service, state: WinServiceState) -> Result<()> {
+    let deadline = Instant::now() + STATE_TIMEOUT;
+    loop {
+        let current = query_state(service)?;
+        if current == state {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(FFIError::Service(format!(
+                "Service {} is still {:?} after {}s",
+                SERVICE_NAME,
+                current,
+                STATE_TIMEOUT.as_secs()
+            )));
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+}
+
+/// Limit the service's token to [`REQUIRED_PRIVILEGES`].
+#[cfg(windows)]
+fn set_required_privileges(service: &Service) -> Result<()> {
+    use windows::core::PWSTR;
+    use windows::Win32::System::Services::{
+        ChangeServiceConfig2W, SC_HANDLE, SERVICE_CONFIG_REQUIRED_PRIVILEGES_INFO, SERVICE_REQUIRED_PRIVILEGES_INFOW,
+    };
+
+    // A multi-string: each name null-terminated, then an empty one
+    let mut privileges: Vec<u16> = REQUIRED_PRIVILEGES
+        .iter()
+        .flat_map(|name| name.encode_utf16().chain(std::iter::once(0)))
+        .chain(std::iter::once(0))
+        .collect();
+    let info = SERVICE_REQUIRED_PRIVILEGES_INFOW {
+        pmszRequiredPrivileges: PWSTR(privileges.as_mut_ptr()),
+    };
+
+    // The handle stays open while `service` lives, and `info` outlives the call
+    unsafe {
+        ChangeServiceConfig2W(
+            SC_HANDLE(service.raw_handle() as _),
+            SERVICE_CONFIG_REQUIRED_PRIVILEGES_INFO,
+            Some(&info as *const SERVICE_REQUIRED_PRIVILEGES_INFOW as *const std::ffi::c_void),
+        )
+    }
+    .map_err(|e| FFIError::Service(format!("Failed to set required privileges: {}", e)))
+}
+
+/// Stub for non-Windows platforms.
+#[cfg(not(windows))]
+pub fn install_service() -> Result<()> {
+    Err(requires_windows())
+}
+
+/// Stub for non-Windows platforms.
+#[cfg(not(windows))]
+pub fn uninstall_service() -> Result<()> {
+    Err(requires_windows())
+}
+
+/// Stub for non-Windows platforms.
+#[cfg(not(windows))]
+pub fn start_service() -> Result<()> {
+    Err(requires_windows())
+}
+
+/// Stub for non-Windows platforms.
+#[cfg(not(windows))]
+pub fn stop_service() -> Result<()> {
+    Err(requires_windows())
+}
+
+#[cfg(not(windows))]
+fn requires_windows() -> crate::FFIError {
+    crate::FFIError::Service("Service management requires Windows".to_string())
+}
@@ -0,0 +1,324 @@
+//! Starting and stopping everything the service runs.
+//!
+//! The Windows service and `ffi-service --console` share this startup and
+//! shutdown path, so a console run indexes and answers searches exactly as
+//! the installed service would. They differ only in where startup progress
+//! and state changes are reported: the SCM, or just the log.
+
+use std::ffi::OsString;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+
+use windows_service::service::ServiceState as WinServiceState;
+
+use super::config::{Config, DatabaseConfig, SharedConfig};
+use super::{
+    start_config_watcher, start_volume_watcher, ConfigWatcherHandle, ControlRequest, VolumeWatcherHandle,
+};
+use crate::db::{self, DatabasePool, WriterThread};
+use crate::indexer::{self, FatReconcilerHandle, JobPool};
+use crate::ipc::IpcServer;
+use crate::{FFIError, Result};
+
+/// The volume watcher and the handler of its events.
+struct VolumeWatcher {
+    watcher: VolumeWatcherHandle,
+    watcher_shutdown_tx: Sender<()>,
+    handler: JoinHandle<()>,
+    handler_shutdown_tx: Sender<()>,
+}
+
+/// Everything the service runs between start and stop.
+pub(crate) struct Components {
+    read_only: bool,
+    db_writer: Option<WriterThread>,
+    job_pool: Option<JobPool>,
+    reconciler: Option<(FatReconcilerHandle, Sender<()>)>,
+    volume_watcher: Option<VolumeWatcher>,
+    ipc_thread: JoinHandle<()>,
+    ipc_shutdown_tx: tokio::sync::broadcast::Sender<()>,
+    config_watcher: ConfigWatcherHandle,
+}
+
+impl Components {
+    /// Load the configuration and start every component in order:
+    /// 1. Load configuration
+    /// 2. Initialize database
+    /// 3. Start the database writer and the job pool, and queue the initial
+    ///    index; the USN monitors start once it completes
+    /// 4. Start the FAT reconciler, the volume watcher and its event handler
+    /// 5. Start the IPC server, and the watcher applying changes to
+    ///    `config.toml` while running
+    ///
+    /// In read-only replica mode (`read_only` in config or the `--read-only`
+    /// start argument) the database is opened read-only and neither the
+    /// indexer nor the real-time updates are started; only IPC searches are
+    /// answered.
+    ///
+    /// # Arguments
+    /// * `arguments` - Start arguments
+    /// * `checkpoint` - Called with each step's number before it runs
+    pub(crate) fn start(arguments: &[OsString], mut checkpoint: impl FnMut(u32) -> Result<()>) -> Result<Self> {
+        // Checkpoint 1: Load configuration
+        checkpoint(1)?;
+        tracing::debug!("Initialization checkpoint 1: loading configuration");
+
+        let loaded = Config::load().unwrap_or_else(|e| {
+            tracing::warn!("Failed to load config, using defaults: {}", e);
+            Config::default()
+        });
+        let data_dir = loaded.data_dir();
+        tracing::info!("Loaded configuration: data_dir={:?}", data_dir);
+
+        if let Err(e) = db::configure_database(loaded.database.clone()) {
+            tracing::warn!("Invalid [database] settings, using defaults: {}", e);
+        }
+
+        // Ensure data directory exists
+        if let Err(e) = std::fs::create_dir_all(&data_dir) {
+            tracing::error!("Failed to create data directory: {}", e);
+            return Err(FFIError::Io(e));
+        }
+
+        // Checkpoint 2: Initialize database
+        checkpoint(2)?;
+        tracing::debug!("Initialization checkpoint 2: opening database");
+
+        let db_path = data_dir.join("index.db");
+        let read_only = loaded.general.read_only || arguments.iter().any(|a| a == "--read-only");
+        if read_only {
+            tracing::info!("Read-only replica mode: indexing disabled, serving searches only");
+        }
+        let readers = loaded.database.reader_connections.clamp(1, DatabaseConfig::MAX_READER_CONNECTIONS);
+        let database = DatabasePool::open(&db_path, read_only, readers)?;
+        tracing::info!("Database opened: {:?} ({} readers)", db_path, readers);
+
+        // Checkpoint 3: Start background indexer
+        checkpoint(3)?;
+        tracing::debug!("Initialization checkpoint 3: starting background indexer");
+
+        // Indexing threads share the configuration, replaced when the file changes
+        let config = SharedConfig::new(loaded);
+
+        // Index writes go through one writer thread owning the writable
+        // connection; scanners and USN monitors send it their batches
+        let db_writer = if read_only {
+            None
+        } else {
+            Some(db::start_db_writer(&db_path)?)
+        };
+
+        // Start the job pool (its workers open their own connections) and queue
+        // the initial index; it also takes rescans of volumes whose USN journal
+        // was lost. The IPC server keeps its connection pool so searches don't
+        // wait on indexing
+        let job_pool = if read_only {
+            None
+        } else {
+            let pool = indexer::start_job_pool(&db_path, config.clone())?;
+            indexer::submit_job(indexer::JobKind::InitialIndex);
+            indexer::submit_job(indexer::JobKind::OfflineCleanup);
+            tracing::info!("Background indexing queued on {} workers", pool.workers());
+            Some(pool)
+        };
+
+        // Checkpoint 4: Start real-time updates
+        checkpoint(4)?;
+        tracing::debug!("Initialization checkpoint 4: starting real-time updates");
+
+        // FAT volumes and network shares are kept current by periodic walks
+        let reconciler = if read_only {
+            None
+        } else {
+            Some(indexer::start_fat_reconciler(config.clone(), db_path.clone()))
+        };
+
+        // Volumes mounted, moved or removed while running are picked up from
+        // device change notifications
+        let volume_watcher = if read_only {
+            None
+        } else {
+            let (watcher, watcher_shutdown_tx, event_rx) = start_volume_watcher();
+            let (handler_shutdown_tx, handler_shutdown_rx) = mpsc::channel();
+            let handler =
+                indexer::start_volume_event_handler(event_rx, config.clone(), db_path.clone(), handler_shutdown_rx);
+            tracing::info!("Volume watcher started");
+            Some(VolumeWatcher {
+                watcher,
+                watcher_shutdown_tx,
+                handler,
+                handler_shutdown_tx,
+            })
+        };
+
+        // Checkpoint 5: Start IPC server
+        checkpoint(5)?;
+        tracing::debug!("Initialization checkpoint 5: starting IPC server");
+
+        let (ipc_thread, ipc_shutdown_tx, server) = start_ipc_server(database, &config.get())?;
+        tracing::info!("IPC server started");
+
+        // Settings changed in config.toml apply without a restart: searches
+        // take the new ones at once, indexing per volume and monitor
+        let monitors = job_pool.as_ref().map(JobPool::monitors);
+        let config_watcher = start_config_watcher(config, move |config, changes| {
+            if let Err(e) = server.apply_search_settings(config) {
+                tracing::error!("Failed to apply reloaded search settings: {}", e);
+            }
+            if let Some(monitors) = &monitors {
+                indexer::apply_config_changes(config, changes, monitors, &db_path);
+            }
+        });
+
+        Ok(Self {
+            read_only,
+            db_writer,
+            job_pool,
+            reconciler,
+            volume_watcher,
+            ipc_thread,
+            ipc_shutdown_tx,
+            config_watcher,
+        })
+    }
+
+    /// Whether this is a read-only replica, with no indexing to pause.
+    pub(crate) fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Carry out pause, continue and power requests until a stop request.
+    ///
+    /// Pausing suspends scans, USN monitors, reconciliation and queued jobs;
+    /// searches keep working.
+    ///
+    /// # Arguments
+    /// * `control_rx` - Requests from the control handler; a closed channel
+    ///   counts as a stop request
+    /// * `report` - Called with the new state after a pause or continue
+    pub(crate) fn serve(&self, control_rx: &Receiver<ControlRequest>, mut report: impl FnMut(WinServiceState)) {
+        // Set while the machine sleeps: whether indexing was already paused
+        let mut suspended: Option<bool> = None;
+        loop {
+            match control_rx.recv() {
+                Ok(ControlRequest::Stop) | Err(_) => return,
+                Ok(ControlRequest::Pause) => {
+                    indexer::pause_indexing();
+                    report(WinServiceState::Paused);
+                }
+                Ok(ControlRequest::Continue) => {
+                    indexer::resume_indexing();
+                    report(WinServiceState::Running);
+                }
+                Ok(ControlRequest::Suspend) => {
+                    // Monitors save their journal position once they see the
+                    // pause; everything committed so far goes into the file now
+                    suspended.get_or_insert(indexer::is_indexing_paused());
+                    indexer::pause_indexing();
+                    if let Some(db_writer) = self.db_writer.as_ref() {
+                        if let Err(e) = db_writer.writer().execute(db::WriteOp::Flush) {
+                            tracing::warn!("Failed to flush the index before suspend: {}", e);
+                        }
+                    }
+                }
+                Ok(ControlRequest::Resume) => {
+                    // Windows sends several resume events; act on the first
+                    if let Some(was_paused) = suspended.take() {
+                        indexer::request_catch_up();
+                        if !was_paused {
+                            indexer::resume_indexing();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Stop everything in reverse order of startup.
+    pub(crate) fn stop(mut self) {
+        // Stop applying configuration changes
+        tracing::info!("Stopping config watcher...");
+        self.config_watcher.stop();
+
+        // Stop answering searches
+        tracing::info!("Signaling IPC server to stop...");
+        let _ = self.ipc_shutdown_tx.send(());
+        if self.ipc_thread.join().is_err() {
+            tracing::warn!("IPC server thread panicked");
+        }
+
+        // Stop reacting to volume changes before the jobs they queue stop
+        if let Some(mut volume_watcher) = self.volume_watcher.take() {
+            tracing::info!("Stopping volume watcher...");
+            let _ = volume_watcher.watcher_shutdown_tx.send(());
+            volume_watcher.watcher.stop();
+            let _ = volume_watcher.handler_shutdown_tx.send(());
+            if volume_watcher.handler.join().is_err() {
+                tracing::warn!("Volume event handler thread panicked");
+            }
+        }
+
+        // Stop the reconciler between volumes
+        if let Some((handle, shutdown_tx)) = self.reconciler.as_mut() {
+            tracing::info!("Stopping FAT reconciler...");
+            let _ = shutdown_tx.send(());
+            handle.stop();
+        }
+
+        // Stop running jobs and wait for the workers to finish, then the USN
+        // monitors the pool started
+        if let Some(job_pool) = self.job_pool.as_mut() {
+            tracing::info!("Stopping job pool...");
+            job_pool.stop();
+        }
+
+        // Apply the last writes once nothing sends more
+        if let Some(db_writer) = self.db_writer.as_mut() {
+            tracing::info!("Stopping database writer...");
+            db_writer.stop();
+        }
+    }
+}
+
+/// Start the IPC server on a dedicated thread with its own tokio runtime.
+///
+/// Returns the thread handle, the sender used to signal shutdown, and the
+/// server, for applying reloaded search settings.
+fn start_ipc_server(
+    database: DatabasePool,
+    config: &Config,
+) -> Result<(JoinHandle<()>, tokio::sync::broadcast::Sender<()>, IpcServer)> {
+    use std::sync::Arc;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| FFIError::Service(format!("Failed to create IPC runtime: {}", e)))?;
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+    let windows_search = crate::search::WindowsSearchFallback::from_config(config);
+    database.set_excluded_attributes(&config.search.hidden_attributes());
+    if let Some(ref fallback) = windows_search {
+        tracing::info!("Windows Search fallback enabled for volumes {:?}", fallback.volumes());
+    }
+
+    let server = IpcServer::new(Arc::new(database))
+        .with_windows_search(windows_search)
+        .with_limits(config.ipc.clone());
+
+    // Claim the pipe before the service reports it started, so startup
+    // fails if another process already owns the name
+    let pipes = {
+        let _runtime = runtime.enter();
+        server.bind()?
+    };
+
+    let running = server.clone();
+    let handle = std::thread::spawn(move || {
+        if let Err(e) = runtime.block_on(running.serve(pipes, shutdown_rx)) {
+            tracing::error!("IPC server failed: {}", e);
+        }
+    });
+
+    Ok((handle, shutdown_tx, server))
+}
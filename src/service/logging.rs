@@ -0,0 +1,246 @@
+//! Service logging.
+//!
+//! The service writes its log to daily files in the `logs` folder of the
+//! data directory, as text or as one JSON object per line (`[logging]`
+//! format), and deletes the oldest files beyond `retention_days`. The level
+//! filter sits behind a reload handle, so [`set_log_level`] (the IPC
+//! `SetLogLevel` command) changes it without a restart.
+
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use chrono::SecondsFormat;
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{self, FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+use super::config::{LogFormat, LoggingConfig};
+use crate::{FFIError, Result};
+
+/// Name of the log files; each day's file gets the date appended.
+pub const LOG_FILE_NAME: &str = "ffi-service.log";
+
+/// Handle for replacing the filter of the subscriber set up by this module.
+static FILTER: Mutex<Option<reload::Handle<EnvFilter, Registry>>> = Mutex::new(None);
+
+/// Log to daily files in `log_dir` for the rest of the process.
+///
+/// One file is written per day; only the newest `retention_days` are kept,
+/// checked now and at every rotation.
+///
+/// # Returns
+/// A guard that writes out buffered lines when dropped; keep it until the
+/// process exits.
+pub fn init_file_logging(log_dir: &Path, config: &LoggingConfig) -> Result<WorkerGuard> {
+    let mut builder = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_NAME);
+    if config.retention_days > 0 {
+        builder = builder.max_log_files(config.retention_days as usize);
+    }
+    let appender = builder
+        .build(log_dir)
+        .map_err(|e| FFIError::Service(format!("Failed to open log file in {:?}: {}", log_dir, e)))?;
+
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    install(config.format, &config.level, writer, false)?;
+    Ok(guard)
+}
+
+/// Log to the console for the rest of the process, filtered by `RUST_LOG`
+/// if set and the configured level otherwise.
+pub fn init_console_logging(config: &LoggingConfig) -> Result<()> {
+    let level = std::env::var("RUST_LOG").unwrap_or_else(|_| config.level.clone());
+    install(config.format, &level, std::io::stdout, true)
+}
+
+/// Change the log filter of this process until it exits.
+///
+/// # Arguments
+/// * `level` - A level or filter directives, as in `[logging] level`
+///
+/// # Errors
+/// Returns an error if `level` is not a valid filter, or if logging was not
+/// set up by this module.
+pub fn set_log_level(level: &str) -> Result<()> {
+    let filter = parse_filter(level)?;
+    let handle = FILTER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .ok_or_else(|| FFIError::Service("Logging is not set up in this process".to_string()))?;
+    handle
+        .reload(filter)
+        .map_err(|e| FFIError::Service(format!("Failed to change the log level: {}", e)))?;
+
+    tracing::info!("Log level set to {}", level);
+    Ok(())
+}
+
+/// Parse a log filter.
+///
+/// A single word must be a level: `EnvFilter` would take a misspelt level
+/// as a module name and quietly log nothing.
+fn parse_filter(level: &str) -> Result<EnvFilter> {
+    let invalid = |e: &dyn std::fmt::Display| FFIError::Config(format!("Invalid log level {:?}: {}", level, e));
+    if !level.contains(['=', ',', ':']) {
+        level.trim().parse::<LevelFilter>().map_err(|e| invalid(&e))?;
+    }
+    EnvFilter::try_new(level).map_err(|e| invalid(&e))
+}
+
+/// Set up the process-wide subscriber with a reloadable filter.
+fn install<W>(format: LogFormat, level: &str, writer: W, ansi: bool) -> Result<()>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let (filter, handle) = reload::Layer::new(parse_filter(level)?);
+    let output = match format {
+        LogFormat::Text => fmt::layer().with_writer(writer).with_ansi(ansi).boxed(),
+        LogFormat::Json => fmt::layer().event_format(JsonFormat).with_writer(writer).boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(output)
+        .try_init()
+        .map_err(|e| FFIError::Service(format!("Failed to set up logging: {}", e)))?;
+
+    *FILTER.lock().unwrap_or_else(PoisonError::into_inner) = Some(handle);
+    Ok(())
+}
+
+/// Formats each event as one JSON object: time, level, target, message,
+/// the event's other fields and the spans it happened in.
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> std::fmt::Result {
+        let metadata = event.metadata();
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::from(chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        );
+        line.insert("level".to_string(), Value::from(metadata.level().as_str()));
+        line.insert("target".to_string(), Value::from(metadata.target()));
+        if let Some(message) = fields.0.remove("message") {
+            line.insert("message".to_string(), message);
+        }
+        if !fields.0.is_empty() {
+            line.insert("fields".to_string(), Value::Object(fields.0));
+        }
+        if let Some(scope) = ctx.event_scope() {
+            let spans: Vec<Value> = scope.from_root().map(|span| Value::from(span.name())).collect();
+            line.insert("spans".to_string(), Value::Array(spans));
+        }
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+/// Collects an event's fields as JSON values.
+#[derive(Default)]
+struct JsonFields(Map<String, Value>);
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.0.insert(field.name().to_string(), Value::from(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::from(format!("{:?}", value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::Arc;
+
+    /// Collects what the formatter writes.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_format() {
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(fmt::layer().event_format(JsonFormat).with_writer(move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("scan");
+            let _entered = span.enter();
+            tracing::warn!(volume = "C:", files = 3u64, done = true, "Scan finished in {}s", 2);
+        });
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["target"], module_path!());
+        assert_eq!(line["message"], "Scan finished in 2s");
+        assert_eq!(line["fields"]["volume"], "C:");
+        assert_eq!(line["fields"]["files"], 3);
+        assert_eq!(line["fields"]["done"], true);
+        assert_eq!(line["spans"], serde_json::json!(["scan"]));
+        assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        for level in ["warn", "DEBUG", "info,ffi::indexer=debug", "ffi::ipc=trace"] {
+            assert!(parse_filter(level).is_ok(), "{}", level);
+        }
+        for level in ["loud", "debugg", "ffi=nope"] {
+            assert!(parse_filter(level).is_err(), "{}", level);
+        }
+    }
+}
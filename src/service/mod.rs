@@ -1,18 +1,31 @@
 //! Windows service lifecycle management.
 //!
 //! This module handles the Windows service lifecycle including:
-//! - Service registration and control
+//! - Service registration and control, and installing it with the SCM
 //! - State transitions (Starting -> Running -> Stopping -> Stopped)
-//! - Configuration loading
+//! - Configuration loading, live reloading and logging
+//! - Exclude matching for the scanners
 //! - Database initialization and indexer management
 //! - Volume mount/unmount detection
 
 pub mod config;
+pub mod config_watcher;
 pub mod control;
+pub mod exclude;
+pub mod install;
+pub mod logging;
+#[cfg(windows)]
+mod lifecycle;
+pub mod selftest;
 pub mod volume_watcher;
 
 pub use config::ServiceConfig;
-pub use control::ServiceState;
+pub use config_watcher::{start_config_watcher, ConfigChanges, ConfigWatcherHandle};
+pub use control::{ControlRequest, ServiceState};
+pub use exclude::ExcludeMatcher;
+pub use install::{install_service, start_service, stop_service, uninstall_service, SERVICE_DESCRIPTION};
+pub use logging::{init_console_logging, init_file_logging, set_log_level};
+pub use selftest::{run_selftest, StageOutcome, StageResult};
 pub use volume_watcher::{VolumeEvent, VolumeWatcherHandle, start_volume_watcher};
 
 #[cfg(windows)]
@@ -44,26 +57,29 @@ pub const SERVICE_DISPLAY_NAME: &str = "FastFileIndex Service";
 /// Run the FFI Windows service.
 ///
 /// This function implements the full service lifecycle:
-/// 1. Create shutdown channel
+/// 1. Create control request channel
 /// 2. Register control handler with SCM
 /// 3. Report StartPending state
-/// 4. Initialize database
-/// 5. Start background indexer
-/// 6. Report Running state
-/// 7. Wait for shutdown signal
-/// 8. Report StopPending state
-/// 9. Stop indexer gracefully
-/// 10. Report Stopped state
+/// 4. Start every component, reporting each startup checkpoint: load
+///    configuration, open the database, start the writer and job pool, the
+///    real-time updates, and the IPC server
+/// 5. Report Running state
+/// 6. Pause and continue indexing on request until the shutdown signal
+/// 7. Report StopPending state
+/// 8. Stop everything in reverse order of startup
+/// 9. Report Stopped state
+///
+/// In read-only replica mode (`read_only` in config or the `--read-only`
+/// start argument) the database is opened read-only and neither the indexer
+/// nor the real-time updates are started; the service only answers IPC
+/// searches.
 #[cfg(windows)]
-pub fn run_service(_arguments: Vec<OsString>) -> Result<()> {
-    use crate::db;
-    use crate::indexer;
-
-    // Create shutdown channel for service control
-    let (shutdown_tx, shutdown_rx) = mpsc::channel();
+pub fn run_service(arguments: Vec<OsString>) -> Result<()> {
+    // Create channel for service control requests
+    let (control_tx, control_rx) = mpsc::channel();
 
     // Create and register the control handler
-    let event_handler = create_event_handler(shutdown_tx);
+    let event_handler = create_event_handler(control_tx);
     let status_handle = service_control_handler::register(SERVICE_NAME, event_handler)
         .map_err(|e| crate::FFIError::Service(format!("Failed to register control handler: {}", e)))?;
 
@@ -84,50 +100,21 @@ pub fn run_service(_arguments: Vec<OsString>) -> Result<()> {
         .map_err(|e| crate::FFIError::Service(format!("Failed to set StartPending status: {}", e)))?;
     tracing::info!("Reported StartPending to SCM");
 
-    // Checkpoint 1: Load configuration
-    status.checkpoint = 1;
-    status_handle
-        .set_service_status(status.clone())
-        .map_err(|e| crate::FFIError::Service(format!("Failed to update checkpoint: {}", e)))?;
-    tracing::debug!("Initialization checkpoint 1: loading configuration");
-
-    let config = ServiceConfig::load();
-    tracing::info!("Loaded configuration: data_dir={:?}", config.data_dir);
-
-    // Ensure data directory exists
-    if let Err(e) = std::fs::create_dir_all(&config.data_dir) {
-        tracing::error!("Failed to create data directory: {}", e);
-        return Err(crate::FFIError::Io(e));
-    }
-
-    // Checkpoint 2: Initialize database
-    status.checkpoint = 2;
-    status_handle
-        .set_service_status(status.clone())
-        .map_err(|e| crate::FFIError::Service(format!("Failed to update checkpoint: {}", e)))?;
-    tracing::debug!("Initialization checkpoint 2: opening database");
-
-    let db_path = config.data_dir.join("index.db");
-    let database = db::open_database(&db_path)?;
-    tracing::info!("Database opened: {:?}", db_path);
-
-    // Checkpoint 3: Start background indexer
-    status.checkpoint = 3;
-    status_handle
-        .set_service_status(status.clone())
-        .map_err(|e| crate::FFIError::Service(format!("Failed to update checkpoint: {}", e)))?;
-    tracing::debug!("Initialization checkpoint 3: starting background indexer");
-
-    // Create shutdown channel for indexer
-    let (indexer_shutdown_tx, indexer_shutdown_rx) = mpsc::channel();
-
-    // Start background indexer
-    let mut indexer_handle = indexer::start_background_indexer(database, indexer_shutdown_rx);
-    tracing::info!("Background indexer started");
+    let components = lifecycle::Components::start(&arguments, |checkpoint| {
+        status.checkpoint = checkpoint;
+        status_handle
+            .set_service_status(status.clone())
+            .map_err(|e| crate::FFIError::Service(format!("Failed to update checkpoint: {}", e)))
+    })?;
 
-    // Report Running - accept STOP and SHUTDOWN controls
+    // Report Running - accept STOP, PRESHUTDOWN and SHUTDOWN controls, and
+    // PAUSE, CONTINUE and power events when there is indexing to suspend
     status.current_state = WinServiceState::Running;
-    status.controls_accepted = ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN;
+    status.controls_accepted =
+        ServiceControlAccept::STOP | ServiceControlAccept::PRESHUTDOWN | ServiceControlAccept::SHUTDOWN;
+    if !components.read_only() {
+        status.controls_accepted |= ServiceControlAccept::PAUSE_CONTINUE | ServiceControlAccept::POWER_EVENT;
+    }
     status.checkpoint = 0;
     status.wait_hint = Duration::default();
     status_handle
@@ -137,7 +124,12 @@ pub fn run_service(_arguments: Vec<OsString>) -> Result<()> {
 
     // Wait for shutdown signal from control handler
     tracing::info!("Waiting for shutdown signal...");
-    shutdown_rx.recv().ok();
+    components.serve(&control_rx, |state| {
+        status.current_state = state;
+        if let Err(e) = status_handle.set_service_status(status.clone()) {
+            tracing::warn!("Failed to report {:?} status: {}", state, e);
+        }
+    });
     tracing::info!("Shutdown signal received");
 
     // Report StopPending
@@ -149,14 +141,7 @@ pub fn run_service(_arguments: Vec<OsString>) -> Result<()> {
         .map_err(|e| crate::FFIError::Service(format!("Failed to set StopPending status: {}", e)))?;
     tracing::info!("Reported StopPending to SCM");
 
-    // Signal indexer to stop
-    tracing::info!("Signaling indexer to stop...");
-    let _ = indexer_shutdown_tx.send(());
-
-    // Wait for indexer to finish (this joins the thread)
-    tracing::info!("Waiting for indexer to finish...");
-    indexer_handle.stop();
-    tracing::info!("Indexer stopped");
+    components.stop();
 
     // Note: Database is closed when dropped (when run_service returns)
 
@@ -171,9 +156,51 @@ pub fn run_service(_arguments: Vec<OsString>) -> Result<()> {
     Ok(())
 }
 
+/// Run the service as a normal console process until Ctrl+C.
+///
+/// Starts and stops the same components in the same order as
+/// [`run_service`], without registering with the SCM, so indexing can be
+/// debugged with its log on the console. Pausing is left to the IPC
+/// commands.
+///
+/// # Arguments
+/// * `arguments` - Start arguments; `--read-only` runs a replica
+#[cfg(windows)]
+pub fn run_console(arguments: Vec<OsString>) -> Result<()> {
+    let (control_tx, control_rx) = mpsc::channel();
+
+    // Ctrl+C stops the process the way the SCM stops the service
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| crate::FFIError::Service(format!("Failed to create signal runtime: {}", e)))?;
+    std::thread::spawn(move || {
+        if runtime.block_on(tokio::signal::ctrl_c()).is_ok() {
+            tracing::info!("Received Ctrl+C");
+        }
+        control_tx.send(ControlRequest::Stop).ok();
+    });
+
+    let components = lifecycle::Components::start(&arguments, |_| Ok(()))?;
+    tracing::info!("Running in the console, press Ctrl+C to stop");
+
+    components.serve(&control_rx, |state| tracing::info!("Service state: {:?}", state));
+    components.stop();
+    tracing::info!("Stopped");
+
+    Ok(())
+}
+
 /// Stub for non-Windows platforms (for development/testing).
 #[cfg(not(windows))]
 pub fn run_service(_arguments: Vec<OsString>) -> Result<()> {
     tracing::warn!("run_service called on non-Windows platform - this is a no-op");
     Ok(())
 }
+
+/// Stub for non-Windows platforms (for development/testing).
+#[cfg(not(windows))]
+pub fn run_console(_arguments: Vec<OsString>) -> Result<()> {
+    tracing::warn!("run_console called on non-Windows platform - this is a no-op");
+    Ok(())
+}
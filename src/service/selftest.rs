@@ -0,0 +1,319 @@
+//! End-to-end self-test of the indexing pipeline.
+//!
+//! `ffi-service selftest` creates, renames and deletes a file in a test
+//! directory and waits for each change to show up in searches sent to the
+//! running service, the same way the search UI would see it. Each stage
+//! passes or fails on its own, so the first failing stage points at where
+//! a new file gets lost: the service, an exclude, or change monitoring.
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(windows)]
+use crate::ipc::IpcClient;
+use crate::ipc::{FileResult, ResultSource, PIPE_NAME};
+use crate::service::config::Config;
+use crate::{FFIError, Result};
+
+/// How often searches are repeated while waiting for a change.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Extra time allowed on top of the slowest USN poll interval.
+const WAIT_MARGIN: Duration = Duration::from_secs(30);
+
+/// Outcome of one self-test stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageOutcome {
+    /// The stage worked
+    Pass,
+    /// The stage failed; see the detail
+    Fail,
+    /// Not run because an earlier stage failed
+    Skipped,
+}
+
+impl StageOutcome {
+    /// Label printed in the report.
+    pub fn label(&self) -> &'static str {
+        match self {
+            StageOutcome::Pass => "PASS",
+            StageOutcome::Fail => "FAIL",
+            StageOutcome::Skipped => "SKIP",
+        }
+    }
+}
+
+/// Result of one self-test stage.
+#[derive(Debug, Clone)]
+pub struct StageResult {
+    /// Stage name (e.g. `create`)
+    pub stage: &'static str,
+    /// Whether it passed
+    pub outcome: StageOutcome,
+    /// What was checked, or why it failed
+    pub detail: String,
+}
+
+/// Stages in the order they run.
+const STAGES: [&str; 5] = ["service", "directory", "create", "rename", "delete"];
+
+/// Run the self-test against the running service.
+///
+/// # Arguments
+/// * `config` - Service configuration, for excludes and poll intervals
+/// * `dir` - Test directory on an indexed NTFS volume (default: `selftest` in the data directory)
+///
+/// # Returns
+/// One result per stage, in order.
+pub fn run_selftest(config: &Config, dir: Option<&Path>) -> Vec<StageResult> {
+    let dir = dir.map(Path::to_path_buf).unwrap_or_else(|| config.data_dir().join("selftest"));
+    let timeout = Duration::from_secs(config.general.usn_poll_max_secs) + WAIT_MARGIN;
+
+    let search = match connect_service() {
+        Ok(search) => search,
+        Err(e) => return report(vec![(StageOutcome::Fail, e.to_string())]),
+    };
+    let mut results = vec![(StageOutcome::Pass, format!("Service reachable at {}", PIPE_NAME))];
+    results.extend(run_file_stages(&dir, config, timeout, search));
+    report(results)
+}
+
+/// Connect to the running service and return a name search over IPC.
+#[cfg(windows)]
+fn connect_service() -> Result<impl FnMut(&str) -> Result<Vec<FileResult>>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| FFIError::Ipc(format!("Failed to start async runtime: {}", e)))?;
+
+    // Opening a pipe registers it with the runtime's reactor
+    let client = IpcClient::new();
+    if !runtime.block_on(async { client.is_service_available() }) {
+        return Err(FFIError::Ipc(format!(
+            "Service not reachable at {}; is FFIService running?",
+            PIPE_NAME
+        )));
+    }
+
+    Ok(move |name: &str| -> Result<Vec<FileResult>> {
+        Ok(runtime.block_on(client.search(name, 100))?.results)
+    })
+}
+
+/// Name search function, for the non-Windows stub.
+#[cfg(not(windows))]
+type SearchFn = fn(&str) -> Result<Vec<FileResult>>;
+
+/// The service only runs on Windows.
+#[cfg(not(windows))]
+fn connect_service() -> Result<SearchFn> {
+    Err(FFIError::Ipc(format!(
+        "Service not reachable at {}: named pipes require Windows",
+        PIPE_NAME
+    )))
+}
+
+/// Run the directory and file stages, searching with `search`.
+fn run_file_stages(
+    dir: &Path,
+    config: &Config,
+    timeout: Duration,
+    mut search: impl FnMut(&str) -> Result<Vec<FileResult>>,
+) -> Vec<(StageOutcome, String)> {
+    let mut results = Vec::new();
+
+    let display = dir.display().to_string();
+    let volume = display.get(..2).unwrap_or_default();
+    if config.exclude_matcher(volume).should_exclude(&display, true) {
+        results.push((
+            StageOutcome::Fail,
+            format!("{} is excluded from indexing; choose another directory", display),
+        ));
+        return results;
+    }
+    if let Err(e) = fs::create_dir_all(dir) {
+        results.push((StageOutcome::Fail, format!("Cannot create {}: {}", display, e)));
+        return results;
+    }
+    results.push((StageOutcome::Pass, format!("Using {}", display)));
+
+    // Unique names, so earlier runs can't satisfy the searches
+    let token = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let created = format!("ffiselftest{}created.txt", token);
+    let renamed = format!("ffiselftest{}renamed.txt", token);
+    let created_path = dir.join(&created);
+    let renamed_path = dir.join(&renamed);
+
+    // Each change, then the name searches must find and the one they must not
+    type Change<'a> = &'a dyn Fn() -> std::io::Result<()>;
+    let stages: [(&str, Change, Option<&str>, Option<&str>); 3] = [
+        ("create", &|| fs::write(&created_path, b"FFI self-test"), Some(&created), None),
+        ("rename", &|| fs::rename(&created_path, &renamed_path), Some(&renamed), Some(&created)),
+        ("delete", &|| fs::remove_file(&renamed_path), None, Some(&renamed)),
+    ];
+
+    for (stage, change, present, absent) in stages {
+        if let Err(e) = change() {
+            results.push((StageOutcome::Fail, format!("Failed to {} the test file: {}", stage, e)));
+            break;
+        }
+
+        let outcome = wait_until(timeout, || {
+            let mut found = |name: &str| -> Result<bool> {
+                Ok(search(name)?
+                    .iter()
+                    .any(|r| r.source == ResultSource::Index && r.name.eq_ignore_ascii_case(name)))
+            };
+            Ok(present.map_or(Ok(true), &mut found)? && !absent.map_or(Ok(false), &mut found)?)
+        });
+
+        match outcome {
+            Ok(elapsed) => results.push((
+                StageOutcome::Pass,
+                format!("Search reflected the {} after {:.1}s", stage, elapsed.as_secs_f64()),
+            )),
+            Err(e) => {
+                results.push((
+                    StageOutcome::Fail,
+                    format!(
+                        "Search did not reflect the {}: {}. Check that the volume is indexed \
+                         and its USN monitor is running (see the service log)",
+                        stage, e
+                    ),
+                ));
+                break;
+            }
+        }
+    }
+
+    let _ = fs::remove_file(&created_path);
+    let _ = fs::remove_file(&renamed_path);
+    let _ = fs::remove_dir(dir);
+    results
+}
+
+/// Repeat `check` until it returns true or `timeout` passes.
+///
+/// # Returns
+/// The time it took, or an error once the timeout passed or a check failed.
+fn wait_until(timeout: Duration, mut check: impl FnMut() -> Result<bool>) -> Result<Duration> {
+    let start = Instant::now();
+    loop {
+        if check()? {
+            return Ok(start.elapsed());
+        }
+        if start.elapsed() >= timeout {
+            return Err(FFIError::Search(format!(
+                "not seen within {}s",
+                timeout.as_secs()
+            )));
+        }
+        thread::sleep(POLL_INTERVAL.min(timeout));
+    }
+}
+
+/// Name the results in stage order, marking stages that never ran as skipped.
+fn report(results: Vec<(StageOutcome, String)>) -> Vec<StageResult> {
+    STAGES
+        .iter()
+        .enumerate()
+        .map(|(i, stage)| match results.get(i) {
+            Some((outcome, detail)) => StageResult {
+                stage,
+                outcome: *outcome,
+                detail: detail.clone(),
+            },
+            None => StageResult {
+                stage,
+                outcome: StageOutcome::Skipped,
+                detail: "Skipped after an earlier failure".to_string(),
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Search that sees the directory as it is on disk, like a perfect index.
+    fn search_dir(dir: &Path) -> impl FnMut(&str) -> Result<Vec<FileResult>> + '_ {
+        move |query| {
+            let results = fs::read_dir(dir)
+                .map(|entries| entries.flatten().collect::<Vec<_>>())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|name| name.contains(query))
+                .map(|name| FileResult {
+                    id: 1,
+                    path: name.clone(),
+                    name,
+                    size: 0,
+                    modified: 0,
+                    is_dir: false,
+                    duplicates: 0,
+                    source: ResultSource::Index,
+                    rank: None,
+                    volume_state: None,
+                    original_path: None,
+                })
+                .collect();
+            Ok(results)
+        }
+    }
+
+    #[test]
+    fn test_file_stages_pass_when_index_follows() {
+        let dir = temp_dir("ffi_selftest_pass");
+        let results = run_file_stages(&dir, &Config::default(), Duration::from_secs(1), search_dir(&dir));
+
+        let outcomes: Vec<StageOutcome> = results.iter().map(|(o, _)| *o).collect();
+        assert_eq!(outcomes, vec![StageOutcome::Pass; 4], "{:?}", results);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_file_stages_report_first_failure() {
+        let dir = temp_dir("ffi_selftest_fail");
+        let results = report(
+            std::iter::once((StageOutcome::Pass, String::new()))
+                .chain(run_file_stages(&dir, &Config::default(), Duration::from_millis(100), |_| {
+                    Ok(Vec::new())
+                }))
+                .collect(),
+        );
+
+        let outcomes: Vec<(&str, StageOutcome)> = results.iter().map(|r| (r.stage, r.outcome)).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("service", StageOutcome::Pass),
+                ("directory", StageOutcome::Pass),
+                ("create", StageOutcome::Fail),
+                ("rename", StageOutcome::Skipped),
+                ("delete", StageOutcome::Skipped),
+            ]
+        );
+        assert!(!dir.exists());
+
+        // Excluded directories fail before any file is created
+        let mut config = Config::default();
+        config.exclude.paths = vec![dir.display().to_string()];
+        let results = run_file_stages(&dir, &config, Duration::from_millis(100), |_| Ok(Vec::new()));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, StageOutcome::Fail);
+    }
+}
@@ -0,0 +1,103 @@
+//! Accessibility: high-contrast theme and screen reader metadata.
+//!
+//! Narrator and NVDA read the popup through egui's AccessKit integration.
+//! Most widgets describe themselves; these helpers name the ones that
+//! don't, give result rows list item roles, and mark the status line as a
+//! live region so search outcomes are announced.
+
+use eframe::egui::{self, accesskit, Color32, Id, Stroke, Theme, Visuals};
+
+/// Visuals with maximum contrast: white on black with yellow outlines.
+pub fn high_contrast_visuals() -> Visuals {
+    let mut visuals = Visuals::dark();
+    let outline = Stroke::new(1.0, Color32::WHITE);
+    let highlight = Stroke::new(2.0, Color32::YELLOW);
+
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.window_stroke = outline;
+    visuals.faint_bg_color = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.code_bg_color = Color32::BLACK;
+    visuals.hyperlink_color = Color32::from_rgb(0x00, 0xFF, 0xFF);
+    visuals.warn_fg_color = Color32::YELLOW;
+    visuals.error_fg_color = Color32::from_rgb(0xFF, 0x80, 0x80);
+    visuals.selection.bg_fill = Color32::from_rgb(0x00, 0x00, 0xB0);
+    visuals.selection.stroke = highlight;
+    visuals.text_cursor.stroke = highlight;
+
+    for widget in [
+        &mut visuals.widgets.noninteractive,
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widget.bg_fill = Color32::BLACK;
+        widget.weak_bg_fill = Color32::BLACK;
+        widget.bg_stroke = outline;
+        widget.fg_stroke = Stroke::new(1.5, Color32::WHITE);
+    }
+    for widget in [
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widget.bg_stroke = highlight;
+    }
+
+    visuals
+}
+
+/// Switch between the high-contrast theme and egui's default themes.
+pub fn apply_theme(ctx: &egui::Context, high_contrast: bool) {
+    if high_contrast {
+        ctx.set_visuals_of(Theme::Dark, high_contrast_visuals());
+        ctx.set_visuals_of(Theme::Light, high_contrast_visuals());
+    } else {
+        ctx.set_visuals_of(Theme::Dark, Visuals::dark());
+        ctx.set_visuals_of(Theme::Light, Visuals::light());
+    }
+}
+
+/// Give a widget an accessible name (e.g. a combo box that only shows its value).
+pub fn set_label(ctx: &egui::Context, id: Id, label: &str) {
+    ctx.accesskit_node_builder(id, |node| node.set_label(label));
+}
+
+/// Mark a widget as a polite live region, so changes to it are announced.
+pub fn mark_live(ctx: &egui::Context, id: Id) {
+    ctx.accesskit_node_builder(id, |node| node.set_live(accesskit::Live::Polite));
+}
+
+/// Describe a result row as a selectable list item.
+pub fn mark_list_item(ctx: &egui::Context, id: Id, selected: bool) {
+    ctx.accesskit_node_builder(id, |node| {
+        node.set_role(accesskit::Role::ListItem);
+        node.set_selected(selected);
+    });
+}
+
+/// Point a text box at the list item its arrow keys select.
+///
+/// Focus stays in the search box while arrows move the selection, so the
+/// selected row is exposed as the box's active descendant instead.
+pub fn set_active_descendant(ctx: &egui::Context, id: Id, descendant: Id) {
+    ctx.accesskit_node_builder(id, |node| {
+        node.set_active_descendant(accesskit::NodeId::from(descendant.value()));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_high_contrast_visuals() {
+        let visuals = high_contrast_visuals();
+        assert_eq!(visuals.panel_fill, Color32::BLACK);
+        assert_eq!(visuals.text_color(), Color32::WHITE);
+        assert_eq!(visuals.widgets.hovered.bg_stroke.color, Color32::YELLOW);
+    }
+}
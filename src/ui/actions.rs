@@ -3,12 +3,138 @@
 //! Provides operations that can be performed on search results:
 //! - Open file with default application
 //! - Reveal file in Explorer/Finder
-//! - Copy file path to clipboard
+//! - Copy file path to clipboard (plain, PowerShell-quoted, file:// URI, or as a file)
+//! - Open with a chosen application, delete to the Recycle Bin, show properties
+//!   (Windows shell dialogs, offered from the result context menu)
+//! - Edit the tags of a result (see [`super::tags`])
 
 use std::path::Path;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{FFIError, Result};
 
+/// Clipboard format used when copying a result path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardFormat {
+    /// Plain path text (`C:\Docs\a.txt`)
+    #[default]
+    Text,
+    /// Single-quoted for PowerShell (`'C:\Bob''s\a.txt'`)
+    PowerShell,
+    /// `file://` URI with percent-encoding (`file:///C:/My%20Docs/a.txt`)
+    Uri,
+    /// The file itself (CF_HDROP on Windows), for pasting into Explorer
+    Files,
+}
+
+impl ClipboardFormat {
+    /// Human-readable label for status messages.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClipboardFormat::Text => "Path",
+            ClipboardFormat::PowerShell => "PowerShell path",
+            ClipboardFormat::Uri => "File URI",
+            ClipboardFormat::Files => "File",
+        }
+    }
+}
+
+/// Action offered by a result row's context menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultAction {
+    /// Open with the default application
+    Open,
+    /// Pick the application to open with
+    OpenWith,
+    /// Show in Explorer with the entry selected
+    Reveal,
+    /// Copy the path as text
+    CopyPath,
+    /// Copy the file itself, for pasting into Explorer
+    CopyFile,
+    /// Move to the Recycle Bin
+    Delete,
+    /// Show the shell's properties dialog
+    Properties,
+    /// Edit the tags the service keeps for the entry
+    Tags,
+}
+
+impl ResultAction {
+    /// Actions in menu order.
+    pub const ALL: [ResultAction; 8] = [
+        ResultAction::Open,
+        ResultAction::OpenWith,
+        ResultAction::Reveal,
+        ResultAction::CopyPath,
+        ResultAction::CopyFile,
+        ResultAction::Delete,
+        ResultAction::Properties,
+        ResultAction::Tags,
+    ];
+
+    /// Menu label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ResultAction::Open => "Open",
+            ResultAction::OpenWith => "Open With...",
+            ResultAction::Reveal => "Reveal in Explorer",
+            ResultAction::CopyPath => "Copy Path",
+            ResultAction::CopyFile => "Copy File",
+            ResultAction::Delete => "Delete",
+            ResultAction::Properties => "Properties",
+            ResultAction::Tags => "Tags...",
+        }
+    }
+
+    /// Whether the action applies to a file (false) or folder (true).
+    pub fn applies_to(&self, is_dir: bool) -> bool {
+        !(is_dir && *self == ResultAction::OpenWith)
+    }
+
+    /// Whether a separator goes above this action in the menu.
+    pub fn starts_group(&self) -> bool {
+        matches!(self, ResultAction::CopyPath | ResultAction::Delete | ResultAction::Tags)
+    }
+}
+
+/// Format a path as clipboard text.
+///
+/// [`ClipboardFormat::Files`] has no text form and falls back to the plain path.
+pub fn format_path(path: &Path, format: ClipboardFormat) -> String {
+    let path = path.to_string_lossy();
+    match format {
+        ClipboardFormat::Text | ClipboardFormat::Files => path.to_string(),
+        ClipboardFormat::PowerShell => format!("'{}'", path.replace('\'', "''")),
+        ClipboardFormat::Uri => path_to_file_uri(&path),
+    }
+}
+
+/// Convert a Windows or Unix path into a `file://` URI.
+///
+/// UNC paths (`\\server\share`) become `file://server/share`.
+fn path_to_file_uri(path: &str) -> String {
+    let normalized = path.replace('\\', "/");
+    let (prefix, rest) = match normalized.strip_prefix("//") {
+        Some(unc) => ("file://", unc.to_string()),
+        None if normalized.starts_with('/') => ("file://", normalized),
+        None => ("file:///", normalized),
+    };
+
+    let mut uri = String::from(prefix);
+    for byte in rest.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
 /// Open a file or folder with the default application.
 ///
 /// For files, opens with the registered application (e.g., .pdf opens in PDF reader).
@@ -52,31 +178,147 @@ pub fn reveal_in_explorer(path: &Path) -> Result<()> {
     })
 }
 
-/// Copy the full file path to the system clipboard.
+/// Copy a file path to the system clipboard in the given format.
+///
+/// Text formats replace the clipboard contents with the formatted path;
+/// [`ClipboardFormat::Files`] places the file itself (CF_HDROP on Windows)
+/// so it can be pasted into Explorer or attached in other applications.
 ///
 /// # Arguments
 /// * `path` - Path to copy
+/// * `format` - Clipboard format to use
 ///
 /// # Errors
 /// Returns error if clipboard access fails.
-pub fn copy_to_clipboard(path: &Path) -> Result<()> {
-    tracing::info!("Copying path to clipboard: {:?}", path);
+pub fn copy_to_clipboard(path: &Path, format: ClipboardFormat) -> Result<()> {
+    tracing::info!("Copying path to clipboard as {:?}: {:?}", format, path);
 
     let mut clipboard = arboard::Clipboard::new().map_err(|e| {
-        FFIError::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to access clipboard: {}", e),
-        ))
+        FFIError::Io(std::io::Error::other(format!("Failed to access clipboard: {}", e)))
     })?;
 
-    clipboard
-        .set_text(path.to_string_lossy().to_string())
-        .map_err(|e| {
-            FFIError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to set clipboard text: {}", e),
-            ))
-        })
+    let result = match format {
+        ClipboardFormat::Files => clipboard.set().file_list(&[path]),
+        _ => clipboard.set_text(format_path(path, format)),
+    };
+
+    result.map_err(|e| {
+        FFIError::Io(std::io::Error::other(format!("Failed to set clipboard contents: {}", e)))
+    })
+}
+
+/// Show the "Open with" dialog to pick the application for a file.
+///
+/// # Arguments
+/// * `path` - Path to the file to open
+///
+/// # Errors
+/// Returns error if the dialog can't be shown (always on non-Windows).
+pub fn open_with(path: &Path) -> Result<()> {
+    tracing::info!("Opening file with: {:?}", path);
+
+    shell_verb(path, "openas").map_err(|e| {
+        FFIError::Io(std::io::Error::other(format!("Failed to open with: {}", e)))
+    })
+}
+
+/// Show the shell's properties dialog for a file or folder.
+///
+/// # Arguments
+/// * `path` - Path to the file or folder
+///
+/// # Errors
+/// Returns error if the dialog can't be shown (always on non-Windows).
+pub fn show_properties(path: &Path) -> Result<()> {
+    tracing::info!("Showing properties: {:?}", path);
+
+    shell_verb(path, "properties").map_err(|e| {
+        FFIError::Io(std::io::Error::other(format!("Failed to show properties: {}", e)))
+    })
+}
+
+/// Move a file or folder to the Recycle Bin.
+///
+/// Nothing is deleted permanently: if the entry can't be recycled (e.g. it
+/// is too large for the Recycle Bin), the shell asks first.
+///
+/// # Arguments
+/// * `path` - Path to the file or folder to delete
+///
+/// # Errors
+/// Returns error if the entry could not be recycled or the user cancelled
+/// (always on non-Windows).
+#[cfg(windows)]
+pub fn delete_to_recycle_bin(path: &Path) -> Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::UI::Shell::{
+        SHFileOperationW, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_NOERRORUI, FOF_WANTNUKEWARNING,
+        FO_DELETE, SHFILEOPSTRUCTW,
+    };
+
+    tracing::info!("Deleting to Recycle Bin: {:?}", path);
+
+    // The source list is double-null-terminated
+    let from: Vec<u16> = path.as_os_str().encode_wide().chain([0, 0]).collect();
+    let mut op = SHFILEOPSTRUCTW {
+        wFunc: FO_DELETE,
+        pFrom: PCWSTR::from_raw(from.as_ptr()),
+        fFlags: (FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_WANTNUKEWARNING).0 as u16,
+        ..Default::default()
+    };
+    let code = unsafe { SHFileOperationW(&mut op) };
+
+    if code != 0 {
+        return Err(FFIError::Io(std::io::Error::other(format!(
+            "Failed to delete {}: error {:#x}",
+            path.display(),
+            code
+        ))));
+    }
+    if op.fAnyOperationsAborted.as_bool() {
+        return Err(FFIError::Io(std::io::Error::other("Delete was cancelled")));
+    }
+    Ok(())
+}
+
+/// Move a file or folder to the Recycle Bin (unsupported on non-Windows).
+#[cfg(not(windows))]
+pub fn delete_to_recycle_bin(path: &Path) -> Result<()> {
+    Err(FFIError::Io(std::io::Error::other(format!(
+        "Failed to delete {}: the Recycle Bin is only supported on Windows",
+        path.display()
+    ))))
+}
+
+/// Invoke a shell verb ("openas", "properties", ...) on a path.
+#[cfg(windows)]
+fn shell_verb(path: &Path, verb: &str) -> std::io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::UI::Shell::{ShellExecuteExW, SEE_MASK_INVOKEIDLIST, SHELLEXECUTEINFOW};
+    use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    let file: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let verb: Vec<u16> = verb.encode_utf16().chain(std::iter::once(0)).collect();
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_INVOKEIDLIST,
+        lpVerb: PCWSTR::from_raw(verb.as_ptr()),
+        lpFile: PCWSTR::from_raw(file.as_ptr()),
+        nShow: SW_SHOWNORMAL.0,
+        ..Default::default()
+    };
+    unsafe { ShellExecuteExW(&mut info) }.map_err(std::io::Error::other)
+}
+
+/// Invoke a shell verb (unsupported on non-Windows).
+#[cfg(not(windows))]
+fn shell_verb(_path: &Path, verb: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("the \"{}\" shell action is only supported on Windows", verb),
+    ))
 }
 
 #[cfg(test)]
@@ -100,6 +342,55 @@ mod tests {
         let _ = reveal_in_explorer(&path);
     }
 
+    #[test]
+    fn test_format_path_powershell() {
+        let path = PathBuf::from(r"C:\Users\Bob's Files\a.txt");
+        assert_eq!(
+            format_path(&path, ClipboardFormat::PowerShell),
+            r"'C:\Users\Bob''s Files\a.txt'"
+        );
+    }
+
+    #[test]
+    fn test_format_path_uri() {
+        assert_eq!(
+            format_path(&PathBuf::from(r"C:\My Docs\report #1.pdf"), ClipboardFormat::Uri),
+            "file:///C:/My%20Docs/report%20%231.pdf"
+        );
+        assert_eq!(
+            format_path(&PathBuf::from(r"\\server\share\a.txt"), ClipboardFormat::Uri),
+            "file://server/share/a.txt"
+        );
+        assert_eq!(
+            format_path(&PathBuf::from("/home/bob/a b.txt"), ClipboardFormat::Uri),
+            "file:///home/bob/a%20b.txt"
+        );
+    }
+
+    #[test]
+    fn test_clipboard_format_serialization() {
+        assert_eq!(serde_json::to_string(&ClipboardFormat::PowerShell).unwrap(), r#""powershell""#);
+        assert_eq!(ClipboardFormat::default(), ClipboardFormat::Text);
+    }
+
+    #[test]
+    fn test_result_actions() {
+        assert_eq!(ResultAction::ALL[0], ResultAction::Open);
+        assert!(ResultAction::OpenWith.applies_to(false));
+        assert!(!ResultAction::OpenWith.applies_to(true));
+        assert!(ResultAction::Delete.applies_to(true));
+
+        // Nothing is deleted where there is no Recycle Bin to restore it from
+        #[cfg(not(windows))]
+        {
+            let temp = std::env::temp_dir().join("ffi_test_recycle.txt");
+            std::fs::write(&temp, "keep").unwrap();
+            assert!(delete_to_recycle_bin(&temp).is_err());
+            assert!(temp.exists());
+            let _ = std::fs::remove_file(&temp);
+        }
+    }
+
     // Note: Clipboard tests are difficult to run in CI environments
     // as they require a display/clipboard manager
 }
@@ -6,22 +6,84 @@
 use std::sync::mpsc::Receiver;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use eframe::egui;
 use tokio::runtime::Handle;
 
-use crate::ipc::IpcClient;
-use crate::ipc::protocol::{FileResult, SearchResponse};
-use crate::ui::results::ResultsView;
-use crate::ui::actions;
+use crate::db::SavedSearch;
+use crate::ipc::{Command, CommandResponse, IpcClient, ServiceStatus};
+use crate::ipc::protocol::{merge_ranked, FileResult, SearchFrame, SearchRequest};
+use crate::search::{parse_query, syntax_help, Filter, Ranking, SortField, SortSpec, SyntaxHelp};
+use crate::service::config::{Config, UiConfig, DEFAULT_SORT_SCOPE};
+use crate::ui::accessibility;
+use crate::ui::help;
+use crate::ui::history::{HistoryEntry, NavigationHistory};
+use crate::ui::results::{format_count, format_date, format_size, reveal_offset, ResultsView};
+use crate::ui::export::ExportView;
+use crate::ui::first_run::FirstRunView;
+use crate::ui::hotkey::{HotkeyCombo, HotkeyManager};
+use crate::ui::icons::IconCache;
+use crate::ui::settings::SettingsView;
+use crate::ui::state::{PopupMode, PopupState};
+use crate::ui::suggestions::{apply_suggestion, suggest_filters};
+use crate::ui::tags::TagsView;
+use crate::ui::usage::UsageView;
+use crate::ui::tray::{Tray, TrayAction};
+use crate::ui::actions::{self, ClipboardFormat, ResultAction};
 
 /// Debounce duration for search queries (100ms).
 const SEARCH_DEBOUNCE_MS: u64 = 100;
 
+/// How often the service status shown by the tray icon is refreshed.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the config file is checked for a changed hotkey.
+const CONFIG_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
 /// Maximum results to fetch per query.
 const MAX_RESULTS: usize = 100;
 
+/// Days of recently modified files shown while the search box is empty.
+const RECENT_DAYS: i64 = 7;
+
+/// Keys that apply the first nine filter suggestions (with Alt).
+const SUGGESTION_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+/// Maximum height of the F1 help window's scrollable content.
+const HELP_MAX_HEIGHT: f32 = 420.0;
+
+/// Keyboard shortcuts listed in the F1 help window.
+const SHORTCUTS: &[(&str, &str)] = &[
+    ("Up / Down", "Select result"),
+    ("Enter", "Open selected result"),
+    ("Ctrl+Enter", "Browse into selected folder"),
+    ("Alt+Left / Alt+Right", "Back / forward in history"),
+    ("Ctrl+Shift+C", "Copy path (add Alt to copy as file)"),
+    ("Ctrl+Shift+E", "Reveal in Explorer"),
+    ("Ctrl+Shift+S", "Export results"),
+    ("Alt+1 to Alt+9", "Apply filter suggestion"),
+    ("Alt+S", "Next sort field"),
+    ("Alt+D", "Toggle sort direction"),
+    ("Alt+R", "Next ranking"),
+    ("Alt+L", "Toggle show all links"),
+    ("Ctrl+,", "Settings"),
+    ("Ctrl+Shift+H", "Toggle high contrast"),
+    ("Tab / Shift+Tab", "Move between controls"),
+    ("F1", "Show search syntax and shortcuts"),
+    ("Esc", "Close window, or hide the popup"),
+];
+
 /// The main search application.
 pub struct SearchApp {
     /// Current search query text.
@@ -41,6 +103,12 @@ pub struct SearchApp {
     runtime: Handle,
     /// Receiver for hotkey events.
     hotkey_rx: Receiver<()>,
+    /// Global hotkey registration (None if no hotkey is registered).
+    hotkey: Option<HotkeyManager>,
+    /// Modification time of the config file when last read.
+    config_modified: Option<SystemTime>,
+    /// When the config file was last checked for changes.
+    last_config_check: Option<Instant>,
     /// Shared visibility state.
     visible: Arc<AtomicBool>,
     /// Search status message.
@@ -49,22 +117,120 @@ pub struct SearchApp {
     total_count: usize,
     /// Last search duration in milliseconds.
     search_time_ms: u64,
-    /// Pending search results (from async task).
-    pending_results: Option<std::sync::mpsc::Receiver<SearchResponse>>,
+    /// Pending search results, streamed in frames (from async task).
+    pending_results: Option<std::sync::mpsc::Receiver<SearchFrame>>,
+    /// Whether the shown results belong to the previous search, replaced
+    /// when the pending search's first results arrive.
+    results_stale: bool,
+    /// Whether the results are the recent files shown for an empty query.
+    showing_recent: bool,
+    /// Task running the pending search; aborting it closes its connection,
+    /// which cancels the search in the service.
+    search_task: Option<tokio::task::JoinHandle<()>>,
     /// Whether this is the first frame (for initial focus).
     first_frame: bool,
+    /// Back/forward navigation history of queries and browsed folders.
+    history: NavigationHistory,
+    /// Whether the next executed search was restored from history
+    /// (and so must not be recorded again).
+    restoring_history: bool,
+    /// Filter suggestion chips with their result counts (None until the service replies).
+    suggestions: Vec<(String, Option<usize>)>,
+    /// Saved searches, pinned as chips above the suggestions.
+    saved_searches: Vec<SavedSearch>,
+    /// Pending reply to a saved search command, and whether its outcome is
+    /// reported in the status bar (from async task).
+    pending_saved: Option<Receiver<(bool, crate::Result<CommandResponse>)>>,
+    /// Name being typed for the current query, while saving it.
+    save_name: Option<String>,
+    /// UI preferences loaded from config (sort per scope).
+    ui_config: UiConfig,
+    /// Scope key the current sort belongs to.
+    sort_scope: String,
+    /// Primary and secondary sort (primary is always set).
+    sort: [Option<SortSpec>; 2],
+    /// Show every link to a file instead of one row per path.
+    show_all_links: bool,
+    /// How the service orders each page of results.
+    ranking: Ranking,
+    /// Settings window (exclusion suggestions).
+    settings: SettingsView,
+    /// Export window (saves all matches of the query to a file).
+    export: ExportView,
+    /// Tag editor of a result.
+    tags: TagsView,
+    /// Disk usage window (space taken below the query's scope).
+    usage: UsageView,
+    /// Volume selection shown while no volume is configured.
+    first_run: FirstRunView,
+    /// File type icons of the result rows.
+    icons: IconCache,
+    /// Current scroll offset of the results list.
+    scroll_offset: f32,
+    /// Scroll offset to apply to the results list on the next frame.
+    scroll_to: Option<f32>,
+    /// Saved selection and scroll to restore once the resumed query's results arrive.
+    pending_restore: Option<PopupState>,
+    /// Whether the popup state should be written to storage this frame.
+    save_state_pending: bool,
+    /// Whether the results list should scroll to the selected row.
+    reveal_selected: bool,
+    /// Visible height of the results list in the last frame.
+    results_height: f32,
+    /// Whether the help window (search syntax and shortcuts) is shown.
+    show_help: bool,
+    /// Search syntax reported by the service (None until requested).
+    syntax: Option<SyntaxHelp>,
+    /// Pending search syntax reply (from async task).
+    pending_syntax: Option<Receiver<SyntaxHelp>>,
+    /// Tray icon (None if it could not be created).
+    tray: Option<Tray>,
+    /// Last status reply from the service (None if it did not answer).
+    service_status: Option<ServiceStatus>,
+    /// Pending status reply (from async task).
+    pending_status: Option<Receiver<Option<ServiceStatus>>>,
+    /// When the service status was last requested.
+    last_status_poll: Option<Instant>,
+    /// Whether the index statistics window is shown.
+    show_stats: bool,
 }
 
 impl SearchApp {
     /// Create a new search application.
     pub fn new(
-        _cc: &eframe::CreationContext<'_>,
+        cc: &eframe::CreationContext<'_>,
         runtime: Handle,
         hotkey_rx: Receiver<()>,
+        hotkey: Option<HotkeyManager>,
         visible: Arc<AtomicBool>,
     ) -> Self {
-        Self {
-            query: String::new(),
+        let config_modified = config_modified_time();
+        let ui_config = match Config::load() {
+            Ok(config) => config.ui,
+            Err(e) => {
+                tracing::warn!("Failed to load config, using default sort: {}", e);
+                UiConfig::default()
+            }
+        };
+        let sort = sort_slots(&ui_config.sort_for_scope(DEFAULT_SORT_SCOPE));
+        let settings = SettingsView::new(runtime.clone());
+        let export = ExportView::new(runtime.clone());
+        let tags = TagsView::new(runtime.clone());
+        let usage = UsageView::new(runtime.clone());
+        let mut first_run = FirstRunView::new(runtime.clone());
+        first_run.check(&cc.egui_ctx);
+        let tray = match Tray::new(&cc.egui_ctx) {
+            Ok(tray) => Some(tray),
+            Err(e) => {
+                tracing::warn!("Tray icon unavailable: {}", e);
+                None
+            }
+        };
+        accessibility::apply_theme(&cc.egui_ctx, ui_config.high_contrast);
+        let initial = PopupState::initial(ui_config.popup, PopupState::load(cc.storage));
+
+        let mut app = Self {
+            query: initial.query.clone(),
             results: Vec::new(),
             selected_index: 0,
             search_pending: false,
@@ -72,12 +238,67 @@ impl SearchApp {
             ipc_client: IpcClient::new(),
             runtime,
             hotkey_rx,
+            hotkey,
+            config_modified,
+            last_config_check: None,
             visible,
             status: "Ready".to_string(),
             total_count: 0,
             search_time_ms: 0,
             pending_results: None,
+            results_stale: false,
+            showing_recent: false,
+            search_task: None,
             first_frame: true,
+            history: NavigationHistory::new(),
+            restoring_history: false,
+            suggestions: Vec::new(),
+            saved_searches: Vec::new(),
+            pending_saved: None,
+            save_name: None,
+            ui_config,
+            sort_scope: DEFAULT_SORT_SCOPE.to_string(),
+            sort,
+            show_all_links: false,
+            ranking: Ranking::default(),
+            settings,
+            export,
+            tags,
+            usage,
+            first_run,
+            icons: IconCache::new(&cc.egui_ctx),
+            scroll_offset: 0.0,
+            scroll_to: None,
+            pending_restore: None,
+            save_state_pending: false,
+            reveal_selected: false,
+            results_height: 0.0,
+            show_help: false,
+            syntax: None,
+            pending_syntax: None,
+            tray,
+            service_status: None,
+            pending_status: None,
+            last_status_poll: None,
+            show_stats: false,
+        };
+
+        if !app.query.is_empty() {
+            app.pending_restore = Some(initial);
+            app.trigger_search();
+        } else {
+            app.show_recent_files(&cc.egui_ctx);
+        }
+        app.send_saved_search_command(&cc.egui_ctx, Command::ListSavedSearches);
+        app
+    }
+
+    /// Snapshot of the query, selection and scroll position.
+    fn popup_state(&self) -> PopupState {
+        PopupState {
+            query: self.query.clone(),
+            selected_index: self.selected_index,
+            scroll_offset: self.scroll_offset,
         }
     }
 
@@ -91,105 +312,593 @@ impl SearchApp {
     fn execute_search(&mut self, ctx: &egui::Context) {
         let query = self.query.clone();
         if query.is_empty() {
-            self.results.clear();
-            self.total_count = 0;
-            self.status = "Ready".to_string();
+            self.show_recent_files(ctx);
             return;
         }
+        self.showing_recent = false;
+
+        // Record in navigation history unless we got here via back/forward
+        if !std::mem::take(&mut self.restoring_history) {
+            self.history.push(HistoryEntry::Query(query.clone()));
+        }
+
+        // Switch to the remembered sort when the search moves to another
+        // scope; a saved search is a scope of its own
+        let scope = self
+            .saved_searches
+            .iter()
+            .find(|saved| saved.query == query)
+            .map(|saved| saved.name.to_lowercase())
+            .unwrap_or_else(|| sort_scope_for(&query));
+        if scope != self.sort_scope {
+            self.sort = sort_slots(&self.ui_config.sort_for_scope(&scope));
+            self.sort_scope = scope;
+        }
 
         self.status = "Searching...".to_string();
 
-        // Create channel for results
+        // Create channel for results; the previous results stay on screen
+        // until the first new ones arrive
         let (tx, rx) = std::sync::mpsc::channel();
         self.pending_results = Some(rx);
+        self.results_stale = true;
+
+        // Ask the service to count each suggestion alongside the search
+        let suggestions = suggest_filters(&query);
+        let count_queries = suggestions
+            .iter()
+            .map(|s| apply_suggestion(&query, s))
+            .collect();
+        self.suggestions = suggestions.into_iter().map(|s| (s, None)).collect();
+
+        let request = SearchRequest {
+            query,
+            limit: MAX_RESULTS,
+            offset: 0,
+            count_queries,
+            sort: self.current_sort(),
+            show_all_links: self.show_all_links,
+            ranking: self.ranking,
+            stream: true,
+        };
 
         // Clone what we need for the async task
         let ipc_client = IpcClient::new();
         let ctx = ctx.clone();
 
-        // Spawn async search task
-        self.runtime.spawn(async move {
-            let result = ipc_client.search(&query, MAX_RESULTS).await;
-            match result {
-                Ok(response) => {
-                    let _ = tx.send(response);
-                }
+        // Spawn async search task; each chunk is shown as it arrives. A
+        // search still running for an earlier query is no longer needed.
+        self.cancel_search();
+        self.search_task = Some(self.runtime.spawn(async move {
+            let result = ipc_client
+                .stream_search(&request, |results| {
+                    let _ = tx.send(SearchFrame::Results {
+                        results: results.to_vec(),
+                    });
+                    ctx.request_repaint();
+                })
+                .await;
+            let done = match result {
+                Ok(response) => SearchFrame::Done {
+                    total_count: response.total_count,
+                    search_time_ms: response.search_time_ms,
+                    counts: response.counts,
+                },
                 Err(e) => {
                     tracing::error!("Search failed: {}", e);
-                    // Send empty response on error
-                    let _ = tx.send(SearchResponse {
-                        results: Vec::new(),
+                    SearchFrame::Done {
                         total_count: 0,
                         search_time_ms: 0,
-                    });
+                        counts: Vec::new(),
+                    }
                 }
-            }
+            };
+            let _ = tx.send(done);
             ctx.request_repaint();
-        });
+        }));
+    }
+
+    /// Show the files modified in the last [`RECENT_DAYS`], newest first:
+    /// the view of an empty search box.
+    fn show_recent_files(&mut self, ctx: &egui::Context) {
+        self.cancel_search();
+        self.suggestions.clear();
+        self.showing_recent = true;
+        self.status = "Loading recent files...".to_string();
+
+        // Delivered like a search, replacing the shown results when it arrives
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending_results = Some(rx);
+        self.results_stale = true;
+
+        let since = chrono::Utc::now().timestamp() - RECENT_DAYS * 24 * 60 * 60;
+        let ctx = ctx.clone();
+        self.search_task = Some(self.runtime.spawn(async move {
+            let start = Instant::now();
+            let total_count = match IpcClient::new().recent_files(since, MAX_RESULTS).await {
+                Ok(results) => {
+                    let total_count = results.len();
+                    let _ = tx.send(SearchFrame::Results { results });
+                    total_count
+                }
+                Err(e) => {
+                    tracing::debug!("Failed to list recent files: {}", e);
+                    0
+                }
+            };
+            let _ = tx.send(SearchFrame::Done {
+                total_count,
+                search_time_ms: start.elapsed().as_millis() as u64,
+                counts: Vec::new(),
+            });
+            ctx.request_repaint();
+        }));
+    }
+
+    /// Stop the pending search, if any.
+    fn cancel_search(&mut self) {
+        if let Some(task) = self.search_task.take() {
+            task.abort();
+        }
     }
 
     /// Check for and process pending search results.
     fn check_pending_results(&mut self) {
-        if let Some(rx) = &self.pending_results {
-            if let Ok(response) = rx.try_recv() {
-                self.results = response.results;
-                self.total_count = response.total_count;
-                self.search_time_ms = response.search_time_ms;
+        let Some(rx) = &self.pending_results else { return };
+        let frames: Vec<SearchFrame> = rx.try_iter().collect();
+
+        for frame in frames {
+            // The first frame of a search replaces the previous results
+            if std::mem::take(&mut self.results_stale) {
+                self.results.clear();
                 self.selected_index = 0;
-                self.status = format!(
-                    "{} results in {}ms",
-                    self.total_count, self.search_time_ms
-                );
-                self.pending_results = None;
             }
+
+            match frame {
+                SearchFrame::Results { results } => merge_ranked(&mut self.results, results),
+                SearchFrame::Done {
+                    total_count,
+                    search_time_ms,
+                    counts,
+                } => {
+                    self.total_count = total_count;
+                    self.search_time_ms = search_time_ms;
+                    if let Some(restore) = self.pending_restore.take() {
+                        self.selected_index =
+                            restore.selected_index.min(self.results.len().saturating_sub(1));
+                        self.scroll_to = Some(restore.scroll_offset);
+                    }
+                    if counts.len() == self.suggestions.len() {
+                        for (suggestion, count) in self.suggestions.iter_mut().zip(counts) {
+                            suggestion.1 = Some(count);
+                        }
+                    }
+                    self.status = if self.showing_recent {
+                        format!("{} files modified in the last {} days", self.total_count, RECENT_DAYS)
+                    } else {
+                        format!("{} results in {}ms", self.total_count, self.search_time_ms)
+                    };
+                    self.pending_results = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Current sort keys, primary first.
+    fn current_sort(&self) -> Vec<SortSpec> {
+        self.sort.iter().flatten().copied().collect()
+    }
+
+    /// Remember the current sort for the current scope in config.
+    fn persist_sort(&mut self) {
+        let sort = self.current_sort();
+        self.ui_config.set_sort_for_scope(&self.sort_scope, sort.clone());
+
+        let scope = self.sort_scope.clone();
+        persist_ui_config("sort", |ui| ui.set_sort_for_scope(&scope, sort));
+    }
+
+    /// Switch the high-contrast theme on or off and remember the choice.
+    fn toggle_high_contrast(&mut self, ctx: &egui::Context) {
+        let high_contrast = !self.ui_config.high_contrast;
+        self.ui_config.high_contrast = high_contrast;
+        accessibility::apply_theme(ctx, high_contrast);
+        persist_ui_config("high contrast", |ui| ui.high_contrast = high_contrast);
+    }
+
+    /// Add the nth filter suggestion to the query.
+    fn apply_suggestion_at(&mut self, index: usize) {
+        if let Some((filter, _)) = self.suggestions.get(index) {
+            self.query = apply_suggestion(&self.query, filter);
+            self.restoring_history = false;
+            self.trigger_search();
+        }
+    }
+
+    /// Move the primary sort to the next field, keeping its direction.
+    fn cycle_sort_field(&mut self) {
+        let current = self.sort[0].unwrap_or(SortSpec::asc(SortField::Name));
+        let position = SortField::ALL.iter().position(|f| *f == current.field).unwrap_or(0);
+        let field = SortField::ALL[(position + 1) % SortField::ALL.len()];
+        self.sort[0] = Some(SortSpec { field, ..current });
+        self.persist_sort();
+        self.trigger_search();
+    }
+
+    /// Flip the direction of the primary sort.
+    fn toggle_sort_direction(&mut self) {
+        if let Some(spec) = self.sort[0].as_mut() {
+            spec.descending = !spec.descending;
+        }
+        self.persist_sort();
+        self.trigger_search();
+    }
+
+    /// Switch to the next ranking.
+    fn cycle_ranking(&mut self) {
+        let position = Ranking::ALL.iter().position(|r| *r == self.ranking).unwrap_or(0);
+        self.ranking = Ranking::ALL[(position + 1) % Ranking::ALL.len()];
+        self.trigger_search();
+    }
+
+    /// Restore a history entry into the search box and re-run it.
+    fn restore_history_entry(&mut self, entry: HistoryEntry) {
+        self.query = entry.to_query();
+        self.restoring_history = true;
+        self.trigger_search();
+    }
+
+    /// Go back to the previous query or folder.
+    fn navigate_back(&mut self) {
+        if let Some(entry) = self.history.back().cloned() {
+            self.restore_history_entry(entry);
         }
     }
 
+    /// Go forward to the next query or folder.
+    fn navigate_forward(&mut self) {
+        if let Some(entry) = self.history.forward().cloned() {
+            self.restore_history_entry(entry);
+        }
+    }
+
+    /// Tell the service a result was opened, for frecency ranking.
+    fn record_open(&self, path: &str) {
+        let command = Command::RecordOpen {
+            path: path.to_string(),
+        };
+        self.runtime.spawn(async move {
+            if let Err(e) = IpcClient::new().send_command(&command).await {
+                tracing::debug!("Failed to record open: {}", e);
+            }
+        });
+    }
+
+    /// Run an action picked from a result's context menu.
+    fn run_result_action(&mut self, index: usize, action: ResultAction) {
+        let Some(result) = self.results.get(index) else { return };
+        self.selected_index = index;
+        let path = std::path::Path::new(&result.path);
+
+        let outcome = match action {
+            ResultAction::Tags => {
+                self.tags.show_window(&result.path, &result.name);
+                return;
+            }
+            ResultAction::Open => actions::open_file(path),
+            ResultAction::OpenWith => actions::open_with(path),
+            ResultAction::Reveal => actions::reveal_in_explorer(path),
+            ResultAction::CopyPath => actions::copy_to_clipboard(path, ClipboardFormat::Text),
+            ResultAction::CopyFile => actions::copy_to_clipboard(path, ClipboardFormat::Files),
+            ResultAction::Delete => actions::delete_to_recycle_bin(path),
+            ResultAction::Properties => actions::show_properties(path),
+        };
+        if let Err(e) = outcome {
+            tracing::error!("{} failed: {}", action.label(), e);
+            self.status = format!("{} failed: {}", action.label(), e);
+            return;
+        }
+
+        match action {
+            ResultAction::Open | ResultAction::OpenWith => self.record_open(&result.path),
+            ResultAction::CopyPath => self.status = "Path copied to clipboard".to_string(),
+            ResultAction::CopyFile => self.status = "File copied to clipboard".to_string(),
+            ResultAction::Delete => {
+                // The index catches up through the USN journal; drop the row now
+                self.status = format!("Moved {} to the Recycle Bin", result.name);
+                self.results.remove(index);
+                self.total_count = self.total_count.saturating_sub(1);
+                self.selected_index = index.min(self.results.len().saturating_sub(1));
+            }
+            ResultAction::Reveal | ResultAction::Properties | ResultAction::Tags => {}
+        }
+    }
+
+    /// Show or hide the help window, asking the service for its search
+    /// syntax the first time it opens.
+    fn toggle_help(&mut self, ctx: &egui::Context) {
+        self.show_help = !self.show_help;
+        if !self.show_help || self.syntax.is_some() || self.pending_syntax.is_some() {
+            return;
+        }
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending_syntax = Some(rx);
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let syntax = match IpcClient::new().send_command(&Command::GetSyntaxHelp).await {
+                Ok(response) if response.syntax.is_some() => response.syntax,
+                Ok(response) => {
+                    tracing::debug!("Service returned no search syntax: {}", response.message);
+                    None
+                }
+                Err(e) => {
+                    tracing::debug!("Failed to get search syntax: {}", e);
+                    None
+                }
+            };
+            // Without the service, describe this build's parser
+            let _ = tx.send(syntax.unwrap_or_else(syntax_help));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Pick up the search syntax once the service replies.
+    fn check_pending_syntax(&mut self) {
+        if let Some(syntax) = self.pending_syntax.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.syntax = Some(syntax);
+            self.pending_syntax = None;
+        }
+    }
+
+    /// Send a saved search command; the reply replaces the saved searches.
+    fn send_saved_search_command(&mut self, ctx: &egui::Context, command: Command) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending_saved = Some(rx);
+
+        // Listing happens in the background, so only its failures are quiet
+        let report = command != Command::ListSavedSearches;
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send((report, IpcClient::new().send_command(&command).await));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Check for a reply to a saved search command.
+    fn check_pending_saved(&mut self) {
+        let Some((report, response)) = self.pending_saved.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return;
+        };
+        self.pending_saved = None;
+
+        match response {
+            Ok(response) => {
+                if let Some(saved) = response.saved_searches {
+                    self.saved_searches = saved;
+                }
+                if report || !response.success {
+                    self.status = response.message;
+                }
+            }
+            Err(e) if report => self.status = format!("Saved searches unavailable: {}", e),
+            Err(e) => tracing::debug!("Failed to list saved searches: {}", e),
+        }
+    }
+
+    /// Draw the saved search chips, with a button to save the current query.
+    fn show_saved_searches(&mut self, ui: &mut egui::Ui) {
+        if self.saved_searches.is_empty() && self.save_name.is_none() && self.query.trim().is_empty() {
+            return;
+        }
+
+        let mut applied: Option<String> = None;
+        let mut removed: Option<String> = None;
+        let mut save = false;
+        ui.horizontal_wrapped(|ui| {
+            for saved in &self.saved_searches {
+                let chip = ui
+                    .selectable_label(saved.query == self.query, &saved.name)
+                    .on_hover_text(&saved.query);
+                if chip.clicked() {
+                    applied = Some(saved.query.clone());
+                }
+                chip.context_menu(|ui| {
+                    if ui.button("Remove").clicked() {
+                        removed = Some(saved.name.clone());
+                        ui.close_menu();
+                    }
+                });
+            }
+
+            match &mut self.save_name {
+                Some(name) => {
+                    let field = ui.add(
+                        egui::TextEdit::singleline(name)
+                            .desired_width(140.0)
+                            .hint_text("Name"),
+                    );
+                    field.request_focus();
+                    let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    save = (entered || ui.small_button("Save").clicked()) && !name.trim().is_empty();
+                    if ui.small_button("Cancel").clicked() {
+                        self.save_name = None;
+                        self.first_frame = true;
+                    }
+                }
+                None => {
+                    if !self.query.trim().is_empty() && ui.small_button("Save search").clicked() {
+                        self.save_name = Some(String::new());
+                    }
+                }
+            }
+        });
+
+        let ctx = ui.ctx().clone();
+        if let Some(query) = applied {
+            self.query = query;
+            self.restoring_history = false;
+            self.trigger_search();
+        }
+        if let Some(name) = removed {
+            self.send_saved_search_command(&ctx, Command::DeleteSavedSearch { name });
+        }
+        if save {
+            if let Some(name) = self.save_name.take() {
+                // Back to the search box
+                self.first_frame = true;
+                let command = Command::SaveSearch {
+                    name: name.trim().to_string(),
+                    query: self.query.clone(),
+                };
+                self.send_saved_search_command(&ctx, command);
+            }
+        }
+    }
+
+    /// Browse into the selected folder by scoping the search to its path.
+    fn browse_selected_folder(&mut self) {
+        let Some(result) = self.results.get(self.selected_index) else {
+            return;
+        };
+        if !result.is_dir {
+            return;
+        }
+
+        let entry = HistoryEntry::Folder(result.path.clone());
+        self.history.push(entry.clone());
+        self.restore_history_entry(entry);
+    }
+
     /// Handle keyboard navigation.
     fn handle_keyboard(&mut self, ctx: &egui::Context) {
+        let mut go_back = false;
+        let mut go_forward = false;
+        let mut browse_folder = false;
+        let mut hide = false;
+        let mut toggle_contrast = false;
+        let mut toggle_help = false;
+        let mut suggestion: Option<usize> = None;
+        let mut cycle_sort = false;
+        let mut flip_sort = false;
+        let mut cycle_rank = false;
+
         ctx.input(|i| {
+            // History navigation (Alt+Left / Alt+Right)
+            if i.modifiers.alt && i.key_pressed(egui::Key::ArrowLeft) {
+                go_back = true;
+            }
+            if i.modifiers.alt && i.key_pressed(egui::Key::ArrowRight) {
+                go_forward = true;
+            }
+
+            // Browse into selected folder (Ctrl+Enter)
+            if i.modifiers.ctrl && i.key_pressed(egui::Key::Enter) {
+                browse_folder = true;
+            }
+
             // Navigate down
             if i.key_pressed(egui::Key::ArrowDown) {
                 if !self.results.is_empty() {
                     self.selected_index = (self.selected_index + 1).min(self.results.len() - 1);
                 }
+                self.reveal_selected = true;
             }
 
             // Navigate up
             if i.key_pressed(egui::Key::ArrowUp) {
                 self.selected_index = self.selected_index.saturating_sub(1);
+                self.reveal_selected = true;
             }
 
-            // Open selected file
-            if i.key_pressed(egui::Key::Enter) {
+            // Open selected file (Enter in the saved search name saves it,
+            // and in the tag editor adds the tags)
+            if !i.modifiers.ctrl && i.key_pressed(egui::Key::Enter) && self.save_name.is_none() && !self.tags.open {
                 if let Some(result) = self.results.get(self.selected_index) {
                     let path = std::path::Path::new(&result.path);
-                    if let Err(e) = actions::open_file(path) {
-                        tracing::error!("Failed to open file: {}", e);
-                        self.status = format!("Failed to open: {}", e);
+                    match actions::open_file(path) {
+                        Ok(()) => self.record_open(&result.path),
+                        Err(e) => {
+                            tracing::error!("Failed to open file: {}", e);
+                            self.status = format!("Failed to open: {}", e);
+                        }
                     }
                 }
             }
 
-            // Close/hide on Escape
+            // Escape closes the topmost window, then hides the popup
             if i.key_pressed(egui::Key::Escape) {
-                self.visible.store(false, Ordering::SeqCst);
-                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+                if self.save_name.is_some() {
+                    self.save_name = None;
+                    self.first_frame = true;
+                } else if self.show_help {
+                    self.show_help = false;
+                } else if self.settings.open {
+                    self.settings.open = false;
+                } else if self.export.open {
+                    self.export.open = false;
+                } else if self.tags.open {
+                    self.tags.open = false;
+                } else if self.usage.open {
+                    self.usage.open = false;
+                } else if self.first_run.open {
+                    self.first_run.open = false;
+                } else if self.show_stats {
+                    self.show_stats = false;
+                } else {
+                    hide = true;
+                }
+            }
+
+            // Help window (F1)
+            toggle_help = i.key_pressed(egui::Key::F1);
+
+            // Settings (Ctrl+,)
+            if i.modifiers.ctrl && i.key_pressed(egui::Key::Comma) {
+                self.settings.show_window();
+            }
+
+            // High-contrast theme (Ctrl+Shift+H)
+            if i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(egui::Key::H) {
+                toggle_contrast = true;
+            }
+
+            // Filter suggestions, sort and ranking (Alt+1-9, Alt+S/D/R/L)
+            if i.modifiers.alt && !i.modifiers.ctrl {
+                suggestion = SUGGESTION_KEYS.iter().position(|key| i.key_pressed(*key));
+                cycle_sort = i.key_pressed(egui::Key::S);
+                flip_sort = i.key_pressed(egui::Key::D);
+                cycle_rank = i.key_pressed(egui::Key::R);
+                if i.key_pressed(egui::Key::L) {
+                    self.show_all_links = !self.show_all_links;
+                    self.trigger_search();
+                }
             }
 
-            // Copy path to clipboard (Ctrl+Shift+C)
+            // Copy path to clipboard (Ctrl+Shift+C, add Alt to copy as file)
             if i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(egui::Key::C) {
+                let format = if i.modifiers.alt {
+                    ClipboardFormat::Files
+                } else {
+                    self.ui_config.copy_format
+                };
                 if let Some(result) = self.results.get(self.selected_index) {
                     let path = std::path::Path::new(&result.path);
-                    if let Err(e) = actions::copy_to_clipboard(path) {
+                    if let Err(e) = actions::copy_to_clipboard(path, format) {
                         tracing::error!("Failed to copy path: {}", e);
                         self.status = format!("Failed to copy: {}", e);
                     } else {
-                        self.status = "Path copied to clipboard".to_string();
+                        self.status = format!("{} copied to clipboard", format.label());
                     }
                 }
             }
 
+            // Export results (Ctrl+Shift+S)
+            if i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(egui::Key::S) {
+                self.export.show_window(&self.query);
+            }
+
             // Reveal in Explorer (Ctrl+Shift+E)
             if i.modifiers.ctrl && i.modifiers.shift && i.key_pressed(egui::Key::E) {
                 if let Some(result) = self.results.get(self.selected_index) {
@@ -201,6 +910,37 @@ impl SearchApp {
                 }
             }
         });
+
+        if hide {
+            self.hide(ctx);
+        }
+        if toggle_contrast {
+            self.toggle_high_contrast(ctx);
+        }
+        if toggle_help {
+            self.toggle_help(ctx);
+        }
+        if let Some(index) = suggestion {
+            self.apply_suggestion_at(index);
+        }
+        if cycle_sort {
+            self.cycle_sort_field();
+        }
+        if flip_sort {
+            self.toggle_sort_direction();
+        }
+        if cycle_rank {
+            self.cycle_ranking();
+        }
+        if go_back {
+            self.navigate_back();
+        }
+        if go_forward {
+            self.navigate_forward();
+        }
+        if browse_folder {
+            self.browse_selected_folder();
+        }
     }
 
     /// Check for hotkey events.
@@ -209,24 +949,270 @@ impl SearchApp {
         while self.hotkey_rx.try_recv().is_ok() {
             let is_visible = self.visible.load(Ordering::SeqCst);
             if is_visible {
-                self.visible.store(false, Ordering::SeqCst);
-                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+                self.hide(ctx);
             } else {
-                self.visible.store(true, Ordering::SeqCst);
-                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
-                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                self.show_popup(ctx);
+            }
+        }
+    }
+
+    /// Re-register the global hotkey when the config file changes it.
+    fn check_config(&mut self, ctx: &egui::Context) {
+        if self.hotkey.is_none() {
+            return;
+        }
+        if self.last_config_check.is_some_and(|at| at.elapsed() < CONFIG_CHECK_INTERVAL) {
+            return;
+        }
+        self.last_config_check = Some(Instant::now());
+        // Keep checking while the popup is hidden
+        ctx.request_repaint_after(CONFIG_CHECK_INTERVAL);
+
+        let modified = config_modified_time();
+        if modified == self.config_modified {
+            return;
+        }
+        self.config_modified = modified;
+
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Failed to reload config: {}", e);
+                return;
+            }
+        };
+        let combo = match config.ui.hotkey.parse::<HotkeyCombo>() {
+            Ok(combo) => combo,
+            Err(e) => {
+                self.status = e.to_string();
+                return;
+            }
+        };
+        let Some(hotkey) = self.hotkey.as_mut() else { return };
+        if *hotkey.hotkey() == combo {
+            return;
+        }
+        self.status = match hotkey.set_hotkey(&combo) {
+            Ok(()) => {
+                self.ui_config.hotkey = config.ui.hotkey;
+                format!("Hotkey changed to {}", combo)
+            }
+            Err(e) => format!("Keeping hotkey {}: {}", hotkey.hotkey(), e),
+        };
+    }
+
+    /// Show and focus the popup.
+    fn show_popup(&mut self, ctx: &egui::Context) {
+        // Pick up searches saved from other clients
+        if self.pending_saved.is_none() {
+            self.send_saved_search_command(ctx, Command::ListSavedSearches);
+        }
+        // The latest files, as of this opening
+        if self.query.is_empty() {
+            self.show_recent_files(ctx);
+        }
+        self.visible.store(true, Ordering::SeqCst);
+        self.first_frame = true;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    }
+
+    /// Handle actions picked from the tray icon and keep its status current.
+    fn check_tray(&mut self, ctx: &egui::Context) {
+        if self.tray.is_none() && !self.show_stats {
+            return;
+        }
+
+        while let Some(action) = self.tray.as_ref().and_then(Tray::try_action) {
+            match action {
+                TrayAction::ShowSearch => self.show_popup(ctx),
+                TrayAction::TogglePause => self.toggle_pause(ctx),
+                TrayAction::OpenSettings => {
+                    self.show_popup(ctx);
+                    self.settings.show_window();
+                }
+                TrayAction::ShowStatistics => {
+                    self.show_popup(ctx);
+                    self.show_stats = true;
+                    self.refresh_status(ctx);
+                }
+                TrayAction::Exit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            }
+        }
+
+        if let Some(status) = self.pending_status.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.pending_status = None;
+            self.service_status = status;
+            if let Some(tray) = self.tray.as_mut() {
+                tray.set_status(self.service_status.as_ref());
+            }
+        }
+        if self.last_status_poll.is_none_or(|at| at.elapsed() >= STATUS_POLL_INTERVAL) {
+            self.refresh_status(ctx);
+        }
+        // Keep polling while the popup is hidden
+        ctx.request_repaint_after(STATUS_POLL_INTERVAL);
+    }
+
+    /// Ask the service for its status, unless a request is pending.
+    fn refresh_status(&mut self, ctx: &egui::Context) {
+        if self.pending_status.is_some() {
+            return;
+        }
+        self.last_status_poll = Some(Instant::now());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending_status = Some(rx);
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let status = match IpcClient::new().get_status().await {
+                Ok(status) => Some(status),
+                Err(e) => {
+                    tracing::debug!("Failed to get service status: {}", e);
+                    None
+                }
+            };
+            let _ = tx.send(status);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Pause indexing, or resume it if the service reported it paused.
+    fn toggle_pause(&mut self, ctx: &egui::Context) {
+        let paused = self.service_status.as_ref().is_some_and(|s| s.indexing_paused);
+        let command = if paused { Command::ResumeIndexing } else { Command::PauseIndexing };
+        // Wait for the command before refreshing, so the tray shows its effect
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending_status = Some(rx);
+        self.last_status_poll = Some(Instant::now());
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let client = IpcClient::new();
+            if let Err(e) = client.send_command(&command).await {
+                tracing::warn!("Failed to {:?}: {}", command, e);
             }
+            let _ = tx.send(client.get_status().await.ok());
+            ctx.request_repaint();
+        });
+    }
+
+    /// Draw the index statistics window if open.
+    fn show_stats_window(&mut self, ctx: &egui::Context) {
+        let status = &self.service_status;
+        egui::Window::new("Index statistics")
+            .open(&mut self.show_stats)
+            .collapsible(false)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                let Some(status) = status else {
+                    ui.weak("The FFI service is not running.");
+                    return;
+                };
+                if status.indexing_paused {
+                    ui.label("Indexing is paused.");
+                }
+                if status.volumes.is_empty() {
+                    ui.weak("No volumes indexed yet.");
+                }
+                egui::Grid::new("stats").striped(true).show(ui, |ui| {
+                    for heading in ["Volume", "Type", "State", "Files", "Folders", "Last scan"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for volume in &status.volumes {
+                        ui.label(&volume.drive_letter);
+                        ui.label(&volume.fs_type);
+                        ui.label(volume.state_label());
+                        ui.label(format_count(volume.file_count.max(0) as usize));
+                        ui.label(format_count(volume.dir_count.max(0) as usize));
+                        ui.label(volume.last_scan_time.map_or_else(|| "never".to_string(), format_date));
+                        ui.end_row();
+                    }
+                });
+                if let Some(maintenance) = &status.maintenance {
+                    let text = format!("Last maintenance: {}", format_date(maintenance.ran_at));
+                    if maintenance.is_healthy() {
+                        ui.weak(text);
+                    } else {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("{} - integrity check failed: {}", text, maintenance.integrity_errors[0]),
+                        );
+                    }
+                }
+                if let Some(pruning) = &status.pruning {
+                    ui.weak(format!(
+                        "Index pruned to its size limit on {}: {} -> {}",
+                        format_date(pruning.ran_at),
+                        format_size(pruning.size_before),
+                        format_size(pruning.size_after)
+                    ));
+                }
+            });
+    }
+
+    /// Draw the help window (search syntax and keyboard shortcuts) if open.
+    fn show_help_window(&mut self, ctx: &egui::Context) {
+        let syntax = &self.syntax;
+        egui::Window::new("Help")
+            .open(&mut self.show_help)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().max_height(HELP_MAX_HEIGHT).show(ui, |ui| {
+                    ui.heading("Search syntax");
+                    match syntax {
+                        Some(syntax) => help::syntax_section(ui, syntax),
+                        None => {
+                            ui.spinner();
+                        }
+                    }
+
+                    ui.separator();
+                    ui.heading("Keyboard shortcuts");
+                    egui::Grid::new("shortcuts").striped(true).show(ui, |ui| {
+                        for (keys, action) in SHORTCUTS {
+                            ui.monospace(*keys);
+                            ui.label(*action);
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+
+    /// Hide the popup, saving its state (or clearing it in fresh mode).
+    fn hide(&mut self, ctx: &egui::Context) {
+        self.visible.store(false, Ordering::SeqCst);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+
+        if self.ui_config.popup == PopupMode::Fresh {
+            self.query.clear();
+            self.results.clear();
+            self.suggestions.clear();
+            self.total_count = 0;
+            self.selected_index = 0;
+            self.scroll_to = Some(0.0);
+            self.search_pending = false;
+            self.cancel_search();
+            self.pending_results = None;
+            self.results_stale = false;
+            self.status = "Ready".to_string();
         }
+        self.save_state_pending = true;
     }
 }
 
 impl eframe::App for SearchApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Check for hotkey events
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // Check for hotkey events and tray actions
         self.check_hotkey(ctx);
+        self.check_config(ctx);
+        self.check_tray(ctx);
 
         // Check for pending search results
         self.check_pending_results();
+        self.check_pending_syntax();
+        self.check_pending_saved();
 
         // Handle keyboard navigation
         self.handle_keyboard(ctx);
@@ -248,13 +1234,15 @@ impl eframe::App for SearchApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical(|ui| {
                 // Search input
-                ui.horizontal(|ui| {
-                    ui.label("Search:");
-                    let response = ui.add(
-                        egui::TextEdit::singleline(&mut self.query)
-                            .desired_width(ui.available_width() - 60.0)
-                            .hint_text("Type to search files...")
-                    );
+                let search_id = ui.horizontal(|ui| {
+                    let label = ui.label("Search:");
+                    let response = ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.query)
+                                .desired_width(ui.available_width() - 60.0)
+                                .hint_text("Type to search files..."),
+                        )
+                        .labelled_by(label.id);
 
                     // Request focus on first frame
                     if self.first_frame {
@@ -264,35 +1252,250 @@ impl eframe::App for SearchApp {
 
                     // Trigger search on text change
                     if response.changed() {
+                        self.restoring_history = false;
+                        self.trigger_search();
+                    }
+                    response.id
+                })
+                .inner;
+
+                // Saved searches, pinned
+                self.show_saved_searches(ui);
+
+                // Filter suggestion chips with live counts
+                if !self.suggestions.is_empty() {
+                    let mut applied: Option<String> = None;
+                    ui.horizontal_wrapped(|ui| {
+                        for (filter, count) in &self.suggestions {
+                            let label = match count {
+                                Some(n) => format!("{} ({})", filter, format_count(*n)),
+                                None => filter.clone(),
+                            };
+                            if ui.small_button(label).clicked() {
+                                applied = Some(filter.clone());
+                            }
+                        }
+                    });
+                    if let Some(filter) = applied {
+                        self.query = apply_suggestion(&self.query, &filter);
+                        self.restoring_history = false;
+                        self.trigger_search();
+                    }
+                }
+
+                // Sort pickers (primary, then optional secondary)
+                let mut sort_changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Sort:");
+                    sort_changed |= sort_picker(ui, "sort_primary", "Sort by", &mut self.sort[0], false);
+                    ui.label("then");
+                    sort_changed |= sort_picker(ui, "sort_secondary", "Then sort by", &mut self.sort[1], true);
+                    ui.add_space(10.0);
+                    if ui.checkbox(&mut self.show_all_links, "Show all links").changed() {
+                        self.trigger_search();
+                    }
+                    ui.add_space(10.0);
+                    ui.label("Rank:");
+                    let before = self.ranking;
+                    let combo = egui::ComboBox::from_id_salt("ranking")
+                        .selected_text(self.ranking.label())
+                        .show_ui(ui, |ui| {
+                            for ranking in Ranking::ALL {
+                                ui.selectable_value(&mut self.ranking, ranking, ranking.label());
+                            }
+                        });
+                    accessibility::set_label(
+                        ctx,
+                        combo.response.id,
+                        &format!("Rank: {}", self.ranking.label()),
+                    );
+                    if self.ranking != before {
                         self.trigger_search();
                     }
                 });
+                if sort_changed {
+                    self.persist_sort();
+                    self.trigger_search();
+                }
 
                 ui.separator();
 
-                // Results list
-                let clicked_index = ResultsView::show(ui, &self.results, self.selected_index);
-                if let Some(index) = clicked_index {
+                // Results list, scrolled to keep the keyboard selection visible
+                if std::mem::take(&mut self.reveal_selected) {
+                    let row_span = ResultsView::row_span(ui.style());
+                    self.scroll_to = self.scroll_to.or(reveal_offset(
+                        self.selected_index,
+                        row_span,
+                        self.scroll_offset,
+                        self.results_height,
+                    ));
+                }
+                let output = ResultsView::show(
+                    ui,
+                    &self.results,
+                    &mut self.icons,
+                    self.selected_index,
+                    self.scroll_to.take(),
+                );
+                self.scroll_offset = output.scroll_offset;
+                self.results_height = output.viewport_height;
+                if let Some(row) = output.selected_row {
+                    accessibility::set_active_descendant(ctx, search_id, row);
+                }
+                if let Some(index) = output.clicked {
                     self.selected_index = index;
                     // Double-click could open the file
                     if let Some(result) = self.results.get(index) {
                         let path = std::path::Path::new(&result.path);
-                        if let Err(e) = actions::open_file(path) {
-                            tracing::error!("Failed to open file: {}", e);
+                        match actions::open_file(path) {
+                            Ok(()) => self.record_open(&result.path),
+                            Err(e) => tracing::error!("Failed to open file: {}", e),
                         }
                     }
                 }
+                if let Some((index, action)) = output.action {
+                    self.run_result_action(index, action);
+                }
 
                 ui.separator();
 
                 // Status bar
+                let mut toggle_contrast = false;
+                let mut toggle_help = false;
                 ui.horizontal(|ui| {
-                    ui.label(&self.status);
+                    // Announced by screen readers whenever it changes
+                    let status = ui.label(&self.status);
+                    accessibility::mark_live(ctx, status.id);
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        ui.label("Esc:close  Enter:open  Ctrl+Shift+E:reveal  Ctrl+Shift+C:copy");
+                        if ui.small_button("Settings").clicked() {
+                            self.settings.show_window();
+                        }
+                        if ui.small_button("Export results...").clicked() {
+                            self.export.show_window(&self.query);
+                        }
+                        if ui.small_button("Disk usage...").clicked() {
+                            self.usage.show_window(&self.query);
+                        }
+                        let mut high_contrast = self.ui_config.high_contrast;
+                        toggle_contrast = ui.checkbox(&mut high_contrast, "High contrast").changed();
+                        if ui.small_button("F1: help").clicked() {
+                            toggle_help = true;
+                        }
+                        ui.label("Esc:close  Enter:open  Ctrl+Enter:browse  Alt+Left/Right:history");
                     });
                 });
+                if toggle_contrast {
+                    self.toggle_high_contrast(ctx);
+                }
+                if toggle_help {
+                    self.toggle_help(ctx);
+                }
             });
         });
+
+        self.settings.show(ctx);
+        self.export.show(ctx);
+        self.tags.show(ctx);
+        self.usage.show(ctx);
+        self.first_run.show(ctx);
+        self.show_stats_window(ctx);
+        self.show_help_window(ctx);
+
+        // Save the popup state as soon as it hides, not only on exit
+        if std::mem::take(&mut self.save_state_pending) {
+            if let Some(storage) = frame.storage_mut() {
+                self.popup_state().save(storage);
+                storage.flush();
+            }
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.popup_state().save(storage);
+    }
+}
+
+/// Scope key used to remember the sort for a query: its lowercased
+/// `path:` scope, or the default scope.
+fn sort_scope_for(query: &str) -> String {
+    parse_query(query)
+        .ok()
+        .and_then(|parsed| {
+            parsed.filters.into_iter().find_map(|f| match f {
+                Filter::PathScope(path) => Some(path.to_lowercase()),
+                _ => None,
+            })
+        })
+        .unwrap_or_else(|| DEFAULT_SORT_SCOPE.to_string())
+}
+
+/// Split saved sort keys into the primary/secondary picker slots.
+fn sort_slots(sort: &[SortSpec]) -> [Option<SortSpec>; 2] {
+    [
+        Some(sort.first().copied().unwrap_or(SortSpec::asc(SortField::Name))),
+        sort.get(1).copied(),
+    ]
+}
+
+/// Modification time of the config file (None if it doesn't exist).
+fn config_modified_time() -> Option<SystemTime> {
+    std::fs::metadata(Config::config_path()).and_then(|m| m.modified()).ok()
+}
+
+/// Apply a change to the `[ui]` section of the config file.
+///
+/// The config is reloaded before saving so service settings edited
+/// elsewhere are kept.
+fn persist_ui_config(what: &str, update: impl FnOnce(&mut UiConfig)) {
+    match Config::load() {
+        Ok(mut config) => {
+            update(&mut config.ui);
+            if let Err(e) = config.save() {
+                tracing::warn!("Failed to save {} preference: {}", what, e);
+            }
+        }
+        Err(e) => tracing::warn!("Not saving {} preference, config unreadable: {}", what, e),
+    }
+}
+
+/// Draw a sort field picker with a direction toggle. Returns true if changed.
+///
+/// `label` names the picker for screen readers.
+fn sort_picker(
+    ui: &mut egui::Ui,
+    id: &str,
+    label: &str,
+    spec: &mut Option<SortSpec>,
+    allow_none: bool,
+) -> bool {
+    let before = *spec;
+    let selected_text = spec.map(|s| s.field.label()).unwrap_or("None");
+
+    let combo = egui::ComboBox::from_id_salt(id)
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            if allow_none {
+                ui.selectable_value(spec, None, "None");
+            }
+            let descending = spec.map(|s| s.descending).unwrap_or(false);
+            for field in SortField::ALL {
+                ui.selectable_value(spec, Some(SortSpec { field, descending }), field.label());
+            }
+        });
+    accessibility::set_label(ui.ctx(), combo.response.id, &format!("{}: {}", label, selected_text));
+
+    if let Some(s) = spec.as_mut() {
+        let (text, spoken) = if s.descending {
+            ("Desc", "descending")
+        } else {
+            ("Asc", "ascending")
+        };
+        let button = ui.small_button(text).on_hover_text("Toggle sort direction");
+        accessibility::set_label(ui.ctx(), button.id, &format!("{} direction: {}", label, spoken));
+        if button.clicked() {
+            s.descending = !s.descending;
+        }
     }
+
+    *spec != before
 }
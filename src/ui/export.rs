@@ -0,0 +1,146 @@
+//! Export window: saves every match of the current query to a file.
+//!
+//! The service streams the matches as CSV, JSON Lines or TSV (see
+//! [`IpcClient::export`]), and they are written to the chosen file as they
+//! arrive, so exporting a large result set doesn't hold them in memory.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use tokio::runtime::Handle;
+
+use crate::db::ExportFormat;
+use crate::ipc::IpcClient;
+use crate::Result;
+
+/// Export window state.
+pub struct ExportView {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// Runtime for the export request.
+    runtime: Handle,
+    /// Query whose matches are exported.
+    query: String,
+    /// Output format.
+    format: ExportFormat,
+    /// File to write, as typed.
+    path: String,
+    /// Number of exported matches, once the running export finishes.
+    pending: Option<Receiver<Result<usize>>>,
+    /// Outcome of the last export.
+    status: String,
+}
+
+impl ExportView {
+    /// Create a closed export window.
+    pub fn new(runtime: Handle) -> Self {
+        Self {
+            open: false,
+            runtime,
+            query: String::new(),
+            format: ExportFormat::default(),
+            path: String::new(),
+            pending: None,
+            status: String::new(),
+        }
+    }
+
+    /// Open the window to export a query's matches, suggesting a file in
+    /// the user's Documents folder.
+    pub fn show_window(&mut self, query: &str) {
+        self.open = true;
+        self.query = query.to_string();
+        if self.pending.is_none() {
+            self.status.clear();
+        }
+        if self.path.is_empty() {
+            let dir = directories::UserDirs::new()
+                .and_then(|dirs| dirs.document_dir().map(Path::to_path_buf))
+                .unwrap_or_default();
+            let name = format!("ffi-export.{}", self.format.extension());
+            self.path = dir.join(name).display().to_string();
+        }
+    }
+
+    /// Draw the window if open.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.check_pending();
+
+        let mut open = self.open;
+        let mut start = false;
+        egui::Window::new("Export results")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Query:");
+                    ui.monospace(&self.query);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Format:");
+                    for format in ExportFormat::ALL {
+                        if ui.radio_value(&mut self.format, format, format.label()).changed() {
+                            let path = PathBuf::from(&self.path).with_extension(format.extension());
+                            self.path = path.display().to_string();
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(f32::INFINITY));
+                });
+
+                ui.horizontal(|ui| {
+                    let ready = self.pending.is_none() && !self.query.trim().is_empty() && !self.path.trim().is_empty();
+                    start = ui.add_enabled(ready, egui::Button::new("Export")).clicked();
+                    if self.pending.is_some() {
+                        ui.spinner();
+                    }
+                    ui.label(&self.status);
+                });
+            });
+        self.open = open;
+
+        if start {
+            self.start(ctx);
+        }
+    }
+
+    /// Start exporting to the chosen file.
+    fn start(&mut self, ctx: &egui::Context) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending = Some(rx);
+        self.status = "Exporting...".to_string();
+
+        let query = self.query.clone();
+        let format = self.format;
+        let path = PathBuf::from(self.path.trim());
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(export_to_file(&query, format, &path).await);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Report the running export's outcome once it finishes.
+    fn check_pending(&mut self) {
+        let Some(rx) = &self.pending else { return };
+        self.status = match rx.try_recv() {
+            Ok(Ok(rows)) => format!("Exported {} matches to {}", rows, self.path.trim()),
+            Ok(Err(e)) => format!("Export failed: {}", e),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => "Export failed".to_string(),
+        };
+        self.pending = None;
+    }
+}
+
+/// Export a query's matches through the service into a file.
+async fn export_to_file(query: &str, format: ExportFormat, path: &Path) -> Result<usize> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let rows = IpcClient::new().export(query, format, &mut file).await?;
+    file.flush()?;
+    Ok(rows)
+}
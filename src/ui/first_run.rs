@@ -0,0 +1,230 @@
+//! First-run volume selection.
+//!
+//! Volumes are only indexed once enabled in `config.toml`, so a fresh
+//! install indexes nothing. At startup the service is asked which volumes
+//! it sees (see [`IpcClient::list_detected_volumes`]); if none of them is
+//! configured yet, this window lets the user pick the volumes to index and
+//! has the service write the choice to its configuration.
+
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use tokio::runtime::Handle;
+
+use crate::ipc::{DetectedVolume, IpcClient};
+use crate::service::config::volume_drive_letter;
+use crate::ui::results::format_size;
+use crate::{FFIError, Result};
+
+/// First-run window state.
+pub struct FirstRunView {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// Runtime for service commands.
+    runtime: Handle,
+    /// Volumes the service detected.
+    volumes: Vec<DetectedVolume>,
+    /// Whether each volume is ticked for indexing.
+    selected: Vec<bool>,
+    /// Volumes of the running list request.
+    pending_volumes: Option<Receiver<Result<Vec<DetectedVolume>>>>,
+    /// Number of volumes enabled by the running save.
+    pending_save: Option<Receiver<Result<usize>>>,
+    /// Whether the selection was written.
+    saved: bool,
+    /// Outcome of the last save.
+    status: String,
+}
+
+impl FirstRunView {
+    /// Create a closed first-run window.
+    pub fn new(runtime: Handle) -> Self {
+        Self {
+            open: false,
+            runtime,
+            volumes: Vec::new(),
+            selected: Vec::new(),
+            pending_volumes: None,
+            pending_save: None,
+            saved: false,
+            status: String::new(),
+        }
+    }
+
+    /// Ask the service for its volumes; the window opens if none of them is
+    /// configured.
+    pub fn check(&mut self, ctx: &egui::Context) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending_volumes = Some(rx);
+
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(IpcClient::new().list_detected_volumes().await);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Draw the window if open.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.check_pending();
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        let mut save = false;
+        let mut close = false;
+        egui::Window::new("Choose volumes to index")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label("No volume is indexed yet. Pick the volumes whose files you want to search.");
+                ui.weak("This can be changed later under [volumes] in config.toml.");
+                ui.add_space(4.0);
+
+                for (volume, selected) in self.volumes.iter().zip(self.selected.iter_mut()) {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(!self.saved, egui::Checkbox::new(selected, &volume.name));
+                        ui.weak(format!(
+                            "{}, {} free of {}",
+                            volume.fs_type,
+                            format_size(volume.free_space as i64),
+                            format_size(volume.total_size as i64)
+                        ));
+                    });
+                }
+
+                ui.add_space(4.0);
+                if !self.status.is_empty() {
+                    ui.label(&self.status);
+                }
+                ui.horizontal(|ui| {
+                    if self.saved {
+                        close = ui.button("Close").clicked();
+                        return;
+                    }
+                    let can_save = self.pending_save.is_none() && self.selected.contains(&true);
+                    save = ui.add_enabled(can_save, egui::Button::new("Index selected")).clicked();
+                    close = ui.button("Not now").clicked();
+                    if self.pending_save.is_some() {
+                        ui.spinner();
+                    }
+                });
+            });
+        self.open = open && !close;
+
+        if save {
+            self.save(ctx);
+        }
+    }
+
+    /// Enable the ticked volumes and disable the others.
+    fn save(&mut self, ctx: &egui::Context) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending_save = Some(rx);
+        self.status.clear();
+
+        let choices: Vec<(String, bool)> = self
+            .volumes
+            .iter()
+            .zip(&self.selected)
+            .map(|(volume, selected)| (volume.name.clone(), *selected))
+            .collect();
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(set_volumes_enabled(&choices).await);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Pick up the replies of running requests.
+    fn check_pending(&mut self) {
+        if let Some(rx) = &self.pending_volumes {
+            match rx.try_recv() {
+                Ok(Ok(volumes)) => {
+                    if needs_setup(&volumes) {
+                        self.selected = default_selection(&volumes);
+                        self.volumes = volumes;
+                        self.open = true;
+                    }
+                    self.pending_volumes = None;
+                }
+                Ok(Err(e)) => {
+                    tracing::debug!("Not checking for unconfigured volumes: {}", e);
+                    self.pending_volumes = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.pending_volumes = None,
+            }
+        }
+
+        let Some(rx) = &self.pending_save else { return };
+        match rx.try_recv() {
+            Ok(Ok(count)) => {
+                self.saved = true;
+                self.status = format!(
+                    "Indexing {} volume{}; files become searchable as they are scanned.",
+                    count,
+                    if count == 1 { "" } else { "s" }
+                );
+            }
+            Ok(Err(e)) => self.status = format!("Could not save the selection: {}", e),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => self.status = "Could not save the selection".to_string(),
+        }
+        self.pending_save = None;
+    }
+}
+
+/// Whether volumes were detected but none of them is configured, as on a
+/// fresh install.
+fn needs_setup(volumes: &[DetectedVolume]) -> bool {
+    !volumes.is_empty() && !volumes.iter().any(|volume| volume.configured)
+}
+
+/// Volumes ticked when the window opens: those with a drive letter, not
+/// volumes mounted in a folder or without a mount point.
+fn default_selection(volumes: &[DetectedVolume]) -> Vec<bool> {
+    volumes.iter().map(|volume| volume_drive_letter(&volume.name).is_some()).collect()
+}
+
+/// Send the choice for each volume to the service.
+///
+/// # Returns
+/// The number of volumes enabled.
+async fn set_volumes_enabled(choices: &[(String, bool)]) -> Result<usize> {
+    let client = IpcClient::new();
+    for (volume, enabled) in choices {
+        let response = client.set_volume_enabled(volume, *enabled).await?;
+        if !response.success {
+            return Err(FFIError::Ipc(response.message));
+        }
+    }
+    Ok(choices.iter().filter(|(_, enabled)| *enabled).count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(name: &str, configured: bool) -> DetectedVolume {
+        DetectedVolume {
+            name: name.to_string(),
+            fs_type: "NTFS".to_string(),
+            total_size: 1000,
+            free_space: 400,
+            enabled: configured,
+            configured,
+        }
+    }
+
+    #[test]
+    fn test_needs_setup() {
+        assert!(!needs_setup(&[]));
+        assert!(needs_setup(&[volume("C:", false), volume("D:", false)]));
+        assert!(!needs_setup(&[volume("C:", false), volume("D:", true)]));
+
+        let volumes = [volume("C:", false), volume(r"C:\Mount\Data", false), volume(r"\\?\Volume{1234}\", false)];
+        assert_eq!(default_selection(&volumes), vec![true, false, false]);
+    }
+}
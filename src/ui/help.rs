@@ -0,0 +1,45 @@
+//! Search syntax section of the F1 help window.
+//!
+//! Renders the [`SyntaxHelp`] the service reports for its parser, so the
+//! help always matches what the running service accepts.
+
+use eframe::egui;
+
+use crate::search::{SyntaxHelp, SyntaxToken};
+
+/// Draw the search syntax: filters with examples, then operators and values.
+pub fn syntax_section(ui: &mut egui::Ui, help: &SyntaxHelp) {
+    egui::Grid::new("syntax_filters").striped(true).show(ui, |ui| {
+        for filter in &help.filters {
+            ui.monospace(format!("{}:", filter.name));
+            ui.label(&filter.description);
+            ui.monospace(filter.examples.join("  "));
+            ui.end_row();
+        }
+    });
+
+    ui.add_space(4.0);
+    token_grid(ui, "syntax_wildcards", "Wildcards", &help.wildcards);
+    token_grid(ui, "syntax_comparators", "Comparators", &help.comparators);
+    token_grid(ui, "syntax_units", "Size units", &help.size_units);
+    token_grid(ui, "syntax_dates", "Relative dates", &help.relative_dates);
+    ui.label(format!("Absolute dates: {}", help.date_format));
+    token_grid(ui, "syntax_sort", "Sort keys (-asc or -desc to reverse)", &help.sort_keys);
+
+    ui.add_space(4.0);
+    for note in &help.notes {
+        ui.label(format!("• {}", note));
+    }
+}
+
+/// Draw a titled two-column list of tokens and their meanings.
+fn token_grid(ui: &mut egui::Ui, id: &str, title: &str, tokens: &[SyntaxToken]) {
+    ui.strong(title);
+    egui::Grid::new(id).show(ui, |ui| {
+        for token in tokens {
+            ui.monospace(&token.token);
+            ui.label(&token.description);
+            ui.end_row();
+        }
+    });
+}
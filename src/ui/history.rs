@@ -0,0 +1,165 @@
+//! Navigation history for the search UI.
+//!
+//! Records executed queries and browsed folders so the user can step
+//! back and forward between them (Alt+Left / Alt+Right), like a browser.
+
+/// Maximum number of entries kept in the history.
+const MAX_HISTORY: usize = 100;
+
+/// A single navigation step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryEntry {
+    /// A search query typed by the user.
+    Query(String),
+    /// A folder browsed into from the results list.
+    Folder(String),
+}
+
+impl HistoryEntry {
+    /// Query text to restore into the search box for this entry.
+    pub fn to_query(&self) -> String {
+        match self {
+            HistoryEntry::Query(query) => query.clone(),
+            HistoryEntry::Folder(path) => format!("path:\"{}\"", path),
+        }
+    }
+}
+
+/// Back/forward navigation history.
+///
+/// Pushing a new entry while positioned in the middle of the history
+/// discards the forward entries, matching browser behavior.
+#[derive(Debug, Default)]
+pub struct NavigationHistory {
+    /// Recorded entries, oldest first.
+    entries: Vec<HistoryEntry>,
+    /// Index of the current entry (meaningless when entries is empty).
+    cursor: usize,
+}
+
+impl NavigationHistory {
+    /// Create an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a new navigation step.
+    ///
+    /// Consecutive duplicates are ignored.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.current() == Some(&entry) {
+            return;
+        }
+
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.cursor + 1);
+        }
+        self.entries.push(entry);
+
+        if self.entries.len() > MAX_HISTORY {
+            let excess = self.entries.len() - MAX_HISTORY;
+            self.entries.drain(..excess);
+        }
+
+        self.cursor = self.entries.len() - 1;
+    }
+
+    /// Get the current entry, if any.
+    pub fn current(&self) -> Option<&HistoryEntry> {
+        self.entries.get(self.cursor)
+    }
+
+    /// Step back one entry, returning the new current entry.
+    pub fn back(&mut self) -> Option<&HistoryEntry> {
+        if !self.can_go_back() {
+            return None;
+        }
+        self.cursor -= 1;
+        self.current()
+    }
+
+    /// Step forward one entry, returning the new current entry.
+    pub fn forward(&mut self) -> Option<&HistoryEntry> {
+        if !self.can_go_forward() {
+            return None;
+        }
+        self.cursor += 1;
+        self.current()
+    }
+
+    /// Whether there is an earlier entry to go back to.
+    pub fn can_go_back(&self) -> bool {
+        !self.entries.is_empty() && self.cursor > 0
+    }
+
+    /// Whether there is a later entry to go forward to.
+    pub fn can_go_forward(&self) -> bool {
+        self.cursor + 1 < self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(s: &str) -> HistoryEntry {
+        HistoryEntry::Query(s.to_string())
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut history = NavigationHistory::new();
+        history.push(query("a"));
+        history.push(query("b"));
+        history.push(query("c"));
+
+        assert_eq!(history.back(), Some(&query("b")));
+        assert_eq!(history.back(), Some(&query("a")));
+        assert_eq!(history.back(), None);
+        assert_eq!(history.forward(), Some(&query("b")));
+        assert_eq!(history.forward(), Some(&query("c")));
+        assert_eq!(history.forward(), None);
+    }
+
+    #[test]
+    fn test_push_discards_forward_entries() {
+        let mut history = NavigationHistory::new();
+        history.push(query("a"));
+        history.push(query("b"));
+        history.back();
+        history.push(query("c"));
+
+        assert!(!history.can_go_forward());
+        assert_eq!(history.back(), Some(&query("a")));
+    }
+
+    #[test]
+    fn test_consecutive_duplicates_ignored() {
+        let mut history = NavigationHistory::new();
+        history.push(query("a"));
+        history.push(query("a"));
+
+        assert!(!history.can_go_back());
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut history = NavigationHistory::new();
+        for i in 0..(MAX_HISTORY + 10) {
+            history.push(query(&i.to_string()));
+        }
+
+        let mut steps = 0;
+        while history.back().is_some() {
+            steps += 1;
+        }
+        assert_eq!(steps, MAX_HISTORY - 1);
+        assert_eq!(history.current(), Some(&query("10")));
+    }
+
+    #[test]
+    fn test_folder_entry_query() {
+        let entry = HistoryEntry::Folder(r"C:\Projects".to_string());
+        assert_eq!(entry.to_query(), r#"path:"C:\Projects""#);
+    }
+}
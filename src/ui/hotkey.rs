@@ -1,21 +1,198 @@
 //! Global hotkey registration and handling.
 //!
-//! Registers Ctrl+Space as the global hotkey to show/hide the search popup.
-//! Currently Windows-only using the global-hotkey crate.
+//! Registers the `[ui] hotkey` combination (Ctrl+Space by default) as the
+//! global hotkey to show/hide the search popup, and re-registers it when
+//! the configuration changes. Currently Windows-only using the
+//! global-hotkey crate.
+
+use std::fmt;
+use std::str::FromStr;
 
 use crate::{FFIError, Result};
 
 #[cfg(windows)]
 use global_hotkey::{GlobalHotKeyManager, GlobalHotKeyEvent, hotkey::{HotKey, Modifiers, Code}};
 
+#[cfg(windows)]
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[cfg(windows)]
+use std::sync::Arc;
+
 #[cfg(windows)]
 use std::thread;
 
+/// Hotkey used when none is configured.
+pub const DEFAULT_HOTKEY: &str = "Ctrl+Space";
+
+/// Key names accepted besides letters, digits and F1-F24, with the key
+/// code each stands for.
+const NAMED_KEYS: &[(&str, &str)] = &[
+    ("space", "Space"),
+    ("enter", "Enter"),
+    ("return", "Enter"),
+    ("tab", "Tab"),
+    ("esc", "Escape"),
+    ("escape", "Escape"),
+    ("backspace", "Backspace"),
+    ("delete", "Delete"),
+    ("del", "Delete"),
+    ("insert", "Insert"),
+    ("ins", "Insert"),
+    ("home", "Home"),
+    ("end", "End"),
+    ("pageup", "PageUp"),
+    ("pgup", "PageUp"),
+    ("pagedown", "PageDown"),
+    ("pgdn", "PageDown"),
+    ("up", "ArrowUp"),
+    ("down", "ArrowDown"),
+    ("left", "ArrowLeft"),
+    ("right", "ArrowRight"),
+    ("`", "Backquote"),
+    ("backquote", "Backquote"),
+    ("-", "Minus"),
+    ("minus", "Minus"),
+    ("=", "Equal"),
+    ("equal", "Equal"),
+    (",", "Comma"),
+    ("comma", "Comma"),
+    (".", "Period"),
+    ("period", "Period"),
+    ("/", "Slash"),
+    ("slash", "Slash"),
+    ("\\", "Backslash"),
+    ("backslash", "Backslash"),
+    (";", "Semicolon"),
+    ("semicolon", "Semicolon"),
+    ("'", "Quote"),
+    ("quote", "Quote"),
+    ("[", "BracketLeft"),
+    ("]", "BracketRight"),
+    ("pause", "Pause"),
+];
+
+/// A hotkey combination such as `Alt+Space` or `Ctrl+Shift+F`.
+///
+/// Parsed case-insensitively from modifiers (`Ctrl`, `Alt`, `Shift`,
+/// `Win`) and one key joined with `+`. Every key except F1-F24 needs a
+/// modifier, so the hotkey doesn't swallow normal typing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotkeyCombo {
+    /// Control key held
+    pub ctrl: bool,
+    /// Alt key held
+    pub alt: bool,
+    /// Shift key held
+    pub shift: bool,
+    /// Windows key held
+    pub win: bool,
+    /// Key code name (e.g. "Space", "KeyF", "Digit1", "F5")
+    pub key: String,
+}
+
+impl Default for HotkeyCombo {
+    fn default() -> Self {
+        DEFAULT_HOTKEY.parse().expect("default hotkey is valid")
+    }
+}
+
+impl FromStr for HotkeyCombo {
+    type Err = FFIError;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let invalid = |reason: &str| FFIError::Config(format!("Invalid hotkey {:?}: {}", text, reason));
+
+        let mut combo = HotkeyCombo {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            win: false,
+            key: String::new(),
+        };
+        for part in text.split('+').map(str::trim) {
+            let lower = part.to_ascii_lowercase();
+            let modifier = match lower.as_str() {
+                "ctrl" | "control" => &mut combo.ctrl,
+                "alt" => &mut combo.alt,
+                "shift" => &mut combo.shift,
+                "win" | "windows" | "super" | "meta" => &mut combo.win,
+                _ => {
+                    if !combo.key.is_empty() {
+                        return Err(invalid("more than one key"));
+                    }
+                    combo.key = key_code(&lower).ok_or_else(|| invalid(&format!("unknown key {:?}", part)))?;
+                    continue;
+                }
+            };
+            if *modifier {
+                return Err(invalid(&format!("{} given twice", part)));
+            }
+            *modifier = true;
+        }
+
+        if combo.key.is_empty() {
+            return Err(invalid("no key given"));
+        }
+        let function_key = combo.key.len() > 1 && combo.key.starts_with('F') && combo.key[1..].parse::<u8>().is_ok();
+        if !(combo.ctrl || combo.alt || combo.shift || combo.win || function_key) {
+            return Err(invalid("needs a modifier (Ctrl, Alt, Shift or Win)"));
+        }
+        Ok(combo)
+    }
+}
+
+impl fmt::Display for HotkeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [(self.ctrl, "Ctrl+"), (self.alt, "Alt+"), (self.shift, "Shift+"), (self.win, "Win+")];
+        for (held, name) in modifiers {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        let key = self.key.strip_prefix("Key").or_else(|| self.key.strip_prefix("Digit"));
+        f.write_str(key.unwrap_or(&self.key))
+    }
+}
+
+/// Key code name for a lowercase key name, if known.
+fn key_code(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_lowercase() {
+            return Some(format!("Key{}", c.to_ascii_uppercase()));
+        }
+        if c.is_ascii_digit() {
+            return Some(format!("Digit{}", c));
+        }
+    }
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=24).contains(&n).then(|| format!("F{}", n));
+    }
+    NAMED_KEYS.iter().find(|(alias, _)| *alias == name).map(|(_, code)| code.to_string())
+}
+
+/// Global hotkey for a combination.
+#[cfg(windows)]
+fn to_hotkey(combo: &HotkeyCombo) -> Result<HotKey> {
+    let code = Code::from_str(&combo.key)
+        .map_err(|_| FFIError::Config(format!("Unsupported hotkey key {:?}", combo.key)))?;
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::CONTROL, combo.ctrl);
+    modifiers.set(Modifiers::ALT, combo.alt);
+    modifiers.set(Modifiers::SHIFT, combo.shift);
+    modifiers.set(Modifiers::SUPER, combo.win);
+    Ok(HotKey::new(Some(modifiers).filter(|m| !m.is_empty()), code))
+}
+
 /// Manager for global hotkey registration.
 #[cfg(windows)]
 pub struct HotkeyManager {
     manager: GlobalHotKeyManager,
-    hotkey_id: u32,
+    hotkey: HotKey,
+    combo: HotkeyCombo,
+    /// Id of the registered hotkey, shared with the listener thread
+    hotkey_id: Arc<AtomicU32>,
     callback: Box<dyn Fn() + Send + 'static>,
 }
 
@@ -23,30 +200,62 @@ pub struct HotkeyManager {
 impl HotkeyManager {
     /// Create a new hotkey manager with the given callback.
     ///
-    /// Registers Ctrl+Space as the default hotkey.
-    pub fn new<F: Fn() + Send + 'static>(on_hotkey: F) -> Result<Self> {
+    /// # Arguments
+    /// * `combo` - Hotkey to register (see [`HotkeyCombo`])
+    /// * `on_hotkey` - Called each time the hotkey is pressed
+    pub fn new<F: Fn() + Send + 'static>(combo: &HotkeyCombo, on_hotkey: F) -> Result<Self> {
         let manager = GlobalHotKeyManager::new()
             .map_err(|e| FFIError::Ipc(format!("Failed to create hotkey manager: {}", e)))?;
 
-        // Register Ctrl+Space
-        let hotkey = HotKey::new(Some(Modifiers::CONTROL), Code::Space);
-        let hotkey_id = hotkey.id();
-
+        let hotkey = to_hotkey(combo)?;
         manager.register(hotkey)
-            .map_err(|e| FFIError::Ipc(format!("Failed to register hotkey Ctrl+Space: {}", e)))?;
+            .map_err(|e| FFIError::Ipc(format!("Failed to register hotkey {}: {}", combo, e)))?;
 
-        tracing::info!("Registered global hotkey: Ctrl+Space (id: {})", hotkey_id);
+        tracing::info!("Registered global hotkey: {} (id: {})", combo, hotkey.id());
 
         Ok(Self {
             manager,
-            hotkey_id,
+            hotkey,
+            combo: combo.clone(),
+            hotkey_id: Arc::new(AtomicU32::new(hotkey.id())),
             callback: Box::new(on_hotkey),
         })
     }
 
+    /// The registered hotkey.
+    pub fn hotkey(&self) -> &HotkeyCombo {
+        &self.combo
+    }
+
+    /// Replace the registered hotkey, keeping the old one if the new one
+    /// cannot be registered (e.g. another program holds it).
+    ///
+    /// Must be called on the thread that created the manager.
+    pub fn set_hotkey(&mut self, combo: &HotkeyCombo) -> Result<()> {
+        if *combo == self.combo {
+            return Ok(());
+        }
+
+        let hotkey = to_hotkey(combo)?;
+        self.manager.unregister(self.hotkey)
+            .map_err(|e| FFIError::Ipc(format!("Failed to unregister hotkey {}: {}", self.combo, e)))?;
+        if let Err(e) = self.manager.register(hotkey) {
+            if let Err(restore) = self.manager.register(self.hotkey) {
+                tracing::warn!("Failed to restore hotkey {}: {}", self.combo, restore);
+            }
+            return Err(FFIError::Ipc(format!("Failed to register hotkey {}: {}", combo, e)));
+        }
+
+        tracing::info!("Global hotkey changed from {} to {}", self.combo, combo);
+        self.hotkey_id.store(hotkey.id(), Ordering::SeqCst);
+        self.hotkey = hotkey;
+        self.combo = combo.clone();
+        Ok(())
+    }
+
     /// Start listening for hotkey events in a background thread.
     pub fn start(&mut self) -> Result<()> {
-        let hotkey_id = self.hotkey_id;
+        let hotkey_id = self.hotkey_id.clone();
 
         // We need to use a channel to forward events since the callback can't be cloned
         let (tx, rx) = std::sync::mpsc::channel();
@@ -55,7 +264,7 @@ impl HotkeyManager {
         thread::spawn(move || {
             loop {
                 if let Ok(event) = GlobalHotKeyEvent::receiver().recv() {
-                    if event.id == hotkey_id {
+                    if event.id == hotkey_id.load(Ordering::SeqCst) {
                         let _ = tx.send(());
                     }
                 }
@@ -90,7 +299,17 @@ pub struct HotkeyManager;
 #[cfg(not(windows))]
 impl HotkeyManager {
     /// Create a new hotkey manager (stub for non-Windows).
-    pub fn new<F: Fn() + Send + 'static>(_on_hotkey: F) -> Result<Self> {
+    pub fn new<F: Fn() + Send + 'static>(_combo: &HotkeyCombo, _on_hotkey: F) -> Result<Self> {
+        Err(FFIError::Ipc("Global hotkeys are only supported on Windows".to_string()))
+    }
+
+    /// The registered hotkey (stub for non-Windows, always the default).
+    pub fn hotkey(&self) -> &HotkeyCombo {
+        unreachable!("the stub manager cannot be created")
+    }
+
+    /// Replace the registered hotkey (stub for non-Windows).
+    pub fn set_hotkey(&mut self, _combo: &HotkeyCombo) -> Result<()> {
         Err(FFIError::Ipc("Global hotkeys are only supported on Windows".to_string()))
     }
 
@@ -99,3 +318,27 @@ impl HotkeyManager {
         Err(FFIError::Ipc("Global hotkeys are only supported on Windows".to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hotkey() {
+        let combo: HotkeyCombo = "alt + space".parse().unwrap();
+        assert!(combo.alt && !combo.ctrl && !combo.shift && !combo.win);
+        assert_eq!(combo.key, "Space");
+        assert_eq!(combo.to_string(), "Alt+Space");
+
+        let combo: HotkeyCombo = "Ctrl+Shift+f".parse().unwrap();
+        assert_eq!(combo.key, "KeyF");
+        assert_eq!(combo.to_string(), "Ctrl+Shift+F");
+        assert_eq!("Win+`".parse::<HotkeyCombo>().unwrap().key, "Backquote");
+        assert_eq!("F12".parse::<HotkeyCombo>().unwrap().key, "F12");
+        assert_eq!(HotkeyCombo::default().to_string(), DEFAULT_HOTKEY);
+
+        for invalid in ["", "Ctrl", "Space", "Ctrl+Ctrl+A", "Ctrl+A+B", "Ctrl+Hyper", "F25"] {
+            assert!(invalid.parse::<HotkeyCombo>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! File type icons for result rows.
+//!
+//! Each row first shows a bundled icon drawn for its kind (folder,
+//! document, image, ...). On Windows the shell's own icon (SHGetFileInfo)
+//! replaces it once a background thread has loaded it, so scrolling never
+//! waits on the shell. Shell icons are cached per extension, or per file
+//! for types whose icon is their own (programs, shortcuts, icon files).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+
+use eframe::egui;
+
+use crate::ipc::protocol::FileResult;
+
+/// Side length of a bundled icon, in pixels.
+pub const BUNDLED_ICON_SIZE: usize = 16;
+
+/// Most shell icons kept before the cache is emptied and reloaded.
+const MAX_CACHED_ICONS: usize = 4_096;
+
+/// Extensions whose icon is stored in the file itself.
+const PER_FILE_EXTENSIONS: &[&str] = &["exe", "lnk", "ico", "url", "msc", "cpl", "scr"];
+
+/// Kind of entry, picking the bundled icon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// Folder
+    Folder,
+    /// Text, office document or PDF
+    Document,
+    /// Picture
+    Image,
+    /// Sound or music
+    Audio,
+    /// Video
+    Video,
+    /// Compressed archive or disk image
+    Archive,
+    /// Source code, script or markup
+    Code,
+    /// Program or installer
+    Executable,
+    /// Anything else
+    Other,
+}
+
+impl FileKind {
+    /// Kind of an entry from its name.
+    pub fn of(name: &str, is_dir: bool) -> Self {
+        if is_dir {
+            return FileKind::Folder;
+        }
+        let extension = Path::new(name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "txt" | "md" | "pdf" | "doc" | "docx" | "rtf" | "odt" | "xls" | "xlsx" | "ods" | "csv" | "ppt"
+            | "pptx" | "odp" | "log" => FileKind::Document,
+            "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" | "ico" | "tif" | "tiff" | "heic" | "raw" => {
+                FileKind::Image
+            }
+            "mp3" | "wav" | "flac" | "ogg" | "m4a" | "aac" | "wma" | "opus" => FileKind::Audio,
+            "mp4" | "mkv" | "avi" | "mov" | "wmv" | "webm" | "m4v" | "mpg" | "mpeg" => FileKind::Video,
+            "zip" | "7z" | "rar" | "tar" | "gz" | "bz2" | "xz" | "zst" | "cab" | "iso" | "vhd" | "vhdx" => {
+                FileKind::Archive
+            }
+            "rs" | "c" | "h" | "cpp" | "hpp" | "cs" | "java" | "py" | "js" | "ts" | "go" | "rb" | "php"
+            | "html" | "css" | "json" | "xml" | "toml" | "yaml" | "yml" | "ps1" | "bat" | "cmd" | "sh" | "sql" => {
+                FileKind::Code
+            }
+            "exe" | "msi" | "com" | "dll" | "sys" | "appx" | "msix" | "lnk" => FileKind::Executable,
+            _ => FileKind::Other,
+        }
+    }
+
+    /// Icon colour.
+    fn color(&self) -> [u8; 3] {
+        match self {
+            FileKind::Folder => [0xe8, 0xb3, 0x39],
+            FileKind::Document => [0x3b, 0x7d, 0xd8],
+            FileKind::Image => [0x2e, 0xa0, 0x43],
+            FileKind::Audio => [0x9b, 0x59, 0xb6],
+            FileKind::Video => [0xd3, 0x45, 0x45],
+            FileKind::Archive => [0x8d, 0x6e, 0x4c],
+            FileKind::Code => [0x16, 0xa0, 0xa0],
+            FileKind::Executable => [0x5c, 0x6b, 0xc0],
+            FileKind::Other => [0x9a, 0x9a, 0x9a],
+        }
+    }
+}
+
+/// RGBA pixels of a bundled icon: a folder with a tab, or a page with a
+/// folded corner, in the kind's colour.
+pub fn bundled_rgba(kind: FileKind) -> Vec<u8> {
+    const SIZE: usize = BUNDLED_ICON_SIZE;
+    let [r, g, b] = kind.color();
+    let mut rgba = vec![0u8; SIZE * SIZE * 4];
+    let mut fill = |x: usize, y: usize, shade: u8| {
+        let i = (y * SIZE + x) * 4;
+        rgba[i..i + 4].copy_from_slice(&[r.saturating_sub(shade), g.saturating_sub(shade), b.saturating_sub(shade), 255]);
+    };
+
+    if kind == FileKind::Folder {
+        for y in 3..14 {
+            for x in 1..15 {
+                // Tab on the top left, darker
+                if y < 5 && x > 6 {
+                    continue;
+                }
+                fill(x, y, if y < 5 { 40 } else { 0 });
+            }
+        }
+    } else {
+        const FOLD: usize = 4;
+        for y in 1..15 {
+            for x in 3..13 {
+                let from_corner = (12 - x) + (y - 1);
+                if from_corner < FOLD {
+                    continue;
+                }
+                // Folded corner, darker
+                let shade = if y - 1 < FOLD && 12 - x < FOLD { 50 } else { 0 };
+                fill(x, y, shade);
+            }
+        }
+    }
+    rgba
+}
+
+/// Cache key of an entry's shell icon, and whether the shell must read the
+/// file itself (rather than just its extension) to find it.
+fn icon_key(path: &str, is_dir: bool) -> (String, bool) {
+    if is_dir {
+        return ("<folder>".to_string(), false);
+    }
+    match Path::new(path).extension().map(|ext| ext.to_string_lossy().to_ascii_lowercase()) {
+        Some(ext) if PER_FILE_EXTENSIONS.contains(&ext.as_str()) => (path.to_lowercase(), true),
+        Some(ext) => (format!(".{}", ext), false),
+        None => ("<file>".to_string(), false),
+    }
+}
+
+/// An icon request for the loader thread.
+#[cfg_attr(not(windows), allow(dead_code))]
+struct IconRequest {
+    key: String,
+    path: PathBuf,
+    is_dir: bool,
+    read_file: bool,
+}
+
+/// Textures of row icons, loading shell icons in the background.
+pub struct IconCache {
+    /// Bundled icon textures, created on first use.
+    bundled: HashMap<FileKind, egui::TextureHandle>,
+    /// Shell icon textures by key (None while loading, or if unavailable).
+    shell: HashMap<String, Option<egui::TextureHandle>>,
+    /// Requests to the loader thread (None if there are no shell icons).
+    requests: Option<Sender<IconRequest>>,
+    /// Icons loaded by the loader thread.
+    loaded: Receiver<(String, Option<egui::ColorImage>)>,
+}
+
+impl IconCache {
+    /// Create the cache, starting the loader thread on Windows.
+    ///
+    /// Loaded icons wake the UI through `ctx`.
+    pub fn new(ctx: &egui::Context) -> Self {
+        let (loaded_tx, loaded) = std::sync::mpsc::channel();
+        Self {
+            bundled: HashMap::new(),
+            shell: HashMap::new(),
+            requests: start_loader(ctx.clone(), loaded_tx),
+            loaded,
+        }
+    }
+
+    /// Texture to show for a result row: its shell icon once loaded,
+    /// otherwise the bundled icon for its kind.
+    pub fn icon(&mut self, ctx: &egui::Context, result: &FileResult) -> egui::TextureId {
+        while let Ok((key, image)) = self.loaded.try_recv() {
+            let texture = image.map(|image| ctx.load_texture(format!("icon:{}", key), image, Default::default()));
+            self.shell.insert(key, texture);
+        }
+
+        let (key, read_file) = icon_key(&result.path, result.is_dir);
+        match self.shell.get(&key) {
+            Some(Some(texture)) => return texture.id(),
+            Some(None) => {}
+            None => {
+                if let Some(requests) = &self.requests {
+                    if self.shell.len() >= MAX_CACHED_ICONS {
+                        self.shell.clear();
+                    }
+                    let request = IconRequest {
+                        key: key.clone(),
+                        path: PathBuf::from(&result.path),
+                        is_dir: result.is_dir,
+                        read_file,
+                    };
+                    if requests.send(request).is_ok() {
+                        self.shell.insert(key, None);
+                    }
+                }
+            }
+        }
+
+        let kind = FileKind::of(&result.name, result.is_dir);
+        self.bundled
+            .entry(kind)
+            .or_insert_with(|| {
+                let image = egui::ColorImage::from_rgba_unmultiplied(
+                    [BUNDLED_ICON_SIZE, BUNDLED_ICON_SIZE],
+                    &bundled_rgba(kind),
+                );
+                ctx.load_texture(format!("icon:{:?}", kind), image, Default::default())
+            })
+            .id()
+    }
+}
+
+/// Start the thread loading shell icons; it stops when the cache is dropped.
+#[cfg(windows)]
+fn start_loader(ctx: egui::Context, loaded: Sender<(String, Option<egui::ColorImage>)>) -> Option<Sender<IconRequest>> {
+    use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED};
+
+    let (tx, requests) = std::sync::mpsc::channel::<IconRequest>();
+    let spawned = std::thread::Builder::new().name("icon-loader".to_string()).spawn(move || {
+        // SHGetFileInfo needs COM on the calling thread
+        let com = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) }.is_ok();
+        while let Ok(request) = requests.recv() {
+            let image = shell_icon(&request.path, request.is_dir, request.read_file);
+            if loaded.send((request.key, image)).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+        if com {
+            unsafe { CoUninitialize() };
+        }
+    });
+
+    match spawned {
+        Ok(_) => Some(tx),
+        Err(e) => {
+            tracing::warn!("Failed to start icon loader, using bundled icons: {}", e);
+            None
+        }
+    }
+}
+
+/// Shell icons are Windows-only; every row keeps its bundled icon.
+#[cfg(not(windows))]
+fn start_loader(_ctx: egui::Context, _loaded: Sender<(String, Option<egui::ColorImage>)>) -> Option<Sender<IconRequest>> {
+    None
+}
+
+/// Small shell icon of a path, or None if the shell has none.
+///
+/// Without `read_file` only the extension is looked at, so the entry
+/// doesn't need to exist and its volume isn't touched.
+#[cfg(windows)]
+fn shell_icon(path: &Path, is_dir: bool, read_file: bool) -> Option<egui::ColorImage> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::{FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_NORMAL};
+    use windows::Win32::UI::Shell::{
+        SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, SHGFI_SMALLICON, SHGFI_USEFILEATTRIBUTES,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::DestroyIcon;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let attributes = if is_dir { FILE_ATTRIBUTE_DIRECTORY } else { FILE_ATTRIBUTE_NORMAL };
+    let flags = if read_file {
+        SHGFI_ICON | SHGFI_SMALLICON
+    } else {
+        SHGFI_ICON | SHGFI_SMALLICON | SHGFI_USEFILEATTRIBUTES
+    };
+
+    let mut info = SHFILEINFOW::default();
+    let found = unsafe {
+        SHGetFileInfoW(
+            PCWSTR::from_raw(wide.as_ptr()),
+            attributes,
+            Some(&mut info as *mut SHFILEINFOW),
+            std::mem::size_of::<SHFILEINFOW>() as u32,
+            flags,
+        )
+    };
+    if found == 0 || info.hIcon.is_invalid() {
+        return None;
+    }
+
+    let image = icon_image(info.hIcon);
+    unsafe {
+        let _ = DestroyIcon(info.hIcon);
+    }
+    image
+}
+
+/// Pixels of an icon handle.
+#[cfg(windows)]
+fn icon_image(icon: windows::Win32::UI::WindowsAndMessaging::HICON) -> Option<egui::ColorImage> {
+    use windows::Win32::Graphics::Gdi::DeleteObject;
+    use windows::Win32::UI::WindowsAndMessaging::{GetIconInfo, ICONINFO};
+
+    let mut info = ICONINFO::default();
+    unsafe { GetIconInfo(icon, &mut info) }.ok()?;
+
+    // Monochrome icons have no colour bitmap
+    let image = if info.hbmColor.is_invalid() {
+        None
+    } else {
+        bitmap_bgra(info.hbmColor).map(|(width, height, mut bgra)| {
+            // Icons without an alpha channel are masked instead
+            if bgra.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+                let mask = bitmap_bgra(info.hbmMask).filter(|(w, h, _)| (*w, *h) == (width, height));
+                for (i, pixel) in bgra.chunks_exact_mut(4).enumerate() {
+                    let transparent = mask.as_ref().is_some_and(|(_, _, mask)| mask[i * 4] != 0);
+                    pixel[3] = if transparent { 0 } else { 255 };
+                }
+            }
+            let rgba: Vec<u8> = bgra.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect();
+            egui::ColorImage::from_rgba_unmultiplied([width, height], &rgba)
+        })
+    };
+
+    unsafe {
+        if !info.hbmColor.is_invalid() {
+            let _ = DeleteObject(info.hbmColor.into());
+        }
+        let _ = DeleteObject(info.hbmMask.into());
+    }
+    image
+}
+
+/// Size and top-down 32-bit BGRA pixels of a bitmap.
+#[cfg(windows)]
+fn bitmap_bgra(bitmap: windows::Win32::Graphics::Gdi::HBITMAP) -> Option<(usize, usize, Vec<u8>)> {
+    use windows::Win32::Graphics::Gdi::{
+        GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+    };
+
+    let mut header = BITMAP::default();
+    let size = std::mem::size_of::<BITMAP>() as i32;
+    if unsafe { GetObjectW(bitmap.into(), size, Some((&mut header as *mut BITMAP).cast())) } == 0 {
+        return None;
+    }
+    let (width, height) = (header.bmWidth, header.bmHeight);
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+
+    let mut info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width,
+            // Negative for top-down rows
+            biHeight: -height,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    let rows = unsafe {
+        let dc = GetDC(None);
+        let rows = GetDIBits(
+            dc,
+            bitmap,
+            0,
+            height as u32,
+            Some(pixels.as_mut_ptr().cast()),
+            &mut info,
+            DIB_RGB_COLORS,
+        );
+        ReleaseDC(None, dc);
+        rows
+    };
+    (rows == height).then_some((width as usize, height as usize, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_kind() {
+        assert_eq!(FileKind::of("Docs", true), FileKind::Folder);
+        assert_eq!(FileKind::of("Report.PDF", false), FileKind::Document);
+        assert_eq!(FileKind::of("main.rs", false), FileKind::Code);
+        assert_eq!(FileKind::of("setup.exe", false), FileKind::Executable);
+        assert_eq!(FileKind::of("Makefile", false), FileKind::Other);
+
+        assert_eq!(icon_key(r"C:\Docs\a.TXT", false), (".txt".to_string(), false));
+        assert_eq!(icon_key(r"C:\Docs", true), ("<folder>".to_string(), false));
+        assert_eq!(icon_key(r"C:\Apps\Tool.exe", false), (r"c:\apps\tool.exe".to_string(), true));
+
+        let rgba = bundled_rgba(FileKind::Folder);
+        assert_eq!(rgba.len(), BUNDLED_ICON_SIZE * BUNDLED_ICON_SIZE * 4);
+        // Transparent corner, opaque body
+        let body = (8 * BUNDLED_ICON_SIZE + 8) * 4 + 3;
+        assert_eq!((rgba[3], rgba[body]), (0, 255));
+    }
+}
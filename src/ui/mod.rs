@@ -3,10 +3,24 @@
 //! Provides the egui-based search popup with global hotkey activation,
 //! keyboard navigation, and file actions.
 
+pub mod accessibility;
 pub mod app;
+pub mod export;
+pub mod first_run;
+pub mod help;
+pub mod history;
 pub mod hotkey;
+pub mod icons;
 pub mod results;
+pub mod settings;
+pub mod state;
+pub mod suggestions;
+pub mod tags;
+pub mod tray;
+pub mod usage;
 pub mod actions;
 
 pub use app::SearchApp;
+pub use history::{HistoryEntry, NavigationHistory};
 pub use hotkey::HotkeyManager;
+pub use state::{PopupMode, PopupState};
@@ -1,36 +1,78 @@
 //! Search results list view.
 //!
 //! Renders the file results with virtual scrolling for performance
-//! with large result sets.
+//! with large result sets. Each row shows its file type icon (see
+//! [`IconCache`]). Right-clicking a row opens a context menu of
+//! file actions. Results on offline volumes are grayed out and flagged;
+//! results in the Recycle Bin show where they were deleted from.
 
 use eframe::egui::{self, ScrollArea, Sense};
 
-use crate::ipc::protocol::FileResult;
+use crate::ipc::protocol::{FileResult, ResultSource};
+
+use super::accessibility;
+use super::actions::ResultAction;
+use super::icons::IconCache;
+
+/// Height of a result row, excluding item spacing.
+const ROW_HEIGHT: f32 = 24.0;
 
 /// View for displaying search results.
 pub struct ResultsView;
 
+/// What happened in the results list this frame.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResultsOutput {
+    /// Index of a clicked row, if any
+    pub clicked: Option<usize>,
+    /// Row and action picked from a row's context menu, if any
+    pub action: Option<(usize, ResultAction)>,
+    /// Current vertical scroll offset, in points
+    pub scroll_offset: f32,
+    /// Height of the visible part of the list, in points
+    pub viewport_height: f32,
+    /// Widget ID of the selected row, if it was drawn this frame
+    pub selected_row: Option<egui::Id>,
+}
+
 impl ResultsView {
     /// Display the results list.
     ///
-    /// Returns the index of a clicked row, if any.
-    pub fn show(ui: &mut egui::Ui, results: &[FileResult], selected: usize) -> Option<usize> {
+    /// # Arguments
+    /// * `ui` - UI to draw into
+    /// * `results` - Rows to show
+    /// * `icons` - File type icons for the rows
+    /// * `selected` - Highlighted row
+    /// * `scroll_to` - Scroll offset to restore this frame, if any
+    pub fn show(
+        ui: &mut egui::Ui,
+        results: &[FileResult],
+        icons: &mut IconCache,
+        selected: usize,
+        scroll_to: Option<f32>,
+    ) -> ResultsOutput {
         let mut clicked_index = None;
+        let mut picked_action = None;
+        let mut selected_row = None;
 
         if results.is_empty() {
             ui.centered_and_justified(|ui| {
                 ui.label("No results. Start typing to search.");
             });
-            return None;
+            return ResultsOutput::default();
         }
 
         // Use ScrollArea with show_rows for virtual scrolling
-        let row_height = 24.0;
+        let row_height = ROW_HEIGHT;
         let available_height = ui.available_height();
         let _visible_rows = (available_height / row_height).ceil() as usize;
 
-        ScrollArea::vertical()
-            .auto_shrink([false, false])
+        let mut scroll_area = ScrollArea::vertical().auto_shrink([false, false]);
+        if let Some(offset) = scroll_to {
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+
+        let output = scroll_area
             .show_rows(ui, row_height, results.len(), |ui, row_range| {
                 for i in row_range {
                     if let Some(result) = results.get(i) {
@@ -48,12 +90,16 @@ impl ResultsView {
                                 );
                             }
 
-                            // Icon based on type
-                            let icon = if result.is_dir { "D " } else { "F " };
-                            ui.monospace(icon);
+                            // File type icon
+                            let icon = icons.icon(ui.ctx(), result);
+                            ui.image((icon, egui::vec2(16.0, 16.0)));
 
-                            // Filename (prominent)
-                            ui.strong(&result.name);
+                            // Filename (prominent, unless its volume is disconnected)
+                            if result.is_offline() {
+                                ui.weak(&result.name);
+                            } else {
+                                ui.strong(&result.name);
+                            }
 
                             // Spacer
                             ui.add_space(10.0);
@@ -61,6 +107,26 @@ impl ResultsView {
                             // Path (dimmed)
                             ui.weak(&result.path);
 
+                            // Results proxied from Windows Search are not from the index
+                            if result.source == ResultSource::WindowsSearch {
+                                ui.weak("[Windows Search]");
+                            }
+
+                            // The volume's files can't be opened until it is reconnected
+                            if result.is_offline() {
+                                ui.weak("[offline]");
+                            }
+
+                            // Recycle Bin items are renamed; their original path is what users know
+                            if let Some(original) = &result.original_path {
+                                ui.weak(format!("[deleted from {}]", original));
+                            }
+
+                            // Other links collapsed into this row
+                            if result.duplicates > 0 {
+                                ui.weak(format!("(+{} links)", result.duplicates));
+                            }
+
                             // Right-aligned info
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                                 // Modified date
@@ -75,19 +141,110 @@ impl ResultsView {
                         });
 
                         // Make the row clickable
-                        if ui.interact(response.response.rect, egui::Id::new(("result", i)), Sense::click()).clicked() {
+                        let row = ui.interact(response.response.rect, egui::Id::new(("result", i)), Sense::click());
+                        if row.clicked() {
                             clicked_index = Some(i);
                         }
+                        row.context_menu(|ui| {
+                            for action in ResultAction::ALL {
+                                if action.starts_group() {
+                                    ui.separator();
+                                }
+                                // Only indexed entries can be tagged
+                                let enabled = action.applies_to(result.is_dir)
+                                    && !(action == ResultAction::Tags && result.source == ResultSource::WindowsSearch);
+                                if ui.add_enabled(enabled, egui::Button::new(action.label())).clicked() {
+                                    picked_action = Some((i, action));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+
+                        // Screen readers get the whole row as one list item
+                        row.widget_info(|| {
+                            egui::WidgetInfo::selected(
+                                egui::WidgetType::SelectableLabel,
+                                true,
+                                is_selected,
+                                accessible_label(result),
+                            )
+                        });
+                        accessibility::mark_list_item(ui.ctx(), row.id, is_selected);
+                        if is_selected {
+                            selected_row = Some(row.id);
+                        }
                     }
                 }
 
                 // Note: Scroll-to-selected is handled by egui's scroll area memory
             });
 
-        clicked_index
+        ResultsOutput {
+            clicked: clicked_index,
+            action: picked_action,
+            scroll_offset: output.state.offset.y,
+            viewport_height: output.inner_rect.height(),
+            selected_row,
+        }
+    }
+
+    /// Distance between the tops of consecutive rows, in points.
+    pub fn row_span(style: &egui::Style) -> f32 {
+        ROW_HEIGHT + style.spacing.item_spacing.y
     }
 }
 
+/// Scroll offset that brings a row fully into view.
+///
+/// # Arguments
+/// * `index` - Row to reveal
+/// * `row_span` - Distance between row tops (see [`ResultsView::row_span`])
+/// * `offset` - Current scroll offset
+/// * `viewport_height` - Height of the visible part of the list
+///
+/// # Returns
+/// The new offset, or None if the row is already visible.
+pub fn reveal_offset(index: usize, row_span: f32, offset: f32, viewport_height: f32) -> Option<f32> {
+    let top = index as f32 * row_span;
+    let bottom = top + row_span;
+
+    if top < offset {
+        Some(top)
+    } else if bottom > offset + viewport_height && viewport_height > 0.0 {
+        Some((bottom - viewport_height).max(0.0))
+    } else {
+        None
+    }
+}
+
+/// Text a screen reader announces for a result row.
+///
+/// Example: "report.pdf, file, C:\Docs\report.pdf, 1.2 MB, modified 2024-01-15 14:30"
+pub fn accessible_label(result: &FileResult) -> String {
+    let mut parts = vec![result.name.clone()];
+    parts.push(if result.is_dir { "folder" } else { "file" }.to_string());
+    parts.push(result.path.clone());
+    if !result.is_dir {
+        parts.push(format_size(result.size));
+    }
+    if result.modified > 0 {
+        parts.push(format!("modified {}", format_date(result.modified)));
+    }
+    if result.duplicates > 0 {
+        parts.push(format!("{} more links", result.duplicates));
+    }
+    if result.source == ResultSource::WindowsSearch {
+        parts.push("from Windows Search".to_string());
+    }
+    if result.is_offline() {
+        parts.push("on an offline volume".to_string());
+    }
+    if let Some(original) = &result.original_path {
+        parts.push(format!("in the Recycle Bin, deleted from {}", original));
+    }
+    parts.join(", ")
+}
+
 /// Format file size in human-readable format.
 ///
 /// Examples: "1.2 MB", "340 KB", "4.5 GB"
@@ -114,6 +271,21 @@ pub fn format_size(bytes: i64) -> String {
     }
 }
 
+/// Format a count with thousands separators.
+///
+/// Examples: "42", "1,204", "3,500,000"
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
 /// Format Unix timestamp as date string.
 ///
 /// Format: "2024-01-15 14:30"
@@ -146,6 +318,61 @@ mod tests {
         assert_eq!(format_size(-1), "---");
     }
 
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1204), "1,204");
+        assert_eq!(format_count(3500000), "3,500,000");
+    }
+
+    #[test]
+    fn test_reveal_offset() {
+        // Rows 0-3 visible at offset 0 with a 100pt viewport and 27pt rows
+        assert_eq!(reveal_offset(2, 27.0, 0.0, 100.0), None);
+        assert_eq!(reveal_offset(4, 27.0, 0.0, 100.0), Some(35.0));
+        assert_eq!(reveal_offset(1, 27.0, 54.0, 100.0), Some(27.0));
+    }
+
+    #[test]
+    fn test_accessible_label() {
+        let result = FileResult {
+            id: 1,
+            name: "report.pdf".to_string(),
+            path: r"C:\Docs\report.pdf".to_string(),
+            size: 2048,
+            modified: 0,
+            is_dir: false,
+            duplicates: 1,
+            source: ResultSource::Index,
+            rank: None,
+            volume_state: None,
+            original_path: None,
+        };
+        assert_eq!(
+            accessible_label(&result),
+            r"report.pdf, file, C:\Docs\report.pdf, 2.0 KB, 1 more links"
+        );
+
+        let offline = FileResult {
+            volume_state: Some("offline".to_string()),
+            duplicates: 0,
+            ..result
+        };
+        assert_eq!(
+            accessible_label(&offline),
+            r"report.pdf, file, C:\Docs\report.pdf, 2.0 KB, on an offline volume"
+        );
+
+        let recycled = FileResult {
+            name: "$RAB12CD.pdf".to_string(),
+            path: r"C:\$Recycle.Bin\S-1-5-21-1000\$RAB12CD.pdf".to_string(),
+            original_path: Some(r"C:\Docs\report.pdf".to_string()),
+            ..offline.clone()
+        };
+        assert!(accessible_label(&recycled).ends_with(r"in the Recycle Bin, deleted from C:\Docs\report.pdf"));
+    }
+
     #[test]
     fn test_format_date() {
         // Test invalid timestamp
@@ -0,0 +1,233 @@
+//! Settings window.
+//!
+//! Shows the statistics stored for each volume by its last full scan, the
+//! exclusion suggestions the service stored after its last scan, with
+//! one-click adoption into `[exclude] paths` in config, and offline volumes
+//! pending cleanup with keep-forever and purge-now actions.
+
+use eframe::egui;
+use tokio::runtime::Handle;
+
+use crate::db::{
+    configure_database, get_all_volumes, get_exclusion_suggestions, get_offline_volumes, get_volume_stats,
+    open_database_read_only, ExclusionSuggestion, OfflineVolume, VolumeStats,
+};
+use crate::ipc::{Command, IpcClient};
+use crate::service::config::Config;
+use crate::Result;
+
+use super::results::{format_count, format_date, format_size};
+
+/// A volume's drive letter with its stored scan statistics.
+type VolumeRow = (String, VolumeStats);
+
+/// Settings window state.
+pub struct SettingsView {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// Runtime for service commands.
+    runtime: Handle,
+    /// Indexed volumes with their stored scan statistics.
+    volumes: Vec<VolumeRow>,
+    /// Exclusion suggestions not yet adopted.
+    suggestions: Vec<ExclusionSuggestion>,
+    /// Offline volumes and when their index is deleted.
+    offline: Vec<OfflineVolume>,
+    /// Outcome of the last load or action.
+    status: String,
+}
+
+impl SettingsView {
+    /// Create a closed settings window.
+    pub fn new(runtime: Handle) -> Self {
+        Self {
+            open: false,
+            runtime,
+            volumes: Vec::new(),
+            suggestions: Vec::new(),
+            offline: Vec::new(),
+            status: String::new(),
+        }
+    }
+
+    /// Open the window, reloading its contents from the index.
+    pub fn show_window(&mut self) {
+        self.open = true;
+        self.reload();
+    }
+
+    /// Reload volume statistics, suggestions and offline volumes from the index.
+    fn reload(&mut self) {
+        match load_settings_data() {
+            Ok((volumes, suggestions, offline)) => {
+                self.status.clear();
+                self.volumes = volumes;
+                self.suggestions = suggestions;
+                self.offline = offline;
+            }
+            Err(e) => {
+                self.volumes.clear();
+                self.suggestions.clear();
+                self.offline.clear();
+                self.status = format!("Cannot read index: {}", e);
+            }
+        }
+    }
+
+    /// Draw the window if open.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        let mut adopted: Option<usize> = None;
+        let mut command: Option<Command> = None;
+
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.heading("Indexed volumes");
+                if self.volumes.is_empty() {
+                    ui.weak("No volumes indexed yet.");
+                }
+                for (drive_letter, stats) in &self.volumes {
+                    ui.horizontal(|ui| {
+                        ui.label(drive_letter);
+                        ui.weak(format!(
+                            "{} files, {} folders, {}",
+                            format_count(stats.file_count.max(0) as usize),
+                            format_count(stats.dir_count.max(0) as usize),
+                            format_size(stats.total_bytes)
+                        ));
+                        if let Some(scanned) = stats.last_scan_time {
+                            ui.weak(format!(
+                                "scanned {} in {:.1}s",
+                                format_date(scanned),
+                                stats.last_scan_duration_ms as f64 / 1000.0
+                            ));
+                        }
+                    });
+                }
+
+                ui.separator();
+                ui.heading("Suggested excludes");
+                ui.weak("Large or constantly changing folders that are rarely worth searching.");
+                ui.add_space(4.0);
+
+                egui::ScrollArea::vertical().id_salt("suggestions").max_height(240.0).show(ui, |ui| {
+                    if self.suggestions.is_empty() {
+                        ui.weak("No exclusion suggestions.");
+                    }
+                    for (i, suggestion) in self.suggestions.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.button("Exclude").clicked() {
+                                adopted = Some(i);
+                            }
+                            ui.label(&suggestion.path);
+                            ui.weak(format!(
+                                "{} ({} entries)",
+                                suggestion.reason,
+                                format_count(suggestion.file_count.max(0) as usize)
+                            ));
+                        });
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Offline volumes");
+                if self.offline.is_empty() {
+                    ui.weak("No offline volumes.");
+                }
+                for offline in &self.offline {
+                    let drive_letter = offline.volume.drive_letter.clone();
+                    ui.horizontal(|ui| {
+                        ui.label(&drive_letter);
+                        match offline.purge_after {
+                            Some(purge_after) => {
+                                ui.weak(format!("index deleted after {}", format_date(purge_after)));
+                                if ui.button("Keep forever").clicked() {
+                                    command = Some(Command::KeepVolume {
+                                        drive_letter: drive_letter.clone(),
+                                        keep: true,
+                                    });
+                                }
+                            }
+                            None => {
+                                ui.weak("kept forever");
+                                if ui.button("Stop keeping").clicked() {
+                                    command = Some(Command::KeepVolume {
+                                        drive_letter: drive_letter.clone(),
+                                        keep: false,
+                                    });
+                                }
+                            }
+                        }
+                        if ui.button("Purge now").clicked() {
+                            command = Some(Command::PurgeVolume {
+                                drive_letter: drive_letter.clone(),
+                            });
+                        }
+                    });
+                }
+
+                if !self.status.is_empty() {
+                    ui.separator();
+                    ui.label(&self.status);
+                }
+            });
+
+        if let Some(i) = adopted {
+            let suggestion = self.suggestions.remove(i);
+            self.status = match adopt_exclusion(&suggestion.path) {
+                Ok(()) => format!("Added {} to excluded paths.", suggestion.path),
+                Err(e) => {
+                    self.suggestions.insert(i, suggestion);
+                    format!("Failed to save config: {}", e)
+                }
+            };
+        }
+
+        if let Some(command) = command {
+            let client = IpcClient::new();
+            let status = match self.runtime.block_on(client.send_command(&command)) {
+                Ok(response) => response.message,
+                Err(e) => format!("Service unavailable: {}", e),
+            };
+            self.reload();
+            self.status = status;
+        }
+
+        self.open = open;
+    }
+}
+
+/// Read volume statistics, stored suggestions and offline volumes from the
+/// service database.
+fn load_settings_data() -> Result<(Vec<VolumeRow>, Vec<ExclusionSuggestion>, Vec<OfflineVolume>)> {
+    let config = Config::load()?;
+    if let Err(e) = configure_database(config.database.clone()) {
+        tracing::warn!("Invalid [database] settings, using defaults: {}", e);
+    }
+    let db = open_database_read_only(&config.data_dir().join("index.db"))?;
+
+    let mut volumes = Vec::new();
+    for volume in get_all_volumes(db.conn())? {
+        let stats = get_volume_stats(db.conn(), volume.id)?;
+        volumes.push((volume.drive_letter, stats));
+    }
+
+    Ok((
+        volumes,
+        get_exclusion_suggestions(db.conn())?,
+        get_offline_volumes(db.conn(), config.offline_retention())?,
+    ))
+}
+
+/// Add a path to `[exclude] paths` in config, unless already excluded.
+fn adopt_exclusion(path: &str) -> Result<()> {
+    let mut config = Config::load()?;
+    if !config.exclude.should_exclude(path, true) {
+        config.exclude.paths.push(path.to_string());
+        config.save()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+//! Search popup state kept between popups.
+//!
+//! The query, selection and scroll position are saved through eframe's
+//! persistence when the popup hides and on exit, so reopening it resumes
+//! where the user left off even if the process was restarted in between.
+//! With `popup = "fresh"` under `[ui]` every popup starts empty instead.
+
+use serde::{Deserialize, Serialize};
+
+/// Storage key of the saved popup state.
+const POPUP_STATE_KEY: &str = "ffi_popup_state";
+
+/// What the popup shows when it is opened again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PopupMode {
+    /// Restore the last query, selection and scroll position
+    #[default]
+    Resume,
+    /// Start with an empty search box
+    Fresh,
+}
+
+/// Snapshot of the popup taken when it hides.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PopupState {
+    /// Search box text
+    pub query: String,
+    /// Selected result row
+    pub selected_index: usize,
+    /// Vertical scroll offset of the results list, in points
+    pub scroll_offset: f32,
+}
+
+impl PopupState {
+    /// State to open the popup with.
+    ///
+    /// # Arguments
+    /// * `mode` - Configured popup mode
+    /// * `saved` - State saved when the popup last hid, if any
+    pub fn initial(mode: PopupMode, saved: Option<PopupState>) -> Self {
+        match mode {
+            PopupMode::Resume => saved.unwrap_or_default(),
+            PopupMode::Fresh => Self::default(),
+        }
+    }
+
+    /// Load the saved state from eframe storage.
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Option<Self> {
+        eframe::get_value(storage?, POPUP_STATE_KEY)
+    }
+
+    /// Save the state to eframe storage.
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, POPUP_STATE_KEY, self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_state() {
+        let saved = PopupState {
+            query: "report ext:pdf".to_string(),
+            selected_index: 12,
+            scroll_offset: 240.0,
+        };
+
+        assert_eq!(PopupState::initial(PopupMode::Resume, Some(saved.clone())), saved);
+        assert_eq!(PopupState::initial(PopupMode::Resume, None), PopupState::default());
+        assert_eq!(PopupState::initial(PopupMode::Fresh, Some(saved)), PopupState::default());
+    }
+}
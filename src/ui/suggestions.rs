@@ -0,0 +1,109 @@
+//! Filter suggestions for the search UI.
+//!
+//! Offers common filters as clickable chips below the search box, narrowed
+//! by prefix while the user is partway through typing a filter (e.g. `ext:p`).
+//! Each chip shows how many results the query would have with it applied.
+
+/// Filters offered as suggestions, in display order.
+const SUGGESTED_FILTERS: &[&str] = &[
+    "ext:pdf",
+    "ext:docx",
+    "ext:xlsx",
+    "ext:jpg",
+    "ext:png",
+    "ext:mp4",
+    "ext:zip",
+    "ext:exe",
+    "type:folder",
+    "type:file",
+    "type:link",
+    "size:>100mb",
+    "modified:today",
+    "modified:lastweek",
+    "attrib:hidden",
+];
+
+/// Maximum number of suggestions shown at once.
+pub const MAX_SUGGESTIONS: usize = 6;
+
+/// Get the token currently being typed (empty if the query ends in whitespace).
+fn partial_token(query: &str) -> &str {
+    if query.ends_with(char::is_whitespace) {
+        return "";
+    }
+    query.rsplit(char::is_whitespace).next().unwrap_or("")
+}
+
+/// Get filter suggestions for the current query text.
+///
+/// If the last token looks like a filter in progress (contains `:`), only
+/// filters starting with it are offered. Filters already present in the
+/// query are never suggested.
+pub fn suggest_filters(query: &str) -> Vec<String> {
+    let partial = partial_token(query).to_lowercase();
+    let present: Vec<String> = query
+        .split_whitespace()
+        .map(|t| t.to_lowercase())
+        .collect();
+
+    SUGGESTED_FILTERS
+        .iter()
+        .filter(|s| !present.iter().any(|p| p == *s))
+        .filter(|s| !partial.contains(':') || s.starts_with(&partial))
+        .take(MAX_SUGGESTIONS)
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Apply a suggestion to the query.
+///
+/// Replaces a partially typed filter token, or appends the filter otherwise.
+pub fn apply_suggestion(query: &str, suggestion: &str) -> String {
+    let partial = partial_token(query);
+
+    if partial.contains(':') {
+        let base = &query[..query.len() - partial.len()];
+        format!("{}{}", base, suggestion)
+    } else if query.trim().is_empty() {
+        suggestion.to_string()
+    } else {
+        format!("{} {}", query.trim_end(), suggestion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions_for_partial_filter() {
+        let suggestions = suggest_filters("report ext:p");
+        assert_eq!(suggestions, vec!["ext:pdf", "ext:png"]);
+    }
+
+    #[test]
+    fn test_suggestions_skip_present_filters() {
+        let suggestions = suggest_filters("report ext:pdf ");
+        assert!(!suggestions.contains(&"ext:pdf".to_string()));
+        assert_eq!(suggestions.len(), MAX_SUGGESTIONS);
+    }
+
+    #[test]
+    fn test_suggestions_for_plain_word() {
+        let suggestions = suggest_filters("report");
+        assert_eq!(suggestions[0], "ext:pdf");
+    }
+
+    #[test]
+    fn test_apply_suggestion_replaces_partial() {
+        assert_eq!(apply_suggestion("report ext:p", "ext:pdf"), "report ext:pdf");
+        assert_eq!(apply_suggestion("ext:p", "ext:pdf"), "ext:pdf");
+    }
+
+    #[test]
+    fn test_apply_suggestion_appends() {
+        assert_eq!(apply_suggestion("report", "type:file"), "report type:file");
+        assert_eq!(apply_suggestion("report ", "type:file"), "report type:file");
+        assert_eq!(apply_suggestion("", "type:file"), "type:file");
+    }
+}
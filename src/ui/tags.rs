@@ -0,0 +1,188 @@
+//! Tag editor: shows and changes the tags of one result.
+//!
+//! Opened from the result context menu. Tags are stored by the service
+//! (see [`IpcClient::add_tags`]), so they can be searched with `tag:` from
+//! any client.
+
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use tokio::runtime::Handle;
+
+use crate::ipc::IpcClient;
+use crate::Result;
+
+/// A change to send to the service.
+enum TagRequest {
+    /// Read the current tags
+    Load,
+    /// Attach tags
+    Add(Vec<String>),
+    /// Remove tags
+    Remove(Vec<String>),
+}
+
+/// Tag editor window state.
+pub struct TagsView {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// Runtime for the tag requests.
+    runtime: Handle,
+    /// Full path of the file being tagged.
+    path: String,
+    /// File name, for the window title.
+    name: String,
+    /// The file's tags as last reported by the service.
+    tags: Vec<String>,
+    /// Tags being typed, comma-separated.
+    input: String,
+    /// Whether the file's tags must be read before it is shown.
+    load_pending: bool,
+    /// The file's tags after the running request.
+    pending: Option<Receiver<Result<Vec<String>>>>,
+    /// Outcome of the last request, if it failed.
+    status: String,
+}
+
+impl TagsView {
+    /// Create a closed tag editor.
+    pub fn new(runtime: Handle) -> Self {
+        Self {
+            open: false,
+            runtime,
+            path: String::new(),
+            name: String::new(),
+            tags: Vec::new(),
+            input: String::new(),
+            load_pending: false,
+            pending: None,
+            status: String::new(),
+        }
+    }
+
+    /// Open the editor for a file; its tags are read when it is next drawn.
+    pub fn show_window(&mut self, path: &str, name: &str) {
+        self.open = true;
+        if self.path != path {
+            self.path = path.to_string();
+            self.name = name.to_string();
+            self.tags.clear();
+            self.input.clear();
+        }
+        self.status.clear();
+        self.load_pending = true;
+    }
+
+    /// Draw the window if open.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.check_pending();
+        if !self.open {
+            return;
+        }
+        if self.load_pending && self.pending.is_none() {
+            self.load_pending = false;
+            self.send(ctx, TagRequest::Load);
+        }
+
+        let mut open = self.open;
+        let mut removed: Option<String> = None;
+        let mut add = false;
+        egui::Window::new(format!("Tags: {}", self.name))
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.weak(&self.path);
+                ui.horizontal_wrapped(|ui| {
+                    if self.tags.is_empty() && self.pending.is_none() {
+                        ui.weak("No tags");
+                    }
+                    for tag in &self.tags {
+                        ui.label(tag);
+                        if ui.small_button("x").on_hover_text(format!("Remove {}", tag)).clicked() {
+                            removed = Some(tag.clone());
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let field = ui.add(
+                        egui::TextEdit::singleline(&mut self.input)
+                            .desired_width(220.0)
+                            .hint_text("New tags, comma-separated"),
+                    );
+                    let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let ready = self.pending.is_none() && !self.input.trim().is_empty();
+                    let clicked = ui.add_enabled(ready, egui::Button::new("Add")).clicked();
+                    add = ready && (entered || clicked);
+                    if self.pending.is_some() {
+                        ui.spinner();
+                    }
+                });
+                if !self.status.is_empty() {
+                    ui.label(&self.status);
+                }
+            });
+        self.open = open;
+
+        if let Some(tag) = removed {
+            self.send(ctx, TagRequest::Remove(vec![tag]));
+        } else if add {
+            let tags = split_tags(&self.input);
+            self.input.clear();
+            self.send(ctx, TagRequest::Add(tags));
+        }
+    }
+
+    /// Send a request for the current file; its reply replaces the tags.
+    fn send(&mut self, ctx: &egui::Context, request: TagRequest) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending = Some(rx);
+        self.status.clear();
+
+        let path = self.path.clone();
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let client = IpcClient::new();
+            let tags = match request {
+                TagRequest::Load => client.tags(&path).await,
+                TagRequest::Add(tags) => client.add_tags(&path, &tags).await,
+                TagRequest::Remove(tags) => client.remove_tags(&path, &tags).await,
+            };
+            let _ = tx.send(tags);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Pick up the tags once the running request finishes.
+    fn check_pending(&mut self) {
+        let Some(rx) = &self.pending else { return };
+        match rx.try_recv() {
+            Ok(Ok(tags)) => self.tags = tags,
+            Ok(Err(e)) => self.status = format!("Tags unavailable: {}", e),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => self.status = "Tags unavailable".to_string(),
+        }
+        self.pending = None;
+    }
+}
+
+/// Split typed tags at commas, dropping blanks.
+fn split_tags(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_tags() {
+        assert_eq!(split_tags(" work, tax 2024 ,,"), vec!["work", "tax 2024"]);
+        assert!(split_tags(" , ").is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+//! System tray icon with the service's status and quick actions.
+//!
+//! The icon stays in the notification area while the popup is hidden. Its
+//! colour and tooltip show whether the service is reachable, indexing or
+//! paused, and its menu shows the search window, pauses or resumes
+//! indexing, opens settings or index statistics, and exits. Clicking the
+//! icon shows the search window. Windows-only, using the tray-icon crate.
+
+use crate::ipc::ServiceStatus;
+use crate::ui::results::format_count;
+
+#[cfg(windows)]
+use crate::{FFIError, Result};
+
+/// Side length of the generated icon, in pixels.
+#[cfg_attr(not(windows), allow(dead_code))]
+const ICON_SIZE: u32 = 32;
+
+/// Action picked from the tray icon or its menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    /// Show and focus the search popup
+    ShowSearch,
+    /// Pause indexing, or resume it if paused
+    TogglePause,
+    /// Open the settings window
+    OpenSettings,
+    /// Open the index statistics window
+    ShowStatistics,
+    /// Quit the search UI
+    Exit,
+}
+
+/// Service state shown by the tray icon's colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceHealth {
+    /// The service is not reachable
+    Unavailable,
+    /// Indexing is paused
+    Paused,
+    /// A volume is being indexed or rescanned
+    Indexing,
+    /// Every volume is up to date
+    Healthy,
+}
+
+impl ServiceHealth {
+    /// Health and tooltip text for the last status reply (None if the
+    /// service did not answer).
+    pub fn summarize(status: Option<&ServiceStatus>) -> (Self, String) {
+        let Some(status) = status else {
+            return (ServiceHealth::Unavailable, "FFI: service not running".to_string());
+        };
+
+        let files: i64 = status.volumes.iter().map(|v| v.file_count.max(0)).sum();
+        let totals = format!(
+            "{} files on {} volume{}",
+            format_count(files as usize),
+            status.volumes.len(),
+            if status.volumes.len() == 1 { "" } else { "s" }
+        );
+        let scanning: Vec<String> = status
+            .volumes
+            .iter()
+            .filter(|v| v.state == "indexing" || v.state == "rescanning")
+            .map(|v| match v.progress {
+                Some(percent) => format!("{} {}%", v.drive_letter, percent),
+                None => v.drive_letter.clone(),
+            })
+            .collect();
+
+        if status.indexing_paused {
+            (ServiceHealth::Paused, format!("FFI: indexing paused - {}", totals))
+        } else if !scanning.is_empty() {
+            (ServiceHealth::Indexing, format!("FFI: indexing {} - {}", scanning.join(", "), totals))
+        } else {
+            (ServiceHealth::Healthy, format!("FFI: {}", totals))
+        }
+    }
+
+    /// Icon colour.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    fn color(&self) -> [u8; 3] {
+        match self {
+            ServiceHealth::Unavailable => [0x80, 0x80, 0x80],
+            ServiceHealth::Paused => [0xd2, 0x99, 0x22],
+            ServiceHealth::Indexing => [0x1f, 0x6f, 0xeb],
+            ServiceHealth::Healthy => [0x2e, 0xa0, 0x43],
+        }
+    }
+}
+
+/// RGBA pixels of the tray icon: a disc in the health colour with a
+/// transparent background.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn icon_rgba(health: ServiceHealth) -> Vec<u8> {
+    let [r, g, b] = health.color();
+    let center = (ICON_SIZE as f32 - 1.0) / 2.0;
+    let radius = ICON_SIZE as f32 / 2.0 - 1.0;
+
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            // Anti-aliased edge
+            let alpha = (radius - distance + 0.5).clamp(0.0, 1.0);
+            rgba.extend_from_slice(&[r, g, b, (alpha * 255.0) as u8]);
+        }
+    }
+    rgba
+}
+
+/// The tray icon and its menu.
+#[cfg(windows)]
+pub struct Tray {
+    icon: tray_icon::TrayIcon,
+    pause_item: tray_icon::menu::MenuItem,
+    actions: std::sync::mpsc::Receiver<TrayAction>,
+    health: ServiceHealth,
+}
+
+#[cfg(windows)]
+impl Tray {
+    /// Add the icon to the notification area.
+    ///
+    /// Must be called on the thread running the window's event loop; picked
+    /// actions wake the UI through `ctx`.
+    pub fn new(ctx: &egui::Context) -> Result<Self> {
+        use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+        use tray_icon::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+
+        let show = MenuItem::new("Show search", true, None);
+        let pause_item = MenuItem::new("Pause indexing", true, None);
+        let settings = MenuItem::new("Settings...", true, None);
+        let statistics = MenuItem::new("Index statistics...", true, None);
+        let exit = MenuItem::new("Exit", true, None);
+
+        let menu = Menu::new();
+        menu.append_items(&[
+            &show,
+            &pause_item,
+            &PredefinedMenuItem::separator(),
+            &settings,
+            &statistics,
+            &PredefinedMenuItem::separator(),
+            &exit,
+        ])
+        .map_err(|e| FFIError::Service(format!("Failed to build tray menu: {}", e)))?;
+
+        let (tx, actions) = std::sync::mpsc::channel();
+        let items = [
+            (show.id().clone(), TrayAction::ShowSearch),
+            (pause_item.id().clone(), TrayAction::TogglePause),
+            (settings.id().clone(), TrayAction::OpenSettings),
+            (statistics.id().clone(), TrayAction::ShowStatistics),
+            (exit.id().clone(), TrayAction::Exit),
+        ];
+        let (menu_tx, menu_ctx) = (tx.clone(), ctx.clone());
+        MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+            if let Some((_, action)) = items.iter().find(|(id, _)| *id == event.id) {
+                let _ = menu_tx.send(*action);
+                menu_ctx.request_repaint();
+            }
+        }));
+        let click_ctx = ctx.clone();
+        TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                let _ = tx.send(TrayAction::ShowSearch);
+                click_ctx.request_repaint();
+            }
+        }));
+
+        let (health, tooltip) = ServiceHealth::summarize(None);
+        let icon = TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_tooltip(tooltip)
+            .with_icon(tray_image(health)?)
+            .build()
+            .map_err(|e| FFIError::Service(format!("Failed to create tray icon: {}", e)))?;
+
+        Ok(Self {
+            icon,
+            pause_item,
+            actions,
+            health,
+        })
+    }
+
+    /// Next action picked since the last call, if any.
+    pub fn try_action(&self) -> Option<TrayAction> {
+        self.actions.try_recv().ok()
+    }
+
+    /// Show the service's last status reply (None if it did not answer).
+    pub fn set_status(&mut self, status: Option<&ServiceStatus>) {
+        let (health, tooltip) = ServiceHealth::summarize(status);
+        if let Err(e) = self.icon.set_tooltip(Some(tooltip)) {
+            tracing::debug!("Failed to update tray tooltip: {}", e);
+        }
+        self.pause_item.set_text(match status {
+            Some(status) if status.indexing_paused => "Resume indexing",
+            _ => "Pause indexing",
+        });
+        self.pause_item.set_enabled(status.is_some());
+
+        if self.health != health {
+            match tray_image(health).and_then(|image| {
+                self.icon
+                    .set_icon(Some(image))
+                    .map_err(|e| FFIError::Service(format!("Failed to update tray icon: {}", e)))
+            }) {
+                Ok(()) => self.health = health,
+                Err(e) => tracing::debug!("{}", e),
+            }
+        }
+    }
+}
+
+/// Tray icon image for a health state.
+#[cfg(windows)]
+fn tray_image(health: ServiceHealth) -> Result<tray_icon::Icon> {
+    tray_icon::Icon::from_rgba(icon_rgba(health), ICON_SIZE, ICON_SIZE)
+        .map_err(|e| FFIError::Service(format!("Failed to create tray icon image: {}", e)))
+}
+
+/// Stub for non-Windows platforms.
+#[cfg(not(windows))]
+pub struct Tray;
+
+#[cfg(not(windows))]
+impl Tray {
+    /// Create the tray icon (stub for non-Windows).
+    pub fn new(_ctx: &egui::Context) -> crate::Result<Self> {
+        Err(crate::FFIError::Service("The tray icon is only supported on Windows".to_string()))
+    }
+
+    /// Picked action (stub for non-Windows, never any).
+    pub fn try_action(&self) -> Option<TrayAction> {
+        None
+    }
+
+    /// Show the service status (stub for non-Windows).
+    pub fn set_status(&mut self, _status: Option<&ServiceStatus>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::VolumeStatus;
+
+    #[test]
+    fn test_service_health() {
+        let volume = |letter: &str, state: &str, files: i64| VolumeStatus {
+            drive_letter: letter.to_string(),
+            fs_type: "NTFS".to_string(),
+            state: state.to_string(),
+            file_count: files,
+            dir_count: 10,
+            last_scan_time: None,
+            progress: None,
+            scan: None,
+            truncated: None,
+        };
+        let mut status = ServiceStatus {
+            indexing_paused: false,
+            volumes: vec![volume("C:", "online", 1500), volume("D:", "online", 500)],
+            maintenance: None,
+            pruning: None,
+        };
+
+        assert_eq!(ServiceHealth::summarize(None).0, ServiceHealth::Unavailable);
+        let (health, tooltip) = ServiceHealth::summarize(Some(&status));
+        assert_eq!(health, ServiceHealth::Healthy);
+        assert!(tooltip.ends_with("files on 2 volumes"), "{}", tooltip);
+
+        status.volumes[1].state = "rescanning".to_string();
+        let (health, tooltip) = ServiceHealth::summarize(Some(&status));
+        assert_eq!(health, ServiceHealth::Indexing);
+        assert!(tooltip.starts_with("FFI: indexing D:"), "{}", tooltip);
+
+        status.volumes[0].state = "indexing".to_string();
+        status.volumes[0].progress = Some(42);
+        let (_, tooltip) = ServiceHealth::summarize(Some(&status));
+        assert!(tooltip.starts_with("FFI: indexing C: 42%, D:"), "{}", tooltip);
+
+        status.indexing_paused = true;
+        assert_eq!(ServiceHealth::summarize(Some(&status)).0, ServiceHealth::Paused);
+
+        let rgba = icon_rgba(ServiceHealth::Healthy);
+        assert_eq!(rgba.len(), (ICON_SIZE * ICON_SIZE * 4) as usize);
+        // Opaque centre, transparent corner
+        let center = ((ICON_SIZE / 2 * ICON_SIZE + ICON_SIZE / 2) * 4 + 3) as usize;
+        assert_eq!((rgba[3], rgba[center]), (0, 255));
+    }
+}
@@ -0,0 +1,196 @@
+//! Disk usage window: where the space under a folder goes.
+//!
+//! Shows the total size of each folder directly below the query's `path:`
+//! scope, or of each extension, largest first (see
+//! [`IpcClient::aggregate`]). Clicking a folder shows what is inside it.
+
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use tokio::runtime::Handle;
+
+use crate::db::{UsageGroup, UsageRow};
+use crate::ipc::IpcClient;
+use crate::ui::results::format_size;
+use crate::Result;
+
+/// Groups shown at once.
+const USAGE_LIMIT: usize = 50;
+
+/// Disk usage window state.
+pub struct UsageView {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// Runtime for the usage requests.
+    runtime: Handle,
+    /// Query selecting the files counted, as typed.
+    query: String,
+    /// What sizes are summed by.
+    group_by: UsageGroup,
+    /// Groups of the last finished request, largest first.
+    rows: Vec<UsageRow>,
+    /// Whether the rows must be fetched before they are shown.
+    load_pending: bool,
+    /// Groups of the running request.
+    pending: Option<Receiver<Result<Vec<UsageRow>>>>,
+    /// Outcome of the last request, if it failed.
+    status: String,
+}
+
+impl UsageView {
+    /// Create a closed disk usage window.
+    pub fn new(runtime: Handle) -> Self {
+        Self {
+            open: false,
+            runtime,
+            query: String::new(),
+            group_by: UsageGroup::default(),
+            rows: Vec::new(),
+            load_pending: false,
+            pending: None,
+            status: String::new(),
+        }
+    }
+
+    /// Open the window for a query; its usage is fetched when it is next
+    /// drawn.
+    pub fn show_window(&mut self, query: &str) {
+        self.open = true;
+        self.query = query.trim().to_string();
+        self.load_pending = true;
+    }
+
+    /// Draw the window if open.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.check_pending();
+        if !self.open {
+            return;
+        }
+        if self.load_pending && self.pending.is_none() {
+            self.load_pending = false;
+            self.send(ctx);
+        }
+
+        let mut open = self.open;
+        let mut refresh = false;
+        let mut drill_into: Option<String> = None;
+        egui::Window::new("Disk usage")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let field = ui.add(
+                        egui::TextEdit::singleline(&mut self.query)
+                            .desired_width(320.0)
+                            .hint_text(r"Folder scope, e.g. path:C:\Users"),
+                    );
+                    refresh = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    refresh |= ui.add_enabled(self.pending.is_none(), egui::Button::new("Refresh")).clicked();
+                    if self.pending.is_some() {
+                        ui.spinner();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Group by:");
+                    refresh |= ui.radio_value(&mut self.group_by, UsageGroup::Folder, "Folder").changed();
+                    refresh |= ui.radio_value(&mut self.group_by, UsageGroup::Extension, "Extension").changed();
+                });
+                if !self.status.is_empty() {
+                    ui.label(&self.status);
+                }
+                ui.separator();
+
+                if self.rows.is_empty() && self.pending.is_none() {
+                    ui.weak("No files");
+                }
+                let largest = self.rows.first().map_or(0, |row| row.bytes).max(1);
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("usage_rows").num_columns(4).striped(true).show(ui, |ui| {
+                        for row in &self.rows {
+                            let name = row_label(self.group_by, &row.key);
+                            if self.group_by == UsageGroup::Folder {
+                                if ui.link(name).on_hover_text("Show what is inside").clicked() {
+                                    drill_into = Some(row.key.clone());
+                                }
+                            } else {
+                                ui.label(name);
+                            }
+                            ui.add(
+                                egui::ProgressBar::new(row.bytes as f32 / largest as f32)
+                                    .desired_width(120.0),
+                            );
+                            ui.label(format_size(row.bytes));
+                            ui.weak(format!("{} files", row.files));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.open = open;
+
+        if let Some(folder) = drill_into {
+            self.query = folder_query(&folder);
+            refresh = true;
+        }
+        if refresh && self.pending.is_none() {
+            self.send(ctx);
+        }
+    }
+
+    /// Fetch the usage of the current query.
+    fn send(&mut self, ctx: &egui::Context) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending = Some(rx);
+        self.status.clear();
+
+        let query = self.query.clone();
+        let group_by = self.group_by;
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(IpcClient::new().aggregate(&query, group_by, USAGE_LIMIT).await);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Pick up the groups once the running request finishes.
+    fn check_pending(&mut self) {
+        let Some(rx) = &self.pending else { return };
+        match rx.try_recv() {
+            Ok(Ok(rows)) => self.rows = rows,
+            Ok(Err(e)) => self.status = format!("Disk usage unavailable: {}", e),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => self.status = "Disk usage unavailable".to_string(),
+        }
+        self.pending = None;
+    }
+}
+
+/// Text shown for a group.
+fn row_label(group_by: UsageGroup, key: &str) -> String {
+    match group_by {
+        UsageGroup::Extension if key.is_empty() => "(no extension)".to_string(),
+        UsageGroup::Extension => format!(".{}", key),
+        UsageGroup::Folder => key.to_string(),
+    }
+}
+
+/// Query scoped to a folder shown as a group.
+fn folder_query(folder: &str) -> String {
+    format!("path:\"{}\"", folder.trim_end_matches('\\'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{parse_query, Filter};
+
+    #[test]
+    fn test_folder_query() {
+        for (folder, scope) in [(r"C:\Users\Program Files", r"C:\Users\Program Files"), (r"D:\", "D:")] {
+            let parsed = parse_query(&folder_query(folder)).unwrap();
+            assert_eq!(parsed.filters, vec![Filter::PathScope(scope.to_string())]);
+        }
+        assert_eq!(row_label(UsageGroup::Extension, ""), "(no extension)");
+        assert_eq!(row_label(UsageGroup::Extension, "mkv"), ".mkv");
+    }
+}